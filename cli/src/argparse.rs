@@ -24,22 +24,39 @@
  *
 */
 
+use crate::completer::{self, SkyshHelper};
+use crate::profile::{self, Profile};
 use crate::runner::Runner;
 use clap::load_yaml;
-use clap::App;
+use clap::{App, ArgMatches};
 use crossterm::terminal::{Clear, ClearType};
+use crossterm::tty::IsTty;
 use crossterm::{cursor, execute};
 use libsky::URL;
 use libsky::VERSION;
-use readline::config::Configurer;
+use readline::config::{CompletionType, Config, EditMode};
 use readline::{error::ReadlineError, Editor};
 use rustyline as readline;
 use skytable::aio::TlsConnection;
 use skytable::AsyncConnection;
-use std::io::stdout;
+use std::env;
+use std::fs;
+use std::io::{stdin, stdout, Read};
+use std::path::PathBuf;
 use std::process;
 use std::process::exit;
 const ADDR: &str = "127.0.0.1";
+const HISTORY_FILE: &str = ".sky_history";
+const PROFILES_FILE: &str = ".skysh.toml";
+
+/// Returns the path to `file` in the user's home directory (or in the current directory if
+/// we can't find one)
+fn home_path(file: &str) -> PathBuf {
+    match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(file),
+        None => PathBuf::from(file),
+    }
+}
 
 macro_rules! inner_eval {
     ($runner:expr, $matches:expr) => {
@@ -53,13 +70,30 @@ macro_rules! inner_eval {
     };
 }
 
+macro_rules! inner_script {
+    ($runner:expr, $matches:expr) => {
+        if let Some(script) = read_script(&$matches) {
+            let okay = $runner
+                .run_script(&script, $matches.is_present("continue"))
+                .await;
+            process::exit(if okay { 0 } else { 0x01 });
+        }
+    };
+}
+
 macro_rules! inner_repl {
     ($runner:expr) => {
         println!("Skytable v{} | {}", VERSION, URL);
-        let mut editor = Editor::<()>::new();
-        editor.set_auto_add_history(true);
-        editor.set_history_ignore_dups(true);
-        let _ = editor.load_history(".sky_history");
+        let config = Config::builder()
+            .edit_mode(EditMode::Emacs)
+            .completion_type(CompletionType::List)
+            .auto_add_history(true)
+            .history_ignore_dups(true)
+            .build();
+        let mut editor = Editor::<SkyshHelper>::with_config(config);
+        editor.set_helper(Some(SkyshHelper::new($runner.fetch_entities().await)));
+        let history_file = home_path(HISTORY_FILE);
+        let _ = editor.load_history(&history_file);
         loop {
             match editor.readline("skysh> ") {
                 Ok(line) => match line.to_lowercase().as_str() {
@@ -74,7 +108,13 @@ macro_rules! inner_repl {
                     }
                     _ => {
                         if !line.is_empty() {
-                            $runner.run_query(&line).await
+                            $runner.run_query(&line).await;
+                            if completer::changes_entities(&line) {
+                                let entities = $runner.fetch_entities().await;
+                                if let Some(helper) = editor.helper_mut() {
+                                    helper.set_entities(entities);
+                                }
+                            }
                         }
                     }
                 },
@@ -85,12 +125,35 @@ macro_rules! inner_repl {
                 }
             }
         }
-        if let Err(e) = editor.save_history(".sky_history") {
+        if let Err(e) = editor.save_history(&history_file) {
             eprintln!("Failed to save history with error: '{}'", e);
         }
     };
 }
 
+/// Returns the script to run, if any. This is either the file passed with `--file` or
+/// whatever is piped into stdin
+fn read_script(matches: &ArgMatches) -> Option<String> {
+    if let Some(file) = matches.value_of("file") {
+        match fs::read_to_string(file) {
+            Ok(script) => Some(script),
+            Err(e) => {
+                eprintln!("ERROR: Failed to read script '{}': {}", file, e);
+                process::exit(0x01);
+            }
+        }
+    } else if !stdin().is_tty() {
+        let mut script = String::new();
+        if let Err(e) = stdin().read_to_string(&mut script) {
+            eprintln!("ERROR: Failed to read script from stdin: {}", e);
+            process::exit(0x01);
+        }
+        Some(script)
+    } else {
+        None
+    }
+}
+
 /// This creates a REPL on the command line and also parses command-line arguments
 ///
 /// Anything that is entered following a return, is parsed into a query and is
@@ -99,7 +162,21 @@ macro_rules! inner_repl {
 pub async fn start_repl() {
     let cfg_layout = load_yaml!("./cli.yml");
     let matches = App::from_yaml(cfg_layout).get_matches();
-    let host = libsky::option_unwrap_or!(matches.value_of("host"), ADDR);
+    let profile = match matches.value_of("profile") {
+        Some(name) => match profile::load(&home_path(PROFILES_FILE), name) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                process::exit(0x01);
+            }
+        },
+        None => Profile::default(),
+    };
+    // options on the command line take precedence over the profile
+    let host = libsky::option_unwrap_or!(
+        matches.value_of("host").or_else(|| profile.host.as_deref()),
+        ADDR
+    );
     let port = match matches.value_of("port") {
         Some(p) => match p.parse::<u16>() {
             Ok(p) => p,
@@ -108,9 +185,12 @@ pub async fn start_repl() {
                 process::exit(0x01);
             }
         },
-        None => 2003,
+        None => profile.port.unwrap_or(2003),
     };
-    if let Some(sslcert) = matches.value_of("cert") {
+    let sslcert = matches
+        .value_of("cert")
+        .or_else(|| profile.sslcert.as_deref());
+    if let Some(sslcert) = sslcert {
         let con = match TlsConnection::new(host, port, sslcert).await {
             Ok(c) => c,
             Err(e) => {
@@ -120,6 +200,7 @@ pub async fn start_repl() {
         };
         let mut runner = Runner::new(con);
         inner_eval!(runner, matches);
+        inner_script!(runner, matches);
         println!("Connected to skyhash-secure://{}:{}", host, port);
         inner_repl!(runner);
     } else {
@@ -132,6 +213,7 @@ pub async fn start_repl() {
         };
         let mut runner = Runner::new(con);
         inner_eval!(runner, matches);
+        inner_script!(runner, matches);
         println!("Connected to skyhash://{}:{}", host, port);
         inner_repl!(runner);
     }
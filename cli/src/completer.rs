@@ -0,0 +1,186 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Tab completion for the REPL
+//!
+//! The first word on the line is completed with action names while every other word is
+//! completed with the names of keyspaces and tables (`ks` and `ks:tbl`) that were fetched
+//! from the server with `INSPECT`
+
+use readline::completion::Completer;
+use readline::highlight::Highlighter;
+use readline::hint::Hinter;
+use readline::validate::Validator;
+use readline::{Context, Helper};
+use rustyline as readline;
+
+/// All the actions that the server understands
+const ACTIONS: [&str; 59] = [
+    "AUTH",
+    "BATCH",
+    "BITCOUNT",
+    "BITOP",
+    "CLONE",
+    "CREATE",
+    "DBSIZE",
+    "DEBUG",
+    "DEL",
+    "DROP",
+    "DUMP",
+    "EXISTS",
+    "EXPIREAT",
+    "EXPLAIN",
+    "FLUSHDB",
+    "GET",
+    "GETBIT",
+    "GETEX",
+    "GETUPDATE",
+    "HELLO",
+    "HEYA",
+    "IDEMPOTENT",
+    "INSPECT",
+    "JDEL",
+    "JGET",
+    "JSET",
+    "KEYLEN",
+    "LSKEYS",
+    "MCAS",
+    "MERGE",
+    "MGET",
+    "MKSNAP",
+    "MPOP",
+    "MSET",
+    "MUPDATE",
+    "OBJECT",
+    "PFADD",
+    "PFCOUNT",
+    "PFMERGE",
+    "PIN",
+    "POP",
+    "PREFIXDROP",
+    "PREFIXSTATS",
+    "SDEL",
+    "SET",
+    "SETBIT",
+    "SETV",
+    "SSET",
+    "SUPDATE",
+    "SYS",
+    "TRACKING",
+    "UNDROP",
+    "UNPIN",
+    "UPDATE",
+    "USE",
+    "USET",
+    "VERBOSE",
+    "WAIT",
+    "WHOAMI",
+];
+
+/// Keywords that are used by DDL and `INSPECT` queries
+const KEYWORDS: [&str; 3] = ["KEYSPACE", "KEYSPACES", "TABLE"];
+
+pub struct SkyshHelper {
+    /// names of keyspaces and tables
+    entities: Vec<String>,
+}
+
+impl SkyshHelper {
+    pub fn new(entities: Vec<String>) -> Self {
+        Self { entities }
+    }
+    /// Replace the known keyspaces and tables
+    pub fn set_entities(&mut self, entities: Vec<String>) {
+        self.entities = entities;
+    }
+}
+
+/// Returns true if the query might have created or removed keyspaces or tables
+pub fn changes_entities(line: &str) -> bool {
+    match line.split_whitespace().next() {
+        Some(action) => {
+            action.eq_ignore_ascii_case("create") || action.eq_ignore_ascii_case("drop")
+        }
+        None => false,
+    }
+}
+
+/// Returns the candidates for `word`, which is the first word on the line if `is_action`
+/// is set
+fn candidates(word: &str, is_action: bool, entities: &[String]) -> Vec<String> {
+    let upper = word.to_ascii_uppercase();
+    // follow the case that the user is typing in
+    let lowercase = word.chars().any(|c| c.is_ascii_lowercase());
+    let keywords: &[&str] = if is_action { &ACTIONS } else { &KEYWORDS };
+    let mut ret: Vec<String> = keywords
+        .iter()
+        .filter(|kw| kw.starts_with(&upper))
+        .map(|kw| {
+            if lowercase {
+                kw.to_ascii_lowercase()
+            } else {
+                kw.to_string()
+            }
+        })
+        .collect();
+    if !is_action {
+        ret.extend(
+            entities
+                .iter()
+                .filter(|entity| entity.starts_with(word))
+                .cloned(),
+        );
+    }
+    ret
+}
+
+impl Completer for SkyshHelper {
+    type Candidate = String;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> readline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line
+            .rfind(char::is_whitespace)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let is_action = line[..start].trim().is_empty();
+        Ok((start, candidates(&line[start..], is_action, &self.entities)))
+    }
+}
+
+impl Hinter for SkyshHelper {
+    type Hint = String;
+}
+
+impl Highlighter for SkyshHelper {}
+
+impl Validator for SkyshHelper {}
+
+impl Helper for SkyshHelper {}
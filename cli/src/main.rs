@@ -29,6 +29,8 @@
 #![deny(unused_imports)]
 
 mod argparse;
+mod completer;
+mod profile;
 mod runner;
 
 #[tokio::main]
@@ -0,0 +1,66 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Named connection profiles
+//!
+//! Profiles are read from `~/.skysh.toml`, where every table is a profile:
+//! ```toml
+//! [prod]
+//! host = "db.example.com"
+//! port = 2004
+//! sslcert = "/etc/skytable/cert.pem"
+//! ```
+//! Any option that is also passed on the command line is overridden by the command line
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+/// A named set of connection settings
+pub struct Profile {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub sslcert: Option<String>,
+}
+
+/// Load the profile called `name` from the profiles file at `path`
+pub fn load(path: &Path, name: &str) -> Result<Profile, String> {
+    let file = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Failed to read profiles file '{}': {}",
+            path.to_string_lossy(),
+            e
+        )
+    })?;
+    let mut profiles: HashMap<String, Profile> =
+        toml::from_str(&file).map_err(|e| format!("Failed to parse profiles file: {}", e))?;
+    profiles
+        .remove(name)
+        .ok_or_else(|| format!("No profile named '{}'", name))
+}
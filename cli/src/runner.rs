@@ -147,7 +147,8 @@ impl<T: AsyncSocket> Runner<T> {
     pub fn new(con: T) -> Self {
         Runner { con }
     }
-    pub async fn run_query(&mut self, unescaped_items: &str) {
+    /// Run a query and print the response. Returns false if the server returned an error
+    pub async fn run_query(&mut self, unescaped_items: &str) -> bool {
         let query = libsky::turn_into_query(unescaped_items);
         match self.con.run_simple_query(query).await {
             Ok(resp) => match resp {
@@ -158,7 +159,11 @@ impl<T: AsyncSocket> Runner<T> {
                 }
                 Element::Array(Array::Bin(brr)) => print_bin_array(brr),
                 Element::Array(Array::Str(srr)) => print_str_array(srr),
-                Element::RespCode(r) => print_rcode(r, None),
+                Element::RespCode(r) => {
+                    let okay = matches!(r, RespCode::Okay);
+                    print_rcode(r, None);
+                    return okay;
+                }
                 Element::UnsignedInt(int) => write_int!(int),
                 Element::Array(Array::Flat(frr)) => write_flat_array(frr),
                 Element::Array(Array::Recursive(a)) => print_array(a),
@@ -169,6 +174,52 @@ impl<T: AsyncSocket> Runner<T> {
                 std::process::exit(1);
             }
         }
+        true
+    }
+    /// Run every statement in `script` (one per line). Returns true if none of them failed
+    ///
+    /// Blank lines and lines starting with `#` are skipped. Unless `keep_going` is set, this
+    /// stops at the first statement that fails
+    pub async fn run_script(&mut self, script: &str, keep_going: bool) -> bool {
+        let mut okay = true;
+        for (lineno, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if !self.run_query(line).await {
+                okay = false;
+                eskysh!(format!("Statement on line {} failed", lineno + 1));
+                if !keep_going {
+                    break;
+                }
+            }
+        }
+        okay
+    }
+    /// Fetch the names of all keyspaces and tables (as `ks:tbl`) on the server
+    pub async fn fetch_entities(&mut self) -> Vec<String> {
+        let keyspaces = self.fetch_names("INSPECT KEYSPACES").await;
+        let mut entities = Vec::with_capacity(keyspaces.len());
+        for ks in keyspaces {
+            let tables = self.fetch_names(&format!("INSPECT KEYSPACE {}", ks)).await;
+            entities.push(ks.clone());
+            entities.extend(tables.into_iter().map(|tbl| format!("{}:{}", ks, tbl)));
+        }
+        entities
+    }
+    async fn fetch_names(&mut self, query: &str) -> Vec<String> {
+        let query = libsky::turn_into_query(query);
+        match self.con.run_simple_query(query).await {
+            Ok(Element::Array(Array::Str(names))) => names.into_iter().flatten().collect(),
+            Ok(Element::Array(Array::Bin(names))) => names
+                .into_iter()
+                .flatten()
+                .map(|name| str!(name).into_owned())
+                .collect(),
+            // this is only used for completions, so don't bother the user if it fails
+            _ => Vec::new(),
+        }
     }
 }
 
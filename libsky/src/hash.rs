@@ -0,0 +1,82 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Key slots
+//!
+//! A stable hash that maps every key to one of [`SLOTS`] slots. The server doesn't partition
+//! anything by it: a table always lives on a single server. It's a helper for clients
+//! and proxies that spread keys across several independent servers themselves, so
+//! that they all pick the same server for a key (by mapping ranges of slots to servers).
+//! The slot of a key is the CRC16 (XMODEM) of the key, modulo [`SLOTS`]; if the key has a
+//! _hash tag_ (the bytes between its first `{` and the first `}` after it, if there are
+//! any), only the hash tag is hashed, so that keys like `{user:1}:name` and `{user:1}:email`
+//! always land in the same slot.
+//!
+//! Unlike the hashers that tables use (which are seeded randomly whenever the server
+//! starts), this never changes, so `SYS HASH <key>` can be used to check what a client
+//! computes
+
+/// The number of slots
+pub const SLOTS: u16 = 16384;
+
+/// Returns the CRC16 (XMODEM) of the given bytes
+pub const fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        crc ^= (bytes[i] as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Returns the part of a key that is hashed: its hash tag if it has one, or else the whole
+/// key
+pub fn hash_tag(key: &[u8]) -> &[u8] {
+    if let Some(open) = key.iter().position(|byte| *byte == b'{') {
+        let rest = &key[open + 1..];
+        if let Some(close) = rest.iter().position(|byte| *byte == b'}') {
+            if close != 0 {
+                return &rest[..close];
+            }
+        }
+    }
+    key
+}
+
+/// Returns the slot of a key
+pub fn slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key)) % SLOTS
+}
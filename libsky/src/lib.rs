@@ -31,6 +31,8 @@
 //!
 //! This contains modules which are shared by both the `cli` and the `server` modules
 
+pub mod hash;
+pub mod manifest;
 pub mod util;
 use skytable::Query;
 use std::error::Error;
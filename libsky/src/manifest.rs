@@ -0,0 +1,229 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Snapshot manifests
+//!
+//! Every snapshot has a `MANIFEST` file at its root that lists every other file in the
+//! snapshot with its size and SHA-256 checksum, one file per line:
+//! ```text
+//! <sha256 (hex)> <size> <path>
+//! ```
+//! The paths are relative to the root of the snapshot, use `/` as the separator and are
+//! sorted. The server writes the manifest once the snapshot is complete, and both the
+//! server (`SYS VERIFYSNAP`) and `sky-admin verify` check snapshots against it, so that a
+//! damaged backup is found long before it's needed
+
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+/// The name of the manifest file
+pub const MANIFEST: &str = "MANIFEST";
+/// The manifest is written here first and then renamed, so that a crash never leaves a
+/// partly written manifest behind
+const MANIFEST_TMP: &str = "MANIFEST_";
+
+#[derive(Debug, PartialEq)]
+/// A file that is listed in a manifest
+pub struct Entry {
+    /// the path of the file, relative to the root of the snapshot
+    pub path: String,
+    /// the size of the file, in bytes
+    pub size: u64,
+    /// the SHA-256 checksum of the file, in lowercase hex
+    pub checksum: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Something that is wrong with a file in a snapshot
+pub enum Problem {
+    /// the file is listed in the manifest, but doesn't exist
+    Missing,
+    /// the file doesn't have the size listed in the manifest
+    SizeMismatch,
+    /// the file doesn't have the checksum listed in the manifest
+    ChecksumMismatch,
+    /// the file isn't listed in the manifest
+    Unlisted,
+}
+
+impl Problem {
+    /// Returns the name of the problem
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::SizeMismatch => "size-mismatch",
+            Self::ChecksumMismatch => "checksum-mismatch",
+            Self::Unlisted => "unlisted",
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The result of verifying a snapshot against its manifest
+pub struct Report {
+    /// the number of files that match the manifest
+    pub verified: usize,
+    /// the files that don't match the manifest (by their path)
+    pub problems: Vec<(String, Problem)>,
+}
+
+impl Report {
+    /// Returns true if every file matches the manifest
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Returns the size and the SHA-256 checksum of the file at `path`
+fn checksum(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    let checksum = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok((size, checksum))
+}
+
+/// Add the paths of all the files under `dir` to `files`, relative to the root of the
+/// snapshot (which `prefix` is the path of `dir` relative to)
+fn walk(dir: &Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if prefix.is_empty() && (name == MANIFEST || name == MANIFEST_TMP) {
+            continue;
+        }
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if entry.file_type()?.is_dir() {
+            walk(&entry.path(), &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the paths of all the files in the snapshot at `root` (except the manifest),
+/// sorted
+fn list(root: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    walk(root, "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Write the manifest of the snapshot at `root`, listing every file that is in it now
+pub fn write(root: &Path) -> Result<()> {
+    let tmp = root.join(MANIFEST_TMP);
+    let mut out = BufWriter::new(File::create(&tmp)?);
+    for path in list(root)? {
+        let (size, checksum) = checksum(&root.join(&path))?;
+        writeln!(out, "{} {} {}", checksum, size, path)?;
+    }
+    let file = out.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    fs::rename(tmp, root.join(MANIFEST))
+}
+
+/// Read the manifest of the snapshot at `root`. A snapshot that has no manifest (because
+/// it was made by an older version, say) returns an error of the kind
+/// [`ErrorKind::NotFound`]
+pub fn read(root: &Path) -> Result<Vec<Entry>> {
+    let file = BufReader::new(File::open(root.join(MANIFEST))?);
+    let mut entries = Vec::new();
+    for line in file.lines() {
+        let line = line?;
+        let mut parts = line.splitn(3, ' ');
+        let entry = match (parts.next(), parts.next(), parts.next()) {
+            (Some(checksum), Some(size), Some(path)) if checksum.len() == 64 => {
+                size.parse().ok().map(|size| Entry {
+                    path: path.to_owned(),
+                    size,
+                    checksum: checksum.to_owned(),
+                })
+            }
+            _ => None,
+        };
+        match entry {
+            Some(entry) => entries.push(entry),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("malformed line in manifest: {}", line),
+                ))
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Verify the snapshot at `root` against its manifest
+pub fn verify(root: &Path) -> Result<Report> {
+    let entries = read(root)?;
+    let mut report = Report {
+        verified: 0,
+        problems: Vec::new(),
+    };
+    for entry in &entries {
+        let problem = match checksum(&root.join(&entry.path)) {
+            Ok((size, _)) if size != entry.size => Some(Problem::SizeMismatch),
+            Ok((_, checksum)) if checksum != entry.checksum => Some(Problem::ChecksumMismatch),
+            Ok(_) => None,
+            Err(e) if e.kind() == ErrorKind::NotFound => Some(Problem::Missing),
+            Err(e) => return Err(e),
+        };
+        match problem {
+            Some(problem) => report.problems.push((entry.path.clone(), problem)),
+            None => report.verified += 1,
+        }
+    }
+    let listed: HashSet<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+    for path in list(root)? {
+        if !listed.contains(path.as_str()) {
+            report.problems.push((path, Problem::Unlisted));
+        }
+    }
+    Ok(report)
+}
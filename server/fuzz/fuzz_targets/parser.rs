@@ -0,0 +1,43 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Feeds arbitrary bytes to the Skyhash parser. Run with `cargo fuzz run parser` from
+//! `server/`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use skyd::fuzz::parse_packet;
+
+fuzz_target!(|data: &[u8]| {
+    // neither mode should ever panic, whatever it's given
+    let lenient = parse_packet(data, false);
+    if let Ok((query, forward_by)) = parse_packet(data, true) {
+        // a packet that passes in strict mode takes up the whole buffer and is read the
+        // same way in lenient mode
+        assert_eq!(forward_by, data.len());
+        assert_eq!(lenient, Ok((query, forward_by)));
+    }
+});
@@ -0,0 +1,296 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Bitmap actions
+//! This module provides actions that treat values as compact bitmaps:
+//! - `SETBIT <key> <offset> <0|1>`: set or clear the bit at `offset`, returning the old bit
+//! - `GETBIT <key> <offset>`: get the bit at `offset`
+//! - `BITCOUNT <key>`: count the number of set bits
+//! - `BITOP <AND|OR|XOR|NOT> <destkey> <srckey> [srckey ...]`: run a bitwise operation on
+//! the source keys and store the result in `destkey`, returning its length
+//!
+//! Bits are numbered from the most significant bit of the first byte. Values are grown
+//! (with zero bytes) as required, and missing keys are treated as empty bitmaps
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::util::compiler;
+
+/// The largest bit offset that we'll accept (this caps a bitmap at 512 MiB)
+const MAX_BIT_OFFSET: usize = (u32::MAX as usize) - 1;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// A bitwise operation that can be run with `BITOP`
+enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl BitOp {
+    fn from_bytes(op: &[u8]) -> Option<Self> {
+        let op = match op.to_ascii_uppercase().as_slice() {
+            b"AND" => BitOp::And,
+            b"OR" => BitOp::Or,
+            b"XOR" => BitOp::Xor,
+            b"NOT" => BitOp::Not,
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+/// Parse a bit offset, returning `None` if it isn't a number or is too large
+fn parse_offset(offset: &[u8]) -> Option<usize> {
+    let offset = std::str::from_utf8(offset).ok()?.parse::<usize>().ok()?;
+    if offset > MAX_BIT_OFFSET {
+        None
+    } else {
+        Some(offset)
+    }
+}
+
+/// Parse a bit value, which has to be either `0` or `1`
+fn parse_bit(bit: &[u8]) -> Option<bool> {
+    match bit {
+        b"0" => Some(false),
+        b"1" => Some(true),
+        _ => None,
+    }
+}
+
+/// Get the bit at `offset`. Bits beyond the end of the bitmap are unset
+fn get_bit(bitmap: &[u8], offset: usize) -> bool {
+    match bitmap.get(offset / 8) {
+        Some(byte) => byte & (0x80 >> (offset % 8)) != 0,
+        None => false,
+    }
+}
+
+/// Set or clear the bit at `offset`, growing the bitmap if needed. Returns the old bit
+fn set_bit(bitmap: &mut Vec<u8>, offset: usize, on: bool) -> bool {
+    let (idx, mask) = (offset / 8, 0x80 >> (offset % 8));
+    if bitmap.len() <= idx {
+        bitmap.resize(idx + 1, 0);
+    }
+    let old = bitmap[idx] & mask != 0;
+    if on {
+        bitmap[idx] |= mask;
+    } else {
+        bitmap[idx] &= !mask;
+    }
+    old
+}
+
+/// Count the number of set bits
+fn count_bits(bitmap: &[u8]) -> usize {
+    bitmap.iter().map(|byte| byte.count_ones() as usize).sum()
+}
+
+/// Run `op` over the source bitmaps. Shorter sources are zero-padded to the length of
+/// the longest source
+fn run_bitop(op: BitOp, sources: &[Vec<u8>]) -> Vec<u8> {
+    let len = sources.iter().map(|src| src.len()).max().unwrap_or(0);
+    let byte_at = |src: &Vec<u8>, idx: usize| src.get(idx).copied().unwrap_or(0);
+    (0..len)
+        .map(|idx| {
+            let mut srcs = sources.iter().map(|src| byte_at(src, idx));
+            let first = srcs.next().unwrap_or(0);
+            match op {
+                BitOp::And => srcs.fold(first, |acc, byte| acc & byte),
+                BitOp::Or => srcs.fold(first, |acc, byte| acc | byte),
+                BitOp::Xor => srcs.fold(first, |acc, byte| acc ^ byte),
+                BitOp::Not => !first,
+            }
+        })
+        .collect()
+}
+
+action!(
+    /// Run a `SETBIT` query
+    fn setbit(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let (key, offset, bit) = unsafe {
+                // SAFETY: We have already checked that there are exactly 3 arguments
+                (
+                    act.next().unsafe_unwrap(),
+                    act.next().unsafe_unwrap(),
+                    act.next().unsafe_unwrap(),
+                )
+            };
+            let (offset, on) = match (parse_offset(&offset), parse_bit(&bit)) {
+                (Some(offset), Some(on)) => (offset, on),
+                _ => return conwrite!(con, groups::BAD_BIT),
+            };
+            let ret = kve.read_modify_write(Data::from(key), |current| {
+                let mut bitmap = current.map(|bm| bm.to_vec()).unwrap_or_default();
+                let oldlen = bitmap.len();
+                let old = set_bit(&mut bitmap, offset, on);
+                if current.is_some() && old == on && bitmap.len() == oldlen {
+                    // nothing changed, so don't bother writing it back
+                    (None, old)
+                } else {
+                    (Some(Data::from(bitmap)), old)
+                }
+            });
+            match ret {
+                Ok(old) => conwrite!(con, old as usize)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `GETBIT` query
+    fn getbit(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let kve = kve!(con, handle);
+        let (key, offset) = unsafe {
+            // SAFETY: We have already checked that there are exactly 2 arguments
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        let offset = match parse_offset(&offset) {
+            Some(offset) => offset,
+            None => return conwrite!(con, groups::BAD_BIT),
+        };
+        // don't hold the bucket lock across an await
+        let bit = kve
+            .get(&key)
+            .map(|bitmap| bitmap.map_or(false, |bitmap| get_bit(&bitmap, offset)));
+        match bit {
+            Ok(bit) => conwrite!(con, bit as usize)?,
+            Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `BITCOUNT` query
+    fn bitcount(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let kve = kve!(con, handle);
+        let key = unsafe {
+            // SAFETY: We have already checked that there is exactly 1 argument
+            act.next().unsafe_unwrap()
+        };
+        let count = kve
+            .get(&key)
+            .map(|bitmap| bitmap.map_or(0, |bitmap| count_bits(&bitmap)));
+        match count {
+            Ok(count) => conwrite!(con, count)?,
+            Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `BITOP` query
+    fn bitop(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let (op, dest) = unsafe {
+                // SAFETY: We have already checked that there are atleast 3 arguments
+                (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+            };
+            let op = match BitOp::from_bytes(&op) {
+                Some(op) => op,
+                None => return conwrite!(con, groups::UNKNOWN_BITOP),
+            };
+            if op == BitOp::Not && act.len() != 1 {
+                // NOT is unary
+                return conwrite!(con, groups::ACTION_ERR);
+            }
+            let mut sources = Vec::with_capacity(act.len());
+            for key in act {
+                match kve.get_cloned(&key) {
+                    Ok(Some(bitmap)) => sources.push(bitmap.to_vec()),
+                    Ok(None) => sources.push(Vec::new()),
+                    Err(()) => return compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR)),
+                }
+            }
+            let result = run_bitop(op, &sources);
+            let len = result.len();
+            let ret = if len == 0 {
+                // all the sources were empty, so is the result
+                kve.remove(&dest).map(|_| ())
+            } else {
+                kve.upsert(Data::from(dest), Data::from(result))
+            };
+            match ret {
+                Ok(()) => conwrite!(con, len)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+#[test]
+fn test_bit_ops() {
+    let mut bitmap = Vec::new();
+    assert!(!set_bit(&mut bitmap, 7, true));
+    assert_eq!(bitmap, vec![0b0000_0001]);
+    assert!(!set_bit(&mut bitmap, 8, true));
+    assert_eq!(bitmap, vec![0b0000_0001, 0b1000_0000]);
+    assert!(set_bit(&mut bitmap, 7, false));
+    assert_eq!(bitmap, vec![0, 0b1000_0000]);
+    assert!(get_bit(&bitmap, 8));
+    assert!(!get_bit(&bitmap, 7));
+    assert!(!get_bit(&bitmap, 1024));
+    assert_eq!(count_bits(b"foobar"), 26);
+}
+
+#[test]
+fn test_parse_offset_and_bit() {
+    assert_eq!(parse_offset(b"0"), Some(0));
+    assert_eq!(parse_offset(b"4294967294"), Some(MAX_BIT_OFFSET));
+    assert_eq!(parse_offset(b"4294967295"), None);
+    assert_eq!(parse_offset(b"-1"), None);
+    assert_eq!(parse_offset(b"ten"), None);
+    assert_eq!(parse_bit(b"1"), Some(true));
+    assert_eq!(parse_bit(b"0"), Some(false));
+    assert_eq!(parse_bit(b"2"), None);
+}
+
+#[test]
+fn test_run_bitop() {
+    let sources = vec![vec![0b1100_1100, 0xFF], vec![0b1010_1010]];
+    assert_eq!(run_bitop(BitOp::And, &sources), vec![0b1000_1000, 0]);
+    assert_eq!(run_bitop(BitOp::Or, &sources), vec![0b1110_1110, 0xFF]);
+    assert_eq!(run_bitop(BitOp::Xor, &sources), vec![0b0110_0110, 0xFF]);
+    assert_eq!(run_bitop(BitOp::Not, &sources[1..]), vec![0b0101_0101]);
+    assert!(run_bitop(BitOp::Or, &[Vec::new(), Vec::new()]).is_empty());
+    assert_eq!(BitOp::from_bytes(b"xor"), Some(BitOp::Xor));
+    assert_eq!(BitOp::from_bytes(b"NAND"), None);
+}
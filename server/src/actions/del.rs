@@ -36,7 +36,6 @@ action!(
     /// Do note that this function is blocking since it acquires a write lock.
     /// It will write an entire datagroup, for this `del` action
     fn del(handle: &Corestore, con: &mut T, act: ActionIter) {
-        err_if_len_is!(act, con, eq 0);
         let kve = kve!(con, handle);
         let encoding_is_okay = if kve.needs_key_encoding() {
             true
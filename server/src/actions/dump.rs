@@ -0,0 +1,143 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `DUMP` queries
+//! This module provides functions to work with `DUMP` queries, which stream a copy of a
+//! table or a keyspace over the connection so that backups can be taken from hosts that
+//! can't get to the data directory. A copy is a table file in the current format (like the
+//! ones in `data/ks`), split up into chunks: putting the chunks together gives a file that
+//! `SYS LOAD` can load
+
+use crate::corestore::memstore::SYSTEM;
+use crate::corestore::table::{DataModel, Table};
+use crate::dbnet::connection::prelude::*;
+use crate::resp::builder::{self, ResponseElement};
+use crate::resp::writer::TypedArrayWriter;
+use crate::storage::compat;
+use crate::storage::interface;
+use bytes::Bytes;
+use std::sync::Arc;
+
+const TABLE: &[u8] = "TABLE".as_bytes();
+const KEYSPACE: &[u8] = "KEYSPACE".as_bytes();
+
+/// The size of the chunks that a table file is split up into
+const CHUNK_SIZE: usize = 64 * 1024;
+
+action!(
+    /// Run a `DUMP` query:
+    /// - `DUMP TABLE <entity>` returns a copy of the table as an array of binary chunks
+    /// - `DUMP KEYSPACE <keyspace>` returns a copy of every table in the keyspace as an
+    /// array of alternating table names and arrays of binary chunks
+    ///
+    /// The copies are taken at a [rendezvous](crate::corestore::memstore::Keyspace::rendezvous)
+    /// (all the tables of a keyspace at the same one), so they never have half of an `MSET`
+    fn dump(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let (what, name) = unsafe {
+            // SAFETY: The dispatcher has checked the length
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        if what.eq_ignore_ascii_case(TABLE) {
+            let entity = handle_entity!(con, name);
+            let (keyspace, table) = get_ks_and_tbl!(entity, handle, con);
+            let copy = keyspace.rendezvous(|| table.snapshot());
+            let chunks = match table_file(copy).await {
+                Ok(chunks) => chunks,
+                Err(e) => {
+                    log::error!("Failed to dump table: {}", e);
+                    return conwrite!(con, groups::SERVER_ERR);
+                }
+            };
+            let mut writer = unsafe {
+                // SAFETY: all the elements are binary strings
+                TypedArrayWriter::new(con, b'?', chunks.len())
+            }
+            .await?;
+            for chunk in chunks {
+                writer.write_element(chunk).await?;
+            }
+        } else if what.eq_ignore_ascii_case(KEYSPACE) {
+            if SYSTEM.eq(&name) {
+                return conwrite!(con, groups::PROTECTED_OBJECT);
+            }
+            let keyspace = match handle.get_keyspace(&name) {
+                Some(keyspace) => keyspace,
+                None => return conwrite!(con, groups::CONTAINER_NOT_FOUND),
+            };
+            let copy = keyspace.duplicate();
+            let tables: Vec<_> = copy
+                .tables
+                .iter()
+                .map(|kv| (unsafe { kv.key().as_str() }.to_owned(), kv.value().clone()))
+                .collect();
+            conwrite!(con, builder::array_header(tables.len() * 2))?;
+            // the tables are written out one by one, so only one of them is ever serialized
+            // at a time
+            for (name, table) in tables {
+                let table = Arc::try_unwrap(table).unwrap_or_else(|table| table.snapshot());
+                let chunks = match table_file(table).await {
+                    Ok(chunks) => chunks,
+                    Err(e) => {
+                        // we've already started writing the response, so all we can do is
+                        // to drop the connection
+                        log::error!("Failed to dump keyspace: {}", e);
+                        return Err(e);
+                    }
+                };
+                let chunks = chunks.into_iter().map(ResponseElement::Binary);
+                conwrite!(con, ResponseElement::from(name))?;
+                conwrite!(con, ResponseElement::array(chunks))?;
+            }
+        } else {
+            aerr!(con, aerr);
+        }
+        Ok(())
+    }
+);
+
+/// Serialize a table (in a blocking task) into a table file in the current format, split
+/// up into chunks
+async fn table_file(table: Table) -> std::io::Result<Vec<Bytes>> {
+    tokio::task::spawn_blocking(move || {
+        let mut file = Vec::new();
+        compat::write_table_header(&mut file, compat::FORMAT_CURRENT)?;
+        match table.get_model_ref() {
+            DataModel::KV(kve) => {
+                // keys that have expired shouldn't come back when the file is loaded
+                kve.purge_expired();
+                interface::serialize_records_into_slow_buffer(&mut file, kve)?;
+            }
+        }
+        let file = Bytes::from(file);
+        let chunks = (0..file.len())
+            .step_by(CHUNK_SIZE)
+            .map(|start| file.slice(start..file.len().min(start + CHUNK_SIZE)))
+            .collect();
+        Ok(chunks)
+    })
+    .await
+    .expect("dump thread panicked")
+}
@@ -34,7 +34,6 @@ use crate::util::compiler;
 action!(
     /// Run an `EXISTS` query
     fn exists(handle: &Corestore, con: &mut T, act: ActionIter) {
-        err_if_len_is!(act, con, eq 0);
         let mut how_many_of_them_exist = 0usize;
         let kve = kve!(con, handle);
         let encoding_is_okay = if kve.needs_key_encoding() {
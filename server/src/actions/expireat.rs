@@ -0,0 +1,60 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `EXPIREAT` queries
+//! This module provides functions to work with `EXPIREAT` queries
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::services::expiry;
+use crate::util::compiler;
+
+action! {
+    /// Run an `EXPIREAT <key> <timestamp>` query, which makes the key expire at the given
+    /// UNIX timestamp (in seconds). Since the deadline is absolute, it stays the same across
+    /// restarts; a timestamp that has already passed removes the key right away
+    fn expireat(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let (key, timestamp) = unsafe {
+            // SAFETY: We have checked for there to be two args
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        let deadline = match String::from_utf8_lossy(&timestamp).parse::<u64>() {
+            Ok(secs) => expiry::jitter(handle, secs.saturating_mul(1000)),
+            Err(_) => return conwrite!(con, groups::WRONGTYPE_ERR),
+        };
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            match kve.set_expiry(Data::from(key), deadline) {
+                Ok(true) => conwrite!(con, groups::OKAY)?,
+                Ok(false) => conwrite!(con, groups::NIL)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+}
@@ -26,21 +26,47 @@
 
 use crate::dbnet::connection::prelude::*;
 use crate::queryengine::ActionIter;
+use crate::resp::BytesWrapper;
+use bytes::Bytes;
+
+const CONFIRM: &[u8] = b"CONFIRM";
 
 action!(
     /// Delete all the keys in the database
-    fn flushdb(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, gt 1);
+    ///
+    /// If flush confirmations are enabled, flushing a non-empty table returns a one-time
+    /// token instead, and the flush only goes through once it is run again with
+    /// `CONFIRM <token>`
+    fn flushdb(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
         if registry::state_okay() {
-            if act.len() == 0 {
+            let tbl = if act.len() % 2 == 0 {
                 // flush the current table
-                get_tbl!(handle, con).truncate_table();
+                get_tbl!(handle, con)
             } else {
                 // flush the entity
                 let raw_entity = unsafe { act.next().unsafe_unwrap() };
                 let entity = handle_entity!(con, raw_entity);
-                get_tbl!(entity, handle, con).truncate_table();
+                get_tbl!(entity, handle, con)
+            };
+            let token = match (act.next(), act.next()) {
+                (None, None) => None,
+                (Some(confirm), Some(token)) if confirm.eq_ignore_ascii_case(CONFIRM) => {
+                    Some(token)
+                }
+                _ => aerr!(con, aerr),
+            };
+            if registry::get_flush_confirm() && tbl.count() != 0 {
+                match token {
+                    Some(token) if handle.take_flush_token(&tbl, &token) => {}
+                    Some(_) => return conwrite!(con, responses::groups::BAD_FLUSH_TOKEN),
+                    None => {
+                        let token = handle.create_flush_token(&tbl);
+                        let token = Bytes::copy_from_slice(token.as_bytes());
+                        return conwrite!(con, BytesWrapper(token));
+                    }
+                }
             }
+            tbl.truncate_table();
             conwrite!(con, responses::groups::OKAY)?;
         } else {
             conwrite!(con, responses::groups::SERVER_ERR)?;
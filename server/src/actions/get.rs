@@ -27,21 +27,103 @@
 //! # `GET` queries
 //! This module provides functions to work with `GET` queries
 
+use crate::corestore::Data;
 use crate::dbnet::connection::prelude::*;
+use crate::registry::ServerMode;
 use crate::resp::writer;
+use crate::services::origin;
 use crate::util::compiler;
 
+const WITHTTL: &[u8] = b"WITHTTL";
+const WITHVERSION: &[u8] = b"WITHVERSION";
+
 action!(
     /// Run a `GET` query
+    ///
+    /// If the key doesn't exist and the table has a read-through origin, then the key is
+    /// fetched from the origin (see [`origin`])
+    ///
+    /// If the table has a response cache, then the response for the key is taken from it
+    /// (see [`respcache`](crate::kvengine::respcache))
+    ///
+    /// `GET <key> WITHTTL` returns an array with the value and the number of seconds after
+    /// which the key expires (rounded up), or nil if the key doesn't expire. Keys are never
+    /// fetched from the origin in this form
+    ///
+    /// `GET <key> WITHVERSION` returns an array with the value and its version, which can be
+    /// passed to `SETV` (see [`setv`](super::setv::setv))
     fn get(handle: &crate::corestore::Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 1);
         let kve = kve!(con, handle);
-        unsafe {
-            match kve.get_cloned_with_tsymbol(&act.next().unsafe_unwrap()) {
-                Ok((Some(val), tsymbol)) => writer::write_raw_mono(con, tsymbol, &val).await?,
+        let key = unsafe { act.next().unsafe_unwrap() };
+        if let Some(option) = act.next() {
+            if option.eq_ignore_ascii_case(WITHVERSION) {
+                match kve.get_versioned(&key) {
+                    Ok(Some((val, version))) => {
+                        con.write_array_length(2).await?;
+                        writer::write_raw_mono(con, kve.get_vt(), &val).await?;
+                        con.write_response(version).await?;
+                    }
+                    Ok(None) => conwrite!(con, groups::NIL)?,
+                    Err(_) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+                }
+                return Ok(());
+            }
+            if !option.eq_ignore_ascii_case(WITHTTL) {
+                aerr!(con, aerr);
+            }
+            match kve.get_cloned_with_ttl(&key) {
+                Ok(Some((val, ttl))) => {
+                    con.write_array_length(2).await?;
+                    writer::write_raw_mono(con, kve.get_vt(), &val).await?;
+                    match ttl {
+                        Some(ms) => con.write_response(ms.saturating_add(999) / 1000).await?,
+                        None => conwrite!(con, groups::NIL)?,
+                    }
+                }
+                Ok(None) => conwrite!(con, groups::NIL)?,
                 Err(_) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
-                Ok(_) => conwrite!(con, groups::NIL)?,
             }
+            return Ok(());
+        }
+        match kve.get_cloned_with_tsymbol(&key) {
+            Ok((Some(val), tsymbol)) => match kve.response_cache() {
+                Some(respcache) => {
+                    con.write_response(respcache.get_or_insert(&key, tsymbol, val))
+                        .await?
+                }
+                None => writer::write_raw_mono(con, tsymbol, &val).await?,
+            },
+            Err(_) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            Ok((None, tsymbol)) if origin::is_enabled() => {
+                let origin = match handle
+                    .ctable_name()
+                    .and_then(|t| origin::get_readthrough(&t))
+                {
+                    Some(origin) => origin,
+                    None => return conwrite!(con, groups::NIL),
+                };
+                let val = match origin.fetch(&key).await {
+                    Ok(Some(val)) => Data::from(val),
+                    Ok(None) => return conwrite!(con, groups::NIL),
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to read a key of `{}` from its origin: {}",
+                            origin.table(),
+                            e
+                        );
+                        return conwrite!(con, groups::ORIGIN_FAILED);
+                    }
+                };
+                if !kve.get_value_encoder().is_ok(&val) {
+                    return conwrite!(con, groups::ENCODING_ERROR);
+                }
+                if registry::get_mode() == ServerMode::Normal && registry::state_okay() {
+                    // if someone set the key in the meantime, then we keep their value
+                    let _ = kve.set(Data::from(key), val.clone());
+                }
+                writer::write_raw_mono(con, tsymbol, &val).await?
+            }
+            Ok(_) => conwrite!(con, groups::NIL)?,
         }
         Ok(())
     }
@@ -0,0 +1,75 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `GETEX` queries
+//! This module provides functions to work with `GETEX` queries
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::kvengine;
+use crate::resp::writer;
+use crate::services::expiry;
+use crate::util::compiler;
+
+const PERSIST: &[u8] = b"PERSIST";
+
+action! {
+    /// Run a `GETEX <key> <seconds>|PERSIST` query, which returns the value of the key
+    /// (or nil) and makes the key expire after the given number of seconds. `PERSIST`
+    /// removes the expiry instead
+    fn getex(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let (key, expiry) = unsafe {
+            // SAFETY: We have checked for there to be two args
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        let deadline = if expiry.eq_ignore_ascii_case(PERSIST) {
+            None
+        } else {
+            match String::from_utf8_lossy(&expiry).parse::<u64>() {
+                Ok(secs) if secs != 0 => Some(expiry::jitter(
+                    handle,
+                    kvengine::now_ms().saturating_add(secs.saturating_mul(1000)),
+                )),
+                _ => return conwrite!(con, groups::WRONGTYPE_ERR),
+            }
+        };
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let tsymbol = kve.get_vt();
+            match kve.get_and_set_expiry(Data::from(key), deadline) {
+                Ok(Some(val)) => unsafe {
+                    // SAFETY: We have verified the tsymbol ourselves
+                    writer::write_raw_mono(con, tsymbol, &val).await?
+                },
+                Ok(None) => conwrite!(con, groups::NIL)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `GETUPDATE` queries
+//! This module provides `GETUPDATE <key> <transform> <arg>`, which atomically transforms the
+//! value of a key and returns the new value. The transforms are:
+//! - `APPEND`: append `arg` to the value
+//! - `ADD`: add the integer `arg` to the value (which has to be an integer too)
+//! - `MAX`: set the value to `arg` if `arg` is the greater integer
+//! - `MIN`: set the value to `arg` if `arg` is the smaller integer
+//!
+//! Missing keys are set to `arg`. Integers are signed 64-bit integers written out in decimal
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::resp::writer;
+use crate::util::compiler;
+use core::str;
+
+/// A transform that `GETUPDATE` can apply to a value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transform {
+    Append,
+    Add,
+    Max,
+    Min,
+}
+
+impl Transform {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        let mut name = name.to_vec();
+        name.make_ascii_uppercase();
+        match name.as_slice() {
+            b"APPEND" => Some(Self::Append),
+            b"ADD" => Some(Self::Add),
+            b"MAX" => Some(Self::Max),
+            b"MIN" => Some(Self::Min),
+            _ => None,
+        }
+    }
+}
+
+fn parse_int(value: &[u8]) -> Option<i64> {
+    str::from_utf8(value).ok()?.parse().ok()
+}
+
+/// Apply `transform` to the current value (if the key exists), returning the new value.
+/// This returns `None` if the value or `arg` isn't an integer when it needs to be, or if
+/// the addition overflows
+fn apply(transform: Transform, current: Option<&[u8]>, arg: &[u8]) -> Option<Vec<u8>> {
+    if let Transform::Append = transform {
+        let mut new = current.map(<[u8]>::to_vec).unwrap_or_default();
+        new.extend_from_slice(arg);
+        return Some(new);
+    }
+    let arg = parse_int(arg)?;
+    let new = match current {
+        Some(current) => {
+            let current = parse_int(current)?;
+            match transform {
+                Transform::Add => current.checked_add(arg)?,
+                Transform::Max => current.max(arg),
+                Transform::Min => current.min(arg),
+                Transform::Append => unreachable!(),
+            }
+        }
+        None => arg,
+    };
+    Some(new.to_string().into_bytes())
+}
+
+action!(
+    /// Run a `GETUPDATE` query
+    fn getupdate(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let (key, transform, arg) = unsafe {
+            // SAFETY: We have already checked that there are exactly 3 arguments
+            (
+                act.next().unsafe_unwrap(),
+                act.next().unsafe_unwrap(),
+                act.next().unsafe_unwrap(),
+            )
+        };
+        let transform = match Transform::from_name(&transform) {
+            Some(transform) => transform,
+            None => return conwrite!(con, groups::ACTION_ERR),
+        };
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let ret = kve.read_modify_write(Data::from(key), |current| {
+                match apply(transform, current.map(|v| v.as_ref()), &arg) {
+                    // values that stay the same aren't written back
+                    Some(new) if current.map_or(false, |v| v.as_ref() == new.as_slice()) => {
+                        (None, current.cloned())
+                    }
+                    Some(new) => {
+                        let new = Data::from(new);
+                        (Some(new.clone()), Some(new))
+                    }
+                    None => (None, None),
+                }
+            });
+            match ret {
+                Ok(Some(new)) => writer::write_raw_mono(con, kve.get_vt(), &new).await?,
+                Ok(None) => conwrite!(con, groups::WRONGTYPE_ERR)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+#[test]
+fn test_apply() {
+    assert_eq!(
+        apply(Transform::Append, Some(&b"hello"[..]), b" world"),
+        Some(b"hello world".to_vec())
+    );
+    assert_eq!(apply(Transform::Append, None, b"hi"), Some(b"hi".to_vec()));
+    assert_eq!(
+        apply(Transform::Add, Some(&b"40"[..]), b"2"),
+        Some(b"42".to_vec())
+    );
+    assert_eq!(
+        apply(Transform::Add, Some(&b"1"[..]), b"-3"),
+        Some(b"-2".to_vec())
+    );
+    assert_eq!(apply(Transform::Add, None, b"5"), Some(b"5".to_vec()));
+    assert_eq!(
+        apply(Transform::Max, Some(&b"10"[..]), b"7"),
+        Some(b"10".to_vec())
+    );
+    assert_eq!(
+        apply(Transform::Max, Some(&b"10"[..]), b"12"),
+        Some(b"12".to_vec())
+    );
+    assert_eq!(
+        apply(Transform::Min, Some(&b"10"[..]), b"7"),
+        Some(b"7".to_vec())
+    );
+    // not integers
+    assert_eq!(apply(Transform::Add, Some(&b"ten"[..]), b"1"), None);
+    assert_eq!(apply(Transform::Max, Some(&b"10"[..]), b"eleven"), None);
+    // overflow
+    assert_eq!(
+        apply(Transform::Add, Some(i64::MAX.to_string().as_bytes()), b"1"),
+        None
+    );
+    assert_eq!(Transform::from_name(b"append"), Some(Transform::Append));
+    assert_eq!(Transform::from_name(b"mul"), None);
+}
@@ -0,0 +1,402 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # HyperLogLog actions
+//! This module provides approximate unique counting with HyperLogLogs stored as values:
+//! - `PFADD <key> [element ...]`: add elements to the HyperLogLog at `key`, returning 1 if
+//! its registers changed (or it was created), and 0 otherwise
+//! - `PFCOUNT <key> [key ...]`: estimate the number of unique elements added to the
+//! HyperLogLogs (their union, if more than one key is passed)
+//! - `PFMERGE <destkey> <srckey> [srckey ...]`: merge the source HyperLogLogs into `destkey`
+//!
+//! ## Encoding
+//! We use 2<sup>14</sup> registers which gives a standard error of about 0.81%. A
+//! HyperLogLog starts off with a sparse encoding that only stores the non-zero registers
+//! and is converted into a dense encoding (one byte per register) once the sparse
+//! encoding grows beyond [`SPARSE_MAX_BYTES`]. This keeps small counters small while
+//! capping the memory cost of a large one at about 16 KiB.
+//!
+//! The layout is the magic `HYLL` followed by an encoding byte (`S` or `D`) and then:
+//! - for a sparse HyperLogLog: 3 bytes per non-zero register, the register index as two
+//! 7-bit halves followed by the register value; sorted by the register index
+//! - for a dense HyperLogLog: one byte per register
+//!
+//! Every byte of the encoding is below `0x80`, so HyperLogLogs are valid UTF-8 and can
+//! be stored in tables with string values as well
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::kvengine::KVEngine;
+use crate::util::compiler;
+
+/// The number of bits of the hash used to pick a register
+const PRECISION: u32 = 14;
+/// The number of registers
+const REGISTERS: usize = 1 << PRECISION;
+/// The largest value a register can hold
+const MAX_RANK: u8 = (64 - PRECISION + 1) as u8;
+/// The magic that every encoded HyperLogLog starts with
+const MAGIC: &[u8] = b"HYLL";
+const TAG_SPARSE: u8 = b'S';
+const TAG_DENSE: u8 = b'D';
+/// The size beyond which a sparse HyperLogLog is converted into a dense one
+const SPARSE_MAX_BYTES: usize = 3000;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Registers {
+    /// The non-zero registers as `(index, value)` pairs, sorted by index
+    Sparse(Vec<(u16, u8)>),
+    /// All the registers
+    Dense(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// A HyperLogLog
+struct Hll {
+    registers: Registers,
+}
+
+impl Hll {
+    /// Create a new (empty) HyperLogLog
+    const fn new() -> Self {
+        Self {
+            registers: Registers::Sparse(Vec::new()),
+        }
+    }
+    /// Decode a HyperLogLog, returning `None` if `raw` isn't a valid HyperLogLog
+    fn decode(raw: &[u8]) -> Option<Self> {
+        if raw.len() < MAGIC.len() + 1 || !raw.starts_with(MAGIC) {
+            return None;
+        }
+        let (tag, body) = (raw[MAGIC.len()], &raw[MAGIC.len() + 1..]);
+        let registers = match tag {
+            TAG_SPARSE if body.len() % 3 == 0 => {
+                let mut registers: Vec<(u16, u8)> = Vec::with_capacity(body.len() / 3);
+                for entry in body.chunks_exact(3) {
+                    let (hi, lo, rank) = (entry[0], entry[1], entry[2]);
+                    if hi >= 0x80 || lo >= 0x80 || rank == 0 || rank > MAX_RANK {
+                        return None;
+                    }
+                    let idx = (u16::from(hi) << 7) | u16::from(lo);
+                    match registers.last() {
+                        Some((last, _)) if *last >= idx => return None,
+                        _ => registers.push((idx, rank)),
+                    }
+                }
+                Registers::Sparse(registers)
+            }
+            TAG_DENSE if body.len() == REGISTERS && body.iter().all(|r| *r <= MAX_RANK) => {
+                Registers::Dense(body.to_vec())
+            }
+            _ => return None,
+        };
+        Some(Self { registers })
+    }
+    /// Encode this HyperLogLog so that it can be stored
+    fn encode(&self) -> Vec<u8> {
+        let mut ret = MAGIC.to_vec();
+        match &self.registers {
+            Registers::Sparse(registers) => {
+                ret.reserve(1 + registers.len() * 3);
+                ret.push(TAG_SPARSE);
+                for (idx, rank) in registers {
+                    ret.extend_from_slice(&[(idx >> 7) as u8, (idx & 0x7F) as u8, *rank]);
+                }
+            }
+            Registers::Dense(registers) => {
+                ret.reserve(1 + REGISTERS);
+                ret.push(TAG_DENSE);
+                ret.extend_from_slice(registers);
+            }
+        }
+        ret
+    }
+    /// Raise the register at `idx` to `rank`, if it is lower. Returns true if the register
+    /// was changed
+    fn set_register(&mut self, idx: usize, rank: u8) -> bool {
+        match &mut self.registers {
+            Registers::Dense(registers) => {
+                if registers[idx] < rank {
+                    registers[idx] = rank;
+                    true
+                } else {
+                    false
+                }
+            }
+            Registers::Sparse(registers) => {
+                match registers.binary_search_by_key(&(idx as u16), |(idx, _)| *idx) {
+                    Ok(pos) if registers[pos].1 >= rank => return false,
+                    Ok(pos) => registers[pos].1 = rank,
+                    Err(pos) => registers.insert(pos, (idx as u16, rank)),
+                }
+                if registers.len() * 3 > SPARSE_MAX_BYTES {
+                    self.registers = Registers::Dense(self.dense_registers());
+                }
+                true
+            }
+        }
+    }
+    /// Returns a copy of all the registers
+    fn dense_registers(&self) -> Vec<u8> {
+        match &self.registers {
+            Registers::Dense(registers) => registers.clone(),
+            Registers::Sparse(sparse) => {
+                let mut registers = vec![0; REGISTERS];
+                for (idx, rank) in sparse {
+                    registers[*idx as usize] = *rank;
+                }
+                registers
+            }
+        }
+    }
+    /// Add an element. Returns true if any register was changed
+    fn add(&mut self, element: &[u8]) -> bool {
+        let hash = hash(element);
+        let idx = (hash & (REGISTERS as u64 - 1)) as usize;
+        // the sentinel bit makes sure that we never count past the bits we have
+        let rank = ((hash >> PRECISION) | (1 << (64 - PRECISION))).trailing_zeros() as u8 + 1;
+        self.set_register(idx, rank)
+    }
+    /// Merge another HyperLogLog into this one. Returns true if any register was changed
+    fn merge(&mut self, other: &Hll) -> bool {
+        let mut changed = false;
+        match &other.registers {
+            Registers::Sparse(registers) => {
+                for (idx, rank) in registers {
+                    changed |= self.set_register(*idx as usize, *rank);
+                }
+            }
+            Registers::Dense(registers) => {
+                for (idx, rank) in registers.iter().enumerate().filter(|(_, r)| **r != 0) {
+                    changed |= self.set_register(idx, *rank);
+                }
+            }
+        }
+        changed
+    }
+    /// Estimate the number of unique elements that were added
+    fn count(&self) -> u64 {
+        let registers = self.dense_registers();
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = registers.iter().map(|r| 2f64.powi(-i32::from(*r))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros != 0 {
+            // small range correction (linear counting)
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// A stable 64-bit hash (FNV-1a followed by the MurmurHash3 finalizer). This has to stay
+/// the same across releases since the registers are persisted
+fn hash(element: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in element {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+/// Errors that can occur while reading the HyperLogLogs for a query
+enum HllError {
+    /// A value isn't a valid HyperLogLog
+    Invalid,
+    /// A key had the wrong encoding
+    Encoding,
+}
+
+impl HllError {
+    const fn response(&self) -> &'static [u8] {
+        match self {
+            HllError::Invalid => groups::INVALID_HLL,
+            HllError::Encoding => groups::ENCODING_ERROR,
+        }
+    }
+}
+
+/// Merge the HyperLogLogs stored at `keys` into a single HyperLogLog. Missing keys are
+/// treated as empty HyperLogLogs
+fn union(kve: &KVEngine, keys: ActionIter) -> Result<Hll, HllError> {
+    let mut ret = Hll::new();
+    for key in keys {
+        match kve.get_cloned(&key) {
+            Ok(Some(raw)) => ret.merge(&Hll::decode(&raw).ok_or(HllError::Invalid)?),
+            Ok(None) => false,
+            Err(()) => return Err(HllError::Encoding),
+        };
+    }
+    Ok(ret)
+}
+
+action!(
+    /// Run a `PFADD` query
+    fn pfadd(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let key = unsafe {
+                // SAFETY: We have already checked that there is atleast 1 argument
+                act.next().unsafe_unwrap()
+            };
+            let ret = kve.read_modify_write(Data::from(key), |current| {
+                let (mut hll, mut changed) = match current {
+                    Some(raw) => match Hll::decode(raw) {
+                        Some(hll) => (hll, false),
+                        None => return (None, Err(())),
+                    },
+                    None => (Hll::new(), true),
+                };
+                for element in act {
+                    changed |= hll.add(&element);
+                }
+                if changed {
+                    (Some(Data::from(hll.encode())), Ok(1usize))
+                } else {
+                    (None, Ok(0))
+                }
+            });
+            match ret {
+                Ok(Ok(changed)) => conwrite!(con, changed)?,
+                Ok(Err(())) => conwrite!(con, groups::INVALID_HLL)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `PFCOUNT` query
+    fn pfcount(handle: &Corestore, con: &mut T, act: ActionIter) {
+        let kve = kve!(con, handle);
+        match union(kve, act) {
+            Ok(hll) => conwrite!(con, hll.count() as usize)?,
+            Err(e) => conwrite!(con, e.response())?,
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `PFMERGE` query
+    fn pfmerge(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let dest = unsafe {
+                // SAFETY: We have already checked that there are atleast 2 arguments
+                act.next().unsafe_unwrap()
+            };
+            let sources = match union(kve, act) {
+                Ok(hll) => hll,
+                Err(e) => return conwrite!(con, e.response()),
+            };
+            let ret = kve.read_modify_write(Data::from(dest), |current| {
+                let mut hll = match current.map(|raw| Hll::decode(raw)) {
+                    Some(Some(hll)) => hll,
+                    Some(None) => return (None, Err(())),
+                    None => Hll::new(),
+                };
+                hll.merge(&sources);
+                (Some(Data::from(hll.encode())), Ok(()))
+            });
+            match ret {
+                Ok(Ok(())) => conwrite!(con, groups::OKAY)?,
+                Ok(Err(())) => conwrite!(con, groups::INVALID_HLL)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+#[test]
+fn test_hll_encode_decode() {
+    let mut hll = Hll::new();
+    assert_eq!(Hll::decode(&hll.encode()).unwrap(), hll);
+    for i in 0..100u32 {
+        hll.add(&i.to_le_bytes());
+    }
+    assert!(matches!(hll.registers, Registers::Sparse(_)));
+    assert_eq!(Hll::decode(&hll.encode()).unwrap(), hll);
+    for i in 100..10_000u32 {
+        hll.add(&i.to_le_bytes());
+    }
+    assert!(matches!(hll.registers, Registers::Dense(_)));
+    let encoded = hll.encode();
+    assert!(encoded.iter().all(|b| *b < 0x80));
+    assert_eq!(Hll::decode(&encoded).unwrap(), hll);
+    // bad values
+    assert!(Hll::decode(b"").is_none());
+    assert!(Hll::decode(b"HYLL").is_none());
+    assert!(Hll::decode(b"HYLLS\x00\x01").is_none());
+    assert!(Hll::decode(b"HYLLS\x00\x02\x01\x00\x01\x01").is_none());
+    assert!(Hll::decode(b"HYLLD\x00").is_none());
+    assert!(Hll::decode(b"hello world").is_none());
+}
+
+#[test]
+fn test_hll_count() {
+    let mut hll = Hll::new();
+    assert_eq!(hll.count(), 0);
+    assert!(hll.add(b"sayan"));
+    assert!(!hll.add(b"sayan"));
+    assert_eq!(hll.count(), 1);
+    for &n in &[1_000u32, 100_000] {
+        let mut hll = Hll::new();
+        for i in 0..n {
+            hll.add(format!("element-{}", i).as_bytes());
+        }
+        let error = (hll.count() as f64 - n as f64).abs() / n as f64;
+        assert!(error < 0.03, "error for {} was {}", n, error);
+    }
+}
+
+#[test]
+fn test_hll_merge() {
+    let (mut a, mut b) = (Hll::new(), Hll::new());
+    for i in 0..5_000u32 {
+        a.add(&i.to_le_bytes());
+    }
+    for i in 2_500..7_500u32 {
+        b.add(&i.to_le_bytes());
+    }
+    assert!(a.merge(&b));
+    assert!(!a.merge(&b));
+    let error = (a.count() as f64 - 7_500.0).abs() / 7_500.0;
+    assert!(error < 0.03);
+}
@@ -0,0 +1,390 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # JSON actions
+//! This module provides functions to work with JSON documents that are stored as values:
+//! - `JSET <key> <path> <json>`: set the value at `path` (creating the document if
+//! `path` is the root)
+//! - `JGET <key> [path]`: get the value at `path` (or the whole document)
+//! - `JDEL <key> <path>`: delete the value at `path` (or the whole document)
+//!
+//! Paths are a small JSONPath-like subset: `$` is the root, `.name` selects an object
+//! member and `[n]` selects an array element. For example, `$.users[0].name`. The leading
+//! `$` is optional. If the current table has a [schema](crate::services::schema), the
+//! documents that `JSET` and `JDEL` leave behind have to match it
+//!
+//! Documents are stored serialized, like any other value, and the parsed documents of the
+//! keys that are used over and over are kept in the table's
+//! [document cache](crate::kvengine::doccache), so that they aren't parsed again for every
+//! action
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::kvengine::doccache::{self, DocumentCache};
+use crate::resp::BytesWrapper;
+use crate::services::schema::{self, Schema};
+use crate::util::compiler;
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+/// A single segment of a JSON path
+enum Segment {
+    /// An object member
+    Key(String),
+    /// An array element
+    Index(usize),
+}
+
+#[derive(Debug, PartialEq)]
+/// Errors that can occur while running a JSON action
+enum JsonError {
+    /// The stored value or the supplied value isn't valid JSON
+    BadJson,
+    /// The path couldn't be parsed
+    BadPath,
+    /// The path doesn't exist in the document
+    NotFound,
+    /// The document wouldn't match the table's schema anymore
+    Schema(Vec<String>),
+}
+
+impl JsonError {
+    const fn response(&self) -> &'static [u8] {
+        match self {
+            JsonError::BadJson => groups::BAD_JSON,
+            JsonError::BadPath => groups::BAD_JSON_PATH,
+            JsonError::NotFound => groups::NIL,
+            JsonError::Schema(_) => groups::SCHEMA_VIOLATION,
+        }
+    }
+}
+
+/// Parse a JSON path into its segments. An empty vector refers to the root
+fn parse_path(path: &[u8]) -> Result<Vec<Segment>, JsonError> {
+    let path = std::str::from_utf8(path).map_err(|_| JsonError::BadPath)?;
+    let mut path = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+    if !path.is_empty() && !path.starts_with('.') && !path.starts_with('[') {
+        // allow `a.b` as shorthand for `$.a.b`
+        let end = path
+            .find(|c: char| c == '.' || c == '[')
+            .unwrap_or(path.len());
+        segments.push(Segment::Key(path[..end].to_owned()));
+        path = &path[end..];
+    }
+    while !path.is_empty() {
+        if let Some(rest) = path.strip_prefix('.') {
+            let end = rest
+                .find(|c: char| c == '.' || c == '[')
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return Err(JsonError::BadPath);
+            }
+            segments.push(Segment::Key(rest[..end].to_owned()));
+            path = &rest[end..];
+        } else if let Some(rest) = path.strip_prefix('[') {
+            let end = rest.find(']').ok_or(JsonError::BadPath)?;
+            let idx = rest[..end].parse().map_err(|_| JsonError::BadPath)?;
+            segments.push(Segment::Index(idx));
+            path = &rest[end + 1..];
+        } else {
+            return Err(JsonError::BadPath);
+        }
+    }
+    Ok(segments)
+}
+
+/// Get a reference to the value at `path`
+fn get_path<'a>(doc: &'a Value, path: &[Segment]) -> Option<&'a Value> {
+    path.iter().try_fold(doc, |current, segment| match segment {
+        Segment::Key(key) => current.as_object()?.get(key),
+        Segment::Index(idx) => current.as_array()?.get(*idx),
+    })
+}
+
+/// Set the value at `path`. The parent of the last segment must exist; a missing object
+/// member is created and an array index equal to the array's length appends to it
+fn set_path(doc: &mut Value, path: &[Segment], new: Value) -> Result<(), JsonError> {
+    let (last, parents) = match path.split_last() {
+        Some(split) => split,
+        None => {
+            *doc = new;
+            return Ok(());
+        }
+    };
+    let parent = parents
+        .iter()
+        .try_fold(doc, |current, segment| match segment {
+            Segment::Key(key) => current.as_object_mut()?.get_mut(key),
+            Segment::Index(idx) => current.as_array_mut()?.get_mut(*idx),
+        })
+        .ok_or(JsonError::NotFound)?;
+    match (last, parent) {
+        (Segment::Key(key), Value::Object(obj)) => {
+            obj.insert(key.clone(), new);
+        }
+        (Segment::Index(idx), Value::Array(arr)) if *idx < arr.len() => arr[*idx] = new,
+        (Segment::Index(idx), Value::Array(arr)) if *idx == arr.len() => arr.push(new),
+        _ => return Err(JsonError::NotFound),
+    }
+    Ok(())
+}
+
+/// Remove the value at `path`, returning true if it existed. `path` must not be the root
+fn del_path(doc: &mut Value, path: &[Segment]) -> bool {
+    let (last, parents) = match path.split_last() {
+        Some(split) => split,
+        None => return false,
+    };
+    let parent = parents
+        .iter()
+        .try_fold(doc, |current, segment| match segment {
+            Segment::Key(key) => current.as_object_mut()?.get_mut(key),
+            Segment::Index(idx) => current.as_array_mut()?.get_mut(*idx),
+        });
+    match (last, parent) {
+        (Segment::Key(key), Some(Value::Object(obj))) => obj.remove(key).is_some(),
+        (Segment::Index(idx), Some(Value::Array(arr))) if *idx < arr.len() => {
+            arr.remove(*idx);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Take the document that the key has out of the document cache (or parse it)
+fn take_doc(cache: &DocumentCache, key: &[u8], current: &Data) -> Result<Value, JsonError> {
+    cache.take(key, current).ok_or(JsonError::BadJson)
+}
+
+/// Check a changed document against the table's schema, if it has one
+fn check_schema(table_schema: Option<&Schema>, doc: &Value) -> Result<(), JsonError> {
+    match table_schema.map(|table_schema| table_schema.validate(doc)) {
+        Some(violations) if !violations.is_empty() => Err(JsonError::Schema(violations)),
+        _ => Ok(()),
+    }
+}
+
+action!(
+    /// Run a `JSET` query
+    fn jset(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let (key, path, value) = unsafe {
+                // SAFETY: We have already checked that there are exactly 3 arguments
+                (
+                    act.next().unsafe_unwrap(),
+                    act.next().unsafe_unwrap(),
+                    act.next().unsafe_unwrap(),
+                )
+            };
+            let path = match parse_path(&path) {
+                Ok(path) => path,
+                Err(e) => return conwrite!(con, e.response()),
+            };
+            let value: Value = match serde_json::from_slice(&value) {
+                Ok(value) => value,
+                Err(_) => return conwrite!(con, groups::BAD_JSON),
+            };
+            let table_schema = schema::get(handle);
+            let cache = kve.document_cache();
+            let key = Data::from(key);
+            let ret = kve.read_modify_write_stored(key.clone(), |current| match current {
+                None if path.is_empty() => match check_schema(table_schema.as_deref(), &value) {
+                    Ok(()) => (Some(doccache::serialize(&value)), Ok(value)),
+                    Err(e) => (None, Err(e)),
+                },
+                None => (None, Err(JsonError::NotFound)),
+                Some(current) => {
+                    let mut doc = match take_doc(cache, &key, current) {
+                        Ok(doc) => doc,
+                        Err(e) => return (None, Err(e)),
+                    };
+                    match set_path(&mut doc, &path, value)
+                        .and_then(|()| check_schema(table_schema.as_deref(), &doc))
+                    {
+                        Ok(()) => (Some(doccache::serialize(&doc)), Ok(doc)),
+                        Err(e) => (None, Err(e)),
+                    }
+                }
+            });
+            match ret {
+                Ok((Ok(doc), stored)) => {
+                    if let Some(stored) = stored {
+                        cache.insert(key, stored, doc);
+                    }
+                    conwrite!(con, groups::OKAY)?
+                }
+                Ok((Err(JsonError::Schema(violations)), _)) => {
+                    conwrite!(con, schema::error(handle, violations))?
+                }
+                Ok((Err(e), _)) => conwrite!(con, e.response())?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `JGET` query
+    fn jget(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let kve = kve!(con, handle);
+        let key = unsafe {
+            // SAFETY: We have already checked that there is atleast one argument
+            act.next().unsafe_unwrap()
+        };
+        let path = match act.next().map(|path| parse_path(&path)) {
+            Some(Ok(path)) => path,
+            Some(Err(e)) => return conwrite!(con, e.response()),
+            None => Vec::new(),
+        };
+        let doc = match kve.get_cloned(&key) {
+            Ok(Some(doc)) => doc,
+            Ok(None) => return conwrite!(con, groups::NIL),
+            Err(()) => return compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR)),
+        };
+        let value = kve.document_cache().with(&key, &doc, |doc| {
+            get_path(doc, &path).map(doccache::serialize)
+        });
+        match value {
+            Some(Some(value)) => conwrite!(con, BytesWrapper(value.into_inner()))?,
+            Some(None) => conwrite!(con, groups::NIL)?,
+            None => conwrite!(con, JsonError::BadJson.response())?,
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `JDEL` query
+    fn jdel(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let (key, path) = unsafe {
+                // SAFETY: We have already checked that there are exactly 2 arguments
+                (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+            };
+            let path = match parse_path(&path) {
+                Ok(path) => path,
+                Err(e) => return conwrite!(con, e.response()),
+            };
+            if path.is_empty() {
+                // deleting the root is the same as deleting the key, if it holds a document
+                let is_doc = |value: &Data| doccache::parse(value).is_some();
+                return match kve.remove_if(&key, is_doc) {
+                    Ok(Some(true)) => conwrite!(con, groups::OKAY),
+                    Ok(Some(false)) => conwrite!(con, JsonError::BadJson.response()),
+                    Ok(None) => conwrite!(con, groups::NIL),
+                    Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR)),
+                };
+            }
+            let table_schema = schema::get(handle);
+            let cache = kve.document_cache();
+            let key = Data::from(key);
+            let ret = kve.read_modify_write_stored(key.clone(), |current| match current {
+                None => (None, Err(JsonError::NotFound)),
+                Some(current) => {
+                    let mut doc = match take_doc(cache, &key, current) {
+                        Ok(doc) => doc,
+                        Err(e) => return (None, Err(e)),
+                    };
+                    if !del_path(&mut doc, &path) {
+                        return (None, Err(JsonError::NotFound));
+                    }
+                    match check_schema(table_schema.as_deref(), &doc) {
+                        Ok(()) => (Some(doccache::serialize(&doc)), Ok(doc)),
+                        Err(e) => (None, Err(e)),
+                    }
+                }
+            });
+            match ret {
+                Ok((Ok(doc), stored)) => {
+                    if let Some(stored) = stored {
+                        cache.insert(key, stored, doc);
+                    }
+                    conwrite!(con, groups::OKAY)?
+                }
+                Ok((Err(JsonError::Schema(violations)), _)) => {
+                    conwrite!(con, schema::error(handle, violations))?
+                }
+                Ok((Err(e), _)) => conwrite!(con, e.response())?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
+
+#[test]
+fn test_parse_path() {
+    use Segment::{Index, Key};
+    assert_eq!(parse_path(b"$").unwrap(), vec![]);
+    assert_eq!(parse_path(b"").unwrap(), vec![]);
+    assert_eq!(
+        parse_path(b"$.users[0].name").unwrap(),
+        vec![Key("users".to_owned()), Index(0), Key("name".to_owned())]
+    );
+    assert_eq!(
+        parse_path(b"users[10]").unwrap(),
+        vec![Key("users".to_owned()), Index(10)]
+    );
+    assert_eq!(parse_path(b"$..a").unwrap_err(), JsonError::BadPath);
+    assert_eq!(parse_path(b"$[a]").unwrap_err(), JsonError::BadPath);
+    assert_eq!(parse_path(b"$[0").unwrap_err(), JsonError::BadPath);
+}
+
+#[test]
+fn test_json_path_ops() {
+    let mut doc: Value = serde_json::from_str(r#"{"users":[{"name":"sayan"}]}"#).unwrap();
+    let path = parse_path(b"$.users[0].name").unwrap();
+    assert_eq!(get_path(&doc, &path), Some(&Value::from("sayan")));
+    // replace a nested value
+    set_path(&mut doc, &path, Value::from("nandan")).unwrap();
+    assert_eq!(get_path(&doc, &path), Some(&Value::from("nandan")));
+    // append to an array
+    let path = parse_path(b"$.users[1]").unwrap();
+    set_path(&mut doc, &path, Value::from(1)).unwrap();
+    assert_eq!(get_path(&doc, &path), Some(&Value::from(1)));
+    // missing parents aren't created
+    let path = parse_path(b"$.groups.admins").unwrap();
+    assert_eq!(
+        set_path(&mut doc, &path, Value::Null).unwrap_err(),
+        JsonError::NotFound
+    );
+    // delete
+    let path = parse_path(b"$.users[0]").unwrap();
+    assert!(del_path(&mut doc, &path));
+    assert_eq!(
+        doc,
+        serde_json::from_str::<Value>(r#"{"users":[1]}"#).unwrap()
+    );
+    assert!(!del_path(&mut doc, &parse_path(b"$.nothing").unwrap()));
+}
@@ -31,7 +31,6 @@ action!(
     ///
     /// At this moment, `keylen` only supports a single key
     fn keylen(handle: &crate::corestore::Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 1);
         let res: Option<usize> = {
             let reader = kve!(con, handle);
             unsafe {
@@ -23,61 +23,135 @@
  * along with this program. If not, see <https://www.gnu.org/licenses/>.
  *
 */
-
 use crate::corestore::memstore::DdlError;
 use crate::dbnet::connection::prelude::*;
 use crate::resp::writer::TypedArrayWriter;
 use bytes::Bytes;
 
 const DEFAULT_COUNT: usize = 10;
+/// The most arguments that an `LSKEYS` query can have
+pub const LSKEYS_MAX_ARGS: usize = 7;
+const LIMIT: &[u8] = "LIMIT".as_bytes();
+const OFFSET: &[u8] = "OFFSET".as_bytes();
+const ORDERED: &[u8] = "ORDERED".as_bytes();
 
-action!(
-    /// Run an `LSKEYS` query
-    fn lskeys(handle: &crate::corestore::Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, gt 3);
-        let (table, count) = if act.len() == 0 {
-            (get_tbl!(handle, con), DEFAULT_COUNT)
-        } else if act.len() == 1 {
-            // two args, could either be count or an entity
-            let nextret = unsafe { act.next().unsafe_unwrap() };
-            if unsafe { nextret.get_unchecked(0) }.is_ascii_digit() {
-                // noice, this is a number; let's try to parse it
-                let count = if let Ok(cnt) = String::from_utf8_lossy(&nextret).parse::<usize>() {
-                    cnt
-                } else {
-                    return con.write_response(responses::groups::WRONGTYPE_ERR).await;
-                };
-                (get_tbl!(handle, con), count)
+/// The options of an `LSKEYS` query:
+/// ```text
+/// LSKEYS [<entity>] [<limit>] [LIMIT <limit>] [OFFSET <offset>] [ORDERED]
+/// ```
+/// The entity and the limit can be given by position (like `LSKEYS` always took them) or
+/// with the options that follow them
+pub struct Listing {
+    /// the entity to list the keys of, if it isn't the current table
+    pub entity: Option<Bytes>,
+    /// the most keys to return
+    pub limit: usize,
+    /// the number of keys to skip
+    pub offset: usize,
+    /// whether the keys are sorted lexicographically (otherwise the order is meaningless)
+    pub ordered: bool,
+}
+
+impl Listing {
+    /// Parse the arguments of an `LSKEYS` query, returning the error response to be written
+    /// if they aren't right
+    pub fn parse(mut act: ActionIter) -> Result<Self, &'static [u8]> {
+        let mut listing = Self {
+            entity: None,
+            limit: DEFAULT_COUNT,
+            offset: 0,
+            ordered: false,
+        };
+        // a lone number is the limit and not an entity
+        match act.as_slice().first() {
+            Some(arg) if !is_number(arg) && !is_option(arg) => listing.entity = act.next(),
+            _ => {}
+        }
+        match act.as_slice().first() {
+            Some(arg) if is_number(arg) => listing.limit = parse_number(act.next())?,
+            _ => {}
+        }
+        while let Some(option) = act.next() {
+            if option.eq_ignore_ascii_case(LIMIT) {
+                listing.limit = parse_number(act.next())?;
+            } else if option.eq_ignore_ascii_case(OFFSET) {
+                listing.offset = parse_number(act.next())?;
+            } else if option.eq_ignore_ascii_case(ORDERED) {
+                listing.ordered = true;
             } else {
-                // sigh, an entity
-                let entity = handle_entity!(con, nextret);
-                (get_tbl!(entity, handle, con), DEFAULT_COUNT)
+                return Err(responses::groups::ACTION_ERR);
             }
+        }
+        Ok(listing)
+    }
+    /// Returns the number of keys that have to be read from the table to make this listing
+    /// (`None` if every key has to be read)
+    pub fn keys_read(&self) -> Option<usize> {
+        if self.ordered {
+            None
         } else {
-            // an entity and a count, gosh this fella is really trying us
-            let entity_ret = unsafe { act.next().unsafe_unwrap() };
-            let count_ret = unsafe { act.next().unsafe_unwrap() };
-            let entity = handle_entity!(con, entity_ret);
-            let count = if let Ok(cnt) = String::from_utf8_lossy(&count_ret).parse::<usize>() {
-                cnt
-            } else {
-                return con.write_response(responses::groups::WRONGTYPE_ERR).await;
-            };
-            (get_tbl!(entity, handle, con), count)
+            Some(self.offset.saturating_add(self.limit))
+        }
+    }
+}
+
+fn is_number(arg: &[u8]) -> bool {
+    arg.first().map_or(false, u8::is_ascii_digit)
+}
+
+fn is_option(arg: &[u8]) -> bool {
+    [LIMIT, OFFSET, ORDERED]
+        .iter()
+        .any(|option| arg.eq_ignore_ascii_case(option))
+}
+
+fn parse_number(arg: Option<Bytes>) -> Result<usize, &'static [u8]> {
+    match arg {
+        Some(arg) => String::from_utf8_lossy(&arg)
+            .parse()
+            .map_err(|_| responses::groups::WRONGTYPE_ERR),
+        None => Err(responses::groups::ACTION_ERR),
+    }
+}
+
+action!(
+    /// Run an `LSKEYS` query
+    fn lskeys(handle: &crate::corestore::Corestore, con: &mut T, act: ActionIter) {
+        let listing = match Listing::parse(act) {
+            Ok(listing) => listing,
+            Err(e) => return conwrite!(con, e),
+        };
+        let (keyspace, table) = match listing.entity {
+            Some(ref entity) => {
+                let entity = handle_entity!(con, entity);
+                get_ks_and_tbl!(entity, handle, con)
+            }
+            None => get_ks_and_tbl!(handle, con),
         };
         let kve = match table.get_kvstore() {
             Ok(kv) => kv,
             Err(DdlError::WrongModel) => return conwrite!(con, responses::groups::WRONG_MODEL),
             Err(_) => unsafe { impossible!() },
         };
-        let items: Vec<Bytes> = kve.__get_inner_ref().get_keys(count);
+        // the keys are picked at a rendezvous, so that we never return half of an `MSET`;
+        // they're sorted and written out after that, so writers aren't held off while we do it
+        let read = listing.keys_read().unwrap_or(usize::MAX);
+        let mut keys: Vec<Bytes> = keyspace.rendezvous(|| kve.__get_inner_ref().get_keys(read));
+        if listing.ordered {
+            keys.sort_unstable();
+        }
+        let keys: Vec<Bytes> = keys
+            .into_iter()
+            .skip(listing.offset)
+            .take(listing.limit)
+            .collect();
         let tsymbol = kve.get_kt();
         let mut writer = unsafe {
             // SAFETY: We have checked kty ourselves
-            TypedArrayWriter::new(con, tsymbol, items.len())
+            TypedArrayWriter::new(con, tsymbol, keys.len())
         }
         .await?;
-        for key in items {
+        for key in keys {
             writer.write_element(key).await?;
         }
         Ok(())
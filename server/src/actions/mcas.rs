@@ -0,0 +1,106 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `MCAS` queries
+//! This module provides `MCAS <key1> <expected1> <new1> <key2> <expected2> <new2> ...`, which
+//! sets every key to its new value only if every key currently has its expected value. This
+//! sits between the strong actions (which only check if keys exist) and full transactions.
+//! If any key doesn't have its expected value (or doesn't exist), nothing is written and the
+//! index of its triple is returned.
+//!
+//! No other multi-key write on the keyspace (like an `MSET` or another `MCAS`) can run while
+//! the keys are checked and set. Single key writes don't wait, but a key that is changed by
+//! one after it was checked is left alone, just like with `SUPDATE`
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::util::compiler;
+
+/// The outcome of checking the expectations
+enum Check {
+    /// Every key has its expected value
+    Okay,
+    /// The key in the triple at this index doesn't have its expected value
+    Mismatch(usize),
+    EncodingError,
+}
+
+action!(
+    /// Run an `MCAS` query
+    ///
+    /// This returns `Okay` if all the keys were set, or the index of the first triple whose
+    /// key didn't have the expected value
+    fn mcas(handle: &Corestore, con: &mut T, act: ActionIter) {
+        if act.len() % 3 != 0 {
+            return conwrite!(con, groups::ACTION_ERR);
+        }
+        let kve = kve!(con, handle);
+        if !registry::state_okay() {
+            return conwrite!(con, groups::SERVER_ERR);
+        }
+        let keyspace = handle.get_cks();
+        let check = {
+            // keep every other multi-key write out until we're done
+            let _lock = keyspace.as_ref().map(|ks| ks.lock_writes());
+            let encoder = kve.get_encoder();
+            let triples = act.as_ref().chunks_exact(3);
+            let mut check = Check::Okay;
+            for (index, triple) in triples.clone().enumerate() {
+                let (key, expected, new) = (&triple[0], &triple[1], &triple[2]);
+                if compiler::unlikely(!encoder.is_ok(key, expected) || !encoder.is_ok(key, new)) {
+                    check = Check::EncodingError;
+                    break;
+                }
+                let matches = kve
+                    .take_snapshot(key)
+                    .map_or(false, |current| current.as_ref() == expected.as_ref());
+                if !matches {
+                    check = Check::Mismatch(index);
+                    break;
+                }
+            }
+            if let Check::Okay = check {
+                for triple in triples {
+                    let (key, expected, new) = (&triple[0], &triple[1], &triple[2]);
+                    // a single key write may have beaten us to it, in which case it wins
+                    let _ =
+                        kve.read_modify_write(Data::from(key.clone()), |current| match current {
+                            Some(current) if current.as_ref() == expected.as_ref() => {
+                                (Some(Data::from(new.clone())), ())
+                            }
+                            _ => (None, ()),
+                        });
+                }
+            }
+            check
+        };
+        match check {
+            Check::Okay => conwrite!(con, groups::OKAY),
+            Check::Mismatch(index) => conwrite!(con, index),
+            Check::EncodingError => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR)),
+        }
+    }
+);
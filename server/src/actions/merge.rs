@@ -0,0 +1,68 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `MERGE` queries
+//! This module provides functions to work with `MERGE` queries, which fold deltas into a
+//! value with the merge operator of the current table (see [`crate::admin::merge`])
+
+use crate::admin::merge;
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::util::compiler;
+
+action!(
+    /// Run a `MERGE <key> <operand> ...` query
+    fn merge(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            let operator = match handle
+                .ctable_name()
+                .and_then(|table| merge::get(handle.get_store(), &table))
+            {
+                Some(operator) => operator,
+                None => return conwrite!(con, groups::NO_MERGE_OPERATOR),
+            };
+            let key = unsafe {
+                // SAFETY: We have already checked that there are atleast 2 arguments
+                act.next().unsafe_unwrap()
+            };
+            let ret = kve.read_modify_write(Data::from(key), |current| {
+                match operator.fold(current.map(|value| &value[..]), act) {
+                    Some(value) => (Some(Data::from(value)), true),
+                    None => (None, false),
+                }
+            });
+            match ret {
+                Ok(true) => conwrite!(con, groups::OKAY)?,
+                Ok(false) => conwrite!(con, groups::BAD_OPERAND)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
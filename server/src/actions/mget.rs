@@ -33,7 +33,6 @@ action!(
     /// Run an `MGET` query
     ///
     fn mget(handle: &crate::corestore::Corestore, con: &mut T, act: ActionIter) {
-        crate::err_if_len_is!(act, con, eq 0);
         let kve = kve!(con, handle);
         let encoding_is_okay = if kve.needs_key_encoding() {
             true
@@ -30,34 +30,51 @@
 //! of the actions supported by Skytable
 //!
 
+pub mod bitmap;
 pub mod dbsize;
 pub mod del;
+pub mod dump;
 pub mod exists;
+pub mod expireat;
 pub mod flushdb;
 pub mod get;
-pub mod jget;
+pub mod getex;
+pub mod getupdate;
+pub mod hll;
+pub mod json;
 pub mod keylen;
 pub mod lskeys;
+pub mod mcas;
+pub mod merge;
 pub mod mget;
 pub mod mpop;
 pub mod mset;
 pub mod mupdate;
+pub mod object;
 pub mod pop;
+pub mod prefix;
 pub mod set;
+pub mod setv;
 pub mod strong;
 pub mod update;
 pub mod uset;
+pub mod wait;
 pub mod heya {
     //! Respond to `HEYA` queries
     use crate::dbnet::connection::prelude::*;
-    use crate::resp::BytesWrapper;
+    use crate::kvengine::encoding;
+    use crate::resp::{BinaryWrapper, BytesWrapper};
     action!(
         /// Returns a `HEY!` `Response`
         fn heya(_handle: &Corestore, con: &mut T, mut act: ActionIter) {
-            err_if_len_is!(act, con, gt 1);
             if act.len() == 1 {
                 let raw_byte = unsafe { act.next().unsafe_unwrap() };
-                con.write_response(BytesWrapper(raw_byte)).await
+                if encoding::is_utf8(&raw_byte) {
+                    con.write_response(BytesWrapper(raw_byte)).await
+                } else {
+                    // don't echo back invalid unicode as a string
+                    con.write_response(BinaryWrapper(raw_byte)).await
+                }
             } else {
                 con.write_response(responses::groups::HEYA).await
             }
@@ -34,7 +34,6 @@ use crate::util::compiler;
 action!(
     /// Run an MPOP action
     fn mpop(handle: &corestore::Corestore, con: &mut T, act: ActionIter) {
-        err_if_len_is!(act, con, eq 0);
         if registry::state_okay() {
             let kve = kve!(con, handle);
             let encoding_is_okay = if kve.needs_key_encoding() {
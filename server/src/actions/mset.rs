@@ -24,14 +24,26 @@
  *
 */
 
+use crate::corestore::memstore::{DdlError, Keyspace};
 use crate::corestore::Data;
 use crate::dbnet::connection::prelude::*;
 use crate::util::compiler;
+use bytes::Bytes;
+use std::sync::Arc;
+
+const TABLES: &[u8] = "TABLES".as_bytes();
 
 action!(
     /// Run an `MSET` query
+    ///
+    /// `MSET TABLES <table> <key> <value> ...` sets keys in one or more tables of the same
+    /// keyspace (see [`mset_tables`])
     fn mset(handle: &crate::corestore::Corestore, con: &mut T, mut act: ActionIter) {
         let howmany = act.len();
+        if howmany != 0 && act.as_ref()[0].eq_ignore_ascii_case(TABLES) {
+            act.next();
+            return mset_tables(handle, con, act).await;
+        }
         if is_lowbit_set!(howmany) || howmany == 0 {
             // An odd number of arguments means that the number of keys
             // is not the same as the number of values, we won't run this
@@ -51,6 +63,9 @@ action!(
         if compiler::likely(encoding_is_okay) {
             let done_howmany: Option<usize>;
             if registry::state_okay() {
+                let keyspace = handle.get_cks();
+                // don't run in the middle of an `MSET TABLES` or a rendezvous on this keyspace
+                let _intent = keyspace.as_ref().map(|ks| ks.write_intent());
                 let mut didmany = 0;
                 while let (Some(key), Some(val)) = (act.next(), act.next()) {
                     if kve.set_unchecked(Data::from(key), Data::from(val)) {
@@ -71,3 +86,76 @@ action!(
         }
     }
 );
+
+/// Run an `MSET TABLES <table> <key> <value> ...` query. The tables can be table names (in
+/// the current keyspace) or `<keyspace>:<table>` entities, but they must all be in the same
+/// keyspace. Every table, key and value is checked before anything is written: if any of
+/// them is bad, then nothing is set. The keys are then set while holding the keyspace's
+/// write lock, so no other multi-key write to the keyspace runs in between. Like `MSET`,
+/// keys that already exist are left alone, and the number of keys set is returned
+async fn mset_tables<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    let howmany = act.len();
+    if howmany == 0 || howmany % 3 != 0 {
+        return conwrite!(con, groups::ACTION_ERR);
+    }
+    let args: Vec<Bytes> = act.collect();
+    let mut keyspace: Option<Arc<Keyspace>> = None;
+    let mut writes = Vec::with_capacity(howmany / 3);
+    for triple in args.chunks_exact(3) {
+        let (entity, key, value) = unsafe {
+            // SAFETY: We're iterating over chunks of three
+            (
+                triple.get_unchecked(0),
+                triple.get_unchecked(1),
+                triple.get_unchecked(2),
+            )
+        };
+        let entity = handle_entity!(con, entity);
+        let (ks, table) = match handle.get_keyspace_and_table(entity) {
+            Ok(kt) => kt,
+            Err(DdlError::DefaultNotFound) => return conwrite!(con, groups::DEFAULT_UNSET),
+            Err(_) => return conwrite!(con, groups::CONTAINER_NOT_FOUND),
+        };
+        table.load().await;
+        match &keyspace {
+            Some(first) if !Arc::ptr_eq(first, &ks) => {
+                return conwrite!(con, groups::CROSS_KEYSPACE)
+            }
+            Some(_) => {}
+            None => keyspace = Some(ks),
+        }
+        match table.get_kvstore() {
+            Ok(kve) if kve.get_encoder().is_ok(key, value) => {}
+            Ok(_) => return compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR)),
+            Err(_) => return conwrite!(con, groups::WRONG_MODEL),
+        }
+        writes.push((table, key.clone(), value.clone()));
+    }
+    if !registry::state_okay() {
+        return conwrite!(con, groups::SERVER_ERR);
+    }
+    let keyspace = unsafe {
+        // SAFETY: There was at least one table, and so at least one keyspace
+        keyspace.unsafe_unwrap()
+    };
+    let didmany = {
+        let _lock = keyspace.lock_writes();
+        writes
+            .into_iter()
+            .map(|(table, key, value)| match table.get_kvstore() {
+                Ok(kve) => kve.set_unchecked(Data::from(key), Data::from(value)),
+                Err(_) => unsafe { impossible!() },
+            })
+            .filter(|set| *set)
+            .count()
+    };
+    conwrite!(con, didmany)
+}
@@ -51,6 +51,9 @@ action!(
         let done_howmany: Option<usize>;
         if compiler::likely(encoding_is_okay) {
             if registry::state_okay() {
+                let keyspace = handle.get_cks();
+                // don't run in the middle of an `MSET TABLES` or a rendezvous on this keyspace
+                let _intent = keyspace.as_ref().map(|ks| ks.write_intent());
                 let mut didmany = 0;
                 while let (Some(key), Some(val)) = (act.next(), act.next()) {
                     if kve.update_unchecked(Data::from(key), Data::from(val)) {
@@ -0,0 +1,97 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `OBJECT` queries
+//! `OBJECT <key>` reports how a key is held and how it has been used, so that we can see
+//! which keys are cold (or about to expire) without dumping their values
+
+use crate::dbnet::connection::prelude::*;
+use crate::kvengine::{now_ms, slab};
+use crate::resp::writer::TypedArrayWriter;
+use crate::resp::TSYMBOL_UNICODE;
+
+action!(
+    /// Run an `OBJECT <key>` query
+    ///
+    /// This returns a flat list of alternating names and values: `type` (`str` or `binstr`),
+    /// `len` (in bytes), `storage` (`slab` or `heap`), `lastaccess` (a UNIX timestamp in
+    /// milliseconds), `idle` (the milliseconds since the last access) and `ttl` (the
+    /// milliseconds left before the key expires). Keys that haven't been used since they were
+    /// loaded have a null `lastaccess` and `idle`, and keys that don't expire have a null `ttl`.
+    /// Looking up a key with `OBJECT` doesn't count as an access
+    fn object(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let kve = kve!(con, handle);
+        let key = unsafe {
+            // SAFETY: We have checked for there to be one arg
+            act.next().unsafe_unwrap()
+        };
+        let value = match kve.peek_cloned(&key) {
+            Ok(Some(value)) => value,
+            Ok(None) => return conwrite!(con, groups::NIL),
+            Err(()) => return conwrite!(con, groups::ENCODING_ERROR),
+        };
+        let now = now_ms();
+        let tsymbol = if kve.get_vt() == TSYMBOL_UNICODE {
+            "str"
+        } else {
+            "binstr"
+        };
+        let storage = if slab::is_small(&value) {
+            "slab"
+        } else {
+            "heap"
+        };
+        let last_access = kve.last_access_of(&key);
+        let fields = [
+            ("type", Some(tsymbol.to_owned())),
+            ("len", Some(value.len().to_string())),
+            ("storage", Some(storage.to_owned())),
+            ("lastaccess", last_access.map(|at| at.to_string())),
+            (
+                "idle",
+                last_access.map(|at| now.saturating_sub(at).to_string()),
+            ),
+            (
+                "ttl",
+                kve.expiry_of(&key)
+                    .map(|deadline| deadline.saturating_sub(now).to_string()),
+            ),
+        ];
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', fields.len() * 2)
+        }
+        .await?;
+        for (name, value) in fields.iter() {
+            writer.write_element(name).await?;
+            match value {
+                Some(value) => writer.write_element(value).await?,
+                None => writer.write_null().await?,
+            }
+        }
+        Ok(())
+    }
+);
@@ -30,7 +30,6 @@ use crate::util::compiler;
 
 action! {
     fn pop(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 1);
         let key = unsafe {
             // SAFETY: We have checked for there to be one arg
             act.next().unsafe_unwrap()
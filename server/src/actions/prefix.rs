@@ -0,0 +1,122 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Prefix actions
+//! Keys are often namespaced with a common prefix (like `user:`). This module provides
+//! actions that work on every key in the current table that starts with a given prefix:
+//! - `PREFIXSTATS <prefix>`: count the keys and estimate the memory that they take up
+//! - `PREFIXDROP <prefix>`: delete the keys in a background job, returning its ID
+//!
+//! The table is walked a stripe at a time, so writers are only ever held off one stripe
+
+use crate::dbnet::connection::prelude::*;
+use crate::registry::jobs::{Job, JobKind};
+use crate::resp::writer::TypedArrayWriter;
+
+action!(
+    /// Run a `PREFIXSTATS <prefix>` query
+    ///
+    /// This returns a flat list of alternating names and values: `keys` (the number of keys
+    /// that start with the prefix) and `bytes` (the bytes held by their keys and values)
+    fn prefixstats(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let _ = kve!(con, handle);
+        let table = unsafe {
+            // SAFETY: We just got the table's kvstore
+            handle.get_ctable().unsafe_unwrap()
+        };
+        let prefix = unsafe {
+            // SAFETY: We have checked for there to be one arg
+            act.next().unsafe_unwrap()
+        };
+        let (count, bytes) = tokio::task::spawn_blocking(move || match table.get_kvstore() {
+            Ok(kve) => kve.__get_inner_ref().prefix_stats(&prefix),
+            Err(_) => (0, 0),
+        })
+        .await
+        .expect("prefix stats thread panicked");
+        let fields = [("keys", count), ("bytes", bytes)];
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', fields.len() * 2)
+        }
+        .await?;
+        for (name, value) in fields.iter() {
+            writer.write_element(name).await?;
+            writer.write_element(value.to_string()).await?;
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Run a `PREFIXDROP <prefix>` query
+    ///
+    /// This starts a cancellable job that deletes every key that starts with the prefix, and
+    /// returns its ID right away (see `SYS JOBS`). Keys that are set while the job runs may
+    /// or may not be deleted
+    fn prefixdrop(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let _ = kve!(con, handle);
+        if !registry::state_okay() {
+            return conwrite!(con, groups::SERVER_ERR);
+        }
+        let table = unsafe {
+            // SAFETY: We just got the table's kvstore
+            handle.get_ctable().unsafe_unwrap()
+        };
+        let keyspace = handle.get_cks();
+        let prefix = unsafe {
+            // SAFETY: We have checked for there to be one arg
+            act.next().unsafe_unwrap()
+        };
+        let job = Job::start_cancellable(JobKind::PrefixDrop);
+        let id = job.id();
+        tokio::task::spawn_blocking(move || {
+            let kve = match table.get_kvstore() {
+                Ok(kve) => kve,
+                Err(_) => return,
+            };
+            let stripes = kve.__get_inner_ref().stripe_count();
+            job.set_total(stripes as u64);
+            let mut dropped = 0;
+            for stripe in 0..stripes {
+                if job.is_cancelled() {
+                    break;
+                }
+                let keys = kve.__get_inner_ref().keys_with_prefix_in(stripe, &prefix);
+                // don't run in the middle of an `MSET TABLES` or a rendezvous on this keyspace
+                let _intent = keyspace.as_ref().map(|ks| ks.write_intent());
+                for key in keys {
+                    if kve.remove_unchecked(&key) {
+                        dropped += 1;
+                    }
+                }
+                job.progress();
+            }
+            log::info!("Dropped {} keys in prefix drop job {}", dropped, job.id());
+        });
+        conwrite!(con, id as usize)
+    }
+);
@@ -31,24 +31,25 @@ use crate::corestore;
 use crate::dbnet::connection::prelude::*;
 use crate::protocol::responses;
 use crate::queryengine::ActionIter;
+use crate::services::origin;
 use crate::util::compiler;
 use corestore::Data;
 
 action!(
     /// Run a `SET` query
+    ///
+    /// If the table has a write-through origin, then the key is also written to the origin
+    /// in the background (see [`origin`])
     fn set(handle: &crate::corestore::Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 2);
         if registry::state_okay() {
+            let (key, value) = unsafe {
+                // UNSAFE(@ohsayan): This is completely safe as we've already checked
+                // that there are exactly 2 arguments
+                (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+            };
             let did_we = {
                 let writer = kve!(con, handle);
-                match unsafe {
-                    // UNSAFE(@ohsayan): This is completely safe as we've already checked
-                    // that there are exactly 2 arguments
-                    writer.set(
-                        Data::from(act.next().unsafe_unwrap()),
-                        Data::from(act.next().unsafe_unwrap()),
-                    )
-                } {
+                match writer.set(Data::from(key.clone()), Data::from(value.clone())) {
                     Ok(true) => Some(true),
                     Ok(false) => Some(false),
                     Err(()) => None,
@@ -56,6 +57,11 @@ action!(
             };
             if let Some(did_we) = did_we {
                 if did_we {
+                    if origin::is_enabled() {
+                        if let Some(table) = handle.ctable_name() {
+                            origin::write_through(&table, key, value);
+                        }
+                    }
                     con.write_response(responses::groups::OKAY).await?;
                 } else {
                     con.write_response(responses::groups::OVERWRITE_ERR).await?;
@@ -0,0 +1,67 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `SETV` queries
+//! This module provides functions to work with `SETV` queries
+
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::util::compiler;
+use core::str;
+
+action!(
+    /// Run a `SETV <key> <version> <value>` query, which sets the value of the key only if
+    /// it hasn't been written since `GET <key> WITHVERSION` returned `version` (a version of
+    /// `0` sets the key only if it doesn't exist). The key's new version is returned
+    fn setv(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let (key, version, value) = unsafe {
+            // SAFETY: We have checked for there to be three args
+            (
+                act.next().unsafe_unwrap(),
+                act.next().unsafe_unwrap(),
+                act.next().unsafe_unwrap(),
+            )
+        };
+        let version = match str::from_utf8(&version)
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            Some(version) => version,
+            None => return conwrite!(con, groups::WRONGTYPE_ERR),
+        };
+        if registry::state_okay() {
+            let kve = kve!(con, handle);
+            match kve.set_versioned(Data::from(key), version, Data::from(value)) {
+                Ok(Some(version)) => conwrite!(con, version)?,
+                Ok(None) => conwrite!(con, groups::VERSION_MISMATCH)?,
+                Err(()) => compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?,
+            }
+        } else {
+            conwrite!(con, groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+);
@@ -34,8 +34,17 @@
 //! There is no point of using _strong actions_ for a single key/value pair, since it will only
 //! slow things down due to the checks performed.
 //! Do note that this isn't the same as the gurantees provided by ACID transactions
+//!
+//! Connections that agreed on the `key-errors` capability (see
+//! [`hello`](crate::protocol::hello)) get the keys that failed the check (as a typed array)
+//! instead of the error code, so that they don't have to check every key again to find them
 
 pub use self::{sdel::sdel, sset::sset, supdate::supdate};
+use crate::dbnet::connection::prelude::*;
+use crate::protocol::hello::Capabilities;
+use crate::resp::writer::TypedArrayWriter;
+use crate::IoResult;
+use bytes::Bytes;
 mod sdel;
 mod sset;
 mod supdate;
@@ -47,16 +56,47 @@ mod tests;
 enum StrongActionResult {
     /// Internal server error
     ServerError,
-    /// A single value was not found
-    Nil,
-    /// An overwrite error for a single value
-    OverwriteError,
+    /// Some keys were not found
+    Nil(Vec<Bytes>),
+    /// Some keys already exist
+    OverwriteError(Vec<Bytes>),
     /// An encoding error occurred
     EncodingError,
     /// Everything worked as expected
     Okay,
 }
 
+/// Write the response for a strong action that failed because of `keys`: the keys themselves
+/// (with the type symbol `tsymbol`) if the connection agreed on the `key-errors` capability,
+/// and `code` otherwise
+async fn write_key_errors<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    tsymbol: u8,
+    keys: Vec<Bytes>,
+    code: &[u8],
+) -> IoResult<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    let key_errors = handle.handshake().map_or(false, |hs| {
+        hs.capabilities.contains(Capabilities::KEY_ERRORS)
+    });
+    if !key_errors {
+        return conwrite!(con, code);
+    }
+    let mut writer = unsafe {
+        // SAFETY: The caller gives us the type of the keys
+        TypedArrayWriter::new(con, tsymbol, keys.len())
+    }
+    .await?;
+    for key in keys {
+        writer.write_element(key).await?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 impl StrongActionResult {
     pub const fn is_ok(&self) -> bool {
@@ -34,9 +34,9 @@ action! {
     /// Run an `SDEL` query
     ///
     /// This either returns `Okay` if all the keys were `del`eted, or it returns a
-    /// `Nil`, which is code `1`
+    /// `Nil`, which is code `1` (or the keys that don't exist, see
+    /// [`write_key_errors`](super::write_key_errors))
     fn sdel(handle: &crate::corestore::Corestore, con: &mut T, act: ActionIter) {
-        err_if_len_is!(act, con, eq 0);
         let kve = kve!(con, handle);
         if registry::state_okay() {
             // guarantee one check: consistency
@@ -46,16 +46,17 @@ action! {
             };
             match outcome {
                 StrongActionResult::Okay => conwrite!(con, groups::OKAY)?,
-                StrongActionResult::Nil => {
-                    // good, it failed because some key didn't exist
-                    conwrite!(con, groups::NIL)?;
+                StrongActionResult::Nil(keys) => {
+                    // good, it failed because some keys didn't exist
+                    let tsymbol = kve.get_kt();
+                    super::write_key_errors(handle, con, tsymbol, keys, groups::NIL).await?
                 },
                 StrongActionResult::ServerError => conwrite!(con, groups::SERVER_ERR)?,
                 StrongActionResult::EncodingError => {
                     // error we love to hate: encoding error, ugh
                     compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?
                 },
-                StrongActionResult::OverwriteError => unsafe {
+                StrongActionResult::OverwriteError(_) => unsafe {
                     // SAFETY check: never the case
                     impossible!()
                 }
@@ -76,21 +77,17 @@ pub(super) fn snapshot_and_del(
 ) -> StrongActionResult {
     let mut snapshots = Vec::with_capacity(act.len());
     let mut err_enc = false;
-    let iter_stat_ok;
-    {
-        iter_stat_ok = act.as_ref().iter().all(|key| {
-            if compiler::likely(key_encoder.is_ok(key)) {
-                if let Some(snap) = kve.take_snapshot(key) {
-                    snapshots.push(snap);
-                    true
-                } else {
-                    false
-                }
-            } else {
-                err_enc = true;
-                false
-            }
-        });
+    let mut missing = Vec::new();
+    for key in act.as_ref() {
+        if compiler::unlikely(!key_encoder.is_ok(key)) {
+            err_enc = true;
+            break;
+        }
+        match kve.take_snapshot(key) {
+            Some(snap) => snapshots.push(snap),
+            // keep going, so that we can tell the client about all of them
+            None => missing.push(key.clone()),
+        }
     }
     cfg_test!({
         // give the caller 10 seconds to do some crap
@@ -101,7 +98,7 @@ pub(super) fn snapshot_and_del(
     }
     if registry::state_okay() {
         // guarantee upholded: consistency
-        if iter_stat_ok {
+        if missing.is_empty() {
             // nice, all keys exist; let's plonk 'em
             let kve = kve;
             let lowtable = kve.__get_inner_ref();
@@ -114,7 +111,7 @@ pub(super) fn snapshot_and_del(
             });
             StrongActionResult::Okay
         } else {
-            StrongActionResult::Nil
+            StrongActionResult::Nil(missing)
         }
     } else {
         StrongActionResult::ServerError
@@ -35,7 +35,8 @@ action! {
     /// Run an `SSET` query
     ///
     /// This either returns `Okay` if all the keys were set, or it returns an
-    /// `Overwrite Error` or code `2`
+    /// `Overwrite Error` or code `2` (or the keys that already exist, see
+    /// [`write_key_errors`](super::write_key_errors))
     fn sset(handle: &crate::corestore::Corestore, con: &mut T, act: ActionIter) {
         let howmany = act.len();
         if is_lowbit_set!(howmany) || howmany == 0 {
@@ -49,13 +50,17 @@ action! {
             };
             match outcome {
                 StrongActionResult::Okay => conwrite!(con, groups::OKAY)?,
-                StrongActionResult::OverwriteError => conwrite!(con, groups::OVERWRITE_ERR)?,
+                StrongActionResult::OverwriteError(keys) => {
+                    let tsymbol = kve.get_kt();
+                    super::write_key_errors(handle, con, tsymbol, keys, groups::OVERWRITE_ERR)
+                        .await?
+                }
                 StrongActionResult::ServerError => conwrite!(con, groups::SERVER_ERR)?,
                 StrongActionResult::EncodingError => {
                     // error we love to hate: encoding error, ugh
                     compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?
                 },
-                StrongActionResult::Nil => unsafe {
+                StrongActionResult::Nil(_) => unsafe {
                     // SAFETY check: never the case
                     impossible!()
                 }
@@ -76,18 +81,17 @@ pub(super) fn snapshot_and_insert(
 ) -> StrongActionResult {
     let mut enc_err = false;
     let lowtable = kve.__get_inner_ref();
-    let key_iter_stat_ok;
-    {
-        key_iter_stat_ok = act.as_ref().chunks_exact(2).all(|kv| unsafe {
-            let key = kv.get_unchecked(0);
-            let value = kv.get_unchecked(1);
-            if compiler::likely(encoder.is_ok(key, value)) {
-                lowtable.get(key).is_none()
-            } else {
-                enc_err = true;
-                false
-            }
-        });
+    let mut existing = Vec::new();
+    for kv in act.as_ref().chunks_exact(2) {
+        let (key, value) = unsafe { (kv.get_unchecked(0), kv.get_unchecked(1)) };
+        if compiler::unlikely(!encoder.is_ok(key, value)) {
+            enc_err = true;
+            break;
+        }
+        if lowtable.get(key).is_some() {
+            // keep going, so that we can tell the client about all of them
+            existing.push(key.clone());
+        }
     }
     cfg_test!({
         // give the caller 10 seconds to do some crap
@@ -97,12 +101,14 @@ pub(super) fn snapshot_and_insert(
         return compiler::cold_err(StrongActionResult::EncodingError);
     }
     if registry::state_okay() {
-        if key_iter_stat_ok {
+        if existing.is_empty() {
             let _kve = kve;
             let lowtable = lowtable;
             // fine, the keys were non-existent when we looked at them
             while let (Some(key), Some(value)) = (act.next(), act.next()) {
-                if let Some(fresh) = lowtable.fresh_entry(Data::from(key)) {
+                let key = Data::from(key);
+                kve.add_to_bloom(&key);
+                if let Some(fresh) = lowtable.fresh_entry(key) {
                     fresh.insert(Data::from(value));
                 }
                 // we don't care if some other thread initialized the value we checked
@@ -110,7 +116,7 @@ pub(super) fn snapshot_and_insert(
             }
             StrongActionResult::Okay
         } else {
-            StrongActionResult::OverwriteError
+            StrongActionResult::OverwriteError(existing)
         }
     } else {
         StrongActionResult::ServerError
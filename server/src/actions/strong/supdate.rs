@@ -35,7 +35,8 @@ action! {
     /// Run an `SUPDATE` query
     ///
     /// This either returns `Okay` if all the keys were updated, or it returns `Nil`
-    /// or code `1`
+    /// or code `1` (or the keys that don't exist, see
+    /// [`write_key_errors`](super::write_key_errors))
     fn supdate(handle: &crate::corestore::Corestore, con: &mut T, act: ActionIter) {
         let howmany = act.len();
         if is_lowbit_set!(howmany) || howmany == 0 {
@@ -49,16 +50,17 @@ action! {
             };
             match outcome {
                 StrongActionResult::Okay => conwrite!(con, groups::OKAY)?,
-                StrongActionResult::Nil => {
-                    // good, it failed because some key didn't exist
-                    conwrite!(con, groups::NIL)?;
+                StrongActionResult::Nil(keys) => {
+                    // good, it failed because some keys didn't exist
+                    let tsymbol = kve.get_kt();
+                    super::write_key_errors(handle, con, tsymbol, keys, groups::NIL).await?
                 },
                 StrongActionResult::ServerError => conwrite!(con, groups::SERVER_ERR)?,
                 StrongActionResult::EncodingError => {
                     // error we love to hate: encoding error, ugh
                     compiler::cold_err(conwrite!(con, groups::ENCODING_ERROR))?
                 },
-                StrongActionResult::OverwriteError => unsafe {
+                StrongActionResult::OverwriteError(_) => unsafe {
                     // SAFETY check: never the case
                     impossible!()
                 }
@@ -80,24 +82,19 @@ pub(super) fn snapshot_and_update(
 ) -> StrongActionResult {
     let mut enc_err = false;
     let mut snapshots = Vec::with_capacity(act.len());
-    let iter_stat_ok;
-    {
-        // snapshot the values at this point in time
-        iter_stat_ok = act.as_ref().chunks_exact(2).all(|kv| unsafe {
-            let key = kv.get_unchecked(0);
-            let value = kv.get_unchecked(1);
-            if compiler::likely(encoder.is_ok(key, value)) {
-                if let Some(snapshot) = kve.take_snapshot(key) {
-                    snapshots.push(snapshot);
-                    true
-                } else {
-                    false
-                }
-            } else {
-                enc_err = true;
-                false
-            }
-        });
+    let mut missing = Vec::new();
+    // snapshot the values at this point in time
+    for kv in act.as_ref().chunks_exact(2) {
+        let (key, value) = unsafe { (kv.get_unchecked(0), kv.get_unchecked(1)) };
+        if compiler::unlikely(!encoder.is_ok(key, value)) {
+            enc_err = true;
+            break;
+        }
+        match kve.take_snapshot(key) {
+            Some(snapshot) => snapshots.push(snapshot),
+            // keep going, so that we can tell the client about all of them
+            None => missing.push(key.clone()),
+        }
     }
     cfg_test!({
         // give the caller 10 seconds to do some crap
@@ -108,7 +105,7 @@ pub(super) fn snapshot_and_update(
     }
     if registry::state_okay() {
         // uphold consistency
-        if iter_stat_ok {
+        if missing.is_empty() {
             let kve = kve;
             // good, so all the values existed when we snapshotted them; let's update 'em
             let mut snap_cc = snapshots.into_iter();
@@ -128,7 +125,7 @@ pub(super) fn snapshot_and_update(
             }
             StrongActionResult::Okay
         } else {
-            StrongActionResult::Nil
+            StrongActionResult::Nil(missing)
         }
     } else {
         StrongActionResult::ServerError
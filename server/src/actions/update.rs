@@ -35,7 +35,6 @@ use crate::util::compiler;
 action!(
     /// Run an `UPDATE` query
     fn update(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 2);
         if registry::state_okay() {
             let did_we = {
                 let writer = kve!(con, handle);
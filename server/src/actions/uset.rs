@@ -54,8 +54,14 @@ action!(
         };
         if compiler::likely(encoding_is_okay) {
             if registry::state_okay() {
-                while let (Some(key), Some(val)) = (act.next(), act.next()) {
-                    kve.upsert_unchecked(Data::from(key), Data::from(val));
+                {
+                    let keyspace = handle.get_cks();
+                    // don't run in the middle of an `MSET TABLES` or a rendezvous on this
+                    // keyspace
+                    let _intent = keyspace.as_ref().map(|ks| ks.write_intent());
+                    while let (Some(key), Some(val)) = (act.next(), act.next()) {
+                        kve.upsert_unchecked(Data::from(key), Data::from(val));
+                    }
                 }
                 conwrite!(con, howmany / 2)
             } else {
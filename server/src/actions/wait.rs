@@ -0,0 +1,67 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `WAIT` queries
+//! `WAIT` lets a client opt into stronger durability for the writes it has just made: it
+//! blocks until everything written so far has been fsynced to disk (just like a `BGSAVE`
+//! would) and returns the number of replicas that have acknowledged the writes. There is no
+//! replication yet, so this is always zero; the arguments are still checked, so that clients
+//! can already send `WAIT <replicas> <timeout>`
+
+use crate::dbnet::connection::prelude::*;
+use crate::registry;
+use crate::storage;
+use core::str;
+
+action!(
+    /// Run a `WAIT [<replicas> [<timeout>]]` query
+    fn wait(handle: &Corestore, con: &mut T, act: ActionIter) {
+        for arg in act {
+            // the number of replicas and the timeout (in milliseconds)
+            let arg = str::from_utf8(&arg)
+                .ok()
+                .and_then(|a| a.parse::<u64>().ok());
+            if arg.is_none() {
+                return conwrite!(con, groups::WRONGTYPE_ERR);
+            }
+        }
+        let store = handle.clone_store();
+        let result = tokio::task::spawn_blocking(move || {
+            let _flush_lock = registry::lock_flush_state();
+            storage::flush::flush_full(&store)
+        })
+        .await
+        .expect("wait thread panicked");
+        match result {
+            // no replicas to wait for
+            Ok(()) => conwrite!(con, 0usize),
+            Err(e) => {
+                log::error!("WAIT failed to flush data: {}", e);
+                conwrite!(con, groups::SERVER_ERR)
+            }
+        }
+    }
+);
@@ -0,0 +1,132 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Self-benchmarks
+//!
+//! A short set of micro-benchmarks that are run by `SYS BENCH`. These are meant to quickly
+//! compare the hardware (or VMs) that different instances are running on and not to
+//! benchmark Skytable itself (use `sky-bench` for that)
+
+use crate::corestore::htable::Coremap;
+use crate::corestore::Data;
+use crate::protocol::Parser;
+use crate::storage::interface::DIR_ROOT;
+use std::fs::{self, OpenOptions};
+use std::io::{Result as IoResult, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// The number of keys inserted into (and then read from) the coremap
+const COREMAP_KEYS: usize = 100_000;
+/// The number of times the query is parsed
+const PARSE_ITERATIONS: usize = 100_000;
+/// The number of writes that are synced to disk
+const FSYNC_ITERATIONS: usize = 16;
+/// The size of every write that is synced to disk
+const FSYNC_BLOCK_SIZE: usize = 4096;
+/// The file used for the fsync benchmark (it is in the data directory since that's where
+/// our fsyncs actually go)
+const FSYNC_FILE: &str = ".sysbench";
+/// The query that is parsed
+const PARSE_QUERY: &[u8] = b"*1\n~3\n3\nSET\n8\nsome-key\n10\nsome-value\n";
+
+/// Run all the benchmarks, returning the results as `(name, value)` pairs. This blocks
+/// for a while, so don't call it on an async worker
+pub fn run() -> Vec<(String, String)> {
+    let fsync = match fsync_latency() {
+        Ok(latency) => format!("{:.1}", latency),
+        Err(e) => {
+            log::error!("Failed to benchmark fsync: {}", e);
+            "error".to_owned()
+        }
+    };
+    vec![
+        ("coremap.ops".to_owned(), format!("{:.0}", coremap_ops())),
+        (
+            "parse.queries".to_owned(),
+            format!("{:.0}", parse_queries()),
+        ),
+        ("fsync.latency_us".to_owned(), fsync),
+    ]
+}
+
+/// Returns the operations per second on a coremap, where half the operations are inserts
+/// and the other half are reads
+fn coremap_ops() -> f64 {
+    let keys: Vec<Data> = (0..COREMAP_KEYS)
+        .map(|idx| Data::from_string(idx.to_string()))
+        .collect();
+    let map: Coremap<Data, Data> = Coremap::new();
+    let start = Instant::now();
+    for key in keys.iter() {
+        map.upsert(key.clone(), key.clone());
+    }
+    let found = keys.iter().filter(|key| map.get(*key).is_some()).count();
+    let elapsed = start.elapsed().as_secs_f64();
+    assert_eq!(found, COREMAP_KEYS);
+    (COREMAP_KEYS * 2) as f64 / elapsed
+}
+
+/// Returns the number of (simple) queries parsed per second
+fn parse_queries() -> f64 {
+    let start = Instant::now();
+    let mut parsed = 0;
+    for _ in 0..PARSE_ITERATIONS {
+        if let Ok((_, forward_by)) = Parser::new(PARSE_QUERY).parse() {
+            parsed += forward_by;
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    assert_eq!(parsed, PARSE_QUERY.len() * PARSE_ITERATIONS);
+    PARSE_ITERATIONS as f64 / elapsed
+}
+
+/// Returns the average latency (in microseconds) of writing a block and syncing it to disk
+fn fsync_latency() -> IoResult<f64> {
+    let path = Path::new(DIR_ROOT).join(FSYNC_FILE);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&path)?;
+    let block = [0u8; FSYNC_BLOCK_SIZE];
+    let mut total = 0;
+    for _ in 0..FSYNC_ITERATIONS {
+        let start = Instant::now();
+        file.write_all(&block)?;
+        file.sync_all()?;
+        total += start.elapsed().as_micros();
+    }
+    drop(file);
+    fs::remove_file(&path)?;
+    Ok(total as f64 / FSYNC_ITERATIONS as f64)
+}
+
+#[test]
+fn test_self_bench_in_memory() {
+    assert!(coremap_ops() > 0.0);
+    assert!(parse_queries() > 0.0);
+}
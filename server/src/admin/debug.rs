@@ -0,0 +1,216 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `DEBUG` queries
+//!
+//! With the `debug-actions` feature, test harnesses can use `DEBUG` to poke at the server:
+//! - `DEBUG SLEEP <ms>` waits for the given number of milliseconds before replying, to
+//! inject latency
+//! - `DEBUG OBJECT <key>` returns how the key's value is held, as a flat list of alternating
+//! names and values: `encoding` (`str` or `binstr`), `len` (in bytes), `storage` (`slab` or
+//! `heap`) and `expiry` (a UNIX timestamp in milliseconds, or `none`)
+//! - `DEBUG SET-EXPIRE-NOW [<key>]` makes the key (if any) expire right away and then removes
+//! every expired key without waiting for the expiry reaper, returning the number of keys
+//! that were removed
+//! - `DEBUG HASHSTATS` returns how the keys of the current table are spread across its
+//! stripes, as a flat list of alternating names and values: `hasher` (`sip` or `ahash`),
+//! `stripes`, `keys`, `minstripe` and `maxstripe` (the number of keys in the emptiest and
+//! fullest stripes)
+//!
+//! Without the feature, `DEBUG` is an unknown action
+
+use crate::dbnet::connection::prelude::*;
+
+#[cfg(feature = "debug-actions")]
+const SLEEP: &[u8] = b"SLEEP";
+#[cfg(feature = "debug-actions")]
+const OBJECT: &[u8] = b"OBJECT";
+#[cfg(feature = "debug-actions")]
+const SET_EXPIRE_NOW: &[u8] = b"SET-EXPIRE-NOW";
+#[cfg(feature = "debug-actions")]
+const HASHSTATS: &[u8] = b"HASHSTATS";
+
+#[cfg(feature = "debug-actions")]
+action! {
+    /// Run a `DEBUG` query
+    fn debug(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let mut what = match act.next() {
+            Some(what) => what.to_vec(),
+            None => aerr!(con, aerr),
+        };
+        what.make_ascii_uppercase();
+        match what.as_ref() {
+            SLEEP => debug_sleep(con, act).await,
+            OBJECT => debug_object(handle, con, act).await,
+            SET_EXPIRE_NOW => debug_expire_now(handle, con, act).await,
+            HASHSTATS => debug_hashstats(handle, con, act).await,
+            _ => aerr!(con, aerr),
+        }
+    }
+}
+
+#[cfg(not(feature = "debug-actions"))]
+action! {
+    /// `DEBUG` needs the `debug-actions` feature
+    fn debug(_handle: &Corestore, con: &mut T, _act: ActionIter) {
+        conwrite!(con, groups::UNKNOWN_ACTION)
+    }
+}
+
+#[cfg(feature = "debug-actions")]
+async fn debug_sleep<T, Strm>(con: &mut T, mut act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 1);
+    let ms = unsafe {
+        // SAFETY: We have checked for there to be one arg
+        act.next().unsafe_unwrap()
+    };
+    match String::from_utf8_lossy(&ms).parse::<u64>() {
+        Ok(ms) => {
+            tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+            conwrite!(con, groups::OKAY)
+        }
+        Err(_) => conwrite!(con, groups::WRONGTYPE_ERR),
+    }
+}
+
+#[cfg(feature = "debug-actions")]
+async fn debug_object<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    use crate::kvengine::slab;
+    use crate::resp::writer::TypedArrayWriter;
+    use crate::resp::TSYMBOL_UNICODE;
+    err_if_len_is!(act, con, not 1);
+    let kve = kve!(con, handle);
+    let key = unsafe {
+        // SAFETY: We have checked for there to be one arg
+        act.next().unsafe_unwrap()
+    };
+    let value = match kve.get_cloned(&key) {
+        Ok(Some(value)) => value,
+        Ok(None) => return conwrite!(con, groups::NIL),
+        Err(()) => return conwrite!(con, groups::ENCODING_ERROR),
+    };
+    let encoding = if kve.get_vt() == TSYMBOL_UNICODE {
+        "str"
+    } else {
+        "binstr"
+    };
+    let storage = if slab::is_small(&value) {
+        "slab"
+    } else {
+        "heap"
+    };
+    let expiry = kve
+        .expiry_of(&key)
+        .map_or_else(|| "none".to_owned(), |deadline| deadline.to_string());
+    let pairs = [
+        ("encoding", encoding.to_owned()),
+        ("len", value.len().to_string()),
+        ("storage", storage.to_owned()),
+        ("expiry", expiry),
+    ];
+    let mut writer = unsafe {
+        // SAFETY: all the elements are strings
+        TypedArrayWriter::new(con, b'+', pairs.len() * 2)
+    }
+    .await?;
+    for (name, value) in pairs.iter() {
+        writer.write_element(name).await?;
+        writer.write_element(value).await?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "debug-actions")]
+async fn debug_expire_now<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    use crate::corestore::Data;
+    err_if_len_is!(act, con, gt 1);
+    if !registry::state_okay() {
+        return conwrite!(con, groups::SERVER_ERR);
+    }
+    let mut removed = 0;
+    if let Some(key) = act.next() {
+        let kve = kve!(con, handle);
+        match kve.set_expiry(Data::from(key), 0) {
+            Ok(found) => removed += found as usize,
+            Err(()) => return conwrite!(con, groups::ENCODING_ERROR),
+        }
+    }
+    removed += handle.get_store().purge_expired_keys();
+    conwrite!(con, removed)
+}
+
+#[cfg(feature = "debug-actions")]
+async fn debug_hashstats<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    use crate::resp::writer::TypedArrayWriter;
+    err_if_len_is!(act, con, not 0);
+    let table = kve!(con, handle).__get_inner_ref();
+    let stripes = table.stripe_lens();
+    let pairs = [
+        ("hasher", table.hash_function().as_str().to_owned()),
+        ("stripes", stripes.len().to_string()),
+        ("keys", stripes.iter().sum::<usize>().to_string()),
+        ("minstripe", stripes.iter().min().unwrap_or(&0).to_string()),
+        ("maxstripe", stripes.iter().max().unwrap_or(&0).to_string()),
+    ];
+    let mut writer = unsafe {
+        // SAFETY: all the elements are strings
+        TypedArrayWriter::new(con, b'+', pairs.len() * 2)
+    }
+    .await?;
+    for (name, value) in pairs.iter() {
+        writer.write_element(name).await?;
+        writer.write_element(value).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # DDL history
+//!
+//! Every DDL query that succeeds (`CREATE`, `DROP`, `UNDROP` and `CLONE`) is recorded in the
+//! `system:history` table (which is flushed like any other table) as a JSON record holding
+//! the query, the user that ran it and the time at which it was run. The records are keyed
+//! by a sequence number and only the last [`MAX_ENTRIES`] of them are kept. The history is
+//! returned by `INSPECT HISTORY`, so that schema changes can be audited and replayed
+
+use crate::corestore::memstore::{Memstore, ObjectID, SYSTEM};
+use crate::corestore::Corestore;
+use crate::corestore::Data;
+use crate::util::Unwrappable;
+use core::str;
+use core::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The number of queries that are kept in the history
+pub const MAX_ENTRIES: u64 = 4096;
+
+/// The sequence number of the next query that is recorded
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A DDL query as it is stored in `system:history` (keyed by its sequence number)
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    /// the time at which the query was run (in seconds since the UNIX epoch)
+    pub time: u64,
+    /// the user that ran the query (`None` if auth is disabled)
+    pub user: Option<String>,
+    /// the keyspace that the query changed
+    pub keyspace: Option<String>,
+    /// the query itself
+    pub query: String,
+}
+
+fn history_tblid() -> ObjectID {
+    unsafe {
+        // SAFETY: the name is shorter than 64 bytes
+        ObjectID::from_slice("history")
+    }
+}
+
+/// Returns the sequence numbers and records in `system:history`, oldest first
+pub fn entries(store: &Memstore) -> Vec<(u64, Entry)> {
+    let system = unsafe {
+        // SAFETY: the system keyspace can't be dropped
+        store.get_keyspace_atomic_ref(&SYSTEM).unsafe_unwrap()
+    };
+    let tbl = match system.get_table_atomic_ref(&history_tblid()) {
+        Some(tbl) => tbl,
+        None => return Vec::new(),
+    };
+    let kve = match tbl.get_kvstore() {
+        Ok(kve) => kve,
+        Err(_) => {
+            log::error!("The `system:history` table isn't a key/value table");
+            return Vec::new();
+        }
+    };
+    let mut entries: Vec<(u64, Entry)> = kve
+        .__get_inner_ref()
+        .iter()
+        .filter_map(|kv| {
+            let seq = str::from_utf8(kv.key()).ok()?.parse().ok()?;
+            let entry = serde_json::from_slice(kv.value()).ok()?;
+            Some((seq, entry))
+        })
+        .collect();
+    entries.sort_unstable_by_key(|(seq, _)| *seq);
+    entries
+}
+
+/// Pick up the sequence numbers after the queries that are already in `system:history`
+pub fn load(store: &Memstore) {
+    if let Some((seq, _)) = entries(store).last() {
+        NEXT_SEQ.store(seq + 1, Ordering::Release);
+    }
+}
+
+/// Returns the keyspace of the table with the given entity (which is the current
+/// keyspace if the entity is a table name alone)
+pub fn entity_keyspace(handle: &Corestore, entity: &[u8]) -> Option<String> {
+    match entity.iter().position(|b| *b == b':') {
+        Some(pos) => Some(String::from_utf8_lossy(&entity[..pos]).into_owned()),
+        None => handle.current_entity().0,
+    }
+}
+
+/// Record a DDL query that changed the given keyspace. The query is the uppercased action
+/// and object (like `CREATE TABLE`) followed by the rest of its arguments
+pub fn record<T: AsRef<[u8]>>(
+    handle: &Corestore,
+    keyspace: Option<String>,
+    action: &str,
+    args: &[T],
+) {
+    let mut query = action.to_owned();
+    for arg in args {
+        query.push(' ');
+        query.push_str(&String::from_utf8_lossy(arg.as_ref()));
+    }
+    let entry = Entry {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |dur| dur.as_secs()),
+        user: handle.user_name().map(String::from),
+        keyspace,
+        query,
+    };
+    let tbl = super::system_table(handle.get_store(), history_tblid());
+    let kve = match tbl.get_kvstore() {
+        Ok(kve) => kve,
+        Err(_) => {
+            log::error!("The `system:history` table isn't a key/value table");
+            return;
+        }
+    };
+    let entry = serde_json::to_string(&entry).expect("Failed to serialize a DDL query");
+    let seq = NEXT_SEQ.fetch_add(1, Ordering::AcqRel);
+    kve.upsert_unchecked(Data::from_string(seq.to_string()), Data::from_string(entry));
+    if seq >= MAX_ENTRIES {
+        kve.remove_unchecked((seq - MAX_ENTRIES).to_string().as_bytes());
+    }
+}
@@ -0,0 +1,262 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Merge operators
+//!
+//! A table can be given a merge operator when it's created
+//! (`CREATE TABLE <name> <model> merge(<operator>)`), so that writers can send deltas with
+//! `MERGE <key> <operand> ...` instead of reading the value, changing it and writing it
+//! back. The operands are folded into the value by the server, under the key's lock, so
+//! concurrent writers of a hot key never race each other or retry. The operators are:
+//! - `append`: the operands are appended to the value
+//! - `add`: the value and the operands are 64-bit signed integers (in ASCII), and the
+//! operands are added to the value
+//! - `union`: the value is a set of members, one per line, and the operands are added to
+//! it as members (if they aren't members already)
+//!
+//! A missing key is treated as an empty value (or zero, for `add`). The operators of the
+//! tables that have one are recorded in the `system:merge` table (keyed by
+//! `<keyspace>:<table>`), so they survive restarts like the tables themselves
+
+use crate::corestore::memstore::{Memstore, ObjectID, SYSTEM};
+use crate::corestore::table::Table;
+use crate::corestore::Data;
+use crate::util::Unwrappable;
+use std::str;
+use std::sync::Arc;
+
+/// The separator between the members of a set that is merged with `union`
+const MEMBER_SEPARATOR: u8 = b'\n';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How the operands of `MERGE` are folded into a value
+pub enum MergeOperator {
+    /// the operands are appended to the value
+    Append,
+    /// the operands are added to the (integer) value
+    Add,
+    /// the operands are added to the set of members
+    Union,
+}
+
+impl MergeOperator {
+    /// Parse a `merge(<operator>)` property
+    pub fn from_property(property: &[u8]) -> Option<Self> {
+        Self::from_name(property.strip_prefix(b"merge(")?.strip_suffix(b")")?)
+    }
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"append" => Some(Self::Append),
+            b"add" => Some(Self::Add),
+            b"union" => Some(Self::Union),
+            _ => None,
+        }
+    }
+    /// Returns the name of the operator
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Append => "append",
+            Self::Add => "add",
+            Self::Union => "union",
+        }
+    }
+    /// Fold the operands into the current value (if there is one), returning the new value
+    /// or `None` if the value or one of the operands can't be merged with this operator
+    pub fn fold<T: AsRef<[u8]>>(
+        &self,
+        current: Option<&[u8]>,
+        operands: impl Iterator<Item = T>,
+    ) -> Option<Vec<u8>> {
+        let current = current.unwrap_or_default();
+        match self {
+            Self::Append => {
+                let mut value = current.to_vec();
+                operands.for_each(|operand| value.extend_from_slice(operand.as_ref()));
+                Some(value)
+            }
+            Self::Add => {
+                let mut sum = if current.is_empty() {
+                    0
+                } else {
+                    parse_integer(current)?
+                };
+                for operand in operands {
+                    sum = sum.checked_add(parse_integer(operand.as_ref())?)?;
+                }
+                Some(sum.to_string().into_bytes())
+            }
+            Self::Union => {
+                let mut value = current.to_vec();
+                for operand in operands {
+                    let member = operand.as_ref();
+                    if member.is_empty() || member.contains(&MEMBER_SEPARATOR) {
+                        return None;
+                    }
+                    let is_member = value
+                        .split(|b| *b == MEMBER_SEPARATOR)
+                        .any(|existing| existing == member);
+                    if !is_member {
+                        if !value.is_empty() {
+                            value.push(MEMBER_SEPARATOR);
+                        }
+                        value.extend_from_slice(member);
+                    }
+                }
+                Some(value)
+            }
+        }
+    }
+}
+
+fn parse_integer(raw: &[u8]) -> Option<i64> {
+    str::from_utf8(raw).ok()?.parse().ok()
+}
+
+fn merge_tblid() -> ObjectID {
+    unsafe {
+        // SAFETY: the name is shorter than 64 bytes
+        ObjectID::from_slice("merge")
+    }
+}
+
+/// Returns the `system:merge` table, if it exists
+fn table(store: &Memstore) -> Option<Arc<Table>> {
+    let system = unsafe {
+        // SAFETY: the system keyspace can't be dropped
+        store.get_keyspace_atomic_ref(&SYSTEM).unsafe_unwrap()
+    };
+    system.get_table_atomic_ref(&merge_tblid())
+}
+
+/// Returns the merge operator of the given table (`<keyspace>:<table>`), if it has one
+pub fn get(store: &Memstore, table_name: &str) -> Option<MergeOperator> {
+    let operator = table(store)?
+        .get_kvstore()
+        .ok()?
+        .get_cloned(table_name.as_bytes())
+        .ok()??;
+    MergeOperator::from_name(&operator.get_blob()[..])
+}
+
+/// Set (or with `None`, remove) the merge operator of the given table
+/// (`<keyspace>:<table>`)
+pub fn set(store: &Memstore, table_name: &str, operator: Option<MergeOperator>) {
+    let tbl = match (operator, table(store)) {
+        (None, None) => return,
+        (None, Some(tbl)) => tbl,
+        (Some(_), _) => super::system_table(store, merge_tblid()),
+    };
+    let kve = match tbl.get_kvstore() {
+        Ok(kve) => kve,
+        Err(_) => {
+            log::error!("The `system:merge` table isn't a key/value table");
+            return;
+        }
+    };
+    match operator {
+        None => {
+            kve.remove_unchecked(table_name.as_bytes());
+        }
+        Some(operator) => {
+            kve.upsert_unchecked(
+                Data::copy_from_slice(table_name.as_bytes()),
+                Data::from(operator.as_str().as_bytes()),
+            );
+        }
+    }
+}
+
+/// Give the tables of the keyspace `dst` the merge operators of the tables with the same
+/// name in the keyspace `src`
+pub fn copy_keyspace(store: &Memstore, src: &[u8], dst: &[u8]) {
+    let kve = match table(store) {
+        Some(tbl) => match tbl.get_kvstore() {
+            Ok(kve) => kve,
+            Err(_) => return,
+        },
+        None => return,
+    };
+    let copies: Vec<(Data, Data)> = kve
+        .__get_inner_ref()
+        .iter()
+        .filter_map(|kv| {
+            let table = kv.key().strip_prefix(src)?.strip_prefix(b":")?;
+            let name = [dst, b":", table].concat();
+            Some((Data::from(name), kv.value().clone()))
+        })
+        .collect();
+    for (name, operator) in copies {
+        kve.upsert_unchecked(name, operator);
+    }
+}
+
+#[test]
+fn test_merge_operator_from_property() {
+    assert_eq!(
+        MergeOperator::from_property(b"merge(append)"),
+        Some(MergeOperator::Append)
+    );
+    assert_eq!(
+        MergeOperator::from_property(b"merge(add)"),
+        Some(MergeOperator::Add)
+    );
+    assert_eq!(
+        MergeOperator::from_property(b"merge(union)"),
+        Some(MergeOperator::Union)
+    );
+    assert_eq!(MergeOperator::from_property(b"merge(max)"), None);
+    assert_eq!(MergeOperator::from_property(b"volatile"), None);
+}
+
+#[test]
+fn test_merge_operator_fold() {
+    let fold = |operator: MergeOperator, current: Option<&[u8]>, operands: &[&str]| {
+        operator
+            .fold(current, operands.iter())
+            .map(|v| String::from_utf8(v).unwrap())
+    };
+    assert_eq!(
+        fold(MergeOperator::Append, Some(b"ab"), &["c", "de"]).unwrap(),
+        "abcde"
+    );
+    assert_eq!(fold(MergeOperator::Append, None, &["x"]).unwrap(), "x");
+    assert_eq!(fold(MergeOperator::Add, None, &["5", "-2"]).unwrap(), "3");
+    assert_eq!(fold(MergeOperator::Add, Some(b"10"), &["1"]).unwrap(), "11");
+    assert_eq!(fold(MergeOperator::Add, Some(b"ten"), &["1"]), None);
+    assert_eq!(fold(MergeOperator::Add, Some(b"10"), &["1.5"]), None);
+    let max = i64::MAX.to_string();
+    assert_eq!(fold(MergeOperator::Add, Some(max.as_bytes()), &["1"]), None);
+    assert_eq!(
+        fold(MergeOperator::Union, None, &["a", "b", "a"]).unwrap(),
+        "a\nb"
+    );
+    assert_eq!(
+        fold(MergeOperator::Union, Some(b"a\nb"), &["b", "c"]).unwrap(),
+        "a\nb\nc"
+    );
+    assert_eq!(fold(MergeOperator::Union, None, &["a\nb"]), None);
+    assert_eq!(fold(MergeOperator::Union, None, &[""]), None);
+}
@@ -29,6 +29,21 @@ use crate::kvengine::encoding;
 use core::str;
 use std::path::{Component, PathBuf};
 
+/// Returns true if the snapshot name could be used to escape the snapshot directory
+pub fn is_illegal_snapshot_name(name: &str) -> bool {
+    PathBuf::from(name)
+        .components()
+        .filter(|dir| {
+            // Sanitize snapshot name, to avoid directory traversal attacks
+            // If the snapshot name has any root directory or parent directory, then
+            // we'll allow it to pass through this adaptor.
+            // As a result, this iterator will give us a count of the 'bad' components
+            dir == &Component::RootDir || dir == &Component::ParentDir
+        })
+        .count()
+        != 0
+}
+
 action!(
     /// Create a snapshot
     ///
@@ -58,19 +73,7 @@ action!(
                 // SAFETY: We have already checked for UTF-8 validity
                 str::from_utf8_unchecked(&name)
             };
-            let path = PathBuf::from(st);
-            let illegal_snapshot = path
-                .components()
-                .filter(|dir| {
-                    // Sanitize snapshot name, to avoid directory traversal attacks
-                    // If the snapshot name has any root directory or parent directory, then
-                    // we'll allow it to pass through this adaptor.
-                    // As a result, this iterator will give us a count of the 'bad' components
-                    dir == &Component::RootDir || dir == &Component::ParentDir
-                })
-                .count()
-                != 0;
-            if illegal_snapshot {
+            if is_illegal_snapshot_name(st) {
                 return conwrite!(con, groups::SNAPSHOT_ILLEGAL_NAME);
             }
 
@@ -78,6 +81,7 @@ action!(
             match engine.mkrsnap(name, handle.clone_store()).await {
                 0 => conwrite!(con, groups::OKAY)?,
                 1 => conwrite!(con, groups::SERVER_ERR)?,
+                2 => conwrite!(con, groups::SNAPSHOT_DISABLED)?,
                 3 => conwrite!(con, groups::SNAPSHOT_BUSY)?,
                 _ => unsafe { impossible!() },
             }
@@ -26,4 +26,40 @@
 
 //! Modules for administration of Skytable
 
+use crate::corestore::memstore::{Memstore, ObjectID, SYSTEM};
+use crate::corestore::table::Table;
+use crate::registry;
+use crate::util::Unwrappable;
+use std::sync::Arc;
+
+pub mod bench;
+pub mod debug;
+pub mod history;
+pub mod merge;
 pub mod mksnap;
+pub mod persistence;
+pub mod sys;
+pub mod users;
+pub mod vtables;
+
+/// Returns the table with the given name in the `system` keyspace, creating it if it
+/// doesn't exist
+fn system_table(store: &Memstore, tblid: ObjectID) -> Arc<Table> {
+    let system = unsafe {
+        // SAFETY: the system keyspace can't be dropped
+        store.get_keyspace_atomic_ref(&SYSTEM).unsafe_unwrap()
+    };
+    if let Some(tbl) = system.get_table_atomic_ref(&tblid) {
+        return tbl;
+    }
+    // see Corestore::create_table for why we lock the flush state
+    let flush_lock = registry::lock_flush_state();
+    if system.create_table(tblid.clone(), Table::new_default_kve()) {
+        registry::get_preload_tripswitch().trip();
+    }
+    drop(flush_lock);
+    unsafe {
+        // SAFETY: we just made sure that the table exists
+        system.get_table_atomic_ref(&tblid).unsafe_unwrap()
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Keyspace persistence
+//!
+//! Every keyspace has a persistence mode, which is set when it's created
+//! (`CREATE KEYSPACE <name> persistence(<mode>)`):
+//! - `snapshot` (the default): the tables are written to disk by BGSAVE and to snapshots
+//! - `none`: the keyspace is a pure cache, so every table in it is volatile (whatever it
+//! was created with) and never pays for being written to disk. The tables come back empty
+//! after a restart
+//!
+//! There is no journal yet, so there is no mode that is more durable than `snapshot`. The
+//! keyspaces that don't use the default mode are recorded in the `system:persistence`
+//! table (which is flushed like any other table)
+
+use crate::corestore::memstore::{Memstore, ObjectID, SYSTEM};
+use crate::corestore::table::Table;
+use crate::corestore::Data;
+use crate::util::Unwrappable;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How the tables in a keyspace are persisted
+pub enum Persistence {
+    /// the tables are volatile
+    None,
+    /// the tables are written to disk by BGSAVE and to snapshots
+    Snapshot,
+}
+
+impl Persistence {
+    /// Parse a `persistence(<mode>)` property
+    pub fn from_property(property: &[u8]) -> Option<Self> {
+        let mode = property.strip_prefix(b"persistence(")?.strip_suffix(b")")?;
+        match mode {
+            b"none" => Some(Self::None),
+            b"snapshot" => Some(Self::Snapshot),
+            _ => None,
+        }
+    }
+    /// Returns the name of the mode
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Snapshot => "snapshot",
+        }
+    }
+}
+
+fn persistence_tblid() -> ObjectID {
+    unsafe {
+        // SAFETY: the name is shorter than 64 bytes
+        ObjectID::from_slice("persistence")
+    }
+}
+
+/// Returns the `system:persistence` table, if it exists
+fn table(store: &Memstore) -> Option<Arc<Table>> {
+    let system = unsafe {
+        // SAFETY: the system keyspace can't be dropped
+        store.get_keyspace_atomic_ref(&SYSTEM).unsafe_unwrap()
+    };
+    system.get_table_atomic_ref(&persistence_tblid())
+}
+
+/// Returns the persistence mode of the given keyspace
+pub fn get(store: &Memstore, ksid: &[u8]) -> Persistence {
+    let mode = table(store).and_then(|tbl| tbl.get_kvstore().ok()?.get_cloned(ksid).ok()?);
+    match mode {
+        Some(mode) if mode.get_blob().as_ref() == Persistence::None.as_str().as_bytes() => {
+            Persistence::None
+        }
+        _ => Persistence::Snapshot,
+    }
+}
+
+/// Set the persistence mode of the given keyspace
+pub fn set(store: &Memstore, ksid: &[u8], mode: Persistence) {
+    let tbl = match (mode, table(store)) {
+        // the default mode isn't recorded
+        (Persistence::Snapshot, None) => return,
+        (Persistence::Snapshot, Some(tbl)) => tbl,
+        (Persistence::None, _) => super::system_table(store, persistence_tblid()),
+    };
+    let kve = match tbl.get_kvstore() {
+        Ok(kve) => kve,
+        Err(_) => {
+            log::error!("The `system:persistence` table isn't a key/value table");
+            return;
+        }
+    };
+    match mode {
+        Persistence::Snapshot => {
+            kve.remove_unchecked(ksid);
+        }
+        Persistence::None => {
+            kve.upsert_unchecked(
+                Data::copy_from_slice(ksid),
+                Data::from(mode.as_str().as_bytes()),
+            );
+        }
+    }
+}
+
+#[test]
+fn test_persistence_from_property() {
+    assert_eq!(
+        Persistence::from_property(b"persistence(none)"),
+        Some(Persistence::None)
+    );
+    assert_eq!(
+        Persistence::from_property(b"persistence(snapshot)"),
+        Some(Persistence::Snapshot)
+    );
+    assert_eq!(Persistence::from_property(b"persistence(journal)"), None);
+    assert_eq!(Persistence::from_property(b"volatile"), None);
+}
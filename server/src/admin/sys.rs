@@ -0,0 +1,1007 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `SYS` queries
+//! This module provides functions to work with `SYS` queries, which are used to
+//! inspect and control the server itself
+
+use crate::admin::bench;
+use crate::admin::mksnap;
+use crate::admin::users;
+use crate::allocator::hugepages;
+use crate::config::cron::Cron;
+use crate::corestore::memstore::Memstore;
+use crate::corestore::memstore::ObjectID;
+use crate::corestore::table::Table;
+use crate::crypto;
+use crate::dbnet::connection::prelude::*;
+use crate::diskstore::instance;
+use crate::kvengine::encoding;
+use crate::queryengine::{self, plugins};
+use crate::registry::admission;
+use crate::registry::clients;
+use crate::registry::jobs::{self, CancelError, Job, JobKind};
+use crate::registry::latency;
+use crate::registry::numa;
+use crate::registry::pressure;
+use crate::registry::schedules;
+use crate::registry::stall;
+use crate::registry::tls;
+use crate::registry::topology;
+use crate::registry::ServerMode;
+use crate::resp::writer::TypedArrayWriter;
+use crate::services::bgsave;
+use crate::services::expiry;
+use crate::services::querylog;
+use crate::services::schema;
+use crate::services::shadow;
+use crate::services::warmup;
+use crate::storage::compat;
+use crate::storage::diff;
+use crate::storage::flush;
+use crate::storage::interface::{
+    DIR_BACKUPS, DIR_IMPORTS, DIR_KSROOT, DIR_RSNAPROOT, DIR_SNAPROOT,
+};
+use crate::storage::unflush;
+use crate::storage::usage;
+use crate::IoResult;
+use core::str;
+use libsky::{hash, manifest};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+
+const MODE: &[u8] = "MODE".as_bytes();
+const INFO: &[u8] = "INFO".as_bytes();
+const BENCH: &[u8] = "BENCH".as_bytes();
+const DIFF: &[u8] = "DIFF".as_bytes();
+const JOBS: &[u8] = "JOBS".as_bytes();
+const CANCEL: &[u8] = "CANCEL".as_bytes();
+const EXPORT: &[u8] = "EXPORT".as_bytes();
+const USER: &[u8] = "USER".as_bytes();
+const TOPCLIENTS: &[u8] = "TOPCLIENTS".as_bytes();
+const CONFIG: &[u8] = "CONFIG".as_bytes();
+const GET: &[u8] = "GET".as_bytes();
+const SET: &[u8] = "SET".as_bytes();
+const DEL: &[u8] = "DEL".as_bytes();
+const LOAD: &[u8] = "LOAD".as_bytes();
+const COMMANDS: &[u8] = "COMMANDS".as_bytes();
+const LATENCY: &[u8] = "LATENCY".as_bytes();
+const RESET: &[u8] = "RESET".as_bytes();
+const VERIFYSNAP: &[u8] = "VERIFYSNAP".as_bytes();
+const WARM: &[u8] = "WARM".as_bytes();
+const DEBUG: &[u8] = "DEBUG".as_bytes();
+const PERSIST: &[u8] = "PERSIST".as_bytes();
+const KEYSPACE: &[u8] = "KEYSPACE".as_bytes();
+const TABLE: &[u8] = "TABLE".as_bytes();
+const HASH: &[u8] = "HASH".as_bytes();
+
+/// The prefix of the `SYS CONFIG` keys for the snapshot schedules
+const SCHEDULE_KEY: &str = "snapshot.schedule.";
+
+/// The number of clients that `SYS TOPCLIENTS` lists, if it isn't told
+const DEFAULT_TOPCLIENTS: usize = 10;
+
+action!(
+    /// Runs a `SYS` query:
+    /// - `SYS MODE` returns the current server mode
+    /// - `SYS MODE <normal|readonly|maintenance>` sets the server mode (only on admin
+    /// listeners)
+    /// - `SYS INFO` returns information about the server
+    /// - `SYS BENCH` runs a short self-benchmark (only on admin listeners)
+    /// - `SYS DIFF <snapshot> [<snapshot>]` compares a snapshot with the live data or with
+    /// another snapshot (only on admin listeners)
+    /// - `SYS JOBS` lists the background jobs that are running or have recently finished
+    /// - `SYS JOBS CANCEL <id>` cancels a running job (only on admin listeners)
+    /// - `SYS EXPORT <name> [<format>]` writes a copy of the data in the given on-disk format
+    /// (only on admin listeners)
+    /// - `SYS USER <ADD|DEL|PASSWD> <name> ...` manages the users that connections can
+    /// authenticate as (only on admin listeners)
+    /// - `SYS TOPCLIENTS [<count>]` lists the clients that have run the most queries (only
+    /// on admin listeners)
+    /// - `SYS CONFIG [GET <key>]` returns the settings that can be changed while the server
+    /// runs, and `SYS CONFIG <SET|DEL> <key> ...` changes them (only on admin listeners)
+    /// - `SYS LOAD <file> <entity>` replaces all the keys in a table with the ones in a table
+    /// file from the imports directory (only on admin listeners)
+    /// - `SYS COMMANDS` lists the actions that can be run, with their arity, the server
+    /// modes they can be run in, the users that can run them and their aliases
+    /// - `SYS LATENCY` reports the recent latency spikes and their likely causes, and
+    /// `SYS LATENCY RESET` forgets them (only on admin listeners)
+    /// - `SYS VERIFYSNAP <snapshot>` checks the files of a snapshot against its manifest
+    /// (only on admin listeners)
+    /// - `SYS WARM [<entity>]` warms up the preloaded tables, or the given table (only on
+    /// admin listeners)
+    /// - `SYS DEBUG PERSIST <KEYSPACE|TABLE> <name>` flushes a keyspace or a table to disk
+    /// right away (only on admin listeners)
+    /// - `SYS HASH <key>` returns the slot of a key (see [`libsky::hash`])
+    fn sys(handle: &Corestore, con: &mut T, act: ActionIter) {
+        run_sys(handle, con, act, false).await
+    }
+);
+
+action!(
+    /// Runs a `SYS` query on an admin listener. This is the same as [`sys`], except that
+    /// `SYS MODE <mode>`, `SYS BENCH`, `SYS DIFF`, `SYS JOBS CANCEL`, `SYS EXPORT`,
+    /// `SYS USER`, `SYS TOPCLIENTS`, `SYS CONFIG SET`, `SYS CONFIG DEL`, `SYS LOAD`,
+    /// `SYS LATENCY RESET`, `SYS VERIFYSNAP`, `SYS WARM` and `SYS DEBUG` are allowed
+    fn sys_admin(handle: &Corestore, con: &mut T, act: ActionIter) {
+        run_sys(handle, con, act, true).await
+    }
+);
+
+async fn run_sys<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+    admin: bool,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    match act.next() {
+        Some(sys_what) => {
+            let mut sys_what = sys_what.to_vec();
+            sys_what.make_ascii_uppercase();
+            match sys_what.as_ref() {
+                MODE => sys_mode(con, act, admin).await?,
+                INFO => sys_info(handle, con, act).await?,
+                BENCH if admin => sys_bench(con, act).await?,
+                BENCH => conwrite!(con, groups::ADMIN_ONLY)?,
+                DIFF if admin => sys_diff(handle, con, act).await?,
+                DIFF => conwrite!(con, groups::ADMIN_ONLY)?,
+                JOBS => sys_jobs(con, act, admin).await?,
+                EXPORT if admin => sys_export(handle, con, act).await?,
+                EXPORT => conwrite!(con, groups::ADMIN_ONLY)?,
+                USER if admin => users::sys_user(handle, con, act).await?,
+                USER => conwrite!(con, groups::ADMIN_ONLY)?,
+                TOPCLIENTS if admin => sys_topclients(con, act).await?,
+                TOPCLIENTS => conwrite!(con, groups::ADMIN_ONLY)?,
+                CONFIG => sys_config(con, act, admin).await?,
+                LOAD if admin => sys_load(handle, con, act).await?,
+                LOAD => conwrite!(con, groups::ADMIN_ONLY)?,
+                COMMANDS => sys_commands(handle, con, act).await?,
+                LATENCY => sys_latency(con, act, admin).await?,
+                VERIFYSNAP if admin => sys_verifysnap(con, act).await?,
+                VERIFYSNAP => conwrite!(con, groups::ADMIN_ONLY)?,
+                WARM if admin => sys_warm(handle, con, act).await?,
+                WARM => conwrite!(con, groups::ADMIN_ONLY)?,
+                DEBUG if admin => sys_debug(handle, con, act).await?,
+                DEBUG => conwrite!(con, groups::ADMIN_ONLY)?,
+                HASH => sys_hash(con, act).await?,
+                _ => conwrite!(con, groups::UNKNOWN_SYS_QUERY)?,
+            }
+        }
+        None => aerr!(con, aerr),
+    }
+    Ok(())
+}
+
+/// Get the server mode, or set it if this is an admin listener
+async fn sys_mode<T, Strm>(con: &mut T, mut act: ActionIter, admin: bool) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, gt 1);
+    match act.next() {
+        Some(_) if !admin => conwrite!(con, groups::ADMIN_ONLY),
+        Some(mode) => match ServerMode::from_bytes(&mode) {
+            Some(mode) => {
+                registry::set_mode(mode);
+                log::info!("Server mode set to `{}`", mode.as_str());
+                conwrite!(con, groups::OKAY)
+            }
+            None => conwrite!(con, groups::UNKNOWN_MODE),
+        },
+        None => conwrite!(con, registry::get_mode().as_str()),
+    }
+}
+
+/// Collect the fields returned by `SYS INFO` as `(name, value)` pairs
+pub(super) fn info() -> Vec<(String, String)> {
+    let mut info = vec![
+        ("version".to_owned(), libsky::VERSION.to_owned()),
+        ("mode".to_owned(), registry::get_mode().as_str().to_owned()),
+        ("crypto.backend".to_owned(), crypto::name().to_owned()),
+        (
+            "instance".to_owned(),
+            instance::instance_id().unwrap_or_default(),
+        ),
+        (
+            "connections.idle_timeout".to_owned(),
+            registry::get_idle_timeout()
+                .map_or(0, |timeout| timeout.as_secs())
+                .to_string(),
+        ),
+        (
+            "connections.reaped".to_owned(),
+            registry::get_reaped_connections().to_string(),
+        ),
+        (
+            "queries.deadline_exceeded".to_owned(),
+            registry::get_deadlines_exceeded().to_string(),
+        ),
+        (
+            "tables.unloaded".to_owned(),
+            registry::get_tables_unloaded().to_string(),
+        ),
+        (
+            "tables.reloaded".to_owned(),
+            registry::get_tables_reloaded().to_string(),
+        ),
+        (
+            "querylog.dropped".to_owned(),
+            querylog::dropped().to_string(),
+        ),
+    ];
+    info.extend(bgsave::info());
+    info.extend(expiry::info());
+    info.extend(schema::info());
+    info.extend(shadow::info());
+    info.extend(pressure::info());
+    info.extend(admission::info());
+    info.extend(topology::info());
+    info.extend(tls::info());
+    alloc_info(&mut info);
+    info
+}
+
+#[cfg(feature = "alloc-accounting")]
+/// Add the number of live bytes allocated by each subsystem
+fn alloc_info(info: &mut Vec<(String, String)>) {
+    use crate::allocator::{self, Subsystem};
+    let total: usize = Subsystem::ALL
+        .iter()
+        .map(|s| allocator::allocated(*s))
+        .sum();
+    info.push(("alloc.total".to_owned(), total.to_string()));
+    for subsystem in Subsystem::ALL.iter() {
+        info.push((
+            format!("alloc.{}", subsystem.as_str()),
+            allocator::allocated(*subsystem).to_string(),
+        ));
+    }
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+/// Allocation accounting is disabled, so there's nothing to add
+fn alloc_info(_info: &mut Vec<(String, String)>) {}
+
+/// Collect the free space on the disk and the keyspaces whose writes are stalled (see
+/// [`stall`]) as `(name, value)` pairs
+fn stall_info(store: &Memstore) -> Vec<(String, String)> {
+    let mut info = vec![
+        (
+            "disk.free".to_owned(),
+            stall::get_free().map_or_else(|| "unknown".to_owned(), |free| free.to_string()),
+        ),
+        ("disk.minfree".to_owned(), stall::get_min_free().to_string()),
+    ];
+    for keyspace in store.keyspaces.iter() {
+        if let Some(reason) = stall::as_str(keyspace.value().stall()) {
+            let name = unsafe { keyspace.key().as_str() };
+            info.push((format!("stalled.{}", name), reason.to_owned()));
+        }
+    }
+    info
+}
+
+/// Collect the disk usage of every keyspace as `(name, value)` pairs
+fn disk_info(keyspaces: Vec<String>) -> Vec<(String, String)> {
+    let mut info = Vec::with_capacity(keyspaces.len() * 2);
+    for ksid in keyspaces {
+        let ksusage = usage::keyspace_usage(&ksid);
+        info.push((format!("disk.{}.data", ksid), ksusage.data.to_string()));
+        info.push((
+            format!("disk.{}.snapshots", ksid),
+            ksusage.snapshots.to_string(),
+        ));
+    }
+    info
+}
+
+/// Returns information about the server as a flat list of alternating field names
+/// and values
+async fn sys_info<T, Strm>(handle: &Corestore, con: &mut T, act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 0);
+    let mut info = info();
+    info.extend(stall_info(handle.get_store()));
+    let keyspaces = handle.get_store().keyspace_names();
+    // walking the data directory (and the memory maps, for the NUMA and huge page stats) can
+    // take a while
+    let usage = tokio::task::spawn_blocking(move || {
+        let mut usage = disk_info(keyspaces);
+        usage.extend(numa::info());
+        usage.extend(hugepages::info());
+        usage
+    })
+    .await
+    .expect("disk usage thread panicked");
+    info.extend(usage);
+    write_pairs(con, info).await
+}
+
+/// Runs the self-benchmarks and returns the results as a flat list of alternating names
+/// and values
+async fn sys_bench<T, Strm>(con: &mut T, act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 0);
+    log::info!("Running self-benchmark");
+    let results = tokio::task::spawn_blocking(bench::run)
+        .await
+        .expect("self-benchmark thread panicked");
+    write_pairs(con, results).await
+}
+
+/// Find the snapshot (or remote snapshot) with the given name, returning its directory
+pub(crate) fn find_snapshot(name: &str) -> Option<String> {
+    [DIR_SNAPROOT, DIR_RSNAPROOT]
+        .iter()
+        .map(|root| concat_str!(root, "/", name))
+        .find(|dir| Path::new(dir).is_dir())
+}
+
+/// Compares a snapshot with the live data (or the first snapshot with the second one)
+/// and returns the number of keys that were added, removed and changed in every table
+/// that changed as a flat list of alternating names and values (like
+/// `default:default.added`)
+async fn sys_diff<T, Strm>(handle: &Corestore, con: &mut T, act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(con, act.len() == 0 || act.len() > 2);
+    let mut snapshots = Vec::with_capacity(act.len());
+    for name in act {
+        if !encoding::is_utf8(&name) {
+            return conwrite!(con, groups::ENCODING_ERROR);
+        }
+        let name = unsafe {
+            // SAFETY: We have already checked for UTF-8 validity
+            str::from_utf8_unchecked(&name)
+        };
+        if mksnap::is_illegal_snapshot_name(name) {
+            return conwrite!(con, groups::SNAPSHOT_ILLEGAL_NAME);
+        }
+        match find_snapshot(name) {
+            Some(dir) => snapshots.push(dir),
+            None => return conwrite!(con, groups::SNAPSHOT_NOT_FOUND),
+        }
+    }
+    let live = handle.clone_store();
+    let result = tokio::task::spawn_blocking(move || -> IoResult<Vec<(String, String)>> {
+        let old = unflush::read_tree(&snapshots[0])?;
+        let new = match snapshots.get(1) {
+            Some(dir) => Arc::new(unflush::read_tree(dir)?),
+            None => live,
+        };
+        Ok(diff_pairs(&old, &new))
+    })
+    .await
+    .expect("snapshot diff thread panicked");
+    match result {
+        Ok(pairs) => write_pairs(con, pairs).await,
+        Err(e) => {
+            log::error!("Failed to read snapshot for diff: {}", e);
+            conwrite!(con, groups::SERVER_ERR)
+        }
+    }
+}
+
+/// Warms up the tables listed in `preload` (or the given table) again, returning the number
+/// of bytes that were read (see [`warmup`])
+async fn sys_warm<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, gt 1);
+    let bytes = match act.next() {
+        Some(entity) => {
+            let entity = handle_entity!(con, entity);
+            let table = get_tbl!(entity, handle, con);
+            tokio::task::spawn_blocking(move || table.warm()).await
+        }
+        None => {
+            let store = handle.clone_store();
+            tokio::task::spawn_blocking(move || warmup::warm_up(&store)).await
+        }
+    }
+    .expect("table warm up thread panicked");
+    conwrite!(con, bytes)
+}
+
+/// Returns the slot of a key, which is the same slot that clients and proxies get from
+/// [`libsky::hash::slot`]
+async fn sys_hash<T, Strm>(con: &mut T, mut act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 1);
+    let key = unsafe {
+        // SAFETY: We have checked that there is one argument
+        act.next().unsafe_unwrap()
+    };
+    conwrite!(con, hash::slot(&key) as usize)
+}
+
+/// Runs `SYS DEBUG PERSIST KEYSPACE <keyspace>` or `SYS DEBUG PERSIST TABLE <entity>`, which
+/// flush the keyspace (with all of its tables) or the table to disk right away and only reply
+/// once the files have been fsynced, like before planned maintenance. Volatile tables aren't
+/// written
+async fn sys_debug<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 3);
+    let (what, kind, name) = unsafe {
+        // SAFETY: We have checked that there are three arguments
+        (
+            act.next().unsafe_unwrap(),
+            act.next().unsafe_unwrap(),
+            act.next().unsafe_unwrap(),
+        )
+    };
+    if !what.eq_ignore_ascii_case(PERSIST) {
+        return conwrite!(con, groups::UNKNOWN_SYS_QUERY);
+    }
+    let (ksid, tblid) = if kind.eq_ignore_ascii_case(KEYSPACE) {
+        if name.len() > 64 {
+            return conwrite!(con, groups::BAD_CONTAINER_NAME);
+        }
+        (unsafe { ObjectID::from_slice(&name) }, None)
+    } else if kind.eq_ignore_ascii_case(TABLE) {
+        let entity = handle_entity!(con, name);
+        // make sure that the table exists
+        get_tbl!(entity, handle, con);
+        match unsafe { entity.into_owned() } {
+            (Some(ksid), Some(tblid)) => (ksid, Some(tblid)),
+            (Some(tblid), None) => match handle.current_entity().0 {
+                Some(ksid) => (unsafe { ObjectID::from_slice(ksid) }, Some(tblid)),
+                None => return conwrite!(con, groups::DEFAULT_UNSET),
+            },
+            _ => unsafe { impossible!() },
+        }
+    } else {
+        return conwrite!(con, groups::ACTION_ERR);
+    };
+    if !instance::is_owner() {
+        log::error!("Refusing to persist: the data directory has been taken over");
+        return conwrite!(con, groups::SERVER_ERR);
+    }
+    let store = handle.clone_store();
+    let result = tokio::task::spawn_blocking(move || {
+        let _flush_lock = registry::lock_flush_state();
+        flush::flush_entity(&store, &ksid, tblid.as_ref())
+    })
+    .await
+    .expect("persist thread panicked");
+    match result {
+        Ok(true) => conwrite!(con, groups::OKAY),
+        Ok(false) => conwrite!(con, groups::CONTAINER_NOT_FOUND),
+        Err(e) => {
+            log::error!("Failed to persist: {}", e);
+            conwrite!(con, groups::SERVER_ERR)
+        }
+    }
+}
+
+/// Checks a snapshot (or remote snapshot) against its manifest, returning the number of
+/// files that match it as `verified`, followed by every file that doesn't and what is wrong
+/// with it (`missing`, `size-mismatch`, `checksum-mismatch` or `unlisted`) as a flat list
+/// of alternating names and values
+async fn sys_verifysnap<T, Strm>(con: &mut T, mut act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 1);
+    let name = unsafe {
+        // SAFETY: We have checked that there is one argument
+        act.next().unsafe_unwrap()
+    };
+    if !encoding::is_utf8(&name) {
+        return conwrite!(con, groups::ENCODING_ERROR);
+    }
+    let name = unsafe {
+        // SAFETY: We have already checked for UTF-8 validity
+        str::from_utf8_unchecked(&name)
+    };
+    if mksnap::is_illegal_snapshot_name(name) {
+        return conwrite!(con, groups::SNAPSHOT_ILLEGAL_NAME);
+    }
+    let dir = match find_snapshot(name) {
+        Some(dir) => dir,
+        None => return conwrite!(con, groups::SNAPSHOT_NOT_FOUND),
+    };
+    let result = tokio::task::spawn_blocking(move || manifest::verify(Path::new(&dir)))
+        .await
+        .expect("snapshot verification thread panicked");
+    match result {
+        Ok(report) => {
+            if !report.is_intact() {
+                log::warn!(
+                    "Snapshot {} has {} file(s) that don't match its manifest",
+                    name,
+                    report.problems.len()
+                );
+            }
+            let mut pairs = vec![("verified".to_owned(), report.verified.to_string())];
+            pairs.extend(
+                report
+                    .problems
+                    .into_iter()
+                    .map(|(path, problem)| (path, problem.as_str().to_owned())),
+            );
+            write_pairs(con, pairs).await
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => conwrite!(con, groups::NO_MANIFEST),
+        Err(e) => {
+            log::error!("Failed to verify snapshot {}: {}", name, e);
+            conwrite!(con, groups::SERVER_ERR)
+        }
+    }
+}
+
+/// Collect the fields of every background job as `(name, value)` pairs (like `3.kind`,
+/// `3.status` and `3.progress`, along with `3.io.rate`, `3.io.priority` and `3.io.waited_ms`
+/// if the job's I/O is limited)
+pub(super) fn jobs_info() -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    for (id, job) in jobs::list() {
+        pairs.push((format!("{}.kind", id), job.kind.as_str().to_owned()));
+        pairs.push((format!("{}.status", id), job.status.as_str().to_owned()));
+        pairs.push((
+            format!("{}.progress", id),
+            format!("{}/{}", job.done, job.total),
+        ));
+        if let Some(limiter) = &job.limiter {
+            for (name, value) in limiter.info() {
+                pairs.push((format!("{}.io.{}", id, name), value));
+            }
+        }
+    }
+    pairs
+}
+
+/// Lists the background jobs as a flat list of alternating names and values (see
+/// [`jobs_info`]), or cancels a job with `CANCEL <id>`
+async fn sys_jobs<T, Strm>(con: &mut T, mut act: ActionIter, admin: bool) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    if act.len() == 0 {
+        return write_pairs(con, jobs_info()).await;
+    }
+    err_if_len_is!(act, con, not 2);
+    let mut subaction = unsafe {
+        // SAFETY: We have checked that there are two arguments
+        act.next().unsafe_unwrap()
+    }
+    .to_vec();
+    subaction.make_ascii_uppercase();
+    if subaction != CANCEL {
+        return conwrite!(con, groups::UNKNOWN_SYS_QUERY);
+    }
+    if !admin {
+        return conwrite!(con, groups::ADMIN_ONLY);
+    }
+    let id = unsafe {
+        // SAFETY: We have checked that there are two arguments
+        act.next().unsafe_unwrap()
+    };
+    let id = match str::from_utf8(&id).ok().and_then(|id| id.parse().ok()) {
+        Some(id) => id,
+        None => return conwrite!(con, groups::UNKNOWN_JOB),
+    };
+    match jobs::cancel(id) {
+        Ok(()) => {
+            log::info!("Cancelling job {}", id);
+            conwrite!(con, groups::OKAY)
+        }
+        Err(CancelError::NotFound) => conwrite!(con, groups::UNKNOWN_JOB),
+        Err(CancelError::NotCancellable) => conwrite!(con, groups::JOB_NOT_CANCELLABLE),
+    }
+}
+
+/// Reports the latency spikes of every event (see [`latency::report`]), or forgets them
+async fn sys_latency<T, Strm>(con: &mut T, mut act: ActionIter, admin: bool) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    if act.len() == 0 {
+        return write_pairs(con, latency::report()).await;
+    }
+    err_if_len_is!(act, con, not 1);
+    let mut subaction = unsafe {
+        // SAFETY: We have checked that there is one argument
+        act.next().unsafe_unwrap()
+    }
+    .to_vec();
+    subaction.make_ascii_uppercase();
+    if subaction != RESET {
+        return conwrite!(con, groups::UNKNOWN_SYS_QUERY);
+    }
+    if !admin {
+        return conwrite!(con, groups::ADMIN_ONLY);
+    }
+    latency::reset();
+    log::info!("Cleared the latency spikes");
+    conwrite!(con, groups::OKAY)
+}
+
+/// Collect the settings that can be changed while the server runs as `(name, value)` pairs
+pub(super) fn config_info() -> Vec<(String, String)> {
+    schedules::list()
+        .into_iter()
+        .map(|(name, cron)| (format!("{}{}", SCHEDULE_KEY, name), cron.to_string()))
+        .collect()
+}
+
+/// Lists (or gets, sets or removes) the settings that can be changed while the server runs.
+/// For now, these are the snapshot schedules (`snapshot.schedule.<name>`, set to a cron
+/// expression)
+async fn sys_config<T, Strm>(con: &mut T, mut act: ActionIter, admin: bool) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    if act.len() == 0 {
+        return write_pairs(con, config_info()).await;
+    }
+    err_if_len_is!(act, con, lt 2);
+    err_if_len_is!(act, con, gt 3);
+    let (mut subaction, key) = unsafe {
+        // SAFETY: We have checked that there are at least two arguments
+        (
+            act.next().unsafe_unwrap().to_vec(),
+            act.next().unsafe_unwrap(),
+        )
+    };
+    subaction.make_ascii_uppercase();
+    let value = act.next();
+    let name = match str::from_utf8(&key)
+        .ok()
+        .and_then(|key| key.strip_prefix(SCHEDULE_KEY))
+    {
+        Some(name) if !name.is_empty() => name,
+        _ => return conwrite!(con, groups::UNKNOWN_PROPERTY),
+    };
+    match (subaction.as_ref(), value) {
+        (GET, None) => match schedules::get(name) {
+            Some(cron) => conwrite!(con, cron.as_str()),
+            None => conwrite!(con, groups::NIL),
+        },
+        (SET, Some(_)) | (DEL, None) if !admin => conwrite!(con, groups::ADMIN_ONLY),
+        (SET, Some(_)) | (DEL, None) if !schedules::is_enabled() => {
+            conwrite!(con, groups::SNAPSHOT_DISABLED)
+        }
+        (SET, Some(expr)) => match str::from_utf8(&expr).map_err(|_| "").and_then(Cron::parse) {
+            Ok(cron) => {
+                log::info!("Setting the snapshot schedule `{}` to `{}`", name, cron);
+                schedules::set(name, cron);
+                conwrite!(con, groups::OKAY)
+            }
+            Err(_) => conwrite!(con, groups::BAD_EXPRESSION),
+        },
+        (DEL, None) => {
+            if schedules::remove(name) {
+                log::info!("Removed the snapshot schedule `{}`", name);
+                conwrite!(con, groups::OKAY)
+            } else {
+                conwrite!(con, groups::NIL)
+            }
+        }
+        (GET, _) | (SET, _) | (DEL, _) => conwrite!(con, groups::ACTION_ERR),
+        _ => conwrite!(con, groups::UNKNOWN_SYS_QUERY),
+    }
+}
+
+/// Lists the busiest clients (the users that connections authenticated as, or the addresses
+/// of the connections that didn't), with the queries they ran and the bytes they sent and
+/// were sent
+async fn sys_topclients<T, Strm>(con: &mut T, mut act: ActionIter) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, gt 1);
+    let count = match act.next() {
+        Some(count) => match str::from_utf8(&count).ok().and_then(|c| c.parse().ok()) {
+            Some(count) => count,
+            None => return conwrite!(con, groups::WRONGTYPE_ERR),
+        },
+        None => DEFAULT_TOPCLIENTS,
+    };
+    write_pairs(con, clients_info(count)).await
+}
+
+/// Collect the totals of the `count` clients that have run the most queries as
+/// `(name, value)` pairs (like `user:app.ops`)
+pub(super) fn clients_info(count: usize) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    for (client, stats) in clients::top(count) {
+        pairs.push((format!("{}.ops", client), stats.ops.to_string()));
+        pairs.push((format!("{}.bytes_in", client), stats.bytes_in.to_string()));
+        pairs.push((format!("{}.bytes_out", client), stats.bytes_out.to_string()));
+    }
+    pairs
+}
+
+/// Lists the actions in the [registry](crate::queryengine::commands) and the
+/// [custom actions](crate::queryengine::plugins) that aren't denied, returning five elements for each action: its name, its arity (`any` if the dispatcher
+/// doesn't check it), its guard (`read`, `write` or `none`), the users that can run it
+/// (`any`, `user` or `unrestricted`) and its aliases (comma separated)
+async fn sys_commands<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 0);
+    let custom = plugins::commands();
+    let commands: Vec<_> = queryengine::COMMANDS
+        .iter()
+        .chain(custom.iter())
+        .filter(|cmd| !handle.is_action_denied(cmd.name.as_bytes()))
+        .collect();
+    let mut writer = unsafe {
+        // SAFETY: all the elements are strings
+        TypedArrayWriter::new(con, b'+', commands.len() * 5)
+    }
+    .await?;
+    for cmd in commands {
+        writer.write_element(cmd.name).await?;
+        let arity = cmd
+            .arity
+            .map_or_else(|| "any".to_owned(), |a| a.to_string());
+        writer.write_element(arity).await?;
+        writer.write_element(cmd.guard.unwrap_or("none")).await?;
+        writer.write_element(cmd.permission()).await?;
+        let aliases: Vec<_> = cmd.aliases().collect();
+        writer.write_element(aliases.join(",")).await?;
+    }
+    Ok(())
+}
+
+/// Writes a copy of the live data to `data/backups/<name>` (laid out like `data/ks`) in the
+/// given on-disk format, which defaults to the current one. An older server can use the
+/// copy as its `data/ks` directory, which is the way back after an upgrade
+async fn sys_export<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(con, act.len() == 0 || act.len() > 2);
+    let name = unsafe {
+        // SAFETY: We have checked that there is at least one argument
+        act.next().unsafe_unwrap()
+    };
+    if !encoding::is_utf8(&name) {
+        return conwrite!(con, groups::ENCODING_ERROR);
+    }
+    let name = unsafe {
+        // SAFETY: We have already checked for UTF-8 validity
+        str::from_utf8_unchecked(&name)
+    }
+    .to_owned();
+    if mksnap::is_illegal_snapshot_name(&name) {
+        return conwrite!(con, groups::SNAPSHOT_ILLEGAL_NAME);
+    }
+    let format = match act.next() {
+        Some(format) => match str::from_utf8(&format).ok().and_then(|f| f.parse().ok()) {
+            Some(format) if compat::is_supported(format) => format,
+            _ => return conwrite!(con, groups::UNKNOWN_FORMAT),
+        },
+        None => compat::FORMAT_CURRENT,
+    };
+    if Path::new(&concat_str!(DIR_BACKUPS, "/", name)).exists() {
+        return conwrite!(con, groups::ALREADY_EXISTS);
+    }
+    let store = handle.clone_store();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut job = Job::start(JobKind::Export);
+        flush::snap_flush_full(DIR_BACKUPS, &name, &store, format, &job).map_err(|e| {
+            job.fail();
+            e
+        })
+    })
+    .await
+    .expect("export thread panicked");
+    match result {
+        Ok(()) => {
+            log::info!("Exported data in format version {}", format);
+            conwrite!(con, groups::OKAY)
+        }
+        Err(e) => {
+            log::error!("Failed to export data: {}", e);
+            conwrite!(con, groups::SERVER_ERR)
+        }
+    }
+}
+
+/// Replace all the keys in a table with the ones in a table file (in any of the supported
+/// formats) from the imports directory, returning the number of keys that were loaded. The
+/// keys are swapped in at once and the table is then written to disk
+async fn sys_load<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(act, con, not 2);
+    let (name, entity) = unsafe {
+        // SAFETY: We have checked that there are two arguments
+        (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+    };
+    let name = match str::from_utf8(&name) {
+        Ok(name) if !mksnap::is_illegal_snapshot_name(name) => name,
+        Ok(_) => return conwrite!(con, groups::SNAPSHOT_ILLEGAL_NAME),
+        Err(_) => return conwrite!(con, groups::ENCODING_ERROR),
+    };
+    let path = concat_str!(DIR_IMPORTS, "/", name);
+    if !Path::new(&path).is_file() {
+        return conwrite!(con, groups::IMPORT_NOT_FOUND);
+    }
+    let entity = handle_entity!(con, entity);
+    let table = get_tbl!(entity, handle, con);
+    if table.get_kvstore().is_err() {
+        return conwrite!(con, groups::WRONG_MODEL);
+    }
+    let (ksid, tblid) = match unsafe { entity.into_owned() } {
+        (Some(ksid), Some(tblid)) => (ksid, tblid),
+        (Some(tblid), None) => match handle.current_entity().0 {
+            Some(ksid) => (unsafe { ObjectID::from_slice(ksid) }, tblid),
+            None => return conwrite!(con, groups::DEFAULT_UNSET),
+        },
+        _ => unsafe { impossible!() },
+    };
+    let table_name = format!(
+        "{}:{}",
+        String::from_utf8_lossy(&ksid),
+        String::from_utf8_lossy(&tblid)
+    );
+    if schema::has_schema(&table_name) {
+        // the imported values aren't checked against the schema
+        return conwrite!(con, schema::refuse(handle, b"SYS LOAD"));
+    }
+    let result = tokio::task::spawn_blocking(move || {
+        let mut job = Job::start(JobKind::Import);
+        let ret = load_table(&path, &ksid, &tblid, &table);
+        if !matches!(ret, Ok(Some(_))) {
+            job.fail();
+        }
+        ret
+    })
+    .await
+    .expect("import thread panicked");
+    match result {
+        Ok(Some(count)) => {
+            log::info!("Loaded {} keys from import `{}`", count, name);
+            conwrite!(con, count)
+        }
+        Ok(None) => conwrite!(con, groups::BAD_IMPORT),
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::InvalidData | ErrorKind::UnexpectedEof | ErrorKind::Unsupported
+            ) =>
+        {
+            conwrite!(con, groups::BAD_IMPORT)
+        }
+        Err(e) => {
+            log::error!("Failed to load import `{}`: {}", name, e);
+            conwrite!(con, groups::SERVER_ERR)
+        }
+    }
+}
+
+/// Read the table file at `path` and swap its keys into `table` (which is `ksid:tblid`),
+/// then write the table to disk. Returns `None` if the keys don't match the table's encoding
+fn load_table(
+    path: &str,
+    ksid: &ObjectID,
+    tblid: &ObjectID,
+    table: &Table,
+) -> IoResult<Option<usize>> {
+    let kve = match table.get_kvstore() {
+        Ok(kve) => kve,
+        Err(_) => unsafe { impossible!() },
+    };
+    let (data, expiry) = unflush::read_table_data(path)?;
+    if !kve.verify_encoding_of(&data) {
+        return Ok(None);
+    }
+    let count = data.len();
+    kve.replace_data(data, expiry);
+    let _flush_lock = registry::lock_flush_state();
+    if !table.is_volatile() {
+        // the keyspace's directory won't exist if it was created after the last flush
+        fs::create_dir_all(unsafe { concat_path!(DIR_KSROOT, ksid.as_str()) })?;
+    }
+    flush::oneshot::flush_table(tblid, ksid, table)?;
+    Ok(Some(count))
+}
+
+/// Turn the differences between two stores into `(name, value)` pairs
+fn diff_pairs(old: &Memstore, new: &Memstore) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    for (entity, tbldiff) in diff::diff(old, new) {
+        pairs.push((format!("{}.added", entity), tbldiff.added.to_string()));
+        pairs.push((format!("{}.removed", entity), tbldiff.removed.to_string()));
+        pairs.push((format!("{}.changed", entity), tbldiff.changed.to_string()));
+    }
+    pairs
+}
+
+/// Write `(name, value)` pairs as a flat string array
+async fn write_pairs<T, Strm>(con: &mut T, pairs: Vec<(String, String)>) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    let mut writer = unsafe {
+        // SAFETY: all the elements are strings
+        TypedArrayWriter::new(con, b'+', pairs.len() * 2)
+    }
+    .await?;
+    for (name, value) in pairs {
+        writer.write_element(name).await?;
+        writer.write_element(value).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `SYS USER` queries
+//!
+//! Users added with `SYS USER ADD` are kept in the `system:users` table (which is flushed
+//! like any other table) as JSON records holding the hash of their password and the
+//! keyspaces that they can access. The records are loaded into the
+//! [user registry](crate::registry::auth) on startup, and every change is applied to both
+
+use crate::corestore::memstore::{Memstore, ObjectID, SYSTEM};
+use crate::corestore::table::Table;
+use crate::corestore::Data;
+use crate::dbnet::connection::prelude::*;
+use crate::kvengine::encoding;
+use crate::kvengine::KVEngine;
+use crate::registry::auth::{self, User};
+use core::str;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const ADD: &[u8] = "ADD".as_bytes();
+const DEL: &[u8] = "DEL".as_bytes();
+const PASSWD: &[u8] = "PASSWD".as_bytes();
+
+/// A user as it is stored in `system:users` (keyed by its name)
+#[derive(Serialize, Deserialize)]
+struct UserRecord {
+    hash: String,
+    keyspaces: Option<Vec<String>>,
+}
+
+fn users_tblid() -> ObjectID {
+    unsafe {
+        // SAFETY: the name is shorter than 64 bytes
+        ObjectID::from_slice("users")
+    }
+}
+
+/// Returns the `system:users` table, creating it if it doesn't exist
+fn users_table(store: &Memstore) -> Arc<Table> {
+    super::system_table(store, users_tblid())
+}
+
+/// Write a user's record to `system:users`
+fn store_user(kve: &KVEngine, name: &str, record: &UserRecord) {
+    let record = serde_json::to_string(record).expect("Failed to serialize a user");
+    kve.upsert_unchecked(
+        Data::copy_from_slice(name.as_bytes()),
+        Data::from_string(record),
+    );
+}
+
+/// Load the users in `system:users` into the user registry
+pub fn load(store: &Memstore) {
+    let system = unsafe {
+        // SAFETY: the system keyspace can't be dropped
+        store.get_keyspace_atomic_ref(&SYSTEM).unsafe_unwrap()
+    };
+    let tbl = match system.get_table_atomic_ref(&users_tblid()) {
+        Some(tbl) => tbl,
+        None => return,
+    };
+    let kve = match tbl.get_kvstore() {
+        Ok(kve) => kve,
+        Err(_) => {
+            log::error!("The `system:users` table isn't a key/value table");
+            return;
+        }
+    };
+    for kv in kve.__get_inner_ref().iter() {
+        let name = String::from_utf8_lossy(kv.key()).into_owned();
+        let record: UserRecord = match serde_json::from_slice(kv.value()) {
+            Ok(record) => record,
+            Err(_) => {
+                log::error!("Skipping user `{}` with a corrupted record", name);
+                continue;
+            }
+        };
+        let user = User::with_password_hash(name.clone(), record.hash, record.keyspaces);
+        if !auth::add_user(user) {
+            log::warn!(
+                "Skipping user `{}` from `system:users` since the config file has a user with the same name",
+                name
+            );
+        }
+    }
+}
+
+/// Runs a `SYS USER` query:
+/// - `SYS USER ADD <name> <password> [<keyspace> ...]` adds a user that can only access
+/// the given keyspaces (or every keyspace, if none are given)
+/// - `SYS USER DEL <name>` removes a user
+/// - `SYS USER PASSWD <name> <password>` changes a user's password
+///
+/// Only the users that were added with `SYS USER ADD` can be changed; the ones in the
+/// config file return a [`PROTECTED_OBJECT`](groups::PROTECTED_OBJECT) error
+pub async fn sys_user<T, Strm>(
+    handle: &Corestore,
+    con: &mut T,
+    mut act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    err_if_len_is!(con, act.len() < 2);
+    let mut subaction = unsafe {
+        // SAFETY: We have checked that there are at least two arguments
+        act.next().unsafe_unwrap()
+    }
+    .to_vec();
+    subaction.make_ascii_uppercase();
+    let name = unsafe {
+        // SAFETY: We have checked that there are at least two arguments
+        act.next().unsafe_unwrap()
+    };
+    if !encoding::is_utf8(&name) {
+        return conwrite!(con, groups::ENCODING_ERROR);
+    }
+    let name = unsafe {
+        // SAFETY: We have already checked for UTF-8 validity
+        str::from_utf8_unchecked(&name)
+    }
+    .to_owned();
+    let mut args = Vec::with_capacity(act.len());
+    for arg in act {
+        if !encoding::is_utf8(&arg) {
+            return conwrite!(con, groups::ENCODING_ERROR);
+        }
+        args.push(arg);
+    }
+    let existing = auth::get_user(&name);
+    match subaction.as_ref() {
+        ADD => {
+            if args.is_empty() {
+                aerr!(con, aerr);
+            }
+            if existing.is_some() {
+                return conwrite!(con, groups::ALREADY_EXISTS);
+            }
+            let password = args.remove(0);
+            let keyspaces: Vec<String> = args
+                .into_iter()
+                .map(|ks| String::from_utf8_lossy(&ks).into_owned())
+                .collect();
+            let keyspaces = if keyspaces.is_empty() {
+                None
+            } else {
+                Some(keyspaces)
+            };
+            let record = UserRecord {
+                hash: hash_password(password.to_vec()).await,
+                keyspaces,
+            };
+            let tbl = users_table(handle.get_store());
+            let kve = kve!(con, tbl);
+            let user = User::with_password_hash(
+                name.clone(),
+                record.hash.clone(),
+                record.keyspaces.clone(),
+            );
+            if !auth::add_user(user) {
+                // someone beat us to it
+                return conwrite!(con, groups::ALREADY_EXISTS);
+            }
+            store_user(kve, &name, &record);
+            log::info!("Added user `{}`", name);
+        }
+        DEL => {
+            if !args.is_empty() {
+                aerr!(con, aerr);
+            }
+            match existing {
+                Some(user) if user.has_password() => {}
+                Some(_) => return conwrite!(con, groups::PROTECTED_OBJECT),
+                None => return conwrite!(con, groups::UNKNOWN_USER),
+            }
+            let tbl = users_table(handle.get_store());
+            let kve = kve!(con, tbl);
+            auth::remove_user(&name);
+            kve.remove_unchecked(name.as_bytes());
+            log::info!("Removed user `{}`", name);
+        }
+        PASSWD => {
+            if args.len() != 1 {
+                aerr!(con, aerr);
+            }
+            let user = match existing {
+                Some(user) if user.has_password() => user,
+                Some(_) => return conwrite!(con, groups::PROTECTED_OBJECT),
+                None => return conwrite!(con, groups::UNKNOWN_USER),
+            };
+            let record = UserRecord {
+                hash: hash_password(args.remove(0).to_vec()).await,
+                keyspaces: user.keyspaces().map(|ks| ks.to_vec()),
+            };
+            let tbl = users_table(handle.get_store());
+            let kve = kve!(con, tbl);
+            auth::replace_user(User::with_password_hash(
+                name.clone(),
+                record.hash.clone(),
+                record.keyspaces.clone(),
+            ));
+            store_user(kve, &name, &record);
+            log::info!("Changed the password of user `{}`", name);
+        }
+        _ => return conwrite!(con, groups::UNKNOWN_SYS_QUERY),
+    }
+    conwrite!(con, groups::OKAY)
+}
+
+/// Hash a password on a blocking thread, since password hashes are slow on purpose
+async fn hash_password(password: Vec<u8>) -> String {
+    tokio::task::spawn_blocking(move || auth::hash_password(&password))
+        .await
+        .expect("password hashing thread panicked")
+}
@@ -0,0 +1,76 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Virtual tables
+//!
+//! The `system` keyspace has read-only virtual tables that expose the internals of the
+//! server as `keymap(str,str)` tables, so that they can be queried with `GET`, `MGET`,
+//! `LSKEYS` and friends (as `system:<table>`) instead of with a new `SYS` query for
+//! everything that one might want to look at:
+//! - `system:info`: the fields of `SYS INFO` (except the disk usage)
+//! - `system:jobs`: the background jobs, like `SYS JOBS`
+//! - `system:clients`: the totals of the clients that have run the most queries, like
+//! `SYS TOPCLIENTS`
+//! - `system:config`: the settings that can be changed while the server runs, like
+//! `SYS CONFIG`
+//!
+//! Virtual tables aren't in the `system` keyspace itself (so they're never flushed), and
+//! they're [refreshed](Table::refresh) every time that a query looks them up
+
+use super::sys;
+use crate::corestore::lazy::Lazy;
+use crate::corestore::table::Table;
+use std::sync::Arc;
+
+/// The number of clients in `system:clients`
+const MAX_CLIENTS: usize = 100;
+
+type VirtualTables = [(&'static str, Arc<Table>); 4];
+
+static VTABLES: Lazy<VirtualTables, fn() -> VirtualTables> = Lazy::new(|| {
+    [
+        ("info", Arc::new(Table::new_virtual(sys::info))),
+        ("jobs", Arc::new(Table::new_virtual(sys::jobs_info))),
+        ("clients", Arc::new(Table::new_virtual(clients))),
+        ("config", Arc::new(Table::new_virtual(sys::config_info))),
+    ]
+});
+
+fn clients() -> Vec<(String, String)> {
+    sys::clients_info(MAX_CLIENTS)
+}
+
+/// Returns true if there's a virtual table with the given name
+pub fn exists(tblid: &[u8]) -> bool {
+    VTABLES.iter().any(|(name, _)| name.as_bytes() == tblid)
+}
+
+/// Returns the freshly refreshed virtual table with the given name, if there is one
+pub fn get(tblid: &[u8]) -> Option<Arc<Table>> {
+    let (_, tbl) = VTABLES.iter().find(|(name, _)| name.as_bytes() == tblid)?;
+    tbl.refresh();
+    Some(tbl.clone())
+}
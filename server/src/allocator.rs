@@ -0,0 +1,323 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Allocation accounting
+//!
+//! When the `alloc-accounting` feature is enabled, the global allocator is wrapped with an
+//! [`Accounted`] allocator that keeps track of the number of live bytes allocated by each
+//! [`Subsystem`]. Code marks the subsystem it is running on behalf of with [`scope`] (for
+//! synchronous sections) or [`tagged`] (for futures) and the breakdown can be seen with
+//! `SYS INFO`.
+//!
+//! Allocations are attributed to the subsystem that was active when they were made, and
+//! are subtracted from the same subsystem when they are freed, irrespective of who frees
+//! them. To do this, every allocation is prefixed with a small header (of the size of its
+//! alignment) that holds the subsystem.
+//!
+//! The binary [registers](register) the allocator that it installed as the global allocator,
+//! and that's where the counts reported by [`allocated`] come from. Processes that embed
+//! the library pick their own global allocator, so everything is reported as zero unless
+//! they register an [`Accounted`] allocator as well.
+//!
+//! When the feature is disabled, [`scope`] and [`tagged`] are no-ops and no accounting is
+//! done.
+//!
+//! Irrespective of the feature, the global allocator backs large allocations with huge
+//! pages when it's configured to (see [`hugepages`]).
+
+use core::future::Future;
+
+pub mod hugepages;
+
+#[cfg_attr(not(feature = "alloc-accounting"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+/// The subsystems that allocations are attributed to
+pub enum Subsystem {
+    /// Anything that isn't attributed to any other subsystem
+    Other = 0,
+    /// Tables and their data, including queries that are being executed
+    Coremap = 1,
+    /// Connections and their buffers
+    Connections = 2,
+    /// Snapshots that are being created
+    Snapshots = 3,
+}
+
+#[cfg_attr(not(feature = "alloc-accounting"), allow(dead_code))]
+impl Subsystem {
+    /// All the subsystems
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::Other,
+        Subsystem::Coremap,
+        Subsystem::Connections,
+        Subsystem::Snapshots,
+    ];
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Subsystem::Other => "other",
+            Subsystem::Coremap => "coremap",
+            Subsystem::Connections => "connections",
+            Subsystem::Snapshots => "snapshots",
+        }
+    }
+}
+
+#[cfg(feature = "alloc-accounting")]
+pub use self::accounting::{allocated, register, Accounted};
+
+#[cfg(feature = "alloc-accounting")]
+/// Run `f`, attributing all the allocations that it makes to `subsystem`
+pub fn scope<R>(subsystem: Subsystem, f: impl FnOnce() -> R) -> R {
+    let _guard = accounting::ScopeGuard::enter(subsystem);
+    f()
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+/// Run `f`, attributing all the allocations that it makes to `subsystem`
+pub fn scope<R>(_subsystem: Subsystem, f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+#[cfg(feature = "alloc-accounting")]
+/// Attribute all the allocations made while polling `fut` to `subsystem`
+pub fn tagged<F: Future>(subsystem: Subsystem, fut: F) -> impl Future<Output = F::Output> {
+    accounting::Tagged { subsystem, fut }
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+/// Attribute all the allocations made while polling `fut` to `subsystem`
+pub fn tagged<F: Future>(_subsystem: Subsystem, fut: F) -> impl Future<Output = F::Output> {
+    fut
+}
+
+#[cfg(feature = "alloc-accounting")]
+mod accounting {
+    use super::Subsystem;
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::cell::Cell;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+    use core::task::{Context, Poll};
+
+    thread_local! {
+        /// The subsystem that the current thread is running on behalf of
+        static CURRENT: Cell<u8> = Cell::new(Subsystem::Other as u8);
+    }
+
+    /// The counters of the allocator that was [registered](register), if any
+    static REGISTERED: AtomicPtr<[AtomicUsize; 4]> = AtomicPtr::new(core::ptr::null_mut());
+
+    /// Report the counts of `global` (which should be the global allocator) from
+    /// [`allocated`]
+    pub fn register<A>(global: &'static Accounted<A>) {
+        REGISTERED.store(&global.allocated as *const _ as *mut _, Ordering::Release);
+    }
+
+    /// Returns the number of live bytes allocated by `subsystem`
+    pub fn allocated(subsystem: Subsystem) -> usize {
+        let counters = REGISTERED.load(Ordering::Acquire);
+        if counters.is_null() {
+            0
+        } else {
+            unsafe {
+                // SAFETY: the counters belong to an allocator that lives forever
+                (*counters)[subsystem as usize].load(Ordering::Relaxed)
+            }
+        }
+    }
+
+    /// Set the current subsystem, returning the previous one
+    fn swap_current(subsystem: u8) -> u8 {
+        // this will fail if the thread local was already destroyed, which is fine
+        CURRENT
+            .try_with(|current| current.replace(subsystem))
+            .unwrap_or(Subsystem::Other as u8)
+    }
+
+    fn current() -> u8 {
+        CURRENT
+            .try_with(|current| current.get())
+            .unwrap_or(Subsystem::Other as u8)
+    }
+
+    /// Restores the previous subsystem when dropped
+    pub struct ScopeGuard {
+        previous: u8,
+    }
+
+    impl ScopeGuard {
+        pub fn enter(subsystem: Subsystem) -> Self {
+            Self {
+                previous: swap_current(subsystem as u8),
+            }
+        }
+    }
+
+    impl Drop for ScopeGuard {
+        fn drop(&mut self) {
+            swap_current(self.previous);
+        }
+    }
+
+    /// A future that attributes the allocations made while polling it to a subsystem
+    pub struct Tagged<F> {
+        pub(super) subsystem: Subsystem,
+        pub(super) fut: F,
+    }
+
+    impl<F: Future> Future for Tagged<F> {
+        type Output = F::Output;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let _guard = ScopeGuard::enter(self.subsystem);
+            unsafe {
+                // SAFETY: we never move fut out of the pinned Tagged
+                self.map_unchecked_mut(|tagged| &mut tagged.fut).poll(cx)
+            }
+        }
+    }
+
+    /// An allocator that wraps another allocator and keeps track of the live bytes
+    /// allocated by each subsystem
+    pub struct Accounted<A> {
+        inner: A,
+        /// the number of live bytes for each subsystem
+        allocated: [AtomicUsize; 4],
+    }
+
+    impl<A> Accounted<A> {
+        pub const fn new(inner: A) -> Self {
+            Self {
+                inner,
+                allocated: [
+                    AtomicUsize::new(0),
+                    AtomicUsize::new(0),
+                    AtomicUsize::new(0),
+                    AtomicUsize::new(0),
+                ],
+            }
+        }
+        /// Returns the number of live bytes allocated by `subsystem`
+        pub fn allocated(&self, subsystem: Subsystem) -> usize {
+            self.allocated[subsystem as usize].load(Ordering::Relaxed)
+        }
+        fn account(&self, subsystem: u8, alloc: usize, dealloc: usize) {
+            let counter = &self.allocated[subsystem as usize % self.allocated.len()];
+            if alloc > dealloc {
+                counter.fetch_add(alloc - dealloc, Ordering::Relaxed);
+            } else {
+                counter.fetch_sub(dealloc - alloc, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the layout of the allocation that includes the header. The header is as
+    /// large as the alignment so that the returned pointer is still aligned
+    fn with_header(layout: &Layout, size: usize) -> Option<Layout> {
+        let size = size.checked_add(layout.align())?;
+        Layout::from_size_align(size, layout.align()).ok()
+    }
+
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for Accounted<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let full = match with_header(&layout, layout.size()) {
+                Some(full) => full,
+                None => return core::ptr::null_mut(),
+            };
+            let base = self.inner.alloc(full);
+            if base.is_null() {
+                return base;
+            }
+            let subsystem = current();
+            self.account(subsystem, layout.size(), 0);
+            let ptr = base.add(layout.align());
+            // the subsystem goes right before the returned pointer
+            ptr.sub(1).write(subsystem);
+            ptr
+        }
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let full = match with_header(&layout, layout.size()) {
+                Some(full) => full,
+                None => return core::ptr::null_mut(),
+            };
+            let base = self.inner.alloc_zeroed(full);
+            if base.is_null() {
+                return base;
+            }
+            let subsystem = current();
+            self.account(subsystem, layout.size(), 0);
+            let ptr = base.add(layout.align());
+            ptr.sub(1).write(subsystem);
+            ptr
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.account(ptr.sub(1).read(), 0, layout.size());
+            // we were able to allocate this layout, so this can't fail
+            let full = with_header(&layout, layout.size()).unwrap();
+            self.inner.dealloc(ptr.sub(layout.align()), full)
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let full = with_header(&layout, layout.size()).unwrap();
+            let new_full_size = match new_size.checked_add(layout.align()) {
+                Some(size) => size,
+                None => return core::ptr::null_mut(),
+            };
+            let base = self
+                .inner
+                .realloc(ptr.sub(layout.align()), full, new_full_size);
+            if base.is_null() {
+                return base;
+            }
+            let ptr = base.add(layout.align());
+            // the header is moved along with the allocation; it still belongs to the
+            // subsystem that made it
+            self.account(ptr.sub(1).read(), new_size, layout.size());
+            ptr
+        }
+    }
+
+    #[test]
+    fn test_accounted_alloc() {
+        use std::alloc::System;
+        let accounted = Accounted::new(System);
+        let _guard = ScopeGuard::enter(Subsystem::Snapshots);
+        unsafe {
+            for align in [1, 8, 64].iter() {
+                let layout = Layout::from_size_align(100, *align).unwrap();
+                let ptr = accounted.alloc_zeroed(layout);
+                assert_eq!(ptr as usize % align, 0);
+                assert!((0..100).all(|i| ptr.add(i).read() == 0));
+                assert_eq!(accounted.allocated(Subsystem::Snapshots), 100);
+                let ptr = accounted.realloc(ptr, layout, 1000);
+                assert_eq!(ptr as usize % align, 0);
+                assert_eq!(accounted.allocated(Subsystem::Snapshots), 1000);
+                accounted.dealloc(ptr, Layout::from_size_align(1000, *align).unwrap());
+                assert_eq!(accounted.allocated(Subsystem::Snapshots), 0);
+            }
+        }
+    }
+}
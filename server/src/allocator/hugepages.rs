@@ -0,0 +1,401 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Huge pages
+//!
+//! Allocations of [`THRESHOLD`] bytes or more (like the tables of large coremaps) can be
+//! backed by huge pages, which cuts down the number of TLB entries (and so, the TLB misses)
+//! that very large datasets need:
+//! - `transparent`: large allocations are mapped separately and marked for transparent huge
+//! pages with `madvise`, which the kernel backs with huge pages when it can
+//! - `explicit`: large allocations are mapped from the reserved huge page pool
+//! (`vm.nr_hugepages`), falling back to `transparent` when the pool is exhausted
+//!
+//! Every large allocation is prefixed with a header (of the size of its alignment, but no
+//! smaller than two words) that records how it was made, so that it is freed correctly
+//! irrespective of the mode. Huge pages are only supported on Linux; the mode is ignored
+//! everywhere else.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use serde::Deserialize;
+
+/// Allocations of this size or more can be backed by huge pages
+pub const THRESHOLD: usize = 2 * 1024 * 1024;
+
+/// How large allocations are backed
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum HugePageMode {
+    /// Large allocations are made by the inner allocator
+    Off = 0,
+    /// Large allocations are marked for transparent huge pages
+    Transparent = 1,
+    /// Large allocations are mapped from the huge page pool
+    Explicit = 2,
+}
+
+impl HugePageMode {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            HugePageMode::Off => "off",
+            HugePageMode::Transparent => "transparent",
+            HugePageMode::Explicit => "explicit",
+        }
+    }
+}
+
+/// The way large allocations are made
+static MODE: AtomicU8 = AtomicU8::new(HugePageMode::Off as u8);
+/// The number of bytes that are currently mapped from the huge page pool
+static EXPLICIT: AtomicUsize = AtomicUsize::new(0);
+/// The number of explicit huge page allocations that fell back to transparent huge pages
+static FALLBACKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the way large allocations are made
+pub fn set_mode(mode: HugePageMode) {
+    MODE.store(mode as u8, Ordering::Release)
+}
+
+/// Get the way large allocations are made
+pub fn mode() -> HugePageMode {
+    match MODE.load(Ordering::Acquire) {
+        1 => HugePageMode::Transparent,
+        2 => HugePageMode::Explicit,
+        _ => HugePageMode::Off,
+    }
+}
+
+/// Returns true if huge pages are supported on this platform
+pub const fn is_supported() -> bool {
+    cfg!(target_os = "linux")
+}
+
+/// Returns the huge page statistics as `(name, value)` pairs
+pub fn info() -> Vec<(String, String)> {
+    #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+    let mut info = vec![
+        ("hugepages.mode".to_owned(), mode().as_str().to_owned()),
+        (
+            "hugepages.explicit".to_owned(),
+            EXPLICIT.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "hugepages.fallbacks".to_owned(),
+            FALLBACKS.load(Ordering::Relaxed).to_string(),
+        ),
+    ];
+    #[cfg(target_os = "linux")]
+    {
+        // the bytes that the kernel actually backs with transparent huge pages
+        if let Some(bytes) = std::fs::read_to_string("/proc/self/smaps_rollup")
+            .ok()
+            .and_then(|smaps| parse_anon_huge_pages(&smaps))
+        {
+            info.push(("hugepages.transparent".to_owned(), bytes.to_string()));
+        }
+    }
+    info
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+/// Parse the number of bytes backed by transparent huge pages out of `smaps_rollup`
+fn parse_anon_huge_pages(smaps: &str) -> Option<usize> {
+    smaps
+        .lines()
+        .find_map(|line| line.strip_prefix("AnonHugePages:"))
+        .and_then(|rest| rest.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<usize>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// An allocator that wraps another allocator and backs large allocations with huge pages
+/// (depending on the [mode](set_mode))
+pub struct HugePages<A> {
+    inner: A,
+}
+
+impl<A> HugePages<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe impl<A: GlobalAlloc> GlobalAlloc for HugePages<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.inner.alloc(layout)
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.inner.alloc_zeroed(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{HugePageMode, HugePages, EXPLICIT, FALLBACKS, THRESHOLD};
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::mem;
+    use core::ptr;
+    use core::sync::atomic::Ordering;
+
+    /// The size of a huge page (on x86_64 and aarch64 with 4KiB pages)
+    const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+    /// The largest alignment that a mapping satisfies without any help
+    const PAGE_SIZE: usize = 4096;
+    /// The allocation was made by the inner allocator
+    const ORIGIN_INNER: usize = 0;
+    /// The allocation was mapped with normal pages (and maybe marked for transparent huge pages)
+    const ORIGIN_MAPPED: usize = 1;
+    /// The allocation was mapped from the huge page pool
+    const ORIGIN_HUGETLB: usize = 2;
+
+    /// The size of the header of a large allocation with the given alignment. The header
+    /// holds the origin and the length of the mapping, right before the returned pointer
+    const fn header_size(align: usize) -> usize {
+        let min = 2 * mem::size_of::<usize>();
+        if align > min {
+            align
+        } else {
+            min
+        }
+    }
+
+    /// Returns the layout that the inner allocator is asked for (for a large allocation)
+    fn inner_layout(layout: &Layout, size: usize) -> Option<Layout> {
+        let size = size.checked_add(header_size(layout.align()))?;
+        Layout::from_size_align(size, layout.align().max(mem::align_of::<usize>())).ok()
+    }
+
+    const fn round_up(size: usize, to: usize) -> usize {
+        (size + to - 1) & !(to - 1)
+    }
+
+    /// Map `len` bytes, returning null on failure
+    unsafe fn map(len: usize, flags: libc::c_int) -> *mut u8 {
+        let base = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+            -1,
+            0,
+        );
+        if base == libc::MAP_FAILED {
+            ptr::null_mut()
+        } else {
+            base as *mut u8
+        }
+    }
+
+    /// Map a large allocation of `size` bytes (including the header), returning the base,
+    /// the origin and the length of the mapping
+    unsafe fn map_large(size: usize, mode: HugePageMode) -> Option<(*mut u8, usize, usize)> {
+        if mode == HugePageMode::Explicit {
+            let len = round_up(size, HUGE_PAGE_SIZE);
+            let base = map(len, libc::MAP_HUGETLB);
+            if !base.is_null() {
+                EXPLICIT.fetch_add(len, Ordering::Relaxed);
+                return Some((base, ORIGIN_HUGETLB, len));
+            }
+            // the pool is exhausted (or was never reserved)
+            FALLBACKS.fetch_add(1, Ordering::Relaxed);
+        }
+        let len = round_up(size, PAGE_SIZE);
+        let base = map(len, 0);
+        if base.is_null() {
+            return None;
+        }
+        // this is only a hint, so it doesn't matter if THP is disabled system-wide
+        libc::madvise(base as *mut libc::c_void, len, libc::MADV_HUGEPAGE);
+        Some((base, ORIGIN_MAPPED, len))
+    }
+
+    impl<A: GlobalAlloc> HugePages<A> {
+        unsafe fn alloc_large(&self, layout: Layout, zeroed: bool) -> *mut u8 {
+            let header = header_size(layout.align());
+            let full = match inner_layout(&layout, layout.size()) {
+                Some(full) => full,
+                None => return ptr::null_mut(),
+            };
+            let mode = super::mode();
+            let mapped = if mode != HugePageMode::Off && layout.align() <= PAGE_SIZE {
+                map_large(full.size(), mode)
+            } else {
+                None
+            };
+            let (base, origin, len) = match mapped {
+                // mappings are always zeroed
+                Some(mapped) => mapped,
+                None => {
+                    let base = if zeroed {
+                        self.inner.alloc_zeroed(full)
+                    } else {
+                        self.inner.alloc(full)
+                    };
+                    if base.is_null() {
+                        return base;
+                    }
+                    (base, ORIGIN_INNER, full.size())
+                }
+            };
+            let ptr = base.add(header);
+            let header = ptr.sub(2 * mem::size_of::<usize>()) as *mut usize;
+            header.write(origin);
+            header.add(1).write(len);
+            ptr
+        }
+        /// Returns the origin and the length of the mapping of a large allocation
+        unsafe fn header_of(ptr: *mut u8) -> (usize, usize) {
+            let header = ptr.sub(2 * mem::size_of::<usize>()) as *const usize;
+            (header.read(), header.add(1).read())
+        }
+    }
+
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for HugePages<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if layout.size() < THRESHOLD {
+                self.inner.alloc(layout)
+            } else {
+                self.alloc_large(layout, false)
+            }
+        }
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            if layout.size() < THRESHOLD {
+                self.inner.alloc_zeroed(layout)
+            } else {
+                self.alloc_large(layout, true)
+            }
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            if layout.size() < THRESHOLD {
+                return self.inner.dealloc(ptr, layout);
+            }
+            let base = ptr.sub(header_size(layout.align()));
+            match Self::header_of(ptr) {
+                (ORIGIN_INNER, _) => {
+                    // we were able to allocate this layout, so this can't fail
+                    let full = inner_layout(&layout, layout.size()).unwrap();
+                    self.inner.dealloc(base, full)
+                }
+                (origin, len) => {
+                    if origin == ORIGIN_HUGETLB {
+                        EXPLICIT.fetch_sub(len, Ordering::Relaxed);
+                    }
+                    libc::munmap(base as *mut libc::c_void, len);
+                }
+            }
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if layout.size() < THRESHOLD && new_size < THRESHOLD {
+                return self.inner.realloc(ptr, layout, new_size);
+            }
+            if layout.size() >= THRESHOLD
+                && new_size >= THRESHOLD
+                && Self::header_of(ptr).0 == ORIGIN_INNER
+                && super::mode() == HugePageMode::Off
+            {
+                // let the inner allocator grow (or shrink) it in place, header and all
+                let full = inner_layout(&layout, layout.size()).unwrap();
+                let new_full = match inner_layout(&layout, new_size) {
+                    Some(new_full) => new_full,
+                    None => return ptr::null_mut(),
+                };
+                let header = header_size(layout.align());
+                let base = self.inner.realloc(ptr.sub(header), full, new_full.size());
+                if base.is_null() {
+                    return base;
+                }
+                let ptr = base.add(header);
+                (ptr.sub(mem::size_of::<usize>()) as *mut usize).write(new_full.size());
+                return ptr;
+            }
+            let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+            let new_ptr = self.alloc(new_layout);
+            if !new_ptr.is_null() {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+            new_ptr
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hugepage_alloc() {
+    use std::alloc::System;
+    let alloc = HugePages::new(System);
+    for mode in [
+        HugePageMode::Off,
+        HugePageMode::Transparent,
+        HugePageMode::Explicit,
+    ]
+    .iter()
+    {
+        // the mode is global, but the allocations only depend on it when they're made
+        set_mode(*mode);
+        unsafe {
+            for align in [1, 8, 64].iter() {
+                let layout = Layout::from_size_align(THRESHOLD, *align).unwrap();
+                let ptr = alloc.alloc_zeroed(layout);
+                assert_eq!(ptr as usize % align, 0);
+                assert!(ptr.read() == 0 && ptr.add(THRESHOLD - 1).read() == 0);
+                ptr.add(THRESHOLD - 1).write(1);
+                let ptr = alloc.realloc(ptr, layout, THRESHOLD * 2);
+                assert_eq!(ptr as usize % align, 0);
+                assert_eq!(ptr.add(THRESHOLD - 1).read(), 1);
+                let ptr = alloc.realloc(
+                    ptr,
+                    Layout::from_size_align(THRESHOLD * 2, *align).unwrap(),
+                    100,
+                );
+                assert_eq!(ptr as usize % align, 0);
+                alloc.dealloc(ptr, Layout::from_size_align(100, *align).unwrap());
+            }
+        }
+    }
+    set_mode(HugePageMode::Off);
+}
+
+#[test]
+fn test_parse_anon_huge_pages() {
+    let smaps = "\
+00400000-7ffd8ef2c000 ---p 00000000 00:00 0                      [rollup]
+Rss:               12812 kB
+AnonHugePages:      4096 kB
+Swap:                  0 kB
+";
+    assert_eq!(parse_anon_huge_pages(smaps), Some(4096 * 1024));
+    assert_eq!(parse_anon_huge_pages("Rss: 12 kB"), None);
+}
@@ -24,18 +24,20 @@
  *
 */
 
+use crate::admin;
 use crate::config::BGSave;
+use crate::config::ListenerConfig;
+use crate::config::PortConfig;
 use crate::config::SnapshotConfig;
 use crate::config::SnapshotPref;
 use crate::corestore::Corestore;
 use crate::dbnet::{self, Terminator};
 use crate::services;
 use crate::storage::sengine::SnapshotEngine;
-use crate::PortConfig;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 
-#[cfg(unix)]
+#[cfg(any(unix, windows))]
 use core::{future::Future, pin::Pin, task::Context, task::Poll};
 #[cfg(unix)]
 use tokio::signal::unix::{signal as fnsignal, Signal, SignalKind};
@@ -63,9 +65,38 @@ impl Future for UnixTerminationSignal {
     }
 }
 
+#[cfg(windows)]
+use tokio::signal::windows::{ctrl_break, CtrlBreak};
+#[cfg(windows)]
+/// Object to bind to Windows-specific console events
+pub struct WindowsTerminationSignal {
+    ctrl_break: CtrlBreak,
+}
+
+#[cfg(windows)]
+impl WindowsTerminationSignal {
+    pub fn init() -> Result<Self, String> {
+        let ctrl_break =
+            ctrl_break().map_err(|e| format!("Failed to bind to signal with: {}", e))?;
+        Ok(Self { ctrl_break })
+    }
+}
+
+#[cfg(windows)]
+impl Future for WindowsTerminationSignal {
+    type Output = Option<()>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.ctrl_break.poll_recv(ctx)
+    }
+}
+
 /// Start the server waiting for incoming connections or a termsig
 pub async fn run(
     ports: PortConfig,
+    deny: Vec<String>,
+    listeners: Vec<ListenerConfig>,
+    socket: Option<String>,
     bgsave_cfg: BGSave,
     snapshot_cfg: SnapshotConfig,
     _restore_filepath: Option<String>,
@@ -76,6 +107,12 @@ pub async fn run(
     let engine;
     match &snapshot_cfg {
         SnapshotConfig::Enabled(SnapshotPref { atmost, .. }) => {
+            if cfg!(not(feature = "snapshots")) {
+                return Err(
+                    "This build of skyd doesn't support snapshots (it needs the `snapshots` feature)"
+                        .to_owned(),
+                );
+            }
             engine = SnapshotEngine::new(*atmost);
             engine
                 .parse_dir()
@@ -86,8 +123,17 @@ pub async fn run(
         }
     }
     let engine = Arc::new(engine);
+    // let clients know that we're recovering (and not hung) until the listeners are up
+    let status_listener = services::recovery::StatusListener::start(&ports);
     let db = Corestore::init_with_snapcfg(engine.clone())
         .map_err(|e| format!("Error while initializing database: {}", e))?;
+    admin::users::load(db.get_store());
+    admin::history::load(db.get_store());
+    let store = db.clone_store();
+    tokio::task::spawn_blocking(move || services::warmup::warm_up(&store))
+        .await
+        .expect("table warm up thread panicked");
+    drop(status_listener);
 
     // initialize the background services
     let bgsave_handle = tokio::spawn(services::bgsave::bgsave_scheduler(
@@ -101,18 +147,66 @@ pub async fn run(
         snapshot_cfg,
         Terminator::new(signal.subscribe()),
     ));
+    let reaper_handle = tokio::spawn(services::reaper::drop_reaper(
+        db.clone(),
+        Terminator::new(signal.subscribe()),
+    ));
+    let expiry_handle = tokio::spawn(services::reaper::expiry_reaper(
+        db.clone(),
+        Terminator::new(signal.subscribe()),
+    ));
+    let unloader_handle = tokio::spawn(services::reaper::idle_unloader(
+        db.clone(),
+        Terminator::new(signal.subscribe()),
+    ));
+    let latency_handle = tokio::spawn(services::latency::latency_monitor(Terminator::new(
+        signal.subscribe(),
+    )));
+    let diskguard_handle = tokio::spawn(services::diskguard::disk_guard(
+        db.clone(),
+        Terminator::new(signal.subscribe()),
+    ));
+    let governor_handle = tokio::spawn(services::governor::memory_governor(
+        db.clone(),
+        Terminator::new(signal.subscribe()),
+    ));
+    let shadow_handle = tokio::spawn(services::shadow::shadow_writer(Terminator::new(
+        signal.subscribe(),
+    )));
 
     // bind the ctrlc handler
     let sig = tokio::signal::ctrl_c();
 
     // start the server (single or multiple listeners)
-    let mut server = dbnet::connect(ports, maxcon, db.clone(), signal.clone()).await?;
+    let mut server = dbnet::connect(
+        ports,
+        deny,
+        listeners,
+        socket,
+        maxcon,
+        db.clone(),
+        signal.clone(),
+    )
+    .await?;
+    // the data is restored and every listener is bound, so we're ready for clients
+    services::systemd::notify_ready();
+    let watchdog_handle = tokio::spawn(services::systemd::watchdog(Terminator::new(
+        signal.subscribe(),
+    )));
 
-    #[cfg(not(unix))]
+    #[cfg(windows)]
+    {
+        let ctrl_break = WindowsTerminationSignal::init()?;
+        // apart from CTRLC, the only other thing we care about is CTRL+BREAK (which is also
+        // what service managers send to a console process to stop it)
+        tokio::select! {
+            _ = server.run_server() => {},
+            _ = sig => {},
+            _ = ctrl_break => {}
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
     {
-        // Non-unix, usually Windows specific signal handling.
-        // FIXME(@ohsayan): For now, let's just
-        // bother with ctrl+c, we'll move ahead as users require them
         tokio::select! {
             _ = server.run_server() => {}
             _ = sig => {}
@@ -131,6 +225,7 @@ pub async fn run(
     }
 
     log::info!("Signalling all workers to shut down");
+    services::systemd::notify_stopping();
     // drop the signal and let others exit
     drop(signal);
     server.finish_with_termsig().await;
@@ -138,5 +233,13 @@ pub async fn run(
     // wait for the background services to terminate
     let _ = snapshot_handle.await;
     let _ = bgsave_handle.await;
+    let _ = reaper_handle.await;
+    let _ = expiry_handle.await;
+    let _ = unloader_handle.await;
+    let _ = latency_handle.await;
+    let _ = diskguard_handle.await;
+    let _ = governor_handle.await;
+    let _ = shadow_handle.await;
+    let _ = watchdog_handle.await;
     Ok(db)
 }
@@ -0,0 +1,239 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Cron expressions
+//!
+//! Snapshot schedules use the five usual cron fields: `minute hour day-of-month month
+//! day-of-week`. Each field is a `*`, a number, a range (like `1-5`) or a comma separated list
+//! of these, and any of them can have a step (like `*/15` or `0-30/10`). Days of the week
+//! start on Sunday, which is both `0` and `7`. Like cron, a day matches if it matches either
+//! the day of the month or the day of the week when both of them are restricted (neither is
+//! `*`). All the times are in UTC
+
+use core::fmt;
+
+/// The number of days that we look ahead for the next run. Some expressions (like
+/// `0 0 29 2 *`) only match once every few years, and it takes at most eight years
+/// (across a century that isn't a leap year) for a date to come around again
+const LOOKAHEAD_DAYS: u64 = 8 * 366;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A parsed cron expression
+pub struct Cron {
+    /// the expression as it was written
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// the day of the month is `*`
+    any_day: bool,
+    /// the day of the week is `*`
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Parse a cron expression
+    pub fn parse(expr: &str) -> Result<Self, &'static str> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err("a cron expression needs five fields");
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            // 7 is sunday too
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        let cron = Self {
+            expr: fields.join(" "),
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        };
+        if cron.next_after(0).is_none() {
+            // like `0 0 31 2 *`
+            return Err("the cron expression never matches");
+        }
+        Ok(cron)
+    }
+    /// Returns the expression as it was written (with single spaces between the fields)
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+    /// Returns the first time (as a UNIX timestamp in seconds) after `after` that matches the
+    /// expression, or `None` if there is none in the next few years
+    pub fn next_after(&self, after: u64) -> Option<u64> {
+        let from = after / 60 + 1;
+        let first_day = from / 1440;
+        (first_day..first_day + LOOKAHEAD_DAYS)
+            .filter(|day| self.matches_day(*day))
+            .find_map(|day| {
+                let start = if day == first_day { from % 1440 } else { 0 };
+                self.first_in_day(start)
+                    .map(|minute| (day * 1440 + minute) * 60)
+            })
+    }
+    /// Check if the day (counted from the UNIX epoch) matches
+    fn matches_day(&self, day: u64) -> bool {
+        let (month, mday) = month_and_day(day);
+        // 1970-01-01 was a thursday
+        let weekday = (day + 4) % 7;
+        if !has(self.months, month) {
+            return false;
+        }
+        let day_ok = has(self.days, mday);
+        let weekday_ok = has(self.weekdays, weekday);
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day_ok || weekday_ok,
+            _ => day_ok && weekday_ok,
+        }
+    }
+    /// Returns the first matching minute of a day, that is at least `start`
+    fn first_in_day(&self, start: u64) -> Option<u64> {
+        (start / 60..24)
+            .filter(|hour| has(self.hours, *hour))
+            .find_map(|hour| {
+                let from = if hour == start / 60 { start % 60 } else { 0 };
+                let minutes = self.minutes >> from;
+                if minutes == 0 {
+                    None
+                } else {
+                    Some(hour * 60 + from + minutes.trailing_zeros() as u64)
+                }
+            })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+const fn has(set: u64, value: u64) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parse a field into a set of values (as bits), all of which are in `min..=max`
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64, &'static str> {
+    let number = |n: &str| {
+        n.parse::<u64>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or("bad value in a cron expression")
+    };
+    let mut set = 0;
+    for part in field.split(',') {
+        let mut split = part.splitn(2, '/');
+        let range = split.next().unwrap_or_default();
+        let step = match split.next() {
+            Some(step) => match step.parse::<usize>() {
+                Ok(step) if step != 0 => Some(step),
+                _ => return Err("bad step in a cron expression"),
+            },
+            None => None,
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else {
+            let mut split = range.splitn(2, '-');
+            let lo = number(split.next().unwrap_or_default())?;
+            match split.next() {
+                Some(hi) => (lo, number(hi)?),
+                // `5/10` is every tenth value starting at 5
+                None if step.is_some() => (lo, max),
+                None => (lo, lo),
+            }
+        };
+        if lo > hi {
+            return Err("bad range in a cron expression");
+        }
+        for value in (lo..=hi).step_by(step.unwrap_or(1)) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+/// Returns the month (`1..=12`) and the day of the month (`1..=31`) of a day counted from
+/// the UNIX epoch (this is Howard Hinnant's `civil_from_days`)
+const fn month_and_day(day: u64) -> (u64, u64) {
+    // shift the epoch to 0000-03-01, so that leap days are at the end of the year
+    let z = day + 719_468;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let mday = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (month, mday)
+}
+
+#[test]
+fn test_cron_parse() {
+    assert!(Cron::parse("0 3 * * *").is_ok());
+    assert!(Cron::parse("*/15 0-6,18-23 1,15 * 1-5").is_ok());
+    assert_eq!(Cron::parse("0  3 * *   *").unwrap().as_str(), "0 3 * * *");
+    assert!(Cron::parse("0 3 * *").is_err());
+    assert!(Cron::parse("60 * * * *").is_err());
+    assert!(Cron::parse("* 24 * * *").is_err());
+    assert!(Cron::parse("* * 0 * *").is_err());
+    assert!(Cron::parse("* * * 13 *").is_err());
+    assert!(Cron::parse("* * * * 8").is_err());
+    assert!(Cron::parse("*/0 * * * *").is_err());
+    assert!(Cron::parse("30-10 * * * *").is_err());
+    assert!(Cron::parse("hourly * * * *").is_err());
+    // february never has 31 days
+    assert!(Cron::parse("0 0 31 2 *").is_err());
+}
+
+#[test]
+fn test_cron_next_after() {
+    // 2021-01-01 00:00:00 (a friday)
+    const NEW_YEAR: u64 = 1_609_459_200;
+    let next = |expr: &str, after: u64| Cron::parse(expr).unwrap().next_after(after).unwrap();
+    assert_eq!(next("* * * * *", NEW_YEAR), NEW_YEAR + 60);
+    assert_eq!(next("* * * * *", NEW_YEAR + 59), NEW_YEAR + 60);
+    assert_eq!(next("*/15 * * * *", NEW_YEAR), NEW_YEAR + 900);
+    assert_eq!(next("0 3 * * *", NEW_YEAR), NEW_YEAR + 3 * 3600);
+    assert_eq!(next("0 3 * * *", NEW_YEAR + 3 * 3600), NEW_YEAR + 27 * 3600);
+    // the first monday
+    assert_eq!(next("0 0 * * 1", NEW_YEAR), NEW_YEAR + 3 * 86400);
+    // the first sunday, either way
+    assert_eq!(next("0 0 * * 0", NEW_YEAR), NEW_YEAR + 2 * 86400);
+    assert_eq!(next("0 0 * * 7", NEW_YEAR), NEW_YEAR + 2 * 86400);
+    // the 1st or a monday, whichever comes first
+    assert_eq!(next("0 0 1 * 1", NEW_YEAR), NEW_YEAR + 3 * 86400);
+    // the next 1st, which is in february
+    assert_eq!(next("0 0 1 * *", NEW_YEAR), NEW_YEAR + 31 * 86400);
+    // 2024-02-29
+    assert_eq!(next("0 0 29 2 *", NEW_YEAR), 1_709_164_800);
+}
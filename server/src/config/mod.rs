@@ -26,7 +26,27 @@
 
 //! This module provides tools to handle configuration files and settings
 
+use self::cron::Cron;
+use crate::allocator::hugepages::{self, HugePageMode};
+use crate::corestore::hasher::HashFunction;
+use crate::dbnet::ALPN_PROTOCOLS;
+use crate::dbnet::DEFAULT_MAX_BUFFER;
 use crate::dbnet::MAXIMUM_CONNECTION_LIMIT;
+use crate::registry::admission::{AdmissionPolicy, Priority};
+use crate::registry::auth::User;
+use crate::registry::bgio::{BackgroundIo, IoLimit, IoPriority};
+use crate::registry::pressure::{self, MemoryPolicy};
+use crate::registry::stall;
+use crate::registry::tls::TlsPolicy;
+use crate::registry::topology::Topology;
+use crate::registry::ServerMode;
+use crate::services::bgsave::{self, AdaptiveFlush};
+use crate::services::expiry::Jitter;
+use crate::services::origin::Origin;
+use crate::services::querylog::{self, QueryLog};
+use crate::services::schema::SchemaFile;
+use crate::services::shadow::Shadow;
+use crate::services::throttle::{Throttle, ThrottleMode};
 #[cfg(test)]
 use libsky::TResult;
 use serde::Deserialize;
@@ -36,6 +56,7 @@ use std::fs;
 #[cfg(test)]
 use std::net::Ipv6Addr;
 use std::net::{IpAddr, Ipv4Addr};
+pub mod cron;
 const DEFAULT_IPV4: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
 #[cfg(test)]
 const DEFAULT_PORT: u16 = 2003;
@@ -52,6 +73,30 @@ pub struct Config {
     snapshot: Option<ConfigKeySnapshot>,
     /// SSL configuration
     ssl: Option<KeySslOpts>,
+    /// Additional listeners
+    listener: Option<Vec<ConfigKeyListener>>,
+    /// Users that connections can authenticate as
+    user: Option<Vec<ConfigKeyUser>>,
+    /// Origins that tables cache
+    origin: Option<Vec<ConfigKeyOrigin>>,
+    /// Maximum write rates of tables
+    throttle: Option<Vec<ConfigKeyThrottle>>,
+    /// TTL jitters of tables
+    jitter: Option<Vec<ConfigKeyJitter>>,
+    /// JSON Schemas of tables
+    schema: Option<Vec<ConfigKeySchema>>,
+    /// Shadow tables that writes are mirrored to
+    shadow: Option<Vec<ConfigKeyShadow>>,
+    /// The query log
+    querylog: Option<ConfigKeyQueryLog>,
+    /// The thread topology
+    topology: Option<ConfigKeyTopology>,
+    /// The memory limit
+    memory: Option<ConfigKeyMemory>,
+    /// The limits beyond which load is shed
+    admission: Option<ConfigKeyAdmission>,
+    /// The limits of background I/O
+    backgroundio: Option<ConfigKeyBackgroundIo>,
 }
 
 /// The BGSAVE section in the config file
@@ -66,6 +111,14 @@ pub struct ConfigKeyBGSAVE {
     /// If this is the only key specified, then it is clear that BGSAVE is enabled
     /// and the duration is `every`
     every: Option<u64>,
+    /// Whether hot tables are flushed early (see [`AdaptiveFlush`])
+    adaptive: Option<bool>,
+    /// The shortest interval (in seconds) at which hot tables are flushed
+    min: Option<u64>,
+    /// The write rate (writes per second) from which a table is hot
+    hotrate: Option<u64>,
+    /// The most bytes per second that early flushes can write
+    iobudget: Option<u64>,
 }
 
 /// The BGSAVE configuration
@@ -113,19 +166,72 @@ pub struct ConfigKeyServer {
     noart: Option<bool>,
     /// The maximum number of clients
     maxclient: Option<usize>,
+    /// The mode the server starts in (`normal`, `readonly` or `maintenance`)
+    mode: Option<ServerMode>,
+    /// The number of seconds after which connections that don't send any queries are
+    /// closed
+    idletimeout: Option<u64>,
+    /// The number of idle seconds after which TCP keepalive probes are sent to a client
+    keepalive: Option<u64>,
+    /// The maximum number of bytes buffered for a connection's queries
+    maxbuffer: Option<usize>,
+    /// Actions that are disabled on all listeners
+    deny: Option<Vec<String>>,
+    /// Whether flushing a non-empty table needs a confirmation token
+    flushconfirm: Option<bool>,
+    /// The number of seconds for which dropped tables can be restored
+    dropretention: Option<u64>,
+    /// The number of seconds after which tables that weren't accessed are unloaded
+    idleunload: Option<u64>,
+    /// Tables (as `<keyspace>:<table>`) that are warmed up at startup
+    preload: Option<Vec<String>>,
+    /// The path of a unix domain socket (or the name of a named pipe on Windows) to listen on
+    socket: Option<String>,
+    /// The number of threads that read tables in parallel while recovering
+    recoverythreads: Option<usize>,
+    /// The number of threads that write tables in parallel while creating a snapshot
+    snapshotthreads: Option<usize>,
+    /// The hash function that tables use (`sip` or `ahash`)
+    hasher: Option<HashFunction>,
+    /// How large allocations are backed (`off`, `transparent` or `explicit`)
+    hugepages: Option<HugePageMode>,
+    /// The free space (in bytes) below which writes are stalled
+    minfreedisk: Option<u64>,
 }
 
 /// The snapshot section in the TOML file
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct ConfigKeySnapshot {
-    /// After how many seconds should the snapshot be created
-    every: u64,
+    /// After how many seconds should the snapshot be created (`0` or missing to only use
+    /// the schedules)
+    every: Option<u64>,
     /// The maximum number of snapshots to keep
     ///
     /// If atmost is set to `0`, then all the snapshots will be kept
     atmost: usize,
     /// Prevent writes to the database if snapshotting fails
     failsafe: Option<bool>,
+    /// Named cron schedules for snapshots, declared as `[[snapshot.schedule]]` entries
+    schedule: Option<Vec<SnapshotSchedule>>,
+}
+
+/// A named cron schedule for snapshots
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct SnapshotSchedule {
+    /// The name of the schedule
+    pub name: String,
+    /// The cron expression (see [`cron`])
+    pub cron: String,
+}
+
+impl SnapshotSchedule {
+    pub fn new(name: String, cron: String) -> Self {
+        Self { name, cron }
+    }
+    /// Parse the cron expression of this schedule
+    pub fn parse(&self) -> Result<Cron, &'static str> {
+        Cron::parse(&self.cron)
+    }
 }
 
 /// Port configuration
@@ -165,6 +271,21 @@ impl PortConfig {
 }
 
 impl PortConfig {
+    /// Returns the `(host, port)` pairs that this configuration will bind to
+    pub fn get_bindings(&self) -> Vec<(IpAddr, u16)> {
+        match self {
+            PortConfig::SecureOnly { host, ssl } => vec![(*host, ssl.port)],
+            PortConfig::InsecureOnly { host, port } => vec![(*host, *port)],
+            PortConfig::Multi { host, port, ssl } => vec![(*host, *port), (*host, ssl.port)],
+        }
+    }
+    /// Returns the TLS settings, if TLS is enabled
+    pub const fn get_ssl(&self) -> Option<&SslOpts> {
+        match self {
+            PortConfig::SecureOnly { ssl, .. } | PortConfig::Multi { ssl, .. } => Some(ssl),
+            PortConfig::InsecureOnly { .. } => None,
+        }
+    }
     pub const fn new_secure_only(host: IpAddr, ssl: SslOpts) -> Self {
         PortConfig::SecureOnly { host, ssl }
     }
@@ -183,6 +304,242 @@ pub struct KeySslOpts {
     port: u16,
     only: Option<bool>,
     passin: Option<String>,
+    /// Whether clients can resume TLS sessions (defaults to true)
+    resumption: Option<bool>,
+    /// The ALPN protocols to offer, most preferred first
+    alpn: Option<Vec<String>>,
+    /// What clients can negotiate
+    policy: Option<TlsPolicy>,
+}
+
+/// An additional listener, declared as a `[[listener]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyListener {
+    /// The address to bind to. Binding to `::` will usually give a dual-stack (IPv4 + IPv6)
+    /// socket, depending on the host's settings
+    host: IpAddr,
+    /// The port to bind to
+    port: u16,
+    /// If this is set, the listener will only accept TLS connections
+    tls: Option<ConfigKeyListenerTls>,
+    /// If this is set to true, the listener will only accept administrative actions
+    admin: Option<bool>,
+    /// Actions that are disabled on this listener (in addition to the ones disabled on
+    /// all listeners)
+    deny: Option<Vec<String>>,
+    /// The priority class of the connections on this listener
+    priority: Option<Priority>,
+}
+
+/// The TLS settings for a `[[listener]]` entry
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyListenerTls {
+    key: String,
+    chain: String,
+    passin: Option<String>,
+    /// Whether clients can resume TLS sessions (defaults to true)
+    resumption: Option<bool>,
+    /// The ALPN protocols to offer, most preferred first
+    alpn: Option<Vec<String>>,
+    /// What clients can negotiate
+    policy: Option<TlsPolicy>,
+}
+
+impl ConfigKeyListener {
+    /// Turn this listener entry into a `ListenerConfig`, with the actions in `deny`
+    /// disabled along with its own
+    fn into_listener_cfg(self, deny: &[String]) -> ListenerConfig {
+        let ports = match self.tls {
+            Some(tls) => PortConfig::new_secure_only(
+                self.host,
+                SslOpts::new(tls.key, tls.chain, self.port, tls.passin)
+                    .with_session_opts(tls.resumption, tls.alpn)
+                    .with_policy(tls.policy),
+            ),
+            None => PortConfig::new_insecure_only(self.host, self.port),
+        };
+        let mut listener = ListenerConfig::new(ports, option_unwrap_or!(self.admin, false));
+        listener.deny = deny.to_vec();
+        listener
+            .deny
+            .extend(option_unwrap_or!(self.deny, Vec::new()));
+        listener.priority = self.priority;
+        listener
+    }
+}
+
+/// A user, declared as a `[[user]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyUser {
+    /// The name to authenticate with
+    name: String,
+    /// The token to authenticate with
+    token: String,
+    /// The keyspaces that this user can access. If this is missing, then the user can
+    /// access every keyspace and run server-wide actions
+    keyspaces: Option<Vec<String>>,
+    /// The maximum number of connections that can be authenticated as this user
+    maxconnections: Option<usize>,
+    /// The maximum number of operations per second for this user (across all its
+    /// connections)
+    opsrate: Option<u64>,
+    /// The priority class of this user's connections
+    priority: Option<Priority>,
+}
+
+/// The origin of a table, declared as an `[[origin]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyOrigin {
+    /// The table that caches the origin (as `<keyspace>:<table>`)
+    table: String,
+    /// The URL that keys are fetched from and stored to (as `<url>/<key>`)
+    url: String,
+    /// Whether missing keys are fetched from the origin
+    readthrough: Option<bool>,
+    /// Whether `SET`s are propagated to the origin
+    writethrough: Option<bool>,
+}
+
+/// The maximum write rate of a table, declared as a `[[throttle]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyThrottle {
+    /// The throttled table (as `<keyspace>:<table>`)
+    table: String,
+    /// The maximum number of writes per second
+    writerate: u64,
+    /// What happens to the writes beyond the rate (`delay` or `reject`)
+    mode: Option<ThrottleMode>,
+}
+
+/// The TTL jitter of a table, declared as a `[[jitter]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyJitter {
+    /// The table (as `<keyspace>:<table>`)
+    table: String,
+    /// The most that a TTL is stretched by, as a percentage of the TTL
+    percent: u8,
+}
+
+/// The JSON Schema of a table, declared as a `[[schema]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeySchema {
+    /// The table (as `<keyspace>:<table>`)
+    table: String,
+    /// The file that the schema is read from
+    file: String,
+}
+
+/// The shadow of a table, declared as a `[[shadow]]` entry in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyShadow {
+    /// The table whose writes are mirrored (as `<keyspace>:<table>`)
+    table: String,
+    /// The shadow table (as `<keyspace>:<table>`)
+    target: String,
+    /// The node that the shadow table is on (as `<host>:<port>`), if it isn't this one
+    host: Option<String>,
+}
+
+/// The `[querylog]` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyQueryLog {
+    /// The percentage of queries that are sampled
+    sample: f64,
+    /// The file that the sampled queries are written to
+    path: Option<String>,
+    /// The size (in bytes) at which the file is rotated
+    maxsize: Option<u64>,
+    /// The number of rotated files that are kept
+    keep: Option<usize>,
+}
+
+/// The `[topology]` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyTopology {
+    /// The number of worker threads (`0` for one per CPU)
+    workers: Option<usize>,
+    /// The cores that the worker threads are pinned to
+    workercores: Option<Vec<usize>>,
+    /// The cores that the background threads are pinned to
+    backgroundcores: Option<Vec<usize>>,
+    /// Whether the worker threads are pinned to NUMA nodes
+    numa: Option<bool>,
+}
+
+/// The `[backgroundio]` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyBackgroundIo {
+    /// The limits of snapshots
+    snapshot: Option<ConfigKeyIoLimit>,
+    /// The limits of exports
+    export: Option<ConfigKeyIoLimit>,
+    /// The limits of slab compaction
+    compaction: Option<ConfigKeyIoLimit>,
+}
+
+/// The limits of a kind of background work, in the `[backgroundio]` section
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyIoLimit {
+    /// The most MB that it moves in a second
+    rate: Option<u64>,
+    /// The I/O priority of its threads
+    priority: Option<IoPriority>,
+}
+
+impl ConfigKeyIoLimit {
+    fn into_limit(self) -> IoLimit {
+        IoLimit::new(
+            self.rate,
+            option_unwrap_or!(self.priority, IoPriority::Normal),
+        )
+    }
+}
+
+/// The `[memory]` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyMemory {
+    /// The most resident memory (in bytes) that the server should use
+    limit: u64,
+    /// The percentage of the limit from which new connections are refused
+    connections: Option<u64>,
+    /// The percentage of the limit from which volatile tables are evicted
+    evict: Option<u64>,
+    /// The percentage of the limit from which writes are rejected
+    writes: Option<u64>,
+}
+
+/// The `[admission]` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ConfigKeyAdmission {
+    /// The most queries that can be queued before load is shed
+    maxqueue: Option<u64>,
+    /// The most that the worker threads can lag behind (in milliseconds) before load is shed
+    maxlag: Option<u64>,
+}
+
+/// The configuration of an additional listener
+#[derive(Debug, PartialEq)]
+pub struct ListenerConfig {
+    /// The port configuration
+    pub ports: PortConfig,
+    /// If this is true, then only administrative actions can be run on this listener
+    pub admin: bool,
+    /// The actions that are disabled on this listener
+    pub deny: Vec<String>,
+    /// The priority class of the connections on this listener (unless they authenticate as
+    /// a user that has one)
+    pub priority: Option<Priority>,
+}
+
+impl ListenerConfig {
+    pub const fn new(ports: PortConfig, admin: bool) -> Self {
+        ListenerConfig {
+            ports,
+            admin,
+            deny: Vec::new(),
+            priority: None,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -191,6 +548,12 @@ pub struct SslOpts {
     pub chain: String,
     pub port: u16,
     pub passfile: Option<String>,
+    /// Whether clients can resume TLS sessions (with session IDs or tickets)
+    pub resumption: bool,
+    /// The ALPN protocols to offer, most preferred first (ALPN is disabled if empty)
+    pub alpn: Vec<String>,
+    /// What clients can negotiate
+    pub policy: TlsPolicy,
 }
 
 impl SslOpts {
@@ -200,20 +563,41 @@ impl SslOpts {
             chain,
             port,
             passfile,
+            resumption: true,
+            alpn: Vec::new(),
+            policy: TlsPolicy {
+                min_version: None,
+                ciphers: None,
+                ciphersuites: None,
+                curves: None,
+            },
         }
     }
+    /// Set the session resumption and ALPN settings from the config file
+    fn with_session_opts(mut self, resumption: Option<bool>, alpn: Option<Vec<String>>) -> Self {
+        self.resumption = option_unwrap_or!(resumption, true);
+        self.alpn = option_unwrap_or!(alpn, Vec::new());
+        self
+    }
+    /// Set the TLS policy from the config file
+    fn with_policy(mut self, policy: Option<TlsPolicy>) -> Self {
+        self.policy = policy.unwrap_or_default();
+        self
+    }
 }
 
 #[derive(Debug, PartialEq)]
 /// The snapshot configuration
 ///
 pub struct SnapshotPref {
-    /// Capture a snapshot `every` seconds
+    /// Capture a snapshot `every` seconds (never, if this is `0`)
     pub every: u64,
     /// The maximum numeber of snapshots to be kept
     pub atmost: usize,
     /// Lock writes if snapshotting fails
     pub poison: bool,
+    /// The named cron schedules on which snapshots are captured
+    pub schedules: Vec<SnapshotSchedule>,
 }
 
 impl SnapshotPref {
@@ -223,11 +607,29 @@ impl SnapshotPref {
             every,
             atmost,
             poison,
+            schedules: Vec::new(),
         }
     }
-    /// Returns `every,almost` as a tuple for pattern matching
-    pub const fn decompose(self) -> (u64, usize, bool) {
-        (self.every, self.atmost, self.poison)
+    /// Capture snapshots on these schedules too
+    pub fn with_schedules(mut self, schedules: Vec<SnapshotSchedule>) -> Self {
+        self.schedules = schedules;
+        self
+    }
+    /// Returns the first schedule whose cron expression is bad along with what's wrong with
+    /// it, if any
+    pub fn invalid_schedule(&self) -> Option<(&SnapshotSchedule, &'static str)> {
+        self.schedules
+            .iter()
+            .find_map(|schedule| schedule.parse().err().map(|e| (schedule, e)))
+    }
+    /// Returns true if two or more schedules have the same name (or a schedule doesn't
+    /// have a name)
+    pub fn has_bad_schedule_names(&self) -> bool {
+        let mut names: Vec<&str> = self.schedules.iter().map(|s| s.name.as_str()).collect();
+        let total = names.len();
+        names.sort_unstable();
+        names.dedup();
+        names.len() != total || names.contains(&"")
     }
 }
 
@@ -266,8 +668,66 @@ pub struct ParsedConfig {
     pub snapshot: SnapshotConfig,
     /// Port configuration
     pub ports: PortConfig,
+    /// Any additional listeners
+    pub listeners: Vec<ListenerConfig>,
     /// The maximum number of connections
     pub maxcon: usize,
+    /// The mode the server starts in
+    pub mode: ServerMode,
+    /// The idle timeout for connections in seconds (`0` if disabled)
+    pub idletimeout: u64,
+    /// The TCP keepalive interval in seconds (`0` if disabled)
+    pub keepalive: u64,
+    /// The maximum number of bytes buffered for a connection's queries (`0` if unlimited)
+    pub maxbuffer: usize,
+    /// The actions that are disabled on the primary listener (and all the other listeners)
+    pub deny: Vec<String>,
+    /// Whether flushing a non-empty table needs a confirmation token
+    pub flushconfirm: bool,
+    /// The number of seconds for which dropped tables can be restored (`0` if disabled)
+    pub dropretention: u64,
+    /// The number of seconds after which tables that weren't accessed are unloaded from
+    /// memory (`0` if disabled)
+    pub idleunload: u64,
+    /// The tables that are warmed up at startup and kept in memory
+    pub preload: Vec<String>,
+    /// The local socket to listen on: a unix domain socket, or a named pipe on Windows
+    pub socket: Option<String>,
+    /// The number of threads that read tables while recovering (`0` for one per CPU)
+    pub recoverythreads: usize,
+    /// The number of threads that write tables while creating a snapshot (`0` for one per CPU)
+    pub snapshotthreads: usize,
+    /// The hash function that tables use
+    pub hasher: HashFunction,
+    /// How large allocations are backed by huge pages
+    pub hugepages: HugePageMode,
+    /// The free space (in bytes) below which writes are stalled (`0` if disabled)
+    pub minfreedisk: u64,
+    /// The users that connections can authenticate as (authentication is disabled if
+    /// there are none)
+    pub users: Vec<User>,
+    /// The origins that tables cache
+    pub origins: Vec<Origin>,
+    /// The maximum write rates of tables
+    pub throttles: Vec<Throttle>,
+    /// The TTL jitters of tables
+    pub jitters: Vec<Jitter>,
+    /// The schema files of tables
+    pub schemas: Vec<SchemaFile>,
+    /// The shadows of tables
+    pub shadows: Vec<Shadow>,
+    /// The query log configuration (if it's enabled)
+    pub querylog: Option<QueryLog>,
+    /// The adaptive flush policy (if it's enabled)
+    pub adaptiveflush: Option<AdaptiveFlush>,
+    /// The memory limit and how the server degrades as it nears it (if there's a limit)
+    pub memory: Option<MemoryPolicy>,
+    /// The limits beyond which load is shed (if there are any)
+    pub admission: Option<AdmissionPolicy>,
+    /// The number of worker threads and the cores that threads are pinned to
+    pub topology: Topology,
+    /// The limits of the I/O of snapshots, exports and slab compaction
+    pub backgroundio: BackgroundIo,
 }
 
 impl ParsedConfig {
@@ -285,6 +745,18 @@ impl ParsedConfig {
     /// Create a `ParsedConfig` instance from a `Config` object, which is a parsed
     /// TOML file (represented as an object)
     fn from_config(cfg_info: Config) -> Self {
+        let deny = option_unwrap_or!(cfg_info.server.deny, Vec::new());
+        let adaptiveflush = cfg_info.bgsave.as_ref().and_then(|bgsave| {
+            if option_unwrap_or!(bgsave.adaptive, false) {
+                Some(AdaptiveFlush::new(
+                    option_unwrap_or!(bgsave.min, bgsave::DEFAULT_MIN),
+                    option_unwrap_or!(bgsave.hotrate, bgsave::DEFAULT_HOT_RATE),
+                    option_unwrap_or!(bgsave.iobudget, 0),
+                ))
+            } else {
+                None
+            }
+        });
         ParsedConfig {
             noart: option_unwrap_or!(cfg_info.server.noart, false),
             bgsave: if let Some(bgsave) = cfg_info.bgsave {
@@ -301,32 +773,29 @@ impl ParsedConfig {
             snapshot: cfg_info
                 .snapshot
                 .map(|snapshot| {
-                    SnapshotConfig::Enabled(SnapshotPref::new(
-                        snapshot.every,
-                        snapshot.atmost,
-                        option_unwrap_or!(snapshot.failsafe, true),
-                    ))
+                    SnapshotConfig::Enabled(
+                        SnapshotPref::new(
+                            option_unwrap_or!(snapshot.every, 0),
+                            snapshot.atmost,
+                            option_unwrap_or!(snapshot.failsafe, true),
+                        )
+                        .with_schedules(option_unwrap_or!(snapshot.schedule, Vec::new())),
+                    )
                 })
                 .unwrap_or_else(SnapshotConfig::default),
             ports: if let Some(sslopts) = cfg_info.ssl {
-                if option_unwrap_or!(sslopts.only, false) {
+                let only = option_unwrap_or!(sslopts.only, false);
+                let ssl = SslOpts::new(sslopts.key, sslopts.chain, sslopts.port, sslopts.passin)
+                    .with_session_opts(sslopts.resumption, sslopts.alpn)
+                    .with_policy(sslopts.policy);
+                if only {
                     PortConfig::SecureOnly {
-                        ssl: SslOpts {
-                            key: sslopts.key,
-                            chain: sslopts.chain,
-                            port: sslopts.port,
-                            passfile: sslopts.passin,
-                        },
+                        ssl,
                         host: cfg_info.server.host,
                     }
                 } else {
                     PortConfig::Multi {
-                        ssl: SslOpts {
-                            key: sslopts.key,
-                            chain: sslopts.chain,
-                            port: sslopts.port,
-                            passfile: sslopts.passin,
-                        },
+                        ssl,
                         host: cfg_info.server.host,
                         port: cfg_info.server.port,
                     }
@@ -337,9 +806,250 @@ impl ParsedConfig {
                     port: cfg_info.server.port,
                 }
             },
+            listeners: cfg_info
+                .listener
+                .map(|listeners| {
+                    listeners
+                        .into_iter()
+                        .map(|listener| listener.into_listener_cfg(&deny))
+                        .collect()
+                })
+                .unwrap_or_default(),
             maxcon: option_unwrap_or!(cfg_info.server.maxclient, MAXIMUM_CONNECTION_LIMIT),
+            mode: option_unwrap_or!(cfg_info.server.mode, ServerMode::Normal),
+            idletimeout: option_unwrap_or!(cfg_info.server.idletimeout, 0),
+            keepalive: option_unwrap_or!(cfg_info.server.keepalive, 0),
+            maxbuffer: option_unwrap_or!(cfg_info.server.maxbuffer, DEFAULT_MAX_BUFFER),
+            deny,
+            flushconfirm: option_unwrap_or!(cfg_info.server.flushconfirm, false),
+            dropretention: option_unwrap_or!(cfg_info.server.dropretention, 0),
+            idleunload: option_unwrap_or!(cfg_info.server.idleunload, 0),
+            preload: cfg_info.server.preload.unwrap_or_default(),
+            socket: cfg_info.server.socket,
+            recoverythreads: option_unwrap_or!(cfg_info.server.recoverythreads, 0),
+            snapshotthreads: option_unwrap_or!(cfg_info.server.snapshotthreads, 0),
+            hasher: option_unwrap_or!(cfg_info.server.hasher, HashFunction::Sip),
+            hugepages: option_unwrap_or!(cfg_info.server.hugepages, HugePageMode::Off),
+            minfreedisk: option_unwrap_or!(cfg_info.server.minfreedisk, stall::DEFAULT_MIN_FREE),
+            users: cfg_info
+                .user
+                .map(|users| {
+                    users
+                        .into_iter()
+                        .map(|user| {
+                            User::new(user.name, user.token, user.keyspaces)
+                                .with_limits(
+                                    option_unwrap_or!(user.maxconnections, 0),
+                                    option_unwrap_or!(user.opsrate, 0),
+                                )
+                                .with_priority(user.priority)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            origins: cfg_info
+                .origin
+                .map(|origins| {
+                    origins
+                        .into_iter()
+                        .map(|origin| {
+                            Origin::new(
+                                origin.table,
+                                origin.url,
+                                option_unwrap_or!(origin.readthrough, true),
+                                option_unwrap_or!(origin.writethrough, true),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            throttles: cfg_info
+                .throttle
+                .map(|throttles| {
+                    throttles
+                        .into_iter()
+                        .map(|throttle| {
+                            Throttle::new(
+                                throttle.table,
+                                throttle.writerate,
+                                option_unwrap_or!(throttle.mode, ThrottleMode::Delay),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            jitters: cfg_info
+                .jitter
+                .map(|jitters| {
+                    jitters
+                        .into_iter()
+                        .map(|jitter| Jitter::new(jitter.table, jitter.percent))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            schemas: cfg_info
+                .schema
+                .map(|schemas| {
+                    schemas
+                        .into_iter()
+                        .map(|schema| SchemaFile::new(schema.table, schema.file))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            shadows: cfg_info
+                .shadow
+                .map(|shadows| {
+                    shadows
+                        .into_iter()
+                        .map(|shadow| Shadow::new(shadow.table, shadow.target, shadow.host))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            querylog: cfg_info.querylog.map(|log| {
+                QueryLog::new(
+                    option_unwrap_or!(log.path, querylog::DEFAULT_PATH.to_owned()),
+                    log.sample,
+                    option_unwrap_or!(log.maxsize, querylog::DEFAULT_MAX_SIZE),
+                    option_unwrap_or!(log.keep, querylog::DEFAULT_KEEP),
+                )
+            }),
+            adaptiveflush,
+            memory: cfg_info.memory.map(|memory| {
+                MemoryPolicy::new(
+                    memory.limit,
+                    option_unwrap_or!(memory.connections, pressure::DEFAULT_CONNECTIONS),
+                    option_unwrap_or!(memory.evict, pressure::DEFAULT_EVICT),
+                    option_unwrap_or!(memory.writes, pressure::DEFAULT_WRITES),
+                )
+            }),
+            admission: cfg_info.admission.map(|admission| {
+                AdmissionPolicy::new(
+                    option_unwrap_or!(admission.maxqueue, 0),
+                    option_unwrap_or!(admission.maxlag, 0),
+                )
+            }),
+            topology: cfg_info
+                .topology
+                .map(|topology| {
+                    Topology::new(
+                        option_unwrap_or!(topology.workers, 0),
+                        option_unwrap_or!(topology.workercores, Vec::new()),
+                        option_unwrap_or!(topology.backgroundcores, Vec::new()),
+                    )
+                    .with_numa(option_unwrap_or!(topology.numa, false))
+                })
+                .unwrap_or_default(),
+            backgroundio: cfg_info
+                .backgroundio
+                .map(|bgio| BackgroundIo {
+                    snapshot: bgio
+                        .snapshot
+                        .map(ConfigKeyIoLimit::into_limit)
+                        .unwrap_or_default(),
+                    export: bgio
+                        .export
+                        .map(ConfigKeyIoLimit::into_limit)
+                        .unwrap_or_default(),
+                    compaction: bgio
+                        .compaction
+                        .map(ConfigKeyIoLimit::into_limit)
+                        .unwrap_or_default(),
+                })
+                .unwrap_or_default(),
         }
     }
+    /// Returns the first origin in the config whose URL isn't supported, if any
+    pub fn unsupported_origin(&self) -> Option<&Origin> {
+        self.origins.iter().find(|origin| !origin.is_supported())
+    }
+    /// Returns true if a table has two or more origins
+    pub fn has_duplicate_origins(&self) -> bool {
+        let mut tables: Vec<&str> = self.origins.iter().map(Origin::table).collect();
+        let total = tables.len();
+        tables.sort_unstable();
+        tables.dedup();
+        tables.len() != total
+    }
+    /// Returns the first throttle in the config that doesn't allow any writes, if any
+    pub fn blocked_throttle(&self) -> Option<&Throttle> {
+        self.throttles.iter().find(|throttle| throttle.is_blocked())
+    }
+    /// Returns the first jitter in the config that isn't between 1 and 100 percent, if any
+    pub fn bad_jitter(&self) -> Option<&Jitter> {
+        self.jitters.iter().find(|jitter| jitter.is_out_of_range())
+    }
+    /// Returns true if a table has two or more jitters
+    pub fn has_duplicate_jitters(&self) -> bool {
+        let mut tables: Vec<&str> = self.jitters.iter().map(Jitter::table).collect();
+        let total = tables.len();
+        tables.sort_unstable();
+        tables.dedup();
+        tables.len() != total
+    }
+    /// Returns true if a table has two or more schemas
+    pub fn has_duplicate_schemas(&self) -> bool {
+        let mut tables: Vec<&str> = self.schemas.iter().map(SchemaFile::table).collect();
+        let total = tables.len();
+        tables.sort_unstable();
+        tables.dedup();
+        tables.len() != total
+    }
+    /// Returns true if a table has two or more shadows
+    pub fn has_duplicate_shadows(&self) -> bool {
+        let mut tables: Vec<&str> = self.shadows.iter().map(Shadow::table).collect();
+        let total = tables.len();
+        tables.sort_unstable();
+        tables.dedup();
+        tables.len() != total
+    }
+    /// Returns the first shadow on this node that is itself a table with a shadow (which
+    /// includes a table that shadows itself), if any
+    pub fn bad_shadow(&self) -> Option<&Shadow> {
+        self.shadows.iter().find(|shadow| {
+            shadow.is_local()
+                && self
+                    .shadows
+                    .iter()
+                    .any(|other| other.table() == shadow.target())
+        })
+    }
+    /// Returns true if a table has two or more throttles
+    pub fn has_duplicate_throttles(&self) -> bool {
+        let mut tables: Vec<&str> = self.throttles.iter().map(Throttle::table).collect();
+        let total = tables.len();
+        tables.sort_unstable();
+        tables.dedup();
+        tables.len() != total
+    }
+    /// Returns the first ALPN protocol in the config that the server doesn't speak, if any
+    pub fn unsupported_alpn(&self) -> Option<&str> {
+        let mut ssl = vec![self.ports.get_ssl()];
+        ssl.extend(self.listeners.iter().map(|l| l.ports.get_ssl()));
+        ssl.into_iter()
+            .flatten()
+            .flat_map(|ssl| ssl.alpn.iter())
+            .map(String::as_str)
+            .find(|protocol| !ALPN_PROTOCOLS.contains(protocol))
+    }
+    /// Returns true if the TLS policy of any listener leaves clients with nothing to negotiate
+    pub fn has_empty_tls_policy(&self) -> bool {
+        let mut ssl = vec![self.ports.get_ssl()];
+        ssl.extend(self.listeners.iter().map(|l| l.ports.get_ssl()));
+        ssl.into_iter()
+            .flatten()
+            .any(|ssl| ssl.policy.has_empty_list())
+    }
+    /// Returns true if two or more listeners attempt to bind to the same host and port
+    pub fn has_duplicate_bindings(&self) -> bool {
+        let mut bindings = self.ports.get_bindings();
+        self.listeners
+            .iter()
+            .for_each(|listener| bindings.extend(listener.ports.get_bindings()));
+        let total = bindings.len();
+        bindings.sort_unstable();
+        bindings.dedup();
+        bindings.len() != total
+    }
     #[cfg(test)]
     /// Create a new `ParsedConfig` from a `TOML` string
     pub fn new_from_toml_str(tomlstr: String) -> TResult<Self> {
@@ -358,7 +1068,35 @@ impl ParsedConfig {
             bgsave,
             snapshot,
             ports,
+            listeners: Vec::new(),
             maxcon,
+            mode: ServerMode::Normal,
+            idletimeout: 0,
+            keepalive: 0,
+            maxbuffer: DEFAULT_MAX_BUFFER,
+            deny: Vec::new(),
+            flushconfirm: false,
+            dropretention: 0,
+            idleunload: 0,
+            preload: Vec::new(),
+            socket: None,
+            recoverythreads: 0,
+            snapshotthreads: 0,
+            hasher: HashFunction::Sip,
+            hugepages: HugePageMode::Off,
+            minfreedisk: stall::DEFAULT_MIN_FREE,
+            users: Vec::new(),
+            origins: Vec::new(),
+            throttles: Vec::new(),
+            jitters: Vec::new(),
+            schemas: Vec::new(),
+            shadows: Vec::new(),
+            backgroundio: BackgroundIo::default(),
+            querylog: None,
+            adaptiveflush: None,
+            memory: None,
+            admission: None,
+            topology: Topology::new(0, Vec::new(), Vec::new()),
         }
     }
     /// Create a default `ParsedConfig` with the following setup defaults:
@@ -374,7 +1112,35 @@ impl ParsedConfig {
             bgsave: BGSave::default(),
             snapshot: SnapshotConfig::default(),
             ports: PortConfig::new_insecure_only(DEFAULT_IPV4, 2003),
+            listeners: Vec::new(),
             maxcon: MAXIMUM_CONNECTION_LIMIT,
+            mode: ServerMode::Normal,
+            idletimeout: 0,
+            keepalive: 0,
+            maxbuffer: DEFAULT_MAX_BUFFER,
+            deny: Vec::new(),
+            flushconfirm: false,
+            dropretention: 0,
+            idleunload: 0,
+            preload: Vec::new(),
+            socket: None,
+            recoverythreads: 0,
+            snapshotthreads: 0,
+            hasher: HashFunction::Sip,
+            hugepages: HugePageMode::Off,
+            minfreedisk: stall::DEFAULT_MIN_FREE,
+            users: Vec::new(),
+            origins: Vec::new(),
+            throttles: Vec::new(),
+            jitters: Vec::new(),
+            schemas: Vec::new(),
+            shadows: Vec::new(),
+            backgroundio: BackgroundIo::default(),
+            querylog: None,
+            adaptiveflush: None,
+            memory: None,
+            admission: None,
+            topology: Topology::new(0, Vec::new(), Vec::new()),
         }
     }
     /// Returns `false` if `noart` is enabled. Otherwise it returns `true`
@@ -389,8 +1155,16 @@ use clap::{load_yaml, App};
 /// - We either used a custom configuration file given to us by the user (`Custom`) OR
 /// - We used the default configuration (`Def`)
 pub enum ConfigType<T, U> {
-    Def(T, Option<U>),
-    Custom(T, Option<U>),
+    Def(T, U),
+    Custom(T, U),
+}
+
+/// The command line arguments that only apply to the current run of the server
+pub struct StartupArgs {
+    /// The snapshot to restore data from
+    pub restore: Option<String>,
+    /// Whether to take over the data directory from a process that crashed
+    pub takeover: bool,
 }
 
 #[derive(Debug)]
@@ -423,10 +1197,14 @@ impl fmt::Display for ConfigError {
 /// This parses a configuration file if it is supplied as a command line argument
 /// or it returns the default configuration. **If** the configuration file
 /// contains an error, then this returns it as an `Err` variant
-pub fn get_config_file_or_return_cfg() -> Result<ConfigType<ParsedConfig, String>, ConfigError> {
+pub fn get_config_file_or_return_cfg() -> Result<ConfigType<ParsedConfig, StartupArgs>, ConfigError>
+{
     let cfg_layout = load_yaml!("../cli.yml");
     let matches = App::from_yaml(cfg_layout).get_matches();
-    let restorefile = matches.value_of("restore").map(|v| v.to_string());
+    let startup = StartupArgs {
+        restore: matches.value_of("restore").map(|v| v.to_string()),
+        takeover: matches.is_present("takeover"),
+    };
     // Check flags
     let sslonly = matches.is_present("sslonly");
     let noart = matches.is_present("noart");
@@ -618,7 +1396,7 @@ pub fn get_config_file_or_return_cfg() -> Result<ConfigType<ParsedConfig, String
             }
         };
         let cfg = ParsedConfig::new(noart, bgsave, snapcfg, portcfg, maxcon);
-        return Ok(ConfigType::Custom(cfg, restorefile));
+        return Ok(ConfigType::Custom(cfg, startup));
     }
     if let Some(filename) = filename {
         match ParsedConfig::new_from_file(filename.to_owned()) {
@@ -627,9 +1405,25 @@ pub fn get_config_file_or_return_cfg() -> Result<ConfigType<ParsedConfig, String
                     log::warn!("BGSAVE is disabled: If this system crashes unexpectedly, it may lead to the loss of data");
                 }
                 if let SnapshotConfig::Enabled(e) = &cfg.snapshot {
-                    if e.every == 0 {
+                    if e.every == 0 && e.schedules.is_empty() {
+                        return Err(ConfigError::CfgError(
+                            "The snapshot duration has to be greater than 0 (unless there are snapshot schedules)!",
+                        ));
+                    }
+                    if let Some((schedule, e)) = e.invalid_schedule() {
+                        log::error!(
+                            "Bad cron expression `{}` for the snapshot schedule `{}`: {}",
+                            schedule.cron,
+                            schedule.name,
+                            e
+                        );
+                        return Err(ConfigError::CfgError(
+                            "A snapshot schedule has a bad cron expression",
+                        ));
+                    }
+                    if e.has_bad_schedule_names() {
                         return Err(ConfigError::CfgError(
-                            "The snapshot duration has to be greater than 0!",
+                            "Two or more snapshot schedules have the same name (or no name)",
                         ));
                     }
                 }
@@ -640,18 +1434,126 @@ pub fn get_config_file_or_return_cfg() -> Result<ConfigType<ParsedConfig, String
                         ));
                     }
                 }
-                Ok(ConfigType::Custom(cfg, restorefile))
+                if let Some(protocol) = cfg.unsupported_alpn() {
+                    log::error!("Unsupported ALPN protocol `{}`", protocol);
+                    return Err(ConfigError::CfgError(
+                        "The TLS settings have an ALPN protocol that isn't supported",
+                    ));
+                }
+                if cfg.has_empty_tls_policy() {
+                    return Err(ConfigError::CfgError(
+                        "A TLS policy has an empty list of ciphers or curves",
+                    ));
+                }
+                if let Some(origin) = cfg.unsupported_origin() {
+                    log::error!(
+                        "Unsupported URL `{}` for the origin of `{}`",
+                        origin.url(),
+                        origin.table()
+                    );
+                    return Err(ConfigError::CfgError(
+                        "An origin has a URL that isn't supported (only http:// is)",
+                    ));
+                }
+                if cfg.has_duplicate_origins() {
+                    return Err(ConfigError::CfgError("A table has two or more origins"));
+                }
+                if let Some(throttle) = cfg.blocked_throttle() {
+                    log::error!("The write rate of `{}` is 0", throttle.table());
+                    return Err(ConfigError::CfgError(
+                        "The write rate of a throttled table has to be greater than 0",
+                    ));
+                }
+                if cfg.has_duplicate_throttles() {
+                    return Err(ConfigError::CfgError("A table has two or more throttles"));
+                }
+                if let Some(jitter) = cfg.bad_jitter() {
+                    log::error!("The jitter of `{}` is out of range", jitter.table());
+                    return Err(ConfigError::CfgError(
+                        "The jitter of a table has to be between 1 and 100 percent",
+                    ));
+                }
+                if cfg.has_duplicate_jitters() {
+                    return Err(ConfigError::CfgError("A table has two or more jitters"));
+                }
+                if cfg.has_duplicate_schemas() {
+                    return Err(ConfigError::CfgError("A table has two or more schemas"));
+                }
+                if cfg.has_duplicate_shadows() {
+                    return Err(ConfigError::CfgError("A table has two or more shadows"));
+                }
+                if let Some(shadow) = cfg.bad_shadow() {
+                    log::error!("The shadow of `{}` has a shadow itself", shadow.table());
+                    return Err(ConfigError::CfgError(
+                        "The shadow of a table can't have a shadow itself",
+                    ));
+                }
+                if cfg.backgroundio.has_zero_rate() {
+                    return Err(ConfigError::CfgError(
+                        "The rate of background I/O has to be greater than 0",
+                    ));
+                }
+                if matches!(&cfg.querylog, Some(querylog) if !querylog.has_valid_sample()) {
+                    return Err(ConfigError::CfgError(
+                        "The query log sample has to be a percentage between 0 and 100",
+                    ));
+                }
+                if matches!(
+                    (&cfg.adaptiveflush, &cfg.bgsave),
+                    (Some(policy), BGSave::Enabled(every)) if !policy.has_valid_interval(*every)
+                ) {
+                    return Err(ConfigError::CfgError(
+                        "The shortest adaptive flush interval has to be between 1 and `every` seconds",
+                    ));
+                }
+                if matches!(&cfg.memory, Some(memory) if !memory.has_valid_thresholds()) {
+                    return Err(ConfigError::CfgError(
+                        "The memory limit has to be set, with thresholds in order and at most 100",
+                    ));
+                }
+                if matches!(&cfg.admission, Some(admission) if !admission.has_limits()) {
+                    return Err(ConfigError::CfgError(
+                        "Admission control needs at least one of `maxqueue` and `maxlag`",
+                    ));
+                }
+                if let Some(core) = cfg.topology.missing_core(num_cpus::get()) {
+                    log::error!(
+                        "Threads are pinned to core {}, which this host doesn't have",
+                        core
+                    );
+                    return Err(ConfigError::CfgError(
+                        "Threads are pinned to a core that doesn't exist",
+                    ));
+                }
+                if cfg.topology.has_conflicting_pins() {
+                    return Err(ConfigError::CfgError(
+                        "The worker threads can't be pinned to both cores and NUMA nodes",
+                    ));
+                }
+                if cfg.topology.has_shared_core() {
+                    log::warn!("The worker threads share cores with the background threads");
+                }
+                if cfg.hugepages != HugePageMode::Off && !hugepages::is_supported() {
+                    log::warn!("Huge pages aren't supported on this platform and will be ignored");
+                }
+                if cfg.has_duplicate_bindings() {
+                    return Err(ConfigError::CfgError(
+                        "Two or more listeners are bound to the same host and port",
+                    ));
+                }
+                Ok(ConfigType::Custom(cfg, startup))
             }
             Err(e) => Err(e),
         }
     } else {
-        Ok(ConfigType::Def(ParsedConfig::default(), restorefile))
+        Ok(ConfigType::Def(ParsedConfig::default(), startup))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::registry::tls::TlsVersion;
     #[test]
     fn test_config_toml_okayport() {
         let file = r#"
@@ -722,7 +1624,35 @@ mod tests {
                 bgsave: BGSave::default(),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
-                maxcon: MAXIMUM_CONNECTION_LIMIT
+                listeners: Vec::new(),
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
             }
         );
     }
@@ -741,7 +1671,35 @@ mod tests {
                     IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0x1)),
                     DEFAULT_PORT
                 ),
-                maxcon: MAXIMUM_CONNECTION_LIMIT
+                listeners: Vec::new(),
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
             }
         );
     }
@@ -788,7 +1746,35 @@ mod tests {
                 bgsave: BGSave::new(true, 600),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
-                maxcon: MAXIMUM_CONNECTION_LIMIT
+                listeners: Vec::new(),
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
             }
         );
     }
@@ -808,7 +1794,35 @@ mod tests {
                 bgsave: BGSave::default(),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
-                maxcon: MAXIMUM_CONNECTION_LIMIT
+                listeners: Vec::new(),
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
             }
         )
     }
@@ -828,7 +1842,35 @@ mod tests {
                 bgsave: BGSave::new(true, 600),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
-                maxcon: MAXIMUM_CONNECTION_LIMIT
+                listeners: Vec::new(),
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
             }
         )
     }
@@ -844,8 +1886,818 @@ mod tests {
                 bgsave: BGSave::default(),
                 noart: false,
                 ports: PortConfig::default(),
-                maxcon: MAXIMUM_CONNECTION_LIMIT
+                listeners: Vec::new(),
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_file_multiple_listeners() {
+        let file = get_toml_from_examples_dir("multilistener.toml".to_owned()).unwrap();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg,
+            ParsedConfig {
+                noart: false,
+                bgsave: BGSave::default(),
+                snapshot: SnapshotConfig::default(),
+                ports: PortConfig::default(),
+                listeners: vec![
+                    ListenerConfig::new(
+                        PortConfig::new_insecure_only(
+                            IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)),
+                            DEFAULT_PORT
+                        ),
+                        false
+                    ),
+                    ListenerConfig::new(
+                        PortConfig::new_secure_only(
+                            IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)),
+                            SslOpts::new(
+                                "/path/to/keyfile.pem".into(),
+                                "/path/to/chain.pem".into(),
+                                2004,
+                                None
+                            )
+                        ),
+                        false
+                    ),
+                    ListenerConfig::new(PortConfig::new_insecure_only(DEFAULT_IPV4, 2005), true),
+                ],
+                maxcon: MAXIMUM_CONNECTION_LIMIT,
+                mode: ServerMode::Normal,
+                idletimeout: 0,
+                keepalive: 0,
+                maxbuffer: DEFAULT_MAX_BUFFER,
+                deny: Vec::new(),
+                flushconfirm: false,
+                dropretention: 0,
+                idleunload: 0,
+                preload: Vec::new(),
+                socket: None,
+                recoverythreads: 0,
+                snapshotthreads: 0,
+                hasher: HashFunction::Sip,
+                hugepages: HugePageMode::Off,
+                minfreedisk: stall::DEFAULT_MIN_FREE,
+                users: Vec::new(),
+                origins: Vec::new(),
+                throttles: Vec::new(),
+                jitters: Vec::new(),
+                schemas: Vec::new(),
+                shadows: Vec::new(),
+                backgroundio: BackgroundIo::default(),
+                querylog: None,
+                adaptiveflush: None,
+                memory: None,
+                admission: None,
+                topology: Topology::default()
             }
         );
+        assert!(!cfg.has_duplicate_bindings());
+    }
+
+    #[test]
+    fn test_config_duplicate_listeners() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[listener]]
+        host = "127.0.0.1"
+        port = 2003
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert!(cfg.has_duplicate_bindings());
+    }
+
+    #[test]
+    fn test_config_server_mode() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        mode = "readonly"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.mode, ServerMode::ReadOnly);
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        mode = "sleeping"
+    "#
+        .to_owned();
+        assert!(ParsedConfig::new_from_toml_str(file).is_err());
+    }
+
+    #[test]
+    fn test_config_timeouts() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        idletimeout = 300
+        keepalive = 60
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.idletimeout, 300);
+        assert_eq!(cfg.keepalive, 60);
+    }
+
+    #[test]
+    fn test_config_maxbuffer() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        maxbuffer = 1048576
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.maxbuffer, 1048576);
+    }
+
+    #[test]
+    fn test_config_deny_actions() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        deny = ["FLUSHDB"]
+        [[listener]]
+        host = "127.0.0.1"
+        port = 2005
+        deny = ["DROP"]
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.deny, vec!["FLUSHDB".to_owned()]);
+        assert_eq!(
+            cfg.listeners[0].deny,
+            vec!["FLUSHDB".to_owned(), "DROP".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_config_flushconfirm() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        flushconfirm = true
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert!(cfg.flushconfirm);
+    }
+
+    #[test]
+    fn test_config_dropretention() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        dropretention = 600
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.dropretention, 600);
+    }
+
+    #[test]
+    fn test_config_idleunload() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        idleunload = 3600
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.idleunload, 3600);
+    }
+
+    #[test]
+    fn test_config_preload() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        preload = ["default:default", "app:sessions"]
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.preload, vec!["default:default", "app:sessions"]);
+    }
+
+    #[test]
+    fn test_config_socket() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        socket = "/tmp/skyd.sock"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.socket.as_deref(), Some("/tmp/skyd.sock"));
+        assert_eq!(ParsedConfig::default().socket, None);
+    }
+
+    #[test]
+    fn test_config_recoverythreads() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        recoverythreads = 4
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.recoverythreads, 4);
+        assert_eq!(ParsedConfig::default().recoverythreads, 0);
+    }
+
+    #[test]
+    fn test_config_snapshotthreads() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        snapshotthreads = 2
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.snapshotthreads, 2);
+        assert_eq!(ParsedConfig::default().snapshotthreads, 0);
+    }
+
+    #[test]
+    fn test_config_hasher() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        hasher = "ahash"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.hasher, HashFunction::Ahash);
+        assert_eq!(ParsedConfig::default().hasher, HashFunction::Sip);
+    }
+
+    #[test]
+    fn test_config_hugepages() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        hugepages = "explicit"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.hugepages, HugePageMode::Explicit);
+        assert_eq!(ParsedConfig::default().hugepages, HugePageMode::Off);
+    }
+
+    #[test]
+    fn test_config_minfreedisk() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        minfreedisk = 0
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.minfreedisk, 0);
+        assert_eq!(ParsedConfig::default().minfreedisk, stall::DEFAULT_MIN_FREE);
+    }
+
+    #[test]
+    fn test_config_snapshot_schedules() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [snapshot]
+        atmost = 4
+        [[snapshot.schedule]]
+        name = "hourly"
+        cron = "0 * * * *"
+        [[snapshot.schedule]]
+        name = "nightly"
+        cron = "0 3 * * 8"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        let pref = match cfg.snapshot {
+            SnapshotConfig::Enabled(pref) => pref,
+            SnapshotConfig::Disabled => panic!("snapshots should be enabled"),
+        };
+        assert_eq!(pref.every, 0);
+        assert_eq!(
+            pref.schedules,
+            vec![
+                SnapshotSchedule::new("hourly".to_owned(), "0 * * * *".to_owned()),
+                SnapshotSchedule::new("nightly".to_owned(), "0 3 * * 8".to_owned()),
+            ]
+        );
+        let (schedule, _) = pref.invalid_schedule().unwrap();
+        assert_eq!(schedule.name, "nightly");
+        assert!(!pref.has_bad_schedule_names());
+    }
+
+    #[test]
+    fn test_config_users() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[user]]
+        name = "root"
+        token = "rootsecret"
+        [[user]]
+        name = "acme"
+        token = "acmesecret"
+        keyspaces = ["acme"]
+        maxconnections = 10
+        opsrate = 1000
+        priority = "batch"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.users,
+            vec![
+                User::new("root".to_owned(), "rootsecret".to_owned(), None),
+                User::new(
+                    "acme".to_owned(),
+                    "acmesecret".to_owned(),
+                    Some(vec!["acme".to_owned()])
+                )
+                .with_limits(10, 1000)
+                .with_priority(Some(Priority::Batch)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_listener_priority() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[listener]]
+        host = "127.0.0.1"
+        port = 2004
+        priority = "batch"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.listeners.len(), 1);
+        assert_eq!(cfg.listeners[0].priority, Some(Priority::Batch));
+        assert!(!cfg.listeners[0].admin);
+    }
+
+    #[test]
+    fn test_config_tls_session_opts() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [ssl]
+        key = "/path/to/keyfile.pem"
+        chain = "/path/to/chain.pem"
+        port = 2004
+        alpn = ["skyhash"]
+        [[listener]]
+        host = "127.0.0.1"
+        port = 2005
+        tls = { key = "/path/to/keyfile.pem", chain = "/path/to/chain.pem", resumption = false, alpn = ["h2"] }
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        let ssl = cfg.ports.get_ssl().unwrap();
+        assert!(ssl.resumption);
+        assert_eq!(ssl.alpn, vec!["skyhash".to_owned()]);
+        let ssl = cfg.listeners[0].ports.get_ssl().unwrap();
+        assert!(!ssl.resumption);
+        assert_eq!(cfg.unsupported_alpn(), Some("h2"));
+    }
+
+    #[test]
+    fn test_config_tls_policy() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [ssl]
+        key = "/path/to/keyfile.pem"
+        chain = "/path/to/chain.pem"
+        port = 2004
+        [ssl.policy]
+        min_version = "1.3"
+        ciphersuites = "TLS_AES_256_GCM_SHA384"
+        curves = ["X25519", "P-256"]
+        [[listener]]
+        host = "127.0.0.1"
+        port = 2005
+        tls = { key = "/path/to/keyfile.pem", chain = "/path/to/chain.pem" }
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        let policy = &cfg.ports.get_ssl().unwrap().policy;
+        assert_eq!(policy.min_version, Some(TlsVersion::Tls13));
+        assert_eq!(policy.ciphers, None);
+        assert_eq!(
+            policy.ciphersuites.as_deref(),
+            Some("TLS_AES_256_GCM_SHA384")
+        );
+        assert_eq!(
+            policy.curves,
+            Some(vec!["X25519".to_owned(), "P-256".to_owned()])
+        );
+        assert!(!cfg.has_empty_tls_policy());
+        // listeners that don't set a policy are left to the defaults
+        let policy = &cfg.listeners[0].ports.get_ssl().unwrap().policy;
+        assert_eq!(policy, &TlsPolicy::default());
+    }
+
+    #[test]
+    fn test_config_origins() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[origin]]
+        table = "cache:users"
+        url = "http://localhost:8080/users"
+        [[origin]]
+        table = "cache:events"
+        url = "ftp://localhost/events"
+        writethrough = false
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.origins,
+            vec![
+                Origin::new(
+                    "cache:users".to_owned(),
+                    "http://localhost:8080/users".to_owned(),
+                    true,
+                    true
+                ),
+                Origin::new(
+                    "cache:events".to_owned(),
+                    "ftp://localhost/events".to_owned(),
+                    true,
+                    false
+                ),
+            ]
+        );
+        assert_eq!(cfg.unsupported_origin().unwrap().table(), "cache:events");
+        assert!(!cfg.has_duplicate_origins());
+    }
+
+    #[test]
+    fn test_config_throttles() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[throttle]]
+        table = "logs:events"
+        writerate = 500
+        [[throttle]]
+        table = "logs:audit"
+        writerate = 0
+        mode = "reject"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.throttles,
+            vec![
+                Throttle::new("logs:events".to_owned(), 500, ThrottleMode::Delay),
+                Throttle::new("logs:audit".to_owned(), 0, ThrottleMode::Reject),
+            ]
+        );
+        assert_eq!(cfg.blocked_throttle().unwrap().table(), "logs:audit");
+        assert!(!cfg.has_duplicate_throttles());
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[throttle]]
+        table = "logs:events"
+        writerate = 500
+        mode = "drop"
+    "#
+        .to_owned();
+        assert!(ParsedConfig::new_from_toml_str(file).is_err());
+    }
+
+    #[test]
+    fn test_config_jitters() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[jitter]]
+        table = "cache:sessions"
+        percent = 10
+        [[jitter]]
+        table = "cache:pages"
+        percent = 0
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.jitters,
+            vec![
+                Jitter::new("cache:sessions".to_owned(), 10),
+                Jitter::new("cache:pages".to_owned(), 0),
+            ]
+        );
+        assert_eq!(cfg.bad_jitter().unwrap().table(), "cache:pages");
+        assert!(!cfg.has_duplicate_jitters());
+    }
+
+    #[test]
+    fn test_config_schemas() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[schema]]
+        table = "app:users"
+        file = "schemas/user.json"
+        [[schema]]
+        table = "app:users"
+        file = "schemas/user-v2.json"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.schemas,
+            vec![
+                SchemaFile::new("app:users".to_owned(), "schemas/user.json".to_owned()),
+                SchemaFile::new("app:users".to_owned(), "schemas/user-v2.json".to_owned()),
+            ]
+        );
+        assert!(cfg.has_duplicate_schemas());
+    }
+
+    #[test]
+    fn test_config_shadows() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [[shadow]]
+        table = "app:users"
+        target = "app:users_v2"
+        [[shadow]]
+        table = "app:orders"
+        target = "app:orders"
+        host = "10.0.0.2:2003"
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.shadows,
+            vec![
+                Shadow::new("app:users".to_owned(), "app:users_v2".to_owned(), None),
+                Shadow::new(
+                    "app:orders".to_owned(),
+                    "app:orders".to_owned(),
+                    Some("10.0.0.2:2003".to_owned())
+                ),
+            ]
+        );
+        assert!(!cfg.has_duplicate_shadows());
+        // the remote shadow has the same name, but it's on another node
+        assert!(cfg.bad_shadow().is_none());
+    }
+
+    #[test]
+    fn test_config_backgroundio() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [backgroundio]
+        snapshot = { rate = 64, priority = "idle" }
+        compaction = { rate = 0 }
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.backgroundio,
+            BackgroundIo {
+                snapshot: IoLimit::new(Some(64), IoPriority::Idle),
+                export: IoLimit::default(),
+                compaction: IoLimit::new(Some(0), IoPriority::Normal),
+            }
+        );
+        assert!(cfg.backgroundio.has_zero_rate());
+    }
+
+    #[test]
+    fn test_config_querylog() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [querylog]
+        sample = 0.5
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.querylog,
+            Some(QueryLog::new(
+                querylog::DEFAULT_PATH.to_owned(),
+                0.5,
+                querylog::DEFAULT_MAX_SIZE,
+                querylog::DEFAULT_KEEP
+            ))
+        );
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [querylog]
+        sample = 150
+        path = "/var/log/skyd/query.log"
+        maxsize = 1048576
+        keep = 0
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.querylog,
+            Some(QueryLog::new(
+                "/var/log/skyd/query.log".to_owned(),
+                150.0,
+                1048576,
+                0
+            ))
+        );
+        assert!(!cfg.querylog.unwrap().has_valid_sample());
+    }
+
+    #[test]
+    fn test_config_adaptive_flush() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [bgsave]
+        every = 120
+        adaptive = true
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.adaptiveflush,
+            Some(AdaptiveFlush::new(
+                bgsave::DEFAULT_MIN,
+                bgsave::DEFAULT_HOT_RATE,
+                0
+            ))
+        );
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [bgsave]
+        every = 60
+        adaptive = true
+        min = 60
+        hotrate = 10
+        iobudget = 1048576
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        let policy = cfg.adaptiveflush.unwrap();
+        assert_eq!(policy, AdaptiveFlush::new(60, 10, 1048576));
+        assert!(!policy.has_valid_interval(60));
+        assert!(policy.has_valid_interval(120));
+        assert_eq!(ParsedConfig::default().adaptiveflush, None);
+    }
+
+    #[test]
+    fn test_config_memory() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [memory]
+        limit = 1073741824
+        evict = 85
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        let memory = cfg.memory.unwrap();
+        assert_eq!(
+            memory,
+            MemoryPolicy::new(
+                1073741824,
+                pressure::DEFAULT_CONNECTIONS,
+                85,
+                pressure::DEFAULT_WRITES
+            )
+        );
+        assert!(memory.has_valid_thresholds());
+        assert_eq!(ParsedConfig::default().memory, None);
+    }
+
+    #[test]
+    fn test_config_admission() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [admission]
+        maxlag = 50
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        let admission = cfg.admission.unwrap();
+        assert_eq!(admission, AdmissionPolicy::new(0, 50));
+        assert!(admission.has_limits());
+        assert_eq!(ParsedConfig::default().admission, None);
+    }
+
+    #[test]
+    fn test_config_topology() {
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [topology]
+        workers = 2
+        workercores = [0, 1]
+        backgroundcores = [2]
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.topology, Topology::new(2, vec![0, 1], vec![2]));
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+        [topology]
+        numa = true
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(
+            cfg.topology,
+            Topology::new(0, Vec::new(), Vec::new()).with_numa(true)
+        );
+        let file = r#"
+        [server]
+        host = "127.0.0.1"
+        port = 2003
+    "#
+        .to_owned();
+        let cfg = ParsedConfig::new_from_toml_str(file).unwrap();
+        assert_eq!(cfg.topology, Topology::default());
     }
 }
@@ -0,0 +1,376 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Protocol conformance vectors
+//!
+//! With the `conformance` feature, client authors can check their implementation of the
+//! Skyhash protocol against the server itself. Every [`Case`] is a query (and the packet
+//! that it must be serialized into) along with the exact response packet that the server
+//! sends back and the typed [`Element`] that a client must decode it into. The cases cover
+//! every kind of element that a simple query can return, error codes and error strings, and
+//! malformed packets.
+//!
+//! The response packets aren't written by hand: [`cases`] runs every query through the
+//! server's own parser and query engine (on an in-memory store, like
+//! [`Embedded`](crate::embedded::Embedded) does) and records what was written back, so the
+//! vectors can't drift from what the server actually does. A client is checked by
+//! implementing [`Client`] and passing it to [`check`]:
+//! ```ignore
+//! let failures = skyd::conformance::check(&MyClient, &skyd::conformance::cases().await);
+//! assert!(failures.is_empty(), "{:?}", failures);
+//! ```
+//!
+//! The cases are run one after the other against the same store, so later cases can depend
+//! on earlier ones (like `get` reading the key written by `set`)
+
+use crate::corestore::memstore::Memstore;
+use crate::corestore::Corestore;
+use crate::storage::sengine::SnapshotEngine;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A typed element of a response, as a client must decode it
+pub enum Element {
+    /// A response code; tsymbol: `!` (like `!1\n0\n` for `Okay`)
+    RespCode(u8),
+    /// An error string; tsymbol: `!` (like `!14\nUnknown action\n`)
+    ErrorString(String),
+    /// A unicode string; tsymbol: `+`
+    String(String),
+    /// A binary string; tsymbol: `?`
+    Binary(Vec<u8>),
+    /// An unsigned integer; tsymbol: `:`
+    UnsignedInt(u64),
+    /// A typed array of unicode strings, in which elements can be null; tsymbol: `@+`
+    StrArray(Vec<Option<String>>),
+    /// A typed array of binary strings, in which elements can be null; tsymbol: `@?`
+    BinArray(Vec<Option<Vec<u8>>>),
+    /// An array of (possibly nested) elements; tsymbol: `&`
+    Array(Vec<Element>),
+}
+
+impl Element {
+    /// Serialize this element into `buf`, exactly as the server would
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::RespCode(code) => encode_sized(buf, b"!", code.to_string().as_bytes()),
+            Self::ErrorString(e) => encode_sized(buf, b"!", e.as_bytes()),
+            Self::String(s) => encode_sized(buf, b"+", s.as_bytes()),
+            Self::Binary(b) => encode_sized(buf, b"?", b),
+            Self::UnsignedInt(int) => encode_sized(buf, b":", int.to_string().as_bytes()),
+            Self::StrArray(elements) => {
+                let elements = elements.iter().map(|e| e.as_ref().map(String::as_bytes));
+                encode_typed_array(buf, b"@+", elements);
+            }
+            Self::BinArray(elements) => {
+                let elements = elements.iter().map(|e| e.as_deref());
+                encode_typed_array(buf, b"@?", elements);
+            }
+            Self::Array(elements) => {
+                encode_header(buf, b"&", elements.len());
+                elements.iter().for_each(|element| element.encode_into(buf));
+            }
+        }
+    }
+    /// Serialize this element into a complete response packet (`*1\n` followed by the
+    /// element)
+    pub fn encode_response(&self) -> Vec<u8> {
+        let mut buf = b"*1\n".to_vec();
+        self.encode_into(&mut buf);
+        buf
+    }
+}
+
+/// Write `<tsymbol><len>\n`
+fn encode_header(buf: &mut Vec<u8>, tsymbol: &[u8], len: usize) {
+    buf.extend_from_slice(tsymbol);
+    buf.extend_from_slice(len.to_string().as_bytes());
+    buf.push(b'\n');
+}
+
+/// Write `<tsymbol><len>\n<payload>\n`
+fn encode_sized(buf: &mut Vec<u8>, tsymbol: &[u8], payload: &[u8]) {
+    encode_header(buf, tsymbol, payload.len());
+    buf.extend_from_slice(payload);
+    buf.push(b'\n');
+}
+
+/// Write `<tsymbol><len>\n` followed by `<len>\n<payload>\n` for every element (or `\0\n`
+/// for a null)
+fn encode_typed_array<'a>(
+    buf: &mut Vec<u8>,
+    tsymbol: &[u8],
+    elements: impl ExactSizeIterator<Item = Option<&'a [u8]>>,
+) {
+    encode_header(buf, tsymbol, elements.len());
+    for element in elements {
+        match element {
+            Some(payload) => encode_sized(buf, b"", payload),
+            None => buf.extend_from_slice(b"\0\n"),
+        }
+    }
+}
+
+/// Serialize a query with the given arguments into a packet, exactly as a client must:
+/// `*1\n~<number of arguments>\n` followed by `<len>\n<argument>\n` for every argument
+pub fn encode_query<T: AsRef<[u8]>>(args: &[T]) -> Vec<u8> {
+    let mut buf = b"*1\n".to_vec();
+    encode_header(&mut buf, b"~", args.len());
+    for arg in args {
+        encode_sized(&mut buf, b"", arg.as_ref());
+    }
+    buf
+}
+
+#[derive(Debug)]
+/// A query, the response that the server sends for it and what it must be decoded into
+pub struct Case {
+    /// A short name for the case (like `get-missing`)
+    pub name: &'static str,
+    /// The arguments of the query, or `None` if the request is a malformed (or unusual)
+    /// packet that a client wouldn't send
+    pub args: Option<Vec<Vec<u8>>>,
+    /// The request packet
+    pub request: Vec<u8>,
+    /// The response packet, as written by the server
+    pub response: Vec<u8>,
+    /// The element that the response must be decoded into
+    pub expected: Element,
+}
+
+/// What is sent for a case
+enum Request {
+    /// a query with these arguments
+    Query(Vec<Vec<u8>>),
+    /// a raw packet
+    Raw(&'static [u8]),
+}
+
+fn query(args: &[&[u8]]) -> Request {
+    Request::Query(args.iter().map(|arg| arg.to_vec()).collect())
+}
+
+/// The cases, in the order in which they're run
+fn specs() -> Vec<(&'static str, Request, Element)> {
+    vec![
+        (
+            "heya",
+            query(&[b"HEYA"]),
+            Element::String("HEY!".to_owned()),
+        ),
+        (
+            "heya-echo",
+            query(&[b"HEYA", b"sayan"]),
+            Element::String("sayan".to_owned()),
+        ),
+        ("set", query(&[b"SET", b"x", b"100"]), Element::RespCode(0)),
+        (
+            "set-existing",
+            query(&[b"SET", b"x", b"200"]),
+            Element::RespCode(2),
+        ),
+        (
+            "get",
+            query(&[b"GET", b"x"]),
+            Element::Binary(b"100".to_vec()),
+        ),
+        ("get-missing", query(&[b"GET", b"y"]), Element::RespCode(1)),
+        (
+            "set-binary",
+            query(&[b"SET", b"bin", b"\xFF\x00\n"]),
+            Element::RespCode(0),
+        ),
+        (
+            "get-binary",
+            query(&[b"GET", b"bin"]),
+            Element::Binary(b"\xFF\x00\n".to_vec()),
+        ),
+        (
+            "mget",
+            query(&[b"MGET", b"x", b"y"]),
+            Element::BinArray(vec![Some(b"100".to_vec()), None]),
+        ),
+        ("del", query(&[b"DEL", b"x", b"y"]), Element::UnsignedInt(1)),
+        ("wrong-arity", query(&[b"GET"]), Element::RespCode(3)),
+        (
+            "unknown-action",
+            query(&[b"NOPE"]),
+            Element::ErrorString("Unknown action".to_owned()),
+        ),
+        (
+            "wrong-type",
+            query(&[b"SETV", b"x", b"one", b"100"]),
+            Element::RespCode(7),
+        ),
+        (
+            "error-string",
+            query(&[b"MERGE", b"x", b"1"]),
+            Element::ErrorString("err-no-merge-operator".to_owned()),
+        ),
+        (
+            "incomplete-packet",
+            Request::Raw(b"*1\n~1\n"),
+            Element::RespCode(4),
+        ),
+        (
+            "not-an-array",
+            Request::Raw(b"*1\n+4\nHEYA\n"),
+            Element::RespCode(7),
+        ),
+        (
+            "unknown-data-type",
+            Request::Raw(b"*1\n#1\nx\n"),
+            Element::RespCode(8),
+        ),
+    ]
+}
+
+/// Run every case through the server's parser and query engine on a fresh in-memory
+/// store, returning the cases along with the responses that were written
+pub async fn cases() -> Vec<Case> {
+    let mut db = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    let mut cases = Vec::new();
+    for (name, request, expected) in specs() {
+        let (args, request) = match request {
+            Request::Query(args) => {
+                let packet = encode_query(&args);
+                (Some(args), packet)
+            }
+            Request::Raw(packet) => (None, packet.to_vec()),
+        };
+        let response = match crate::dbnet::memory::execute(&mut db, &request).await {
+            Ok(response) => response,
+            Err(e) => panic!("failed to run conformance case `{}`: {}", name, e),
+        };
+        cases.push(Case {
+            name,
+            args,
+            request,
+            response,
+            expected,
+        });
+    }
+    cases
+}
+
+/// A client implementation of the protocol that can be checked with [`check`]
+pub trait Client {
+    /// Serialize a query with the given arguments into a packet
+    fn encode_query(&self, args: &[Vec<u8>]) -> Vec<u8>;
+    /// Deserialize a response packet that holds one element, returning `None` if the
+    /// client can't make sense of it
+    fn decode_response(&self, response: &[u8]) -> Option<Element>;
+}
+
+#[derive(Debug)]
+/// A case that a client got wrong
+pub struct Failure {
+    /// The name of the case
+    pub case: &'static str,
+    /// What went wrong
+    pub reason: String,
+}
+
+/// Check a client against the cases, returning the ones that it got wrong
+pub fn check(client: &impl Client, cases: &[Case]) -> Vec<Failure> {
+    let mut failures = Vec::new();
+    for case in cases {
+        if let Some(args) = &case.args {
+            let packet = client.encode_query(args);
+            if packet != case.request {
+                failures.push(Failure {
+                    case: case.name,
+                    reason: format!(
+                        "encoded the query as {:?} instead of {:?}",
+                        String::from_utf8_lossy(&packet),
+                        String::from_utf8_lossy(&case.request)
+                    ),
+                });
+            }
+        }
+        match client.decode_response(&case.response) {
+            Some(element) if element == case.expected => {}
+            decoded => failures.push(Failure {
+                case: case.name,
+                reason: format!(
+                    "decoded the response as {:?} instead of {:?}",
+                    decoded, case.expected
+                ),
+            }),
+        }
+    }
+    failures
+}
+
+#[tokio::test]
+async fn test_cases_match_server() {
+    use crate::protocol::{Element as QueryElement, Parser, Query};
+    for case in cases().await {
+        // the server wrote exactly what the case says that it must be decoded into
+        assert_eq!(
+            case.response,
+            case.expected.encode_response(),
+            "case `{}`",
+            case.name
+        );
+        // and the request is what the server's parser understands as the arguments
+        if let Some(args) = case.args {
+            let (query, _) = Parser::new_strict(&case.request).parse().unwrap();
+            let args = args.into_iter().map(Into::into).collect();
+            assert_eq!(
+                query,
+                Query::SimpleQuery(QueryElement::AnyArray(args)),
+                "case `{}`",
+                case.name
+            );
+        }
+    }
+}
+
+#[test]
+fn test_encode_query() {
+    assert_eq!(
+        encode_query(&["SET", "x", "100"]),
+        b"*1\n~3\n3\nSET\n1\nx\n3\n100\n"
+    );
+    assert_eq!(encode_query::<&str>(&[]), b"*1\n~0\n");
+}
+
+#[test]
+fn test_encode_elements() {
+    assert_eq!(Element::RespCode(0).encode_response(), b"*1\n!1\n0\n");
+    assert_eq!(
+        Element::StrArray(vec![Some("a".to_owned()), None]).encode_response(),
+        b"*1\n@+2\n1\na\n\0\n"
+    );
+    assert_eq!(
+        Element::Array(vec![Element::UnsignedInt(12), Element::Binary(vec![0])]).encode_response(),
+        b"*1\n&2\n:2\n12\n?1\n\0\n"
+    );
+}
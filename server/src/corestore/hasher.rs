@@ -0,0 +1,159 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Coremap hashers
+//!
+//! Coremaps hash their keys with either:
+//! - SipHash-1-3 keyed with random keys picked when the server starts (the default), which
+//! makes it impossible for clients to pick keys that all land in the same buckets (and flood
+//! a table with collisions), or
+//! - aHash, which is faster but is only as hard to flood as its seeds are hard to guess
+//!
+//! The hash function is picked with the `hasher` key in the config file, and applies to every
+//! coremap created after that (which is every table, since the config is applied before the
+//! tables are restored)
+
+use ahash::{AHasher, RandomState as AhashState};
+use core::hash::{BuildHasher, Hasher};
+use core::sync::atomic::{AtomicU8, Ordering};
+use serde::Deserialize;
+use std::collections::hash_map::{DefaultHasher, RandomState as SipState};
+
+/// The hash function that coremaps use
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum HashFunction {
+    /// Keyed SipHash-1-3
+    Sip = 0,
+    /// aHash
+    Ahash = 1,
+}
+
+impl HashFunction {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            HashFunction::Sip => "sip",
+            HashFunction::Ahash => "ahash",
+        }
+    }
+}
+
+/// The hash function that new coremaps use
+static HASH_FUNCTION: AtomicU8 = AtomicU8::new(HashFunction::Sip as u8);
+
+/// Set the hash function that new coremaps use
+pub fn set_hash_function(function: HashFunction) {
+    HASH_FUNCTION.store(function as u8, Ordering::Release)
+}
+
+/// Get the hash function that new coremaps use
+pub fn get_hash_function() -> HashFunction {
+    match HASH_FUNCTION.load(Ordering::Acquire) {
+        1 => HashFunction::Ahash,
+        _ => HashFunction::Sip,
+    }
+}
+
+#[derive(Clone)]
+/// The state of a coremap's hasher, which is seeded when the coremap is created
+pub enum CoreState {
+    Sip(SipState),
+    Ahash(AhashState),
+}
+
+impl CoreState {
+    /// Returns the hash function that this state builds hashers for
+    pub const fn function(&self) -> HashFunction {
+        match self {
+            CoreState::Sip(_) => HashFunction::Sip,
+            CoreState::Ahash(_) => HashFunction::Ahash,
+        }
+    }
+}
+
+impl Default for CoreState {
+    fn default() -> Self {
+        match get_hash_function() {
+            HashFunction::Sip => CoreState::Sip(SipState::new()),
+            HashFunction::Ahash => CoreState::Ahash(AhashState::new()),
+        }
+    }
+}
+
+impl BuildHasher for CoreState {
+    type Hasher = CoreHasher;
+    fn build_hasher(&self) -> CoreHasher {
+        match self {
+            CoreState::Sip(state) => CoreHasher::Sip(state.build_hasher()),
+            CoreState::Ahash(state) => CoreHasher::Ahash(state.build_hasher()),
+        }
+    }
+}
+
+/// A hasher built by a [`CoreState`]
+pub enum CoreHasher {
+    Sip(DefaultHasher),
+    Ahash(AHasher),
+}
+
+impl Hasher for CoreHasher {
+    fn finish(&self) -> u64 {
+        match self {
+            CoreHasher::Sip(hasher) => hasher.finish(),
+            CoreHasher::Ahash(hasher) => hasher.finish(),
+        }
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            CoreHasher::Sip(hasher) => hasher.write(bytes),
+            CoreHasher::Ahash(hasher) => hasher.write(bytes),
+        }
+    }
+    fn write_usize(&mut self, i: usize) {
+        match self {
+            CoreHasher::Sip(hasher) => hasher.write_usize(i),
+            CoreHasher::Ahash(hasher) => hasher.write_usize(i),
+        }
+    }
+}
+
+#[test]
+fn test_core_state() {
+    let sip = CoreState::Sip(SipState::new());
+    let ahash = CoreState::Ahash(AhashState::new());
+    assert_eq!(sip.function(), HashFunction::Sip);
+    assert_eq!(ahash.function(), HashFunction::Ahash);
+    // the same state always hashes a key the same way
+    for state in [sip, ahash].iter() {
+        let hash = |key: &[u8]| {
+            let mut hasher = state.build_hasher();
+            hasher.write(key);
+            hasher.finish()
+        };
+        assert_eq!(hash(b"sayan"), hash(b"sayan"));
+        assert_ne!(hash(b"sayan"), hash(b"nandan"));
+    }
+}
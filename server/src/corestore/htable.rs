@@ -24,19 +24,19 @@
  *
 */
 
+use crate::corestore::hasher::{CoreState, HashFunction};
 use crate::corestore::map::{
-    bref::{Entry, OccupiedEntry, Ref, VacantEntry},
-    iter::{BorrowedIter, OwnedIter},
+    bref::{Entry, OccupiedEntry, Ref, RefMut, VacantEntry},
+    iter::{BorrowedIter, BorrowedIterMut, OwnedIter},
     Skymap,
 };
-use ahash::RandomState;
 use bytes::Bytes;
 use std::borrow::Borrow;
 use std::hash::Hash;
 use std::iter::FromIterator;
 use std::ops::Deref;
 
-type HashTable<K, V> = Skymap<K, V, RandomState>;
+type HashTable<K, V> = Skymap<K, V, CoreState>;
 
 #[derive(Debug)]
 /// The Coremap contains the actual key/value pairs along with additional fields for data safety
@@ -51,7 +51,7 @@ where
 impl<K: Eq + Hash, V> Default for Coremap<K, V> {
     fn default() -> Self {
         Coremap {
-            inner: HashTable::new_ahash(),
+            inner: HashTable::new(),
         }
     }
 }
@@ -104,10 +104,29 @@ where
     pub fn clear(&self) {
         self.inner.clear()
     }
+    /// Replace all the key/value pairs with the ones in `other`, in one go
+    pub fn replace_all(&self, other: Coremap<K, V>) {
+        self.inner.replace_all(other)
+    }
+    /// Returns the hash function that this coremap uses
+    pub fn hash_function(&self) -> HashFunction {
+        self.inner.hasher().function()
+    }
+    /// Returns the number of key value pairs in each stripe, which shows how evenly the
+    /// keys are spread
+    pub fn stripe_lens(&self) -> Vec<usize> {
+        self.inner.shard_lens()
+    }
     /// Return a non-consuming iterator
-    pub fn iter(&self) -> BorrowedIter<'_, K, V, RandomState> {
+    pub fn iter(&self) -> BorrowedIter<'_, K, V, CoreState> {
         self.inner.get_iter()
     }
+    /// Return a non-consuming iterator that allows mutating values in place
+    ///
+    /// This write-locks one shard at a time
+    pub fn iter_mut(&self) -> BorrowedIterMut<'_, K, V, CoreState> {
+        self.inner.get_iter_mut()
+    }
     /// Get a reference to the value of a key, if it exists
     pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, K, V>>
     where
@@ -116,6 +135,14 @@ where
     {
         self.inner.get(key)
     }
+    /// Get a mutable reference to the value of a key, if it exists
+    pub fn get_mut<Q>(&self, key: &Q) -> Option<RefMut<'_, K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.inner.get_mut(key)
+    }
     /// Returns true if the non-existent key was assigned to a value
     pub fn true_if_insert(&self, k: K, v: V) -> bool {
         if let Entry::Vacant(ve) = self.inner.entry(k) {
@@ -152,14 +179,18 @@ where
             false
         }
     }
-    pub fn mut_entry(&self, key: K) -> Option<OccupiedEntry<K, V, RandomState>> {
+    pub fn mut_entry(&self, key: K) -> Option<OccupiedEntry<K, V, CoreState>> {
         if let Entry::Occupied(oe) = self.inner.entry(key) {
             Some(oe)
         } else {
             None
         }
     }
-    pub fn fresh_entry(&self, key: K) -> Option<VacantEntry<K, V, RandomState>> {
+    /// Get the entry for `key`. The shard's write lock is held until the entry is dropped
+    pub fn entry(&self, key: K) -> Entry<K, V, CoreState> {
+        self.inner.entry(key)
+    }
+    pub fn fresh_entry(&self, key: K) -> Option<VacantEntry<K, V, CoreState>> {
         if let Entry::Vacant(ve) = self.inner.entry(key) {
             Some(ve)
         } else {
@@ -179,20 +210,88 @@ impl<K: Eq + Hash, V: Clone> Coremap<K, V> {
 }
 
 impl Coremap<Data, Data> {
-    /// Returns atleast `count` number of keys from the hashtable
+    /// Returns atmost `count` keys of the hashtable, which are read a stripe at a time (so
+    /// writers are only ever held off one stripe)
     pub fn get_keys(&self, count: usize) -> Vec<Bytes> {
-        let mut v = Vec::with_capacity(count);
-        self.iter()
-            .take(count)
-            .map(|kv| kv.key().get_blob().clone())
-            .for_each(|key| v.push(key));
+        let mut v = Vec::with_capacity(count.min(self.len()));
+        for shard in 0..self.inner.shard_count() {
+            if v.len() == count {
+                break;
+            }
+            self.inner.for_each_in_shard(shard, |k, _| {
+                if v.len() == count {
+                    return false;
+                }
+                v.push(k.get_blob().clone());
+                true
+            });
+        }
+        v
+    }
+    /// Read a byte from every page of every key and value, so that they're faulted into
+    /// memory, returning the number of bytes held by the keys and values. Like
+    /// [`Coremap::get_keys`], this is read a stripe at a time
+    pub fn prefault(&self) -> usize {
+        let mut bytes = 0;
+        for shard in 0..self.inner.shard_count() {
+            self.inner.for_each_in_shard(shard, |k, v| {
+                touch_pages(k);
+                touch_pages(v);
+                bytes += k.len() + v.len();
+                true
+            });
+        }
+        bytes
+    }
+    /// Returns the number of keys that start with `prefix` along with the bytes held by
+    /// those keys and their values. Like [`Coremap::get_keys`], this is read a stripe at a time
+    pub fn prefix_stats(&self, prefix: &[u8]) -> (usize, usize) {
+        let (mut count, mut bytes) = (0, 0);
+        for shard in 0..self.inner.shard_count() {
+            self.inner.for_each_in_shard(shard, |k, v| {
+                if k.starts_with(prefix) {
+                    count += 1;
+                    bytes += k.len() + v.len();
+                }
+                true
+            });
+        }
+        (count, bytes)
+    }
+    /// Returns the keys in the given stripe that start with `prefix`
+    pub fn keys_with_prefix_in(&self, shard: usize, prefix: &[u8]) -> Vec<Data> {
+        let mut v = Vec::new();
+        self.inner.for_each_in_shard(shard, |k, _| {
+            if k.starts_with(prefix) {
+                v.push(k.clone());
+            }
+            true
+        });
         v
     }
+    /// Returns the number of stripes in the hashtable
+    pub fn stripe_count(&self) -> usize {
+        self.inner.shard_count()
+    }
+}
+
+/// The smallest page size of the platforms that we run on
+const PAGE_SIZE: usize = 4096;
+
+/// Read a byte from every page that `data` is on
+fn touch_pages(data: &[u8]) {
+    // the last byte can be on a page of its own
+    for byte in data.iter().step_by(PAGE_SIZE).chain(data.last()) {
+        unsafe {
+            // SAFETY: The reference is valid; this is volatile so that it isn't optimized out
+            core::ptr::read_volatile(byte);
+        }
+    }
 }
 
 impl<K: Eq + Hash, V> IntoIterator for Coremap<K, V> {
     type Item = (K, V);
-    type IntoIter = OwnedIter<K, V, RandomState>;
+    type IntoIter = OwnedIter<K, V, CoreState>;
     fn into_iter(self) -> Self::IntoIter {
         self.inner.get_owned_iter()
     }
@@ -34,6 +34,7 @@ use core::cmp;
 use core::fmt;
 use core::hash::{self, Hash};
 use core::iter::FromIterator;
+use core::marker::PhantomData;
 use core::mem;
 use core::mem::ManuallyDrop;
 use core::mem::MaybeUninit;
@@ -53,6 +54,9 @@ pub const fn new_const_iarray<T, const N: usize>() -> IArray<[T; N]> {
 }
 
 /// An arbitrary trait used for identifying something as a contiguous block of memory
+///
+/// This is implemented for arrays of any size, so an [`IArray`] can have any inline
+/// capacity: for example, `IArray<[u8; 11]>` holds upto 11 bytes on the stack
 pub trait MemoryBlock {
     /// The type that will be used for the memory layout
     type LayoutItem;
@@ -145,6 +149,38 @@ unsafe fn dealloc<T>(start_ptr: *mut T, capacity: usize) {
     )
 }
 
+#[derive(Debug, PartialEq, Clone)]
+/// Errors that can occur while trying to grow an [`IArray`]
+pub enum TryReserveError {
+    /// The required capacity overflowed
+    CapacityOverflow,
+    /// The allocator couldn't allocate memory for the given layout
+    AllocError(Layout),
+}
+
+impl TryReserveError {
+    /// Panic (or abort, for allocation errors) like the infallible methods do
+    fn bail(self) -> ! {
+        match self {
+            TryReserveError::CapacityOverflow => panic!("Capacity overflow"),
+            TryReserveError::AllocError(layout) => std_alloc::handle_alloc_error(layout),
+        }
+    }
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => write!(f, "capacity overflow"),
+            TryReserveError::AllocError(layout) => {
+                write!(f, "failed to allocate {} bytes", layout.size())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TryReserveError {}
+
 // Break free from Rust's aliasing rules with these typedefs
 type DataptrLenptrCapacity<T> = (*const T, usize, usize);
 type DataptrLenptrCapacityMut<'a, T> = (*mut T, &'a mut usize, usize);
@@ -185,6 +221,8 @@ impl<A: MemoryBlock> IArray<A> {
             let len = vec.len();
             unsafe {
                 ptr::copy_nonoverlapping(vec.as_ptr(), store.stack_ptr_mut(), len);
+                // the elements were moved out, so don't let the vec drop them
+                vec.set_len(0);
             }
             // done with the copy
             Self { cap: len, store }
@@ -199,6 +237,23 @@ impl<A: MemoryBlock> IArray<A> {
             }
         }
     }
+    /// Convert this IArray into a vector. This doesn't reallocate if the IArray is
+    /// already on the heap
+    pub fn into_vec(self) -> Vec<A::LayoutItem> {
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            if this.went_off_stack() {
+                let (ptr, len) = this.store.heap();
+                Vec::from_raw_parts(ptr, len, this.cap)
+            } else {
+                let len = this.len();
+                let mut vec = Vec::with_capacity(len);
+                ptr::copy_nonoverlapping(this.store.stack_ptr(), vec.as_mut_ptr(), len);
+                vec.set_len(len);
+                vec
+            }
+        }
+    }
     /// Returns the total capacity of the inline stack
     fn stack_capacity() -> usize {
         if mem::size_of::<A::LayoutItem>() > 0 {
@@ -277,64 +332,90 @@ impl<A: MemoryBlock> IArray<A> {
     /// Grow the allocation, if required, to make space for a total of `new_cap`
     /// elements
     fn grow_block(&mut self, new_cap: usize) {
-        // infallible
+        if let Err(e) = self.try_grow_block(new_cap) {
+            e.bail()
+        }
+    }
+    /// Same as `grow_block`, but returns an error instead of panicking if the capacity
+    /// overflows or the allocation fails. The IArray is left untouched on error
+    fn try_grow_block(&mut self, new_cap: usize) -> Result<(), TryReserveError> {
         unsafe {
             let (data_ptr, &mut len, cap) = self.meta_triple_mut();
             let still_on_stack = !self.went_off_stack();
             assert!(new_cap > len);
             if new_cap <= Self::stack_capacity() {
                 if still_on_stack {
-                    return;
+                    return Ok(());
                 }
                 self.store = InlineArray::from_stack(MaybeUninit::uninit());
                 ptr::copy_nonoverlapping(data_ptr, self.store.stack_ptr_mut(), len);
                 self.cap = len;
                 dealloc(data_ptr, cap);
             } else if new_cap != cap {
-                let layout =
-                    calculate_memory_layout::<A::LayoutItem>(new_cap).expect("Capacity overflow");
+                let layout = calculate_memory_layout::<A::LayoutItem>(new_cap)
+                    .map_err(|_| TryReserveError::CapacityOverflow)?;
                 assert!(layout.size() > 0);
                 let new_alloc;
                 if still_on_stack {
                     new_alloc = NonNull::new(std_alloc::alloc(layout).cast())
-                        .expect("Allocation error")
+                        .ok_or(TryReserveError::AllocError(layout))?
                         .as_ptr();
                     ptr::copy_nonoverlapping(data_ptr, new_alloc, len);
                 } else {
                     // not on stack
-                    let old_layout =
-                        calculate_memory_layout::<A::LayoutItem>(cap).expect("Capacity overflow");
-                    // realloc the earlier buffer
+                    let old_layout = calculate_memory_layout::<A::LayoutItem>(cap)
+                        .map_err(|_| TryReserveError::CapacityOverflow)?;
+                    // realloc the earlier buffer (which is left as is if this fails)
                     let new_memory_block_ptr =
                         std_alloc::realloc(data_ptr as *mut _, old_layout, layout.size());
                     new_alloc = NonNull::new(new_memory_block_ptr.cast())
-                        .expect("Allocation error")
+                        .ok_or(TryReserveError::AllocError(layout))?
                         .as_ptr();
                 }
                 self.store = InlineArray::from_heap_ptr(new_alloc, len);
                 self.cap = new_cap;
             }
         }
+        Ok(())
     }
     /// Reserve space for `additional` elements
-    fn reserve(&mut self, additional: usize) {
+    ///
+    /// ## Panics
+    /// Panics if the capacity overflows or the allocation fails
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(e) = self.try_reserve(additional) {
+            e.bail()
+        }
+    }
+    /// Reserve space for `additional` elements, returning an error instead of panicking
+    /// if the capacity overflows or the allocation fails
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
         let (_, &mut len, cap) = self.meta_triple_mut();
         if cap - len >= additional {
             // already have enough space
-            return;
+            return Ok(());
         }
         let new_cap = len
             .checked_add(additional)
-            .map(usize::next_power_of_two)
-            .expect("Capacity overflow");
-        self.grow_block(new_cap)
+            .and_then(usize::checked_next_power_of_two)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        self.try_grow_block(new_cap)
     }
     /// Push an element into this IArray
     pub fn push(&mut self, val: A::LayoutItem) {
+        if let Err((e, _)) = self.try_push(val) {
+            e.bail()
+        }
+    }
+    /// Push an element into this IArray, returning an error (along with the element) instead
+    /// of panicking if the capacity overflows or the allocation fails
+    pub fn try_push(&mut self, val: A::LayoutItem) -> Result<(), (TryReserveError, A::LayoutItem)> {
         unsafe {
             let (mut data_ptr, mut len, cap) = self.meta_triple_mut();
             if (*len).eq(&cap) {
-                self.reserve(1);
+                if let Err(e) = self.try_reserve(1) {
+                    return Err((e, val));
+                }
                 let (heap_ptr, heap_len) = self.store.heap_mut();
                 data_ptr = heap_ptr;
                 len = heap_len;
@@ -342,6 +423,7 @@ impl<A: MemoryBlock> IArray<A> {
             ptr::write(data_ptr.add(*len), val);
             *len += 1;
         }
+        Ok(())
     }
     /// Pop an element off this IArray
     pub fn pop(&mut self) -> Option<A::LayoutItem> {
@@ -360,16 +442,137 @@ impl<A: MemoryBlock> IArray<A> {
             }
         }
     }
-    /// This is amazingly dangerous if `idx` doesn't exist. You can potentially
-    /// corrupt a bunch of things
-    pub unsafe fn remove(&mut self, idx: usize) -> A::LayoutItem {
-        let (mut ptr, len_ref, _) = self.meta_triple_mut();
+    /// Insert an element at `index`, shifting all the elements after it to the right
+    ///
+    /// ## Panics
+    /// Panics if `index > len`
+    pub fn insert(&mut self, index: usize, val: A::LayoutItem) {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            len
+        );
+        self.reserve(1);
+        unsafe {
+            let ptr = self.get_data_ptr_mut().add(index);
+            // make space for the new element
+            ptr::copy(ptr, ptr.add(1), len - index);
+            ptr::write(ptr, val);
+            self.set_len(len + 1);
+        }
+    }
+    /// Remove and return the element at `index`, shifting all the elements after it to
+    /// the left
+    ///
+    /// ## Panics
+    /// Panics if `index >= len`
+    pub fn remove(&mut self, index: usize) -> A::LayoutItem {
+        let (ptr, len_ref, _) = self.meta_triple_mut();
         let len = *len_ref;
-        *len_ref = len - 1;
-        ptr = ptr.add(idx);
-        let item = ptr::read(ptr);
-        ptr::copy(ptr.add(1), ptr, len - idx - 1);
-        item
+        assert!(
+            index < len,
+            "removal index (is {}) should be < len (is {})",
+            index,
+            len
+        );
+        unsafe {
+            let ptr = ptr.add(index);
+            let item = ptr::read(ptr);
+            ptr::copy(ptr.add(1), ptr, len - index - 1);
+            *len_ref = len - 1;
+            item
+        }
+    }
+    /// Remove and return the element at `index`, replacing it with the last element. This
+    /// doesn't preserve ordering, but is O(1)
+    ///
+    /// ## Panics
+    /// Panics if `index >= len`
+    pub fn swap_remove(&mut self, index: usize) -> A::LayoutItem {
+        let (ptr, len_ref, _) = self.meta_triple_mut();
+        let len = *len_ref;
+        assert!(
+            index < len,
+            "swap_remove index (is {}) should be < len (is {})",
+            index,
+            len
+        );
+        unsafe {
+            let item = ptr::read(ptr.add(index));
+            // the regions overlap if we're removing the last element
+            ptr::copy(ptr.add(len - 1), ptr.add(index), 1);
+            *len_ref = len - 1;
+            item
+        }
+    }
+    /// Remove the elements in `range`, returning them as an iterator. The elements that
+    /// aren't consumed by the iterator are dropped along with it
+    ///
+    /// ## Panics
+    /// Panics if the start of the range is greater than its end or if the end of the range
+    /// is greater than `len`
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, A>
+    where
+        R: ops::RangeBounds<usize>,
+    {
+        let len = self.len();
+        let start = match range.start_bound() {
+            ops::Bound::Included(&n) => n,
+            ops::Bound::Excluded(&n) => n.checked_add(1).expect("Range overflow"),
+            ops::Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            ops::Bound::Included(&n) => n.checked_add(1).expect("Range overflow"),
+            ops::Bound::Excluded(&n) => n,
+            ops::Bound::Unbounded => len,
+        };
+        assert!(
+            start <= end,
+            "drain start (is {}) > end (is {})",
+            start,
+            end
+        );
+        assert!(end <= len, "drain end (is {}) > len (is {})", end, len);
+        unsafe {
+            // if the drain is leaked, we'll just leak the drained elements and the tail
+            // instead of exposing elements that were moved out
+            self.set_len(start);
+        }
+        Drain {
+            iarray: NonNull::from(self),
+            idx: start,
+            end,
+            tail_start: end,
+            tail_len: len - end,
+            _marker: PhantomData,
+        }
+    }
+    /// Only retain the elements for which `f` returns true. The order of the retained
+    /// elements is preserved
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&A::LayoutItem) -> bool,
+    {
+        let len = self.len();
+        let data_ptr = self.get_data_ptr_mut();
+        unsafe {
+            // if `f` or a destructor panics, we'll leak the elements instead of dropping
+            // them twice
+            self.set_len(0);
+            let mut deleted = 0;
+            for i in 0..len {
+                let current = data_ptr.add(i);
+                if !f(&*current) {
+                    ptr::drop_in_place(current);
+                    deleted += 1;
+                } else if deleted > 0 {
+                    ptr::copy_nonoverlapping(current, data_ptr.add(i - deleted), 1);
+                }
+            }
+            self.set_len(len - deleted);
+        }
     }
     /// Shrink this IArray so that it only occupies the required space and not anything
     /// more
@@ -467,9 +670,9 @@ where
         debug_assert!(index <= len);
         unsafe {
             let slice_ptr = slice.as_ptr();
-            // we need to add it from the end of the current item
-            let data_ptr_start = self.get_data_ptr_mut().add(len);
-            // copy the slice over
+            // we need to add it at the index
+            let data_ptr_start = self.get_data_ptr_mut().add(index);
+            // move the elements after the index out of the way and copy the slice over
             ptr::copy(data_ptr_start, data_ptr_start.add(slice.len()), len - index);
             ptr::copy_nonoverlapping(slice_ptr, data_ptr_start, slice.len());
             self.set_len(len + slice.len());
@@ -634,6 +837,124 @@ impl<A: MemoryBlock> FromIterator<A::LayoutItem> for IArray<A> {
     }
 }
 
+impl<A: MemoryBlock> From<Vec<A::LayoutItem>> for IArray<A> {
+    fn from(vec: Vec<A::LayoutItem>) -> Self {
+        Self::from_vec(vec)
+    }
+}
+
+impl<A: MemoryBlock> From<IArray<A>> for Vec<A::LayoutItem> {
+    fn from(iarray: IArray<A>) -> Self {
+        iarray.into_vec()
+    }
+}
+
+impl<A: MemoryBlock> Default for IArray<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: MemoryBlock> Clone for IArray<A>
+where
+    A::LayoutItem: Clone,
+{
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+/// An owning iterator for an [`IArray`], created by [`IArray::into_iter`]
+pub struct IntoIter<A: MemoryBlock> {
+    /// the IArray with its length set to zero so that it only frees the allocation
+    iarray: IArray<A>,
+    /// the index of the next element to be yielded
+    idx: usize,
+    /// one past the index of the last element to be yielded
+    end: usize,
+}
+
+impl<A: MemoryBlock> Iterator for IntoIter<A> {
+    type Item = A::LayoutItem;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx == self.end {
+            None
+        } else {
+            let idx = self.idx;
+            self.idx += 1;
+            unsafe {
+                // SAFETY: idx is yet to be moved out
+                Some(ptr::read(self.iarray.get_data_ptr_mut().add(idx)))
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.idx;
+        (len, Some(len))
+    }
+}
+
+impl<A: MemoryBlock> DoubleEndedIterator for IntoIter<A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.idx == self.end {
+            None
+        } else {
+            self.end -= 1;
+            unsafe {
+                // SAFETY: end is yet to be moved out
+                Some(ptr::read(self.iarray.get_data_ptr_mut().add(self.end)))
+            }
+        }
+    }
+}
+
+impl<A: MemoryBlock> ExactSizeIterator for IntoIter<A> {}
+
+impl<A: MemoryBlock> Drop for IntoIter<A> {
+    fn drop(&mut self) {
+        unsafe {
+            // drop whatever wasn't consumed; the IArray will free the allocation
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.iarray.get_data_ptr_mut().add(self.idx),
+                self.end - self.idx,
+            ));
+        }
+    }
+}
+
+impl<A: MemoryBlock> IntoIterator for IArray<A> {
+    type Item = A::LayoutItem;
+    type IntoIter = IntoIter<A>;
+    fn into_iter(mut self) -> Self::IntoIter {
+        let end = self.len();
+        unsafe {
+            // the iterator owns the elements now
+            self.set_len(0);
+        }
+        IntoIter {
+            iarray: self,
+            idx: 0,
+            end,
+        }
+    }
+}
+
+impl<'a, A: MemoryBlock> IntoIterator for &'a IArray<A> {
+    type Item = &'a A::LayoutItem;
+    type IntoIter = slice::Iter<'a, A::LayoutItem>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, A: MemoryBlock> IntoIterator for &'a mut IArray<A> {
+    type Item = &'a mut A::LayoutItem;
+    type IntoIter = slice::IterMut<'a, A::LayoutItem>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
 impl<'a, A: MemoryBlock> From<&'a [A::LayoutItem]> for IArray<A>
 where
     A::LayoutItem: Clone,
@@ -643,6 +964,134 @@ where
     }
 }
 
+/// A draining iterator for an [`IArray`], created by [`IArray::drain`]
+pub struct Drain<'a, A: MemoryBlock> {
+    iarray: NonNull<IArray<A>>,
+    /// the index of the next element to be yielded
+    idx: usize,
+    /// one past the index of the last element to be yielded
+    end: usize,
+    /// the index of the first element after the drained range
+    tail_start: usize,
+    /// the number of elements after the drained range
+    tail_len: usize,
+    _marker: PhantomData<&'a mut IArray<A>>,
+}
+
+impl<'a, A: MemoryBlock> Iterator for Drain<'a, A> {
+    type Item = A::LayoutItem;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx == self.end {
+            None
+        } else {
+            let idx = self.idx;
+            self.idx += 1;
+            unsafe {
+                // SAFETY: idx is in the drained range, and hence yet to be moved out
+                Some(ptr::read(self.iarray.as_mut().get_data_ptr_mut().add(idx)))
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.idx;
+        (len, Some(len))
+    }
+}
+
+impl<'a, A: MemoryBlock> DoubleEndedIterator for Drain<'a, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.idx == self.end {
+            None
+        } else {
+            self.end -= 1;
+            unsafe {
+                // SAFETY: end is in the drained range, and hence yet to be moved out
+                Some(ptr::read(
+                    self.iarray.as_mut().get_data_ptr_mut().add(self.end),
+                ))
+            }
+        }
+    }
+}
+
+impl<'a, A: MemoryBlock> ExactSizeIterator for Drain<'a, A> {}
+
+impl<'a, A: MemoryBlock> Drop for Drain<'a, A> {
+    fn drop(&mut self) {
+        unsafe {
+            let iarray = self.iarray.as_mut();
+            let data_ptr = iarray.get_data_ptr_mut();
+            // drop whatever wasn't consumed
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                data_ptr.add(self.idx),
+                self.end - self.idx,
+            ));
+            // now move the tail back
+            let start = iarray.len();
+            if self.tail_len != 0 {
+                ptr::copy(
+                    data_ptr.add(self.tail_start),
+                    data_ptr.add(start),
+                    self.tail_len,
+                );
+            }
+            iarray.set_len(start + self.tail_len);
+        }
+    }
+}
+
+#[cfg(feature = "iarray-serde")]
+mod serde_impls {
+    //! An IArray is serialized exactly like a `Vec`, so the two are interchangeable in
+    //! serialized data
+    use super::{IArray, MemoryBlock};
+    use core::fmt;
+    use core::marker::PhantomData;
+    use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    /// Don't trust the size hint of the deserializer beyond this
+    const MAX_PREALLOC: usize = 4096;
+
+    impl<A: MemoryBlock> Serialize for IArray<A>
+    where
+        A::LayoutItem: Serialize,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    struct IArrayVisitor<A>(PhantomData<A>);
+
+    impl<'de, A: MemoryBlock> Visitor<'de> for IArrayVisitor<A>
+    where
+        A::LayoutItem: Deserialize<'de>,
+    {
+        type Value = IArray<A>;
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence")
+        }
+        fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+            let mut iarray = IArray::new();
+            iarray.reserve(seq.size_hint().unwrap_or(0).min(MAX_PREALLOC));
+            while let Some(item) = seq.next_element()? {
+                iarray.push(item);
+            }
+            Ok(iarray)
+        }
+    }
+
+    impl<'de, A: MemoryBlock> Deserialize<'de> for IArray<A>
+    where
+        A::LayoutItem: Deserialize<'de>,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(IArrayVisitor(PhantomData))
+        }
+    }
+}
+
 unsafe impl<A: MemoryBlock> Send for IArray<A> where A::LayoutItem: Send {}
 unsafe impl<A: MemoryBlock> Sync for IArray<A> where A::LayoutItem: Sync {}
 
@@ -658,3 +1107,160 @@ fn test_equality() {
         i
     })
 }
+
+#[test]
+fn test_insert_slice_at_index() {
+    let mut x = IArray::<[u8; 8]>::from_slice(b"held");
+    x.insert_slice_at_index(b"llo wor", 2);
+    assert_eq!(&x[..], b"hello world");
+}
+
+#[test]
+fn test_insert_remove() {
+    let mut x = IArray::<[String; 4]>::new();
+    x.insert(0, "b".to_owned());
+    x.insert(0, "a".to_owned());
+    x.insert(2, "d".to_owned());
+    x.insert(2, "c".to_owned());
+    // this one goes off the stack
+    x.insert(4, "e".to_owned());
+    assert_eq!(x[..], ["a", "b", "c", "d", "e"]);
+    assert_eq!(x.remove(0), "a");
+    assert_eq!(x.swap_remove(0), "b");
+    assert_eq!(x[..], ["e", "c", "d"]);
+    assert_eq!(x.swap_remove(2), "d");
+    assert_eq!(x[..], ["e", "c"]);
+}
+
+#[test]
+#[should_panic]
+fn test_remove_out_of_bounds() {
+    let mut x = IArray::<[u8; 4]>::from_slice(b"abc");
+    x.remove(3);
+}
+
+#[test]
+fn test_drain() {
+    let mut x: IArray<[String; 4]> = IArray::new();
+    (0..8).for_each(|i| x.push(i.to_string()));
+    let drained: Vec<String> = x.drain(2..5).collect();
+    assert_eq!(drained, ["2", "3", "4"]);
+    assert_eq!(x[..], ["0", "1", "5", "6", "7"]);
+    // partially consumed drains drop the rest
+    let mut drain = x.drain(1..=3);
+    assert_eq!(drain.next_back().unwrap(), "6");
+    drop(drain);
+    assert_eq!(x[..], ["0", "7"]);
+    assert_eq!(x.drain(..).count(), 2);
+    assert!(x.is_empty());
+    // on the stack
+    let mut y = IArray::<[u8; 8]>::from_slice(b"abcdef");
+    assert_eq!(y.drain(..3).collect::<Vec<u8>>(), b"abc");
+    assert_eq!(&y[..], b"def");
+}
+
+#[test]
+fn test_retain() {
+    let mut x: IArray<[String; 4]> = IArray::new();
+    (0..10).for_each(|i| x.push(i.to_string()));
+    x.retain(|v| v.parse::<u8>().unwrap() % 3 == 0);
+    assert_eq!(x[..], ["0", "3", "6", "9"]);
+    let mut y = IArray::<[u8; 8]>::from_slice(b"skytable");
+    y.retain(|c| *c != b't');
+    assert_eq!(&y[..], b"skyable");
+}
+
+#[test]
+fn test_into_iter() {
+    let mut x: IArray<[String; 4]> = IArray::new();
+    (0..8).for_each(|i| x.push(i.to_string()));
+    let mut iter = x.into_iter();
+    assert_eq!(iter.len(), 8);
+    assert_eq!(iter.next().unwrap(), "0");
+    assert_eq!(iter.next_back().unwrap(), "7");
+    // the rest are dropped with the iterator
+    drop(iter);
+    let y: IArray<[String; 4]> = (0..2).map(|i| i.to_string()).collect();
+    assert_eq!(y.into_iter().collect::<Vec<String>>(), ["0", "1"]);
+    let mut z = IArray::<[u8; 4]>::from_slice(b"abc");
+    for c in &mut z {
+        c.make_ascii_uppercase();
+    }
+    assert_eq!((&z).into_iter().copied().collect::<Vec<u8>>(), b"ABC");
+}
+
+#[test]
+fn test_vec_conversions() {
+    // on the stack
+    let x: IArray<[String; 4]> = IArray::from(vec!["a".to_owned(), "b".to_owned()]);
+    assert_eq!(x[..], ["a", "b"]);
+    assert_eq!(Vec::from(x), ["a", "b"]);
+    // on the heap
+    let v: Vec<String> = (0..8).map(|i| i.to_string()).collect();
+    let x: IArray<[String; 4]> = IArray::from(v.clone());
+    assert_eq!(x.len(), 8);
+    assert_eq!(x.into_vec(), v);
+}
+
+#[test]
+fn test_clone_default() {
+    let x: IArray<[String; 2]> = IArray::default();
+    assert!(x.is_empty());
+    let mut y: IArray<[String; 2]> = IArray::default();
+    (0..4).for_each(|i| y.push(i.to_string()));
+    let z = y.clone();
+    y.clear();
+    assert_eq!(z[..], ["0", "1", "2", "3"]);
+}
+
+#[test]
+fn test_arbitrary_stack_sizes() {
+    let mut x = IArray::<[u8; 11]>::from_slice(b"hello world");
+    assert!(!x.went_off_stack());
+    x.push(b'!');
+    assert!(x.went_off_stack());
+    assert_eq!(&x[..], b"hello world!");
+    let mut y = IArray::<[String; 3]>::new();
+    (0..3).for_each(|i| y.push(i.to_string()));
+    assert!(!y.went_off_stack());
+    y.push(3.to_string());
+    assert!(y.went_off_stack());
+    assert_eq!(y[..], ["0", "1", "2", "3"]);
+}
+
+#[cfg(feature = "iarray-serde")]
+#[test]
+fn test_serde() {
+    let stack = IArray::<[String; 4]>::from(vec!["a".to_owned(), "b".to_owned()]);
+    let heap: IArray<[String; 4]> = (0..8).map(|i| i.to_string()).collect();
+    for iarray in [stack, heap].iter() {
+        let encoded = bincode::serialize(iarray).unwrap();
+        // this should be the same as a vec
+        assert_eq!(encoded, bincode::serialize(&iarray.to_vec()).unwrap());
+        let decoded: IArray<[String; 4]> = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(&decoded, iarray);
+    }
+}
+
+#[test]
+fn test_try_reserve_push() {
+    let mut x = IArray::<[u64; 4]>::new();
+    assert!(x.try_reserve(4).is_ok());
+    assert!(!x.went_off_stack());
+    assert!(x.try_reserve(8).is_ok());
+    assert!(x.went_off_stack());
+    (0..8).for_each(|i| x.try_push(i).unwrap());
+    assert_eq!(x[..], [0, 1, 2, 3, 4, 5, 6, 7]);
+    // this can't fit in the address space
+    assert_eq!(
+        x.try_reserve(usize::MAX),
+        Err(TryReserveError::CapacityOverflow)
+    );
+    assert_eq!(
+        x.try_reserve(usize::MAX / 2),
+        Err(TryReserveError::CapacityOverflow)
+    );
+    // the IArray is still usable
+    x.push(8);
+    assert_eq!(x.len(), 9);
+}
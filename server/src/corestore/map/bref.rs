@@ -32,6 +32,8 @@ use core::hash::Hash;
 use core::mem;
 use core::ops::Deref;
 use core::ops::DerefMut;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
 use parking_lot::RwLockReadGuard;
 use parking_lot::RwLockWriteGuard;
 use std::collections::hash_map::RandomState;
@@ -127,6 +129,7 @@ pub struct OccupiedEntry<'a, K, V, S> {
     elem: (&'a K, &'a mut V),
     key: K,
     hasher: S,
+    counter: &'a AtomicUsize,
 }
 
 impl<'a, K: Hash + Eq, V, S: BuildHasher> OccupiedEntry<'a, K, V, S> {
@@ -136,12 +139,14 @@ impl<'a, K: Hash + Eq, V, S: BuildHasher> OccupiedEntry<'a, K, V, S> {
         key: K,
         elem: (&'a K, &'a mut V),
         hasher: S,
+        counter: &'a AtomicUsize,
     ) -> Self {
         Self {
             guard,
             elem,
             key,
             hasher,
+            counter,
         }
     }
     /// Get a ref to the key
@@ -159,6 +164,7 @@ impl<'a, K: Hash + Eq, V, S: BuildHasher> OccupiedEntry<'a, K, V, S> {
     /// Remove this element from the map
     pub fn remove(mut self) -> V {
         let hash = super::make_hash::<K, _, S>(&self.hasher, &self.key);
+        self.counter.fetch_sub(1, Ordering::Release);
         unsafe {
             self.guard
                 .remove_entry(hash, super::ceq(self.elem.0))
@@ -176,12 +182,23 @@ pub struct VacantEntry<'a, K, V, S> {
     guard: RwLockWriteGuard<'a, LowMap<K, V>>,
     key: K,
     hasher: S,
+    counter: &'a AtomicUsize,
 }
 
 impl<'a, K: Hash + Eq, V, S: BuildHasher> VacantEntry<'a, K, V, S> {
     /// Create a vacant entry ref
-    pub(super) fn new(guard: RwLockWriteGuard<'a, LowMap<K, V>>, key: K, hasher: S) -> Self {
-        Self { guard, key, hasher }
+    pub(super) fn new(
+        guard: RwLockWriteGuard<'a, LowMap<K, V>>,
+        key: K,
+        hasher: S,
+        counter: &'a AtomicUsize,
+    ) -> Self {
+        Self {
+            guard,
+            key,
+            hasher,
+            counter,
+        }
     }
     /// Insert a value into this bucket
     pub fn insert(mut self, value: V) -> RefMut<'a, K, V> {
@@ -192,6 +209,7 @@ impl<'a, K: Hash + Eq, V, S: BuildHasher> VacantEntry<'a, K, V, S> {
                 (self.key, value),
                 super::make_hasher::<K, _, V, S>(&self.hasher),
             );
+            self.counter.fetch_add(1, Ordering::Release);
             let kptr = compiler::extend_lifetime(k);
             let vptr = compiler::extend_lifetime_mut(v);
             RefMut::new(self.guard, kptr, vptr)
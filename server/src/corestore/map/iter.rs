@@ -28,6 +28,7 @@ use super::bref::{RefMulti, RefMultiMut};
 use super::LowMap;
 use super::Skymap;
 use core::mem;
+use core::sync::atomic::Ordering;
 use hashbrown::raw::RawIntoIter;
 use hashbrown::raw::RawIter;
 use parking_lot::RwLockReadGuard;
@@ -67,6 +68,9 @@ impl<K, V, S> Iterator for OwnedIter<K, V, S> {
             let mut wshard = unsafe { self.map.get_wshard_unchecked(self.cs) };
             // get the next map's iterator
             let current_map = mem::replace(&mut *wshard, LowMap::new());
+            self.map
+                .counter()
+                .fetch_sub(current_map.len(), Ordering::Release);
             drop(wshard);
             let iter = current_map.into_iter();
             self.current = Some(iter);
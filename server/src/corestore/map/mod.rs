@@ -34,6 +34,8 @@ use core::hash::Hash;
 use core::hash::Hasher;
 use core::iter::FromIterator;
 use core::mem;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
 use parking_lot::RwLock;
 use parking_lot::RwLockReadGuard;
 use parking_lot::RwLockWriteGuard;
@@ -101,6 +103,9 @@ pub struct Skymap<K, V, S = RandomState> {
     shards: Box<ShardSlice<K, V>>,
     hasher: S,
     shift: usize,
+    /// the number of entries across all stripes, updated while the stripe's wlock is held so
+    /// that we don't have to lock every stripe just to find the len
+    len: AtomicUsize,
 }
 
 impl<K, V> Default for Skymap<K, V, RandomState> {
@@ -136,13 +141,6 @@ where
     }
 }
 
-impl<K, V> Skymap<K, V, ahash::RandomState> {
-    /// Get a Skymap with the ahash hasher
-    pub fn new_ahash() -> Self {
-        Skymap::new()
-    }
-}
-
 // basic impls
 impl<K, V, S> Skymap<K, V, S>
 where
@@ -171,6 +169,7 @@ where
                 .collect(),
             hasher,
             shift,
+            len: AtomicUsize::new(0),
         }
     }
     /// Create a new Skymap with the provided hasher
@@ -179,7 +178,7 @@ where
     }
     /// Get the len of the Skymap
     pub fn len(&self) -> usize {
-        self.shards.iter().map(|s| s.read().len()).sum()
+        self.len.load(Ordering::Acquire)
     }
     /// Get the capacity of the Skymap
     pub fn capacity(&self) -> usize {
@@ -218,6 +217,22 @@ impl<K, V, S> Skymap<K, V, S> {
     const fn h(&self) -> &S {
         &self.hasher
     }
+    /// Get the number of stripes
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+    /// Get the number of entries in each stripe
+    pub fn shard_lens(&self) -> Vec<usize> {
+        self.shards.iter().map(|s| s.read().len()).collect()
+    }
+    /// Get a ref to the hasher
+    pub const fn hasher(&self) -> &S {
+        &self.hasher
+    }
+    /// Get a ref to the entry counter
+    const fn counter(&self) -> &AtomicUsize {
+        &self.len
+    }
 }
 
 // insert/get/remove impls
@@ -238,6 +253,7 @@ where
                 Some(mem::replace(item, v))
             } else {
                 lowtable.insert(hash, (k, v), make_hasher::<K, _, V, S>(self.h()));
+                self.len.fetch_add(1, Ordering::Release);
                 None
             }
             // end critical section
@@ -255,7 +271,10 @@ where
             // begin critical section
             let mut lowtable = self.get_wshard_unchecked(idx);
             match lowtable.remove_entry(hash, ceq(k)) {
-                Some(kv) => Some(kv),
+                Some(kv) => {
+                    self.len.fetch_sub(1, Ordering::Release);
+                    Some(kv)
+                }
                 None => None,
             }
             // end critical section
@@ -276,6 +295,7 @@ where
                 Some(bucket) => {
                     let (kptr, vptr) = bucket.as_ref();
                     if f(kptr, vptr) {
+                        self.len.fetch_sub(1, Ordering::Release);
                         Some(lowtable.remove(bucket))
                     } else {
                         None
@@ -351,9 +371,15 @@ impl<'a, K: 'a + Hash + Eq, V: 'a, S: BuildHasher + Clone> Skymap<K, V, S> {
                     key,
                     (kptr, vptr),
                     self.hasher.clone(),
+                    self.counter(),
                 ))
             } else {
-                Entry::Vacant(VacantEntry::new(lowtable, key, self.hasher.clone()))
+                Entry::Vacant(VacantEntry::new(
+                    lowtable,
+                    key,
+                    self.hasher.clone(),
+                    self.counter(),
+                ))
             }
             // end critical section
         }
@@ -368,7 +394,46 @@ impl<'a, K: 'a + Hash + Eq, V: 'a, S: BuildHasher + Clone> Skymap<K, V, S> {
     }
     /// Clear out all the entries in the Skymap
     pub fn clear(&self) {
-        self.shards().iter().for_each(|shard| shard.write().clear())
+        self.shards().iter().for_each(|shard| {
+            let mut shard = shard.write();
+            self.len.fetch_sub(shard.len(), Ordering::Release);
+            shard.clear()
+        })
+    }
+    /// Replace all the entries in the Skymap with `entries`, in one go: nobody can see a
+    /// mix of the old and the new entries. The new stripes are built before we take any
+    /// lock, and since we only ever try for the wlocks (backing off if any stripe is busy),
+    /// we can't deadlock with a thread that holds one stripe while waiting for another
+    pub fn replace_all(&self, entries: impl IntoIterator<Item = (K, V)>) {
+        let mut shards: Vec<LowMap<K, V>> =
+            (0..self.shard_count()).map(|_| LowMap::new()).collect();
+        let mut len = 0;
+        for (k, v) in entries {
+            let hash = make_insert_hash::<K, S>(self.h(), &k);
+            let shard = &mut shards[self.determine_shard(hash as usize)];
+            if let Some((_, item)) = shard.get_mut(hash, ceq(&k)) {
+                *item = v;
+            } else {
+                shard.insert(hash, (k, v), make_hasher::<K, _, V, S>(self.h()));
+                len += 1;
+            }
+        }
+        let mut locks = Vec::with_capacity(self.shard_count());
+        while locks.len() != self.shard_count() {
+            match self.shards()[locks.len()].try_write() {
+                Some(lock) => locks.push(lock),
+                None => {
+                    locks.clear();
+                    std::thread::yield_now();
+                }
+            }
+        }
+        for (lock, shard) in locks.iter_mut().zip(shards.iter_mut()) {
+            mem::swap(&mut **lock, shard);
+        }
+        self.len.store(len, Ordering::Release);
+        drop(locks);
+        // the old entries (now in `shards`) are dropped after the locks are released
     }
 }
 
@@ -403,6 +468,35 @@ impl<'a, K: 'a, V: 'a, S> Skymap<K, V, S> {
     unsafe fn get_wshard_unchecked(&'a self, shard: usize) -> SWlock<'a, K, V> {
         self.shards.get_unchecked(shard).write()
     }
+    /// Call `f` on every key/value in a certain stripe (or until it returns false), while
+    /// holding a rlock to the stripe. This lets callers walk the map a stripe at a time,
+    /// without holding any lock in between
+    pub fn for_each_in_shard(&'a self, shard: usize, mut f: impl FnMut(&K, &V) -> bool) {
+        if let Some(shard) = self.shards.get(shard) {
+            let shard = shard.read();
+            unsafe {
+                // the rlock is held for as long as we use the iterator
+                for bucket in shard.iter() {
+                    let (k, v) = bucket.as_ref();
+                    if !f(k, v) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_replace_all() {
+    let map = Skymap::default();
+    map.insert("hello", "world");
+    map.insert("sayan", "likes computational dark arts");
+    map.replace_all(vec![("hello", "there"), ("x", "y"), ("x", "z")]);
+    assert_eq!(map.len(), 2);
+    assert_eq!(*map.get("hello").unwrap(), "there");
+    assert_eq!(*map.get("x").unwrap(), "z");
+    assert!(map.get("sayan").is_none());
 }
 
 #[test]
@@ -429,6 +523,63 @@ fn test_insert_get() {
     assert_eq!(*_ref, "likes computational dark arts")
 }
 
+#[test]
+fn test_for_each_in_shard() {
+    let map = Skymap::default();
+    (0..100).for_each(|i| {
+        map.insert(i, i);
+    });
+    let mut seen = 0;
+    for shard in 0..map.shard_count() {
+        map.for_each_in_shard(shard, |k, v| {
+            assert_eq!(k, v);
+            seen += 1;
+            true
+        });
+    }
+    assert_eq!(seen, 100);
+    // stopping early
+    let mut seen = 0;
+    for shard in 0..map.shard_count() {
+        map.for_each_in_shard(shard, |_, _| {
+            seen += 1;
+            false
+        });
+    }
+    assert!(seen <= map.shard_count());
+    // stripes that don't exist are empty
+    map.for_each_in_shard(map.shard_count(), |_, _| unreachable!());
+}
+
+#[test]
+fn test_len_tracking() {
+    let map = Skymap::default();
+    (0..100).for_each(|i| {
+        map.insert(i, i);
+    });
+    assert_eq!(map.len(), 100);
+    // updates don't change the len
+    map.insert(0, 1);
+    assert_eq!(map.len(), 100);
+    assert!(map.remove(&0).is_some());
+    assert!(map.remove(&0).is_none());
+    assert!(map.remove_if(&1, |_, _| false).is_none());
+    assert!(map.remove_if(&1, |_, _| true).is_some());
+    assert_eq!(map.len(), 98);
+    if let Entry::Vacant(ve) = map.entry(0) {
+        ve.insert(0);
+    }
+    if let Entry::Occupied(oe) = map.entry(2) {
+        oe.remove();
+    }
+    assert_eq!(map.len(), 98);
+    let actual: usize = map.shards().iter().map(|s| s.read().len()).sum();
+    assert_eq!(map.len(), actual);
+    map.clear();
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+}
+
 #[test]
 fn test_entry() {
     let map = Skymap::default();
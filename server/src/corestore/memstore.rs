@@ -60,10 +60,14 @@ use crate::corestore::htable::Coremap;
 use crate::corestore::lock::{QLGuard, QuickLock};
 use crate::corestore::table::Table;
 use crate::registry;
+use crate::registry::jobs::{Job, JobKind};
 use core::borrow::Borrow;
 use core::hash::Hash;
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[sky_macros::array]
 const DEFAULT_ARRAY: [MaybeUninit<u8>; 64] = [b'd', b'e', b'f', b'a', b'u', b'l', b't'];
@@ -213,10 +217,81 @@ impl Memstore {
     {
         self.keyspaces.get(keyspace_identifier).map(|ns| ns.clone())
     }
+    /// Take a copy of every keyspace and its tables (see [`Keyspace::duplicate`]). Every
+    /// keyspace is copied at its own rendezvous
+    pub fn duplicate(&self) -> Self {
+        let keyspaces = Coremap::new();
+        for keyspace in self.keyspaces.iter() {
+            keyspaces.true_if_insert(
+                keyspace.key().clone(),
+                Arc::new(keyspace.value().duplicate()),
+            );
+        }
+        Self::init_with_all(keyspaces)
+    }
+    /// Returns the names of all the keyspaces
+    pub fn keyspace_names(&self) -> Vec<String> {
+        self.keyspaces
+            .iter()
+            .map(|kv| unsafe { kv.key().as_str() }.to_owned())
+            .collect()
+    }
+    /// Free the dropped tables (in every keyspace) that were dropped more than `retention`
+    /// ago
+    pub fn reap_dropped_tables(&self, retention: Duration) {
+        for keyspace in self.keyspaces.iter() {
+            keyspace.reap_dropped(retention);
+        }
+    }
+    /// Remove the expired keys from every table in every keyspace, returning the number of
+    /// keys removed
+    pub fn purge_expired_keys(&self) -> usize {
+        let mut purged = 0;
+        for keyspace in self.keyspaces.iter() {
+            for table in keyspace.tables.iter() {
+                if let Ok(kve) = table.get_kvstore() {
+                    purged += kve.purge_expired();
+                }
+            }
+        }
+        purged
+    }
+    /// Compact the small value slabs of every table in every keyspace. This runs as a
+    /// cancellable job; a cancelled compaction simply leaves the remaining tables as they are.
+    /// If the job is [limited](crate::registry::bgio), it waits between tables to keep to its
+    /// rate
+    pub fn compact_slabs(&self) {
+        let job = Job::start_cancellable(JobKind::Compaction);
+        let _priority = job.limiter().map(|limiter| limiter.prioritize());
+        job.set_total(
+            self.keyspaces
+                .iter()
+                .map(|keyspace| keyspace.tables.len() as u64)
+                .sum(),
+        );
+        for keyspace in self.keyspaces.iter() {
+            for table in keyspace.tables.iter() {
+                if job.is_cancelled() {
+                    return;
+                }
+                if let Ok(kve) = table.get_kvstore() {
+                    let copied = kve.compact_slab();
+                    if let Some(limiter) = job.limiter() {
+                        limiter.consume(copied);
+                    }
+                }
+                job.progress();
+            }
+        }
+    }
     /// Returns true if a new keyspace was created
     pub fn create_keyspace(&self, keyspace_identifier: ObjectID) -> bool {
+        self.create_keyspace_from(keyspace_identifier, Keyspace::empty())
+    }
+    /// Returns true if the given keyspace was added as a new keyspace
+    pub fn create_keyspace_from(&self, keyspace_identifier: ObjectID, keyspace: Keyspace) -> bool {
         self.keyspaces
-            .true_if_insert(keyspace_identifier, Arc::new(Keyspace::empty()))
+            .true_if_insert(keyspace_identifier, Arc::new(keyspace))
     }
     /// Drop a keyspace only if it is empty and has no clients connected to it
     ///
@@ -302,15 +377,30 @@ impl Memstore {
     }
 }
 
+#[derive(Debug)]
+/// A table that was dropped, but can still be restored until it is reaped
+struct DroppedTable {
+    /// the table
+    table: Arc<Table>,
+    /// when the table was dropped
+    dropped_at: Instant,
+}
+
 #[derive(Debug)]
 /// A keyspace houses all the other tables
 pub struct Keyspace {
     /// the tables
     pub tables: Coremap<ObjectID, Arc<Table>>,
+    /// the tables that were dropped, but haven't been reaped yet
+    dropped: Coremap<ObjectID, DroppedTable>,
     /// the replication strategy for this keyspace
     replication_strategy: cluster::ReplicationStrategy,
     /// A **virtual lock** on the partmap for this keyspace
     partmap_lock: QuickLock<()>,
+    /// A **virtual lock** for writes that span more than one key in this keyspace
+    write_lock: RwLock<()>,
+    /// why writes to this keyspace are stalled, if they are (see [`stall`](crate::registry::stall))
+    stall: AtomicU8,
 }
 
 #[cfg(test)]
@@ -330,25 +420,47 @@ impl Keyspace {
                 ht.true_if_insert(DEFAULT, Arc::new(Table::new_default_kve()));
                 ht
             },
+            dropped: Coremap::new(),
             replication_strategy: cluster::ReplicationStrategy::default(),
             partmap_lock: QuickLock::new(()),
+            write_lock: RwLock::new(()),
+            stall: AtomicU8::new(0),
         }
     }
     pub fn init_with_all_def_strategy(tables: Coremap<ObjectID, Arc<Table>>) -> Self {
         Self {
             tables,
+            dropped: Coremap::new(),
             replication_strategy: cluster::ReplicationStrategy::default(),
             partmap_lock: QuickLock::new(()),
+            write_lock: RwLock::new(()),
+            stall: AtomicU8::new(0),
         }
     }
     /// Create a new empty keyspace with zero tables
     pub fn empty() -> Self {
         Self {
             tables: Coremap::new(),
+            dropped: Coremap::new(),
             replication_strategy: cluster::ReplicationStrategy::default(),
             partmap_lock: QuickLock::new(()),
+            write_lock: RwLock::new(()),
+            stall: AtomicU8::new(0),
         }
     }
+    /// Returns why writes to this keyspace are stalled (see [`stall`](crate::registry::stall)), or `0` if they aren't
+    pub fn stall(&self) -> u8 {
+        self.stall.load(Ordering::Acquire)
+    }
+    /// Stall writes to this keyspace for the given reason, returning true if they weren't
+    /// stalled for it already
+    pub fn set_stall(&self, reason: u8) -> bool {
+        self.stall.fetch_or(reason, Ordering::AcqRel) & reason == 0
+    }
+    /// Lift the stall for the given reason, returning true if writes were stalled for it
+    pub fn clear_stall(&self, reason: u8) -> bool {
+        self.stall.fetch_and(!reason, Ordering::AcqRel) & reason != 0
+    }
     pub fn table_count(&self) -> usize {
         self.tables.len()
     }
@@ -360,6 +472,35 @@ impl Keyspace {
     {
         self.tables.get(table_identifier).map(|v| v.clone())
     }
+    /// Lock out every other write that has declared its intent to write to this keyspace
+    /// (see [`Keyspace::write_intent`]), so that writes to many tables can be made at once
+    pub fn lock_writes(&self) -> RwLockWriteGuard<'_, ()> {
+        self.write_lock.write()
+    }
+    /// Declare the intent to write to this keyspace. Any number of writes can hold the intent
+    /// at the same time, but none can while [`Keyspace::lock_writes`] is held
+    pub fn write_intent(&self) -> RwLockReadGuard<'_, ()> {
+        self.write_lock.read()
+    }
+    /// Run `f` at a point where no write that holds the intent to write to this keyspace
+    /// (like an `MSET`) is halfway done. Such writes wait for `f` to return, so it should
+    /// only take a quick snapshot of what it needs (single key writes don't wait at all)
+    pub fn rendezvous<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _lock = self.lock_writes();
+        f()
+    }
+    /// Take a copy of this keyspace and its tables (see [`Table::duplicate`]) at a
+    /// [rendezvous](Keyspace::rendezvous). Dropped tables that haven't been reaped yet
+    /// aren't copied
+    pub fn duplicate(&self) -> Self {
+        let tables = Coremap::new();
+        self.rendezvous(|| {
+            for table in self.tables.iter() {
+                tables.true_if_insert(table.key().clone(), Arc::new(table.value().duplicate()));
+            }
+        });
+        Self::init_with_all_def_strategy(tables)
+    }
     /// Create a new table
     pub fn create_table(&self, tableid: ObjectID, table: Table) -> bool {
         self.tables.true_if_insert(tableid, Arc::new(table))
@@ -397,6 +538,90 @@ impl Keyspace {
             }
         }
     }
+    /// Drop a table like [`Keyspace::drop_table`], but keep it around so that it can be
+    /// restored with [`Keyspace::undrop_table`] until it is reaped. A table with the same
+    /// name that was dropped earlier is freed
+    ///
+    /// **Trip switch handled:** Yes
+    pub fn soft_drop_table<Q>(&self, table_identifier: &Q) -> KeyspaceResult<()>
+    where
+        ObjectID: Borrow<Q>,
+        Q: Hash + Eq + PartialEq<ObjectID> + ?Sized,
+    {
+        if table_identifier.eq(&DEFAULT) {
+            Err(DdlError::ProtectedObject)
+        } else if !self.tables.contains_key(table_identifier) {
+            Err(DdlError::ObjectNotFound)
+        } else {
+            let removed = self
+                .tables
+                .remove_if(table_identifier, |_table_id, table_atomic_ref| {
+                    // 1 because this should just be us, the one instance
+                    Arc::strong_count(table_atomic_ref) == 1
+                });
+            match removed {
+                Some((tableid, table)) => {
+                    // the table's file is removed on the next flush, so an unloaded table has
+                    // to be brought back into memory for it to be restorable
+                    table.ensure_loaded();
+                    let dropped = DroppedTable {
+                        table,
+                        dropped_at: Instant::now(),
+                    };
+                    self.dropped.upsert(tableid, dropped);
+                    registry::get_preload_tripswitch().trip();
+                    Ok(())
+                }
+                None => Err(DdlError::StillInUse),
+            }
+        }
+    }
+    /// Restore a table that was dropped with [`Keyspace::soft_drop_table`], if it hasn't
+    /// been reaped yet and no other table with the same name was created in the meantime
+    ///
+    /// **Trip switch handled:** Yes
+    pub fn undrop_table<Q>(&self, table_identifier: &Q) -> KeyspaceResult<()>
+    where
+        ObjectID: Borrow<Q>,
+        Q: Hash + Eq + PartialEq<ObjectID> + ?Sized,
+    {
+        if !self.dropped.contains_key(table_identifier) {
+            Err(DdlError::ObjectNotFound)
+        } else if self.tables.contains_key(table_identifier) {
+            Err(DdlError::AlreadyExists)
+        } else {
+            match self.dropped.remove(table_identifier) {
+                Some((tableid, dropped)) => match self.tables.fresh_entry(tableid.clone()) {
+                    Some(entry) => {
+                        entry.insert(dropped.table);
+                        registry::get_preload_tripswitch().trip();
+                        Ok(())
+                    }
+                    None => {
+                        // someone created a table with the same name; keep this one around
+                        self.dropped.upsert(tableid, dropped);
+                        Err(DdlError::AlreadyExists)
+                    }
+                },
+                None => Err(DdlError::ObjectNotFound),
+            }
+        }
+    }
+    /// Free the dropped tables that were dropped more than `retention` ago
+    pub fn reap_dropped(&self, retention: Duration) {
+        let expired: Vec<ObjectID> = self
+            .dropped
+            .iter()
+            .filter(|dropped| dropped.value().dropped_at.elapsed() >= retention)
+            .map(|dropped| dropped.key().clone())
+            .collect();
+        for tableid in expired {
+            // the table might have been restored and dropped again since we looked
+            self.dropped.true_remove_if(&tableid, |_, dropped| {
+                dropped.dropped_at.elapsed() >= retention
+            });
+        }
+    }
 
     /// Remove a table without doing any reference checks. This will just pull it off
     pub unsafe fn force_remove_table(&self, tblid: &ObjectID) {
@@ -439,6 +664,69 @@ fn test_keyspace_drop_fail_with_atomic_ref() {
     );
 }
 
+#[test]
+fn test_keyspace_soft_drop_and_undrop() {
+    let our_keyspace = Keyspace::empty_default();
+    assert!(our_keyspace.create_table(
+        unsafe_objectid_from_slice!("apps"),
+        Table::new_default_kve()
+    ));
+    assert!(our_keyspace
+        .soft_drop_table(&unsafe_objectid_from_slice!("apps"))
+        .is_ok());
+    assert!(our_keyspace
+        .get_table_atomic_ref(&unsafe_objectid_from_slice!("apps"))
+        .is_none());
+    // a new table with the same name blocks the restore
+    assert!(our_keyspace.create_table(
+        unsafe_objectid_from_slice!("apps"),
+        Table::new_default_kve()
+    ));
+    assert_eq!(
+        our_keyspace
+            .undrop_table(&unsafe_objectid_from_slice!("apps"))
+            .unwrap_err(),
+        DdlError::AlreadyExists
+    );
+    assert!(our_keyspace
+        .drop_table(&unsafe_objectid_from_slice!("apps"))
+        .is_ok());
+    assert!(our_keyspace
+        .undrop_table(&unsafe_objectid_from_slice!("apps"))
+        .is_ok());
+    assert!(our_keyspace
+        .get_table_atomic_ref(&unsafe_objectid_from_slice!("apps"))
+        .is_some());
+    assert_eq!(
+        our_keyspace
+            .undrop_table(&unsafe_objectid_from_slice!("apps"))
+            .unwrap_err(),
+        DdlError::ObjectNotFound
+    );
+}
+
+#[test]
+fn test_keyspace_reap_dropped() {
+    let our_keyspace = Keyspace::empty_default();
+    assert!(our_keyspace.create_table(
+        unsafe_objectid_from_slice!("apps"),
+        Table::new_default_kve()
+    ));
+    assert!(our_keyspace
+        .soft_drop_table(&unsafe_objectid_from_slice!("apps"))
+        .is_ok());
+    our_keyspace.reap_dropped(Duration::from_secs(60));
+    assert_eq!(our_keyspace.dropped.len(), 1);
+    our_keyspace.reap_dropped(Duration::from_secs(0));
+    assert_eq!(our_keyspace.dropped.len(), 0);
+    assert_eq!(
+        our_keyspace
+            .undrop_table(&unsafe_objectid_from_slice!("apps"))
+            .unwrap_err(),
+        DdlError::ObjectNotFound
+    );
+}
+
 #[test]
 fn test_keyspace_try_delete_protected_table() {
     let our_keyspace = Keyspace::empty_default();
@@ -449,3 +737,19 @@ fn test_keyspace_try_delete_protected_table() {
         DdlError::ProtectedObject
     );
 }
+
+#[test]
+fn test_keyspace_stall() {
+    use crate::registry::stall;
+    let our_keyspace = Keyspace::empty_default();
+    assert_eq!(our_keyspace.stall(), 0);
+    assert!(our_keyspace.set_stall(stall::FLUSH_FAILED));
+    assert!(!our_keyspace.set_stall(stall::FLUSH_FAILED));
+    assert!(our_keyspace.set_stall(stall::DISK_FULL));
+    // lifting one stall leaves the other in place
+    assert!(our_keyspace.clear_stall(stall::DISK_FULL));
+    assert_eq!(our_keyspace.stall(), stall::FLUSH_FAILED);
+    assert!(our_keyspace.clear_stall(stall::FLUSH_FAILED));
+    assert!(!our_keyspace.clear_stall(stall::FLUSH_FAILED));
+    assert_eq!(our_keyspace.stall(), 0);
+}
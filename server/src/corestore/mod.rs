@@ -24,36 +24,46 @@
  *
 */
 
+use crate::admin::vtables;
 use crate::corestore::memstore::DdlError;
 use crate::corestore::memstore::Keyspace;
 use crate::corestore::memstore::Memstore;
 use crate::corestore::memstore::ObjectID;
 use crate::corestore::memstore::DEFAULT;
+use crate::corestore::memstore::SYSTEM;
+use crate::corestore::session::{ResumeError, Session, SessionEntity};
 use crate::corestore::table::Table;
 use crate::dbnet::connection::ProtocolConnectionExt;
 use crate::kvengine::KVEngine;
+use crate::protocol::hello::Handshake;
 use crate::protocol::responses;
 use crate::protocol::Query;
 use crate::queryengine;
+use crate::queryengine::idempotency::Tokens;
+use crate::queryengine::verbose::KeyCounts;
 use crate::registry;
+use crate::registry::admission::Priority;
+use crate::registry::auth::{self, AuthError, User};
+use crate::registry::tracking::Tracker;
 use crate::storage;
 use crate::storage::sengine::SnapshotEngine;
 use crate::util::Unwrappable;
 use crate::IoResult;
-use core::borrow::Borrow;
-use core::hash::Hash;
 pub use htable::Data;
 use libsky::TResult;
+use parking_lot::Mutex;
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 pub mod array;
 pub mod buffers;
+pub mod hasher;
 pub mod htable;
 pub mod iarray;
 pub mod lazy;
 pub mod lock;
 pub mod map;
 pub mod memstore;
+pub mod session;
 pub mod table;
 #[cfg(test)]
 mod tests;
@@ -65,7 +75,7 @@ pub type OwnedEntityGroup = OptionTuple<ObjectID>;
 /// A raw borrowed entity (not the struct, but in a tuple form)
 type BorrowedEntityGroupRaw<'a> = OptionTuple<&'a [u8]>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 /// An entity group borrowed from a byte slice
 pub struct BorrowedEntityGroup<'a> {
     va: Option<&'a [u8]>,
@@ -109,6 +119,32 @@ pub struct Corestore {
     store: Arc<Memstore>,
     /// the snapshot engine
     sengine: Arc<SnapshotEngine>,
+    /// the entity swaps made by this instance (used for sessions)
+    entity: SessionEntity,
+    /// the session attached to this instance, if any
+    session: Option<Session>,
+    /// the actions that are disabled for this instance (uppercased)
+    denied: Arc<Vec<Vec<u8>>>,
+    /// the priority class of this instance, unless the authenticated user has one
+    priority: Option<Priority>,
+    /// the pending `FLUSHDB` confirmation token and the table that it is for
+    flush_token: Option<(String, Arc<Table>)>,
+    /// the user that this instance has authenticated as, if any
+    user: Option<Arc<User>>,
+    /// the protocol version and capabilities agreed on with `HELLO`, if any
+    handshake: Option<Handshake>,
+    /// the live store, if this instance is pinned to a frozen copy of it (see
+    /// [`Corestore::pin`])
+    live: Option<Arc<Memstore>>,
+    /// the tracking state of this instance, if it has turned tracking on (see
+    /// [`tracking`](crate::registry::tracking))
+    tracker: Option<Arc<Tracker>>,
+    /// the keys that the current query has counted, if this instance is verbose (see
+    /// [`verbose`](crate::queryengine::verbose))
+    key_counts: Option<Arc<KeyCounts>>,
+    /// the idempotency tokens that this instance has used while it wasn't authenticated (see
+    /// [`idempotency`](crate::queryengine::idempotency))
+    tokens: Option<Arc<Mutex<Tokens>>>,
 }
 
 impl Corestore {
@@ -121,14 +157,32 @@ impl Corestore {
     pub fn clone_store(&self) -> Arc<Memstore> {
         self.store.clone()
     }
+    /// Returns the live store, even if this instance is pinned to a frozen copy of it
+    pub fn clone_live_store(&self) -> Arc<Memstore> {
+        self.live.clone().unwrap_or_else(|| self.store.clone())
+    }
     pub fn default_with_store(store: Memstore, sengine: Arc<SnapshotEngine>) -> Self {
+        Self::default_with_store_ref(Arc::new(store), sengine)
+    }
+    fn default_with_store_ref(store: Arc<Memstore>, sengine: Arc<SnapshotEngine>) -> Self {
         let cks = unsafe { store.get_keyspace_atomic_ref(&DEFAULT).unsafe_unwrap() };
         let ctable = unsafe { cks.get_table_atomic_ref(&DEFAULT).unsafe_unwrap() };
         Self {
             cks: Some(cks),
             ctable: Some(ctable),
-            store: Arc::new(store),
+            store,
             sengine,
+            entity: SessionEntity::default(),
+            session: None,
+            denied: Arc::new(Vec::new()),
+            priority: None,
+            flush_token: None,
+            user: None,
+            handshake: None,
+            live: None,
+            tracker: None,
+            key_counts: None,
+            tokens: None,
         }
     }
     pub fn get_engine(&self) -> &SnapshotEngine {
@@ -147,7 +201,7 @@ impl Corestore {
             BorrowedEntityGroup {
                 va: Some(ks),
                 vb: None,
-            } => match self.store.get_keyspace_atomic_ref(ks) {
+            } => match self.get_keyspace(ks) {
                 Some(ksref) => {
                     self.cks = Some(ksref);
                     self.ctable = None;
@@ -158,8 +212,8 @@ impl Corestore {
             BorrowedEntityGroup {
                 va: Some(ks),
                 vb: Some(tbl),
-            } => match self.store.get_keyspace_atomic_ref(ks) {
-                Some(kspace) => match kspace.get_table_atomic_ref(tbl) {
+            } => match self.get_keyspace(ks) {
+                Some(kspace) => match Self::lookup_table(ks, &kspace, tbl) {
                     Some(tblref) => self.ctable = Some(tblref),
                     None => return Err(DdlError::ObjectNotFound),
                 },
@@ -167,24 +221,327 @@ impl Corestore {
             },
             _ => unsafe { impossible!() },
         }
+        self.entity.record(&entity);
+        if let Some(attached) = &self.session {
+            session::update(attached, &self.entity);
+        }
         Ok(())
     }
-    pub fn get_keyspace<Q>(&self, ksid: &Q) -> Option<Arc<Keyspace>>
-    where
-        ObjectID: Borrow<Q>,
-        Q: Hash + Eq + ?Sized,
-    {
-        self.store.get_keyspace_atomic_ref(ksid)
+    /// Start a new session for the current entity and user, returning its token. A session
+    /// that was already attached to this instance is detached
+    pub fn create_session(&mut self) -> &str {
+        self.detach_session();
+        self.session
+            .insert(session::create(self.entity.clone(), self.user.clone()))
+            .token()
+    }
+    /// Resume the session with the given token, switching to its user and entity. This
+    /// instance is left untouched if the session can't be resumed
+    pub fn resume_session(&mut self, token: &[u8]) -> Result<(), ResumeError> {
+        let (entity, user) = session::get(token).ok_or(ResumeError::Unknown)?;
+        // replay the swaps on a fresh instance so that we're left untouched on failure
+        let mut resumed = self.on_store(self.store.clone());
+        resumed.user = user;
+        for swap in entity.replay() {
+            resumed.swap_entity(swap).map_err(ResumeError::Entity)?;
+        }
+        resumed.drop_inaccessible_entity();
+        if let Some(user) = &resumed.user {
+            auth::resume(user).map_err(|e| match e {
+                AuthError::BadCredentials => ResumeError::Unknown,
+                AuthError::TooManyConnections => ResumeError::TooManyConnections,
+            })?;
+        }
+        match session::attach(token) {
+            Some(attached) => {
+                self.detach_session();
+                self.logout();
+                resumed.session = Some(attached);
+                *self = resumed;
+                Ok(())
+            }
+            None => {
+                resumed.logout();
+                Err(ResumeError::Unknown)
+            }
+        }
+    }
+    /// Pin this instance to `frozen`, a copy of the store that never changes (like a
+    /// snapshot), so that everything that it reads comes from the copy while the live data
+    /// keeps changing. The current keyspace and table are switched to in the copy, and an
+    /// `Err` is returned (leaving this instance untouched) if the copy doesn't have them
+    pub fn pin(&mut self, frozen: Memstore) -> KeyspaceResult<()> {
+        let mut pinned = self.on_store(Arc::new(frozen));
+        pinned.replay_entity(&self.entity)?;
+        pinned.session = self.session.take();
+        pinned.live = Some(self.clone_live_store());
+        *self = pinned;
+        Ok(())
+    }
+    /// Switch this instance back to the live store if it's pinned (see [`Corestore::pin`]),
+    /// returning false if it wasn't. If the current table no longer exists in the live
+    /// store, the instance is switched to the default table
+    pub fn unpin(&mut self) -> bool {
+        let live = match self.live.take() {
+            Some(live) => live,
+            None => return false,
+        };
+        let mut unpinned = self.on_store(live.clone());
+        if unpinned.replay_entity(&self.entity).is_err() {
+            // the current table was dropped while we were pinned
+            unpinned = self.on_store(live);
+        }
+        unpinned.session = self.session.take();
+        if let Some(attached) = &unpinned.session {
+            session::update(attached, &unpinned.entity);
+        }
+        *self = unpinned;
+        true
+    }
+    /// Returns true if this instance is pinned to a frozen copy of the store
+    pub const fn is_pinned(&self) -> bool {
+        self.live.is_some()
+    }
+    /// Create an instance on `store` with the same user, handshake and settings as this one,
+    /// that is on the default table
+    fn on_store(&self, store: Arc<Memstore>) -> Self {
+        let mut instance = Self::default_with_store_ref(store, self.sengine.clone());
+        instance.denied = self.denied.clone();
+        instance.priority = self.priority;
+        instance.user = self.user.clone();
+        instance.handshake = self.handshake;
+        instance.tracker = self.tracker.clone();
+        instance.key_counts = self.key_counts.clone();
+        instance.tokens = self.tokens.clone();
+        instance
+    }
+    /// Create an instance on the live store with the same user and handshake as this one,
+    /// that is on the default table, to run queries on behalf of this one (like the writes
+    /// that are mirrored to a [shadow table](crate::services::shadow))
+    pub fn detach(&self) -> Self {
+        let mut instance = self.on_store(self.clone_live_store());
+        instance.key_counts = None;
+        instance.tokens = None;
+        instance
+    }
+    /// Make the same entity swaps as `entity` did
+    fn replay_entity(&mut self, entity: &SessionEntity) -> KeyspaceResult<()> {
+        for swap in entity.replay() {
+            self.swap_entity(swap)?;
+        }
+        self.drop_inaccessible_entity();
+        Ok(())
+    }
+    /// Detach the session attached to this instance (if any), since it's going away
+    pub fn detach_session(&mut self) {
+        if let Some(attached) = self.session.take() {
+            session::detach(&attached);
+        }
+    }
+    /// Disable the given actions for this instance (and its clones)
+    pub fn set_denied_actions(&mut self, actions: &[String]) {
+        let actions = actions
+            .iter()
+            .map(|action| action.to_ascii_uppercase().into_bytes())
+            .collect();
+        self.denied = Arc::new(actions);
+    }
+    /// Put this instance (and its clones) in the given priority class, unless the user that
+    /// it authenticates as has one
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        self.priority = priority;
+    }
+    /// Returns the priority class of this instance: the authenticated user's, or else the
+    /// one that was set for it (or [`Priority::Application`] if neither has one)
+    pub fn priority(&self) -> Priority {
+        self.user
+            .as_ref()
+            .and_then(|user| user.priority())
+            .or(self.priority)
+            .unwrap_or(Priority::Application)
+    }
+    /// Returns true if the given (uppercased) action is disabled for this instance
+    pub fn is_action_denied(&self, action: &[u8]) -> bool {
+        self.denied.iter().any(|denied| denied == action)
+    }
+    /// Authenticate as the given user (in place of the user that we had authenticated as
+    /// earlier, if any). If the user can't access the current keyspace, then the current
+    /// keyspace and table are unset
+    pub async fn authenticate(&mut self, name: &[u8], token: &[u8]) -> Result<(), AuthError> {
+        let user = auth::authenticate(name, token).await?;
+        self.logout();
+        self.user = Some(user);
+        self.flush_token = None;
+        self.drop_inaccessible_entity();
+        Ok(())
+    }
+    /// Stop counting this instance against the authenticated user's connection limit, since
+    /// it's going away
+    pub fn logout(&mut self) {
+        if let Some(user) = self.user.take() {
+            user.logout();
+        }
+    }
+    /// Returns the name of the user that this instance has authenticated as, if any
+    pub fn user_name(&self) -> Option<&str> {
+        self.user.as_deref().map(User::name)
+    }
+    /// Account for one operation against the authenticated user's rate limit, returning
+    /// false if the user has run out of operations for the current second
+    pub fn take_op(&self) -> bool {
+        self.user.as_ref().map_or(true, |user| user.take_op())
+    }
+    /// Returns the protocol version and capabilities agreed on by this instance, if it
+    /// has said `HELLO`
+    pub const fn handshake(&self) -> Option<Handshake> {
+        self.handshake
+    }
+    pub fn set_handshake(&mut self, handshake: Handshake) {
+        self.handshake = Some(handshake);
+    }
+    /// Turn tracking on for this instance, unless it's already on
+    pub fn start_tracking(&mut self) {
+        if self.tracker.is_none() {
+            self.tracker = Some(Tracker::start());
+        }
+    }
+    /// Turn tracking off for this instance, dropping the invalidations that weren't sent
+    pub fn stop_tracking(&mut self) {
+        self.tracker = None;
+    }
+    /// Returns the tracking state of this instance, if it has turned tracking on
+    pub fn tracker(&self) -> Option<&Arc<Tracker>> {
+        self.tracker.as_ref()
+    }
+    /// Have a metadata frame sent after every response, unless it's already sent
+    pub fn start_verbose(&mut self) {
+        if self.key_counts.is_none() {
+            self.key_counts = Some(Arc::new(KeyCounts::default()));
+        }
+    }
+    /// Stop sending metadata frames
+    pub fn stop_verbose(&mut self) {
+        self.key_counts = None;
+    }
+    /// Returns the keys that the current query has counted, if this instance is verbose
+    pub fn key_counts(&self) -> Option<&Arc<KeyCounts>> {
+        self.key_counts.as_ref()
+    }
+    /// Returns the idempotency tokens that this instance has used while it wasn't
+    /// authenticated
+    pub fn connection_tokens(&mut self) -> Arc<Mutex<Tokens>> {
+        self.tokens.get_or_insert_with(Default::default).clone()
+    }
+    /// Unset the current keyspace and table if the authenticated user can't access them
+    fn drop_inaccessible_entity(&mut self) {
+        let accessible = match (&self.user, &self.cks) {
+            (Some(user), Some(cks)) => self
+                .store
+                .keyspaces
+                .iter()
+                .any(|kv| Arc::ptr_eq(kv.value(), cks) && user.can_access(kv.key())),
+            _ => true,
+        };
+        if !accessible {
+            self.cks = None;
+            self.ctable = None;
+            self.entity = SessionEntity::default();
+        }
+    }
+    /// Returns true if the authenticated user can access the keyspace with the given ID
+    pub fn can_access(&self, ksid: &[u8]) -> bool {
+        self.user
+            .as_ref()
+            .map_or(true, |user| user.can_access(ksid))
+    }
+    /// Returns the name of the current keyspace and the `keyspace:table` name of the current
+    /// table, if they are set
+    pub fn current_entity(&self) -> (Option<String>, Option<String>) {
+        let (mut ks, mut tbl) = (None, None);
+        for keyspace in self.store.keyspaces.iter() {
+            let ksname = unsafe { keyspace.key().as_str() };
+            if matches!(&self.cks, Some(cks) if Arc::ptr_eq(cks, keyspace.value())) {
+                ks = Some(ksname.to_owned());
+            }
+            if let Some(ctable) = &self.ctable {
+                for table in keyspace.value().tables.iter() {
+                    if Arc::ptr_eq(ctable, table.value()) {
+                        let tblname = unsafe { table.key().as_str() };
+                        tbl = Some(format!("{}:{}", ksname, tblname));
+                    }
+                }
+            }
+        }
+        (ks, tbl)
+    }
+    /// Returns the names of the keyspaces that the authenticated user can access
+    pub fn keyspace_names(&self) -> Vec<String> {
+        let mut names = self.store.keyspace_names();
+        names.retain(|ksid| self.can_access(ksid.as_bytes()));
+        names
+    }
+    /// Returns the error response to be written if the given (uppercased) action can't be
+    /// run, either because this instance hasn't authenticated or because the authenticated
+    /// user isn't allowed to run it. If the authenticated user was removed or replaced,
+    /// then this instance is logged out first
+    pub fn check_auth(&mut self, action: &[u8]) -> Option<&'static [u8]> {
+        if self.user.as_ref().map_or(false, |user| user.is_revoked()) {
+            // the user was removed or its password was changed after we authenticated
+            self.logout();
+        }
+        if !auth::is_enabled() {
+            return None;
+        }
+        match &self.user {
+            None if !auth::OPEN_ACTIONS.contains(&action) => Some(responses::groups::AUTH_REQUIRED),
+            Some(user) if !user.is_superuser() && auth::SERVER_ACTIONS.contains(&action) => {
+                Some(responses::groups::PERMISSION_DENIED)
+            }
+            _ => None,
+        }
+    }
+    /// Create a one-time token that confirms a flush of the given table. A token that
+    /// was issued earlier is invalidated
+    pub fn create_flush_token(&mut self, table: &Arc<Table>) -> &str {
+        &self
+            .flush_token
+            .insert((session::new_token(), table.clone()))
+            .0
+    }
+    /// Consume the pending flush token, returning true if it matches the given token and
+    /// was issued for the given table
+    pub fn take_flush_token(&mut self, table: &Arc<Table>, token: &[u8]) -> bool {
+        match self.flush_token.take() {
+            Some((expected, tbl)) => expected.as_bytes() == token && Arc::ptr_eq(&tbl, table),
+            None => false,
+        }
+    }
+    /// Get an atomic reference to a keyspace. Keyspaces that the authenticated user can't
+    /// access are treated as if they don't exist
+    pub fn get_keyspace(&self, ksid: &[u8]) -> Option<Arc<Keyspace>> {
+        if self.can_access(ksid) {
+            self.store.get_keyspace_atomic_ref(ksid)
+        } else {
+            None
+        }
     }
     /// Get an atomic reference to a table
     pub fn get_table(&self, entity: BorrowedEntityGroup) -> KeyspaceResult<Arc<Table>> {
+        self.get_keyspace_and_table(entity).map(|(_, tbl)| tbl)
+    }
+    /// Get atomic references to a table and the keyspace that it is in. A table name alone
+    /// is looked up in the current keyspace
+    pub fn get_keyspace_and_table(
+        &self,
+        entity: BorrowedEntityGroup,
+    ) -> KeyspaceResult<(Arc<Keyspace>, Arc<Table>)> {
         match entity {
             BorrowedEntityGroup {
                 va: Some(ksid),
                 vb: Some(table),
-            } => match self.store.get_keyspace_atomic_ref(ksid) {
-                Some(ks) => match ks.get_table_atomic_ref(table) {
-                    Some(tbl) => Ok(tbl),
+            } => match self.get_keyspace(ksid) {
+                Some(ks) => match Self::lookup_table(ksid, &ks, table) {
+                    Some(tbl) => Ok((ks, tbl)),
                     None => Err(DdlError::ObjectNotFound),
                 },
                 None => Err(DdlError::ObjectNotFound),
@@ -194,7 +551,10 @@ impl Corestore {
                 vb: None,
             } => match &self.cks {
                 Some(ks) => match ks.get_table_atomic_ref(tbl) {
-                    Some(tbl) => Ok(tbl),
+                    Some(tbl) => {
+                        tbl.touch();
+                        Ok((ks.clone(), tbl))
+                    }
                     None => Err(DdlError::ObjectNotFound),
                 },
                 None => Err(DdlError::DefaultNotFound),
@@ -202,9 +562,48 @@ impl Corestore {
             _ => unsafe { impossible!() },
         }
     }
+    /// Look up a table in the keyspace `ksid`. The tables in the `system` keyspace can't be
+    /// looked up, except for its [virtual tables](crate::admin::vtables)
+    fn lookup_table(ksid: &[u8], ks: &Keyspace, tblid: &[u8]) -> Option<Arc<Table>> {
+        if SYSTEM.eq(ksid) {
+            vtables::get(tblid)
+        } else {
+            let tbl = ks.get_table_atomic_ref(tblid)?;
+            tbl.touch();
+            Some(tbl)
+        }
+    }
+    /// Load the current table back into memory if it was unloaded (see [`Table::load`])
+    pub async fn load_ctable(&self) {
+        if let Some(tbl) = &self.ctable {
+            tbl.load().await;
+        }
+    }
     pub fn get_ctable(&self) -> Option<Arc<Table>> {
+        if let Some(tbl) = &self.ctable {
+            tbl.refresh();
+            tbl.touch();
+        }
         self.ctable.clone()
     }
+    /// Get an atomic reference to the current keyspace
+    pub fn get_cks(&self) -> Option<Arc<Keyspace>> {
+        self.cks.clone()
+    }
+    /// Returns the name of the current table (as `<keyspace>:<table>`), if there is one
+    pub fn ctable_name(&self) -> Option<String> {
+        self.ctable.as_ref()?;
+        let name = match self.entity.table() {
+            Some((ks, tbl)) => format!(
+                "{}:{}",
+                String::from_utf8_lossy(ks),
+                String::from_utf8_lossy(tbl)
+            ),
+            // we never left the table that we start with
+            None => "default:default".to_owned(),
+        };
+        Some(name)
+    }
 
     /// Get the key/value store
     ///
@@ -212,10 +611,14 @@ impl Corestore {
     /// the default table is unset
     pub fn get_kvstore(&self) -> KeyspaceResult<&KVEngine> {
         match &self.ctable {
-            Some(tbl) => match tbl.get_kvstore() {
-                Ok(kvs) => Ok(kvs),
-                _ => Err(DdlError::WrongModel),
-            },
+            Some(tbl) => {
+                tbl.refresh();
+                tbl.touch();
+                match tbl.get_kvstore() {
+                    Ok(kvs) => Ok(kvs),
+                    _ => Err(DdlError::WrongModel),
+                }
+            }
             None => Err(DdlError::DefaultNotFound),
         }
     }
@@ -227,13 +630,33 @@ impl Corestore {
     /// This enables the flush routine to permanently write the table to disk. But it's all about
     /// luck -- the next mutual access may be yielded to the next `create table` command
     ///
+    /// The table gets a bloom filter if `bloom` has a false positive rate, and a response
+    /// cache if `respcache` has a size. Tables can't be created in the `system` keyspace
+    ///
     /// **Trip switch handled:** Yes
     pub fn create_table(
         &self,
         entity: OwnedEntityGroup,
         modelcode: u8,
         volatile: bool,
+        bloom: Option<f64>,
+        respcache: Option<usize>,
     ) -> KeyspaceResult<()> {
+        let new_table = || {
+            Table::from_model_code(modelcode, volatile)
+                .map(|tbl| match bloom {
+                    Some(fprate) => tbl.with_bloom(fprate),
+                    None => tbl,
+                })
+                .map(|tbl| match respcache {
+                    Some(maxkeys) => tbl.with_response_cache(maxkeys),
+                    None => tbl,
+                })
+        };
+        if matches!(&entity, (Some(ksid), Some(_)) if ksid.eq(&SYSTEM)) {
+            // the system keyspace only has the tables that we put there
+            return Err(DdlError::ProtectedObject);
+        }
         // first lock the global flush state
         let flush_lock = registry::lock_flush_state();
         let ret;
@@ -242,7 +665,7 @@ impl Corestore {
             (Some(tblid), None) => {
                 ret = match &self.cks {
                     Some(ks) => {
-                        let tbl = Table::from_model_code(modelcode, volatile);
+                        let tbl = new_table();
                         if let Some(tbl) = tbl {
                             if ks.create_table(tblid, tbl) {
                                 // we need to re-init tree; so trip
@@ -259,9 +682,9 @@ impl Corestore {
                 };
             }
             (Some(ksid), Some(tblid)) => {
-                ret = match self.store.get_keyspace_atomic_ref(&ksid) {
+                ret = match self.get_keyspace(&ksid) {
                     Some(kspace) => {
-                        let tbl = Table::from_model_code(modelcode, volatile);
+                        let tbl = new_table();
                         if let Some(tbl) = tbl {
                             if kspace.create_table(tblid, tbl) {
                                 // trip the preload switch
@@ -286,24 +709,48 @@ impl Corestore {
 
     /// Drop a table
     pub fn drop_table(&self, entity: BorrowedEntityGroup) -> KeyspaceResult<()> {
+        // keep the table around for UNDROP if a retention period is set
+        let soft = registry::get_drop_retention().is_some();
         match entity {
             BorrowedEntityGroup {
                 va: Some(tblid),
                 vb: None,
             } => match &self.cks {
+                Some(ks) if soft => ks.soft_drop_table(tblid),
                 Some(ks) => ks.drop_table(tblid),
                 None => Err(DdlError::DefaultNotFound),
             },
             BorrowedEntityGroup {
                 va: Some(ksid),
                 vb: Some(tblid),
-            } => match self.store.get_keyspace_atomic_ref(ksid) {
+            } => match self.get_keyspace(ksid) {
+                Some(ks) if soft => ks.soft_drop_table(tblid),
                 Some(ks) => ks.drop_table(tblid),
                 None => Err(DdlError::ObjectNotFound),
             },
             _ => unsafe { impossible!() },
         }
     }
+    /// Restore a dropped table that hasn't been reaped yet
+    pub fn undrop_table(&self, entity: BorrowedEntityGroup) -> KeyspaceResult<()> {
+        match entity {
+            BorrowedEntityGroup {
+                va: Some(tblid),
+                vb: None,
+            } => match &self.cks {
+                Some(ks) => ks.undrop_table(tblid),
+                None => Err(DdlError::DefaultNotFound),
+            },
+            BorrowedEntityGroup {
+                va: Some(ksid),
+                vb: Some(tblid),
+            } => match self.get_keyspace(ksid) {
+                Some(ks) => ks.undrop_table(tblid),
+                None => Err(DdlError::ObjectNotFound),
+            },
+            _ => unsafe { impossible!() },
+        }
+    }
 
     /// Create a keyspace **without any transactional guarantees**
     ///
@@ -324,20 +771,105 @@ impl Corestore {
         ret
     }
 
+    /// Clone the table `src` into a new table `dst`. The copy shares its values with the
+    /// source table, so only the keys are copied (a value is only written anew when either
+    /// table replaces it). If `volatile` is set, the copy is volatile even if the source
+    /// table isn't
+    ///
+    /// **Trip switch handled:** Yes
+    pub fn clone_table(
+        &self,
+        src: BorrowedEntityGroup,
+        dst: OwnedEntityGroup,
+        volatile: bool,
+    ) -> KeyspaceResult<()> {
+        let (dstks, dsttbl) = match dst {
+            (Some(tblid), None) => match &self.cks {
+                Some(ks) => (ks.clone(), tblid),
+                None => return Err(DdlError::DefaultNotFound),
+            },
+            (Some(ksid), Some(_)) if ksid.eq(&SYSTEM) => return Err(DdlError::ProtectedObject),
+            (Some(ksid), Some(tblid)) => match self.get_keyspace(&ksid) {
+                Some(ks) => (ks, tblid),
+                None => return Err(DdlError::ObjectNotFound),
+            },
+            _ => unsafe { impossible!() },
+        };
+        let (srcks, srctbl) = self.get_keyspace_and_table(src)?;
+        if dstks.get_table_atomic_ref(&dsttbl).is_some() {
+            // don't bother copying the table
+            return Err(DdlError::AlreadyExists);
+        }
+        let mut copy = srcks.rendezvous(|| srctbl.duplicate());
+        if volatile {
+            copy = copy.into_volatile();
+        }
+        // lock the global flush lock (see comment in create_table to know why)
+        let flush_lock = registry::lock_flush_state();
+        let ret = if dstks.create_table(dsttbl, copy) {
+            registry::get_preload_tripswitch().trip();
+            Ok(())
+        } else {
+            Err(DdlError::AlreadyExists)
+        };
+        drop(flush_lock);
+        ret
+    }
+
+    /// Clone the keyspace `src` (with all its tables) into a new keyspace `dst`, sharing
+    /// values like [`Corestore::clone_table`]
+    ///
+    /// **Trip switch handled:** Yes
+    pub fn clone_keyspace(&self, src: &[u8], dst: ObjectID) -> KeyspaceResult<()> {
+        if SYSTEM.eq(src) {
+            return Err(DdlError::ProtectedObject);
+        }
+        let srcks = match self.get_keyspace(src) {
+            Some(ks) => ks,
+            None => return Err(DdlError::ObjectNotFound),
+        };
+        if self.store.get_keyspace_atomic_ref(&dst).is_some() {
+            return Err(DdlError::AlreadyExists);
+        }
+        let copy = srcks.duplicate();
+        let flush_lock = registry::lock_flush_state();
+        let ret = if self.store.create_keyspace_from(dst, copy) {
+            registry::get_preload_tripswitch().trip();
+            Ok(())
+        } else {
+            Err(DdlError::AlreadyExists)
+        };
+        drop(flush_lock);
+        ret
+    }
+
     /// Drop a keyspace
     pub fn drop_keyspace(&self, ksid: ObjectID) -> KeyspaceResult<()> {
+        if !self.can_access(&ksid) {
+            return Err(DdlError::ObjectNotFound);
+        }
         // trip switch is handled by memstore here
         self.store.drop_keyspace(ksid)
     }
 
     /// Force drop a keyspace
     pub fn force_drop_keyspace(&self, ksid: ObjectID) -> KeyspaceResult<()> {
+        if !self.can_access(&ksid) {
+            return Err(DdlError::ObjectNotFound);
+        }
         // trip switch is handled by memstore here
         self.store.force_drop_keyspace(ksid)
     }
 
     /// Execute a query that has already been validated by `Connection::read_query`
-    pub async fn execute_query<T, Strm>(&mut self, query: Query, con: &mut T) -> TResult<()>
+    ///
+    /// If `admin` is set, then only administrative actions will be executed
+    pub async fn execute_query<T, Strm>(
+        &mut self,
+        query: Query,
+        con: &mut T,
+        admin: bool,
+    ) -> TResult<()>
     where
         T: ProtocolConnectionExt<Strm>,
         Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
@@ -345,16 +877,20 @@ impl Corestore {
         match query {
             Query::SimpleQuery(q) => {
                 con.write_simple_query_header().await?;
-                queryengine::execute_simple(self, con, q).await?;
-                con.flush_stream().await?;
+                if admin {
+                    queryengine::execute_simple_admin(self, con, q).await?;
+                } else {
+                    queryengine::execute_simple(self, con, q).await?;
+                }
             }
             // TODO(@ohsayan): Pipeline commands haven't been implemented yet
             Query::PipelinedQuery(_) => {
                 con.write_response(responses::full_responses::R_PIPELINE_UNSUPPORTED)
                     .await?;
-                con.flush_stream().await?;
             }
         }
+        // the response isn't flushed here: the connection flushes before it waits for
+        // more queries, so that the responses to queries sent together go out together
         Ok(())
     }
     pub fn strong_count(&self) -> usize {
@@ -0,0 +1,271 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Sessions
+//!
+//! A session token lets a client that reconnects (say after a network blip) get back the
+//! user it authenticated as and the entity it was using with a single
+//! `SESSION RESUME <token>`, instead of replaying its `AUTH` and all of its `USE`s. A session
+//! is attached to one connection at a time; once that connection goes away, the session is
+//! kept around for [`SESSION_TTL`] before it is expired. The sessions of a user are revoked
+//! once the user is removed or its token or password is changed
+
+use crate::corestore::lazy::Lazy;
+use crate::corestore::memstore::DdlError;
+use crate::corestore::BorrowedEntityGroup;
+use crate::crypto;
+use crate::registry::auth::User;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a session is kept after its connection goes away
+pub const SESSION_TTL: Duration = Duration::from_secs(300);
+/// The number of random bytes in a token (it is hex encoded, so tokens are twice as long)
+const TOKEN_BYTES: usize = 16;
+
+type SessionMap = HashMap<String, SessionState>;
+
+static SESSIONS: Lazy<Mutex<SessionMap>, fn() -> Mutex<SessionMap>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The `USE`s that got a connection to its current entity, in the order they need to be
+/// replayed: the keyspace first and then the table
+pub struct SessionEntity {
+    keyspace: Option<Vec<u8>>,
+    table: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl SessionEntity {
+    /// Record a successful swap to `entity`
+    pub fn record(&mut self, entity: &BorrowedEntityGroup) {
+        match (entity.va, entity.vb) {
+            (Some(ks), None) => {
+                self.keyspace = Some(ks.to_owned());
+                self.table = None;
+            }
+            (Some(ks), Some(tbl)) => self.table = Some((ks.to_owned(), tbl.to_owned())),
+            _ => {}
+        }
+    }
+    /// Returns the keyspace and table of the last table that was swapped to, if any
+    pub fn table(&self) -> Option<(&[u8], &[u8])> {
+        self.table
+            .as_ref()
+            .map(|(ks, tbl)| (ks.as_slice(), tbl.as_slice()))
+    }
+    /// Returns the entities to swap to (in order) to get back to this entity
+    pub fn replay(&self) -> impl Iterator<Item = BorrowedEntityGroup<'_>> {
+        let ks = self
+            .keyspace
+            .as_ref()
+            .map(|ks| BorrowedEntityGroup::from((Some(ks.as_slice()), None)));
+        let tbl = self.table.as_ref().map(|(ks, tbl)| {
+            BorrowedEntityGroup::from((Some(ks.as_slice()), Some(tbl.as_slice())))
+        });
+        ks.into_iter().chain(tbl)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A connection's handle to its session
+pub struct Session {
+    token: String,
+    /// bumped every time the session is resumed, so that a stale connection that goes
+    /// away later doesn't detach the session from the connection that resumed it
+    generation: u64,
+}
+
+impl Session {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// The reasons a session can't be resumed
+pub enum ResumeError {
+    /// The session doesn't exist (or has expired or been revoked)
+    Unknown,
+    /// The session's entity no longer exists
+    Entity(DdlError),
+    /// The session's user already has as many connections as it is allowed to have
+    TooManyConnections,
+}
+
+#[derive(Debug)]
+struct SessionState {
+    entity: SessionEntity,
+    /// the user that the session's connection authenticated as, if any
+    user: Option<Arc<User>>,
+    generation: u64,
+    /// when the session's connection went away, or `None` if it is still attached
+    detached_at: Option<Instant>,
+}
+
+/// Generate a random, hex encoded token
+pub(super) fn new_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    crypto::fill_random(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn purge_expired(sessions: &mut SessionMap, now: Instant) {
+    sessions.retain(|_, state| match state.detached_at {
+        Some(at) => now.saturating_duration_since(at) < SESSION_TTL,
+        None => true,
+    })
+}
+
+/// Create a new session (attached to the calling connection) for `entity` and `user`
+pub fn create(entity: SessionEntity, user: Option<Arc<User>>) -> Session {
+    let mut sessions = SESSIONS.lock();
+    purge_expired(&mut sessions, Instant::now());
+    let token = new_token();
+    sessions.insert(
+        token.clone(),
+        SessionState {
+            entity,
+            user,
+            generation: 0,
+            detached_at: None,
+        },
+    );
+    Session {
+        token,
+        generation: 0,
+    }
+}
+
+/// Returns the entity and the user for the session with the given token, if it hasn't
+/// expired
+pub fn get(token: &[u8]) -> Option<(SessionEntity, Option<Arc<User>>)> {
+    let token = std::str::from_utf8(token).ok()?;
+    let mut sessions = SESSIONS.lock();
+    purge_expired(&mut sessions, Instant::now());
+    sessions
+        .get(token)
+        .map(|state| (state.entity.clone(), state.user.clone()))
+}
+
+/// Revoke all the sessions of the user with the given name (since it was removed or its
+/// credentials were changed)
+pub fn revoke(name: &str) {
+    SESSIONS
+        .lock()
+        .retain(|_, state| state.user.as_ref().map_or(true, |user| user.name() != name));
+}
+
+/// Attach the session with the given token to the calling connection (taking it over from
+/// any other connection that has it). Returns `None` if the session has expired
+pub fn attach(token: &[u8]) -> Option<Session> {
+    let token = std::str::from_utf8(token).ok()?;
+    let mut sessions = SESSIONS.lock();
+    let state = sessions.get_mut(token)?;
+    state.generation += 1;
+    state.detached_at = None;
+    Some(Session {
+        token: token.to_owned(),
+        generation: state.generation,
+    })
+}
+
+/// Update the entity of `session`, if it's still attached to the calling connection
+pub fn update(session: &Session, entity: &SessionEntity) {
+    if let Some(state) = SESSIONS.lock().get_mut(&session.token) {
+        if state.generation == session.generation {
+            state.entity = entity.clone();
+        }
+    }
+}
+
+/// Detach `session` from the calling connection (which is going away), after which the
+/// session will expire in [`SESSION_TTL`] unless it is resumed
+pub fn detach(session: &Session) {
+    if let Some(state) = SESSIONS.lock().get_mut(&session.token) {
+        if state.generation == session.generation {
+            state.detached_at = Some(Instant::now());
+        }
+    }
+}
+
+#[test]
+fn test_session_lifecycle() {
+    let mut entity = SessionEntity::default();
+    entity.record(&BorrowedEntityGroup::from((Some(&b"ks"[..]), None)));
+    entity.record(&BorrowedEntityGroup::from((
+        Some(&b"ks"[..]),
+        Some(&b"tbl"[..]),
+    )));
+    let replayed: Vec<_> = entity.replay().collect();
+    assert_eq!(
+        replayed,
+        vec![
+            BorrowedEntityGroup::from((Some(&b"ks"[..]), None)),
+            BorrowedEntityGroup::from((Some(&b"ks"[..]), Some(&b"tbl"[..])))
+        ]
+    );
+    let first = create(entity.clone(), None);
+    assert_eq!(first.token().len(), TOKEN_BYTES * 2);
+    // the session is taken over by a new connection; the old one going away shouldn't
+    // detach it
+    let second = attach(first.token().as_bytes()).unwrap();
+    detach(&first);
+    update(&first, &SessionEntity::default());
+    assert_eq!(get(first.token().as_bytes()).unwrap().0, entity);
+    // once detached, it only lives for the TTL
+    detach(&second);
+    let mut sessions = SESSIONS.lock();
+    purge_expired(&mut sessions, Instant::now());
+    assert!(sessions.contains_key(second.token()));
+    purge_expired(&mut sessions, Instant::now() + SESSION_TTL);
+    assert!(!sessions.contains_key(second.token()));
+}
+
+#[test]
+fn test_session_unknown_token() {
+    assert!(get(b"nope").is_none());
+    assert!(attach(b"nope").is_none());
+    assert!(attach(b"\xFF").is_none());
+}
+
+#[test]
+fn test_session_revoke() {
+    let user = Arc::new(User::new(
+        "sessionuser".to_owned(),
+        "token".to_owned(),
+        None,
+    ));
+    let session = create(SessionEntity::default(), Some(user));
+    let anonymous = create(SessionEntity::default(), None);
+    let (_, resumed) = get(session.token().as_bytes()).unwrap();
+    assert_eq!(resumed.unwrap().name(), "sessionuser");
+    revoke("sessionuser");
+    assert!(get(session.token().as_bytes()).is_none());
+    assert!(get(anonymous.token().as_bytes()).is_some());
+}
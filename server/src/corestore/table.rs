@@ -28,8 +28,14 @@ use crate::corestore::htable::Coremap;
 use crate::corestore::memstore::DdlError;
 use crate::corestore::Data;
 use crate::corestore::KeyspaceResult;
-use crate::kvengine::KVEngine;
+use crate::kvengine::respcache::ResponseCache;
+use crate::kvengine::{self, KVEngine};
+use crate::registry;
 use crate::storage::bytemarks;
+use crate::storage::unflush;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::Mutex;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub enum DataModel {
@@ -38,6 +44,40 @@ pub enum DataModel {
 
 // same 8 byte ptrs; any chance of optimizations?
 
+/// The source of a virtual table's contents (as `(key, value)` pairs)
+pub type VirtualSource = fn() -> Vec<(String, String)>;
+
+#[derive(Debug)]
+/// Whether a table's data is in memory and when the table was last accessed (see
+/// [`Table::unload`])
+struct Residency {
+    /// when the table was last accessed (in seconds since the UNIX epoch)
+    accessed: AtomicU64,
+    /// set while the table is unloaded, so that looking up a loaded table doesn't need to
+    /// lock `file`
+    unloaded: AtomicBool,
+    /// the file that the data of an unloaded table is read back from
+    file: Mutex<Option<String>>,
+    /// set once the table was warmed up, which keeps it from being unloaded (see
+    /// [`Table::warm`])
+    warm: AtomicBool,
+}
+
+impl Residency {
+    fn new() -> Self {
+        Self {
+            accessed: AtomicU64::new(now_secs()),
+            unloaded: AtomicBool::new(false),
+            file: Mutex::new(None),
+            warm: AtomicBool::new(false),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    kvengine::now_ms() / 1000
+}
+
 #[derive(Debug)]
 /// The underlying table type. This is the place for the other data models (soon!)
 pub struct Table {
@@ -45,6 +85,13 @@ pub struct Table {
     model_store: DataModel,
     /// is the table volatile
     volatile: bool,
+    /// where the contents come from, if this is a virtual table (see
+    /// [`vtables`](crate::admin::vtables))
+    source: Option<VirtualSource>,
+    /// the number of writes run against the table (see [`Table::record_write`])
+    writes: AtomicU64,
+    /// whether the data is in memory (see [`Table::unload`])
+    residency: Residency,
 }
 
 impl Table {
@@ -76,6 +123,101 @@ impl Table {
             _ => unsafe { impossible!() },
         }
     }
+    /// Count a write against the table. This is done for the `@write` actions that are run
+    /// against the current table, which is what the adaptive flush policy goes by (see
+    /// [`bgsave`](crate::services::bgsave))
+    pub fn record_write(&self) {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Returns the number of writes that were run against the table
+    pub fn writes(&self) -> u64 {
+        self.writes.load(Ordering::Relaxed)
+    }
+    /// Record an access to the table by a query. This doesn't load the table's data back
+    /// into memory if it was unloaded; that is done by [`Table::load`] before the query runs
+    pub fn touch(&self) {
+        let now = now_secs();
+        if self.residency.accessed.load(Ordering::Relaxed) != now {
+            self.residency.accessed.store(now, Ordering::Relaxed);
+        }
+    }
+    /// Load the table's data back into memory if it was unloaded (see
+    /// [`Table::ensure_loaded`]). Reading the data back can take a while, so it's done on a
+    /// blocking thread instead of holding up the other connections
+    pub async fn load(self: &Arc<Self>) {
+        if !self.is_unloaded() {
+            return;
+        }
+        let table = self.clone();
+        tokio::task::spawn_blocking(move || table.ensure_loaded())
+            .await
+            .expect("Something caused the table loader to panic");
+    }
+    /// Returns the number of seconds since the table was last accessed by a query
+    pub fn idle_secs(&self) -> u64 {
+        now_secs().saturating_sub(self.residency.accessed.load(Ordering::Relaxed))
+    }
+    /// Returns true if the table's data isn't in memory
+    pub fn is_unloaded(&self) -> bool {
+        self.residency.unloaded.load(Ordering::Acquire)
+    }
+    /// Returns the file that the table's data is in, if the table is unloaded
+    pub fn unloaded_from(&self) -> Option<String> {
+        self.residency.file.lock().clone()
+    }
+    /// Drop the table's data from memory. The data must have just been written to `file`,
+    /// which it is read back from once the table is accessed again (see
+    /// [`Table::ensure_loaded`]). The caller has to make sure that nothing else is using
+    /// the table while it's unloaded
+    pub fn unload(&self, file: String) {
+        let mut unloaded_from = self.residency.file.lock();
+        match &self.model_store {
+            DataModel::KV(kve) => kve.truncate_table(),
+        }
+        *unloaded_from = Some(file);
+        self.residency.unloaded.store(true, Ordering::Release);
+        registry::table_unloaded();
+    }
+    /// Read the table's data back into memory if the table was unloaded. If the data can't
+    /// be read, the table stays unloaded (and empty) and reading it is retried on the next
+    /// access. This blocks while the data is read, so async tasks should use
+    /// [`Table::load`] instead
+    pub fn ensure_loaded(&self) {
+        if !self.is_unloaded() {
+            return;
+        }
+        let mut unloaded_from = self.residency.file.lock();
+        let file = match unloaded_from.as_ref() {
+            Some(file) => file,
+            // another thread loaded it while we were waiting for the lock
+            None => return,
+        };
+        match unflush::read_table_data(file) {
+            Ok((data, expiry)) => {
+                match &self.model_store {
+                    DataModel::KV(kve) => kve.reload(data, expiry),
+                }
+                *unloaded_from = None;
+                self.residency.unloaded.store(false, Ordering::Release);
+                registry::table_reloaded();
+            }
+            Err(e) => log::error!("Failed to load the unloaded table from `{}`: {}", file, e),
+        }
+    }
+    /// Load the table's data if it was unloaded and read through all of it, so that it's in
+    /// memory before the table is first accessed, returning the number of bytes that were
+    /// read. A table that was warmed up is never unloaded
+    pub fn warm(&self) -> usize {
+        self.residency.warm.store(true, Ordering::Release);
+        self.ensure_loaded();
+        match &self.model_store {
+            DataModel::KV(kve) => kve.__get_inner_ref().prefault(),
+        }
+    }
+    /// Returns true if the table was warmed up (see [`Table::warm`])
+    pub fn is_warm(&self) -> bool {
+        self.residency.warm.load(Ordering::Acquire)
+    }
     pub fn truncate_table(&self) {
         match self.model_store {
             DataModel::KV(ref kv) => kv.truncate_table(),
@@ -99,12 +241,18 @@ impl Table {
         Self {
             volatile,
             model_store: DataModel::KV(KVEngine::init_with_data(k_enc, v_enc, data)),
+            source: None,
+            writes: AtomicU64::new(0),
+            residency: Residency::new(),
         }
     }
     pub fn new_kve_with_encoding(volatile: bool, k_enc: bool, v_enc: bool) -> Self {
         Self {
             volatile,
             model_store: DataModel::KV(KVEngine::init(k_enc, v_enc)),
+            source: None,
+            writes: AtomicU64::new(0),
+            residency: Residency::new(),
         }
     }
     pub fn from_model_code(code: u8, volatile: bool) -> Option<Self> {
@@ -117,6 +265,107 @@ impl Table {
         };
         Some(ret)
     }
+    /// Take a copy of the table (see [`KVEngine::snapshot`]), loading it back into memory
+    /// first if it was unloaded
+    pub fn snapshot(&self) -> Self {
+        self.ensure_loaded();
+        let model_store = match &self.model_store {
+            DataModel::KV(kve) => DataModel::KV(kve.snapshot()),
+        };
+        Self {
+            model_store,
+            volatile: self.volatile,
+            source: None,
+            writes: AtomicU64::new(0),
+            residency: Residency::new(),
+        }
+    }
+    /// Make this table volatile
+    pub fn into_volatile(self) -> Self {
+        Self {
+            volatile: true,
+            ..self
+        }
+    }
+    /// Take a copy of the table like [`Table::snapshot`], keeping its bloom filter and its
+    /// response cache (if it has them)
+    pub fn duplicate(&self) -> Self {
+        let copy = self.snapshot();
+        let (fprate, maxkeys) = match &self.model_store {
+            DataModel::KV(kve) => (
+                kve.bloom_fprate(),
+                kve.response_cache().map(ResponseCache::maxkeys),
+            ),
+        };
+        let copy = match fprate {
+            Some(fprate) => copy.with_bloom(fprate),
+            None => copy,
+        };
+        match maxkeys {
+            Some(maxkeys) => copy.with_response_cache(maxkeys),
+            None => copy,
+        }
+    }
+    /// Give the table a bloom filter with the given false positive rate
+    pub fn with_bloom(self, fprate: f64) -> Self {
+        let model_store = match self.model_store {
+            DataModel::KV(kve) => DataModel::KV(kve.with_bloom(fprate)),
+        };
+        Self {
+            model_store,
+            volatile: self.volatile,
+            source: self.source,
+            writes: self.writes,
+            residency: self.residency,
+        }
+    }
+    /// Give the table a response cache that holds up to `maxkeys` responses
+    pub fn with_response_cache(self, maxkeys: usize) -> Self {
+        let model_store = match self.model_store {
+            DataModel::KV(kve) => DataModel::KV(kve.with_response_cache(maxkeys)),
+        };
+        Self {
+            model_store,
+            volatile: self.volatile,
+            source: self.source,
+            writes: self.writes,
+            residency: self.residency,
+        }
+    }
+    /// Create a virtual table, which is a volatile `keymap(str,str)` that is filled from
+    /// `source` every time that it is [refreshed](Table::refresh)
+    pub fn new_virtual(source: VirtualSource) -> Self {
+        Self {
+            source: Some(source),
+            ..Self::new_kve_with_encoding(true, true, true)
+        }
+    }
+    /// Returns true if this is a virtual table
+    pub const fn is_virtual(&self) -> bool {
+        self.source.is_some()
+    }
+    /// Fill a virtual table with the current contents of its source (this does nothing for
+    /// other tables). Keys that are still in the source are overwritten in place, so
+    /// concurrent reads never miss them
+    pub fn refresh(&self) {
+        let (source, kve) = match (self.source, &self.model_store) {
+            (Some(source), DataModel::KV(kve)) => (source, kve),
+            _ => return,
+        };
+        let pairs = source();
+        let stale: Vec<Data> = kve
+            .__get_inner_ref()
+            .iter()
+            .filter(|kv| !pairs.iter().any(|(key, _)| key.as_bytes() == &kv.key()[..]))
+            .map(|kv| kv.key().clone())
+            .collect();
+        for key in stale {
+            kve.remove_unchecked(&key[..]);
+        }
+        for (key, value) in pairs {
+            kve.upsert_unchecked(Data::from_string(key), Data::from_string(value));
+        }
+    }
     /// Create a new kve with default settings but with provided volatile configuration
     pub fn new_kve_with_volatile(volatile: bool) -> Self {
         Self::new_kve_with_data(Coremap::new(), volatile, false, false)
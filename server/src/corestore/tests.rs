@@ -119,3 +119,46 @@ mod memstore_keyspace_tests {
         assert!(ms.force_drop_keyspace(obj).is_ok());
     }
 }
+
+mod corestore_auth_tests {
+    use super::super::memstore::*;
+    use super::super::{BorrowedEntityGroup, Corestore};
+    use crate::registry::auth::User;
+    use crate::storage::sengine::SnapshotEngine;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_keyspace_isolation() {
+        let mut db = Corestore::default_with_store(
+            Memstore::new_default(),
+            Arc::new(SnapshotEngine::new_disabled()),
+        );
+        db.create_keyspace(unsafe { ObjectID::from_slice("acme") })
+            .unwrap();
+        db.create_keyspace(unsafe { ObjectID::from_slice("other") })
+            .unwrap();
+        db.user = Some(Arc::new(User::new(
+            "acme".to_owned(),
+            "secret".to_owned(),
+            Some(vec!["acme".to_owned()]),
+        )));
+        // we were in `default`, which this user can't see
+        db.drop_inaccessible_entity();
+        assert!(db.get_ctable().is_none());
+        assert!(db.get_keyspace(b"default").is_none());
+        assert_eq!(
+            db.swap_entity(BorrowedEntityGroup::from((Some(&b"other"[..]), None)))
+                .unwrap_err(),
+            DdlError::ObjectNotFound
+        );
+        assert!(db
+            .swap_entity(BorrowedEntityGroup::from((Some(&b"acme"[..]), None)))
+            .is_ok());
+        assert_eq!(db.keyspace_names(), vec!["acme".to_owned()]);
+        assert_eq!(
+            db.drop_keyspace(unsafe { ObjectID::from_slice("other") })
+                .unwrap_err(),
+            DdlError::ObjectNotFound
+        );
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! The FIPS backend: the system's OpenSSL in FIPS mode for everything, with passwords hashed
+//! with PBKDF2-HMAC-SHA256 (since argon2 isn't an approved algorithm) and TLS 1.2 as the
+//! oldest version that listeners accept
+//!
+//! Password hashes are encoded as `$pbkdf2-sha256$i=<iterations>$<salt>$<hash>` (with the
+//! salt and the hash in hex). Users whose passwords were hashed by a build without FIPS
+//! (with argon2) can't authenticate and need their passwords set again
+
+use super::{ossl, Provider, TlsProvider, SALT_BYTES};
+use crate::config::SslOpts;
+use crate::registry::tls::TlsVersion;
+use core::future::Future;
+use core::pin::Pin;
+use libsky::TResult;
+use openssl::hash::MessageDigest;
+use openssl::ssl::SslAcceptor;
+use tokio::net::TcpStream;
+use tokio_openssl::SslStream;
+
+/// The prefix of our password hashes
+const SCHEME: &str = "$pbkdf2-sha256$";
+/// The number of PBKDF2 iterations for new password hashes
+const ITERATIONS: usize = 600_000;
+/// The number of bytes in a password hash
+const HASH_BYTES: usize = 32;
+
+/// The FIPS backend
+pub struct Fips;
+
+impl Provider for Fips {
+    const NAME: &'static str = "fips";
+    fn init() -> TResult<()> {
+        openssl::fips::enable(true)
+            .map_err(|e| format!("Failed to turn on the FIPS mode of OpenSSL: {}", e))?;
+        log::info!("OpenSSL is running in FIPS mode");
+        Ok(())
+    }
+    fn fill_random(buf: &mut [u8]) {
+        openssl::rand::rand_bytes(buf).expect("Failed to generate random bytes");
+    }
+    fn hash_password(password: &[u8]) -> String {
+        let mut salt = [0u8; SALT_BYTES];
+        Self::fill_random(&mut salt);
+        let hash = pbkdf2(password, &salt, ITERATIONS).expect("Failed to hash a password");
+        format!(
+            "{}i={}${}${}",
+            SCHEME,
+            ITERATIONS,
+            to_hex(&salt),
+            to_hex(&hash)
+        )
+    }
+    fn verify_password(hash: &str, password: &[u8]) -> bool {
+        let parsed = hash.strip_prefix(SCHEME).and_then(|fields| {
+            let mut fields = fields.split('$');
+            let iterations = fields.next()?.strip_prefix("i=")?.parse().ok()?;
+            let salt = from_hex(fields.next()?)?;
+            let hash = from_hex(fields.next()?)?;
+            match fields.next() {
+                Some(_) => None,
+                None => Some((iterations, salt, hash)),
+            }
+        });
+        match parsed {
+            Some((iterations, salt, hash)) if hash.len() == HASH_BYTES => {
+                match pbkdf2(password, &salt, iterations) {
+                    Ok(computed) => openssl::memcmp::eq(&computed, &hash),
+                    Err(_) => false,
+                }
+            }
+            _ => {
+                log::warn!("A password hash isn't in the FIPS backend's format");
+                false
+            }
+        }
+    }
+}
+
+impl TlsProvider for Fips {
+    type Acceptor = SslAcceptor;
+    type Stream = SslStream<TcpStream>;
+    fn acceptor(ssl: SslOpts) -> TResult<SslAcceptor> {
+        ossl::acceptor(ssl, Some(TlsVersion::Tls12))
+    }
+    fn accept(
+        acceptor: &SslAcceptor,
+        stream: TcpStream,
+    ) -> Pin<Box<dyn Future<Output = TResult<SslStream<TcpStream>>> + Send + '_>> {
+        Box::pin(ossl::accept(acceptor, stream))
+    }
+}
+
+fn pbkdf2(
+    password: &[u8],
+    salt: &[u8],
+    iterations: usize,
+) -> Result<[u8; HASH_BYTES], openssl::error::ErrorStack> {
+    let mut hash = [0u8; HASH_BYTES];
+    openssl::pkcs5::pbkdf2_hmac(
+        password,
+        salt,
+        iterations,
+        MessageDigest::sha256(),
+        &mut hash,
+    )?;
+    Ok(hash)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[test]
+fn test_fips_password_hash() {
+    let hash = Fips::hash_password(b"hunter2");
+    assert!(hash.starts_with("$pbkdf2-sha256$i=600000$"));
+    assert!(Fips::verify_password(&hash, b"hunter2"));
+    assert!(!Fips::verify_password(&hash, b"hunter3"));
+    assert!(!Fips::verify_password(
+        "$argon2id$v=19$m=4096,t=3,p=1$c2FsdA$aGFzaA",
+        b"hunter2"
+    ));
+}
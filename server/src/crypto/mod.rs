@@ -0,0 +1,132 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Cryptography backends
+//!
+//! Everything that the server needs from a cryptography library goes through a
+//! [`Provider`]: random bytes for salts and tokens, password hashes for users added with
+//! `SYS USER ADD` and (with the `tls` feature) the [TLS listeners](TlsProvider). The
+//! [`Backend`] is picked with cargo features:
+//! - by default, [`Standard`](standard::Standard) hashes passwords with argon2, takes random
+//! bytes from the OS and runs TLS on OpenSSL
+//! - with the `fips` feature, [`Fips`](fips::Fips) does all three with the system's OpenSSL
+//! in FIPS mode (hashing passwords with PBKDF2), so that only FIPS validated cryptography
+//! is used. Since OpenSSL has to be the system's validated build, this can't be combined
+//! with the `vendored-openssl` feature
+//!
+//! A new backend is a type that implements the traits and is picked as the [`Backend`]; the
+//! rest of the server (and the listeners in particular) only ever uses the [`Backend`]
+
+#[cfg(feature = "tls")]
+use crate::config::SslOpts;
+#[cfg(feature = "tls")]
+use crate::dbnet::tcp::BufferedSocketStream;
+#[cfg(feature = "tls")]
+use core::future::Future;
+#[cfg(feature = "tls")]
+use core::pin::Pin;
+use libsky::TResult;
+#[cfg(feature = "tls")]
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "tls")]
+use tokio::net::TcpStream;
+#[cfg(feature = "fips")]
+mod fips;
+#[cfg(feature = "tls")]
+mod ossl;
+#[cfg(not(feature = "fips"))]
+mod standard;
+
+#[cfg(all(feature = "fips", feature = "vendored-openssl"))]
+compile_error!("The `fips` feature needs the system's OpenSSL; disable `vendored-openssl`");
+
+#[cfg(not(feature = "fips"))]
+/// The backend that this build uses
+pub type Backend = standard::Standard;
+#[cfg(feature = "fips")]
+/// The backend that this build uses
+pub type Backend = fips::Fips;
+
+/// The number of random bytes in a password's salt
+const SALT_BYTES: usize = 16;
+
+/// A cryptography backend
+pub trait Provider {
+    /// The name of the backend, as reported by `SYS INFO`
+    const NAME: &'static str;
+    /// Get the backend ready; this is called once, before anything else is used
+    fn init() -> TResult<()> {
+        Ok(())
+    }
+    /// Fill `buf` with cryptographically secure random bytes
+    fn fill_random(buf: &mut [u8]);
+    /// Hash a password with a random salt, returning the encoded hash
+    fn hash_password(password: &[u8]) -> String;
+    /// Returns true if `password` matches an encoded hash from [`Provider::hash_password`]
+    fn verify_password(hash: &str, password: &[u8]) -> bool;
+}
+
+#[cfg(feature = "tls")]
+/// A backend that can run TLS listeners
+pub trait TlsProvider: Provider {
+    /// What is built from a listener's TLS settings, and accepts its connections
+    type Acceptor: Send + Sync;
+    /// An accepted TLS connection
+    type Stream: BufferedSocketStream + AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static;
+    /// Build an acceptor from a listener's TLS settings (including its
+    /// [policy](crate::registry::tls))
+    fn acceptor(ssl: SslOpts) -> TResult<Self::Acceptor>;
+    /// Run the TLS handshake with a client that has just connected
+    fn accept(
+        acceptor: &Self::Acceptor,
+        stream: TcpStream,
+    ) -> Pin<Box<dyn Future<Output = TResult<Self::Stream>> + Send + '_>>;
+}
+
+/// Get the backend ready
+pub fn init() -> TResult<()> {
+    Backend::init()
+}
+
+/// Returns the name of the backend
+pub const fn name() -> &'static str {
+    Backend::NAME
+}
+
+/// Fill `buf` with cryptographically secure random bytes
+pub fn fill_random(buf: &mut [u8]) {
+    Backend::fill_random(buf)
+}
+
+/// Hash a password with a random salt, returning the encoded hash
+pub fn hash_password(password: &[u8]) -> String {
+    Backend::hash_password(password)
+}
+
+/// Returns true if `password` matches an encoded hash
+pub fn verify_password(hash: &str, password: &[u8]) -> bool {
+    Backend::verify_password(hash, password)
+}
@@ -0,0 +1,178 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! OpenSSL, which the TLS of the [`Standard`](super::standard::Standard) and
+//! [`Fips`](super::fips::Fips) backends runs on
+
+use crate::config::SslOpts;
+use crate::dbnet::tcp::BufferedSocketStream;
+use crate::registry::tls::{self, TlsPolicy, TlsVersion};
+use libsky::TResult;
+use openssl::error::ErrorStack;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::ssl::{
+    self, AlpnError, Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslOptions,
+    SslSessionCacheMode, SslVersion,
+};
+use std::ffi::CString;
+use std::fs;
+use std::io::Error as IoError;
+use std::os::raw::{c_int, c_void};
+use std::pin::Pin;
+use tokio::net::TcpStream;
+use tokio_openssl::SslStream;
+
+impl BufferedSocketStream for SslStream<TcpStream> {
+    fn client_name(&self) -> String {
+        self.get_ref().client_name()
+    }
+}
+
+/// Identifies our sessions in the session cache
+const SESSION_ID_CONTEXT: &[u8] = b"skyd";
+
+/// Turn a list of protocols into the ALPN wire format (each protocol prefixed by its length)
+fn alpn_wire_format(protocols: &[String]) -> Vec<u8> {
+    let mut wire = Vec::new();
+    for protocol in protocols {
+        wire.push(protocol.len() as u8);
+        wire.extend_from_slice(protocol.as_bytes());
+    }
+    wire
+}
+
+/// Set up session resumption and ALPN as configured
+fn set_session_opts(builder: &mut SslAcceptorBuilder, opts: &SslOpts) -> TResult<()> {
+    if opts.resumption {
+        // clients can resume with a session ID (from our cache) or with a session ticket,
+        // which saves them a full handshake
+        builder.set_session_cache_mode(SslSessionCacheMode::SERVER);
+        builder.set_session_id_context(SESSION_ID_CONTEXT)?;
+    } else {
+        builder.set_session_cache_mode(SslSessionCacheMode::OFF);
+        builder.set_options(SslOptions::NO_TICKET);
+    }
+    if !opts.alpn.is_empty() {
+        let protocols = alpn_wire_format(&opts.alpn);
+        builder.set_alpn_select_callback(move |_, client| {
+            // clients that don't offer any of our protocols go on without ALPN
+            ssl::select_next_proto(&protocols, client).ok_or(AlpnError::NOACK)
+        });
+    }
+    Ok(())
+}
+
+/// `SSL_CTRL_SET_GROUPS_LIST` (what `SSL_CTX_set1_groups_list` expands to), which the
+/// `openssl` crate doesn't wrap
+const SSL_CTRL_SET_GROUPS_LIST: c_int = 92;
+
+/// Restrict the versions, cipher suites and curves that clients can negotiate
+fn set_policy(builder: &mut SslAcceptorBuilder, policy: &TlsPolicy) -> TResult<()> {
+    if let Some(version) = policy.min_version {
+        let version = match version {
+            TlsVersion::Tls12 => SslVersion::TLS1_2,
+            TlsVersion::Tls13 => SslVersion::TLS1_3,
+        };
+        builder.set_min_proto_version(Some(version))?;
+    }
+    if let Some(ciphers) = &policy.ciphers {
+        builder.set_cipher_list(ciphers)?;
+    }
+    if let Some(ciphersuites) = &policy.ciphersuites {
+        builder.set_ciphersuites(ciphersuites)?;
+    }
+    if let Some(curves) = &policy.curves {
+        let curves = CString::new(curves.join(":"))
+            .map_err(|_| "The TLS curves can't have NUL bytes".to_owned())?;
+        let ret = unsafe {
+            // SAFETY: The context is alive for as long as the builder is, and OpenSSL copies
+            // the list out of the string
+            openssl_sys::SSL_CTX_ctrl(
+                builder.as_ptr(),
+                SSL_CTRL_SET_GROUPS_LIST,
+                0,
+                curves.as_ptr() as *mut c_void,
+            )
+        };
+        if ret != 1 {
+            return Err(ErrorStack::get().into());
+        }
+    }
+    Ok(())
+}
+
+/// Build an acceptor from a listener's TLS settings, using `min_version` as the oldest TLS
+/// version if the listener's policy doesn't set one
+pub fn acceptor(mut ssl: SslOpts, min_version: Option<TlsVersion>) -> TResult<SslAcceptor> {
+    if ssl.policy.min_version.is_none() {
+        ssl.policy.min_version = min_version;
+    }
+    let mut acceptor_builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    set_session_opts(&mut acceptor_builder, &ssl)?;
+    set_policy(&mut acceptor_builder, &ssl.policy)?;
+    let SslOpts {
+        key: key_file,
+        chain: chain_file,
+        passfile: tls_passfile,
+        port,
+        policy,
+        ..
+    } = ssl;
+    // cert is the same for both
+    acceptor_builder.set_certificate_chain_file(chain_file)?;
+    if let Some(tls_passfile) = tls_passfile {
+        // first read in the private key
+        let tls_private_key = fs::read(key_file).map_err(|e: IoError| {
+            format!("Failed to read TLS private key file with error: {}", e)
+        })?;
+        // read the passphrase because the passphrase file stream was provided
+        let tls_keyfile_stream = fs::read(tls_passfile).map_err(|e: IoError| {
+            format!(
+                "Failed to read TLS private key passphrase file with error: {}",
+                e
+            )
+        })?;
+        // decrypt the private key
+        let pkey = Rsa::private_key_from_pem_passphrase(&tls_private_key, &tls_keyfile_stream)?;
+        let pkey = PKey::from_rsa(pkey)?;
+        // set the private key for the acceptor
+        acceptor_builder.set_private_key(&pkey)?;
+    } else {
+        // no passphrase, needs interactive
+        acceptor_builder.set_private_key_file(key_file, SslFiletype::PEM)?;
+    }
+    tls::register(port, policy);
+    Ok(acceptor_builder.build())
+}
+
+/// Run the TLS handshake with a client that has just connected
+pub async fn accept(acceptor: &SslAcceptor, stream: TcpStream) -> TResult<SslStream<TcpStream>> {
+    let ssl = Ssl::new(acceptor.context())?;
+    let mut stream = SslStream::new(ssl, stream)?;
+    Pin::new(&mut stream).accept().await?;
+    Ok(stream)
+}
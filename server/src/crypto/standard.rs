@@ -0,0 +1,84 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! The default backend: argon2 for passwords, the OS for random bytes and OpenSSL for TLS
+
+use super::Provider;
+use super::SALT_BYTES;
+#[cfg(feature = "tls")]
+use super::{ossl, TlsProvider};
+#[cfg(feature = "tls")]
+use crate::config::SslOpts;
+use argon2::{Config, Variant};
+#[cfg(feature = "tls")]
+use core::future::Future;
+#[cfg(feature = "tls")]
+use core::pin::Pin;
+#[cfg(feature = "tls")]
+use libsky::TResult;
+#[cfg(feature = "tls")]
+use openssl::ssl::SslAcceptor;
+#[cfg(feature = "tls")]
+use tokio::net::TcpStream;
+#[cfg(feature = "tls")]
+use tokio_openssl::SslStream;
+
+/// The default backend
+pub struct Standard;
+
+impl Provider for Standard {
+    const NAME: &'static str = "standard";
+    fn fill_random(buf: &mut [u8]) {
+        getrandom::getrandom(buf).expect("Failed to generate random bytes");
+    }
+    fn hash_password(password: &[u8]) -> String {
+        let mut salt = [0u8; SALT_BYTES];
+        Self::fill_random(&mut salt);
+        let config = Config {
+            variant: Variant::Argon2id,
+            ..Config::default()
+        };
+        argon2::hash_encoded(password, &salt, &config).expect("Failed to hash a password")
+    }
+    fn verify_password(hash: &str, password: &[u8]) -> bool {
+        argon2::verify_encoded(hash, password).unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl TlsProvider for Standard {
+    type Acceptor = SslAcceptor;
+    type Stream = SslStream<TcpStream>;
+    fn acceptor(ssl: SslOpts) -> TResult<SslAcceptor> {
+        ossl::acceptor(ssl, None)
+    }
+    fn accept(
+        acceptor: &SslAcceptor,
+        stream: TcpStream,
+    ) -> Pin<Box<dyn Future<Output = TResult<SslStream<TcpStream>>> + Send + '_>> {
+        Box::pin(ossl::accept(acceptor, stream))
+    }
+}
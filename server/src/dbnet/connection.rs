@@ -36,14 +36,21 @@
 //! respones in compliance with the Skyhash protocol.
 
 use super::tcp::Connection;
+use crate::allocator::{self, Subsystem};
 use crate::corestore::Corestore;
 use crate::dbnet::tcp::BufferedSocketStream;
+use crate::dbnet::tcp::CountedStream;
 use crate::dbnet::Terminator;
 use crate::protocol;
 use crate::protocol::responses;
 use crate::protocol::ParseError;
 use crate::protocol::Query;
+use crate::queryengine::verbose::Metadata;
+use crate::registry;
+use crate::registry::admission::{self, Priority};
+use crate::registry::clients::{self, ClientStats};
 use crate::resp::Writable;
+use crate::services::querylog;
 use crate::IoResult;
 use bytes::Buf;
 use bytes::BytesMut;
@@ -54,19 +61,27 @@ use std::io::ErrorKind;
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufWriter;
 use tokio::sync::mpsc;
 use tokio::sync::Semaphore;
 
+/// How often a connection adds what it has done to its client's totals
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
 pub const SIMPLE_QUERY_HEADER: [u8; 3] = [b'*', b'1', b'\n'];
 
 pub enum QueryResult {
-    Q(Query),
+    /// a query, its size in bytes and the instant by which it should be run (if the client
+    /// gave it a deadline)
+    Q(Query, usize, Option<Instant>),
     E(&'static [u8]),
     Empty,
     Wrongtype,
+    /// the query is larger than the per-connection buffer limit
+    TooLarge,
 }
 
 pub mod prelude {
@@ -133,7 +148,10 @@ pub mod prelude {
         ($entity:expr, $store:expr, $con:expr) => {{
             use crate::corestore::memstore::DdlError;
             match $store.get_table($entity) {
-                Ok(tbl) => tbl,
+                Ok(tbl) => {
+                    tbl.load().await;
+                    tbl
+                }
                 Err(DdlError::DefaultNotFound) => {
                     return conwrite!($con, crate::protocol::responses::groups::DEFAULT_UNSET);
                 }
@@ -154,6 +172,34 @@ pub mod prelude {
         }};
     }
     #[macro_export]
+    macro_rules! get_ks_and_tbl {
+        ($entity:expr, $store:expr, $con:expr) => {{
+            use crate::corestore::memstore::DdlError;
+            match $store.get_keyspace_and_table($entity) {
+                Ok((ks, tbl)) => {
+                    tbl.load().await;
+                    (ks, tbl)
+                }
+                Err(DdlError::DefaultNotFound) => {
+                    return conwrite!($con, crate::protocol::responses::groups::DEFAULT_UNSET);
+                }
+                Err(DdlError::ObjectNotFound) => {
+                    return conwrite!(
+                        $con,
+                        crate::protocol::responses::groups::CONTAINER_NOT_FOUND
+                    );
+                }
+                Err(_) => unsafe { impossible!() },
+            }
+        }};
+        ($store:expr, $con:expr) => {{
+            match ($store.get_cks(), $store.get_ctable()) {
+                (Some(ks), Some(tbl)) => (ks, tbl),
+                _ => return conwrite!($con, crate::protocol::responses::groups::DEFAULT_UNSET),
+            }
+        }};
+    }
+    #[macro_export]
     macro_rules! handle_entity {
         ($con:expr, $ident:expr) => {{
             match crate::queryengine::parser::get_query_entity(&$ident) {
@@ -204,11 +250,14 @@ where
         })
     }
     /// Try to parse a query from the buffered data
-    fn try_query(&self) -> Result<(Query, usize), ParseError> {
+    fn try_query(&self) -> Result<(Query, usize, Option<u64>), ParseError> {
         if self.get_buffer().is_empty() {
             return Err(ParseError::Empty);
         }
-        protocol::Parser::new(self.get_buffer()).parse()
+        // the parsed query ends up in the tables, so it's accounted for under them
+        allocator::scope(Subsystem::Coremap, || {
+            protocol::Parser::new(self.get_buffer()).parse_with_deadline()
+        })
     }
     /// Read a query from the remote end
     ///
@@ -225,19 +274,48 @@ where
             let mv_self = self;
             let _: Result<QueryResult, IoError> = {
                 loop {
-                    mv_self.read_again().await?;
                     match mv_self.try_query() {
-                        Ok((query, forward_by)) => {
+                        Ok((query, forward_by, deadline)) => {
                             mv_self.advance_buffer(forward_by);
-                            return Ok(QueryResult::Q(query));
+                            // a query that waited in the buffer behind others has been
+                            // waiting since it was received, not since it was parsed (and a
+                            // deadline too far out to represent is no deadline at all)
+                            let received_at = mv_self.get_received_at();
+                            let deadline = deadline
+                                .and_then(|ms| received_at.checked_add(Duration::from_millis(ms)));
+                            return Ok(QueryResult::Q(query, forward_by, deadline));
+                        }
+                        Err(ParseError::Empty) | Err(ParseError::NotEnough) => {
+                            // we only read once the buffered queries have been run, so a client
+                            // that doesn't read its responses stops being read from (instead
+                            // of having its queries pile up in the buffer)
+                            if mv_self.get_buffer().len() >= registry::get_max_buffer() {
+                                mv_self.clear_buffer();
+                                return Ok(QueryResult::TooLarge);
+                            }
+                            // send out the responses to the queries we've run before we
+                            // wait; queries that were sent together get their responses
+                            // coalesced into as few writes as possible
+                            mv_self.flush_stream().await?;
+                            mv_self.read_again().await?;
+                            if mv_self.get_buffer().is_empty() {
+                                return Ok(QueryResult::Empty);
+                            }
+                        }
+                        Err(ParseError::DatatypeParseFailure) => {
+                            mv_self.clear_buffer();
+                            return Ok(QueryResult::Wrongtype);
                         }
-                        Err(ParseError::Empty) => return Ok(QueryResult::Empty),
-                        Err(ParseError::NotEnough) => (),
-                        Err(ParseError::DatatypeParseFailure) => return Ok(QueryResult::Wrongtype),
-                        Err(ParseError::UnexpectedByte) | Err(ParseError::BadPacket) => {
+                        Err(ParseError::UnexpectedByte)
+                        | Err(ParseError::BadPacket)
+                        | Err(ParseError::TrailingBytes)
+                        | Err(ParseError::OversizeSizeline)
+                        | Err(ParseError::EmbeddedNewline) => {
+                            mv_self.clear_buffer();
                             return Ok(QueryResult::E(responses::full_responses::R_PACKET_ERR));
                         }
                         Err(ParseError::UnknownDatatype) => {
+                            mv_self.clear_buffer();
                             return Ok(QueryResult::E(
                                 responses::full_responses::R_UNKNOWN_DATA_TYPE,
                             ));
@@ -400,6 +478,20 @@ pub trait ProtocolConnection<Strm> {
     fn clear_buffer(&mut self) {
         self.get_mut_buffer().clear()
     }
+    /// Returns the name that the client goes by in the per-client metrics (if it isn't
+    /// authenticated)
+    fn get_client(&self) -> &str {
+        "local"
+    }
+    /// Returns the number of bytes read from and written to the stream so far
+    fn get_io_counts(&self) -> (u64, u64) {
+        (0, 0)
+    }
+    /// Returns when the data in the read buffer was last received; query deadlines are
+    /// counted from here
+    fn get_received_at(&self) -> Instant {
+        Instant::now()
+    }
 }
 
 // Give ProtocolConnection implementors a free ProtocolConnectionExt impl
@@ -411,25 +503,34 @@ where
 {
 }
 
-impl<T> ProtocolConnection<T> for Connection<T>
+impl<T> ProtocolConnection<CountedStream<T>> for Connection<T>
 where
     T: BufferedSocketStream,
 {
     fn get_buffer(&self) -> &BytesMut {
         &self.buffer
     }
-    fn get_stream(&self) -> &BufWriter<T> {
+    fn get_stream(&self) -> &BufWriter<CountedStream<T>> {
         &self.stream
     }
     fn get_mut_buffer(&mut self) -> &mut BytesMut {
         &mut self.buffer
     }
-    fn get_mut_stream(&mut self) -> &mut BufWriter<T> {
+    fn get_mut_stream(&mut self) -> &mut BufWriter<CountedStream<T>> {
         &mut self.stream
     }
-    fn get_mut_both(&mut self) -> (&mut BytesMut, &mut BufWriter<T>) {
+    fn get_mut_both(&mut self) -> (&mut BytesMut, &mut BufWriter<CountedStream<T>>) {
         (&mut self.buffer, &mut self.stream)
     }
+    fn get_client(&self) -> &str {
+        &self.client
+    }
+    fn get_io_counts(&self) -> (u64, u64) {
+        self.stream.get_ref().get_counts()
+    }
+    fn get_received_at(&self) -> Instant {
+        self.stream.get_ref().received_at()
+    }
 }
 
 /// # A generic connection handler
@@ -448,6 +549,13 @@ where
     climit: Arc<Semaphore>,
     terminator: Terminator,
     _term_sig_tx: mpsc::Sender<()>,
+    admin: bool,
+    /// what this connection has done since it last added to its client's totals
+    stats: ClientStats,
+    /// the bytes read and written up to the last time that they were counted
+    io_counts: (u64, u64),
+    /// when this connection last added to its client's totals
+    last_recorded: Instant,
     _marker: PhantomData<Strm>,
 }
 
@@ -462,6 +570,7 @@ where
         climit: Arc<Semaphore>,
         terminator: Terminator,
         _term_sig_tx: mpsc::Sender<()>,
+        admin: bool,
     ) -> Self {
         Self {
             db,
@@ -469,20 +578,146 @@ where
             climit,
             terminator,
             _term_sig_tx,
+            admin,
+            stats: ClientStats::default(),
+            io_counts: (0, 0),
+            last_recorded: Instant::now(),
             _marker: PhantomData,
         }
     }
+    /// Returns the user that this connection authenticated as (like `user:app`), or its
+    /// address if it didn't
+    fn client_name(&self) -> String {
+        match self.db.user_name() {
+            Some(user) => format!("user:{}", user),
+            None => self.con.get_client().to_owned(),
+        }
+    }
+    /// Returns the priority class of this connection (connections on admin listeners are
+    /// always [`Priority::Admin`])
+    fn priority(&self) -> Priority {
+        if self.admin {
+            Priority::Admin
+        } else {
+            self.db.priority()
+        }
+    }
+    /// Returns the bytes written to the stream so far, including the ones that are still
+    /// buffered
+    fn bytes_out(&self) -> u64 {
+        self.con.get_io_counts().1 + self.con.get_stream().buffer().len() as u64
+    }
+    /// Count the bytes that were read and written since the last time, and add what this
+    /// connection has done to its client's totals if it has been a while (or if `now` is set)
+    fn record_stats(&mut self, now: bool) {
+        let (read, written) = self.con.get_io_counts();
+        self.stats.bytes_in += read - self.io_counts.0;
+        self.stats.bytes_out += written - self.io_counts.1;
+        self.io_counts = (read, written);
+        if self.stats.is_empty() || !(now || self.last_recorded.elapsed() >= STATS_INTERVAL) {
+            return;
+        }
+        clients::record(&self.client_name(), &self.stats);
+        self.stats = ClientStats::default();
+        self.last_recorded = Instant::now();
+    }
     pub async fn run(&mut self) -> TResult<()> {
+        if !self.admin && registry::pressure::refuse_connection() {
+            // the server is running out of memory; admin connections are still let in so
+            // that it can be looked into
+            return Ok(());
+        }
         while !self.terminator.is_termination_signal() {
             let try_df = tokio::select! {
                 tdf = self.con.read_query() => tdf,
                 _ = self.terminator.receive_signal() => {
                     return Ok(());
                 }
+                _ = idle_timeout() => {
+                    // the client neither sent a query nor kept the connection alive
+                    registry::reaped_connection();
+                    return Ok(());
+                }
+                _ = invalidations(&self.db) => {
+                    // keys that this connection read have changed; the frames are flushed
+                    // along with the responses, when we next wait for a query
+                    if let Some(tracker) = self.db.tracker() {
+                        for invalidation in tracker.take_pending() {
+                            self.con.write_response(invalidation.encode()).await?;
+                        }
+                    }
+                    continue;
+                }
             };
             match try_df {
-                Ok(QueryResult::Q(s)) => {
-                    self.db.execute_query(s, &mut self.con).await?;
+                Ok(QueryResult::Q(_, _, Some(deadline))) if Instant::now() > deadline => {
+                    // the client has stopped waiting for this one, so don't bother running it
+                    registry::deadline_exceeded();
+                    self.con
+                        .write_response(responses::full_responses::R_DEADLINE_EXCEEDED)
+                        .await?
+                }
+                Ok(QueryResult::Q(..)) if admission::should_shed(self.priority()) => {
+                    // the server is overloaded; turn the query away before it adds to the
+                    // queue, so that the ones that are already in it finish on time
+                    self.con
+                        .write_response(responses::full_responses::R_BUSY)
+                        .await?
+                }
+                Ok(QueryResult::Q(..)) if !self.db.take_op() => {
+                    // the user has run out of operations for this second
+                    self.con
+                        .write_response(responses::full_responses::R_RATE_LIMITED)
+                        .await?
+                }
+                Ok(QueryResult::Q(s, size, _)) => {
+                    let sample = if querylog::should_sample() {
+                        Some((
+                            querylog::action_of(&s),
+                            self.db.ctable_name(),
+                            self.bytes_out(),
+                            Instant::now(),
+                        ))
+                    } else {
+                        None
+                    };
+                    // queries that turn verbose mode on or off don't get a metadata frame
+                    let verbose = match self.db.key_counts() {
+                        Some(counts) => {
+                            counts.take();
+                            Some((self.bytes_out(), Instant::now()))
+                        }
+                        None => None,
+                    };
+                    let queued = admission::enqueue();
+                    let query = self.db.execute_query(s, &mut self.con, self.admin);
+                    allocator::tagged(Subsystem::Coremap, query).await?;
+                    drop(queued);
+                    if let (Some((bytes_out, start)), Some(counts)) =
+                        (verbose, self.db.key_counts())
+                    {
+                        let (keys_examined, keys_modified) = counts.take();
+                        let metadata = Metadata {
+                            duration_us: start.elapsed().as_micros() as u64,
+                            keys_examined,
+                            keys_modified,
+                            bytes_read: size as u64,
+                            bytes_written: self.bytes_out().saturating_sub(bytes_out),
+                        };
+                        self.con.write_response(metadata.encode()).await?;
+                    }
+                    if let Some((action, entity, bytes_out, start)) = sample {
+                        querylog::log(querylog::Entry::new(
+                            self.client_name(),
+                            action,
+                            entity,
+                            start.elapsed().as_micros() as u64,
+                            size as u64,
+                            self.bytes_out().saturating_sub(bytes_out),
+                        ));
+                    }
+                    self.stats.ops += 1;
+                    self.record_stats(false);
                 }
                 Ok(QueryResult::E(r)) => self.con.close_conn_with_error(r).await?,
                 Ok(QueryResult::Wrongtype) => {
@@ -490,6 +725,13 @@ where
                         .close_conn_with_error(responses::groups::WRONGTYPE_ERR.to_owned())
                         .await?
                 }
+                Ok(QueryResult::TooLarge) => {
+                    // we can't skip the rest of the query, so we can't go on
+                    self.con
+                        .close_conn_with_error(responses::full_responses::R_QUERY_TOO_LARGE)
+                        .await?;
+                    return Ok(());
+                }
                 Ok(QueryResult::Empty) => return Ok(()),
                 #[cfg(windows)]
                 Err(e) => match e.kind() {
@@ -504,6 +746,23 @@ where
     }
 }
 
+/// Resolves once the idle timeout elapses (or never, if there is no idle timeout)
+async fn idle_timeout() {
+    match registry::get_idle_timeout() {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves once there are invalidations to be pushed to the client (or never, if the
+/// connection isn't tracking the keys it reads)
+async fn invalidations(db: &Corestore) {
+    match db.tracker() {
+        Some(tracker) => tracker.notified().await,
+        None => std::future::pending().await,
+    }
+}
+
 impl<T, Strm> Drop for ConnectionHandler<T, Strm>
 where
     T: ProtocolConnectionExt<Strm>,
@@ -513,5 +772,10 @@ where
         // Make sure that the permit is returned to the semaphore
         // in the case that there is a panic inside
         self.climit.add_permits(1);
+        // this has to happen before we log out, so that it counts against the right user
+        self.record_stats(true);
+        // the session (if any) outlives the connection, so that it can be resumed
+        self.db.detach_session();
+        self.db.logout();
     }
 }
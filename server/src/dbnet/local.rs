@@ -0,0 +1,180 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Local listeners
+//!
+//! Clients on the same machine can connect over a unix domain socket (or a named pipe on
+//! Windows) instead of TCP, set with the `socket` key in the `server` section of the config.
+//! Local connections speak Skyhash just like TCP connections do, with the actions that are
+//! disabled on the primary listener disabled on them too
+
+use crate::allocator::{self, Subsystem};
+use crate::corestore::Corestore;
+use crate::dbnet::connection::ConnectionHandler;
+use crate::dbnet::tcp::{BufferedSocketStream, Connection};
+use crate::dbnet::{BaseListener, Terminator};
+use libsky::TResult;
+use std::io::Error as IoError;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::time;
+
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+#[cfg(unix)]
+use tokio::net::{UnixListener as LocalSocket, UnixStream as LocalStream};
+#[cfg(windows)]
+/// The pipe instance that the next client connects to
+type LocalSocket = NamedPipeServer;
+#[cfg(windows)]
+/// A pipe instance that a client has connected to
+type LocalStream = NamedPipeServer;
+
+impl BufferedSocketStream for LocalStream {}
+
+/// A listener for a unix domain socket or, on Windows, a named pipe
+pub struct LocalListener {
+    /// On Windows, this is the pipe instance that the next client connects to
+    pub base: BaseListener<LocalSocket>,
+    /// The path of the socket (or the name of the pipe)
+    path: String,
+}
+
+impl LocalListener {
+    pub fn init(
+        db: &Corestore,
+        path: String,
+        semaphore: Arc<Semaphore>,
+        signal: broadcast::Sender<()>,
+    ) -> Result<Self, IoError> {
+        let (terminate_tx, terminate_rx) = mpsc::channel(1);
+        let listener = bind(&path)?;
+        Ok(Self {
+            base: BaseListener {
+                db: db.clone(),
+                listener,
+                climit: semaphore,
+                signal,
+                terminate_tx,
+                terminate_rx,
+                admin: false,
+            },
+            path,
+        })
+    }
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// Accept an incoming connection, backing off on errors just like the TCP listener
+    async fn accept(&mut self) -> TResult<LocalStream> {
+        let mut backoff = 1;
+        loop {
+            match self.try_accept().await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    if backoff > 64 {
+                        return Err(e.into());
+                    }
+                }
+            }
+            time::sleep(Duration::from_secs(backoff)).await;
+            backoff *= 2;
+        }
+    }
+    #[cfg(unix)]
+    async fn try_accept(&mut self) -> Result<LocalStream, IoError> {
+        let (stream, _) = self.base.listener.accept().await?;
+        Ok(stream)
+    }
+    #[cfg(windows)]
+    /// A pipe instance serves a single client, so a new instance is created for the next
+    /// client once one connects
+    async fn try_accept(&mut self) -> Result<LocalStream, IoError> {
+        self.base.listener.connect().await?;
+        let next = ServerOptions::new().create(&self.path)?;
+        Ok(core::mem::replace(&mut self.base.listener, next))
+    }
+    /// Run the listener
+    pub async fn run(&mut self) -> TResult<()> {
+        loop {
+            // Take the permit first, but we won't use it right now
+            // that's why we will forget it
+            self.base.climit.acquire().await.unwrap().forget();
+            let stream = skip_loop_err!(self.accept().await);
+            let mut chandle = ConnectionHandler::new(
+                self.base.db.clone(),
+                Connection::new(stream),
+                self.base.climit.clone(),
+                Terminator::new(self.base.signal.subscribe()),
+                self.base.terminate_tx.clone(),
+                self.base.admin,
+            );
+            tokio::spawn(allocator::tagged(Subsystem::Connections, async move {
+                if let Err(e) = chandle.run().await {
+                    log::error!("Error: {}", e);
+                }
+            }));
+        }
+    }
+    /// Signal the listener to shut down and only return after all its connections have
+    /// shut down
+    pub async fn release_self(self) {
+        let Self { base, path } = self;
+        base.release_self().await;
+        unbind(&path);
+    }
+}
+
+#[cfg(unix)]
+/// Bind to the unix domain socket at `path`. A socket file that was left behind by a server
+/// that didn't shut down cleanly is removed first (but any other file at `path` is an error)
+fn bind(path: &str) -> Result<LocalSocket, IoError> {
+    use std::os::unix::fs::FileTypeExt;
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if meta.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    LocalSocket::bind(path)
+}
+
+#[cfg(unix)]
+/// Remove the socket file, which stays around after the listener is closed
+fn unbind(path: &str) {
+    let _ = std::fs::remove_file(path);
+}
+
+#[cfg(windows)]
+/// Create the first instance of the named pipe `path` (such as `\\.\pipe\skyd`). This fails
+/// if some other process already has a pipe with the same name
+fn bind(path: &str) -> Result<LocalSocket, IoError> {
+    ServerOptions::new().first_pipe_instance(true).create(path)
+}
+
+#[cfg(windows)]
+/// A named pipe goes away along with its last instance
+fn unbind(_path: &str) {}
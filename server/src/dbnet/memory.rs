@@ -0,0 +1,98 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # In-memory connections
+//!
+//! Queries that the server runs on its own (for [embedded](crate::embedded) instances or
+//! for [shadow tables](crate::services::shadow), say) go through a [`MemoryConnection`],
+//! which collects the response in memory instead of writing it to a socket
+
+use crate::corestore::Corestore;
+use crate::dbnet::connection::prelude::*;
+use crate::dbnet::connection::ProtocolConnection;
+use crate::protocol::{ParseError, Parser};
+use crate::IoResult;
+use bytes::BytesMut;
+use std::io::{Cursor, Error as IoError, ErrorKind};
+use tokio::io::BufWriter;
+
+/// Execute a query packet on `db`, returning the response packet
+pub async fn execute(db: &mut Corestore, query: &[u8]) -> IoResult<Vec<u8>> {
+    let mut con = MemoryConnection::new();
+    match Parser::new(query).parse() {
+        Ok((query, _)) => db
+            .execute_query(query, &mut con, false)
+            .await
+            .map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))?,
+        Err(e) => {
+            let resp = match e {
+                ParseError::DatatypeParseFailure => groups::WRONGTYPE_ERR,
+                ParseError::UnknownDatatype => groups::UNKNOWN_DATA_TYPE,
+                _ => groups::PACKET_ERR,
+            };
+            con.write_simple_query_header().await?;
+            con.close_conn_with_error(resp).await?;
+        }
+    }
+    con.flush_stream().await?;
+    Ok(con.into_response())
+}
+
+/// A connection that writes responses to memory
+pub struct MemoryConnection {
+    buffer: BytesMut,
+    stream: BufWriter<Cursor<Vec<u8>>>,
+}
+
+impl MemoryConnection {
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            stream: BufWriter::new(Cursor::new(Vec::new())),
+        }
+    }
+    pub(crate) fn into_response(self) -> Vec<u8> {
+        self.stream.into_inner().into_inner()
+    }
+}
+
+impl ProtocolConnection<Cursor<Vec<u8>>> for MemoryConnection {
+    fn get_buffer(&self) -> &BytesMut {
+        &self.buffer
+    }
+    fn get_stream(&self) -> &BufWriter<Cursor<Vec<u8>>> {
+        &self.stream
+    }
+    fn get_mut_buffer(&mut self) -> &mut BytesMut {
+        &mut self.buffer
+    }
+    fn get_mut_stream(&mut self) -> &mut BufWriter<Cursor<Vec<u8>>> {
+        &mut self.stream
+    }
+    fn get_mut_both(&mut self) -> (&mut BytesMut, &mut BufWriter<Cursor<Vec<u8>>>) {
+        (&mut self.buffer, &mut self.stream)
+    }
+}
@@ -38,26 +38,47 @@
 //! is used to parse the stream
 //! 5. Now errors are handled if they occur. Otherwise, the query is executed by `Corestore::execute_query()`
 //!
+//! Clients on the same machine can also connect over a unix domain socket (or a named pipe on
+//! Windows), which is handled by the [`local`] listener
+//!
 
+use self::local::LocalListener;
 use self::tcp::Listener;
+use crate::config::ListenerConfig;
 use crate::config::PortConfig;
+#[cfg(feature = "tls")]
 use crate::config::SslOpts;
 use crate::corestore::Corestore;
+use core::future::Future;
+use core::pin::Pin;
 use libsky::TResult;
 use std::io::Error as IoError;
 use std::net::IpAddr;
+use std::net::SocketAddr;
 use std::sync::Arc;
+#[cfg(feature = "tls")]
 use tls::SslListener;
 use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::TcpSocket;
+use tokio::net::TcpStream;
 use tokio::sync::Semaphore;
 use tokio::sync::{broadcast, mpsc};
 pub mod connection;
 #[macro_use]
 mod macros;
-mod tcp;
+mod local;
+pub mod memory;
+pub mod tcp;
+#[cfg(feature = "tls")]
 mod tls;
 
 pub const MAXIMUM_CONNECTION_LIMIT: usize = 50000;
+/// The default limit on the bytes buffered for a connection's queries (128 MiB)
+pub const DEFAULT_MAX_BUFFER: usize = 128 * 1024 * 1024;
+/// The ALPN protocols that TLS listeners can offer (all of them are served by the query
+/// engine, so new ones need a handler before they can be added here)
+pub const ALPN_PROTOCOLS: [&str; 1] = ["skyhash"];
 
 /// Responsible for gracefully shutting down the server instead of dying randomly
 // Sounds very sci-fi ;)
@@ -91,12 +112,12 @@ impl Terminator {
     }
 }
 
-/// The base TCP listener
-pub struct BaseListener {
+/// The base listener (a TCP listener, unless it is a [`LocalListener`])
+pub struct BaseListener<L = TcpListener> {
     /// An atomic reference to the coretable
     pub db: Corestore,
     /// The incoming connection listener (binding)
-    pub listener: TcpListener,
+    pub listener: L,
     /// The maximum number of connections
     pub climit: Arc<Semaphore>,
     /// The shutdown broadcaster
@@ -105,6 +126,9 @@ pub struct BaseListener {
     // We send a clone of `terminate_tx` to each `CHandler`
     pub terminate_tx: mpsc::Sender<()>,
     pub terminate_rx: mpsc::Receiver<()>,
+    /// If this is true, then only administrative actions can be run on connections
+    /// accepted by this listener
+    pub admin: bool,
 }
 
 impl BaseListener {
@@ -112,19 +136,30 @@ impl BaseListener {
         db: &Corestore,
         host: IpAddr,
         port: u16,
+        v6only: bool,
+        admin: bool,
         semaphore: Arc<Semaphore>,
         signal: broadcast::Sender<()>,
     ) -> Result<Self, IoError> {
         let (terminate_tx, terminate_rx) = mpsc::channel(1);
+        let listener = if v6only {
+            bind_v6only(SocketAddr::new(host, port))?
+        } else {
+            TcpListener::bind((host, port)).await?
+        };
         Ok(Self {
             db: db.clone(),
-            listener: TcpListener::bind((host, port)).await?,
+            listener,
             climit: semaphore,
             signal,
             terminate_tx,
             terminate_rx,
+            admin,
         })
     }
+}
+
+impl<L> BaseListener<L> {
     pub async fn release_self(self) {
         let Self {
             mut terminate_rx,
@@ -138,6 +173,80 @@ impl BaseListener {
     }
 }
 
+#[cfg(unix)]
+/// Bind to an IPv6 address without accepting IPv4-mapped connections. This lets an IPv4 listener
+/// and an IPv6 wildcard listener share the same port
+fn bind_v6only(addr: SocketAddr) -> Result<TcpListener, IoError> {
+    use std::os::unix::io::AsRawFd;
+    let socket = TcpSocket::new_v6()?;
+    let on: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_V6ONLY,
+            &on as *const libc::c_int as *const libc::c_void,
+            core::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(IoError::last_os_error());
+    }
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(1024)
+}
+
+#[cfg(not(unix))]
+/// IPv6 sockets are already IPv6-only by default on non-unix systems
+fn bind_v6only(addr: SocketAddr) -> Result<TcpListener, IoError> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener)
+}
+
+#[cfg(unix)]
+/// Enable TCP keepalive probes on an accepted connection (if a keepalive interval is set), so
+/// that clients which went away without closing their connection are detected
+fn set_keepalive(stream: &TcpStream) -> Result<(), IoError> {
+    use std::os::unix::io::AsRawFd;
+    let interval = match crate::registry::get_keepalive() {
+        Some(interval) => interval.as_secs() as libc::c_int,
+        None => return Ok(()),
+    };
+    let setopt = |level, name, value: libc::c_int| {
+        let ret = unsafe {
+            libc::setsockopt(
+                stream.as_raw_fd(),
+                level,
+                name,
+                &value as *const libc::c_int as *const libc::c_void,
+                core::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(())
+        }
+    };
+    setopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        setopt(libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, interval)?;
+        setopt(libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, interval)?;
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = interval;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+/// TCP keepalive isn't configured on non-unix systems
+fn set_keepalive(_stream: &TcpStream) -> Result<(), IoError> {
+    Ok(())
+}
+
 /// This macro returns the bind address of a listener
 ///
 /// We were just very lazy, so we just used a macro instead of a member function
@@ -160,28 +269,43 @@ macro_rules! bindaddr {
 /// - The `Multi` variant holds both an `SslListener` and a `Listener`
 ///     This variant enables listening to both secure and insecure sockets at the same time
 ///     asynchronously
+/// - The `Local` variant holds a `LocalListener` (a unix domain socket or a named pipe)
 pub enum MultiListener {
+    #[cfg(feature = "tls")]
     SecureOnly(SslListener),
     InsecureOnly(Listener),
+    #[cfg(feature = "tls")]
     Multi(Listener, SslListener),
+    Local(LocalListener),
 }
 
 impl MultiListener {
     /// Create a new `InsecureOnly` listener
     pub fn new_insecure_only(base: BaseListener) -> Result<Self, String> {
-        log::info!("Server started on: skyhash://{}", bindaddr!(base));
+        if base.admin {
+            log::info!("Admin listener started on: skyhash://{}", bindaddr!(base));
+        } else {
+            log::info!("Server started on: skyhash://{}", bindaddr!(base));
+        }
         Ok(MultiListener::InsecureOnly(Listener { base }))
     }
+    #[cfg(feature = "tls")]
     /// Create a new `SecureOnly` listener
     pub fn new_secure_only(base: BaseListener, ssl: SslOpts) -> Result<Self, String> {
         let bindaddr = bindaddr!(base);
+        let admin = base.admin;
         let slf = MultiListener::SecureOnly(
-            SslListener::new_pem_based_ssl_connection(ssl.key, ssl.chain, base, ssl.passfile)
+            SslListener::new_pem_based_ssl_connection(ssl, base)
                 .map_err(|e| format!("Couldn't bind to secure port: {}", e))?,
         );
-        log::info!("Server started on: skyhash-secure://{}", bindaddr);
+        if admin {
+            log::info!("Admin listener started on: skyhash-secure://{}", bindaddr);
+        } else {
+            log::info!("Server started on: skyhash-secure://{}", bindaddr);
+        }
         Ok(slf)
     }
+    #[cfg(feature = "tls")]
     /// Create a new `Multi` listener that has both a secure and an insecure listener
     pub async fn new_multi(
         ssl_base_listener: BaseListener,
@@ -190,13 +314,8 @@ impl MultiListener {
     ) -> Result<Self, String> {
         let sec_bindaddr = bindaddr!(ssl_base_listener);
         let insec_binaddr = bindaddr!(tcp_base_listener);
-        let secure_listener = SslListener::new_pem_based_ssl_connection(
-            ssl.key,
-            ssl.chain,
-            ssl_base_listener,
-            ssl.passfile,
-        )
-        .map_err(|e| format!("Couldn't bind to secure port: {}", e))?;
+        let secure_listener = SslListener::new_pem_based_ssl_connection(ssl, ssl_base_listener)
+            .map_err(|e| format!("Couldn't bind to secure port: {}", e))?;
         let insecure_listener = Listener {
             base: tcp_base_listener,
         };
@@ -207,14 +326,22 @@ impl MultiListener {
         );
         Ok(MultiListener::Multi(insecure_listener, secure_listener))
     }
+    /// Create a new `Local` listener
+    pub fn new_local(listener: LocalListener) -> Self {
+        log::info!("Local listener started on: {}", listener.path());
+        MultiListener::Local(listener)
+    }
     /// Start the server
     ///
     /// The running of single and/or parallel listeners is handled by this function by
     /// exploiting the working of async functions
     pub async fn run_server(&mut self) -> TResult<()> {
         match self {
+            #[cfg(feature = "tls")]
             MultiListener::SecureOnly(secure_listener) => secure_listener.run().await,
             MultiListener::InsecureOnly(insecure_listener) => insecure_listener.run().await,
+            MultiListener::Local(local_listener) => local_listener.run().await,
+            #[cfg(feature = "tls")]
             MultiListener::Multi(insecure_listener, secure_listener) => {
                 let insec = insecure_listener.run();
                 let sec = secure_listener.run();
@@ -236,7 +363,10 @@ impl MultiListener {
     pub async fn finish_with_termsig(self) {
         match self {
             MultiListener::InsecureOnly(server) => server.base.release_self().await,
+            #[cfg(feature = "tls")]
             MultiListener::SecureOnly(server) => server.base.release_self().await,
+            MultiListener::Local(server) => server.release_self().await,
+            #[cfg(feature = "tls")]
             MultiListener::Multi(insecure, secure) => {
                 insecure.base.release_self().await;
                 secure.base.release_self().await;
@@ -245,37 +375,163 @@ impl MultiListener {
     }
 }
 
-/// Initialize the database networking
-pub async fn connect(
+/// A group of [`MultiListener`]s that share the same connection limit and are run
+/// in parallel
+pub struct ListenerGroup {
+    listeners: Vec<MultiListener>,
+}
+
+impl ListenerGroup {
+    /// Start all the listeners in this group
+    pub async fn run_server(&mut self) {
+        run_all(&mut self.listeners).await
+    }
+    /// Signal all the listeners to shut down and only return after they have shut down
+    ///
+    /// **Do note:** This function doesn't flush the `Corestore` object! The **caller has to
+    /// make sure that the data is saved!**
+    pub async fn finish_with_termsig(self) {
+        for listener in self.listeners {
+            listener.finish_with_termsig().await;
+        }
+    }
+}
+
+/// Run all the listeners in `listeners` in parallel
+fn run_all(listeners: &mut [MultiListener]) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+    Box::pin(async move {
+        if let Some((first, rest)) = listeners.split_first_mut() {
+            let (ret, _) = tokio::join!(first.run_server(), run_all(rest));
+            if let Err(e) = ret {
+                log::error!("Listener failed with: {}", e);
+            }
+        }
+    })
+}
+
+/// Returns true if `host` is an IPv6 address and some IPv4 binding uses the same `port`. In that
+/// case, the IPv6 socket has to be IPv6-only, or the bind would fail on dual-stack hosts
+fn needs_v6only(host: IpAddr, port: u16, bindings: &[(IpAddr, u16)]) -> bool {
+    host.is_ipv6()
+        && bindings
+            .iter()
+            .any(|(bhost, bport)| bhost.is_ipv4() && *bport == port)
+}
+
+/// Initialize a single listener
+async fn init_listener(
     ports: PortConfig,
-    maxcon: usize,
-    db: Corestore,
-    signal: broadcast::Sender<()>,
+    admin: bool,
+    bindings: &[(IpAddr, u16)],
+    climit: &Arc<Semaphore>,
+    db: &Corestore,
+    signal: &broadcast::Sender<()>,
 ) -> Result<MultiListener, String> {
-    let climit = Arc::new(Semaphore::const_new(maxcon));
     let server = match ports {
         PortConfig::InsecureOnly { host, port } => MultiListener::new_insecure_only(
-            BaseListener::init(&db, host, port, climit.clone(), signal.clone())
-                .await
-                .map_err(|e| format!("Failed to bind to TCP port with error: {}", e))?,
+            BaseListener::init(
+                db,
+                host,
+                port,
+                needs_v6only(host, port, bindings),
+                admin,
+                climit.clone(),
+                signal.clone(),
+            )
+            .await
+            .map_err(|e| format!("Failed to bind to TCP port with error: {}", e))?,
         )?,
+        #[cfg(feature = "tls")]
         PortConfig::SecureOnly { host, ssl } => MultiListener::new_secure_only(
-            BaseListener::init(&db, host, ssl.port, climit.clone(), signal.clone())
-                .await
-                .map_err(|e| format!("Failed to initialize secure port with error: {}", e))?,
+            BaseListener::init(
+                db,
+                host,
+                ssl.port,
+                needs_v6only(host, ssl.port, bindings),
+                admin,
+                climit.clone(),
+                signal.clone(),
+            )
+            .await
+            .map_err(|e| format!("Failed to initialize secure port with error: {}", e))?,
             ssl,
         )?,
+        #[cfg(feature = "tls")]
         PortConfig::Multi { host, port, ssl } => {
-            let secure_listener =
-                BaseListener::init(&db, host, ssl.port, climit.clone(), signal.clone())
-                    .await
-                    .map_err(|e| format!("Failed to bind to TCP port with error: {}", e))?;
-            let insecure_listener =
-                BaseListener::init(&db, host, port, climit.clone(), signal.clone())
-                    .await
-                    .map_err(|e| format!("Failed to initialize secure port with error: {}", e))?;
+            let secure_listener = BaseListener::init(
+                db,
+                host,
+                ssl.port,
+                needs_v6only(host, ssl.port, bindings),
+                admin,
+                climit.clone(),
+                signal.clone(),
+            )
+            .await
+            .map_err(|e| format!("Failed to initialize secure port with error: {}", e))?;
+            let insecure_listener = BaseListener::init(
+                db,
+                host,
+                port,
+                needs_v6only(host, port, bindings),
+                admin,
+                climit.clone(),
+                signal.clone(),
+            )
+            .await
+            .map_err(|e| format!("Failed to bind to TCP port with error: {}", e))?;
             MultiListener::new_multi(secure_listener, insecure_listener, ssl).await?
         }
+        #[cfg(not(feature = "tls"))]
+        PortConfig::SecureOnly { .. } | PortConfig::Multi { .. } => {
+            return Err(
+                "This build of skyd doesn't support TLS (it needs the `tls` feature)".to_owned(),
+            );
+        }
     };
     Ok(server)
 }
+
+/// Initialize the database networking
+///
+/// `ports` is the primary listener (with the actions in `deny` disabled) while `listeners`
+/// holds any additional listeners. If a `socket` is given, local clients can connect to it
+/// with the same actions disabled as on the primary listener. All the listeners share the
+/// same connection limit
+pub async fn connect(
+    ports: PortConfig,
+    deny: Vec<String>,
+    listeners: Vec<ListenerConfig>,
+    socket: Option<String>,
+    maxcon: usize,
+    db: Corestore,
+    signal: broadcast::Sender<()>,
+) -> Result<ListenerGroup, String> {
+    let climit = Arc::new(Semaphore::const_new(maxcon));
+    let mut bindings = ports.get_bindings();
+    listeners
+        .iter()
+        .for_each(|listener| bindings.extend(listener.ports.get_bindings()));
+    let mut group = Vec::with_capacity(listeners.len() + 2);
+    let mut ldb = db.clone();
+    ldb.set_denied_actions(&deny);
+    group.push(init_listener(ports, false, &bindings, &climit, &ldb, &signal).await?);
+    if let Some(socket) = socket {
+        let local = LocalListener::init(&ldb, socket, climit.clone(), signal.clone())
+            .map_err(|e| format!("Failed to bind to local socket with error: {}", e))?;
+        group.push(MultiListener::new_local(local));
+    }
+    for ListenerConfig {
+        ports,
+        admin,
+        deny,
+        priority,
+    } in listeners
+    {
+        let mut ldb = db.clone();
+        ldb.set_denied_actions(&deny);
+        ldb.set_priority(priority);
+        group.push(init_listener(ports, admin, &bindings, &climit, &ldb, &signal).await?);
+    }
+    Ok(ListenerGroup { listeners: group })
+}
@@ -24,24 +24,107 @@
  *
 */
 
+use crate::allocator::{self, Subsystem};
 use crate::dbnet::connection::ConnectionHandler;
 use crate::dbnet::BaseListener;
 use crate::dbnet::Terminator;
 use crate::protocol;
+use crate::IoResult;
 use bytes::BytesMut;
 use libsky::TResult;
 use libsky::BUF_CAP;
 pub use protocol::ParseResult;
 pub use protocol::Query;
-use std::time::Duration;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 use tokio::io::BufWriter;
+use tokio::io::ReadBuf;
 use tokio::net::TcpStream;
 use tokio::time;
 
-pub trait BufferedSocketStream: AsyncWrite {}
+pub trait BufferedSocketStream: AsyncWrite {
+    /// Returns the name that the client at the other end goes by in the per-client
+    /// metrics (if it isn't authenticated)
+    fn client_name(&self) -> String {
+        "local".to_owned()
+    }
+}
+
+impl BufferedSocketStream for TcpStream {
+    fn client_name(&self) -> String {
+        // the port changes with every connection, so leave it out
+        self.peer_addr()
+            .map_or_else(|_| "unknown".to_owned(), |addr| addr.ip().to_string())
+    }
+}
+
+/// A stream that counts the bytes that are read from and written to it
+pub struct CountedStream<T> {
+    inner: T,
+    read: u64,
+    written: u64,
+    /// when bytes were last read from the stream
+    received_at: Instant,
+}
 
-impl BufferedSocketStream for TcpStream {}
+impl<T> CountedStream<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            read: 0,
+            written: 0,
+            received_at: Instant::now(),
+        }
+    }
+    /// Returns the number of bytes read and written so far
+    pub const fn get_counts(&self) -> (u64, u64) {
+        (self.read, self.written)
+    }
+    /// Returns when bytes were last read from the stream
+    pub const fn received_at(&self) -> Instant {
+        self.received_at
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for CountedStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let before = buf.filled().len();
+        let ret = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - before;
+        if read != 0 {
+            self.read += read as u64;
+            self.received_at = Instant::now();
+        }
+        ret
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for CountedStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        let ret = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = ret {
+            self.written += written as u64;
+        }
+        ret
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
 
 /// A TCP/SSL connection wrapper
 pub struct Connection<T>
@@ -50,9 +133,11 @@ where
 {
     /// The connection to the remote socket, wrapped in a buffer to speed
     /// up writing
-    pub stream: BufWriter<T>,
+    pub stream: BufWriter<CountedStream<T>>,
     /// The in-memory read buffer. The size is given by `BUF_CAP`
     pub buffer: BytesMut,
+    /// The name of the client at the other end
+    pub client: String,
 }
 
 impl<T> Connection<T>
@@ -62,7 +147,8 @@ where
     /// Initiailize a new `Connection` instance
     pub fn new(stream: T) -> Self {
         Connection {
-            stream: BufWriter::new(stream),
+            client: stream.client_name(),
+            stream: BufWriter::new(CountedStream::new(stream)),
             buffer: BytesMut::with_capacity(BUF_CAP),
         }
     }
@@ -83,7 +169,12 @@ impl Listener {
         loop {
             match self.base.listener.accept().await {
                 // We don't need the bindaddr
-                Ok((stream, _)) => return Ok(stream),
+                Ok((stream, _)) => {
+                    // not being able to set up keepalive isn't reason enough to turn
+                    // the client away
+                    let _ = super::set_keepalive(&stream);
+                    return Ok(stream);
+                }
                 Err(e) => {
                     if backoff > 64 {
                         // Too many retries, goodbye user
@@ -118,12 +209,13 @@ impl Listener {
                 self.base.climit.clone(),
                 Terminator::new(self.base.signal.subscribe()),
                 self.base.terminate_tx.clone(),
+                self.base.admin,
             );
-            tokio::spawn(async move {
+            tokio::spawn(allocator::tagged(Subsystem::Connections, async move {
                 if let Err(e) = chandle.run().await {
                     log::error!("Error: {}", e);
                 }
-            });
+            }));
         }
     }
 }
@@ -25,65 +25,28 @@
 */
 
 use super::connection::ConnectionHandler;
-use crate::dbnet::tcp::BufferedSocketStream;
+use crate::allocator::{self, Subsystem};
+use crate::config::SslOpts;
+use crate::crypto::{Backend, TlsProvider};
 use crate::dbnet::tcp::Connection;
 use crate::dbnet::BaseListener;
 use crate::dbnet::Terminator;
 use libsky::TResult;
-use openssl::pkey::PKey;
-use openssl::rsa::Rsa;
-use openssl::ssl::{Ssl, SslAcceptor, SslFiletype, SslMethod};
-use std::fs;
-use std::io::Error as IoError;
-use std::pin::Pin;
-use tokio::net::TcpStream;
 use tokio::time::{self, Duration};
-use tokio_openssl::SslStream;
-
-impl BufferedSocketStream for SslStream<TcpStream> {}
 
 pub struct SslListener {
     pub base: BaseListener,
-    acceptor: SslAcceptor,
+    acceptor: <Backend as TlsProvider>::Acceptor,
 }
 
 impl SslListener {
-    pub fn new_pem_based_ssl_connection(
-        key_file: String,
-        chain_file: String,
-        base: BaseListener,
-        tls_passfile: Option<String>,
-    ) -> TResult<Self> {
-        let mut acceptor_builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
-        // cert is the same for both
-        acceptor_builder.set_certificate_chain_file(chain_file)?;
-        if let Some(tls_passfile) = tls_passfile {
-            // first read in the private key
-            let tls_private_key = fs::read(key_file).map_err(|e: IoError| {
-                format!("Failed to read TLS private key file with error: {}", e)
-            })?;
-            // read the passphrase because the passphrase file stream was provided
-            let tls_keyfile_stream = fs::read(tls_passfile).map_err(|e: IoError| {
-                format!(
-                    "Failed to read TLS private key passphrase file with error: {}",
-                    e
-                )
-            })?;
-            // decrypt the private key
-            let pkey = Rsa::private_key_from_pem_passphrase(&tls_private_key, &tls_keyfile_stream)?;
-            let pkey = PKey::from_rsa(pkey)?;
-            // set the private key for the acceptor
-            acceptor_builder.set_private_key(&pkey)?;
-        } else {
-            // no passphrase, needs interactive
-            acceptor_builder.set_private_key_file(key_file, SslFiletype::PEM)?;
-        }
+    pub fn new_pem_based_ssl_connection(ssl: SslOpts, base: BaseListener) -> TResult<Self> {
         Ok(SslListener {
             base,
-            acceptor: acceptor_builder.build(),
+            acceptor: Backend::acceptor(ssl)?,
         })
     }
-    async fn accept(&mut self) -> TResult<SslStream<TcpStream>> {
+    async fn accept(&mut self) -> TResult<<Backend as TlsProvider>::Stream> {
         let mut backoff = 1;
         loop {
             match self.base.listener.accept().await {
@@ -91,10 +54,10 @@ impl SslListener {
                 // We get the encrypted stream which we need to decrypt
                 // by using the acceptor
                 Ok((stream, _)) => {
-                    let ssl = Ssl::new(self.acceptor.context())?;
-                    let mut stream = SslStream::new(ssl, stream)?;
-                    Pin::new(&mut stream).accept().await?;
-                    return Ok(stream);
+                    // not being able to set up keepalive isn't reason enough to turn
+                    // the client away
+                    let _ = super::set_keepalive(&stream);
+                    return Backend::accept(&self.acceptor, stream).await;
                 }
                 Err(e) => {
                     if backoff > 64 {
@@ -129,13 +92,14 @@ impl SslListener {
                 self.base.climit.clone(),
                 Terminator::new(self.base.signal.subscribe()),
                 self.base.terminate_tx.clone(),
+                self.base.admin,
             );
-            tokio::spawn(async move {
+            tokio::spawn(allocator::tagged(Subsystem::Connections, async move {
                 log::debug!("Spawned listener task");
                 if let Err(e) = sslhandle.run().await {
                     log::error!("Error: {}", e);
                 }
-            });
+            }));
         }
     }
 }
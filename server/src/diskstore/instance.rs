@@ -0,0 +1,206 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Instances
+//!
+//! Only one process (`skyd` or an embedded instance) can use a data directory at a time. The
+//! process that does holds an exclusive lock on the PID file, and writes its PID and a random
+//! instance ID (a version 4 UUID) to it, so that any other process that tries to use the
+//! directory can say which one is using it.
+//!
+//! The lock goes away along with the process that holds it, but it can outlive a crash on
+//! some network filesystems. A process started with `--takeover` replaces the PID file in
+//! that case, and the previous owner (if it is still around) will refuse to write its data
+//! over the new owner's (see [`is_owner`])
+
+use super::flock::FileLock;
+use crate::crypto;
+use parking_lot::{const_mutex, Mutex};
+use std::fmt;
+use std::fs;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::process;
+
+/// The PID file
+pub const PID_FILE: &str = ".sky_pid";
+
+/// The instance ID of this process (empty until the PID file is locked)
+static INSTANCE_ID: Mutex<String> = const_mutex(String::new());
+
+/// Generate a random instance ID (a version 4 UUID)
+fn new_instance_id() -> String {
+    let mut bytes = [0u8; 16];
+    crypto::fill_random(&mut bytes);
+    // set the version (4) and the variant (RFC 4122)
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Returns the instance ID of this process, if it has locked the PID file
+pub fn instance_id() -> Option<String> {
+    let id = INSTANCE_ID.lock();
+    if id.is_empty() {
+        None
+    } else {
+        Some(id.clone())
+    }
+}
+
+/// Returns `false` if another process has taken over the data directory that this process
+/// locked. This is always `true` if this process hasn't locked it
+pub fn is_owner() -> bool {
+    let id = INSTANCE_ID.lock();
+    if id.is_empty() {
+        return true;
+    }
+    match fs::read_to_string(PID_FILE) {
+        Ok(contents) => Owner::parse(&contents).instance == *id,
+        Err(e) if e.kind() == ErrorKind::NotFound => false,
+        // on Windows, our own lock keeps us from reading it (and also keeps anyone
+        // else from replacing it)
+        Err(_) => true,
+    }
+}
+
+/// The owner of a data directory, as read back from the PID file
+pub struct Owner {
+    pid: String,
+    instance: String,
+}
+
+impl Owner {
+    /// Parse the contents of a PID file. Older versions only wrote the PID
+    fn parse(contents: &str) -> Self {
+        let mut lines = contents.lines().map(str::trim);
+        Self {
+            pid: lines.next().unwrap_or_default().to_owned(),
+            instance: lines.next().unwrap_or_default().to_owned(),
+        }
+    }
+    /// Read the owner from the PID file
+    fn read() -> Self {
+        Self::parse(&fs::read_to_string(PID_FILE).unwrap_or_default())
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.pid.is_empty(), self.instance.is_empty()) {
+            (true, _) => write!(f, "another process"),
+            (false, true) => write!(f, "the process with PID {}", self.pid),
+            (false, false) => write!(
+                f,
+                "the process with PID {} (instance {})",
+                self.pid, self.instance
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Errors that can occur while locking the data directory
+pub enum LockError {
+    /// The data directory is in use by another process
+    InUse(String, IoError),
+    /// The PID file couldn't be locked, written or replaced
+    Io(&'static str, IoError),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InUse(owner, e) => write!(
+                f,
+                "The data directory is in use by {} (failed to lock the PID file: {})",
+                owner, e
+            ),
+            Self::Io(what, e) => write!(f, "Failed to {} the PID file: {}", what, e),
+        }
+    }
+}
+
+/// Lock the data directory, writing our PID and a new instance ID to the PID file
+///
+/// If another process holds the lock, this fails with [`LockError::InUse`] unless `takeover`
+/// is set, in which case the PID file is replaced
+pub fn lock(takeover: bool) -> Result<FileLock, LockError> {
+    let mut file = match FileLock::lock(PID_FILE) {
+        Ok(file) => file,
+        Err(e) => {
+            let owner = Owner::read();
+            if !takeover {
+                return Err(LockError::InUse(owner.to_string(), e));
+            }
+            log::warn!("Taking over the data directory from {}", owner);
+            fs::remove_file(PID_FILE).map_err(|e| LockError::Io("replace", e))?;
+            FileLock::lock(PID_FILE).map_err(|e| LockError::Io("lock", e))?
+        }
+    };
+    let id = new_instance_id();
+    let contents = format!("{}\n{}\n", process::id(), id);
+    write(&mut file, contents.as_bytes()).map_err(|e| LockError::Io("write to", e))?;
+    log::info!("Started instance {}", id);
+    *INSTANCE_ID.lock() = id;
+    Ok(file)
+}
+
+fn write(file: &mut FileLock, contents: &[u8]) -> IoResult<()> {
+    file.write(contents)?;
+    file.fsync()
+}
+
+#[test]
+fn test_instance_id() {
+    let id = new_instance_id();
+    assert_eq!(id.len(), 36);
+    assert_eq!(id.matches('-').count(), 4);
+    assert_eq!(&id[14..15], "4");
+    assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+    assert_ne!(id, new_instance_id());
+}
+
+#[test]
+fn test_owner_parse() {
+    let owner = Owner::parse("1234\nabcd\n");
+    assert_eq!(
+        owner.to_string(),
+        "the process with PID 1234 (instance abcd)"
+    );
+    // written by an older version
+    let owner = Owner::parse("1234");
+    assert_eq!(owner.to_string(), "the process with PID 1234");
+    let owner = Owner::parse("");
+    assert_eq!(owner.to_string(), "another process");
+}
@@ -27,3 +27,4 @@
 //! This module provides tools for handling persistently stored data
 
 pub mod flock;
+pub mod instance;
@@ -0,0 +1,104 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Embedded mode
+//!
+//! An [`Embedded`] instance runs queries directly against the storage engine, without any
+//! listeners or background services. Just like `skyd`, the data lives in the `data` directory
+//! under the current working directory, and only one process (embedded or not) can use
+//! it at a time. Data is only written to disk when the instance is [closed](Embedded::close)
+//! (or if `BGSAVE`/`MKSNAP` are run)
+
+use crate::crypto;
+use crate::dbnet::connection::prelude::*;
+use crate::dbnet::memory::execute;
+use crate::diskstore::flock::FileLock;
+use crate::diskstore::instance;
+use crate::services;
+use crate::storage;
+use crate::storage::sengine::SnapshotEngine;
+use crate::IoResult;
+use std::sync::Arc;
+
+/// An embedded instance
+pub struct Embedded {
+    db: Corestore,
+    pid_file: FileLock,
+}
+
+impl Embedded {
+    /// Lock the data directory and load all the data in it
+    pub fn open() -> Result<Self, String> {
+        crypto::init().map_err(|e| e.to_string())?;
+        let pid_file = instance::lock(false).map_err(|e| e.to_string())?;
+        let db = Corestore::init_with_snapcfg(Arc::new(SnapshotEngine::new_disabled()))
+            .map_err(|e| format!("Error while initializing database: {}", e))?;
+        Ok(Self { db, pid_file })
+    }
+    /// Execute a query packet and return the response packet (both in the Skyhash format)
+    ///
+    /// Like connections, every instance has its own current table (which can be changed
+    /// with `USE`)
+    pub async fn execute(&mut self, query: &[u8]) -> IoResult<Vec<u8>> {
+        execute(&mut self.db, query).await
+    }
+    /// Write all the data to disk and unlock the data directory
+    pub fn close(self) -> Result<(), String> {
+        let Self { db, mut pid_file } = self;
+        services::bgsave::run_bgsave(&db)
+            .map_err(|e| format!("Failed to write data to disk: {}", e))?;
+        storage::interface::cleanup_tree(db.get_store())
+            .map_err(|e| format!("Failed to compact tree: {}", e))?;
+        pid_file
+            .unlock()
+            .map_err(|e| format!("Failed to unlock pid file: {}", e))
+    }
+}
+
+#[tokio::test]
+async fn test_embedded_execute() {
+    use crate::corestore::memstore::Memstore;
+    let mut db = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    assert_eq!(
+        execute(&mut db, b"*1\n~1\n4\nHEYA\n").await.unwrap(),
+        responses::full_responses::R_HEYA
+    );
+    assert_eq!(
+        execute(&mut db, b"*1\n~3\n3\nSET\n1\nx\n3\n100\n")
+            .await
+            .unwrap(),
+        responses::full_responses::R_OKAY
+    );
+    let get = execute(&mut db, b"*1\n~2\n3\nGET\n1\nx\n").await.unwrap();
+    assert!(get.ends_with(b"3\n100\n"));
+    assert_eq!(
+        execute(&mut db, b"*1\n~1\n").await.unwrap(),
+        responses::full_responses::R_PACKET_ERR
+    );
+}
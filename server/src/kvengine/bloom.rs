@@ -0,0 +1,191 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Bloom filters
+//!
+//! A table can be created with a bloom filter (`CREATE TABLE ... bloom(<fprate>)`) so that
+//! looking up a key that isn't in the table usually doesn't touch the table at all. The
+//! filter grows with the table: it's a series of slices where every slice holds twice as
+//! many keys as the one before it at half the false positive rate, which keeps the overall
+//! false positive rate under the one that the table was created with.
+//!
+//! A filter never forgets a key, so keys that are removed (or expire) keep their bits set
+//! and only cost a lookup in the table. Since keys are added to the filter before they're
+//! added to the table, the filter never misses a key that the table has.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use parking_lot::RwLock;
+use std::collections::hash_map::RandomState;
+
+/// The number of keys that the first slice of a filter holds
+const FIRST_SLICE_KEYS: usize = 4096;
+
+/// Returns true if `fprate` is a false positive rate that a filter can be created with
+pub fn is_valid_fprate(fprate: f64) -> bool {
+    fprate > 0.0 && fprate < 1.0
+}
+
+/// A fixed-size bloom filter that holds up to `capacity` keys at its false positive rate
+struct Slice {
+    bits: Box<[AtomicU64]>,
+    hashes: u64,
+    capacity: usize,
+    count: AtomicUsize,
+}
+
+impl Slice {
+    fn new(capacity: usize, fprate: f64) -> Self {
+        let ln2 = core::f64::consts::LN_2;
+        let nbits = (-(capacity as f64) * fprate.ln() / (ln2 * ln2)).ceil() as usize;
+        let words = (nbits + 63) / 64;
+        let hashes = ((words * 64) as f64 / capacity as f64 * ln2)
+            .round()
+            .max(1.0) as u64;
+        Self {
+            bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            hashes,
+            capacity,
+            count: AtomicUsize::new(0),
+        }
+    }
+    /// Returns the bits for the given hash (as `(word, mask)` pairs)
+    fn positions(&self, hash: u64) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let nbits = self.bits.len() as u64 * 64;
+        let (h1, h2) = (hash, hash.rotate_left(32) | 1);
+        (0..self.hashes).map(move |i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % nbits;
+            ((bit / 64) as usize, 1 << (bit % 64))
+        })
+    }
+    fn insert(&self, hash: u64) {
+        for (word, mask) in self.positions(hash) {
+            self.bits[word].fetch_or(mask, Ordering::AcqRel);
+        }
+        self.count.fetch_add(1, Ordering::AcqRel);
+    }
+    fn may_contain(&self, hash: u64) -> bool {
+        self.positions(hash)
+            .all(|(word, mask)| self.bits[word].load(Ordering::Acquire) & mask != 0)
+    }
+    fn is_full(&self) -> bool {
+        self.count.load(Ordering::Acquire) >= self.capacity
+    }
+}
+
+/// A bloom filter that grows as keys are added to it
+pub struct Bloom {
+    /// the false positive rate of the whole filter
+    fprate: f64,
+    hasher: RandomState,
+    slices: RwLock<Vec<Slice>>,
+}
+
+impl fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bloom")
+            .field("fprate", &self.fprate)
+            .field("slices", &self.slices.read().len())
+            .finish()
+    }
+}
+
+impl Bloom {
+    /// Create an empty filter with the given false positive rate (see [`is_valid_fprate`])
+    pub fn new(fprate: f64) -> Self {
+        Self {
+            fprate,
+            hasher: RandomState::new(),
+            // the rates of the slices add up to less than twice the rate of the first one
+            slices: RwLock::new(vec![Slice::new(FIRST_SLICE_KEYS, fprate / 2.0)]),
+        }
+    }
+    pub const fn fprate(&self) -> f64 {
+        self.fprate
+    }
+    fn hash_of<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+    /// Add a key to the filter
+    pub fn insert<Q: Hash + ?Sized>(&self, key: &Q) {
+        let hash = self.hash_of(key);
+        {
+            let slices = self.slices.read();
+            if slices.iter().any(|slice| slice.may_contain(hash)) {
+                // don't count a key that's (most likely) already here twice
+                return;
+            }
+            let last = &slices[slices.len() - 1];
+            if !last.is_full() {
+                return last.insert(hash);
+            }
+        }
+        let mut slices = self.slices.write();
+        let last = &slices[slices.len() - 1];
+        if last.is_full() {
+            let slice = Slice::new(
+                last.capacity * 2,
+                self.fprate / 2f64.powi(slices.len() as i32 + 1),
+            );
+            slices.push(slice);
+        }
+        slices[slices.len() - 1].insert(hash);
+    }
+    /// Returns false if the key was never added to the filter
+    pub fn may_contain<Q: Hash + ?Sized>(&self, key: &Q) -> bool {
+        let hash = self.hash_of(key);
+        self.slices
+            .read()
+            .iter()
+            .any(|slice| slice.may_contain(hash))
+    }
+}
+
+#[test]
+fn test_bloom() {
+    let bloom = Bloom::new(0.01);
+    let keys: Vec<String> = (0..20_000).map(|i| format!("key{}", i)).collect();
+    for key in keys.iter() {
+        bloom.insert(key.as_bytes());
+    }
+    // the filter had to grow
+    assert!(bloom.slices.read().len() > 1);
+    assert!(keys.iter().all(|key| bloom.may_contain(key.as_bytes())));
+    let false_positives = (0..20_000)
+        .filter(|i| bloom.may_contain(format!("missing{}", i).as_bytes()))
+        .count();
+    assert!(false_positives < 400, "{} false positives", false_positives);
+}
+
+#[test]
+fn test_valid_fprate() {
+    assert!(is_valid_fprate(0.01));
+    assert!(!is_valid_fprate(0.0));
+    assert!(!is_valid_fprate(1.0));
+    assert!(!is_valid_fprate(f64::NAN));
+}
@@ -0,0 +1,184 @@
+/*
+ * Created on Mon Dec 13 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Document caches
+//!
+//! The JSON actions (`JSET`, `JGET` and `JDEL`) keep the documents that they have parsed
+//! in the table's document cache, so that a document that is read or changed over and over
+//! is only parsed once. Just like the [response cache](super::respcache), every cached
+//! document is kept along with the value that it was parsed from (or serialized into), and
+//! it is only used while the key still has that very value (see
+//! [`is_same_value`](super::is_same_value)), so any write to the key invalidates it,
+//! whatever action it comes from.
+//!
+//! The cache holds up to [`MAX_DOCUMENTS`] documents. Once it's full, caching another
+//! document evicts a random one, so that the documents that are still hot mostly stay. The
+//! cache only lives in memory
+
+use super::is_same_value;
+use crate::corestore::htable::Coremap;
+use crate::corestore::htable::Data;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use serde_json::Value;
+
+/// The number of documents that a table's cache holds before it starts evicting them
+pub const MAX_DOCUMENTS: usize = 1024;
+
+#[derive(Debug)]
+/// The parsed JSON documents of a table's hot keys
+pub struct DocumentCache {
+    /// the documents, along with the values that they were parsed from
+    documents: Coremap<Data, (Data, Value)>,
+}
+
+impl Default for DocumentCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DocumentCache {
+    pub fn new() -> Self {
+        Self {
+            documents: Coremap::new(),
+        }
+    }
+    /// Run `f` on the document stored in `value` (which the key has), parsing it and caching
+    /// it for the next time if it wasn't cached. Returns `None` if the value isn't a JSON
+    /// document
+    pub fn with<R>(&self, key: &[u8], value: &Data, f: impl FnOnce(&Value) -> R) -> Option<R> {
+        if let Some(cached) = self.documents.get(key) {
+            if is_same_value(&cached.value().0, value) {
+                return Some(f(&cached.value().1));
+            }
+        }
+        let document = parse(value)?;
+        let ret = f(&document);
+        self.insert(Data::copy_from_slice(key), value.clone(), document);
+        Some(ret)
+    }
+    /// Take the document stored in `value` (which the key has) out of the cache (so that
+    /// it can be changed), or parse it if it wasn't cached. Returns `None` if the value
+    /// isn't a JSON document
+    pub fn take(&self, key: &[u8], value: &Data) -> Option<Value> {
+        match self
+            .documents
+            .remove_if(key, |_, cached| is_same_value(&cached.0, value))
+        {
+            Some((_, (_, document))) => Some(document),
+            None => parse(value),
+        }
+    }
+    /// Cache the document that was serialized into `value`, which the key now has
+    pub fn insert(&self, key: Data, value: Data, document: Value) {
+        // empty values can't be told apart from the values that replace them
+        if value.is_empty() {
+            return;
+        }
+        if self.documents.len() >= MAX_DOCUMENTS && !self.documents.contains_key(&key) {
+            self.evict_one();
+        }
+        self.documents.upsert(key, (value, document));
+    }
+    /// Drop a document to make room for another one. The documents are placed by a randomly
+    /// seeded hash, so the first one that we come across is as good as a random pick
+    fn evict_one(&self) {
+        let victim = self.documents.iter().next().map(|kv| kv.key().clone());
+        if let Some(victim) = victim {
+            self.documents.remove(&victim);
+        }
+    }
+    /// Drop the document of the key (if there is one), since the key is gone
+    pub fn forget<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.documents.len() != 0 {
+            self.documents.remove(key);
+        }
+    }
+    /// Drop all the documents
+    pub fn clear(&self) {
+        self.documents.clear();
+    }
+}
+
+/// Parse a stored value into a JSON document
+pub fn parse(value: &[u8]) -> Option<Value> {
+    serde_json::from_slice(value).ok()
+}
+
+/// Serialize a JSON document so that it can be stored or returned
+pub fn serialize(document: &Value) -> Data {
+    // serializing a `Value` can't fail since all keys are strings
+    Data::from(serde_json::to_vec(document).unwrap_or_default())
+}
+
+#[test]
+fn test_document_cache() {
+    let cache = DocumentCache::new();
+    let value = Data::from(r#"{"a":1}"#);
+    assert_eq!(
+        cache.with(b"doc", &value, |doc| doc["a"].clone()),
+        Some(Value::from(1))
+    );
+    assert_eq!(cache.documents.len(), 1);
+    // a new value (even an equal one) isn't answered from the cache
+    let rewritten = Data::copy_from_slice(br#"{"a":1}"#);
+    assert!(cache.take(b"doc", &rewritten).is_some());
+    assert_eq!(cache.documents.len(), 1);
+    // taking the cached document removes it
+    let mut document = cache.take(b"doc", &value).unwrap();
+    assert_eq!(cache.documents.len(), 0);
+    document["a"] = Value::from(2);
+    let value = serialize(&document);
+    cache.insert(Data::from("doc"), value.clone(), document);
+    assert_eq!(
+        cache.with(b"doc", &value, |doc| doc["a"].clone()),
+        Some(Value::from(2))
+    );
+    // values that aren't documents aren't cached
+    assert_eq!(cache.with(b"bad", &Data::from("{"), |_| ()), None);
+    assert_eq!(cache.documents.len(), 1);
+    cache.forget(b"doc".as_ref());
+    assert_eq!(cache.documents.len(), 0);
+}
+
+#[test]
+fn test_document_cache_eviction() {
+    let cache = DocumentCache::new();
+    let value = Data::from("{}");
+    for key in 0..MAX_DOCUMENTS {
+        cache.insert(Data::from(key.to_string()), value.clone(), Value::Null);
+    }
+    assert_eq!(cache.documents.len(), MAX_DOCUMENTS);
+    // another document only pushes out one of the others
+    cache.insert(Data::from("new"), value.clone(), Value::Null);
+    assert_eq!(cache.documents.len(), MAX_DOCUMENTS);
+    assert!(cache.documents.contains_key(b"new".as_ref()));
+}
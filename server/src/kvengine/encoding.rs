@@ -89,6 +89,10 @@ const UTF8_TRANSITION_MAP: [u8; 108] = [
 /// This function gives us as much as a ~300% improvement over std's validation algorithm
 pub fn is_utf8(bytes: impl AsRef<[u8]>) -> bool {
     let bytes = bytes.as_ref();
+    if bytes.is_empty() {
+        // nothing to validate (and nothing to read at `half` either)
+        return true;
+    }
     let mut half = bytes.len() / 2;
     unsafe {
         while *bytes.get_unchecked(half) <= 0xBF && *bytes.get_unchecked(half) >= 0x80 && half > 0 {
@@ -239,3 +243,8 @@ fn test_the_emojis_with_invalid_codepoint() {
     emojistr.extend(rem);
     assert!(!is_utf8(emojistr));
 }
+
+#[test]
+fn test_empty_is_utf8() {
+    assert!(is_utf8(b""));
+}
@@ -26,12 +26,23 @@
 
 use crate::corestore::htable::Coremap;
 use crate::corestore::htable::Data;
+use crate::corestore::map::bref::Entry;
 use crate::corestore::map::bref::Ref;
 use crate::resp::TSYMBOL_BINARY;
 use crate::resp::TSYMBOL_UNICODE;
 use core::borrow::Borrow;
 use core::hash::Hash;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+pub mod bloom;
+pub mod doccache;
 pub mod encoding;
+pub mod respcache;
+pub mod slab;
+use self::bloom::Bloom;
+use self::doccache::DocumentCache;
+use self::respcache::ResponseCache;
+use self::slab::Slab;
 
 /// An arbitrary unicode/binary _double encoder_ for two byte slice inputs
 pub struct DoubleEncoder {
@@ -95,6 +106,58 @@ pub struct KVEngine {
     encoded_k: bool,
     /// the encoding switch for the value
     encoded_v: bool,
+    /// the slab for small values
+    slab: Slab,
+    /// when the keys that expire do so (as UNIX timestamps in milliseconds)
+    expiry: Coremap<Data, u64>,
+    /// set once any key is given an expiry, so that tables without expiring keys don't
+    /// pay for looking them up
+    has_expiry: AtomicBool,
+    /// when the keys were last read or written (as UNIX timestamps in milliseconds)
+    access: Coremap<Data, u64>,
+    /// the versions that were handed out for keys, along with the value that each version
+    /// was handed out for (see [`KVEngine::get_versioned`])
+    versions: Coremap<Data, (u64, Data)>,
+    /// the last version that was handed out in this table
+    version_seq: AtomicU64,
+    /// the filter that lookups of missing keys are answered from, if the table has one
+    bloom: Option<Bloom>,
+    /// the serialized responses of hot keys, if the table has a response cache
+    respcache: Option<ResponseCache>,
+    /// the parsed JSON documents of hot keys
+    documents: DocumentCache,
+}
+
+/// The last access time of a key is only updated once it is at least this old (in
+/// milliseconds), so that looking up a hot key doesn't wlock the access map every time
+const ACCESS_RESOLUTION_MS: u64 = 1000;
+
+/// Returns true if `a` and `b` are views into the same bytes. A value that is written is
+/// always stored in a new place (neither the slab nor the heap reuses the space of a value
+/// that is still referenced), so this tells us if a key has been written since we last
+/// looked at its value. Empty values can't be told apart this way
+fn is_same_value(a: &Data, b: &Data) -> bool {
+    let (a, b) = (a.get_blob(), b.get_blob());
+    a.as_ptr() == b.as_ptr() && a.len() == b.len()
+}
+
+/// Returns the current time as a UNIX timestamp in milliseconds
+#[cfg(not(feature = "simulation"))]
+pub fn now_ms() -> u64 {
+    system_now_ms()
+}
+
+/// Returns the current time as a UNIX timestamp in milliseconds (or the virtual time, if
+/// a simulation is running on this thread)
+#[cfg(feature = "simulation")]
+pub fn now_ms() -> u64 {
+    crate::sim::clock::now_ms().unwrap_or_else(system_now_ms)
+}
+
+fn system_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
 }
 
 impl Default for KVEngine {
@@ -115,7 +178,63 @@ impl KVEngine {
             table,
             encoded_k,
             encoded_v,
+            slab: Slab::new(),
+            expiry: Coremap::new(),
+            has_expiry: AtomicBool::new(false),
+            access: Coremap::new(),
+            versions: Coremap::new(),
+            version_seq: AtomicU64::new(0),
+            bloom: None,
+            respcache: None,
+            documents: DocumentCache::new(),
+        }
+    }
+    /// Give the table a bloom filter with the given false positive rate (see
+    /// [`bloom::is_valid_fprate`]), which the keys that it already has are added to
+    pub fn with_bloom(mut self, fprate: f64) -> Self {
+        let bloom = Bloom::new(fprate);
+        for kv in self.table.iter() {
+            bloom.insert(kv.key());
         }
+        self.bloom = Some(bloom);
+        self
+    }
+    /// Returns the false positive rate of the table's bloom filter, if it has one
+    pub fn bloom_fprate(&self) -> Option<f64> {
+        self.bloom.as_ref().map(Bloom::fprate)
+    }
+    /// Give the table a response cache that holds up to `maxkeys` responses (see
+    /// [`respcache::is_valid_maxkeys`])
+    pub fn with_response_cache(mut self, maxkeys: usize) -> Self {
+        self.respcache = Some(ResponseCache::new(maxkeys));
+        self
+    }
+    /// Returns the table's response cache, if it has one
+    pub const fn response_cache(&self) -> Option<&ResponseCache> {
+        self.respcache.as_ref()
+    }
+    /// Returns the table's document cache
+    pub const fn document_cache(&self) -> &DocumentCache {
+        &self.documents
+    }
+    /// Add a key to the table's bloom filter, if it has one. This has to be done before the
+    /// key is added to the table, so that lookups never miss it
+    pub fn add_to_bloom<Q>(&self, key: &Q)
+    where
+        Q: Hash + ?Sized,
+    {
+        if let Some(bloom) = &self.bloom {
+            bloom.insert(key);
+        }
+    }
+    /// Returns false if the key definitely isn't in the table
+    fn may_contain<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + ?Sized,
+    {
+        self.bloom
+            .as_ref()
+            .map_or(true, |bloom| bloom.may_contain(key))
     }
     pub fn get_encoding(&self) -> (bool, bool) {
         (self.encoded_k, self.encoded_v)
@@ -194,11 +313,66 @@ impl KVEngine {
         Data: Borrow<Q>,
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
+        self.expire_if_due(key);
         self.table.get(key).map(|v| v.clone())
     }
+    /// Returns true if every key and value in the table satisfies the table's encoding
+    pub fn verify_encoding(&self) -> bool {
+        self.verify_encoding_of(&self.table)
+    }
+    /// Returns true if every key and value in `data` satisfies the table's encoding
+    pub fn verify_encoding_of(&self, data: &Coremap<Data, Data>) -> bool {
+        if !(self.encoded_k || self.encoded_v) {
+            return true;
+        }
+        data.iter()
+            .all(|kv| self._encode_key(kv.key()).is_ok() && self._encode_value(kv.value()).is_ok())
+    }
+    /// Replace all the keys in the table (and their deadlines) with `data` and `expiry`, in
+    /// one go
+    pub fn replace_data(&self, data: Coremap<Data, Data>, expiry: Coremap<Data, u64>) {
+        if expiry.len() != 0 {
+            self.has_expiry.store(true, Ordering::Release);
+        }
+        for kv in data.iter() {
+            self.add_to_bloom(kv.key());
+        }
+        self.table.replace_all(data);
+        self.expiry.replace_all(expiry);
+        self.access.clear();
+        self.versions.clear();
+        self.forget_all_cached();
+    }
+    /// Take a copy of the table (that shares the keys and values of this one) along with
+    /// the deadlines of its keys. The copy is taken a stripe at a time, so writes to the
+    /// table aren't held off while it's taken; run this in a [`Keyspace::rendezvous`] to
+    /// not see half of a multi-key write
+    ///
+    /// [`Keyspace::rendezvous`]: crate::corestore::memstore::Keyspace::rendezvous
+    pub fn snapshot(&self) -> Self {
+        let table = self
+            .table
+            .iter()
+            .map(|kv| (kv.key().clone(), kv.value().clone()))
+            .collect();
+        let snapshot = Self::init_with_data(self.encoded_k, self.encoded_v, table);
+        if self.has_expiry.load(Ordering::Acquire) {
+            snapshot.load_expiries(
+                self.expiry
+                    .iter()
+                    .map(|kv| (kv.key().clone(), *kv.value()))
+                    .collect(),
+            );
+        }
+        snapshot
+    }
     /// Truncate the table
     pub fn truncate_table(&self) {
-        self.table.clear()
+        self.table.clear();
+        self.expiry.clear();
+        self.access.clear();
+        self.versions.clear();
+        self.forget_all_cached();
     }
     pub const fn needs_value_encoding(&self) -> bool {
         self.encoded_v
@@ -230,6 +404,11 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok((None, self.get_vt()));
+        }
+        self.expire_if_due(key);
+        self.touch(key);
         Ok((self.table.get(key), self.get_vt()))
     }
     /// Get the value for a given key if it exists
@@ -239,6 +418,11 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+        self.expire_if_due(key);
+        self.touch(key);
         Ok(self.table.get(key))
     }
     /// Get the value for a given key if it exists, returning a cloned reference
@@ -248,6 +432,11 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+        self.expire_if_due(key);
+        self.touch(key);
         Ok(self.table.get_cloned(key))
     }
     pub fn get_cloned_unchecked<Q>(&self, key: &Q) -> Option<Data>
@@ -255,8 +444,26 @@ impl KVEngine {
         Data: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
+        if !self.may_contain(key) {
+            return None;
+        }
+        self.expire_if_due(key);
+        self.touch(key);
         self.table.get_cloned(key)
     }
+    /// Get the value for a given key if it exists, without counting it as an access
+    pub fn peek_cloned<Q>(&self, key: &Q) -> Result<Option<Data>, ()>
+    where
+        Data: Borrow<Q>,
+        Q: AsRef<[u8]> + Hash + Eq + ?Sized,
+    {
+        self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+        self.expire_if_due(key);
+        Ok(self.table.get_cloned(key))
+    }
     /// Get the value for a given key if it exists, returning a cloned reference
     pub fn get_cloned_with_tsymbol<Q>(&self, key: &Q) -> Result<(Option<Data>, u8), ()>
     where
@@ -264,6 +471,11 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok((None, self.get_vt()));
+        }
+        self.expire_if_due(key);
+        self.touch(key);
         Ok((self.table.get_cloned(key), self.get_vt()))
     }
     pub fn exists<Q>(&self, key: &Q) -> Result<bool, ()>
@@ -272,6 +484,10 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok(false);
+        }
+        self.expire_if_due(key);
         Ok(self.table.contains_key(key))
     }
     pub fn exists_unchecked<Q>(&self, key: &Q) -> bool
@@ -279,6 +495,10 @@ impl KVEngine {
         Data: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
+        if !self.may_contain(key) {
+            return false;
+        }
+        self.expire_if_due(key);
         self.table.contains_key(key)
     }
     /// Check the unicode encoding of a given byte array
@@ -306,36 +526,149 @@ impl KVEngine {
             Ok(())
         }
     }
+    /// Move the value into the slab if it is small enough
+    fn slab(&self, value: Data) -> Data {
+        match self.slab.try_store(&value) {
+            Some(blob) => Data::from_blob(blob),
+            None => value,
+        }
+    }
+    /// Copy all the small values into fresh slab chunks so that chunks which are mostly
+    /// holding values that have since been overwritten or removed can be freed. This is
+    /// only done if enough of the slab is holes (see [`slab::COMPACT_THRESHOLD`]), which
+    /// is checked by adding up the small values without copying them. Returns the number
+    /// of bytes that were copied
+    pub fn compact_slab(&self) -> usize {
+        let live = self
+            .table
+            .iter()
+            .map(|kv| kv.value().len())
+            .filter(|len| *len != 0 && *len < slab::SLAB_VALUE_MAX)
+            .sum();
+        if !self.slab.needs_compaction(live) {
+            return 0;
+        }
+        self.slab.start_compaction();
+        let mut copied = 0;
+        for mut kv in self.table.iter_mut() {
+            let value = kv.value_mut();
+            if let Some(blob) = self.slab.try_store(value) {
+                copied += blob.len();
+                *value = Data::from_blob(blob);
+            }
+        }
+        if copied != 0 {
+            // the cached responses and documents would keep the old chunks around
+            self.forget_all_cached();
+        }
+        copied
+    }
     /// Set the value of a non-existent key
     pub fn set(&self, key: Data, value: Data) -> Result<bool, ()> {
         self._encode_key(&key)?;
         self._encode_value(&value)?;
-        Ok(self.table.true_if_insert(key, value))
+        Ok(self.set_unchecked(key, value))
     }
     /// Set the value of a non-existent key
     pub fn set_unchecked(&self, key: Data, value: Data) -> bool {
-        self.table.true_if_insert(key, value)
+        self.expire_if_due(&key);
+        self.add_to_bloom(&key);
+        let stale = key.clone();
+        let inserted = self.table.true_if_insert(key, self.slab(value));
+        if inserted {
+            // a key that was removed behind our back (by the strong actions, for example) may
+            // have left its expiry and access time behind; a new key shouldn't inherit them
+            self.forget_expiry(&stale);
+            self.forget_version(&stale);
+            self.access.upsert(stale, now_ms());
+        }
+        inserted
     }
-    /// Update the value of an existing key
+    /// Update the value of an existing key (a new value doesn't expire)
     pub fn update(&self, key: Data, value: Data) -> Result<bool, ()> {
         self._encode_key(&key)?;
         self._encode_value(&value)?;
-        Ok(self.table.true_if_update(key, value))
+        Ok(self.update_unchecked(key, value))
     }
-    /// Update the value of an existing key
+    /// Update the value of an existing key (a new value doesn't expire)
     pub fn update_unchecked(&self, key: Data, value: Data) -> bool {
-        self.table.true_if_update(key, value)
+        self.expire_if_due(&key);
+        self.forget_expiry(&key);
+        let lookup = key.clone();
+        let updated = self.table.true_if_update(key, self.slab(value));
+        if updated {
+            self.forget_version(&lookup);
+            self.touch(&lookup);
+        }
+        updated
     }
-    /// Update or insert the value of a key
+    /// Update or insert the value of a key (a new value doesn't expire)
     pub fn upsert(&self, key: Data, value: Data) -> Result<(), ()> {
         self._encode_key(&key)?;
         self._encode_value(&value)?;
-        self.table.upsert(key, value);
+        self.upsert_unchecked(key, value);
         Ok(())
     }
-    /// Update or insert the value of a key
+    /// Update or insert the value of a key (a new value doesn't expire)
     pub fn upsert_unchecked(&self, key: Data, value: Data) {
-        self.table.upsert(key, value);
+        self.forget_expiry(&key);
+        self.add_to_bloom(&key);
+        self.access.upsert(key.clone(), now_ms());
+        let lookup = key.clone();
+        self.table.upsert(key, self.slab(value));
+        self.forget_version(&lookup);
+    }
+    /// Atomically read and modify the value of a key
+    ///
+    /// `f` is passed the current value (if any) and returns the new value (or `None` if the
+    /// value shouldn't be changed) along with a return value that is passed back to the
+    /// caller. The key is locked for the whole duration of `f`, and keeps its expiry
+    pub fn read_modify_write<R>(
+        &self,
+        key: Data,
+        f: impl FnOnce(Option<&Data>) -> (Option<Data>, R),
+    ) -> Result<R, ()> {
+        self.read_modify_write_stored(key, f).map(|(ret, _)| ret)
+    }
+    /// Same as [`KVEngine::read_modify_write`], but also returns the value that was stored
+    /// (if any), which is a copy of the value returned by `f` if it was moved into the slab
+    pub fn read_modify_write_stored<R>(
+        &self,
+        key: Data,
+        f: impl FnOnce(Option<&Data>) -> (Option<Data>, R),
+    ) -> Result<(R, Option<Data>), ()> {
+        self._encode_key(&key)?;
+        self.expire_if_due(&key);
+        let lookup = key.clone();
+        let ret = match self.table.entry(key) {
+            Entry::Occupied(mut oe) => {
+                let (new, ret) = f(Some(oe.value()));
+                match new {
+                    Some(new) => {
+                        self._encode_value(&new)?;
+                        let stored = self.slab(new);
+                        oe.insert(stored.clone());
+                        (ret, Some(stored))
+                    }
+                    None => (ret, None),
+                }
+            }
+            Entry::Vacant(ve) => {
+                let (new, ret) = f(None);
+                match new {
+                    Some(new) => {
+                        self._encode_value(&new)?;
+                        self.add_to_bloom(&lookup);
+                        let stored = self.slab(new);
+                        ve.insert(stored.clone());
+                        (ret, Some(stored))
+                    }
+                    None => (ret, None),
+                }
+            }
+        };
+        self.touch(&lookup);
+        Ok(ret)
     }
     /// Remove an existing key
     pub fn remove<Q>(&self, key: &Q) -> Result<bool, ()>
@@ -344,7 +677,7 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
-        Ok(self.table.true_if_removed(key))
+        Ok(self.remove_unchecked(key))
     }
     /// Remove an existing key
     pub fn remove_unchecked<Q>(&self, key: &Q) -> bool
@@ -352,7 +685,7 @@ impl KVEngine {
         Data: Borrow<Q>,
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
-        self.table.true_if_removed(key)
+        self.pop_unchecked(key).is_some()
     }
     pub fn pop<Q>(&self, key: &Q) -> Result<Option<(Data, Data)>, ()>
     where
@@ -360,14 +693,329 @@ impl KVEngine {
         Q: AsRef<[u8]> + Hash + Eq + ?Sized,
     {
         self._encode_key(key)?;
-        Ok(self.table.remove(key))
+        Ok(self.pop_unchecked(key))
     }
     pub fn pop_unchecked<Q>(&self, key: &Q) -> Option<(Data, Data)>
     where
         Data: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.table.remove(key)
+        self.expire_if_due(key);
+        let popped = self.table.remove(key);
+        if popped.is_some() {
+            self.forget_removed(key);
+        }
+        popped
+    }
+    /// Remove the key if `f` accepts its value, returning `None` if there is no such key, or
+    /// else whether it was removed
+    pub fn remove_if<Q>(&self, key: &Q, f: impl FnOnce(&Data) -> bool) -> Result<Option<bool>, ()>
+    where
+        Data: Borrow<Q>,
+        Q: AsRef<[u8]> + Hash + Eq + ?Sized,
+    {
+        self._encode_key(key)?;
+        self.expire_if_due(key);
+        let mut found = false;
+        let removed = self.table.true_remove_if(key, |_, value| {
+            found = true;
+            f(value)
+        });
+        if removed {
+            self.forget_removed(key);
+        }
+        Ok(if found { Some(removed) } else { None })
+    }
+    /// Forget everything that we know about a key that was just removed
+    fn forget_removed<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.forget_expiry(key);
+        self.forget_version(key);
+        self.forget_cached(key);
+        self.access.remove(key);
+    }
+    /// Remove the key if it has expired
+    fn expire_if_due<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.has_expiry.load(Ordering::Acquire) {
+            return;
+        }
+        let now = now_ms();
+        if self
+            .expiry
+            .true_remove_if(key, |_, deadline| *deadline <= now)
+        {
+            self.table.remove(key);
+            self.access.remove(key);
+            self.forget_version(key);
+            self.forget_cached(key);
+        }
+    }
+    /// Record that the key (if it exists) was just read or written
+    fn touch<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = now_ms();
+        match self.access.get_cloned(key) {
+            Some(last) if now.saturating_sub(last) < ACCESS_RESOLUTION_MS => {}
+            Some(_) => {
+                if let Some(mut last) = self.access.get_mut(key) {
+                    *last.value_mut() = now;
+                }
+            }
+            None => {
+                // keys that were read from disk (or set by the strong actions) don't have an
+                // access time until they're first looked up
+                let key = self.table.get(key).map(|kv| kv.key().clone());
+                if let Some(key) = key {
+                    self.access.upsert(key, now);
+                }
+            }
+        }
+    }
+    /// Returns when the key was last read or written (as a UNIX timestamp in milliseconds),
+    /// if it has been since it was loaded
+    pub fn last_access_of<Q>(&self, key: &Q) -> Option<u64>
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.access.get_cloned(key)
+    }
+    /// Returns the value of the key along with its version. Versions only go up: a key is
+    /// handed out a new version once its value has been written since it was handed out the
+    /// last one (see [`KVEngine::set_versioned`])
+    pub fn get_versioned<Q>(&self, key: &Q) -> Result<Option<(Data, u64)>, ()>
+    where
+        Data: Borrow<Q>,
+        Q: AsRef<[u8]> + Hash + Eq + ?Sized,
+    {
+        self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+        self.expire_if_due(key);
+        let (key, value) = match self.table.get(key) {
+            Some(kv) => (kv.key().clone(), kv.value().clone()),
+            None => return Ok(None),
+        };
+        self.touch(&key);
+        let version = match self.versions.entry(key) {
+            Entry::Occupied(mut oe) if !is_same_value(&oe.value().1, &value) => {
+                let version = self.next_version();
+                oe.insert((version, value.clone()));
+                version
+            }
+            Entry::Occupied(oe) => oe.value().0,
+            Entry::Vacant(ve) => {
+                let version = self.next_version();
+                ve.insert((version, value.clone()));
+                version
+            }
+        };
+        Ok(Some((value, version)))
+    }
+    /// Set the value of the key, but only if it still has the value that `version` was
+    /// handed out for (or, if `version` is `0`, only if the key doesn't exist). Returns the
+    /// key's new version, or `None` if the key has been written since (a new value doesn't
+    /// expire)
+    pub fn set_versioned(&self, key: Data, version: u64, value: Data) -> Result<Option<u64>, ()> {
+        self._encode_key(&key)?;
+        self._encode_value(&value)?;
+        self.expire_if_due(&key);
+        let lookup = key.clone();
+        let value = self.slab(value);
+        let stamp = |this: &Self| {
+            let new = this.next_version();
+            this.versions.upsert(lookup.clone(), (new, value.clone()));
+            new
+        };
+        // the key stays locked till its new version is in place
+        let new = match self.table.entry(key) {
+            Entry::Occupied(mut oe) => {
+                let unchanged = matches!(
+                    self.versions.get_cloned(&lookup),
+                    Some((seen, snapshot)) if seen == version && is_same_value(&snapshot, oe.value())
+                );
+                if !unchanged {
+                    return Ok(None);
+                }
+                oe.insert(value.clone());
+                stamp(self)
+            }
+            Entry::Vacant(ve) if version == 0 => {
+                self.add_to_bloom(&lookup);
+                ve.insert(value.clone());
+                stamp(self)
+            }
+            Entry::Vacant(_) => return Ok(None),
+        };
+        self.forget_expiry(&lookup);
+        self.access.upsert(lookup, now_ms());
+        Ok(Some(new))
+    }
+    /// Returns a version that hasn't been handed out yet
+    fn next_version(&self) -> u64 {
+        self.version_seq.fetch_add(1, Ordering::Relaxed) + 1
+    }
+    /// Drop the version that was handed out for the key (if any), since it can't be used
+    /// once the key has been written anyway
+    fn forget_version<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.versions.len() != 0 {
+            self.versions.remove(key);
+        }
+    }
+    /// Drop the cached response and document of a key that is gone (a key that is written
+    /// doesn't need this, since they are no longer used once its value changes)
+    fn forget_cached<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(respcache) = &self.respcache {
+            respcache.forget(key);
+        }
+        self.documents.forget(key);
+    }
+    /// Drop all the cached responses and documents
+    fn forget_all_cached(&self) {
+        if let Some(respcache) = &self.respcache {
+            respcache.clear();
+        }
+        self.documents.clear();
+    }
+    /// Drop the expiry of the key, if it has one
+    fn forget_expiry<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.has_expiry.load(Ordering::Acquire) {
+            self.expiry.remove(key);
+        }
+    }
+    /// Get the value of a key and change when it expires (to `deadline`, as a UNIX
+    /// timestamp in milliseconds, or never if it is `None`). Nothing is changed if the key
+    /// doesn't exist
+    pub fn get_and_set_expiry(&self, key: Data, deadline: Option<u64>) -> Result<Option<Data>, ()> {
+        self._encode_key(&key)?;
+        self.expire_if_due(&key);
+        self.touch(&key);
+        // the key can't be removed while we hold a reference to it
+        let value = match self.table.get(&key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        match deadline {
+            Some(deadline) => {
+                self.has_expiry.store(true, Ordering::Release);
+                self.expiry.upsert(key, deadline);
+            }
+            None => self.forget_expiry(&key),
+        }
+        Ok(Some(value.clone()))
+    }
+    /// Make the key expire at `deadline` (a UNIX timestamp in milliseconds), returning
+    /// false if the key doesn't exist. A key with a deadline that has passed is removed
+    /// right away
+    pub fn set_expiry(&self, key: Data, deadline: u64) -> Result<bool, ()> {
+        let lookup = key.clone();
+        let found = self.get_and_set_expiry(key, Some(deadline))?.is_some();
+        if found && deadline <= now_ms() {
+            self.expire_if_due(&lookup);
+        }
+        Ok(found)
+    }
+    /// Get the value of a key along with the number of milliseconds after which it expires
+    /// (`None` if it doesn't)
+    pub fn get_cloned_with_ttl<Q>(&self, key: &Q) -> Result<Option<(Data, Option<u64>)>, ()>
+    where
+        Data: Borrow<Q>,
+        Q: AsRef<[u8]> + Hash + Eq + ?Sized,
+    {
+        self._encode_key(key)?;
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+        self.expire_if_due(key);
+        self.touch(key);
+        let value = match self.table.get(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let ttl = if self.has_expiry.load(Ordering::Acquire) {
+            self.expiry
+                .get_cloned(key)
+                .map(|deadline| deadline.saturating_sub(now_ms()))
+        } else {
+            None
+        };
+        Ok(Some((value.clone(), ttl)))
+    }
+    /// Returns the deadline of the key (as a UNIX timestamp in milliseconds), if it expires
+    pub fn expiry_of<Q>(&self, key: &Q) -> Option<u64>
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.has_expiry.load(Ordering::Acquire) {
+            self.expiry.get_cloned(key)
+        } else {
+            None
+        }
+    }
+    /// Put back the keys (and their deadlines) that were read from disk into a table that
+    /// was emptied when it was unloaded (see
+    /// [`Table::unload`](crate::corestore::table::Table::unload))
+    pub fn reload(&self, data: Coremap<Data, Data>, expiry: Coremap<Data, u64>) {
+        self.table.replace_all(data);
+        self.load_expiries(expiry);
+    }
+    /// Set the deadlines of the keys that were read from disk
+    pub fn load_expiries(&self, expiry: Coremap<Data, u64>) {
+        if expiry.len() == 0 {
+            return;
+        }
+        self.has_expiry.store(true, Ordering::Release);
+        for kv in expiry.iter() {
+            self.expiry.upsert(kv.key().clone(), *kv.value());
+        }
+    }
+    /// Remove all the keys that have expired, returning the number of keys removed
+    pub fn purge_expired(&self) -> usize {
+        if !self.has_expiry.load(Ordering::Acquire) {
+            return 0;
+        }
+        let now = now_ms();
+        let due: Vec<Data> = self
+            .expiry
+            .iter()
+            .filter(|kv| *kv.value() <= now)
+            .map(|kv| kv.key().clone())
+            .collect();
+        due.iter()
+            .filter(|key| {
+                self.expiry
+                    .true_remove_if(*key, |_, deadline| *deadline <= now)
+            })
+            .filter(|key| {
+                self.access.remove(*key);
+                self.forget_version(*key);
+                self.table.true_if_removed(*key)
+            })
+            .count()
     }
 }
 
@@ -447,3 +1095,251 @@ fn test_encoder_validate_with_non_unicode() {
     let encoder = tbl.get_encoder();
     assert!(!encoder.is_ok("hello".as_bytes(), b"Hello \xF0\x90\x80World"));
 }
+
+#[test]
+fn test_read_modify_write() {
+    let tbl = KVEngine::init(true, true);
+    // a vacant key gets inserted
+    let ret = tbl.read_modify_write(Data::from("hello"), |v| {
+        assert!(v.is_none());
+        (Some(Data::from("world")), 1)
+    });
+    assert_eq!(ret, Ok(1));
+    // returning `None` leaves the value untouched
+    tbl.read_modify_write(Data::from("hello"), |v| {
+        assert_eq!(v.unwrap(), &Data::from("world"));
+        (None, ())
+    })
+    .unwrap();
+    assert_eq!(
+        tbl.get_cloned("hello".as_bytes()).unwrap().unwrap(),
+        Data::from("world")
+    );
+    // bad encodings are rejected
+    assert!(tbl
+        .read_modify_write(Data::from("hello"), |_| (
+            Some(Data::from(b"\xF0\x90\x80".to_vec())),
+            ()
+        ))
+        .is_err());
+}
+
+#[test]
+fn test_slab_compaction() {
+    let tbl = KVEngine::default();
+    tbl.set(Data::from("a"), Data::from("small")).unwrap();
+    tbl.set(
+        Data::from("b"),
+        Data::from(vec![b'x'; slab::SLAB_VALUE_MAX]),
+    )
+    .unwrap();
+    // nothing has been overwritten, so there are no holes to get rid of
+    let before = tbl.get_cloned_unchecked("a".as_bytes()).unwrap();
+    assert_eq!(tbl.compact_slab(), 0);
+    let after = tbl.get_cloned_unchecked("a".as_bytes()).unwrap();
+    assert_eq!(before.as_ptr(), after.as_ptr());
+    // overwriting the small value leaves a hole as large as itself
+    tbl.update(Data::from("a"), Data::from("SMALL")).unwrap();
+    let before = tbl.get_cloned_unchecked("a".as_bytes()).unwrap();
+    assert_eq!(tbl.compact_slab(), 5);
+    let after = tbl.get_cloned_unchecked("a".as_bytes()).unwrap();
+    assert_eq!(before, after);
+    // the small value was moved into a new position in the slab
+    assert_ne!(before.as_ptr(), after.as_ptr());
+    assert_eq!(
+        tbl.get_cloned_unchecked("b".as_bytes()).unwrap().len(),
+        slab::SLAB_VALUE_MAX
+    );
+}
+
+#[test]
+fn test_verify_encoding() {
+    let bad_unicode = b"Hello \xF0\x90\x80World".to_vec();
+    let data = Coremap::new();
+    data.upsert(Data::from("good"), Data::from(bad_unicode.clone()));
+    data.upsert(Data::from(""), Data::from(""));
+    // binstr values are fine
+    assert!(KVEngine::init_with_data(true, false, data).verify_encoding());
+    let data = Coremap::new();
+    data.upsert(Data::from("good"), Data::from(bad_unicode));
+    assert!(!KVEngine::init_with_data(true, true, data).verify_encoding());
+}
+
+#[test]
+fn test_expiry() {
+    let tbl = KVEngine::default();
+    tbl.set(Data::from("a"), Data::from("1")).unwrap();
+    tbl.set(Data::from("b"), Data::from("2")).unwrap();
+    // no expiry
+    assert_eq!(
+        tbl.get_cloned_with_ttl("a".as_bytes()).unwrap(),
+        Some((Data::from("1"), None))
+    );
+    // already due
+    assert_eq!(
+        tbl.get_and_set_expiry(Data::from("a"), Some(now_ms() - 1))
+            .unwrap(),
+        Some(Data::from("1"))
+    );
+    assert!(tbl.get_cloned("a".as_bytes()).unwrap().is_none());
+    // far in the future
+    tbl.get_and_set_expiry(Data::from("b"), Some(now_ms() + 60_000))
+        .unwrap();
+    let (_, ttl) = tbl.get_cloned_with_ttl("b".as_bytes()).unwrap().unwrap();
+    assert!(ttl.unwrap() <= 60_000);
+    // updating the key clears its expiry
+    tbl.update(Data::from("b"), Data::from("3")).unwrap();
+    assert_eq!(
+        tbl.get_cloned_with_ttl("b".as_bytes()).unwrap(),
+        Some((Data::from("3"), None))
+    );
+    // missing keys don't get an expiry
+    assert!(tbl
+        .get_and_set_expiry(Data::from("c"), Some(now_ms() + 60_000))
+        .unwrap()
+        .is_none());
+    assert!(tbl.set(Data::from("c"), Data::from("4")).unwrap());
+    assert_eq!(
+        tbl.get_cloned_with_ttl("c".as_bytes()).unwrap(),
+        Some((Data::from("4"), None))
+    );
+}
+
+#[test]
+fn test_purge_expired() {
+    let tbl = KVEngine::default();
+    tbl.set(Data::from("a"), Data::from("1")).unwrap();
+    tbl.set(Data::from("b"), Data::from("2")).unwrap();
+    tbl.set(Data::from("c"), Data::from("3")).unwrap();
+    tbl.get_and_set_expiry(Data::from("a"), Some(now_ms() - 1))
+        .unwrap();
+    tbl.get_and_set_expiry(Data::from("b"), Some(now_ms() + 60_000))
+        .unwrap();
+    assert_eq!(tbl.purge_expired(), 1);
+    assert_eq!(tbl.__get_inner_ref().len(), 2);
+    // persisting the key drops its expiry
+    tbl.get_and_set_expiry(Data::from("b"), None).unwrap();
+    assert_eq!(
+        tbl.get_cloned_with_ttl("b".as_bytes()).unwrap(),
+        Some((Data::from("2"), None))
+    );
+}
+
+#[test]
+fn test_set_expiry() {
+    let tbl = KVEngine::default();
+    tbl.set(Data::from("a"), Data::from("1")).unwrap();
+    tbl.set(Data::from("b"), Data::from("2")).unwrap();
+    assert!(!tbl.set_expiry(Data::from("c"), now_ms() + 60_000).unwrap());
+    assert!(tbl.set_expiry(Data::from("a"), now_ms() + 60_000).unwrap());
+    // a deadline in the past removes the key right away
+    assert!(tbl.set_expiry(Data::from("b"), 0).unwrap());
+    assert_eq!(tbl.__get_inner_ref().len(), 1);
+    let (_, ttl) = tbl.get_cloned_with_ttl("a".as_bytes()).unwrap().unwrap();
+    assert!(ttl.is_some());
+}
+
+#[test]
+fn test_last_access() {
+    let tbl = KVEngine::init_with_data(false, false, {
+        let map = Coremap::new();
+        map.upsert(Data::from("loaded"), Data::from("1"));
+        map
+    });
+    // keys read from disk haven't been accessed yet
+    assert_eq!(tbl.last_access_of("loaded".as_bytes()), None);
+    assert!(tbl.peek_cloned("loaded".as_bytes()).unwrap().is_some());
+    assert_eq!(tbl.last_access_of("loaded".as_bytes()), None);
+    assert!(tbl.get_cloned("loaded".as_bytes()).unwrap().is_some());
+    assert!(tbl.last_access_of("loaded".as_bytes()).is_some());
+    // writes count as accesses too
+    tbl.set(Data::from("a"), Data::from("1")).unwrap();
+    assert!(tbl.last_access_of("a".as_bytes()).is_some());
+    // and the access time goes away with the key
+    assert!(tbl.remove("a".as_bytes()).unwrap());
+    assert_eq!(tbl.last_access_of("a".as_bytes()), None);
+    tbl.truncate_table();
+    assert_eq!(tbl.last_access_of("loaded".as_bytes()), None);
+}
+
+#[test]
+fn test_replace_data() {
+    let tbl = KVEngine::init(true, true);
+    tbl.set(Data::from("old"), Data::from("1")).unwrap();
+    let data = Coremap::new();
+    data.upsert(Data::from("new"), Data::from("2"));
+    assert!(tbl.verify_encoding_of(&data));
+    tbl.replace_data(data, Coremap::new());
+    assert_eq!(tbl.len(), 1);
+    assert!(tbl.get_cloned("old".as_bytes()).unwrap().is_none());
+    assert_eq!(
+        tbl.get_cloned("new".as_bytes()).unwrap().unwrap(),
+        Data::from("2")
+    );
+    // a str table can't take binary keys
+    let bad = Coremap::new();
+    bad.upsert(Data::from(vec![0xF0, 0x99]), Data::from("3"));
+    assert!(!tbl.verify_encoding_of(&bad));
+}
+
+#[test]
+fn test_snapshot() {
+    let tbl = KVEngine::init(true, true);
+    tbl.set(Data::from("a"), Data::from("1")).unwrap();
+    tbl.set(Data::from("b"), Data::from("2")).unwrap();
+    let deadline = now_ms() + 60_000;
+    tbl.set_expiry(Data::from("b"), deadline).unwrap();
+    let snapshot = tbl.snapshot();
+    // writes after the snapshot don't show up in it
+    tbl.set(Data::from("c"), Data::from("3")).unwrap();
+    tbl.remove("a".as_bytes()).unwrap();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot.get_encoding(), (true, true));
+    assert_eq!(
+        snapshot.get_cloned("a".as_bytes()).unwrap().unwrap(),
+        Data::from("1")
+    );
+    assert_eq!(snapshot.expiry_of("b".as_bytes()), Some(deadline));
+    assert!(snapshot.get_cloned("c".as_bytes()).unwrap().is_none());
+}
+
+#[test]
+fn test_versions() {
+    let tbl = KVEngine::init(false, false);
+    // a version of 0 only sets a key that doesn't exist
+    let v1 = tbl
+        .set_versioned(Data::from("a"), 0, Data::from("1"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        tbl.set_versioned(Data::from("a"), 0, Data::from("2"))
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        tbl.get_versioned("a".as_bytes()).unwrap(),
+        Some((Data::from("1"), v1))
+    );
+    let v2 = tbl
+        .set_versioned(Data::from("a"), v1, Data::from("2"))
+        .unwrap()
+        .unwrap();
+    assert!(v2 > v1);
+    // the old version can't be used again
+    assert_eq!(
+        tbl.set_versioned(Data::from("a"), v1, Data::from("3"))
+            .unwrap(),
+        None
+    );
+    // and neither can a version that was handed out before some other write
+    tbl.upsert(Data::from("a"), Data::from("4")).unwrap();
+    assert_eq!(
+        tbl.set_versioned(Data::from("a"), v2, Data::from("5"))
+            .unwrap(),
+        None
+    );
+    let (value, v3) = tbl.get_versioned("a".as_bytes()).unwrap().unwrap();
+    assert_eq!(value, Data::from("4"));
+    assert!(v3 > v2);
+    assert_eq!(tbl.get_versioned("b".as_bytes()).unwrap(), None);
+}
@@ -0,0 +1,145 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Response caches
+//!
+//! A table can be created with a response cache (`CREATE TABLE ... cache(<maxkeys>)`) so
+//! that a `GET` of a key that is read over and over without being written is answered with
+//! the response that was serialized for it the last time, instead of serializing the value
+//! again. Every cached response is kept along with the value that it was serialized from, and
+//! it is only used while the key still has that very value (see
+//! [`is_same_value`](super::is_same_value)), so any write to the key invalidates it, whatever
+//! action it comes from.
+//!
+//! The cache holds up to `maxkeys` responses. Once it's full, it's emptied, so that the keys
+//! that are still hot fill it up again while the ones that have gone cold drop out. Like the
+//! bloom filter, the cache only lives in memory, so a table comes back without one after a
+//! restart
+
+use super::is_same_value;
+use crate::corestore::htable::Coremap;
+use crate::corestore::htable::Data;
+use bytes::{BufMut, Bytes, BytesMut};
+use core::borrow::Borrow;
+use core::hash::Hash;
+
+/// Returns true if `maxkeys` is a size that a cache can be created with
+pub const fn is_valid_maxkeys(maxkeys: usize) -> bool {
+    maxkeys != 0
+}
+
+#[derive(Debug)]
+/// The serialized `GET` responses of a table's hot keys
+pub struct ResponseCache {
+    maxkeys: usize,
+    /// the responses, along with the values that they were serialized from
+    responses: Coremap<Data, (Data, Bytes)>,
+}
+
+impl ResponseCache {
+    pub fn new(maxkeys: usize) -> Self {
+        Self {
+            maxkeys,
+            responses: Coremap::new(),
+        }
+    }
+    pub const fn maxkeys(&self) -> usize {
+        self.maxkeys
+    }
+    /// Returns the response for the key if it was serialized from `value`
+    pub fn get<Q>(&self, key: &Q, value: &Data) -> Option<Bytes>
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.responses.get(key) {
+            Some(cached) if is_same_value(&cached.value().0, value) => {
+                Some(cached.value().1.clone())
+            }
+            _ => None,
+        }
+    }
+    /// Returns the response for `value` (which the key has with the type `tsymbol`),
+    /// serializing it and caching it for the next time if it wasn't cached
+    pub fn get_or_insert(&self, key: &[u8], tsymbol: u8, value: Data) -> Bytes {
+        if let Some(response) = self.get(key, &value) {
+            return response;
+        }
+        let response = serialize(tsymbol, &value);
+        // empty values can't be told apart from the values that replace them
+        if !value.is_empty() {
+            if self.responses.len() >= self.maxkeys && !self.responses.contains_key(key) {
+                self.responses.clear();
+            }
+            self.responses
+                .upsert(Data::copy_from_slice(key), (value, response.clone()));
+        }
+        response
+    }
+    /// Drop the response for the key (if there is one), since the key is gone
+    pub fn forget<Q>(&self, key: &Q)
+    where
+        Data: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.responses.len() != 0 {
+            self.responses.remove(key);
+        }
+    }
+    /// Drop all the responses
+    pub fn clear(&self) {
+        self.responses.clear();
+    }
+}
+
+/// Serialize `value` into a response with the type `tsymbol` (`<tsymbol><len>\n<value>\n`)
+fn serialize(tsymbol: u8, value: &[u8]) -> Bytes {
+    let len = value.len().to_string();
+    let mut response = BytesMut::with_capacity(len.len() + value.len() + 3);
+    response.put_u8(tsymbol);
+    response.put_slice(len.as_bytes());
+    response.put_u8(b'\n');
+    response.put_slice(value);
+    response.put_u8(b'\n');
+    response.freeze()
+}
+
+#[test]
+fn test_response_cache() {
+    let cache = ResponseCache::new(2);
+    let value = Data::from("world");
+    let response = cache.get_or_insert(b"hello", b'+', value.clone());
+    assert_eq!(&response[..], b"+5\nworld\n");
+    assert_eq!(cache.get(b"hello".as_ref(), &value), Some(response));
+    // a new value (even an equal one) invalidates the response
+    let rewritten = Data::copy_from_slice(b"world");
+    assert_eq!(cache.get(b"hello".as_ref(), &rewritten), None);
+    // the cache is emptied once it's full
+    cache.get_or_insert(b"a", b'+', Data::from("1"));
+    cache.get_or_insert(b"b", b'+', Data::from("2"));
+    assert_eq!(cache.get(b"hello".as_ref(), &value), None);
+    cache.forget(b"b".as_ref());
+    assert_eq!(cache.responses.len(), 0);
+}
@@ -0,0 +1,149 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Slab storage for small values
+//!
+//! Storing every small value in its own heap allocation wastes a lot of memory on allocator
+//! overhead and fragments the heap. Instead, small values are copied into a shared chunk
+//! that is owned by the table and every value just holds a reference counted view into it.
+//! A chunk is only freed once every value that points into it is gone, so values that are
+//! overwritten or removed leave holes behind; [`KVEngine::compact_slab`] gets rid of them
+//! by copying the live values into fresh chunks. Since that copies every small value, it's
+//! only done once more than [`COMPACT_THRESHOLD`] percent of the bytes that were copied
+//! into the slab (since it was last compacted) are holes.
+//!
+//! [`KVEngine::compact_slab`]: super::KVEngine::compact_slab
+
+use crate::corestore::lock::QuickLock;
+use bytes::{Bytes, BytesMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Values smaller than this (in bytes) are stored in the slab
+pub const SLAB_VALUE_MAX: usize = 64;
+/// The size of every chunk that is allocated by the slab
+pub const CHUNK_SIZE: usize = 16 * 1024;
+/// The share (in percent) of a slab's bytes that have to be holes before it's compacted
+pub const COMPACT_THRESHOLD: usize = 25;
+
+/// Returns true if the value is small enough to be stored in a slab
+pub const fn is_small(value: &[u8]) -> bool {
+    !value.is_empty() && value.len() < SLAB_VALUE_MAX
+}
+
+#[derive(Debug)]
+/// A per-table slab that packs small values into shared chunks
+pub struct Slab {
+    chunk: QuickLock<BytesMut>,
+    /// the number of bytes that were copied into the chunks since the last compaction
+    stored: AtomicUsize,
+}
+
+impl Default for Slab {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Slab {
+    pub fn new() -> Self {
+        Self {
+            chunk: QuickLock::new(BytesMut::new()),
+            stored: AtomicUsize::new(0),
+        }
+    }
+    /// Copy `value` into the slab, returning a view into the current chunk
+    ///
+    /// This returns `None` if the value is not small enough or if some other writer is
+    /// currently holding the slab (in which case the caller should just keep its own
+    /// allocation instead of waiting on the lock)
+    pub fn try_store(&self, value: &[u8]) -> Option<Bytes> {
+        if !is_small(value) {
+            return None;
+        }
+        let mut chunk = self.chunk.try_lock()?;
+        if chunk.capacity() < value.len() {
+            // not enough space left; the old chunk lives on till its last value is dropped
+            *chunk = BytesMut::with_capacity(CHUNK_SIZE);
+        }
+        chunk.extend_from_slice(value);
+        self.stored.fetch_add(value.len(), Ordering::Relaxed);
+        Some(chunk.split().freeze())
+    }
+    /// Returns true if more than [`COMPACT_THRESHOLD`] percent of the bytes that were
+    /// copied into the slab are holes, given that `live` of them are still in use
+    pub fn needs_compaction(&self, live: usize) -> bool {
+        let stored = self.stored.load(Ordering::Relaxed);
+        stored.saturating_sub(live) * 100 > stored * COMPACT_THRESHOLD
+    }
+    /// Start counting the bytes that are copied into the slab afresh, since the live
+    /// values are about to be copied into new chunks
+    pub fn start_compaction(&self) {
+        self.stored.store(0, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_slab_store() {
+    let slab = Slab::new();
+    let a = slab.try_store(b"hello").unwrap();
+    let b = slab.try_store(b"world").unwrap();
+    assert_eq!(a, Bytes::from_static(b"hello"));
+    assert_eq!(b, Bytes::from_static(b"world"));
+    // both values should be adjacent in the same chunk
+    assert_eq!(unsafe { a.as_ptr().add(a.len()) }, b.as_ptr());
+}
+
+#[test]
+fn test_slab_rejects_large_and_empty() {
+    let slab = Slab::new();
+    assert!(slab.try_store(&[0u8; SLAB_VALUE_MAX]).is_none());
+    assert!(slab.try_store(b"").is_none());
+}
+
+#[test]
+fn test_slab_needs_compaction() {
+    let slab = Slab::new();
+    assert!(!slab.needs_compaction(0));
+    for _ in 0..4 {
+        slab.try_store(b"hello").unwrap();
+    }
+    // a quarter of the bytes being holes is fine
+    assert!(!slab.needs_compaction(15));
+    assert!(slab.needs_compaction(10));
+    slab.start_compaction();
+    slab.try_store(b"hello").unwrap();
+    assert!(!slab.needs_compaction(5));
+}
+
+#[test]
+fn test_slab_new_chunk() {
+    let slab = Slab::new();
+    let value = [b'x'; SLAB_VALUE_MAX - 1];
+    let stored: Vec<Bytes> = (0..(CHUNK_SIZE / value.len()) * 2)
+        .map(|_| slab.try_store(&value).unwrap())
+        .collect();
+    assert!(stored.iter().all(|v| v.as_ref() == value));
+}
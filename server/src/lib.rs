@@ -0,0 +1,99 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#![deny(unused_crate_dependencies)]
+#![deny(unused_imports)]
+
+//! # Skytable
+//!
+//! The `skyd` crate (or the `server` folder) is Skytable's database server. The `skyd`
+//! binary (see `main.rs`) is a thin wrapper that reads the configuration and starts the
+//! [`arbiter`]; everything else lives in this library, see the modules for their
+//! respective documentation. The modules that the binary uses are public so that it can
+//! reach them, but they aren't a stable API
+//!
+//! With the `embedded` feature, the library can also be used to run the storage engine and
+//! the query engine within another Rust process, without the network stack. See
+//! [`embedded::Embedded`] to get started
+//!
+//! With the `fuzz` feature, the library also exposes the Skyhash parser through [`fuzz`], for
+//! the fuzz targets in `fuzz/`
+//!
+//! With the `conformance` feature, the library exposes the protocol conformance vectors
+//! through [`conformance`], so that client authors can check their implementations against
+//! the server
+
+// the logger and the global allocator are set up by the binary
+use env_logger as _;
+#[cfg(all(not(target_env = "msvc"), feature = "jemalloc"))]
+use jemallocator as _;
+
+#[macro_use]
+mod util;
+#[macro_use] // HACK(@ohsayan): macro_use will only work with extern crate for some moon reasons
+extern crate libsky;
+mod actions;
+mod admin;
+pub mod allocator;
+pub mod arbiter;
+pub mod config;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+pub mod corestore;
+pub mod crypto;
+mod dbnet;
+pub mod diskstore;
+#[cfg(feature = "embedded")]
+pub mod embedded;
+mod kvengine;
+mod protocol;
+pub mod queryengine;
+pub mod registry;
+mod resp;
+pub mod services;
+#[cfg(feature = "simulation")]
+mod sim;
+pub mod storage;
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "embedded")]
+/// Custom actions (see [`register`](plugins::register))
+pub mod plugins {
+    pub use crate::queryengine::arity::Arity;
+    pub use crate::queryengine::plugins::{
+        register, Action, Context, Error, RegisterError, Response,
+    };
+    pub use crate::resp::builder::ResponseElement;
+}
+
+#[cfg(feature = "fuzz")]
+/// The Skyhash parser, for fuzz targets
+pub mod fuzz {
+    pub use crate::protocol::{parse_packet, Element, ParseError, ParseResult, Query};
+}
+
+type IoResult<T> = std::io::Result<T>;
@@ -24,55 +24,68 @@
  *
 */
 
-#![deny(unused_crate_dependencies)]
 #![deny(unused_imports)]
 
 //! # Skytable
 //!
-//! The `skyd` crate (or the `server` folder) is Skytable's database server and maybe
-//! is the most important part of the project. There are several modules within this crate; see
-//! the modules for their respective documentation.
+//! The `skyd` binary: it reads the configuration, sets up the global allocator and the
+//! logger and runs the server (see the `skyd` library for everything else)
 
-use crate::corestore::memstore::Memstore;
-use crate::diskstore::flock::FileLock;
 use env_logger::Builder;
 use libsky::util::terminal;
 use libsky::URL;
 use libsky::VERSION;
+#[cfg(feature = "alloc-accounting")]
+use skyd::allocator;
+use skyd::allocator::hugepages::{self, HugePages};
+use skyd::config::{self, ParsedConfig, StartupArgs};
+use skyd::corestore::{self, memstore::Memstore};
+use skyd::diskstore::flock::FileLock;
+use skyd::diskstore::instance::{self, LockError};
+use skyd::{arbiter, crypto, queryengine, registry, services, storage};
 use std::env;
 use std::process;
 use std::thread;
 use std::time;
-#[macro_use]
-mod util;
-#[macro_use] // HACK(@ohsayan): macro_use will only work with extern crate for some moon reasons
-extern crate libsky;
-mod actions;
-mod admin;
-mod arbiter;
-mod config;
-mod corestore;
-mod dbnet;
-mod diskstore;
-mod kvengine;
-mod protocol;
-mod queryengine;
-pub mod registry;
-mod resp;
-mod services;
-mod storage;
-#[cfg(test)]
-mod tests;
 
-const PATH: &str = ".sky_pid";
-
-#[cfg(not(target_env = "msvc"))]
+#[cfg(all(not(target_env = "msvc"), feature = "jemalloc"))]
 use jemallocator::Jemalloc;
 
-#[cfg(not(target_env = "msvc"))]
+#[cfg(all(
+    not(target_env = "msvc"),
+    feature = "jemalloc",
+    not(feature = "alloc-accounting")
+))]
 #[global_allocator]
 /// Jemallocator - this is the default memory allocator for platforms other than msvc
-static GLOBAL: Jemalloc = Jemalloc;
+static GLOBAL: HugePages<Jemalloc> = HugePages::new(Jemalloc);
+
+#[cfg(all(
+    not(target_env = "msvc"),
+    feature = "jemalloc",
+    feature = "alloc-accounting"
+))]
+#[global_allocator]
+/// Jemallocator, with allocation accounting
+static GLOBAL: allocator::Accounted<HugePages<Jemalloc>> =
+    allocator::Accounted::new(HugePages::new(Jemalloc));
+
+#[cfg(all(
+    any(target_env = "msvc", not(feature = "jemalloc")),
+    feature = "alloc-accounting"
+))]
+#[global_allocator]
+/// The system allocator, with allocation accounting
+static GLOBAL: allocator::Accounted<HugePages<std::alloc::System>> =
+    allocator::Accounted::new(HugePages::new(std::alloc::System));
+
+#[cfg(all(
+    any(target_env = "msvc", not(feature = "jemalloc")),
+    not(feature = "alloc-accounting")
+))]
+#[global_allocator]
+/// The system allocator
+static GLOBAL: HugePages<std::alloc::System> = HugePages::new(std::alloc::System);
 
 /// The terminal art for `!noart` configurations
 const TEXT: &str = "
@@ -83,32 +96,79 @@ const TEXT: &str = "
 ███████ ██   ██    ██       ██    ██   ██ ██████  ███████ ███████
 ";
 
-type IoResult<T> = std::io::Result<T>;
-
 fn main() {
+    #[cfg(feature = "alloc-accounting")]
+    allocator::register(&GLOBAL);
     Builder::new()
         .parse_filters(&env::var("SKY_LOG").unwrap_or_else(|_| "info".to_owned()))
         .init();
+    let (cfg, startup) = check_args_and_get_cfg();
+    // the crypto backend has to be ready before anything is hashed or any listener starts
+    if let Err(e) = crypto::init() {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+    // the topology decides how many worker threads the runtime has, and where they run
+    registry::topology::set_topology(cfg.topology);
+    registry::bgio::set_limits(cfg.backgroundio);
     // Start the server which asynchronously waits for a CTRL+C signal
     // which will safely shut down the server
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .thread_name("server")
+        .worker_threads(registry::topology::worker_threads())
+        .on_thread_start(registry::topology::on_thread_start)
         .enable_all()
         .build()
         .unwrap();
-    let (ports, bgsave_config, snapshot_config, restore_filepath, maxcon) =
-        check_args_and_get_cfg();
+    if cfg.mode != registry::ServerMode::Normal {
+        log::warn!("Starting in `{}` mode", cfg.mode.as_str());
+    }
+    registry::set_mode(cfg.mode);
+    registry::set_idle_timeout(cfg.idletimeout);
+    registry::set_keepalive(cfg.keepalive);
+    registry::set_max_buffer(cfg.maxbuffer);
+    registry::set_flush_confirm(cfg.flushconfirm);
+    registry::set_drop_retention(cfg.dropretention);
+    registry::set_idle_unload(cfg.idleunload);
+    registry::set_recovery_threads(cfg.recoverythreads);
+    registry::set_snapshot_threads(cfg.snapshotthreads);
+    corestore::hasher::set_hash_function(cfg.hasher);
+    hugepages::set_mode(cfg.hugepages);
+    registry::stall::set_min_free(cfg.minfreedisk);
+    registry::pressure::set_policy(cfg.memory);
+    registry::admission::set_policy(cfg.admission);
+    registry::auth::set_users(cfg.users);
+    services::origin::set_origins(cfg.origins);
+    services::throttle::set_throttles(cfg.throttles);
+    services::expiry::set_jitters(cfg.jitters);
+    if let Err(e) = services::schema::load(cfg.schemas) {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+    services::shadow::set_shadows(cfg.shadows);
+    services::warmup::set_tables(cfg.preload);
+    services::bgsave::set_adaptive(cfg.adaptiveflush);
+    if let Some(querylog) = cfg.querylog {
+        if let Err(e) = services::querylog::start(querylog) {
+            log::error!("Failed to open the query log: {}", e);
+            process::exit(1);
+        }
+    }
+    queryengine::plugins::register_static();
     // check if any other process is using the data directory and lock it if not (else error)
     // important: create the pid_file just here and nowhere else because check_args can also
     // involve passing --help or wrong arguments which can falsely create a PID file
-    let pid_file = run_pre_startup_tasks();
+    let pid_file = run_pre_startup_tasks(startup.takeover);
     let db: Result<corestore::Corestore, String> = runtime.block_on(async move {
         arbiter::run(
-            ports,
-            bgsave_config,
-            snapshot_config,
-            restore_filepath,
-            maxcon,
+            cfg.ports,
+            cfg.deny,
+            cfg.listeners,
+            cfg.socket,
+            cfg.bgsave,
+            cfg.snapshot,
+            startup.restore,
+            cfg.maxcon,
         )
         .await
     });
@@ -129,6 +189,12 @@ fn main() {
         "Maybe the compiler reordered the drop causing more than one instance of Corestore to live at this point"
     );
     log::info!("Stopped accepting incoming connections");
+    if !instance::is_owner() {
+        // someone used `--takeover`, so anything we write now would clobber their data
+        log::error!("The data directory has been taken over by another process. Not saving data");
+        pre_shutdown_cleanup(pid_file, None);
+        process::exit(0x01);
+    }
     loop {
         // Keep looping until we successfully write the in-memory table to disk
         match services::bgsave::run_bgsave(&db) {
@@ -149,7 +215,7 @@ fn main() {
     terminal::write_info("Goodbye :)\n").unwrap();
 }
 
-pub fn pre_shutdown_cleanup(mut pid_file: FileLock, mr: Option<&Memstore>) {
+fn pre_shutdown_cleanup(mut pid_file: FileLock, mr: Option<&Memstore>) {
     if let Err(e) = pid_file.unlock() {
         log::error!("Shutdown failure: Failed to unlock pid file: {}", e);
         process::exit(0x01);
@@ -163,13 +229,11 @@ pub fn pre_shutdown_cleanup(mut pid_file: FileLock, mr: Option<&Memstore>) {
     }
 }
 
-use self::config::{BGSave, PortConfig, SnapshotConfig};
-
 /// This function checks the command line arguments and either returns a config object
 /// or prints an error to `stderr` and terminates the server
-fn check_args_and_get_cfg() -> (PortConfig, BGSave, SnapshotConfig, Option<String>, usize) {
+fn check_args_and_get_cfg() -> (ParsedConfig, StartupArgs) {
     let cfg = config::get_config_file_or_return_cfg();
-    let binding_and_cfg = match cfg {
+    match cfg {
         Ok(config::ConfigType::Custom(cfg, file)) => {
             if cfg.is_artful() {
                 println!("Skytable v{} | {}\n{}", VERSION, URL, TEXT);
@@ -177,40 +241,40 @@ fn check_args_and_get_cfg() -> (PortConfig, BGSave, SnapshotConfig, Option<Strin
                 println!("Skytable v{} | {}", VERSION, URL);
             }
             log::info!("Using settings from supplied configuration");
-            (cfg.ports, cfg.bgsave, cfg.snapshot, file, cfg.maxcon)
+            (cfg, file)
         }
         Ok(config::ConfigType::Def(cfg, file)) => {
             println!("Skytable v{} | {}\n{}", VERSION, URL, TEXT);
             log::warn!("No configuration file supplied. Using default settings");
-            (cfg.ports, cfg.bgsave, cfg.snapshot, file, cfg.maxcon)
+            (cfg, file)
         }
         Err(e) => {
             log::error!("{}", e);
             std::process::exit(0x01);
         }
-    };
-    binding_and_cfg
+    }
 }
 
-/// On startup, we attempt to check if a `.sky_pid` file exists. If it does, then
-/// this file will contain the kernel/operating system assigned process ID of the
-/// skyd process. We will attempt to read that and log an error complaining that
-/// the directory is in active use by another process. If the file doesn't then
-/// we're free to create our own file and write our own PID to it. Any subsequent
-/// processes will detect this and this helps us prevent two processes from writing
-/// to the same directory which can cause potentially undefined behavior.
+/// On startup, we attempt to lock the `.sky_pid` file. If another skyd process holds
+/// the lock, then this file will contain the kernel/operating system assigned process ID
+/// of that process (and its instance ID). We will read that and log an error complaining
+/// that the directory is in active use by that process. If nobody holds the lock, then
+/// we're free to write our own PID and instance ID to it. Any subsequent processes will
+/// detect this and this helps us prevent two processes from writing to the same directory
+/// which can cause potentially undefined behavior.
 ///
-fn run_pre_startup_tasks() -> FileLock {
-    let mut file = match FileLock::lock(PATH) {
-        Ok(fle) => fle,
+/// If `takeover` is set, we'll replace the file even if it's locked (see [`instance::lock`])
+fn run_pre_startup_tasks(takeover: bool) -> FileLock {
+    match instance::lock(takeover) {
+        Ok(file) => file,
         Err(e) => {
-            log::error!("Startup failure: Failed to lock pid file: {}", e);
+            log::error!("Startup failure: {}", e);
+            if let LockError::InUse(..) = e {
+                log::error!(
+                    "If that process crashed and isn't running anymore, restart with `--takeover`"
+                );
+            }
             process::exit(0x01);
         }
-    };
-    if let Err(e) = file.write(process::id().to_string().as_bytes()) {
-        log::error!("Startup failure: Failed to write to pid file: {}", e);
-        process::exit(0x01);
     }
-    file
 }
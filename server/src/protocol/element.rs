@@ -36,6 +36,8 @@ pub enum Element {
     Array(Vec<Element>),
     /// A String value; `<tsymbol>` is `+`
     String(Bytes),
+    /// A binary string (a blob of raw bytes that need not be valid unicode); `<tsymbol>` is `?`
+    Binary(Bytes),
     /// An unsigned integer value; `<tsymbol>` is `:`
     UnsignedInt(u64),
     /// A non-recursive String array; tsymbol: `_`
@@ -0,0 +1,142 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Protocol handshakes
+//!
+//! A client can run `HELLO <version> [<capability> ...]` to tell the server the highest
+//! protocol version that it speaks and the optional protocol features (capabilities) that
+//! it understands. The server agrees on the lower of the two versions and on the
+//! capabilities that both ends know about, and then only uses the agreed features on that
+//! connection. Clients that never say `HELLO` keep getting what they get today, so new
+//! features can be added as capabilities without breaking older clients
+
+/// The highest version of the protocol that the server speaks
+pub const VERSION: u64 = 1;
+
+/// The capabilities that the server can agree on, along with their names
+const KNOWN: [(&str, Capabilities); 6] = [
+    ("typed-arrays", Capabilities::TYPED_ARRAYS),
+    ("verbose-errors", Capabilities::VERBOSE_ERRORS),
+    ("key-errors", Capabilities::KEY_ERRORS),
+    ("invalidations", Capabilities::INVALIDATIONS),
+    ("metadata", Capabilities::METADATA),
+    ("nested", Capabilities::NESTED),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// A set of capabilities
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// Arrays whose elements all have the same type are sent as typed arrays
+    pub const TYPED_ARRAYS: Self = Self(1 << 0);
+    /// Queries with the wrong number of arguments get an error that names the action
+    /// (see [`arity`](crate::queryengine::arity))
+    pub const VERBOSE_ERRORS: Self = Self(1 << 1);
+    /// Strong actions that fail return the keys that failed their check (see
+    /// [`strong`](crate::actions::strong))
+    pub const KEY_ERRORS: Self = Self(1 << 2);
+    /// Push frames with invalidations can be sent in between responses (see
+    /// [`tracking`](crate::registry::tracking))
+    pub const INVALIDATIONS: Self = Self(1 << 3);
+    /// A metadata frame can be sent after every response (see
+    /// [`verbose`](crate::queryengine::verbose))
+    pub const METADATA: Self = Self(1 << 4);
+    /// Responses that are naturally nested (like `INSPECT USAGE`) are sent as nested arrays
+    /// and maps (see [`builder`](crate::resp::builder)) instead of flat arrays
+    pub const NESTED: Self = Self(1 << 5);
+    /// Returns all the capabilities that the server supports
+    pub fn all() -> Self {
+        KNOWN
+            .iter()
+            .fold(Self::NONE, |all, (_, cap)| all.with(*cap))
+    }
+    pub const fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    /// Returns the names of the capabilities in this set
+    pub fn names(&self) -> Vec<&'static str> {
+        KNOWN
+            .iter()
+            .filter(|(_, cap)| self.contains(*cap))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The protocol version and capabilities that a connection agreed on
+pub struct Handshake {
+    pub version: u64,
+    pub capabilities: Capabilities,
+}
+
+impl Handshake {
+    /// Returns what the server offers to clients that haven't said `HELLO` yet
+    pub fn offer() -> Self {
+        Self {
+            version: VERSION,
+            capabilities: Capabilities::all(),
+        }
+    }
+    /// Agree on a version and capabilities with a client that speaks up to `version` and
+    /// understands the given capabilities. Capabilities that the server doesn't know
+    /// (possibly because they were added in a later version) are ignored. Returns `None`
+    /// if the client doesn't speak any version that we do
+    pub fn negotiate<'a>(version: u64, requested: impl Iterator<Item = &'a [u8]>) -> Option<Self> {
+        if version == 0 {
+            return None;
+        }
+        let capabilities = requested
+            .filter_map(|name| {
+                KNOWN
+                    .iter()
+                    .find(|(known, _)| known.as_bytes().eq_ignore_ascii_case(name))
+                    .map(|(_, cap)| *cap)
+            })
+            .fold(Capabilities::NONE, Capabilities::with);
+        Some(Self {
+            version: version.min(VERSION),
+            capabilities,
+        })
+    }
+}
+
+#[test]
+fn test_handshake_negotiate() {
+    let caps: [&[u8]; 3] = [b"TYPED-ARRAYS", b"compression", b"typed-arrays"];
+    let hs = Handshake::negotiate(7, caps.iter().copied()).unwrap();
+    assert_eq!(hs.version, VERSION);
+    assert_eq!(hs.capabilities, Capabilities::TYPED_ARRAYS);
+    assert_eq!(hs.capabilities.names(), vec!["typed-arrays"]);
+    let hs = Handshake::negotiate(1, std::iter::empty()).unwrap();
+    assert_eq!(hs.capabilities, Capabilities::NONE);
+    assert!(hs.capabilities.names().is_empty());
+    assert!(Handshake::negotiate(0, std::iter::empty()).is_none());
+}
@@ -35,8 +35,24 @@
 //! a slice of unsigned 8-bit integers and the parser will do everything else. The Skyhash protocol was designed
 //! and implemented by the Author (Sayan Nandan)
 //!
+//! ## Strict mode
+//! By default, the parser is lenient in a few places so that it can work on a stream: it
+//! leaves bytes that follow the query in the buffer (they can be the beginning of the next
+//! query). A parser created with [`Parser::new_strict`] expects the buffer to hold exactly
+//! one query packet and rejects any deviation from the spec with a precise [`ParseError`]:
+//! trailing bytes, sizelines that are longer than a size can ever be (or that have leading
+//! zeros) and newlines where a size was expected. Fuzz targets use this mode through
+//! [`parse_packet`]
+//!
+//! ## Deadlines
+//! A client can attach a deadline (in milliseconds) to a query by following the count in the
+//! metaframe with `@<ms>`, like `*1@50\n`. The parser only reads the deadline (see
+//! [`Parser::parse_with_deadline`]); it is up to the connection to skip queries that have
+//! waited for longer than that by the time that they would be run
+//!
 
 mod element;
+pub mod hello;
 pub mod responses;
 use crate::util::Unwrappable;
 use bytes::Bytes;
@@ -47,7 +63,12 @@ const ASCII_UNDERSCORE: u8 = b'_';
 const ASCII_AMPERSAND: u8 = b'&';
 const ASCII_COLON: u8 = b':';
 const ASCII_PLUS_SIGN: u8 = b'+';
+const ASCII_QUESTION_MARK: u8 = b'?';
 const ASCII_TILDE_SIGN: u8 = b'~';
+const ASCII_AT_SIGN: u8 = b'@';
+/// The number of digits in [`usize::MAX`] (on 64-bit targets); a longer sizeline can only
+/// overflow
+const MAX_SIZELINE_DIGITS: usize = 20;
 
 #[derive(Debug)]
 /// # Skyhash Deserializer (Parser)
@@ -74,6 +95,10 @@ pub(super) struct Parser<'a> {
     cursor: usize,
     /// The buffer slice
     buffer: &'a [u8],
+    /// Whether any deviation from the spec should be rejected (see the module docs)
+    strict: bool,
+    /// The deadline (in milliseconds) that was given in the metaframe, if any
+    deadline: Option<u64>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -103,6 +128,18 @@ pub enum ParseError {
     ///
     /// The **parser will never return this**, but instead it is provided for convenience with [`dbnet`]
     Empty,
+    /// There are bytes after the end of the query
+    ///
+    /// This is only returned in strict mode
+    TrailingBytes,
+    /// A sizeline has more digits than a size can have, or has leading zeros
+    ///
+    /// This is only returned in strict mode
+    OversizeSizeline,
+    /// A newline was found where a size was expected (for example, an empty sizeline)
+    ///
+    /// This is only returned in strict mode
+    EmbeddedNewline,
 }
 
 #[derive(Debug, PartialEq)]
@@ -125,18 +162,40 @@ impl<'a> Parser<'a> {
         Parser {
             cursor: 0usize,
             buffer,
+            strict: false,
+            deadline: None,
+        }
+    }
+    #[cfg(any(test, feature = "fuzz"))]
+    /// Initialize a new parser instance that expects `buffer` to hold exactly one query
+    /// packet and rejects any deviation from the spec
+    pub const fn new_strict(buffer: &'a [u8]) -> Self {
+        Parser {
+            cursor: 0usize,
+            buffer,
+            strict: true,
+            deadline: None,
         }
     }
     /// Read from the current cursor position to `until` number of positions ahead
     /// This **will forward the cursor itself** if the bytes exist or it will just return a `NotEnough` error
     fn read_until(&mut self, until: usize) -> ParseResult<&[u8]> {
-        if let Some(b) = self.buffer.get(self.cursor..self.cursor + until) {
-            self.cursor += until;
+        let end = match self.cursor.checked_add(until) {
+            Some(end) => end,
+            None => return Err(ParseError::NotEnough),
+        };
+        if let Some(b) = self.buffer.get(self.cursor..end) {
+            self.cursor = end;
             Ok(b)
         } else {
             Err(ParseError::NotEnough)
         }
     }
+    /// The number of bytes that are left in the buffer. No array can have more elements than
+    /// this, so it is used to cap allocations (a client can claim any size that it wants)
+    fn remaining(&self) -> usize {
+        self.buffer.len().saturating_sub(self.cursor)
+    }
     /// This returns the position at which the line parsing began and the position at which the line parsing
     /// stopped, in other words, you should be able to do self.buffer[started_at..stopped_at] to get a line
     /// and do it unchecked. This **will move the internal cursor ahead** and place it **at the `\n` byte**
@@ -211,6 +270,21 @@ impl<'a> Parser<'a> {
         }
         Ok(item_usize)
     }
+    /// Parse the sizeline `line` (that was just read with [`Self::read_line`] and stops at
+    /// `stop`) into a size, rejecting malformed sizelines in strict mode
+    fn parse_sizeline(&self, line: &[u8], stop: usize) -> ParseResult<usize> {
+        if self.strict {
+            if line.len() > MAX_SIZELINE_DIGITS || (line.len() > 1 && line[0] == b'0') {
+                return Err(ParseError::OversizeSizeline);
+            }
+            // read_line forwards the cursor past the LF (if there is one), so an empty line
+            // with the cursor ahead of it means that we got a bare LF
+            if line.is_empty() && self.cursor > stop {
+                return Err(ParseError::EmbeddedNewline);
+            }
+        }
+        Self::parse_into_usize(line)
+    }
     /// Pasre a stream of bytes into an [`u64`]
     fn parse_into_u64(bytes: &[u8]) -> ParseResult<u64> {
         if bytes.is_empty() {
@@ -252,14 +326,22 @@ impl<'a> Parser<'a> {
         if self.buffer.len() < 3 {
             return Err(ParseError::NotEnough);
         }
-        // Now we want to read `*<n>\n`
+        // Now we want to read `*<n>\n` (or `*<n>@<ms>\n` if the query has a deadline)
         let (start, stop) = self.read_line();
         if let Some(our_chunk) = self.buffer.get(start..stop) {
-            if our_chunk[0] == b'*' {
+            if our_chunk.first() == Some(&b'*') {
                 // Good, this will tell us the number of actions
                 // Let us attempt to read the usize from this point onwards
                 // that is excluding the '*' (so 1..)
-                let ret = Self::parse_into_usize(&our_chunk[1..])?;
+                let line = &our_chunk[1..];
+                let (count, deadline) = match line.iter().position(|b| *b == ASCII_AT_SIGN) {
+                    Some(at) => (&line[..at], Some(&line[at + 1..])),
+                    None => (line, None),
+                };
+                let ret = self.parse_sizeline(count, stop)?;
+                if let Some(deadline) = deadline {
+                    self.deadline = Some(self.parse_sizeline(deadline, stop)? as u64);
+                }
                 Ok(ret)
             } else {
                 Err(ParseError::UnexpectedByte)
@@ -274,7 +356,7 @@ impl<'a> Parser<'a> {
     fn __get_next_element(&mut self) -> ParseResult<&[u8]> {
         let string_sizeline = self.read_line();
         if let Some(line) = self.buffer.get(string_sizeline.0..string_sizeline.1) {
-            let string_size = Self::parse_into_usize(line)?;
+            let string_size = self.parse_sizeline(line, string_sizeline.1)?;
             let our_chunk = self.read_until(string_size)?;
             Ok(our_chunk)
         } else {
@@ -304,7 +386,7 @@ impl<'a> Parser<'a> {
             self.incr_cursor();
             Ok(our_ks_name)
         } else {
-            Err(ParseError::NotEnough)
+            Err(ParseError::UnexpectedByte)
         }
     }
     /// The cursor should have passed the `:` tsymbol
@@ -328,6 +410,7 @@ impl<'a> Parser<'a> {
             self.incr_cursor();
             let ret = match *tsymbol {
                 ASCII_PLUS_SIGN => Element::String(self.parse_next_string()?),
+                ASCII_QUESTION_MARK => Element::Binary(self.parse_next_blob()?),
                 ASCII_COLON => Element::UnsignedInt(self.parse_next_u64()?),
                 ASCII_AMPERSAND => Element::Array(self.parse_next_array()?),
                 ASCII_TILDE_SIGN => Element::AnyArray(self.parse_next_any_array()?),
@@ -358,8 +441,8 @@ impl<'a> Parser<'a> {
     fn parse_next_any_array(&mut self) -> ParseResult<Vec<Bytes>> {
         let (start, stop) = self.read_line();
         if let Some(our_size_chunk) = self.buffer.get(start..stop) {
-            let array_size = Self::parse_into_usize(our_size_chunk)?;
-            let mut array = Vec::with_capacity(array_size);
+            let array_size = self.parse_sizeline(our_size_chunk, stop)?;
+            let mut array = Vec::with_capacity(array_size.min(self.remaining()));
             for _ in 0..array_size {
                 array.push(self.parse_next_blob()?);
             }
@@ -372,14 +455,15 @@ impl<'a> Parser<'a> {
     fn parse_next_flat_array(&mut self) -> ParseResult<Vec<Bytes>> {
         let (start, stop) = self.read_line();
         if let Some(our_size_chunk) = self.buffer.get(start..stop) {
-            let array_size = Self::parse_into_usize(our_size_chunk)?;
-            let mut array = Vec::with_capacity(array_size);
+            let array_size = self.parse_sizeline(our_size_chunk, stop)?;
+            let mut array = Vec::with_capacity(array_size.min(self.remaining()));
             for _ in 0..array_size {
                 if let Some(tsymbol) = self.buffer.get(self.cursor) {
                     // good, there is a tsymbol; move the cursor ahead
                     self.incr_cursor();
                     let ret = match *tsymbol {
-                        b'+' => self.parse_next_string()?,
+                        ASCII_PLUS_SIGN => self.parse_next_string()?,
+                        ASCII_QUESTION_MARK => self.parse_next_blob()?,
                         _ => return Err(ParseError::UnknownDatatype),
                     };
                     array.push(ret);
@@ -396,8 +480,8 @@ impl<'a> Parser<'a> {
     fn parse_next_array(&mut self) -> ParseResult<Vec<Element>> {
         let (start, stop) = self.read_line();
         if let Some(our_size_chunk) = self.buffer.get(start..stop) {
-            let array_size = Self::parse_into_usize(our_size_chunk)?;
-            let mut array = Vec::with_capacity(array_size);
+            let array_size = self.parse_sizeline(our_size_chunk, stop)?;
+            let mut array = Vec::with_capacity(array_size.min(self.remaining()));
             for _ in 0..array_size {
                 array.push(self.parse_next_element()?);
             }
@@ -410,7 +494,23 @@ impl<'a> Parser<'a> {
     /// can be safely discarded from the buffer. It will otherwise return errors if they are found.
     ///
     /// This object will drop `Self`
-    pub fn parse(mut self) -> Result<(Query, usize), ParseError> {
+    pub fn parse(self) -> Result<(Query, usize), ParseError> {
+        self.parse_with_deadline()
+            .map(|(query, forward_by, _)| (query, forward_by))
+    }
+    /// Like [`Self::parse`], but also returns the deadline (in milliseconds) that was given
+    /// in the metaframe, if any
+    pub fn parse_with_deadline(self) -> Result<(Query, usize, Option<u64>), ParseError> {
+        let strict = self.strict;
+        let len = self.buffer.len();
+        let (query, forward_by, deadline) = self.parse_query()?;
+        if strict && forward_by != len {
+            Err(ParseError::TrailingBytes)
+        } else {
+            Ok((query, forward_by, deadline))
+        }
+    }
+    fn parse_query(mut self) -> Result<(Query, usize, Option<u64>), ParseError> {
         let number_of_queries = self.parse_metaframe_get_datagroup_count()?;
         if number_of_queries == 0 {
             // how on earth do you expect us to execute 0 queries? waste of bandwidth
@@ -423,13 +523,16 @@ impl<'a> Parser<'a> {
             // or it checks if the next time is a \r char; if it is, then it is the beginning
             // of the next query
             // clippy thinks we're doing something complex when we aren't, at all!
+            // (in strict mode, parse checks that nothing at all follows the query)
             #[allow(clippy::blocks_in_if_conditions)]
-            if unsafe {
-                // UNSAFE(@ohsayan): This will never be the case because we'll always get a result and no error value
-                // as we've passed true which will yield Ok(true) even if there is no byte ahead
-                self.will_cursor_give_char(b'*', true).unsafe_unwrap()
-            } {
-                Ok((Query::SimpleQuery(single_group), self.cursor))
+            if self.strict
+                || unsafe {
+                    // UNSAFE(@ohsayan): This will never be the case because we'll always get a result and no error value
+                    // as we've passed true which will yield Ok(true) even if there is no byte ahead
+                    self.will_cursor_give_char(b'*', true).unsafe_unwrap()
+                }
+            {
+                Ok((Query::SimpleQuery(single_group), self.cursor, self.deadline))
             } else {
                 // the next item isn't the beginning of a query but something else?
                 // that doesn't look right!
@@ -438,12 +541,12 @@ impl<'a> Parser<'a> {
         } else {
             // This is a pipelined query
             // We'll first make space for all the actiongroups
-            let mut queries = Vec::with_capacity(number_of_queries);
+            let mut queries = Vec::with_capacity(number_of_queries.min(self.remaining()));
             for _ in 0..number_of_queries {
                 queries.push(self.parse_next_element()?);
             }
-            if self.will_cursor_give_char(b'*', true)? {
-                Ok((Query::PipelinedQuery(queries), self.cursor))
+            if self.strict || self.will_cursor_give_char(b'*', true)? {
+                Ok((Query::PipelinedQuery(queries), self.cursor, self.deadline))
             } else {
                 Err(ParseError::UnexpectedByte)
             }
@@ -451,6 +554,18 @@ impl<'a> Parser<'a> {
     }
 }
 
+#[cfg(feature = "fuzz")]
+/// Parse a query packet, returning the [`Query`] and the number of bytes that it took up.
+/// This is the entry point for fuzz targets: with `strict` set, `buffer` should hold exactly
+/// one query packet (see the module docs)
+pub fn parse_packet(buffer: &[u8], strict: bool) -> ParseResult<(Query, usize)> {
+    if strict {
+        Parser::new_strict(buffer).parse()
+    } else {
+        Parser::new(buffer).parse()
+    }
+}
+
 #[test]
 fn test_metaframe_parse() {
     let metaframe = "*2\n".as_bytes();
@@ -459,6 +574,39 @@ fn test_metaframe_parse() {
     assert_eq!(parser.cursor, metaframe.len());
 }
 
+#[test]
+fn test_metaframe_deadline() {
+    let metaframe = "*2@150\n".as_bytes();
+    let mut parser = Parser::new(metaframe);
+    assert_eq!(2, parser.parse_metaframe_get_datagroup_count().unwrap());
+    assert_eq!(parser.deadline, Some(150));
+    assert_eq!(parser.cursor, metaframe.len());
+    let bytes = "*1@50\n+4\nHEYA\n".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse_with_deadline().unwrap(),
+        (
+            Query::SimpleQuery(Element::String(Bytes::from("HEYA"))),
+            bytes.len(),
+            Some(50)
+        )
+    );
+    // the deadline isn't part of the query itself
+    assert_eq!(Parser::new(bytes).parse().unwrap().1, bytes.len());
+    let bytes = "*1\n+4\nHEYA\n".as_bytes();
+    assert_eq!(Parser::new(bytes).parse_with_deadline().unwrap().2, None);
+    // the deadline has to be a number
+    let bytes = "*1@soon\n+4\nHEYA\n".as_bytes();
+    assert_eq!(
+        Parser::new(bytes).parse().unwrap_err(),
+        ParseError::DatatypeParseFailure
+    );
+    let bytes = "*1@\n+4\nHEYA\n".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::EmbeddedNewline
+    );
+}
+
 #[test]
 fn test_cursor_next_char() {
     let bytes = &[b'\n'];
@@ -843,3 +991,131 @@ fn test_parse_any_array() {
         ]))
     )
 }
+
+#[test]
+fn test_parse_binary() {
+    let bytes = b"*1\n?6\n\xF0\x90\n\0\r\n\n";
+    let (query, forward_by) = Parser::new(bytes).parse().unwrap();
+    assert_eq!(forward_by, bytes.len());
+    assert_eq!(
+        query,
+        Query::SimpleQuery(Element::Binary(Bytes::from_static(b"\xF0\x90\n\0\r\n")))
+    );
+}
+
+#[test]
+fn test_parse_flat_array_with_binary() {
+    let bytes = b"_2\n+3\nSET\n?3\n\xFF\n\xFE\n";
+    let res = Parser::new(bytes).parse_next_element().unwrap();
+    assert_eq!(
+        res,
+        Element::FlatArray(vec![
+            Bytes::from_static(b"SET"),
+            Bytes::from_static(b"\xFF\n\xFE")
+        ])
+    );
+}
+
+#[test]
+fn test_parse_any_array_binary_safe() {
+    // every possible byte, including LFs and invalid unicode, in a single element
+    let all_bytes: Vec<u8> = (0..=u8::MAX).collect();
+    let mut anyarray = b"*1\n~2\n3\nSET\n256\n".to_vec();
+    anyarray.extend_from_slice(&all_bytes);
+    anyarray.push(b'\n');
+    let (query, forward_by) = Parser::new(&anyarray).parse().unwrap();
+    assert_eq!(forward_by, anyarray.len());
+    assert_eq!(
+        query,
+        Query::SimpleQuery(Element::AnyArray(vec![
+            Bytes::from_static(b"SET"),
+            Bytes::from(all_bytes)
+        ]))
+    );
+}
+
+#[test]
+fn test_strict_trailing_bytes() {
+    let bytes = "*1\n+4\nHEYA\n*1\n".as_bytes();
+    // the lenient parser leaves the start of the next query in the buffer
+    let (_, forward_by) = Parser::new(bytes).parse().unwrap();
+    assert_eq!(forward_by, bytes.len() - "*1\n".len());
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::TrailingBytes
+    );
+    let bytes = "*1\n+4\nHEYA\n".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap(),
+        (
+            Query::SimpleQuery(Element::String(Bytes::from("HEYA"))),
+            bytes.len()
+        )
+    );
+}
+
+#[test]
+fn test_strict_sizelines() {
+    // longer than any size can be
+    let bytes = "*1\n+000000000000000000004\nHEYA\n".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::OversizeSizeline
+    );
+    // leading zeros
+    let bytes = "*1\n+04\nHEYA\n".as_bytes();
+    assert_eq!(Parser::new(bytes).parse().unwrap().1, bytes.len());
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::OversizeSizeline
+    );
+    // a bare newline instead of a size
+    let bytes = "*1\n~\n".as_bytes();
+    assert_eq!(
+        Parser::new(bytes).parse().unwrap_err(),
+        ParseError::NotEnough
+    );
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::EmbeddedNewline
+    );
+    // but an incomplete sizeline still needs more data
+    let bytes = "*1\n~".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::NotEnough
+    );
+}
+
+#[test]
+fn test_malformed_packets_dont_panic() {
+    // sizes that would overflow the cursor or ask for a huge allocation
+    for packet in [
+        "*1\n+18446744073709551615\nHEYA\n",
+        "*1\n~18446744073709551615\n",
+        "*1\n&18446744073709551615\n",
+        "*1\n_18446744073709551615\n",
+        "*18446744073709551615\n+4\nHEYA\n",
+        "\n\n\n",
+    ]
+    .iter()
+    {
+        assert!(Parser::new(packet.as_bytes()).parse().is_err());
+        assert!(Parser::new_strict(packet.as_bytes()).parse().is_err());
+    }
+}
+
+#[test]
+fn test_strict_incomplete_element_sizeline() {
+    // the sizeline of the second element hasn't come in yet
+    let bytes = "*1\n~2\n3\nSET\n".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::NotEnough
+    );
+    let bytes = "*1\n+4\nHEYA\nX".as_bytes();
+    assert_eq!(
+        Parser::new_strict(bytes).parse().unwrap_err(),
+        ParseError::TrailingBytes
+    );
+}
@@ -61,8 +61,44 @@ pub mod groups {
     pub const SNAPSHOT_DISABLED: &[u8] = "!21\nerr-snapshot-disabled\n".as_bytes();
     /// Snapshot has illegal name (other error)
     pub const SNAPSHOT_ILLEGAL_NAME: &[u8] = "!25\nerr-invalid-snapshot-name\n".as_bytes();
+    /// Snapshot doesn't exist
+    pub const SNAPSHOT_NOT_FOUND: &[u8] = "!22\nerr-snapshot-not-found\n".as_bytes();
+    /// The snapshot has no manifest to verify it against
+    pub const NO_MANIFEST: &[u8] = "!15\nerr-no-manifest\n".as_bytes();
+    /// There is no running job with the given ID
+    pub const UNKNOWN_JOB: &[u8] = "!15\nerr-unknown-job\n".as_bytes();
+    /// The job can't be cancelled
+    pub const JOB_NOT_CANCELLABLE: &[u8] = "!23\nerr-job-not-cancellable\n".as_bytes();
+    /// The on-disk format version isn't supported
+    pub const UNKNOWN_FORMAT: &[u8] = "!18\nerr-unknown-format\n".as_bytes();
+    /// There is no file with the given name to import
+    pub const IMPORT_NOT_FOUND: &[u8] = "!20\nerr-import-not-found\n".as_bytes();
+    /// The file to import is corrupted or doesn't match the table's encoding
+    pub const BAD_IMPORT: &[u8] = "!14\nerr-bad-import\n".as_bytes();
     /// Access after termination signal (other error)
     pub const ERR_ACCESS_AFTER_TERMSIG: &[u8] = "!24\nerr-access-after-termsig\n".as_bytes();
+    /// A non-administrative action was run on an admin-only listener
+    pub const ADMIN_ONLY: &[u8] = "!14\nerr-admin-only\n".as_bytes();
+    /// A mutation was attempted while the server is in read-only mode
+    pub const READ_ONLY_MODE: &[u8] = "!18\nerr-read-only-mode\n".as_bytes();
+    /// A non-administrative action was run while the server is in maintenance mode
+    pub const MAINTENANCE_MODE: &[u8] = "!20\nerr-maintenance-mode\n".as_bytes();
+    /// The connection has to `AUTH` before it can run this action
+    pub const AUTH_REQUIRED: &[u8] = "!17\nerr-auth-required\n".as_bytes();
+    /// The username or token passed to `AUTH` is wrong
+    pub const BAD_CREDENTIALS: &[u8] = "!19\nerr-bad-credentials\n".as_bytes();
+    /// The authenticated user isn't allowed to run this action
+    pub const PERMISSION_DENIED: &[u8] = "!21\nerr-permission-denied\n".as_bytes();
+    /// The user already has as many connections as it is allowed to have
+    pub const TOO_MANY_CONNECTIONS: &[u8] = "!24\nerr-too-many-connections\n".as_bytes();
+    /// There is no user with the given name
+    pub const UNKNOWN_USER: &[u8] = "!16\nerr-unknown-user\n".as_bytes();
+    /// The client doesn't speak any protocol version that the server speaks
+    pub const UNSUPPORTED_VERSION: &[u8] = "!23\nerr-unsupported-version\n".as_bytes();
+    /// `EXPLAIN` was asked to explain an action that it can't explain
+    pub const CANNOT_EXPLAIN: &[u8] = "!18\nerr-cannot-explain\n".as_bytes();
+    /// The table's origin couldn't be reached or returned an error
+    pub const ORIGIN_FAILED: &[u8] = "!17\nerr-origin-failed\n".as_bytes();
 
     // keyspace related resps
     /// The default container was not set
@@ -73,6 +109,12 @@ pub mod groups {
     pub const STILL_IN_USE: &[u8] = "!12\nstill-in-use\n".as_bytes();
     /// This is a protected object and hence cannot be accessed
     pub const PROTECTED_OBJECT: &[u8] = "!20\nerr-protected-object\n".as_bytes();
+    /// The key was written since the version passed to `SETV` was handed out
+    pub const VERSION_MISMATCH: &[u8] = "!20\nerr-version-mismatch\n".as_bytes();
+    /// The tables in a query aren't all in the same keyspace
+    pub const CROSS_KEYSPACE: &[u8] = "!18\nerr-cross-keyspace\n".as_bytes();
+    /// The current table has run out of writes for this second
+    pub const THROTTLED: &[u8] = "!13\nerr-throttled\n".as_bytes();
     /// The action was applied against the wrong model
     pub const WRONG_MODEL: &[u8] = "!11\nwrong-model\n".as_bytes();
     /// The container already exists
@@ -97,8 +139,53 @@ pub mod groups {
     pub const UNKNOWN_INSPECT_QUERY: &[u8] = "!21\nunknown-inspect-query\n".as_bytes();
     /// An unknown table property was passed
     pub const UNKNOWN_PROPERTY: &[u8] = "!16\nunknown-property\n".as_bytes();
+    /// An unknown sys query
+    pub const UNKNOWN_SYS_QUERY: &[u8] = "!17\nunknown-sys-query\n".as_bytes();
+    /// An unknown server mode was passed
+    pub const UNKNOWN_MODE: &[u8] = "!12\nunknown-mode\n".as_bytes();
+    /// The value is not a valid JSON document
+    pub const BAD_JSON: &[u8] = "!12\nerr-bad-json\n".as_bytes();
+    /// The JSON path is malformed
+    pub const BAD_JSON_PATH: &[u8] = "!17\nerr-bad-json-path\n".as_bytes();
+    /// The bit offset or bit value is invalid
+    pub const BAD_BIT: &[u8] = "!11\nerr-bad-bit\n".as_bytes();
+    /// An unknown bitwise operation was passed
+    pub const UNKNOWN_BITOP: &[u8] = "!13\nunknown-bitop\n".as_bytes();
+    /// The value is not a valid HyperLogLog
+    pub const INVALID_HLL: &[u8] = "!15\nerr-invalid-hll\n".as_bytes();
+    /// The current table has no merge operator
+    pub const NO_MERGE_OPERATOR: &[u8] = "!21\nerr-no-merge-operator\n".as_bytes();
+    /// The value or an operand can't be merged with the table's merge operator
+    pub const BAD_OPERAND: &[u8] = "!15\nerr-bad-operand\n".as_bytes();
     /// The keyspace is not empty and hence cannot be removed
     pub const KEYSPACE_NOT_EMPTY: &[u8] = "!18\nkeyspace-not-empty\n".as_bytes();
+    /// The connection is pinned to a frozen view of the data, which the action can't be run on
+    pub const PINNED: &[u8] = "!10\nerr-pinned\n".as_bytes();
+    /// `UNPIN` was run on a connection that isn't pinned
+    pub const NOT_PINNED: &[u8] = "!14\nerr-not-pinned\n".as_bytes();
+    /// `TRACKING ON` was run on a connection that hasn't agreed on the `invalidations`
+    /// capability
+    pub const TRACKING_NOT_NEGOTIATED: &[u8] = "!27\nerr-tracking-not-negotiated\n".as_bytes();
+    /// A write to a table with a schema has values that don't match it
+    pub const SCHEMA_VIOLATION: &[u8] = "!20\nerr-schema-violation\n".as_bytes();
+    /// `VERBOSE ON` was run on a connection that hasn't agreed on the `metadata` capability
+    pub const VERBOSE_NOT_NEGOTIATED: &[u8] = "!26\nerr-verbose-not-negotiated\n".as_bytes();
+    /// An idempotency token was sent with a different query than the one it was first used with
+    pub const TOKEN_REUSED: &[u8] = "!16\nerr-token-reused\n".as_bytes();
+    /// The write that an idempotency token was first used with is still running
+    pub const TOKEN_IN_PROGRESS: &[u8] = "!21\nerr-token-in-progress\n".as_bytes();
+    /// The session doesn't exist or has expired
+    pub const UNKNOWN_SESSION: &[u8] = "!19\nerr-unknown-session\n".as_bytes();
+    /// An unknown `SESSION` query
+    pub const UNKNOWN_SESSION_QUERY: &[u8] = "!21\nunknown-session-query\n".as_bytes();
+    /// The flush confirmation token is invalid or was already used
+    pub const BAD_FLUSH_TOKEN: &[u8] = "!19\nerr-bad-flush-token\n".as_bytes();
+    /// Writes to the keyspace are stalled because the disk is (almost) full
+    pub const DISK_FULL: &[u8] = "!13\nerr-disk-full\n".as_bytes();
+    /// Writes to the keyspace are stalled because it failed to flush
+    pub const FLUSH_FAILED: &[u8] = "!16\nerr-flush-failed\n".as_bytes();
+    /// Writes are rejected because the server is running out of memory
+    pub const MEMORY_PRESSURE: &[u8] = "!19\nerr-memory-pressure\n".as_bytes();
 }
 
 pub mod full_responses {
@@ -141,6 +228,16 @@ pub mod full_responses {
     pub const R_SNAPSHOT_ILLEGAL_NAME: &[u8] = "*1\n!25\nerr-invalid-snapshot-name\n".as_bytes();
     /// Access after termination signal (other error)
     pub const R_ERR_ACCESS_AFTER_TERMSIG: &[u8] = "*1\n!24\nerr-access-after-termsig\n".as_bytes();
+    /// The query is larger than the per-connection buffer limit (other error)
+    pub const R_QUERY_TOO_LARGE: &[u8] = "*1\n!19\nerr-query-too-large\n".as_bytes();
+    /// The authenticated user has run out of operations for this second (other error)
+    pub const R_RATE_LIMITED: &[u8] = "*1\n!16\nerr-rate-limited\n".as_bytes();
+    /// The query wasn't run because it was dequeued after its deadline (other error)
+    pub const R_DEADLINE_EXCEEDED: &[u8] = "*1\n!21\nerr-deadline-exceeded\n".as_bytes();
+    /// The server is still restoring its data and isn't accepting queries yet (other error)
+    pub const R_RECOVERING: &[u8] = "*1\n!14\nerr-recovering\n".as_bytes();
+    /// The server is overloaded and shed the query (other error)
+    pub const R_BUSY: &[u8] = "*1\n!8\nerr-busy\n".as_bytes();
     /// Pipelines are currently not supported
     // TODO(@ohsayan): Remove this once we implement pipelines
     pub const R_PIPELINE_UNSUPPORTED: &[u8] = "*1\n!26\npipeline-not-supported-yet".as_bytes();
@@ -0,0 +1,113 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Argument counts
+//!
+//! Actions declare the number of arguments that they take (their arity) where they're
+//! dispatched (see [`execute_simple`](super::execute_simple)), so that queries with the
+//! wrong number of arguments are rejected the same way for every action, before the action
+//! runs. Connections that agreed on the `verbose-errors` capability (see
+//! [`hello`](crate::protocol::hello)) are told which action it was with
+//! `err-wrong-arity <ACTION>`, while all others get the action error that they always got
+
+use crate::corestore::Corestore;
+use crate::protocol::hello::Capabilities;
+use crate::protocol::responses;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The number of arguments that an action takes
+pub enum Arity {
+    /// exactly this many arguments
+    Exact(usize),
+    /// this many arguments or more
+    AtLeast(usize),
+    /// this many arguments or fewer
+    AtMost(usize),
+    /// between these many arguments (both inclusive)
+    Between(usize, usize),
+    /// a non-zero even number of arguments (key/value pairs)
+    Pairs,
+}
+
+impl Arity {
+    /// Returns true if an action with this arity can be run with `count` arguments
+    pub const fn accepts(self, count: usize) -> bool {
+        match self {
+            Self::Exact(n) => count == n,
+            Self::AtLeast(n) => count >= n,
+            Self::AtMost(n) => count <= n,
+            Self::Between(min, max) => count >= min && count <= max,
+            Self::Pairs => count != 0 && count % 2 == 0,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    /// Formats the arity the way that `SYS COMMANDS` returns it, like `2`, `>=1`, `<=3`,
+    /// `1..2` or `pairs`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(n) => write!(f, "{}", n),
+            Self::AtLeast(n) => write!(f, ">={}", n),
+            Self::AtMost(n) => write!(f, "<={}", n),
+            Self::Between(min, max) => write!(f, "{}..{}", min, max),
+            Self::Pairs => f.write_str("pairs"),
+        }
+    }
+}
+
+/// Returns the error to be written for a query on `action` (uppercased) that has the wrong
+/// number of arguments
+pub fn error(action: &[u8], handle: &Corestore) -> Vec<u8> {
+    let verbose = handle.handshake().map_or(false, |hs| {
+        hs.capabilities.contains(Capabilities::VERBOSE_ERRORS)
+    });
+    if !verbose {
+        return responses::groups::ACTION_ERR.to_vec();
+    }
+    let mut message = b"err-wrong-arity ".to_vec();
+    message.extend_from_slice(action);
+    let mut ret = format!("!{}\n", message.len()).into_bytes();
+    ret.extend(message);
+    ret.push(b'\n');
+    ret
+}
+
+#[test]
+fn test_arity_accepts() {
+    assert!(Arity::Exact(2).accepts(2));
+    assert!(!Arity::Exact(2).accepts(3));
+    assert!(Arity::AtLeast(1).accepts(5));
+    assert!(!Arity::AtLeast(1).accepts(0));
+    assert!(Arity::AtMost(1).accepts(0));
+    assert!(!Arity::AtMost(1).accepts(2));
+    assert!(Arity::Between(1, 2).accepts(2));
+    assert!(!Arity::Between(1, 2).accepts(3));
+    assert!(Arity::Pairs.accepts(4));
+    assert!(!Arity::Pairs.accepts(3));
+    assert!(!Arity::Pairs.accepts(0));
+}
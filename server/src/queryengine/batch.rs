@@ -0,0 +1,67 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Batches
+//!
+//! A batch carries many independent actions in a single query, for clients that can't
+//! easily send several queries before reading their responses:
+//! ```text
+//! BATCH <count> <action> <args ...> [<count> <action> <args ...> ...]
+//! ```
+//! Every action is prefixed with its number of elements (the name of the action included).
+//! The actions are run one after the other, exactly like separate queries would be (so a `USE`
+//! in a batch affects the actions after it), and their responses are returned as a single
+//! array, in the same order. An action that fails doesn't stop the ones after it
+
+use crate::dbnet::connection::prelude::*;
+use crate::protocol::Element;
+use bytes::Bytes;
+
+const BATCH: &[u8] = "BATCH".as_bytes();
+
+action! {
+    /// Run a batch of actions. A batch that isn't framed properly is rejected before any of
+    /// its actions are run, as are batches that have a batch in them
+    fn batch(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let mut queries = Vec::new();
+        while let Some(count) = act.next() {
+            let count = match String::from_utf8_lossy(&count).parse::<usize>() {
+                Ok(count) if count != 0 && count <= act.len() => count,
+                _ => return conwrite!(con, responses::groups::ACTION_ERR),
+            };
+            let query: Vec<Bytes> = act.by_ref().take(count).collect();
+            if query[0].eq_ignore_ascii_case(BATCH) {
+                return conwrite!(con, responses::groups::ACTION_ERR);
+            }
+            queries.push(query);
+        }
+        con.write_array_length(queries.len()).await?;
+        for query in queries {
+            super::execute_boxed(handle, con, Element::AnyArray(query)).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # The action registry
+//!
+//! Every action that [`execute_simple`](super::execute_simple) dispatches has an entry in
+//! [`COMMANDS`](super::COMMANDS), with its arity and the server modes that it can be run in. The registry is
+//! generated from the same list as the dispatcher, so that the two can't drift apart, and
+//! can be inspected with `SYS COMMANDS`
+//!
+//! Some actions can also be run under another name (an alias, like `DELETE` for `DEL`).
+//! Aliases are resolved to the action's name before anything else is done with the query,
+//! so an alias is denied, authorized and reported exactly like the action itself
+
+use super::arity::Arity;
+use crate::registry::auth;
+
+/// Aliases and the actions that they stand for
+const ALIASES: [(&[u8], &[u8]); 2] = [(b"DELETE", b"DEL"), (b"LEN", b"KEYLEN")];
+
+#[derive(Debug)]
+/// An action that can be run on a connection
+pub struct Command {
+    /// the name of the action
+    pub name: &'static str,
+    /// the number of arguments that the action takes, if the dispatcher checks it
+    pub arity: Option<Arity>,
+    /// `read` if the action is rejected in maintenance mode, `write` if it's also
+    /// rejected in read-only mode
+    pub guard: Option<&'static str>,
+}
+
+impl Command {
+    /// Returns the names that this action can also be run under
+    pub fn aliases(&self) -> impl Iterator<Item = &'static str> + '_ {
+        ALIASES
+            .iter()
+            .filter(move |(_, name)| *name == self.name.as_bytes())
+            .map(|(alias, _)| unsafe {
+                // SAFETY: the aliases are ASCII
+                core::str::from_utf8_unchecked(alias)
+            })
+    }
+    /// Returns the users that can run this action when authentication is enabled: `any`
+    /// for actions that can be run before `AUTH`, `unrestricted` for the server-wide
+    /// actions and `user` for everything else
+    pub fn permission(&self) -> &'static str {
+        let name = self.name.as_bytes();
+        if auth::OPEN_ACTIONS.contains(&name) {
+            "any"
+        } else if auth::SERVER_ACTIONS.contains(&name) {
+            "unrestricted"
+        } else {
+            "user"
+        }
+    }
+}
+
+/// Returns the action that `action` (uppercased) is an alias for, if it's an alias
+pub fn resolve_alias(action: &[u8]) -> Option<&'static [u8]> {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == action)
+        .map(|(_, name)| *name)
+}
+
+#[test]
+fn test_resolve_alias() {
+    assert_eq!(resolve_alias(b"DELETE"), Some(&b"DEL"[..]));
+    assert_eq!(resolve_alias(b"LEN"), Some(&b"KEYLEN"[..]));
+    assert_eq!(resolve_alias(b"DEL"), None);
+}
@@ -26,24 +26,31 @@
 
 use super::parser;
 use super::parser::VALID_CONTAINER_NAME;
+use crate::admin::history;
+use crate::admin::merge::{self, MergeOperator};
+use crate::admin::persistence::{self, Persistence};
 use crate::corestore::memstore::DdlError;
 use crate::corestore::memstore::ObjectID;
 use crate::dbnet::connection::prelude::*;
+use crate::kvengine::bloom;
 use crate::kvengine::encoding;
+use crate::kvengine::respcache;
 use crate::registry;
+use crate::services::schema;
 use core::str;
 
 pub const TABLE: &[u8] = "TABLE".as_bytes();
 pub const KEYSPACE: &[u8] = "KEYSPACE".as_bytes();
 const VOLATILE: &[u8] = "volatile".as_bytes();
+const BLOOM_PREFIX: &[u8] = "bloom(".as_bytes();
+const CACHE_PREFIX: &[u8] = "cache(".as_bytes();
 const FORCE_REMOVE: &[u8] = "force".as_bytes();
 
 action!(
     /// Handle `create table <tableid> <model>(args)` and `create keyspace <ksid>`
     /// like queries
     fn create(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        // minlength is 2 (create has already been checked)
-        err_if_len_is!(act, con, lt 2);
+        // minlength is 2 (checked by the dispatcher)
         let mut create_what = unsafe { act.next().unsafe_unwrap() }.to_vec();
         create_what.make_ascii_uppercase();
         match create_what.as_ref() {
@@ -62,8 +69,7 @@ action!(
     /// Handle `drop table <tableid>` and `drop keyspace <ksid>`
     /// like queries
     fn ddl_drop(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        // minlength is 2 (create has already been checked)
-        err_if_len_is!(act, con, lt 2);
+        // minlength is 2 (checked by the dispatcher)
         let mut create_what = unsafe { act.next().unsafe_unwrap() }.to_vec();
         create_what.make_ascii_uppercase();
         match create_what.as_ref() {
@@ -79,26 +85,88 @@ action!(
 );
 
 action!(
-    /// We should have `<tableid> <model>(args)`
+    /// Handle `undrop table <tableid>` like queries
+    fn ddl_undrop(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let mut undrop_what = unsafe { act.next().unsafe_unwrap() }.to_vec();
+        undrop_what.make_ascii_uppercase();
+        match undrop_what.as_ref() {
+            TABLE => undrop_table(handle, con, act).await?,
+            _ => {
+                con.write_response(responses::groups::UNKNOWN_DDL_QUERY)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Handle `clone table <src> <dst>` and `clone keyspace <src> <dst>` like queries
+    fn ddl_clone(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let mut clone_what = unsafe { act.next().unsafe_unwrap() }.to_vec();
+        clone_what.make_ascii_uppercase();
+        match clone_what.as_ref() {
+            TABLE => clone_table(handle, con, act).await?,
+            KEYSPACE => clone_keyspace(handle, con, act).await?,
+            _ => {
+                con.write_response(responses::groups::UNKNOWN_DDL_QUERY)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// We should have `<tableid> <model>(args)`, followed by the properties of the table
+    /// (`volatile`, `bloom(<fprate>)`, `cache(<maxkeys>)` and `merge(<operator>)`) in any
+    /// order
     fn create_table(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(con, act.len() > 3 || act.len() < 2);
+        err_if_len_is!(con, act.len() > 6 || act.len() < 2);
+        let args = act.as_slice().to_vec();
         let (table_entity, model_code) = match parser::parse_table_args(&mut act) {
             Ok(v) => v,
             Err(e) => return con.write_response(e).await,
         };
-        let is_volatile = match act.next() {
-            Some(maybe_volatile) => {
-                if maybe_volatile.eq(VOLATILE) {
-                    true
-                } else {
-                    return conwrite!(con, responses::groups::UNKNOWN_PROPERTY);
-                }
+        let keyspace = history::entity_keyspace(handle, &args[0]);
+        // every table in a keyspace that isn't persisted is volatile
+        let cache = matches!(
+            &keyspace,
+            Some(ks) if persistence::get(handle.get_store(), ks.as_bytes()) == Persistence::None
+        );
+        let mut is_volatile = false;
+        let mut bloom = None;
+        let mut respcache = None;
+        let mut operator = None;
+        for property in act {
+            if property.eq(VOLATILE) && !is_volatile {
+                is_volatile = true;
+            } else if let (Some(fprate), None) = (parse_bloom(&property), bloom) {
+                bloom = Some(fprate);
+            } else if let (Some(maxkeys), None) = (parse_cache(&property), respcache) {
+                respcache = Some(maxkeys);
+            } else if let (Some(op), None) = (MergeOperator::from_property(&property), operator) {
+                operator = Some(op);
+            } else {
+                return conwrite!(con, responses::groups::UNKNOWN_PROPERTY);
             }
-            None => false,
-        };
+        }
         if registry::state_okay() {
-            match handle.create_table(table_entity, model_code, is_volatile) {
-                Ok(_) => con.write_response(responses::groups::OKAY).await?,
+            match handle.create_table(
+                table_entity,
+                model_code,
+                is_volatile || cache,
+                bloom,
+                respcache,
+            ) {
+                Ok(_) => {
+                    // this also forgets the operator of a dropped table with the same name
+                    if let Some(table) = table_name(handle, &args[0]) {
+                        merge::set(handle.get_store(), &table, operator);
+                    }
+                    history::record(handle, keyspace, "CREATE TABLE", &args);
+                    con.write_response(responses::groups::OKAY).await?
+                }
                 Err(DdlError::AlreadyExists) => {
                     con.write_response(responses::groups::ALREADY_EXISTS)
                         .await?;
@@ -110,6 +178,10 @@ action!(
                 Err(DdlError::DefaultNotFound) => {
                     con.write_response(responses::groups::DEFAULT_UNSET).await?
                 }
+                Err(DdlError::ProtectedObject) => {
+                    con.write_response(responses::groups::PROTECTED_OBJECT)
+                        .await?
+                }
                 Err(_) => unsafe {
                     // we know that Corestore::create_table won't return anything else
                     impossible!()
@@ -122,10 +194,49 @@ action!(
     }
 );
 
+/// Returns the name of the given table entity as `<keyspace>:<table>`
+fn table_name(handle: &Corestore, entity: &[u8]) -> Option<String> {
+    let keyspace = history::entity_keyspace(handle, entity)?;
+    let table = match entity.iter().position(|b| *b == b':') {
+        Some(pos) => &entity[pos + 1..],
+        None => entity,
+    };
+    Some(format!("{}:{}", keyspace, String::from_utf8_lossy(table)))
+}
+
+/// Parse a `bloom(<fprate>)` property, returning the false positive rate
+fn parse_bloom(property: &[u8]) -> Option<f64> {
+    let fprate = property.strip_prefix(BLOOM_PREFIX)?.strip_suffix(b")")?;
+    str::from_utf8(fprate)
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|fprate| bloom::is_valid_fprate(*fprate))
+}
+
+/// Parse a `cache(<maxkeys>)` property, returning the most responses that the cache holds
+fn parse_cache(property: &[u8]) -> Option<usize> {
+    let maxkeys = property.strip_prefix(CACHE_PREFIX)?.strip_suffix(b")")?;
+    str::from_utf8(maxkeys)
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|maxkeys| respcache::is_valid_maxkeys(*maxkeys))
+}
+
 action!(
-    /// We should have `<ksid>`
+    /// We should have `<ksid>`, optionally followed by `persistence(<mode>)` (see
+    /// [`persistence`])
     fn create_keyspace(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 1);
+        err_if_len_is!(con, act.len() > 2 || act.len() < 1);
+        let args = act.as_slice().to_vec();
+        let mode = match args.get(1) {
+            Some(property) => match Persistence::from_property(property) {
+                Some(mode) => mode,
+                None => return conwrite!(con, responses::groups::UNKNOWN_PROPERTY),
+            },
+            None => Persistence::Snapshot,
+        };
         match act.next() {
             Some(ksid) => {
                 if !encoding::is_utf8(&ksid) {
@@ -140,10 +251,19 @@ action!(
                         .write_response(responses::groups::CONTAINER_NAME_TOO_LONG)
                         .await;
                 }
+                if !handle.can_access(&ksid) {
+                    // users can only create the keyspaces that they're allowed to use
+                    return conwrite!(con, responses::groups::PERMISSION_DENIED);
+                }
                 let ksid = unsafe { ObjectID::from_slice(ksid_str) };
                 if registry::state_okay() {
                     match handle.create_keyspace(ksid) {
-                        Ok(()) => return con.write_response(responses::groups::OKAY).await,
+                        Ok(()) => {
+                            persistence::set(handle.get_store(), ksid_str.as_bytes(), mode);
+                            let keyspace = Some(ksid_str.to_owned());
+                            history::record(handle, keyspace, "CREATE KEYSPACE", &args);
+                            return con.write_response(responses::groups::OKAY).await;
+                        }
                         Err(DdlError::AlreadyExists) => {
                             return con.write_response(responses::groups::ALREADY_EXISTS).await
                         }
@@ -161,19 +281,142 @@ action!(
     }
 );
 
+action! {
+    /// Clone a table (`<src> <dst>`)
+    fn clone_table(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let args = act.as_slice().to_vec();
+        let (src, dst) = unsafe {
+            // SAFETY: The dispatcher has checked the length
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        let (src, dst) = match (parser::get_query_entity(&src), parser::get_query_entity(&dst)) {
+            (Ok(src), Ok(dst)) => (src, unsafe { dst.into_owned() }),
+            (Err(e), _) | (_, Err(e)) => return con.write_response(e).await,
+        };
+        if table_name(handle, &args[1]).map_or(false, |dst| schema::has_schema(&dst)) {
+            // the copied values aren't checked against the schema
+            return conwrite!(con, schema::refuse(handle, b"CLONE"));
+        }
+        if let Ok(table) = handle.get_table(src) {
+            table.load().await;
+        }
+        // a copy in a keyspace that isn't persisted is volatile
+        let volatile = matches!(
+            history::entity_keyspace(handle, &args[1]),
+            Some(ks) if persistence::get(handle.get_store(), ks.as_bytes()) == Persistence::None
+        );
+        if registry::state_okay() {
+            let ret = match handle.clone_table(src, dst, volatile) {
+                Ok(()) => {
+                    // the copy is merged like the source
+                    if let (Some(src), Some(dst)) =
+                        (table_name(handle, &args[0]), table_name(handle, &args[1]))
+                    {
+                        let operator = merge::get(handle.get_store(), &src);
+                        merge::set(handle.get_store(), &dst, operator);
+                    }
+                    let keyspace = history::entity_keyspace(handle, &args[1]);
+                    history::record(handle, keyspace, "CLONE TABLE", &args);
+                    responses::groups::OKAY
+                }
+                Err(DdlError::DefaultNotFound) => responses::groups::DEFAULT_UNSET,
+                Err(DdlError::ObjectNotFound) => responses::groups::CONTAINER_NOT_FOUND,
+                Err(DdlError::AlreadyExists) => responses::groups::ALREADY_EXISTS,
+                Err(DdlError::ProtectedObject) => responses::groups::PROTECTED_OBJECT,
+                Err(_) => unsafe {
+                    // we know that Corestore::clone_table won't ever return anything else
+                    impossible!()
+                }
+            };
+            con.write_response(ret).await?;
+        } else {
+            conwrite!(con, responses::groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Clone a keyspace (`<src> <dst>`)
+    fn clone_keyspace(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let args = act.as_slice().to_vec();
+        let (src, dst) = unsafe {
+            // SAFETY: The dispatcher has checked the length
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        if src.len() > 64 || dst.len() > 64 {
+            return conwrite!(con, responses::groups::CONTAINER_NAME_TOO_LONG);
+        }
+        if !encoding::is_utf8(&dst) {
+            return conwrite!(con, responses::groups::ENCODING_ERROR);
+        }
+        if !VALID_CONTAINER_NAME.is_match(unsafe { str::from_utf8_unchecked(&dst) }) {
+            return conwrite!(con, responses::groups::BAD_EXPRESSION);
+        }
+        if !handle.can_access(&dst) {
+            // users can only create the keyspaces that they're allowed to use
+            return conwrite!(con, responses::groups::PERMISSION_DENIED);
+        }
+        if schema::keyspace_has_schema(unsafe { str::from_utf8_unchecked(&dst) }) {
+            // the copied values aren't checked against the schemas
+            return conwrite!(con, schema::refuse(handle, b"CLONE"));
+        }
+        if let Some(ks) = handle.get_keyspace(&src) {
+            let tables: Vec<_> = ks.tables.iter().map(|table| table.value().clone()).collect();
+            for table in tables {
+                table.load().await;
+            }
+        }
+        if registry::state_okay() {
+            let dst = unsafe { ObjectID::from_slice(dst) };
+            let ret = match handle.clone_keyspace(&src, dst) {
+                Ok(()) => {
+                    // the copy is persisted like the source
+                    let mode = persistence::get(handle.get_store(), &args[0]);
+                    persistence::set(handle.get_store(), &args[1], mode);
+                    merge::copy_keyspace(handle.get_store(), &args[0], &args[1]);
+                    let keyspace = Some(String::from_utf8_lossy(&args[1]).into_owned());
+                    history::record(handle, keyspace, "CLONE KEYSPACE", &args);
+                    responses::groups::OKAY
+                }
+                Err(DdlError::ProtectedObject) => responses::groups::PROTECTED_OBJECT,
+                Err(DdlError::ObjectNotFound) => responses::groups::CONTAINER_NOT_FOUND,
+                Err(DdlError::AlreadyExists) => responses::groups::ALREADY_EXISTS,
+                Err(_) => unsafe {
+                    // we know that Corestore::clone_keyspace won't ever return anything else
+                    impossible!()
+                }
+            };
+            con.write_response(ret).await?;
+        } else {
+            conwrite!(con, responses::groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+}
+
 action! {
     /// Drop a table (`<tblid>` only)
     fn drop_table(handle: &Corestore, con: &mut T, mut act: ActionIter) {
         err_if_len_is!(act, con, not 1);
+        let args = act.as_slice().to_vec();
         match act.next() {
             Some(eg) => {
                 let entity_group = match parser::get_query_entity(&eg) {
                     Ok(egroup) => egroup,
                     Err(e) => return con.write_response(e).await,
                 };
+                if let Ok(table) = handle.get_table(entity_group) {
+                    // a dropped table is kept in memory so that it can be restored
+                    table.load().await;
+                }
                 if registry::state_okay() {
                     let ret = match handle.drop_table(entity_group) {
-                        Ok(()) => responses::groups::OKAY,
+                        Ok(()) => {
+                            let keyspace = history::entity_keyspace(handle, &args[0]);
+                            history::record(handle, keyspace, "DROP TABLE", &args);
+                            responses::groups::OKAY
+                        }
                         Err(DdlError::DefaultNotFound) => responses::groups::DEFAULT_UNSET,
                         Err(DdlError::ProtectedObject) => responses::groups::PROTECTED_OBJECT,
                         Err(DdlError::ObjectNotFound) => responses::groups::CONTAINER_NOT_FOUND,
@@ -194,10 +437,44 @@ action! {
     }
 }
 
+action! {
+    /// Restore a dropped table (`<tblid>` only)
+    fn undrop_table(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        err_if_len_is!(act, con, not 1);
+        let args = act.as_slice().to_vec();
+        let eg = unsafe { act.next().unsafe_unwrap() };
+        let entity_group = match parser::get_query_entity(&eg) {
+            Ok(egroup) => egroup,
+            Err(e) => return con.write_response(e).await,
+        };
+        if registry::state_okay() {
+            let ret = match handle.undrop_table(entity_group) {
+                Ok(()) => {
+                    let keyspace = history::entity_keyspace(handle, &args[0]);
+                    history::record(handle, keyspace, "UNDROP TABLE", &args);
+                    responses::groups::OKAY
+                }
+                Err(DdlError::DefaultNotFound) => responses::groups::DEFAULT_UNSET,
+                Err(DdlError::ObjectNotFound) => responses::groups::CONTAINER_NOT_FOUND,
+                Err(DdlError::AlreadyExists) => responses::groups::ALREADY_EXISTS,
+                Err(_) => unsafe {
+                    // we know that Keyspace::undrop_table won't ever return anything else
+                    impossible!()
+                }
+            };
+            con.write_response(ret).await?;
+        } else {
+            conwrite!(con, responses::groups::SERVER_ERR)?;
+        }
+        Ok(())
+    }
+}
+
 action! {
     /// Drop a keyspace (`<ksid>` only)
     fn drop_keyspace(handle: &Corestore, con: &mut T, mut act: ActionIter) {
         err_if_len_is!(act, con, not 1);
+        let args = act.as_slice().to_vec();
         match act.next() {
             Some(ksid) => {
                 if ksid.len() > 64 {
@@ -216,7 +493,12 @@ action! {
                         handle.drop_keyspace(objid)
                     };
                     let ret = match result {
-                        Ok(()) => responses::groups::OKAY,
+                        Ok(()) => {
+                            persistence::set(handle.get_store(), &args[0], Persistence::Snapshot);
+                            let keyspace = Some(String::from_utf8_lossy(&args[0]).into_owned());
+                            history::record(handle, keyspace, "DROP KEYSPACE", &args);
+                            responses::groups::OKAY
+                        }
                         Err(DdlError::ProtectedObject) => responses::groups::PROTECTED_OBJECT,
                         Err(DdlError::ObjectNotFound) => responses::groups::CONTAINER_NOT_FOUND,
                         Err(DdlError::StillInUse) => responses::groups::STILL_IN_USE,
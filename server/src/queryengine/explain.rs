@@ -0,0 +1,442 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Query plans
+//!
+//! `EXPLAIN <action> <args ...>` reports how an action would be run without running it:
+//! the table that it resolves to, how the table's data is accessed and (roughly) how many
+//! keys are touched
+//!
+//! `EXPLAIN DRYRUN <action> <args ...>` goes a step further for the actions that create,
+//! overwrite or delete keys: it works out what the action would do to the current table
+//! (the keys that it would create, overwrite and delete and by how many bytes the table's
+//! keys and values would grow or shrink), again without running it
+
+use crate::actions::lskeys::{Listing, LSKEYS_MAX_ARGS};
+use crate::corestore::table::Table;
+use crate::dbnet::connection::prelude::*;
+use crate::resp::writer::TypedArrayWriter;
+use bytes::Bytes;
+use std::collections::HashMap;
+
+const CONFIRM: &[u8] = "CONFIRM".as_bytes();
+const DRYRUN: &[u8] = "DRYRUN".as_bytes();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How an action gets to a table's data
+enum Access {
+    /// the keys are looked up one by one
+    Lookup,
+    /// the keys are walked in no particular order until enough are found
+    Scan,
+    /// the whole table is cleared
+    Clear,
+    /// only the table's metadata is read
+    Metadata,
+}
+
+impl Access {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lookup => "lookup",
+            Self::Scan => "scan",
+            Self::Clear => "clear",
+            Self::Metadata => "metadata",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Which of the arguments of a lookup are keys
+enum Keys {
+    /// every argument is a key
+    All,
+    /// the arguments are key/value pairs
+    Pairs,
+    /// only the first argument is a key
+    First,
+    /// all the arguments but the first are keys
+    AllButFirst,
+}
+
+impl Keys {
+    /// Returns the number of keys in the given arguments, or `None` if the arguments
+    /// can't be right
+    const fn count(&self, args: usize) -> Option<usize> {
+        match self {
+            _ if args == 0 => None,
+            Self::All => Some(args),
+            Self::Pairs if args % 2 == 0 => Some(args / 2),
+            Self::Pairs => None,
+            Self::First => Some(1),
+            Self::AllButFirst if args > 1 => Some(args - 1),
+            Self::AllButFirst => None,
+        }
+    }
+}
+
+/// Returns the keys that the given lookup action takes, or `None` if it isn't a lookup
+fn lookup_keys(action: &[u8]) -> Option<Keys> {
+    let keys = match action {
+        b"MGET" | b"DEL" | b"EXISTS" | b"SDEL" | b"POP" | b"MPOP" | b"PFCOUNT" | b"PFMERGE" => {
+            Keys::All
+        }
+        b"SET" | b"MSET" | b"UPDATE" | b"MUPDATE" | b"SSET" | b"SUPDATE" | b"USET" => Keys::Pairs,
+        b"GET" | b"GETEX" | b"EXPIREAT" | b"KEYLEN" | b"JSET" | b"JGET" | b"JDEL" | b"SETBIT"
+        | b"GETBIT" | b"BITCOUNT" | b"PFADD" | b"MERGE" => Keys::First,
+        b"BITOP" => Keys::AllButFirst,
+        _ => return None,
+    };
+    Some(keys)
+}
+
+/// Returns the keys that the given lookup action takes from its arguments, or `None` if it
+/// isn't a lookup (or if the arguments can't be right)
+pub(super) fn action_keys(action: &[u8], args: &[Bytes]) -> Option<Vec<Bytes>> {
+    let keys = lookup_keys(action)?;
+    keys.count(args.len())?;
+    let keys = match keys {
+        Keys::All => args.to_vec(),
+        Keys::Pairs => args.iter().step_by(2).cloned().collect(),
+        Keys::First => args[..1].to_vec(),
+        Keys::AllButFirst => args[1..].to_vec(),
+    };
+    Some(keys)
+}
+
+/// Returns the number of keys that the given lookup action takes from `args` arguments, or
+/// `None` if it isn't a lookup (or if the arguments can't be right)
+pub(super) fn key_count(action: &[u8], args: usize) -> Option<usize> {
+    lookup_keys(action)?.count(args)
+}
+
+/// The plan for an action
+struct Plan {
+    /// the entity that was passed to the action, if any
+    entity: Option<Bytes>,
+    access: Access,
+    /// the keys that are touched (`None` if it depends on the table)
+    keys: Option<usize>,
+}
+
+/// Work out the plan for an action with the given arguments, returning the error response
+/// to be written if it can't be worked out
+fn plan(action: &[u8], mut args: ActionIter) -> Result<Plan, &'static [u8]> {
+    if let Some(keys) = lookup_keys(action) {
+        let keys = keys.count(args.len()).ok_or(groups::ACTION_ERR)?;
+        return Ok(Plan {
+            entity: None,
+            access: Access::Lookup,
+            keys: Some(keys),
+        });
+    }
+    let plan = match action {
+        b"DBSIZE" if args.len() < 2 => Plan {
+            entity: args.next(),
+            access: Access::Metadata,
+            keys: Some(0),
+        },
+        b"FLUSHDB" if args.len() < 4 => Plan {
+            entity: args.next().filter(|arg| !arg.eq_ignore_ascii_case(CONFIRM)),
+            access: Access::Clear,
+            keys: None,
+        },
+        b"LSKEYS" if args.len() <= LSKEYS_MAX_ARGS => {
+            let listing = Listing::parse(args)?;
+            Plan {
+                keys: listing.keys_read(),
+                entity: listing.entity,
+                access: Access::Scan,
+            }
+        }
+        b"DBSIZE" | b"FLUSHDB" | b"LSKEYS" => return Err(groups::ACTION_ERR),
+        _ => return Err(groups::CANNOT_EXPLAIN),
+    };
+    Ok(plan)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What a mutating action does to each of its keys
+enum Effect {
+    /// the key is created if it doesn't exist
+    Create,
+    /// the key is overwritten if it exists
+    Overwrite,
+    /// the key is created or overwritten
+    Upsert,
+    /// the key is deleted if it exists
+    Delete,
+}
+
+/// Returns what the given action does to its keys and whether it's all-or-nothing (the
+/// strong actions don't do anything if any key can't be changed), or `None` if the action
+/// doesn't create, overwrite or delete keys
+fn effect(action: &[u8]) -> Option<(Effect, bool)> {
+    let effect = match action {
+        b"SET" | b"MSET" => (Effect::Create, false),
+        b"SSET" => (Effect::Create, true),
+        b"UPDATE" | b"MUPDATE" => (Effect::Overwrite, false),
+        b"SUPDATE" => (Effect::Overwrite, true),
+        b"USET" => (Effect::Upsert, false),
+        b"DEL" | b"POP" | b"MPOP" => (Effect::Delete, false),
+        b"SDEL" => (Effect::Delete, true),
+        _ => return None,
+    };
+    Some(effect)
+}
+
+#[derive(Debug, Default, PartialEq)]
+/// What a mutating action would do
+struct DryRun {
+    created: usize,
+    overwritten: usize,
+    deleted: usize,
+    /// the keys that would be left as they are
+    unchanged: usize,
+    /// the change in the total size of the table's keys and values
+    bytes: i64,
+}
+
+impl DryRun {
+    /// Account for one key, given the size of its current value (if it exists) and of
+    /// the value that the action sets (if it sets one), returning the size of the value
+    /// that the key would be left with
+    fn apply(
+        &mut self,
+        effect: Effect,
+        key: usize,
+        current: Option<usize>,
+        new: Option<usize>,
+    ) -> Option<usize> {
+        match (effect, current, new) {
+            (Effect::Create, None, Some(new)) | (Effect::Upsert, None, Some(new)) => {
+                self.created += 1;
+                self.bytes += (key + new) as i64;
+                Some(new)
+            }
+            (Effect::Overwrite, Some(old), Some(new)) | (Effect::Upsert, Some(old), Some(new)) => {
+                self.overwritten += 1;
+                self.bytes += new as i64 - old as i64;
+                Some(new)
+            }
+            (Effect::Delete, Some(old), _) => {
+                self.deleted += 1;
+                self.bytes -= (key + old) as i64;
+                None
+            }
+            _ => {
+                self.unchanged += 1;
+                current
+            }
+        }
+    }
+    /// An all-or-nothing action doesn't change anything if any key would be left as it is
+    fn all_or_nothing(self) -> Self {
+        if self.unchanged == 0 {
+            return self;
+        }
+        Self {
+            unchanged: self.created + self.overwritten + self.deleted + self.unchanged,
+            ..Self::default()
+        }
+    }
+}
+
+action! {
+    /// Handle `EXPLAIN DRYRUN <action> <args ...>`, which returns what the action would do
+    /// to the current table as a flat list of alternating names and values: the number of
+    /// keys that would be `created`, `overwritten`, `deleted` and left `unchanged` and the
+    /// change in the size of the table's keys and values in `bytes` (which can be negative)
+    fn dry_run(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let mut action = match act.next() {
+            Some(action) => action.to_vec(),
+            None => aerr!(con, aerr),
+        };
+        action.make_ascii_uppercase();
+        if handle.is_action_denied(&action) {
+            return conwrite!(con, groups::UNKNOWN_ACTION);
+        }
+        let (effect, strong) = match effect(&action) {
+            Some(effect) => effect,
+            None => return conwrite!(con, groups::CANNOT_EXPLAIN),
+        };
+        let accepts = super::COMMANDS
+            .iter()
+            .find(|cmd| cmd.name.as_bytes() == action.as_slice())
+            .and_then(|cmd| cmd.arity)
+            .map_or(true, |arity| arity.accepts(act.len()));
+        let step = if effect == Effect::Delete { 1 } else { 2 };
+        if !accepts || act.len() == 0 || act.len() % step != 0 {
+            aerr!(con, aerr);
+        }
+        let kve = kve!(con, handle);
+        let args = act.as_slice();
+        let mut pending: HashMap<&[u8], Option<usize>> = HashMap::new();
+        let mut outcome = DryRun::default();
+        for kv in args.chunks_exact(step) {
+            let (key, value) = (&kv[0], kv.get(1));
+            let encoded = match value {
+                Some(value) => kve.get_encoder().is_ok(key, value),
+                None => kve.get_key_encoder().is_ok(key),
+            };
+            if !encoded {
+                return conwrite!(con, groups::ENCODING_ERROR);
+            }
+            let current = match pending.get(key.as_ref()) {
+                Some(current) => *current,
+                None => match kve.get_cloned(key.as_ref()) {
+                    Ok(current) => current.map(|val| val.get_blob().len()),
+                    Err(()) => return conwrite!(con, groups::ENCODING_ERROR),
+                },
+            };
+            let after = outcome.apply(effect, key.len(), current, value.map(Bytes::len));
+            pending.insert(key.as_ref(), after);
+        }
+        if strong {
+            outcome = outcome.all_or_nothing();
+        }
+        let pairs = [
+            ("created", outcome.created.to_string()),
+            ("overwritten", outcome.overwritten.to_string()),
+            ("deleted", outcome.deleted.to_string()),
+            ("unchanged", outcome.unchanged.to_string()),
+            ("bytes", outcome.bytes.to_string()),
+        ];
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', pairs.len() * 2)
+        }
+        .await?;
+        for (name, value) in pairs.iter() {
+            writer.write_element(name).await?;
+            writer.write_element(value).await?;
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Handle `EXPLAIN <action> <args ...>`, which returns the plan for the action as a flat
+    /// list of alternating names and values:
+    /// - `entity`: the table that the action is run on
+    /// - `model`: the table's model
+    /// - `access`: `lookup` (the keys are looked up one by one), `scan` (the keys are walked
+    /// until enough are found), `clear` (the table is cleared) or `metadata` (only the table's
+    /// metadata is read)
+    /// - `keys`: the estimated number of keys that are touched
+    ///
+    /// `EXPLAIN DRYRUN <action> <args ...>` is handled by [`dry_run`]
+    fn explain(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        let mut action = match act.next() {
+            Some(action) => action.to_vec(),
+            None => aerr!(con, aerr),
+        };
+        action.make_ascii_uppercase();
+        if action == DRYRUN {
+            return dry_run(handle, con, act).await;
+        }
+        if handle.is_action_denied(&action) {
+            return conwrite!(con, groups::UNKNOWN_ACTION);
+        }
+        let plan = match plan(&action, act) {
+            Ok(plan) => plan,
+            Err(e) => return conwrite!(con, e),
+        };
+        let (entity, table) = match &plan.entity {
+            Some(entity) => {
+                let table = get_tbl!(handle_entity!(con, entity), handle, con);
+                (String::from_utf8_lossy(entity).into_owned(), table)
+            }
+            None => match (handle.ctable_name(), handle.get_ctable()) {
+                (Some(name), Some(table)) => (name, table),
+                _ => return conwrite!(con, groups::DEFAULT_UNSET),
+            },
+        };
+        let keys = estimate_keys(&plan, &table);
+        let pairs = [
+            ("entity", entity),
+            ("model", table.describe_self().to_owned()),
+            ("access", plan.access.as_str().to_owned()),
+            ("keys", keys.to_string()),
+        ];
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', pairs.len() * 2)
+        }
+        .await?;
+        for (name, value) in pairs.iter() {
+            writer.write_element(name).await?;
+            writer.write_element(value).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of keys that the plan touches in the given table
+fn estimate_keys(plan: &Plan, table: &Table) -> usize {
+    let count = table.count();
+    match (plan.access, plan.keys) {
+        (Access::Scan, Some(keys)) => keys.min(count),
+        (_, Some(keys)) => keys,
+        (_, None) => count,
+    }
+}
+
+#[test]
+fn test_keys_count() {
+    assert_eq!(Keys::All.count(3), Some(3));
+    assert_eq!(Keys::Pairs.count(4), Some(2));
+    assert_eq!(Keys::Pairs.count(3), None);
+    assert_eq!(Keys::First.count(3), Some(1));
+    assert_eq!(Keys::AllButFirst.count(3), Some(2));
+    assert_eq!(Keys::AllButFirst.count(1), None);
+    assert_eq!(Keys::All.count(0), None);
+}
+
+#[test]
+fn test_dry_run_apply() {
+    let mut dry_run = DryRun::default();
+    assert_eq!(dry_run.apply(Effect::Create, 1, None, Some(3)), Some(3));
+    assert_eq!(dry_run.apply(Effect::Create, 1, Some(3), Some(5)), Some(3));
+    assert_eq!(dry_run.apply(Effect::Upsert, 1, Some(3), Some(5)), Some(5));
+    assert_eq!(dry_run.apply(Effect::Delete, 1, Some(5), None), None);
+    assert_eq!(
+        dry_run,
+        DryRun {
+            created: 1,
+            overwritten: 1,
+            deleted: 1,
+            unchanged: 1,
+            bytes: 4 + 2 - 6,
+        }
+    );
+    assert_eq!(
+        dry_run.all_or_nothing(),
+        DryRun {
+            unchanged: 4,
+            ..DryRun::default()
+        }
+    );
+}
@@ -0,0 +1,296 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Idempotency tokens
+//!
+//! A client that times out waiting for the response to a write can't tell if the write ran,
+//! so retrying it might run it twice (which matters for writes like `POP` or `PFADD`). Writes
+//! that are sent with a token, as in:
+//! ```text
+//! IDEMPOTENT <token> <action> <args ...>
+//! ```
+//! are run only once for every token: if the token was used in the last
+//! [`TOKEN_TTL`], the write isn't run again and the response that it got the first time is
+//! returned instead. Tokens are remembered for the user that the connection has authenticated
+//! as (so that a retry on a new connection is caught), or for the connection if it hasn't
+//! authenticated. Only the last [`MAX_TOKENS`] tokens are remembered, and only as many as
+//! fit in [`MAX_TOKEN_BYTES`] (so a write with a huge response might not be remembered).
+//!
+//! A token that is sent with a different query (or on a different table) than the one that
+//! it was first used with is rejected with `err-token-reused`, and a token whose write is
+//! still running (on another connection of the same user) with `err-token-in-progress`
+
+use super::commands;
+use crate::corestore::lazy::Lazy;
+use crate::dbnet::connection::prelude::*;
+use crate::dbnet::memory::MemoryConnection;
+use crate::protocol::Element;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a token is remembered for
+pub const TOKEN_TTL: Duration = Duration::from_secs(600);
+/// The most tokens that are remembered for a user (or a connection)
+pub const MAX_TOKENS: usize = 4096;
+/// The most bytes of queries and responses that are remembered for a user (or a connection)
+pub const MAX_TOKEN_BYTES: usize = 4 * 1024 * 1024;
+/// The longest that a token can be
+const MAX_TOKEN_LEN: usize = 128;
+
+type UserTokens = HashMap<String, Arc<Mutex<Tokens>>>;
+
+/// The tokens of every user that has used one
+static USER_TOKENS: Lazy<Mutex<UserTokens>, fn() -> Mutex<UserTokens>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+action! {
+    /// Run a write action with an idempotency token, or return the response that it got the
+    /// first time if the token was used before
+    fn idempotent(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let token = unsafe {
+            // SAFETY: The arity is checked by the dispatcher
+            act.next().unsafe_unwrap()
+        };
+        let query: Vec<Bytes> = act.collect();
+        if token.is_empty() || token.len() > MAX_TOKEN_LEN || !is_write(&query[0]) {
+            return conwrite!(con, groups::ACTION_ERR);
+        }
+        let tokens = match handle.user_name() {
+            Some(user) => USER_TOKENS
+                .lock()
+                .entry(user.to_owned())
+                .or_default()
+                .clone(),
+            None => handle.connection_tokens(),
+        };
+        let (_, table) = handle.current_entity();
+        let begun = tokens.lock().begin(&token, table, &query);
+        match begun {
+            Begin::New => {}
+            Begin::Done(response) => return conwrite!(con, response),
+            Begin::Running => return conwrite!(con, groups::TOKEN_IN_PROGRESS),
+            Begin::Reused => return conwrite!(con, groups::TOKEN_REUSED),
+        }
+        let mut memcon = MemoryConnection::new();
+        let ret = super::execute_boxed(handle, &mut memcon, Element::AnyArray(query)).await;
+        if let Err(e) = ret {
+            // we can't tell what happened, so let the client try again
+            tokens.lock().forget(&token);
+            return Err(e);
+        }
+        memcon.flush_stream().await?;
+        let response = memcon.into_response();
+        tokens.lock().finish(&token, response.clone());
+        conwrite!(con, response)
+    }
+}
+
+/// Returns true if `action` is a built-in action that writes data
+fn is_write(action: &[u8]) -> bool {
+    let mut action = action.to_ascii_uppercase();
+    if let Some(name) = commands::resolve_alias(&action) {
+        action = name.to_vec();
+    }
+    super::COMMANDS
+        .iter()
+        .any(|command| command.name.as_bytes() == action && command.guard == Some("write"))
+}
+
+#[derive(Debug)]
+/// What happened to the write that a token was first used with
+enum State {
+    /// the write is still running
+    Running,
+    /// the write ran and got this response
+    Done(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct Remembered {
+    /// the `keyspace:table` that the query ran on
+    table: Option<String>,
+    query: Vec<Bytes>,
+    state: State,
+    at: Instant,
+}
+
+impl Remembered {
+    /// The bytes that this takes up in the [`MAX_TOKEN_BYTES`] budget
+    fn size(&self) -> usize {
+        let response = match &self.state {
+            State::Running => 0,
+            State::Done(response) => response.len(),
+        };
+        self.query.iter().map(Bytes::len).sum::<usize>() + response
+    }
+}
+
+/// What to do with a write that was sent with a token
+#[derive(Debug, PartialEq)]
+enum Begin {
+    /// run it, since the token is new
+    New,
+    /// return this response, since the write has already run
+    Done(Vec<u8>),
+    /// reject it, since the write is still running
+    Running,
+    /// reject it, since the token was used with another query
+    Reused,
+}
+
+#[derive(Debug, Default)]
+/// The tokens that a user (or a connection) has used recently
+pub struct Tokens {
+    remembered: HashMap<Bytes, Remembered>,
+    /// the tokens, oldest first
+    order: VecDeque<Bytes>,
+    /// the size of the remembered queries and responses
+    bytes: usize,
+}
+
+impl Tokens {
+    /// Start running `query` on `table` with `token`, unless the token has been used before
+    fn begin(&mut self, token: &Bytes, table: Option<String>, query: &[Bytes]) -> Begin {
+        self.expire(Instant::now());
+        if let Some(remembered) = self.remembered.get(token) {
+            return if remembered.table != table || remembered.query != query {
+                Begin::Reused
+            } else {
+                match &remembered.state {
+                    State::Running => Begin::Running,
+                    State::Done(response) => Begin::Done(response.clone()),
+                }
+            };
+        }
+        if self.order.len() >= MAX_TOKENS {
+            self.forget_oldest();
+        }
+        let remembered = Remembered {
+            table,
+            query: query.to_vec(),
+            state: State::Running,
+            at: Instant::now(),
+        };
+        self.bytes += remembered.size();
+        self.order.push_back(token.clone());
+        self.remembered.insert(token.clone(), remembered);
+        self.shrink();
+        Begin::New
+    }
+    /// Remember the response that the write with `token` got
+    fn finish(&mut self, token: &Bytes, response: Vec<u8>) {
+        if let Some(remembered) = self.remembered.get_mut(token) {
+            self.bytes += response.len();
+            remembered.state = State::Done(response);
+            self.shrink();
+        }
+    }
+    /// Forget `token`, so that the write can be tried again
+    fn forget(&mut self, token: &Bytes) {
+        if let Some(remembered) = self.remembered.remove(token) {
+            self.bytes -= remembered.size();
+            self.order.retain(|remembered| remembered != token);
+        }
+    }
+    /// Forget the oldest token
+    fn forget_oldest(&mut self) {
+        if let Some(oldest) = self.order.pop_front() {
+            if let Some(remembered) = self.remembered.remove(&oldest) {
+                self.bytes -= remembered.size();
+            }
+        }
+    }
+    /// Forget the oldest tokens until the rest fit in [`MAX_TOKEN_BYTES`]. This can forget
+    /// the token that was used last too, if its query or response alone doesn't fit
+    fn shrink(&mut self) {
+        while self.bytes > MAX_TOKEN_BYTES && !self.order.is_empty() {
+            self.forget_oldest();
+        }
+    }
+    /// Forget the tokens that were used more than [`TOKEN_TTL`] ago
+    fn expire(&mut self, now: Instant) {
+        while let Some(oldest) = self.order.front() {
+            let expired = self.remembered.get(oldest).map_or(true, |remembered| {
+                now.duration_since(remembered.at) >= TOKEN_TTL
+            });
+            if !expired {
+                break;
+            }
+            self.forget_oldest();
+        }
+    }
+}
+
+#[test]
+fn test_tokens() {
+    let table = || Some("default:default".to_owned());
+    let query = vec![Bytes::from("POP"), Bytes::from("jobs")];
+    let token = Bytes::from("retry-1");
+    let mut tokens = Tokens::default();
+    assert_eq!(tokens.begin(&token, table(), &query), Begin::New);
+    assert_eq!(tokens.begin(&token, table(), &query), Begin::Running);
+    tokens.finish(&token, b"+3\njob\n".to_vec());
+    assert_eq!(
+        tokens.begin(&token, table(), &query),
+        Begin::Done(b"+3\njob\n".to_vec())
+    );
+    assert_eq!(
+        tokens.begin(&token, table(), &[Bytes::from("POP"), Bytes::from("mail")]),
+        Begin::Reused
+    );
+    // the same query on another table is another write
+    assert_eq!(
+        tokens.begin(&token, Some("default:mail".to_owned()), &query),
+        Begin::Reused
+    );
+    tokens.forget(&token);
+    assert_eq!(tokens.begin(&token, table(), &query), Begin::New);
+    // tokens are forgotten once they expire
+    tokens.expire(Instant::now() + TOKEN_TTL);
+    assert_eq!(tokens.begin(&token, table(), &query), Begin::New);
+    assert_eq!(tokens.bytes, 7);
+}
+
+#[test]
+fn test_tokens_byte_limit() {
+    let query = vec![Bytes::from("POP"), Bytes::from("jobs")];
+    let (first, second) = (Bytes::from("retry-1"), Bytes::from("retry-2"));
+    let mut tokens = Tokens::default();
+    assert_eq!(tokens.begin(&first, None, &query), Begin::New);
+    tokens.finish(&first, vec![0; MAX_TOKEN_BYTES / 2]);
+    assert_eq!(tokens.begin(&second, None, &query), Begin::New);
+    // the second response pushes out the first one
+    tokens.finish(&second, vec![0; MAX_TOKEN_BYTES / 2]);
+    assert_eq!(tokens.begin(&first, None, &query), Begin::New);
+    assert!(tokens.bytes <= MAX_TOKEN_BYTES);
+    // and a response that doesn't fit at all isn't remembered
+    tokens.finish(&first, vec![0; MAX_TOKEN_BYTES + 1]);
+    assert_eq!(tokens.begin(&first, None, &query), Begin::New);
+    assert!(tokens.bytes <= MAX_TOKEN_BYTES);
+}
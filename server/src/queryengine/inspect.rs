@@ -25,16 +25,24 @@
 */
 
 use super::ddl::{KEYSPACE, TABLE};
-use crate::corestore::memstore::ObjectID;
+use crate::admin::history::{self, Entry};
 use crate::dbnet::connection::prelude::*;
+use crate::protocol::hello::Capabilities;
+use crate::resp::builder::ResponseElement;
 use crate::resp::writer::TypedArrayWriter;
+use crate::storage::usage::{self, DiskUsage};
 
 const KEYSPACES: &[u8] = "KEYSPACES".as_bytes();
+const USAGE: &[u8] = "USAGE".as_bytes();
+const HISTORY: &[u8] = "HISTORY".as_bytes();
 action! {
     /// Runs an inspect query:
-    /// - `INSPECT KEYSPACES` is run by this function itself
+    /// - `INSPECT KEYSPACES [<cursor> <count> [<prefix>]]` is run by this function itself
     /// - `INSPECT TABLE <tblid>` is delegated to self::inspect_table
-    /// - `INSPECT KEYSPACE <ksid>` is delegated to self::inspect_keyspace
+    /// - `INSPECT KEYSPACE <ksid> [<cursor> <count> [<prefix>]]` is delegated to
+    /// self::inspect_keyspace
+    /// - `INSPECT USAGE [<ksid>]` is delegated to self::inspect_usage
+    /// - `INSPECT HISTORY` is delegated to self::inspect_history
     fn inspect(handle: &Corestore, con: &mut T, mut act: ActionIter) {
         match act.next() {
             Some(inspect_what) => {
@@ -43,21 +51,12 @@ action! {
                 match inspect_what.as_ref() {
                     KEYSPACE => inspect_keyspace(handle, con, act).await?,
                     TABLE => inspect_table(handle, con, act).await?,
+                    USAGE => inspect_usage(handle, con, act).await?,
+                    HISTORY => inspect_history(handle, con, act).await?,
                     KEYSPACES => {
-                        err_if_len_is!(act, con, not 0);
-                        // let's return what all keyspaces exist
-                        let ks_list: Vec<ObjectID> = handle
-                            .get_store()
-                            .keyspaces
-                            .iter()
-                            .map(|kv| kv.key().clone())
-                            .collect();
-                        let mut writer = unsafe {
-                            TypedArrayWriter::new(con, b'+', ks_list.len())
-                        }.await?;
-                        for tbl in ks_list {
-                            writer.write_element(tbl).await?;
-                        }
+                        // let's return what all keyspaces exist (that we can see)
+                        let ks_list = handle.keyspace_names();
+                        write_listing(con, act, ks_list).await?;
                     }
                     _ => conwrite!(con, responses::groups::UNKNOWN_INSPECT_QUERY)?,
                 }
@@ -69,9 +68,10 @@ action! {
 }
 
 action! {
-    /// INSPECT a keyspace. This should only have the keyspace ID
+    /// INSPECT a keyspace. This should have the keyspace ID, optionally followed by a cursor,
+    /// a count and a name prefix (see [`write_listing`])
     fn inspect_keyspace(handle: &Corestore, con: &mut T, mut act: ActionIter) {
-        err_if_len_is!(act, con, not 1);
+        err_if_len_is!(act, con, eq 0);
         match act.next() {
             Some(keyspace_name) => {
                 let ksid = if keyspace_name.len() > 64 {
@@ -83,13 +83,12 @@ action! {
                     Some(kspace) => kspace,
                     None => return conwrite!(con, responses::groups::CONTAINER_NOT_FOUND),
                 };
-                let tbl_list: Vec<ObjectID> = ks.tables.iter().map(|kv| kv.key().clone()).collect();
-                let mut writer = unsafe {
-                    TypedArrayWriter::new(con, b'+', tbl_list.len())
-                }.await?;
-                for tbl in tbl_list {
-                    writer.write_element(tbl).await?;
-                }
+                let tbl_list: Vec<String> = ks
+                    .tables
+                    .iter()
+                    .map(|kv| unsafe { kv.key().as_str() }.to_owned())
+                    .collect();
+                write_listing(con, act, tbl_list).await?;
             },
             None => aerr!(con, aerr),
         }
@@ -97,6 +96,82 @@ action! {
     }
 }
 
+/// Write a listing of container names. Without any more arguments, every name is written (in
+/// no particular order). Otherwise, the remaining arguments are `<cursor> <count> [<prefix>]`
+/// and the listing is paginated: the names (only those starting with `prefix`, if one is given)
+/// are sorted and at most `count` of the names that come after `cursor` are written, preceded
+/// by the cursor for the next page. A cursor of `0` starts a listing and is returned once the
+/// listing is complete (container names can't start with a digit, so this is never a name)
+async fn write_listing<T, Strm>(
+    con: &mut T,
+    mut act: ActionIter,
+    mut names: Vec<String>,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    if act.len() == 0 {
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', names.len())
+        }
+        .await?;
+        for name in names {
+            writer.write_element(name).await?;
+        }
+        return Ok(());
+    }
+    if act.len() != 2 && act.len() != 3 {
+        return conwrite!(con, responses::groups::ACTION_ERR);
+    }
+    let (cursor, count) = unsafe {
+        // SAFETY: we've checked the length
+        (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+    };
+    let count = match String::from_utf8_lossy(&count).parse::<usize>() {
+        Ok(cnt) if cnt != 0 => cnt,
+        _ => return conwrite!(con, responses::groups::WRONGTYPE_ERR),
+    };
+    if let Some(prefix) = act.next() {
+        names.retain(|name| name.as_bytes().starts_with(&prefix));
+    }
+    let (next, page) = paginate(names, &cursor, count);
+    let mut writer = unsafe {
+        // SAFETY: all the elements are strings
+        TypedArrayWriter::new(con, b'+', page.len() + 1)
+    }
+    .await?;
+    writer.write_element(next).await?;
+    for name in page {
+        writer.write_element(name).await?;
+    }
+    Ok(())
+}
+
+/// Sort `names` and return at most `count` of the names that come after `cursor` (or from the
+/// first name if the cursor is `0`), along with the cursor for the next page (`0` if there are
+/// no more names)
+fn paginate(mut names: Vec<String>, cursor: &[u8], count: usize) -> (String, Vec<String>) {
+    names.sort_unstable();
+    let start = if cursor == b"0" {
+        0
+    } else {
+        names
+            .iter()
+            .position(|name| name.as_bytes() > cursor)
+            .unwrap_or_else(|| names.len())
+    };
+    let mut page: Vec<String> = names.drain(start..).collect();
+    let next = if page.len() > count {
+        page.truncate(count);
+        page[count - 1].clone()
+    } else {
+        "0".to_owned()
+    };
+    (next, page)
+}
+
 action! {
     /// INSPECT a table. This should only have the table ID
     fn inspect_table(handle: &Corestore, con: &mut T, mut act: ActionIter) {
@@ -111,3 +186,175 @@ action! {
         Ok(())
     }
 }
+
+/// Returns true if the connection agreed on the `nested` capability, in which case the
+/// responses that are naturally nested are sent as nested arrays and maps
+fn wants_nested(handle: &Corestore) -> bool {
+    handle
+        .handshake()
+        .map_or(false, |hs| hs.capabilities.contains(Capabilities::NESTED))
+}
+
+/// Returns the disk usage as a map of names to maps with the `data` and `snapshots` byte
+/// counts
+fn nested_usage(usage: Vec<(String, DiskUsage)>) -> ResponseElement {
+    ResponseElement::map(usage.into_iter().map(|(name, objusage)| {
+        (
+            name.into(),
+            ResponseElement::map(vec![
+                ("data".into(), objusage.data.into()),
+                ("snapshots".into(), objusage.snapshots.into()),
+            ]),
+        )
+    }))
+}
+
+/// Returns the DDL history as an array of maps with the `seq`, `time`, `user` and `query`
+/// of every query
+fn nested_history(entries: Vec<(u64, Entry)>) -> ResponseElement {
+    ResponseElement::array(entries.into_iter().map(|(seq, entry)| {
+        ResponseElement::map(vec![
+            ("seq".into(), seq.into()),
+            ("time".into(), entry.time.into()),
+            (
+                "user".into(),
+                entry
+                    .user
+                    .map_or(ResponseElement::Nil, ResponseElement::from),
+            ),
+            ("query".into(), entry.query.into()),
+        ])
+    }))
+}
+
+action! {
+    /// INSPECT the disk usage. Without arguments, the usage of every keyspace is returned,
+    /// while with a keyspace ID, the usage of every table in that keyspace is returned. The
+    /// usage is returned as a flat list of alternating names and byte counts (like
+    /// `default.data` and `default.snapshots`), or as a map (see [`nested_usage`]) if the
+    /// connection agreed on the `nested` capability
+    fn inspect_usage(handle: &Corestore, con: &mut T, mut act: ActionIter) {
+        err_if_len_is!(act, con, gt 1);
+        let usage = match act.next() {
+            Some(keyspace_name) => {
+                let ksid = if keyspace_name.len() > 64 {
+                    return conwrite!(con, responses::groups::BAD_CONTAINER_NAME);
+                } else {
+                    &keyspace_name[..]
+                };
+                let ks = match handle.get_keyspace(ksid) {
+                    Some(kspace) => kspace,
+                    None => return conwrite!(con, responses::groups::CONTAINER_NOT_FOUND),
+                };
+                let ksid = unsafe {
+                    // SAFETY: the keyspace exists, so its name is valid UTF-8
+                    core::str::from_utf8_unchecked(ksid)
+                }.to_owned();
+                let tbl_list: Vec<String> = ks
+                    .tables
+                    .iter()
+                    .map(|kv| unsafe { kv.key().as_str() }.to_owned())
+                    .collect();
+                tokio::task::spawn_blocking(move || {
+                    tbl_list
+                        .into_iter()
+                        .map(|tblid| {
+                            let tblusage = usage::table_usage(&ksid, &tblid);
+                            (tblid, tblusage)
+                        })
+                        .collect::<Vec<(String, DiskUsage)>>()
+                })
+                .await
+                .expect("disk usage thread panicked")
+            }
+            None => {
+                let ks_list = handle.keyspace_names();
+                tokio::task::spawn_blocking(move || {
+                    ks_list
+                        .into_iter()
+                        .map(|ksid| {
+                            let ksusage = usage::keyspace_usage(&ksid);
+                            (ksid, ksusage)
+                        })
+                        .collect::<Vec<(String, DiskUsage)>>()
+                })
+                .await
+                .expect("disk usage thread panicked")
+            }
+        };
+        if wants_nested(handle) {
+            return conwrite!(con, nested_usage(usage));
+        }
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', usage.len() * 4)
+        }.await?;
+        for (name, objusage) in usage {
+            writer.write_element(format!("{}.data", name)).await?;
+            writer.write_element(objusage.data.to_string()).await?;
+            writer.write_element(format!("{}.snapshots", name)).await?;
+            writer.write_element(objusage.snapshots.to_string()).await?;
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// INSPECT the DDL history. Every query is returned as its sequence number, the time at
+    /// which it was run (in seconds since the UNIX epoch), the user that ran it (null if auth
+    /// is disabled) and the query itself, oldest first (as a flat list, or as an array of maps
+    /// if the connection agreed on the `nested` capability). Queries on keyspaces that the
+    /// authenticated user can't access are left out
+    fn inspect_history(handle: &Corestore, con: &mut T, act: ActionIter) {
+        err_if_len_is!(act, con, not 0);
+        let mut entries = history::entries(handle.get_store());
+        entries.retain(|(_, entry)| {
+            entry
+                .keyspace
+                .as_ref()
+                .map_or(true, |ks| handle.can_access(ks.as_bytes()))
+        });
+        if wants_nested(handle) {
+            return conwrite!(con, nested_history(entries));
+        }
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', entries.len() * 4)
+        }.await?;
+        for (seq, entry) in entries {
+            writer.write_element(seq.to_string()).await?;
+            writer.write_element(entry.time.to_string()).await?;
+            match entry.user {
+                Some(user) => writer.write_element(user).await?,
+                None => writer.write_null().await?,
+            }
+            writer.write_element(entry.query).await?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_nested_responses() {
+    let usage = vec![(
+        "default".to_owned(),
+        DiskUsage {
+            data: 10,
+            snapshots: 0,
+        },
+    )];
+    assert_eq!(
+        nested_usage(usage).encode(),
+        b"%1\n+7\ndefault\n%2\n+4\ndata\n:2\n10\n+9\nsnapshots\n:1\n0\n"
+    );
+    let entry = Entry {
+        time: 1,
+        user: None,
+        keyspace: None,
+        query: "CREATE KEYSPACE a".to_owned(),
+    };
+    assert_eq!(
+        nested_history(vec![(0, entry)]).encode(),
+        b"&1\n%4\n+3\nseq\n:1\n0\n+4\ntime\n:1\n1\n+4\nuser\n!1\n1\n+5\nquery\n+17\nCREATE KEYSPACE a\n"
+    );
+}
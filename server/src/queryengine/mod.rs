@@ -26,24 +26,54 @@
 
 //! # The Query Engine
 
+use self::arity::Arity::*;
 use crate::corestore::memstore::DdlError;
+use crate::corestore::session::ResumeError;
 use crate::corestore::Corestore;
 use crate::dbnet::connection::prelude::*;
+use crate::protocol::hello::Handshake;
 use crate::protocol::responses;
 use crate::protocol::Element;
+use crate::registry::auth::{self, AuthError};
+use crate::resp::writer::TypedArrayWriter;
+use crate::resp::BytesWrapper;
+use crate::services::{schema, shadow};
 use crate::{actions, admin};
 use bytes::Bytes;
+use core::future::Future;
+use core::pin::Pin;
+pub mod arity;
+mod batch;
+pub mod commands;
 mod ddl;
+mod explain;
+pub mod idempotency;
 mod inspect;
 pub mod parser;
+mod pin;
+pub mod plugins;
 #[cfg(test)]
 mod tests;
+mod tracking;
+pub mod verbose;
 
 use std::vec::IntoIter;
 pub type ActionIter = IntoIter<Bytes>;
 
 macro_rules! gen_constants_and_matches {
-    ($con:ident, $buf:ident, $db:ident, $($action:ident => $fns:expr),*) => {
+    (
+        $con:ident, $buf:ident, $db:ident,
+        $($action:ident $(($arity:expr))? => $(@$guard:ident)? $fns:expr),*
+    ) => {
+        gen_constants_and_matches!(
+            $con, $buf, $db, @else responses::groups::UNKNOWN_ACTION, @plugins,
+            $($action $(($arity))? => $(@$guard)? $fns),*
+        )
+    };
+    (
+        $con:ident, $buf:ident, $db:ident, @else $fallback:expr, $(@$plugins:ident,)?
+        $($action:ident $(($arity:expr))? => $(@$guard:ident)? $fns:expr),*
+    ) => {
         mod tags {
             //! This module is a collection of tags/strings used for evaluating queries
             //! and responses
@@ -56,17 +86,308 @@ macro_rules! gen_constants_and_matches {
             None => return $con.write_response(responses::groups::PACKET_ERR).await,
         };
         first.make_ascii_uppercase();
+        if let Some(action) = commands::resolve_alias(&first) {
+            first = action.to_vec();
+        }
+        if $db.is_action_denied(&first) {
+            // disabled actions don't exist as far as the client is concerned
+            return $con.write_response(responses::groups::UNKNOWN_ACTION).await;
+        }
+        if let Some(e) = $db.check_auth(&first) {
+            return $con.write_response(e).await;
+        }
         match first.as_ref() {
             $(
-                tags::$action => $fns($db, $con, $buf).await?,
+                tags::$action => {
+                    $(
+                        if !$arity.accepts($buf.len()) {
+                            return $con.write_response(arity::error(&first, $db)).await;
+                        }
+                    )?
+                    if let Some(e) = pinned_guard!($(@$guard)? $db, tags::$action) {
+                        return $con.write_response(e).await;
+                    }
+                    $(
+                        if let Some(e) = guard::$guard() {
+                            return $con.write_response(e).await;
+                        }
+                        if let Some(e) = schema_guard!($guard, $db, &first, $buf) {
+                            return $con.write_response(e).await;
+                        }
+                        if let Some(e) = table_guard!($guard, $db) {
+                            return $con.write_response(e).await;
+                        }
+                    )?
+                    // a cold table is read back on a blocking thread before the action runs
+                    $db.load_ctable().await;
+                    let written = track!($(@$guard)? $db, &first, $buf);
+                    let mirrored = mirror!($(@$guard)? $db, $con, &first, $buf);
+                    let ret = $fns($db, $con, $buf).await;
+                    if let Some(write) = mirrored {
+                        write.queue(&*$con);
+                    }
+                    if let Some(keys) = written {
+                        tracking::invalidate_written($db, keys);
+                    }
+                    ret?
+                },
             )*
             _ => {
-                return $con.write_response(responses::groups::UNKNOWN_ACTION).await;
+                $(
+                    // custom actions can only be run where the built-in actions can
+                    if let Some(action) = $plugins::get(&first) {
+                        if $db.is_pinned() {
+                            return $con.write_response(responses::groups::PINNED).await;
+                        }
+                        $db.load_ctable().await;
+                        let ret = $plugins::run(action, $db, $con, $buf).await;
+                        // we can't tell what a custom action changes
+                        tracking::invalidate_written($db, None);
+                        return ret;
+                    }
+                )?
+                return $con.write_response($fallback).await;
             }
         }
     };
 }
 
+/// Invokes the given macro (with the given leading arguments) on the list of the actions
+/// that can be run on a regular connection, so that the dispatcher and the
+/// [registry](self::commands) are generated from the same list
+macro_rules! simple_actions {
+    ($callback:ident!($($args:tt)*)) => {
+        $callback!(
+            $($args)*
+            GET(Between(1, 2)) => @read actions::get::get,
+            GETEX(Exact(2)) => @write actions::getex::getex,
+            EXPIREAT(Exact(2)) => @write actions::expireat::expireat,
+            SET(Exact(2)) => @write actions::set::set,
+            UPDATE(Exact(2)) => @write actions::update::update,
+            DEL(AtLeast(1)) => @write actions::del::del,
+            HEYA(AtMost(1)) => actions::heya::heya,
+            HELLO => self::hello,
+            AUTH => self::auth,
+            EXISTS(AtLeast(1)) => @read actions::exists::exists,
+            MSET => @write actions::mset::mset,
+            MGET(AtLeast(1)) => @read actions::mget::mget,
+            MUPDATE(Pairs) => @write actions::mupdate::mupdate,
+            SSET(Pairs) => @write actions::strong::sset,
+            SDEL(AtLeast(1)) => @write actions::strong::sdel,
+            SUPDATE(Pairs) => @write actions::strong::supdate,
+            DBSIZE(AtMost(1)) => @read actions::dbsize::dbsize,
+            FLUSHDB(AtMost(3)) => @write actions::flushdb::flushdb,
+            USET(Pairs) => @write actions::uset::uset,
+            KEYLEN(Exact(1)) => @read actions::keylen::keylen,
+            MKSNAP => admin::mksnap::mksnap,
+            LSKEYS(AtMost(actions::lskeys::LSKEYS_MAX_ARGS)) => @read actions::lskeys::lskeys,
+            POP(Exact(1)) => @write actions::pop::pop,
+            CREATE(AtLeast(2)) => @write ddl::create,
+            DROP(AtLeast(2)) => @write ddl::ddl_drop,
+            UNDROP(Exact(2)) => @write ddl::ddl_undrop,
+            CLONE(Exact(3)) => @write ddl::ddl_clone,
+            USE => @read self::entity_swap,
+            SESSION => @read self::session,
+            INSPECT => inspect::inspect,
+            EXPLAIN => @read explain::explain,
+            MPOP(AtLeast(1)) => @write actions::mpop::mpop,
+            SYS => admin::sys::sys,
+            JSET(Exact(3)) => @write actions::json::jset,
+            JGET(Between(1, 2)) => @read actions::json::jget,
+            JDEL(Exact(2)) => @write actions::json::jdel,
+            SETBIT(Exact(3)) => @write actions::bitmap::setbit,
+            GETBIT(Exact(2)) => @read actions::bitmap::getbit,
+            BITCOUNT(Exact(1)) => @read actions::bitmap::bitcount,
+            BITOP(AtLeast(3)) => @write actions::bitmap::bitop,
+            PFADD(AtLeast(1)) => @write actions::hll::pfadd,
+            PFCOUNT(AtLeast(1)) => @read actions::hll::pfcount,
+            PFMERGE(AtLeast(2)) => @write actions::hll::pfmerge,
+            MERGE(AtLeast(2)) => @write actions::merge::merge,
+            DEBUG => admin::debug::debug,
+            WAIT(AtMost(2)) => actions::wait::wait,
+            WHOAMI(Exact(0)) => self::whoami,
+            OBJECT(Exact(1)) => @read actions::object::object,
+            SETV(Exact(3)) => @write actions::setv::setv,
+            GETUPDATE(Exact(3)) => @write actions::getupdate::getupdate,
+            DUMP(Exact(2)) => @read actions::dump::dump,
+            MCAS(AtLeast(3)) => @write actions::mcas::mcas,
+            PREFIXSTATS(Exact(1)) => @read actions::prefix::prefixstats,
+            PREFIXDROP(Exact(1)) => @write actions::prefix::prefixdrop,
+            BATCH(AtLeast(2)) => batch::batch,
+            PIN(AtMost(1)) => pin::pin,
+            UNPIN(Exact(0)) => pin::unpin,
+            TRACKING(Exact(1)) => tracking::tracking,
+            VERBOSE(Exact(1)) => verbose::verbose,
+            IDEMPOTENT(AtLeast(2)) => idempotency::idempotent
+        )
+    };
+}
+
+macro_rules! gen_registry {
+    (@opt) => { None };
+    (@opt $val:expr) => { Some($val) };
+    ($($action:ident $(($arity:expr))? => $(@$guard:ident)? $fns:expr),*) => {
+        /// The actions that can be run on a regular connection (see [`commands`])
+        pub static COMMANDS: &[commands::Command] = &[
+            $(
+                commands::Command {
+                    name: stringify!($action),
+                    arity: gen_registry!(@opt $($arity)?),
+                    guard: gen_registry!(@opt $(stringify!($guard))?),
+                },
+            )*
+        ];
+    };
+}
+
+simple_actions!(gen_registry!());
+
+/// Decides if an action can be run on a [pinned](self::pin) connection: actions that read
+/// data are run on the frozen view, while actions that write data are rejected
+macro_rules! pinned_guard {
+    (@read $db:ident, $action:expr) => {
+        None::<&'static [u8]>
+    };
+    (@write $db:ident, $action:expr) => {
+        guard::pinned($db)
+    };
+    ($db:ident, $action:expr) => {
+        guard::pinned_action($db, $action)
+    };
+}
+
+/// Works out what has to be done for [tracking](self::tracking) around an action: the keys
+/// that `@read` actions look up are recorded before they run, while the keys that `@write`
+/// actions change are returned (before the arguments are consumed) so that they can be
+/// invalidated once the action has run. The keys are also counted for [verbose](self::verbose)
+/// connections
+macro_rules! track {
+    (@read $db:ident, $action:expr, $buf:ident) => {{
+        tracking::record_reads($db, $action, $buf.as_slice());
+        verbose::count_keys($db, $action, $buf.len(), false);
+        None
+    }};
+    (@write $db:ident, $action:expr, $buf:ident) => {{
+        verbose::count_keys($db, $action, $buf.len(), true);
+        Some(explain::action_keys($action, $buf.as_slice()))
+    }};
+    ($db:ident, $action:expr, $buf:ident) => {
+        None
+    };
+}
+
+/// Prepares to mirror `@write` actions to the shadow of the current table, if it has one
+macro_rules! mirror {
+    (@write $db:ident, $con:ident, $action:expr, $buf:ident) => {
+        shadow::prepare($db, &*$con, $action, $buf.as_slice())
+    };
+    ($(@$guard:ident)? $db:ident, $con:ident, $action:expr, $buf:ident) => {
+        None::<shadow::Prepared>
+    };
+}
+
+macro_rules! table_guard {
+    (read, $db:ident) => {
+        None::<&'static [u8]>
+    };
+    (write, $db:ident) => {
+        guard::write_table($db).await
+    };
+}
+
+/// Checks the values that `@write` actions are about to write against the schema of the
+/// current table, if it has one
+macro_rules! schema_guard {
+    (read, $db:ident, $action:expr, $buf:ident) => {
+        None::<Vec<u8>>
+    };
+    (write, $db:ident, $action:expr, $buf:ident) => {
+        schema::check($db, $action, $buf.as_slice())
+    };
+}
+
+mod guard {
+    //! Guards that check if an action can be run in the current server mode. Each guard
+    //! returns the error response to be written if the action is disallowed
+    use crate::corestore::Corestore;
+    use crate::protocol::responses;
+    use crate::registry::{self, ServerMode};
+    use crate::services::throttle;
+
+    /// The actions that aren't marked with `@read` or `@write`, but can still be run on a
+    /// pinned connection
+    const PINNED_ACTIONS: [&[u8]; 9] = [
+        b"HEYA", b"HELLO", b"AUTH", b"WHOAMI", b"INSPECT", b"BATCH", b"PIN", b"UNPIN", b"VERBOSE",
+    ];
+
+    /// Guard for actions that read data
+    pub fn read() -> Option<&'static [u8]> {
+        match registry::get_mode() {
+            ServerMode::Maintenance => Some(responses::groups::MAINTENANCE_MODE),
+            _ => None,
+        }
+    }
+    /// Guard for actions that mutate data
+    pub fn write() -> Option<&'static [u8]> {
+        match registry::get_mode() {
+            ServerMode::Normal => None,
+            ServerMode::ReadOnly => Some(responses::groups::READ_ONLY_MODE),
+            ServerMode::Maintenance => Some(responses::groups::MAINTENANCE_MODE),
+        }
+    }
+    /// Guard for actions on a [pinned](super::pin) connection
+    pub fn pinned(handle: &Corestore) -> Option<&'static [u8]> {
+        if handle.is_pinned() {
+            Some(responses::groups::PINNED)
+        } else {
+            None
+        }
+    }
+    /// Guard for the actions that aren't marked with `@read` or `@write`: only the ones that
+    /// don't write anything can be run on a [pinned](super::pin) connection
+    pub fn pinned_action(handle: &Corestore, action: &[u8]) -> Option<&'static [u8]> {
+        if PINNED_ACTIONS.contains(&action) {
+            None
+        } else {
+            pinned(handle)
+        }
+    }
+    /// Guard for writes to the current table: virtual tables can't be written to, and a
+    /// write to a throttled table is accounted for (waiting for its next window if it's
+    /// in delay mode). Writes to a keyspace that can't be persisted are rejected (see
+    /// [`stall`](crate::registry::stall)), as are all writes while the server is under
+    /// [memory pressure](crate::registry::pressure). The write is also counted against the
+    /// table for the adaptive flush policy
+    pub async fn write_table(handle: &Corestore) -> Option<&'static [u8]> {
+        if registry::pressure::rejects_writes() {
+            return Some(responses::groups::MEMORY_PRESSURE);
+        }
+        match handle.get_ctable() {
+            Some(tbl) if tbl.is_virtual() => return Some(responses::groups::PROTECTED_OBJECT),
+            Some(tbl) => {
+                let stalled = handle
+                    .get_cks()
+                    .and_then(|ks| registry::stall::response(ks.stall()));
+                if stalled.is_some() {
+                    return stalled;
+                }
+                tbl.record_write()
+            }
+            None => {}
+        }
+        if !throttle::is_enabled() {
+            return None;
+        }
+        match handle
+            .ctable_name()
+            .and_then(|t| throttle::get_throttle(&t))
+        {
+            Some(throttle) if !throttle.acquire().await => Some(responses::groups::THROTTLED),
+            _ => None,
+        }
+    }
+}
+
 macro_rules! swap_entity {
     ($con:expr, $handle:expr, $entity:expr) => {
         match parser::get_query_entity(&$entity) {
@@ -91,11 +412,61 @@ macro_rules! swap_entity {
 }
 
 /// Execute a simple(*) query
+///
+/// Actions marked with `@read` or `@write` are checked against the current
+/// [`ServerMode`](crate::registry::ServerMode) before they are run, and actions marked
+/// with `@write` also count against the write rate of the current table, if it has one
+/// (see [`throttle`](crate::services::throttle)). Actions marked with `@write` are
+/// rejected if the current table is a [virtual table](crate::admin::vtables), or if the
+/// values that they write don't match the table's [schema](crate::services::schema)
+///
+/// If any users are configured, then the connection has to `AUTH` before it can run
+/// anything else (see [`registry::auth`](crate::registry::auth))
+///
+/// Action names are case-insensitive and can also be one of the aliases in the
+/// [registry](self::commands). Queries for other actions are run by the
+/// [custom action](self::plugins) with that name, if one is registered
 pub async fn execute_simple<T, Strm>(
     db: &mut Corestore,
     con: &mut T,
     buf: Element,
 ) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    let buf = match buf {
+        Element::AnyArray(a) => a,
+        _ => return con.write_response(responses::groups::WRONGTYPE_ERR).await,
+    };
+    let mut buf = buf.into_iter();
+    simple_actions!(gen_constants_and_matches!(con, buf, db,));
+    Ok(())
+}
+
+/// Execute a simple query like [`execute_simple`], behind a box so that a
+/// [batch](self::batch) can run its actions
+fn execute_boxed<'a, T, Strm>(
+    db: &'a mut Corestore,
+    con: &'a mut T,
+    buf: Element,
+) -> Pin<Box<dyn Future<Output = std::io::Result<()>> + Send + 'a>>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync + 'a,
+{
+    Box::pin(execute_simple(db, con, buf))
+}
+
+/// Execute a simple(*) query on an admin-only connection
+///
+/// Only administrative actions can be run; all other actions will return an
+/// [`ADMIN_ONLY`](responses::groups::ADMIN_ONLY) error
+pub async fn execute_simple_admin<T, Strm>(
+    db: &mut Corestore,
+    con: &mut T,
+    buf: Element,
+) -> std::io::Result<()>
 where
     T: ProtocolConnectionExt<Strm>,
     Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
@@ -106,31 +477,13 @@ where
     };
     let mut buf = buf.into_iter();
     gen_constants_and_matches!(
-        con, buf, db,
-        GET => actions::get::get,
-        SET => actions::set::set,
-        UPDATE => actions::update::update,
-        DEL => actions::del::del,
-        HEYA => actions::heya::heya,
-        EXISTS => actions::exists::exists,
-        MSET => actions::mset::mset,
-        MGET => actions::mget::mget,
-        MUPDATE => actions::mupdate::mupdate,
-        SSET => actions::strong::sset,
-        SDEL => actions::strong::sdel,
-        SUPDATE => actions::strong::supdate,
-        DBSIZE => actions::dbsize::dbsize,
-        FLUSHDB => actions::flushdb::flushdb,
-        USET => actions::uset::uset,
-        KEYLEN => actions::keylen::keylen,
+        con, buf, db, @else responses::groups::ADMIN_ONLY,
+        HELLO => self::hello,
+        AUTH => self::auth,
         MKSNAP => admin::mksnap::mksnap,
-        LSKEYS => actions::lskeys::lskeys,
-        POP => actions::pop::pop,
-        CREATE => ddl::create,
-        DROP => ddl::ddl_drop,
-        USE => self::entity_swap,
         INSPECT => inspect::inspect,
-        MPOP => actions::mpop::mpop
+        SYS => admin::sys::sys_admin,
+        WHOAMI(Exact(0)) => self::whoami
     );
     Ok(())
 }
@@ -147,3 +500,144 @@ action! {
         Ok(())
     }
 }
+
+const CREATE: &[u8] = "CREATE".as_bytes();
+const RESUME: &[u8] = "RESUME".as_bytes();
+
+action! {
+    /// Handle session queries:
+    /// - `SESSION CREATE` starts a session for the current user and entity and returns its
+    /// token
+    /// - `SESSION RESUME <token>` switches to the user and the entity of the session with the
+    /// given token and attaches the session to this connection (this can be run before
+    /// `AUTH`)
+    fn session(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let mut subaction = match act.next() {
+            Some(subaction) => subaction.to_vec(),
+            None => aerr!(con, aerr),
+        };
+        subaction.make_ascii_uppercase();
+        match subaction.as_ref() {
+            CREATE => {
+                err_if_len_is!(act, con, not 0);
+                if auth::is_enabled() && handle.user_name().is_none() {
+                    return conwrite!(con, responses::groups::AUTH_REQUIRED);
+                }
+                let token = Bytes::copy_from_slice(handle.create_session().as_bytes());
+                conwrite!(con, BytesWrapper(token))?;
+            }
+            RESUME => {
+                err_if_len_is!(act, con, not 1);
+                let token = unsafe {
+                    // SAFETY: Already checked len
+                    act.next().unsafe_unwrap()
+                };
+                match handle.resume_session(&token) {
+                    Ok(()) => conwrite!(con, responses::groups::OKAY)?,
+                    Err(ResumeError::Unknown) => {
+                        conwrite!(con, responses::groups::UNKNOWN_SESSION)?
+                    }
+                    Err(ResumeError::Entity(DdlError::ObjectNotFound)) => {
+                        conwrite!(con, responses::groups::CONTAINER_NOT_FOUND)?
+                    }
+                    Err(ResumeError::TooManyConnections) => {
+                        conwrite!(con, responses::groups::TOO_MANY_CONNECTIONS)?
+                    }
+                    Err(ResumeError::Entity(_)) => unsafe {
+                        // we know Corestore::swap_entity doesn't return anything else
+                        impossible!()
+                    },
+                }
+            }
+            _ => conwrite!(con, responses::groups::UNKNOWN_SESSION_QUERY)?,
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Handle `AUTH <user> <token>`, which authenticates this connection as the given user
+    fn auth(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        err_if_len_is!(act, con, not 2);
+        let (user, token) = unsafe {
+            // SAFETY: Already checked len
+            (act.next().unsafe_unwrap(), act.next().unsafe_unwrap())
+        };
+        let ret = match handle.authenticate(&user, &token).await {
+            Ok(()) => responses::groups::OKAY,
+            Err(AuthError::BadCredentials) => responses::groups::BAD_CREDENTIALS,
+            Err(AuthError::TooManyConnections) => responses::groups::TOO_MANY_CONNECTIONS,
+        };
+        conwrite!(con, ret)?;
+        Ok(())
+    }
+}
+
+action! {
+    /// Handle `HELLO <version> [<capability> ...]`, which agrees on a protocol version and
+    /// capabilities with the client (see [`protocol::hello`](crate::protocol::hello)). A
+    /// bare `HELLO` returns what was agreed on, or what the server offers if nothing was
+    fn hello(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let handshake = match act.next() {
+            Some(version) => {
+                let version = match std::str::from_utf8(&version).ok().and_then(|v| v.parse().ok()) {
+                    Some(version) => version,
+                    None => return conwrite!(con, responses::groups::UNSUPPORTED_VERSION),
+                };
+                match Handshake::negotiate(version, act.as_slice().iter().map(|cap| cap.as_ref())) {
+                    Some(handshake) => {
+                        handle.set_handshake(handshake);
+                        handshake
+                    }
+                    None => return conwrite!(con, responses::groups::UNSUPPORTED_VERSION),
+                }
+            }
+            None => handle.handshake().unwrap_or_else(Handshake::offer),
+        };
+        let capabilities = handshake.capabilities.names();
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', capabilities.len() + 1)
+        }
+        .await?;
+        writer.write_element(handshake.version.to_string()).await?;
+        for capability in capabilities {
+            writer.write_element(capability).await?;
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Handle `WHOAMI`, which returns the current keyspace and table, the user that this
+    /// connection has authenticated as and the protocol version and capabilities that were
+    /// agreed on with `HELLO`, as alternating field names and values (a value is null if it
+    /// isn't set). Connection pools can use this to check connections that are handed out
+    fn whoami(handle: &Corestore, con: &mut T, _act: ActionIter) {
+        let (keyspace, table) = handle.current_entity();
+        let handshake = handle.handshake();
+        let fields = [
+            ("keyspace", keyspace),
+            ("table", table),
+            ("user", handle.user_name().map(str::to_owned)),
+            ("version", handshake.map(|hs| hs.version.to_string())),
+            (
+                "capabilities",
+                handshake.map(|hs| hs.capabilities.names().join(",")),
+            ),
+        ];
+        let mut writer = unsafe {
+            // SAFETY: all the elements are strings
+            TypedArrayWriter::new(con, b'+', fields.len() * 2)
+        }
+        .await?;
+        for (name, value) in fields.iter() {
+            writer.write_element(name).await?;
+            match value {
+                Some(value) => writer.write_element(value).await?,
+                None => writer.write_null().await?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -24,6 +24,7 @@
  *
 */
 
+use crate::admin::vtables;
 use crate::corestore::lazy::Lazy;
 use crate::corestore::{BorrowedEntityGroup, OwnedEntityGroup};
 use crate::kvengine::encoding;
@@ -37,6 +38,8 @@ use regex::Regex;
 
 const KEYMAP: &[u8] = "keymap".as_bytes();
 const BINSTR: &[u8] = "binstr".as_bytes();
+/// an explicit alias for `binstr`
+const BLOB: &[u8] = "blob".as_bytes();
 const STR: &[u8] = "str".as_bytes();
 
 pub(super) static VALID_CONTAINER_NAME: Lazy<Regex, fn() -> Regex> =
@@ -112,10 +115,10 @@ pub(super) fn parse_table_args(
     let key_ty = key_ty.as_bytes();
     let val_ty = val_ty.as_bytes();
     let model_code: u8 = match (key_ty, val_ty) {
-        (BINSTR, BINSTR) => 0,
-        (BINSTR, STR) => 1,
+        (BINSTR | BLOB, BINSTR | BLOB) => 0,
+        (BINSTR | BLOB, STR) => 1,
         (STR, STR) => 2,
-        (STR, BINSTR) => 3,
+        (STR, BINSTR | BLOB) => 3,
         _ => return Err(responses::groups::UNKNOWN_DATA_TYPE),
     };
     Ok((
@@ -158,7 +161,9 @@ pub fn get_query_entity<'a>(input: &'a [u8]) -> Result<BorrowedEntityGroup, &'st
                     || !VALID_CONTAINER_NAME.is_match(str::from_utf8_unchecked(tblret)),
             ) {
                 Err(responses::groups::BAD_CONTAINER_NAME)
-            } else if compiler::unlikely(ksret.eq(&"system".as_bytes())) {
+            } else if compiler::unlikely(ksret.eq(&"system".as_bytes()) && !vtables::exists(tblret))
+            {
+                // only the virtual tables in the system keyspace can be used
                 Err(responses::groups::PROTECTED_OBJECT)
             } else {
                 Ok(BorrowedEntityGroup::from((Some(*ksret), Some(*tblret))))
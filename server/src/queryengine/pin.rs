@@ -0,0 +1,104 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Pinned connections
+//!
+//! A connection can pin itself to a frozen view of the data, so that everything that it
+//! reads comes from one point in time while the live data keeps changing:
+//! ```text
+//! PIN               # pin to a copy of the data as it is right now
+//! PIN <snapshot>    # pin to the data in a snapshot (or remote snapshot)
+//! UNPIN             # switch back to the live data
+//! ```
+//! The frozen view is held in memory for as long as the connection stays pinned, although
+//! the values themselves are shared with the live data. Actions that write data (and the
+//! ones that work on the server rather than the data, like `SYS`) are rejected with
+//! `err-pinned` on a pinned connection
+
+use crate::admin::{mksnap, sys};
+use crate::dbnet::connection::prelude::*;
+use crate::kvengine::encoding;
+use crate::storage::unflush;
+use core::str;
+
+action! {
+    /// Pin the connection to a copy of the current data, or to the snapshot with the
+    /// given name. A connection that is already pinned is simply re-pinned
+    fn pin(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let frozen = match act.next() {
+            Some(name) => {
+                if !encoding::is_utf8(&name) {
+                    return conwrite!(con, groups::ENCODING_ERROR);
+                }
+                let name = unsafe {
+                    // SAFETY: We have already checked for UTF-8 validity
+                    str::from_utf8_unchecked(&name)
+                };
+                if mksnap::is_illegal_snapshot_name(name) {
+                    return conwrite!(con, groups::SNAPSHOT_ILLEGAL_NAME);
+                }
+                let dir = match sys::find_snapshot(name) {
+                    Some(dir) => dir,
+                    None => return conwrite!(con, groups::SNAPSHOT_NOT_FOUND),
+                };
+                let read = tokio::task::spawn_blocking(move || unflush::read_tree(&dir))
+                    .await
+                    .expect("snapshot reading thread panicked");
+                match read {
+                    Ok(store) => store,
+                    Err(e) => {
+                        log::error!("Failed to read snapshot '{}' to pin to: {}", name, e);
+                        return conwrite!(con, groups::SERVER_ERR);
+                    }
+                }
+            }
+            None => {
+                let live = handle.clone_live_store();
+                tokio::task::spawn_blocking(move || live.duplicate())
+                    .await
+                    .expect("store copying thread panicked")
+            }
+        };
+        match handle.pin(frozen) {
+            Ok(()) => conwrite!(con, groups::OKAY)?,
+            // the current keyspace or table isn't in the snapshot
+            Err(_) => conwrite!(con, groups::CONTAINER_NOT_FOUND)?,
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Switch the connection back to the live data
+    fn unpin(handle: &mut Corestore, con: &mut T, _act: ActionIter) {
+        if handle.unpin() {
+            conwrite!(con, groups::OKAY)?;
+        } else {
+            conwrite!(con, groups::NOT_PINNED)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Custom actions
+//!
+//! Forks and processes that embed Skytable can add their own actions without touching the
+//! dispatcher: implement [`Action`] and [`register`] it before any queries are run. Forks
+//! can also add a constructor to [`STATIC`], which `skyd` registers when it starts
+//!
+//! A custom action is only looked up if the query isn't for a built-in action (or one of
+//! its aliases), so it can't shadow one. It goes through the same checks as the built-in
+//! actions: it can be denied, needs an authenticated connection if auth is enabled, has its
+//! arity checked (if it declares one) and is rejected in maintenance mode (and in read-only
+//! mode, or for virtual and throttled tables, if it writes). The values that it sets are
+//! checked against the [schema](crate::services::schema) of the table, if it has one
+
+// the API is for forks and embedding processes, so skyd itself doesn't use most of it
+#![allow(dead_code)]
+
+use super::arity::{self, Arity};
+use super::{commands, guard, COMMANDS};
+use crate::corestore::lazy::Lazy;
+use crate::corestore::{Corestore, Data};
+use crate::dbnet::connection::prelude::*;
+use crate::resp::builder::ResponseElement;
+use crate::services::schema;
+use bytes::Bytes;
+use parking_lot::RwLock;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// The custom actions that `skyd` registers when it starts
+pub static STATIC: &[fn() -> Box<dyn Action>] = &[];
+
+type PluginList = Vec<Arc<dyn Action>>;
+
+static PLUGINS: Lazy<RwLock<PluginList>, fn() -> RwLock<PluginList>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// A custom action
+pub trait Action: Send + Sync + 'static {
+    /// The name of the action, in uppercase
+    fn name(&self) -> &'static str;
+    /// The number of arguments that the action takes. If this is `None`, the action has
+    /// to check its arguments itself
+    fn arity(&self) -> Option<Arity> {
+        None
+    }
+    /// Returns true if the action mutates data
+    fn writes(&self) -> bool {
+        false
+    }
+    /// Run the action with the given arguments
+    fn run(&self, ctx: &Context<'_>, args: &[Bytes]) -> Result<Response, Error>;
+}
+
+#[derive(Debug, PartialEq)]
+/// What a custom action returns
+pub enum Response {
+    /// The `Okay` response code
+    Okay,
+    /// A (possibly nested) element
+    Element(ResponseElement),
+}
+
+#[derive(Debug, PartialEq)]
+/// An error returned by a custom action
+pub struct Error(Cow<'static, [u8]>);
+
+impl Error {
+    /// The action error, for arguments that the action can't make sense of
+    pub const fn action() -> Self {
+        Self(Cow::Borrowed(groups::ACTION_ERR))
+    }
+    /// An error string, like `err-unknown-thing`
+    pub fn message(message: &str) -> Self {
+        let mut ret = format!("!{}\n", message.len()).into_bytes();
+        ret.extend_from_slice(message.as_bytes());
+        ret.push(b'\n');
+        Self(Cow::Owned(ret))
+    }
+    const fn code(code: &'static [u8]) -> Self {
+        Self(Cow::Borrowed(code))
+    }
+}
+
+/// The view of the server that a custom action gets: the current table (which is a
+/// key/value table if the action uses it) and the user that the connection has
+/// authenticated as
+pub struct Context<'a> {
+    handle: &'a Corestore,
+}
+
+impl<'a> Context<'a> {
+    /// Returns the name of the user that the connection has authenticated as, if any
+    pub fn user(&self) -> Option<&str> {
+        self.handle.user_name()
+    }
+    /// Returns the value of a key in the current table, if it exists
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>, Error> {
+        self.handle
+            .get_kvstore()
+            .map_err(|_| Error::code(groups::WRONG_MODEL))?
+            .get_cloned(key)
+            .map(|val| val.map(Data::into_inner))
+            .map_err(|_| Error::code(groups::ENCODING_ERROR))
+    }
+    /// Set a key in the current table, replacing its value if it exists. Values that don't
+    /// match the table's schema aren't set
+    pub fn set(&self, key: Bytes, value: Bytes) -> Result<(), Error> {
+        if !registry::state_okay() {
+            return Err(Error::code(groups::SERVER_ERR));
+        }
+        if let Some(table_schema) = schema::get(self.handle) {
+            let violations = schema::validate(&table_schema, &value);
+            if !violations.is_empty() {
+                return Err(Error(Cow::Owned(schema::error(self.handle, violations))));
+            }
+        }
+        self.handle
+            .get_kvstore()
+            .map_err(|_| Error::code(groups::WRONG_MODEL))?
+            .upsert(Data::from(key), Data::from(value))
+            .map_err(|_| Error::code(groups::ENCODING_ERROR))
+    }
+    /// Remove a key from the current table, returning true if it existed
+    pub fn remove(&self, key: &[u8]) -> Result<bool, Error> {
+        if !registry::state_okay() {
+            return Err(Error::code(groups::SERVER_ERR));
+        }
+        self.handle
+            .get_kvstore()
+            .map_err(|_| Error::code(groups::WRONG_MODEL))?
+            .remove(key)
+            .map_err(|_| Error::code(groups::ENCODING_ERROR))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// The reasons a custom action can't be registered
+pub enum RegisterError {
+    /// The name isn't made up of uppercase letters, digits and underscores
+    BadName,
+    /// The name is taken by a built-in action or an alias
+    Builtin,
+    /// A custom action with the same name is already registered
+    AlreadyRegistered,
+}
+
+/// Register a custom action
+pub fn register(action: Box<dyn Action>) -> Result<(), RegisterError> {
+    let name = action.name().as_bytes();
+    let valid_name = !name.is_empty()
+        && name
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || *b == b'_');
+    if !valid_name {
+        return Err(RegisterError::BadName);
+    }
+    if COMMANDS.iter().any(|cmd| cmd.name.as_bytes() == name)
+        || commands::resolve_alias(name).is_some()
+    {
+        return Err(RegisterError::Builtin);
+    }
+    let mut plugins = PLUGINS.write();
+    if plugins
+        .iter()
+        .any(|plugin| plugin.name().as_bytes() == name)
+    {
+        return Err(RegisterError::AlreadyRegistered);
+    }
+    plugins.push(Arc::from(action));
+    Ok(())
+}
+
+/// Register the custom actions in [`STATIC`]
+pub fn register_static() {
+    for ctor in STATIC {
+        let action = ctor();
+        let name = action.name();
+        if let Err(e) = register(action) {
+            log::error!("Failed to register custom action `{}`: {:?}", name, e);
+        }
+    }
+}
+
+/// Returns the custom action with the given name (uppercased), if one is registered
+pub fn get(name: &[u8]) -> Option<Arc<dyn Action>> {
+    PLUGINS
+        .read()
+        .iter()
+        .find(|plugin| plugin.name().as_bytes() == name)
+        .cloned()
+}
+
+/// Returns the registered custom actions the way that the [registry](super::commands)
+/// describes built-in actions
+pub fn commands() -> Vec<commands::Command> {
+    PLUGINS
+        .read()
+        .iter()
+        .map(|plugin| commands::Command {
+            name: plugin.name(),
+            arity: plugin.arity(),
+            guard: Some(if plugin.writes() { "write" } else { "read" }),
+        })
+        .collect()
+}
+
+/// Run a custom action after checking its arity and the guards for it
+pub async fn run<T, Strm>(
+    action: Arc<dyn Action>,
+    handle: &Corestore,
+    con: &mut T,
+    act: ActionIter,
+) -> std::io::Result<()>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    if let Some(arity) = action.arity() {
+        if !arity.accepts(act.len()) {
+            return conwrite!(con, arity::error(action.name().as_bytes(), handle));
+        }
+    }
+    let guard = if action.writes() {
+        guard::write()
+    } else {
+        guard::read()
+    };
+    if let Some(e) = guard {
+        return conwrite!(con, e);
+    }
+    if action.writes() {
+        if let Some(e) = guard::write_table(handle).await {
+            return conwrite!(con, e);
+        }
+    }
+    let ctx = Context { handle };
+    match action.run(&ctx, act.as_slice()) {
+        Ok(Response::Okay) => conwrite!(con, groups::OKAY),
+        Ok(Response::Element(element)) => conwrite!(con, element),
+        Err(Error(e)) => conwrite!(con, e.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo(&'static str);
+
+    impl Action for Echo {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+        fn arity(&self) -> Option<Arity> {
+            Some(Arity::Exact(1))
+        }
+        fn run(&self, _ctx: &Context<'_>, args: &[Bytes]) -> Result<Response, Error> {
+            Ok(Response::Element(ResponseElement::String(args[0].clone())))
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        assert_eq!(register(Box::new(Echo("PLUGINECHO"))), Ok(()));
+        assert!(get(b"PLUGINECHO").is_some());
+        assert_eq!(
+            register(Box::new(Echo("PLUGINECHO"))),
+            Err(RegisterError::AlreadyRegistered)
+        );
+        assert!(commands().iter().any(|cmd| cmd.name == "PLUGINECHO"));
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        assert_eq!(register(Box::new(Echo("GET"))), Err(RegisterError::Builtin));
+        assert_eq!(
+            register(Box::new(Echo("DELETE"))),
+            Err(RegisterError::Builtin)
+        );
+        assert_eq!(
+            register(Box::new(Echo("echo"))),
+            Err(RegisterError::BadName)
+        );
+        assert!(get(b"GET").is_none());
+    }
+}
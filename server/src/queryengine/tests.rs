@@ -105,6 +105,25 @@ mod parser_ddl_tests {
         assert_eq!(mcode, 3);
     }
 
+    #[test]
+    fn test_table_blob_alias() {
+        let mut it = vec![byt!("mytbl"), byt!("keymap(blob,blob)")].into_iter();
+        let (_, mcode) = parse_table_args(&mut it).unwrap();
+        assert_eq!(mcode, 0);
+
+        let mut it = vec![byt!("mytbl"), byt!("keymap(blob, str)")].into_iter();
+        let (_, mcode) = parse_table_args(&mut it).unwrap();
+        assert_eq!(mcode, 1);
+
+        let mut it = vec![byt!("mytbl"), byt!("keymap(str, blob)")].into_iter();
+        let (_, mcode) = parse_table_args(&mut it).unwrap();
+        assert_eq!(mcode, 3);
+
+        let mut it = vec![byt!("mytbl"), byt!("keymap(binstr, blob)")].into_iter();
+        let (_, mcode) = parse_table_args(&mut it).unwrap();
+        assert_eq!(mcode, 0);
+    }
+
     #[test]
     fn test_table_badty() {
         let mut it = vec![byt!("mycooltbl"), byt!("keymap(wth, str)")].into_iter();
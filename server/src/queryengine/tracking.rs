@@ -0,0 +1,95 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Client-side caching
+//!
+//! A connection that runs `TRACKING ON` has the keys that it looks up remembered, and gets
+//! an invalidation pushed to it once any of them changes (see
+//! [`tracking`](crate::registry::tracking)), so that it can cache what it reads. Since
+//! invalidations arrive in between responses, the connection must have agreed on the
+//! `invalidations` capability with `HELLO` first.
+//!
+//! The keys are worked out by the dispatcher: the keys that an `@read` action looks up are
+//! recorded before the action runs, and the keys that an `@write` action changes are
+//! invalidated once it has run. Writes that don't say which keys they change (like `FLUSHDB`)
+//! invalidate every key that was read
+
+use super::explain;
+use crate::corestore::Corestore;
+use crate::dbnet::connection::prelude::*;
+use crate::protocol::hello::Capabilities;
+use crate::registry::tracking;
+use bytes::Bytes;
+
+const ON: &[u8] = "ON".as_bytes();
+const OFF: &[u8] = "OFF".as_bytes();
+
+action! {
+    /// Handle `TRACKING ON` and `TRACKING OFF`
+    fn tracking(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let toggle = unsafe {
+            // SAFETY: The arity is checked by the dispatcher
+            act.next().unsafe_unwrap()
+        };
+        if toggle.eq_ignore_ascii_case(ON) {
+            let agreed = handle
+                .handshake()
+                .map_or(false, |hs| hs.capabilities.contains(Capabilities::INVALIDATIONS));
+            if !agreed {
+                return conwrite!(con, groups::TRACKING_NOT_NEGOTIATED);
+            }
+            handle.start_tracking();
+        } else if toggle.eq_ignore_ascii_case(OFF) {
+            handle.stop_tracking();
+        } else {
+            return conwrite!(con, groups::ACTION_ERR);
+        }
+        conwrite!(con, groups::OKAY)
+    }
+}
+
+/// Record the keys that a lookup is about to read, if the connection tracks
+pub fn record_reads(handle: &Corestore, action: &[u8], args: &[Bytes]) {
+    if let Some(tracker) = handle.tracker() {
+        if let (Some(keys), Some(table)) =
+            (explain::action_keys(action, args), handle.ctable_name())
+        {
+            tracker.record(&table, keys);
+        }
+    }
+}
+
+/// Invalidate what a write has just changed: the given keys of the current table, or every
+/// key if the write doesn't say which keys it changes
+pub fn invalidate_written(handle: &Corestore, keys: Option<Vec<Bytes>>) {
+    if !tracking::is_active() {
+        return;
+    }
+    match (keys, handle.ctable_name()) {
+        (Some(keys), Some(table)) => tracking::invalidate(&table, keys),
+        _ => tracking::invalidate_all(),
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Execution metadata
+//!
+//! A connection that runs `VERBOSE ON` is sent a metadata frame right after every response,
+//! so that clients can tell where the time of a query went: how long the server took to run
+//! it, how many keys it looked up and changed, and how many bytes it read and wrote (see
+//! [`Metadata::encode`]). Since the frames arrive along with the responses, the connection
+//! must have agreed on the `metadata` capability with `HELLO` first.
+//!
+//! The keys are counted by the dispatcher from the arguments of the lookups, so only the keys
+//! that a query names are counted: writes that don't name their keys (like `FLUSHDB`) and
+//! actions that walk a table (like `LSKEYS`) don't add to the counts
+
+use super::explain;
+use crate::corestore::Corestore;
+use crate::dbnet::connection::prelude::*;
+use crate::protocol::hello::Capabilities;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+const ON: &[u8] = "ON".as_bytes();
+const OFF: &[u8] = "OFF".as_bytes();
+
+action! {
+    /// Handle `VERBOSE ON` and `VERBOSE OFF`
+    fn verbose(handle: &mut Corestore, con: &mut T, mut act: ActionIter) {
+        let toggle = unsafe {
+            // SAFETY: The arity is checked by the dispatcher
+            act.next().unsafe_unwrap()
+        };
+        if toggle.eq_ignore_ascii_case(ON) {
+            let agreed = handle
+                .handshake()
+                .map_or(false, |hs| hs.capabilities.contains(Capabilities::METADATA));
+            if !agreed {
+                return conwrite!(con, groups::VERBOSE_NOT_NEGOTIATED);
+            }
+            handle.start_verbose();
+        } else if toggle.eq_ignore_ascii_case(OFF) {
+            handle.stop_verbose();
+        } else {
+            return conwrite!(con, groups::ACTION_ERR);
+        }
+        conwrite!(con, groups::OKAY)
+    }
+}
+
+#[derive(Debug, Default)]
+/// The keys that the query that a connection is running has looked up and changed so far
+pub struct KeyCounts {
+    examined: AtomicU64,
+    modified: AtomicU64,
+}
+
+impl KeyCounts {
+    /// Returns the keys that were looked up and changed (in that order), starting over
+    pub fn take(&self) -> (u64, u64) {
+        (
+            self.examined.swap(0, Ordering::Relaxed),
+            self.modified.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Count the keys that an action is about to look up (or change, if `write` is set), if the
+/// connection is verbose
+pub fn count_keys(handle: &Corestore, action: &[u8], args: usize, write: bool) {
+    if let Some(counts) = handle.key_counts() {
+        if let Some(keys) = explain::key_count(action, args) {
+            let counter = if write {
+                &counts.modified
+            } else {
+                &counts.examined
+            };
+            counter.fetch_add(keys as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// What it took to run a query
+pub struct Metadata {
+    /// the time that the server took to run the query
+    pub duration_us: u64,
+    pub keys_examined: u64,
+    pub keys_modified: u64,
+    /// the size of the query
+    pub bytes_read: u64,
+    /// the size of the response
+    pub bytes_written: u64,
+}
+
+impl Metadata {
+    /// Serialize this metadata into a frame: `>2\n` followed by a typed array of strings
+    /// with the name of every field followed by its value
+    pub fn encode(&self) -> Vec<u8> {
+        let fields = [
+            ("duration_us", self.duration_us),
+            ("keys_examined", self.keys_examined),
+            ("keys_modified", self.keys_modified),
+            ("bytes_read", self.bytes_read),
+            ("bytes_written", self.bytes_written),
+        ];
+        let mut frame = format!(">2\n@+{}\n", fields.len() * 2).into_bytes();
+        for (name, value) in fields.iter() {
+            for element in [name.to_string(), value.to_string()].iter() {
+                frame.extend_from_slice(element.len().to_string().as_bytes());
+                frame.push(b'\n');
+                frame.extend_from_slice(element.as_bytes());
+                frame.push(b'\n');
+            }
+        }
+        frame
+    }
+}
+
+#[test]
+fn test_metadata_encode() {
+    let metadata = Metadata {
+        duration_us: 42,
+        keys_examined: 3,
+        keys_modified: 0,
+        bytes_read: 120,
+        bytes_written: 7,
+    };
+    assert_eq!(
+        metadata.encode(),
+        b">2\n@+10\n11\nduration_us\n2\n42\n13\nkeys_examined\n1\n3\n13\nkeys_modified\n1\n0\n10\nbytes_read\n3\n120\n13\nbytes_written\n1\n7\n".to_vec()
+    );
+}
@@ -0,0 +1,234 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Admission control
+//!
+//! With an `[admission]` section, the server sheds load once it's overloaded, so that the
+//! latency of the traffic that it keeps serving stays bounded instead of every query getting
+//! slower. The server is overloaded while either:
+//! - the queue (the queries that have been read off connections and haven't finished yet)
+//! holds `maxqueue` queries or more, or
+//! - the worker threads run tasks `maxlag` milliseconds late or more (as last measured by
+//! the [latency monitor](crate::services::latency))
+//!
+//! Every connection has a [`Priority`] class, which is taken from the user that it
+//! authenticated as or else from the listener that accepted it. Queries are rejected with
+//! `err-busy` before they're run:
+//! - from [`Priority::Batch`] connections as soon as the server is contended (past half of
+//! either limit), so that bulk work never starves interactive traffic
+//! - from [`Priority::Application`] connections while the server is overloaded
+//!
+//! [`Priority::Admin`] connections (which includes all connections on admin listeners) are
+//! never shed, so that the overload can be looked into
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// The most queries that can be queued (`0` if there's no limit)
+static MAX_QUEUE: AtomicU64 = AtomicU64::new(0);
+/// The most that the worker threads can lag behind in milliseconds (`0` if there's no limit)
+static MAX_LAG: AtomicU64 = AtomicU64::new(0);
+/// The number of queries that are queued
+static QUEUE: AtomicU64 = AtomicU64::new(0);
+/// How late the worker threads ran a task when they were last measured (in milliseconds)
+static LAG: AtomicU64 = AtomicU64::new(0);
+/// Whether the server was overloaded the last time that it was checked
+static OVERLOADED: AtomicBool = AtomicBool::new(false);
+/// The number of queries that were shed
+static SHED: AtomicU64 = AtomicU64::new(0);
+/// The number of queries from batch connections that were shed
+static SHED_BATCH: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// How important the queries of a connection are, from most to least important
+pub enum Priority {
+    /// Never shed
+    Admin,
+    /// Shed while the server is overloaded (the default)
+    Application,
+    /// Shed as soon as the server is contended
+    Batch,
+}
+
+impl Priority {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Admin => "admin",
+            Priority::Application => "application",
+            Priority::Batch => "batch",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// The limits beyond which the server is overloaded (`0` for no limit)
+pub struct AdmissionPolicy {
+    maxqueue: u64,
+    maxlag: u64,
+}
+
+impl AdmissionPolicy {
+    pub const fn new(maxqueue: u64, maxlag: u64) -> Self {
+        Self { maxqueue, maxlag }
+    }
+    /// Returns true if there's a limit at all
+    pub const fn has_limits(&self) -> bool {
+        self.maxqueue != 0 || self.maxlag != 0
+    }
+    /// Returns true if the server is overloaded with `queue` queries queued and the worker
+    /// threads lagging `lag` milliseconds behind
+    pub const fn is_overloaded(&self, queue: u64, lag: u64) -> bool {
+        (self.maxqueue != 0 && queue >= self.maxqueue) || (self.maxlag != 0 && lag >= self.maxlag)
+    }
+    /// Returns true if the server is contended (past half of either limit) with `queue`
+    /// queries queued and the worker threads lagging `lag` milliseconds behind
+    pub const fn is_contended(&self, queue: u64, lag: u64) -> bool {
+        (self.maxqueue != 0 && queue.saturating_mul(2) >= self.maxqueue)
+            || (self.maxlag != 0 && lag.saturating_mul(2) >= self.maxlag)
+    }
+}
+
+/// Set the admission policy (or `None` to never shed load)
+pub fn set_policy(policy: Option<AdmissionPolicy>) {
+    let policy = policy.unwrap_or_else(|| AdmissionPolicy::new(0, 0));
+    MAX_QUEUE.store(policy.maxqueue, Ordering::Release);
+    MAX_LAG.store(policy.maxlag, Ordering::Release);
+}
+
+/// Returns the admission policy
+pub fn get_policy() -> AdmissionPolicy {
+    AdmissionPolicy::new(
+        MAX_QUEUE.load(Ordering::Acquire),
+        MAX_LAG.load(Ordering::Acquire),
+    )
+}
+
+/// Record how late the worker threads ran a task
+pub fn record_lag(lag: Duration) {
+    LAG.store(lag.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// A query in the queue, which leaves it once this is dropped
+pub struct Queued(());
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        QUEUE.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Add a query to the queue until the returned guard is dropped
+pub fn enqueue() -> Queued {
+    QUEUE.fetch_add(1, Ordering::AcqRel);
+    Queued(())
+}
+
+/// Returns true if a query from a connection with the given priority has to be shed. The
+/// shed query is counted, and the server going in and out of overload is logged
+pub fn should_shed(priority: Priority) -> bool {
+    let policy = get_policy();
+    if priority == Priority::Admin || !policy.has_limits() {
+        return false;
+    }
+    let (queue, lag) = (QUEUE.load(Ordering::Acquire), LAG.load(Ordering::Relaxed));
+    let overloaded = policy.is_overloaded(queue, lag);
+    if OVERLOADED.swap(overloaded, Ordering::AcqRel) != overloaded {
+        if overloaded {
+            log::warn!(
+                "Server is overloaded ({} queries queued, {}ms lag); shedding low-priority queries",
+                queue,
+                lag
+            );
+        } else {
+            log::info!("Server is no longer overloaded; accepting all queries");
+        }
+    }
+    let shed = match priority {
+        Priority::Batch => overloaded || policy.is_contended(queue, lag),
+        _ => overloaded,
+    };
+    if shed {
+        SHED.fetch_add(1, Ordering::Relaxed);
+        if priority == Priority::Batch {
+            SHED_BATCH.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    shed
+}
+
+/// Returns the admission statistics as `(name, value)` pairs
+pub fn info() -> Vec<(String, String)> {
+    let policy = get_policy();
+    vec![
+        ("admission.maxqueue".to_owned(), policy.maxqueue.to_string()),
+        ("admission.maxlag".to_owned(), policy.maxlag.to_string()),
+        (
+            "admission.queue".to_owned(),
+            QUEUE.load(Ordering::Acquire).to_string(),
+        ),
+        (
+            "admission.lag".to_owned(),
+            LAG.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "admission.overloaded".to_owned(),
+            OVERLOADED.load(Ordering::Acquire).to_string(),
+        ),
+        (
+            "admission.shed".to_owned(),
+            SHED.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "admission.shedbatch".to_owned(),
+            SHED_BATCH.load(Ordering::Relaxed).to_string(),
+        ),
+    ]
+}
+
+#[test]
+fn test_is_overloaded() {
+    let policy = AdmissionPolicy::new(100, 50);
+    assert!(policy.has_limits());
+    assert!(!policy.is_overloaded(99, 49));
+    assert!(policy.is_overloaded(100, 0));
+    assert!(policy.is_overloaded(0, 50));
+    // a limit of `0` is no limit at all
+    let policy = AdmissionPolicy::new(0, 50);
+    assert!(!policy.is_overloaded(u64::MAX, 0));
+    assert!(!AdmissionPolicy::new(0, 0).has_limits());
+}
+
+#[test]
+fn test_is_contended() {
+    let policy = AdmissionPolicy::new(100, 50);
+    assert!(!policy.is_contended(49, 24));
+    assert!(policy.is_contended(50, 0));
+    assert!(policy.is_contended(0, 25));
+    // the server is contended before it's overloaded
+    assert!(policy.is_contended(60, 0) && !policy.is_overloaded(60, 0));
+    assert!(!AdmissionPolicy::new(0, 50).is_contended(u64::MAX, 0));
+}
@@ -0,0 +1,412 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Users
+//!
+//! Users are either declared as `[[user]]` entries (with a static token) in the config file
+//! or are added with `SYS USER ADD`, in which case they're kept with a hash of their
+//! password in the `system` keyspace and can be changed without a restart. Once any user
+//! exists, connections have to run `AUTH <user> <token|password>` before they can run
+//! anything other than the [`OPEN_ACTIONS`] (or resume a [session](crate::corestore::session)
+//! that was started by a connection that did). A user can be restricted to a set of keyspaces (say, the keyspaces of
+//! one application) in which case it can only see and use those keyspaces and can't run
+//! any of the server-wide actions in [`SERVER_ACTIONS`]. Users that aren't restricted
+//! can do everything. Restricted users can never access the `system` keyspace (which holds
+//! the password hashes)
+//!
+//! A user can also be limited to a number of concurrent connections and to a number of
+//! operations per second (across all of its connections), so that one tenant can't starve
+//! the others on a shared instance, and can be given a [priority class](Priority) that its
+//! connections are shed by when the server is overloaded
+
+use crate::corestore::lazy::Lazy;
+use crate::corestore::session;
+use crate::crypto;
+use crate::registry::admission::Priority;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use parking_lot::{Mutex, RwLock};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The actions that can be run before authenticating (`SESSION` can only resume a session
+/// before authenticating)
+pub const OPEN_ACTIONS: [&[u8]; 5] = [b"AUTH", b"HELLO", b"HEYA", b"WHOAMI", b"SESSION"];
+/// The actions that affect the whole server, and hence can only be run by users that
+/// aren't restricted to a set of keyspaces
+pub const SERVER_ACTIONS: [&[u8]; 2] = [b"MKSNAP", b"SYS"];
+
+type UserList = Vec<Arc<User>>;
+
+static USERS: Lazy<RwLock<UserList>, fn() -> RwLock<UserList>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// A password hash that is verified against when authenticating as a user that doesn't
+/// exist, so that the time taken doesn't tell which users exist
+static DUMMY_HASH: Lazy<String, fn() -> String> =
+    Lazy::new(|| crypto::hash_password(b"skytable-dummy-password"));
+
+#[derive(Debug, PartialEq)]
+/// Why authenticating failed
+pub enum AuthError {
+    /// The username or token is wrong
+    BadCredentials,
+    /// The user already has as many connections as it is allowed to have
+    TooManyConnections,
+}
+
+/// Restricted users can never access this keyspace
+const SYSTEM_KEYSPACE: &[u8] = b"system";
+
+#[derive(Debug, PartialEq)]
+/// What a user authenticates with
+enum Credential {
+    /// A static token from the config file
+    Token(String),
+    /// An encoded hash of a password set with `SYS USER` (see [`crypto::hash_password`])
+    PasswordHash(String),
+}
+
+#[derive(Debug)]
+/// A user that connections can authenticate as
+pub struct User {
+    name: String,
+    credential: Credential,
+    /// the keyspaces that this user can access (`None` if it can access all of them)
+    keyspaces: Option<Vec<String>>,
+    /// the maximum number of connections authenticated as this user (`0` if unlimited)
+    maxconnections: usize,
+    /// the maximum number of operations per second (`0` if unlimited)
+    opsrate: u64,
+    /// the priority class of this user's connections (`None` to go by the listener's)
+    priority: Option<Priority>,
+    /// the number of connections that are authenticated as this user (shared with the user
+    /// that replaces this one, since the old user's connections only log out later)
+    connections: Arc<AtomicUsize>,
+    /// the start of the current one-second window and the operations run in it
+    window: Mutex<(Instant, u64)>,
+    /// set once this user is removed or replaced, after which the connections that
+    /// authenticated as it have to authenticate again
+    revoked: AtomicBool,
+}
+
+impl PartialEq for User {
+    fn eq(&self, other: &Self) -> bool {
+        // the connection and operation counts are runtime state
+        self.name == other.name
+            && self.credential == other.credential
+            && self.keyspaces == other.keyspaces
+            && self.maxconnections == other.maxconnections
+            && self.opsrate == other.opsrate
+            && self.priority == other.priority
+    }
+}
+
+impl User {
+    /// Create a user that authenticates with a static token
+    pub fn new(name: String, token: String, keyspaces: Option<Vec<String>>) -> Self {
+        Self::with_credential(name, Credential::Token(token), keyspaces)
+    }
+    /// Create a user that authenticates with a password, given the encoded hash of the
+    /// password (see [`hash_password`])
+    pub fn with_password_hash(name: String, hash: String, keyspaces: Option<Vec<String>>) -> Self {
+        Self::with_credential(name, Credential::PasswordHash(hash), keyspaces)
+    }
+    fn with_credential(
+        name: String,
+        credential: Credential,
+        keyspaces: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            name,
+            credential,
+            keyspaces,
+            maxconnections: 0,
+            opsrate: 0,
+            priority: None,
+            connections: Arc::new(AtomicUsize::new(0)),
+            window: Mutex::new((Instant::now(), 0)),
+            revoked: AtomicBool::new(false),
+        }
+    }
+    /// Limit this user to `maxconnections` concurrent connections and `opsrate` operations
+    /// per second (`0` means unlimited)
+    pub fn with_limits(mut self, maxconnections: usize, opsrate: u64) -> Self {
+        self.maxconnections = maxconnections;
+        self.opsrate = opsrate;
+        self
+    }
+    /// Put this user's connections in the given priority class (`None` to go by the
+    /// listener's)
+    pub fn with_priority(mut self, priority: Option<Priority>) -> Self {
+        self.priority = priority;
+        self
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the priority class of this user's connections, if it has one
+    pub const fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+    /// Returns true if this user isn't restricted to a set of keyspaces
+    pub fn is_superuser(&self) -> bool {
+        self.keyspaces.is_none()
+    }
+    /// Returns true if this user can access the keyspace with the given ID
+    pub fn can_access(&self, ksid: &[u8]) -> bool {
+        match &self.keyspaces {
+            Some(keyspaces) => {
+                ksid != SYSTEM_KEYSPACE && keyspaces.iter().any(|ks| ks.as_bytes() == ksid)
+            }
+            None => true,
+        }
+    }
+    /// Returns the keyspaces that this user can access (`None` if it can access all of them)
+    pub fn keyspaces(&self) -> Option<&[String]> {
+        self.keyspaces.as_deref()
+    }
+    /// Returns true if this user was added with `SYS USER ADD` (and not in the config file)
+    pub fn has_password(&self) -> bool {
+        matches!(self.credential, Credential::PasswordHash(_))
+    }
+    /// Returns true if the given token (or password) is right
+    fn verify(&self, secret: &[u8]) -> bool {
+        match &self.credential {
+            Credential::Token(token) => token_eq(token.as_bytes(), secret),
+            Credential::PasswordHash(hash) => crypto::verify_password(hash, secret),
+        }
+    }
+    /// Count one more connection as authenticated as this user, returning false if the
+    /// user already has as many connections as it is allowed to have
+    fn login(&self) -> bool {
+        let maxconnections = self.maxconnections;
+        self.connections
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                if maxconnections == 0 || count < maxconnections {
+                    Some(count + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+    /// Count one less connection as authenticated as this user. Every successful
+    /// [`authenticate`] should be followed by exactly one call to this, once the connection
+    /// logs out or goes away
+    pub fn logout(&self) {
+        self.connections.fetch_sub(1, Ordering::AcqRel);
+    }
+    /// Returns true if this user was removed or replaced since connections authenticated
+    /// as it
+    pub fn is_revoked(&self) -> bool {
+        self.revoked.load(Ordering::Acquire)
+    }
+    fn revoke(&self) {
+        self.revoked.store(true, Ordering::Release);
+    }
+    /// Account for one operation, returning false if the user has already run as many
+    /// operations as it is allowed to run in the current second
+    pub fn take_op(&self) -> bool {
+        if self.opsrate == 0 {
+            return true;
+        }
+        let mut window = self.window.lock();
+        let now = Instant::now();
+        if now.duration_since(window.0) >= Duration::from_secs(1) {
+            *window = (now, 0);
+        }
+        if window.1 < self.opsrate {
+            window.1 += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Set the users that connections can authenticate as. If `users` is empty, then
+/// authentication is disabled
+pub fn set_users(users: Vec<User>) {
+    *USERS.write() = users.into_iter().map(Arc::new).collect();
+}
+
+/// Returns true if connections have to authenticate
+pub fn is_enabled() -> bool {
+    !USERS.read().is_empty()
+}
+
+/// Returns the user with the given name
+pub fn get_user(name: &str) -> Option<Arc<User>> {
+    USERS.read().iter().find(|user| user.name == name).cloned()
+}
+
+/// Add a user, returning false if there already is a user with the same name
+pub fn add_user(user: User) -> bool {
+    let mut users = USERS.write();
+    if users.iter().any(|u| u.name == user.name) {
+        false
+    } else {
+        users.push(Arc::new(user));
+        true
+    }
+}
+
+/// Replace the user that has the same name as `user`. Connections that authenticated as
+/// the old user have to authenticate again, and the sessions that they started are revoked.
+/// Until then, they still count against the new user's connection limit
+pub fn replace_user(mut user: User) {
+    let name = user.name.clone();
+    {
+        let mut users = USERS.write();
+        match users.iter_mut().find(|u| u.name == user.name) {
+            Some(old) => {
+                user.connections = old.connections.clone();
+                old.revoke();
+                *old = Arc::new(user);
+            }
+            None => users.push(Arc::new(user)),
+        }
+    }
+    session::revoke(&name);
+}
+
+/// Remove the user with the given name, returning false if there is no such user.
+/// Connections that authenticated as the user have to authenticate again, and the sessions
+/// that they started are revoked
+pub fn remove_user(name: &str) -> bool {
+    let removed = {
+        let mut users = USERS.write();
+        match users.iter().position(|user| user.name == name) {
+            Some(idx) => {
+                users.remove(idx).revoke();
+                true
+            }
+            None => false,
+        }
+    };
+    session::revoke(name);
+    removed
+}
+
+/// Hash a password with the [crypto backend](crypto) and a random salt, returning the
+/// encoded hash
+pub fn hash_password(password: &[u8]) -> String {
+    crypto::hash_password(password)
+}
+
+/// Returns the user with the given name if the token is right, counting the connection
+/// against the user's connection limit. Password hashes are slow on purpose, so they are
+/// verified on a blocking thread and without holding the lock on the users
+pub async fn authenticate(name: &[u8], token: &[u8]) -> Result<Arc<User>, AuthError> {
+    let user = USERS
+        .read()
+        .iter()
+        .find(|user| user.name.as_bytes() == name)
+        .cloned();
+    let token = token.to_owned();
+    let user = tokio::task::spawn_blocking(move || match user {
+        Some(user) if user.verify(&token) => Some(user),
+        Some(_) => None,
+        None => {
+            // take as long as we would have for a user that exists
+            crypto::verify_password(&DUMMY_HASH, &token);
+            None
+        }
+    })
+    .await
+    .expect("password verification thread panicked")
+    .ok_or(AuthError::BadCredentials)?;
+    if user.login() {
+        Ok(user)
+    } else {
+        Err(AuthError::TooManyConnections)
+    }
+}
+
+/// Count a connection that resumed a session of `user` against the user's connection limit.
+/// This fails with [`AuthError::BadCredentials`] if the user was removed or replaced since
+/// the session was started
+pub fn resume(user: &Arc<User>) -> Result<(), AuthError> {
+    if !USERS
+        .read()
+        .iter()
+        .any(|current| Arc::ptr_eq(current, user))
+    {
+        return Err(AuthError::BadCredentials);
+    }
+    if user.login() {
+        Ok(())
+    } else {
+        Err(AuthError::TooManyConnections)
+    }
+}
+
+/// Compare two tokens in constant time (for tokens of the same length), so that the time
+/// taken doesn't tell how much of a token was right
+fn token_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[test]
+fn test_user_access() {
+    let tenant = User::new(
+        "acme".to_owned(),
+        "secret".to_owned(),
+        Some(vec!["acme".to_owned()]),
+    );
+    assert!(!tenant.is_superuser());
+    assert!(tenant.can_access(b"acme"));
+    assert!(!tenant.can_access(b"default"));
+    let root = User::new("root".to_owned(), "secret".to_owned(), None);
+    assert!(root.is_superuser());
+    assert!(root.can_access(b"default"));
+    assert!(token_eq(b"secret", b"secret"));
+    assert!(!token_eq(b"secret", b"secreT"));
+    assert!(!token_eq(b"secret", b"secre"));
+}
+
+#[test]
+fn test_user_limits() {
+    let user = User::new("acme".to_owned(), "secret".to_owned(), None).with_limits(1, 2);
+    assert!(user.login());
+    assert!(!user.login());
+    user.logout();
+    assert!(user.login());
+    assert!(user.take_op());
+    assert!(user.take_op());
+    assert!(!user.take_op());
+}
+
+#[test]
+fn test_user_password() {
+    let hash = hash_password(b"hunter2");
+    let user = User::with_password_hash("acme".to_owned(), hash, Some(vec!["system".to_owned()]));
+    assert!(user.has_password());
+    assert!(user.verify(b"hunter2"));
+    assert!(!user.verify(b"hunter3"));
+    // even if it's listed
+    assert!(!user.can_access(b"system"));
+}
@@ -0,0 +1,334 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Background I/O
+//!
+//! Snapshots, exports and slab compaction can move a lot of data in a short time, which can
+//! starve the queries that clients are running when they share a disk. Each of them can be
+//! limited with the `[backgroundio]` section in the config file:
+//! - `rate`: the most data that it moves in a second, in MB (unlimited if unset). Snapshots
+//! and exports count the bytes that they write, while compaction counts the values that it
+//! copies and is only held back between tables (so that writers never wait on it)
+//! - `priority`: the I/O priority of the threads that do the work, like `ionice`: `normal`,
+//! `low` (the lowest best-effort priority) or `idle` (only when no one else is using the
+//! disk). Priorities are only supported on Linux, and only by the I/O schedulers that
+//! support them (like BFQ); elsewhere, they are ignored
+//!
+//! The limits of a running job (and how long it has been held back) are listed by `SYS JOBS`
+
+use super::jobs::JobKind;
+use crate::corestore::lazy::Lazy;
+use core::sync::atomic::{AtomicU64, Ordering};
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use std::io::{Result as IoResult, Write};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of bytes in a MB
+const MB: u64 = 1024 * 1024;
+/// A job that is ahead of its rate by less than this doesn't wait yet, so that it doesn't
+/// sleep after every small write
+const MIN_WAIT: Duration = Duration::from_millis(10);
+
+static LIMITS: Lazy<RwLock<BackgroundIo>, fn() -> RwLock<BackgroundIo>> =
+    Lazy::new(|| RwLock::new(BackgroundIo::default()));
+
+/// The I/O priority of background work
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoPriority {
+    /// The same priority as everything else
+    Normal,
+    /// The lowest best-effort priority
+    Low,
+    /// Only when the disk is otherwise idle
+    Idle,
+}
+
+impl IoPriority {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Low => "low",
+            Self::Idle => "idle",
+        }
+    }
+}
+
+impl Default for IoPriority {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// The limits of a kind of background work
+pub struct IoLimit {
+    /// the most MB that it moves in a second, if it's limited
+    rate: Option<u64>,
+    priority: IoPriority,
+}
+
+impl IoLimit {
+    pub const fn new(rate: Option<u64>, priority: IoPriority) -> Self {
+        Self { rate, priority }
+    }
+    /// Returns true if the rate is `0`, which would never let the work finish
+    pub fn has_zero_rate(&self) -> bool {
+        self.rate == Some(0)
+    }
+    /// Returns true if this changes nothing
+    fn is_unlimited(&self) -> bool {
+        self.rate.is_none() && self.priority == IoPriority::Normal
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// The limits of every kind of background work
+pub struct BackgroundIo {
+    pub snapshot: IoLimit,
+    pub export: IoLimit,
+    pub compaction: IoLimit,
+}
+
+impl BackgroundIo {
+    /// Returns true if any of the rates is `0`
+    pub fn has_zero_rate(&self) -> bool {
+        [self.snapshot, self.export, self.compaction]
+            .iter()
+            .any(IoLimit::has_zero_rate)
+    }
+}
+
+/// Set the limits of background work, which apply to the jobs that start after this
+pub fn set_limits(limits: BackgroundIo) {
+    *LIMITS.write() = limits;
+}
+
+/// Returns a limiter for a job of the given kind, if its work is limited
+pub(super) fn limiter(kind: JobKind) -> Option<Arc<Limiter>> {
+    let limits = LIMITS.read();
+    let limit = match kind {
+        JobKind::Snapshot | JobKind::RemoteSnapshot => limits.snapshot,
+        JobKind::Export => limits.export,
+        JobKind::Compaction => limits.compaction,
+        _ => return None,
+    };
+    if limit.is_unlimited() {
+        None
+    } else {
+        Some(Arc::new(Limiter::new(limit)))
+    }
+}
+
+#[derive(Debug)]
+/// Holds a job to its limits. A limiter can be shared by all the threads of a job, which are
+/// then held to the rate together
+pub struct Limiter {
+    limit: IoLimit,
+    /// when the job will have caught up with its rate, given what it has moved so far
+    next: Mutex<Instant>,
+    /// the time that the job has been held back for, in microseconds
+    waited_us: AtomicU64,
+}
+
+impl Limiter {
+    fn new(limit: IoLimit) -> Self {
+        Self {
+            limit,
+            next: Mutex::new(Instant::now()),
+            waited_us: AtomicU64::new(0),
+        }
+    }
+    /// Account for `bytes` that were moved, blocking the current thread if the job is ahead
+    /// of its rate
+    pub fn consume(&self, bytes: usize) {
+        let rate = match self.limit.rate {
+            Some(rate) if rate != 0 => rate * MB,
+            _ => return,
+        };
+        let cost = Duration::from_nanos((bytes as u64).saturating_mul(1_000_000_000) / rate);
+        let wait = {
+            let mut next = self.next.lock();
+            let now = Instant::now();
+            if *next < now {
+                // we were slower than the rate, which doesn't earn us a burst later
+                *next = now;
+            }
+            *next += cost;
+            next.saturating_duration_since(now)
+        };
+        if wait >= MIN_WAIT {
+            thread::sleep(wait);
+            self.waited_us
+                .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+    /// Run the current thread at the I/O priority of the job, until the returned guard is
+    /// dropped
+    pub fn prioritize(&self) -> PriorityGuard {
+        PriorityGuard::new(self.limit.priority)
+    }
+    /// Returns the state of the limiter as `(name, value)` pairs: the `rate` in MB/s (or
+    /// `unlimited`), the `priority` and the time that the job has been held back for, in
+    /// milliseconds (`waited_ms`)
+    pub fn info(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "rate",
+                self.limit
+                    .rate
+                    .map_or_else(|| "unlimited".to_owned(), |rate| rate.to_string()),
+            ),
+            ("priority", self.limit.priority.as_str().to_owned()),
+            (
+                "waited_ms",
+                (self.waited_us.load(Ordering::Relaxed) / 1000).to_string(),
+            ),
+        ]
+    }
+}
+
+/// A writer that is held to the rate of a [`Limiter`]
+pub struct Limited<'a, W> {
+    inner: W,
+    limiter: &'a Limiter,
+}
+
+impl<'a, W: Write> Limited<'a, W> {
+    pub fn new(inner: W, limiter: &'a Limiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<'a, W: Write> Write for Limited<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let written = self.inner.write(buf)?;
+        self.limiter.consume(written);
+        Ok(written)
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// Restores the I/O priority of the current thread when dropped
+pub struct PriorityGuard {
+    /// the priority that the thread had, if we changed it
+    previous: Option<i32>,
+}
+
+impl PriorityGuard {
+    fn new(priority: IoPriority) -> Self {
+        let previous = match priority {
+            IoPriority::Normal => None,
+            priority => ioprio::set(priority),
+        };
+        Self { previous }
+    }
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous {
+            ioprio::restore(previous);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod ioprio {
+    //! I/O priorities with `ioprio_set(2)`, for the current thread
+    use super::IoPriority;
+
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_SHIFT: i32 = 13;
+    const IOPRIO_CLASS_BE: i32 = 2;
+    const IOPRIO_CLASS_IDLE: i32 = 3;
+    /// The lowest best-effort priority
+    const IOPRIO_BE_LOWEST: i32 = 7;
+
+    /// Set the I/O priority of the current thread, returning the one that it had if it was
+    /// changed
+    pub(super) fn set(priority: IoPriority) -> Option<i32> {
+        let ioprio = match priority {
+            IoPriority::Normal => return None,
+            IoPriority::Low => (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | IOPRIO_BE_LOWEST,
+            IoPriority::Idle => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        };
+        let previous = unsafe {
+            // SAFETY: `ioprio_get` only reads its arguments; `0` is the current thread
+            libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0)
+        };
+        if previous < 0 || !self::apply(ioprio) {
+            log::warn!("Failed to set the I/O priority of a background thread");
+            return None;
+        }
+        Some(previous as i32)
+    }
+
+    /// Give the current thread back the priority that it had
+    pub(super) fn restore(previous: i32) {
+        self::apply(previous);
+    }
+
+    fn apply(ioprio: i32) -> bool {
+        unsafe {
+            // SAFETY: `ioprio_set` only reads its arguments; `0` is the current thread
+            libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) == 0
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod ioprio {
+    //! I/O priorities aren't supported on this platform
+    use super::IoPriority;
+
+    pub(super) fn set(_priority: IoPriority) -> Option<i32> {
+        None
+    }
+
+    pub(super) fn restore(_previous: i32) {}
+}
+
+#[test]
+fn test_limiter_rate() {
+    let limiter = Limiter::new(IoLimit::new(Some(100), IoPriority::Normal));
+    let start = Instant::now();
+    // 5 MB at 100 MB/s take at least 50ms
+    for _ in 0..5 {
+        limiter.consume(MB as usize);
+    }
+    assert!(start.elapsed() >= Duration::from_millis(40));
+    assert!(limiter.waited_us.load(Ordering::Relaxed) > 0);
+    // an unlimited limiter never waits
+    let limiter = Limiter::new(IoLimit::new(None, IoPriority::Idle));
+    limiter.consume(usize::MAX);
+    assert_eq!(limiter.waited_us.load(Ordering::Relaxed), 0);
+}
@@ -0,0 +1,116 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Per-client metrics
+//!
+//! Every connection counts the queries it runs and the bytes it reads and writes, and
+//! every now and then adds them to the totals of its client: the user it is authenticated
+//! as or, if it isn't, the address it connected from. `SYS TOPCLIENTS` lists the busiest
+//! clients, so that a noisy neighbour can be found without a packet capture. Only the
+//! [`MAX_CLIENTS`] busiest clients are remembered
+
+use crate::corestore::lazy::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// The maximum number of clients whose metrics are kept
+pub const MAX_CLIENTS: usize = 1024;
+
+type ClientMap = HashMap<String, ClientStats>;
+
+static CLIENTS: Lazy<Mutex<ClientMap>, fn() -> Mutex<ClientMap>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// What a client has done so far
+pub struct ClientStats {
+    /// the queries run
+    pub ops: u64,
+    /// the bytes read from the client
+    pub bytes_in: u64,
+    /// the bytes written to the client
+    pub bytes_out: u64,
+}
+
+impl ClientStats {
+    pub const fn is_empty(&self) -> bool {
+        self.ops == 0 && self.bytes_in == 0 && self.bytes_out == 0
+    }
+    fn add(&mut self, other: &ClientStats) {
+        self.ops += other.ops;
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+    }
+}
+
+/// Add `stats` to the totals of `client`
+pub fn record(client: &str, stats: &ClientStats) {
+    let mut clients = CLIENTS.lock();
+    if let Some(total) = clients.get_mut(client) {
+        total.add(stats);
+        return;
+    }
+    if clients.len() >= MAX_CLIENTS {
+        // make room by forgetting the quietest client
+        let quietest = clients
+            .iter()
+            .min_by_key(|(_, total)| total.ops)
+            .map(|(client, _)| client.clone());
+        if let Some(quietest) = quietest {
+            clients.remove(&quietest);
+        }
+    }
+    clients.insert(client.to_owned(), *stats);
+}
+
+/// Returns the `count` clients that have run the most queries, busiest first
+pub fn top(count: usize) -> Vec<(String, ClientStats)> {
+    let mut clients: Vec<(String, ClientStats)> = CLIENTS
+        .lock()
+        .iter()
+        .map(|(client, total)| (client.clone(), *total))
+        .collect();
+    clients.sort_by(|(a, a_total), (b, b_total)| b_total.ops.cmp(&a_total.ops).then(a.cmp(b)));
+    clients.truncate(count);
+    clients
+}
+
+#[test]
+fn test_record_and_top() {
+    let stats = |ops| ClientStats {
+        ops,
+        bytes_in: ops * 10,
+        bytes_out: ops * 20,
+    };
+    record("user:test-top-quiet", &stats(1));
+    record("user:test-top-busy", &stats(500_000));
+    record("user:test-top-busy", &stats(500_000));
+    let top = top(1);
+    assert_eq!(
+        top,
+        vec![("user:test-top-busy".to_owned(), stats(1_000_000))]
+    );
+}
@@ -0,0 +1,276 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Background jobs
+//!
+//! Long-running internal work (snapshots, BGSAVE, slab compaction and exports) registers
+//! itself as a [`Job`] while it runs, so that it can be listed with `SYS JOBS` along with
+//! its progress. Jobs that can safely stop halfway can also be cancelled with
+//! `SYS JOBS CANCEL <id>`. The last [`HISTORY`] finished jobs are kept around so that
+//! their outcome can be looked up. Jobs whose I/O is [limited](super::bgio) carry their
+//! [`Limiter`] along
+
+use super::bgio::{self, Limiter};
+use crate::corestore::lazy::Lazy;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The number of finished jobs that are kept around
+pub const HISTORY: usize = 16;
+
+type JobMap = BTreeMap<u64, JobInfo>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static JOBS: Lazy<Mutex<JobMap>, fn() -> Mutex<JobMap>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The kind of work that a job does
+pub enum JobKind {
+    Snapshot,
+    RemoteSnapshot,
+    Bgsave,
+    Compaction,
+    Export,
+    Import,
+    PrefixDrop,
+}
+
+impl JobKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Snapshot => "snapshot",
+            Self::RemoteSnapshot => "remote-snapshot",
+            Self::Bgsave => "bgsave",
+            Self::Compaction => "compaction",
+            Self::Export => "export",
+            Self::Import => "import",
+            Self::PrefixDrop => "prefix-drop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// What `SYS JOBS` knows about a job
+pub struct JobInfo {
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// the units of work that are done
+    pub done: u64,
+    /// the units of work there are in all (`0` if unknown)
+    pub total: u64,
+    /// set if the job was asked to stop; `None` if the job can't be cancelled
+    cancelled: Option<Arc<AtomicBool>>,
+    /// the limits of the job's I/O, if it's limited
+    pub limiter: Option<Arc<Limiter>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CancelError {
+    /// There is no running job with this ID
+    NotFound,
+    /// The job can't be stopped halfway
+    NotCancellable,
+}
+
+/// A handle to a running job. The job is marked as finished when this is dropped: it is
+/// reported as failed if [`Job::fail`] was called (or if we're panicking) and as
+/// cancelled if it was cancelled
+pub struct Job {
+    id: u64,
+    cancelled: Option<Arc<AtomicBool>>,
+    limiter: Option<Arc<Limiter>>,
+    failed: bool,
+}
+
+impl Job {
+    fn new(kind: JobKind, cancellable: bool) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let cancelled = if cancellable {
+            Some(Arc::new(AtomicBool::new(false)))
+        } else {
+            None
+        };
+        let limiter = bgio::limiter(kind);
+        let info = JobInfo {
+            kind,
+            status: JobStatus::Running,
+            done: 0,
+            total: 0,
+            cancelled: cancelled.clone(),
+            limiter: limiter.clone(),
+        };
+        JOBS.lock().insert(id, info);
+        Self {
+            id,
+            cancelled,
+            limiter,
+            failed: false,
+        }
+    }
+    /// Register a new job that runs until it is done
+    pub fn start(kind: JobKind) -> Self {
+        Self::new(kind, false)
+    }
+    /// Register a new job that can be cancelled. The job should check
+    /// [`Job::is_cancelled`] every now and then, and stop if it returns true
+    pub fn start_cancellable(kind: JobKind) -> Self {
+        Self::new(kind, true)
+    }
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+    /// Returns the limits of the job's I/O, if it's limited
+    pub fn limiter(&self) -> Option<&Arc<Limiter>> {
+        self.limiter.as_ref()
+    }
+    /// Set the units of work that there are in all
+    pub fn set_total(&self, total: u64) {
+        if let Some(job) = JOBS.lock().get_mut(&self.id) {
+            job.total = total;
+        }
+    }
+    /// Mark one more unit of work as done
+    pub fn progress(&self) {
+        if let Some(job) = JOBS.lock().get_mut(&self.id) {
+            job.done += 1;
+        }
+    }
+    /// Returns true if someone asked this job to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .as_ref()
+            .map_or(false, |cancelled| cancelled.load(Ordering::Acquire))
+    }
+    /// Mark the job as failed
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let status = if self.failed || std::thread::panicking() {
+            JobStatus::Failed
+        } else if self.is_cancelled() {
+            JobStatus::Cancelled
+        } else {
+            JobStatus::Done
+        };
+        let mut jobs = JOBS.lock();
+        if let Some(job) = jobs.get_mut(&self.id) {
+            job.status = status;
+            job.cancelled = None;
+        }
+        // forget the oldest finished jobs
+        let finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| job.status != JobStatus::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished.iter().take(finished.len().saturating_sub(HISTORY)) {
+            jobs.remove(id);
+        }
+    }
+}
+
+/// Returns all the jobs that are running or have recently finished (oldest first)
+pub fn list() -> Vec<(u64, JobInfo)> {
+    JOBS.lock()
+        .iter()
+        .map(|(id, job)| (*id, job.clone()))
+        .collect()
+}
+
+/// Ask the running job with the given ID to stop
+pub fn cancel(id: u64) -> Result<(), CancelError> {
+    match JOBS.lock().get(&id) {
+        Some(job) if job.status == JobStatus::Running => match &job.cancelled {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Release);
+                Ok(())
+            }
+            None => Err(CancelError::NotCancellable),
+        },
+        _ => Err(CancelError::NotFound),
+    }
+}
+
+#[test]
+fn test_job_lifecycle() {
+    let job = Job::start(JobKind::Snapshot);
+    let id = job.id();
+    job.set_total(2);
+    job.progress();
+    let (_, info) = list().into_iter().find(|(jid, _)| *jid == id).unwrap();
+    assert_eq!(info.status, JobStatus::Running);
+    assert_eq!((info.done, info.total), (1, 2));
+    assert_eq!(cancel(id).unwrap_err(), CancelError::NotCancellable);
+    drop(job);
+    let (_, info) = list().into_iter().find(|(jid, _)| *jid == id).unwrap();
+    assert_eq!(info.status, JobStatus::Done);
+    assert_eq!(cancel(id).unwrap_err(), CancelError::NotFound);
+}
+
+#[test]
+fn test_job_cancel() {
+    let mut job = Job::start_cancellable(JobKind::Compaction);
+    let id = job.id();
+    assert!(!job.is_cancelled());
+    cancel(id).unwrap();
+    assert!(job.is_cancelled());
+    drop(job);
+    let (_, info) = list().into_iter().find(|(jid, _)| *jid == id).unwrap();
+    assert_eq!(info.status, JobStatus::Cancelled);
+    // failures win over cancellations
+    job = Job::start_cancellable(JobKind::Compaction);
+    let id = job.id();
+    cancel(id).unwrap();
+    job.fail();
+    drop(job);
+    let (_, info) = list().into_iter().find(|(jid, _)| *jid == id).unwrap();
+    assert_eq!(info.status, JobStatus::Failed);
+}
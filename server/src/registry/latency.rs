@@ -0,0 +1,182 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Latency monitoring
+//!
+//! A few kinds of events can hold up queries: the worker threads being too busy to run a
+//! task on time, a slow fsync, a stalled allocation or a snapshot holding off writes while
+//! it copies a table. How long each of these took is [`record`]ed as it happens (or is
+//! measured every now and then by the [latency monitor](crate::services::latency)), and the
+//! ones that take [`SPIKE_THRESHOLD`] or longer are remembered as spikes. `SYS LATENCY`
+//! reports the latest, the worst and the most recent spikes of every event, along with what
+//! likely caused them
+
+use crate::corestore::lazy::Lazy;
+use crate::kvengine::now_ms;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Events that take at least this long are spikes
+pub const SPIKE_THRESHOLD: Duration = Duration::from_millis(10);
+/// The number of recent spikes that are kept for every event
+const MAX_RECENT: usize = 8;
+
+static SPIKES: Lazy<Mutex<[Spikes; 4]>, fn() -> Mutex<[Spikes; 4]>> =
+    Lazy::new(|| Mutex::new(Default::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// An event that can hold up queries
+pub enum Event {
+    /// a task ran late on the worker threads
+    EventLoop,
+    /// a file was synced to disk
+    Fsync,
+    /// memory was allocated
+    Alloc,
+    /// writes were held off while a snapshot copied a table
+    Snapshot,
+}
+
+impl Event {
+    pub const ALL: [Event; 4] = [Self::EventLoop, Self::Fsync, Self::Alloc, Self::Snapshot];
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::EventLoop => "eventloop",
+            Self::Fsync => "fsync",
+            Self::Alloc => "alloc",
+            Self::Snapshot => "snapshot",
+        }
+    }
+    /// What most likely causes a spike of this event
+    pub const fn cause(&self) -> &'static str {
+        match self {
+            Self::EventLoop => {
+                "the worker threads were busy: look for expensive queries (like large MGETs or \
+                LSKEYS) or for other processes competing for the CPU"
+            }
+            Self::Fsync => {
+                "the disk was slow to persist data: look for other I/O on the disk, or move the \
+                data directory to faster storage"
+            }
+            Self::Alloc => {
+                "the allocator was slow to hand out memory: the host may be short on memory \
+                or swapping"
+            }
+            Self::Snapshot => {
+                "writes were held off while a snapshot copied a large table: snapshot less \
+                often, or split up the largest tables"
+            }
+        }
+    }
+    const fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+/// The spikes of an event
+struct Spikes {
+    /// the number of spikes
+    count: u64,
+    /// the worst spike (in milliseconds)
+    max: u64,
+    /// the most recent spikes (when they ended, as UNIX timestamps in milliseconds, and
+    /// how long they took in milliseconds), oldest first
+    recent: VecDeque<(u64, u64)>,
+}
+
+impl Spikes {
+    /// Add a spike that took `ms` milliseconds and ended at `at`
+    fn add(&mut self, at: u64, ms: u64) {
+        self.count += 1;
+        self.max = self.max.max(ms);
+        if self.recent.len() == MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((at, ms));
+    }
+}
+
+/// Record that an event took `duration`, remembering it if it's a spike
+pub fn record(event: Event, duration: Duration) {
+    if duration < SPIKE_THRESHOLD {
+        return;
+    }
+    SPIKES.lock()[event.index()].add(now_ms(), duration.as_millis() as u64);
+}
+
+/// Returns the spikes of every event that has had any as `(name, value)` pairs: `count`,
+/// `latest` (in milliseconds), `latest_at` (a UNIX timestamp in milliseconds), `max` (in
+/// milliseconds), `recent` (the most recent spikes in milliseconds, oldest first) and
+/// `cause` (like `fsync.max`)
+pub fn report() -> Vec<(String, String)> {
+    report_of(&SPIKES.lock())
+}
+
+fn report_of(spikes: &[Spikes; 4]) -> Vec<(String, String)> {
+    let mut report = Vec::new();
+    for event in Event::ALL.iter() {
+        let spikes = &spikes[event.index()];
+        let (latest_at, latest) = match spikes.recent.back() {
+            Some(latest) => *latest,
+            None => continue,
+        };
+        let recent: Vec<String> = spikes.recent.iter().map(|(_, ms)| ms.to_string()).collect();
+        let name = event.as_str();
+        report.push((format!("{}.count", name), spikes.count.to_string()));
+        report.push((format!("{}.latest", name), latest.to_string()));
+        report.push((format!("{}.latest_at", name), latest_at.to_string()));
+        report.push((format!("{}.max", name), spikes.max.to_string()));
+        report.push((format!("{}.recent", name), recent.join(",")));
+        report.push((format!("{}.cause", name), event.cause().to_owned()));
+    }
+    report
+}
+
+/// Forget all the spikes
+pub fn reset() {
+    *SPIKES.lock() = Default::default();
+}
+
+#[test]
+fn test_report() {
+    let mut spikes: [Spikes; 4] = Default::default();
+    assert!(report_of(&spikes).is_empty());
+    for ms in 10..20 {
+        spikes[Event::Fsync.index()].add(ms, ms);
+    }
+    spikes[Event::Fsync.index()].add(100, 12);
+    let report = report_of(&spikes);
+    // only the events that have had spikes are reported
+    assert_eq!(report.len(), 6);
+    assert_eq!(report[0], ("fsync.count".to_owned(), "11".to_owned()));
+    assert_eq!(report[1], ("fsync.latest".to_owned(), "12".to_owned()));
+    assert_eq!(report[2], ("fsync.latest_at".to_owned(), "100".to_owned()));
+    assert_eq!(report[3], ("fsync.max".to_owned(), "19".to_owned()));
+    // only the most recent spikes are kept
+    assert_eq!(report[4].1, "13,14,15,16,17,18,19,12");
+}
@@ -30,8 +30,28 @@
 //!
 
 use crate::corestore::lock::{QLGuard, QuickLock};
+use crate::dbnet::DEFAULT_MAX_BUFFER;
 use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering;
+use serde::Deserialize;
+use std::time::Duration;
+
+pub mod admission;
+pub mod auth;
+pub mod bgio;
+pub mod clients;
+pub mod jobs;
+pub mod latency;
+pub mod numa;
+pub mod pressure;
+pub mod schedules;
+pub mod stall;
+pub mod tls;
+pub mod topology;
+pub mod tracking;
 
 const ORD_ACQ: Ordering = Ordering::Acquire;
 const ORD_REL: Ordering = Ordering::Release;
@@ -73,18 +93,236 @@ impl Trip {
     }
 }
 
+/// The mode in which the server is operating
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum ServerMode {
+    /// All actions are allowed
+    Normal = 0,
+    /// Actions that mutate data are rejected
+    ReadOnly = 1,
+    /// Only administrative actions are allowed
+    Maintenance = 2,
+}
+
+impl ServerMode {
+    const fn from_u8(mode: u8) -> Self {
+        match mode {
+            1 => ServerMode::ReadOnly,
+            2 => ServerMode::Maintenance,
+            _ => ServerMode::Normal,
+        }
+    }
+    /// Parse a mode from a (case insensitive) byte slice
+    pub fn from_bytes(mode: &[u8]) -> Option<Self> {
+        let mode = mode.to_ascii_lowercase();
+        match mode.as_slice() {
+            b"normal" => Some(ServerMode::Normal),
+            b"readonly" => Some(ServerMode::ReadOnly),
+            b"maintenance" => Some(ServerMode::Maintenance),
+            _ => None,
+        }
+    }
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ServerMode::Normal => "normal",
+            ServerMode::ReadOnly => "readonly",
+            ServerMode::Maintenance => "maintenance",
+        }
+    }
+}
+
 /// The global system health
 static GLOBAL_STATE: AtomicBool = AtomicBool::new(true);
+/// The global server mode
+static SERVER_MODE: AtomicU8 = AtomicU8::new(ServerMode::Normal as u8);
 /// The global flush state
 static FLUSH_STATE: QuickLock<()> = QuickLock::new(());
 /// The preload trip switch
 static PRELOAD_TRIPSWITCH: Trip = Trip::new_untripped();
+/// The idle timeout for connections in seconds (`0` if disabled)
+static IDLE_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+/// The TCP keepalive interval in seconds (`0` if disabled)
+static KEEPALIVE: AtomicU64 = AtomicU64::new(0);
+/// The number of connections that were closed for being idle
+static REAPED_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+/// The number of queries that were skipped because they were dequeued after their deadline
+static DEADLINES_EXCEEDED: AtomicU64 = AtomicU64::new(0);
+/// The maximum number of bytes buffered for a connection's queries
+static MAX_BUFFER: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BUFFER);
+/// Whether flushing a non-empty table needs a confirmation token
+static FLUSH_CONFIRM: AtomicBool = AtomicBool::new(false);
+/// The number of seconds for which dropped tables can be restored (`0` if disabled)
+static DROP_RETENTION: AtomicU64 = AtomicU64::new(0);
+/// The number of seconds after which tables that weren't accessed are unloaded (`0` if disabled)
+static IDLE_UNLOAD: AtomicU64 = AtomicU64::new(0);
+/// The number of tables that were unloaded for being idle
+static TABLES_UNLOADED: AtomicU64 = AtomicU64::new(0);
+/// The number of unloaded tables that were loaded again
+static TABLES_RELOADED: AtomicU64 = AtomicU64::new(0);
+/// The number of threads that read tables while recovering (`0` for one per CPU)
+static RECOVERY_THREADS: AtomicUsize = AtomicUsize::new(0);
+/// The number of threads that write tables while creating a snapshot (`0` for one per CPU)
+static SNAPSHOT_THREADS: AtomicUsize = AtomicUsize::new(0);
 
 /// Check the global system state
 pub fn state_okay() -> bool {
     GLOBAL_STATE.load(ORD_ACQ)
 }
 
+/// Get the current server mode
+pub fn get_mode() -> ServerMode {
+    ServerMode::from_u8(SERVER_MODE.load(ORD_ACQ))
+}
+
+/// Set the current server mode
+pub fn set_mode(mode: ServerMode) {
+    SERVER_MODE.store(mode as u8, ORD_REL)
+}
+
+/// Set the idle timeout for connections in seconds (`0` disables it)
+pub fn set_idle_timeout(secs: u64) {
+    IDLE_TIMEOUT.store(secs, ORD_REL)
+}
+
+/// Get the idle timeout for connections, if one is set
+pub fn get_idle_timeout() -> Option<Duration> {
+    match IDLE_TIMEOUT.load(ORD_ACQ) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Set the TCP keepalive interval in seconds (`0` disables it)
+pub fn set_keepalive(secs: u64) {
+    KEEPALIVE.store(secs, ORD_REL)
+}
+
+/// Get the TCP keepalive interval, if one is set
+pub fn get_keepalive() -> Option<Duration> {
+    match KEEPALIVE.load(ORD_ACQ) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Count a connection that was closed for being idle
+pub fn reaped_connection() {
+    REAPED_CONNECTIONS.fetch_add(1, ORD_SEQ);
+}
+
+/// Get the number of connections that were closed for being idle
+pub fn get_reaped_connections() -> u64 {
+    REAPED_CONNECTIONS.load(ORD_SEQ)
+}
+
+/// Count a query that was skipped because it was dequeued after its deadline
+pub fn deadline_exceeded() {
+    DEADLINES_EXCEEDED.fetch_add(1, ORD_SEQ);
+}
+
+/// Get the number of queries that were skipped because they were dequeued after their
+/// deadline
+pub fn get_deadlines_exceeded() -> u64 {
+    DEADLINES_EXCEEDED.load(ORD_SEQ)
+}
+
+/// Set the maximum number of bytes buffered for a connection's queries (`0` disables
+/// the limit)
+pub fn set_max_buffer(bytes: usize) {
+    MAX_BUFFER.store(if bytes == 0 { usize::MAX } else { bytes }, ORD_REL)
+}
+
+/// Get the maximum number of bytes buffered for a connection's queries
+pub fn get_max_buffer() -> usize {
+    MAX_BUFFER.load(ORD_ACQ)
+}
+
+/// Set whether flushing a non-empty table needs a confirmation token
+pub fn set_flush_confirm(confirm: bool) {
+    FLUSH_CONFIRM.store(confirm, ORD_REL)
+}
+
+/// Check if flushing a non-empty table needs a confirmation token
+pub fn get_flush_confirm() -> bool {
+    FLUSH_CONFIRM.load(ORD_ACQ)
+}
+
+/// Set the number of seconds for which dropped tables can be restored (`0` disables it)
+pub fn set_drop_retention(secs: u64) {
+    DROP_RETENTION.store(secs, ORD_REL)
+}
+
+/// Get the period for which dropped tables can be restored, if one is set
+pub fn get_drop_retention() -> Option<Duration> {
+    match DROP_RETENTION.load(ORD_ACQ) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Set the number of seconds after which tables that weren't accessed are unloaded (`0`
+/// disables it)
+pub fn set_idle_unload(secs: u64) {
+    IDLE_UNLOAD.store(secs, ORD_REL)
+}
+
+/// Get the period after which tables that weren't accessed are unloaded, if one is set
+pub fn get_idle_unload() -> Option<Duration> {
+    match IDLE_UNLOAD.load(ORD_ACQ) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Count a table that was unloaded for being idle
+pub fn table_unloaded() {
+    TABLES_UNLOADED.fetch_add(1, ORD_SEQ);
+}
+
+/// Get the number of tables that were unloaded for being idle
+pub fn get_tables_unloaded() -> u64 {
+    TABLES_UNLOADED.load(ORD_SEQ)
+}
+
+/// Count an unloaded table that was loaded again
+pub fn table_reloaded() {
+    TABLES_RELOADED.fetch_add(1, ORD_SEQ);
+}
+
+/// Get the number of unloaded tables that were loaded again
+pub fn get_tables_reloaded() -> u64 {
+    TABLES_RELOADED.load(ORD_SEQ)
+}
+
+/// Set the number of threads that read tables while recovering (`0` uses one per CPU)
+pub fn set_recovery_threads(threads: usize) {
+    RECOVERY_THREADS.store(threads, ORD_REL)
+}
+
+/// Get the number of threads that read tables while recovering
+pub fn get_recovery_threads() -> usize {
+    match RECOVERY_THREADS.load(ORD_ACQ) {
+        0 => num_cpus::get(),
+        threads => threads,
+    }
+}
+
+/// Set the number of threads that write tables while creating a snapshot (`0` uses one
+/// per CPU)
+pub fn set_snapshot_threads(threads: usize) {
+    SNAPSHOT_THREADS.store(threads, ORD_REL)
+}
+
+/// Get the number of threads that write tables while creating a snapshot
+pub fn get_snapshot_threads() -> usize {
+    match SNAPSHOT_THREADS.load(ORD_ACQ) {
+        0 => num_cpus::get(),
+        threads => threads,
+    }
+}
+
 /// Lock the global flush state. **Remember to drop the lock guard**; else you'll
 /// end up pausing all sorts of global flushing/transactional systems
 pub fn lock_flush_state() -> QLGuard<'static, ()> {
@@ -105,3 +343,17 @@ pub fn unpoison() {
 pub fn get_preload_tripswitch() -> &'static Trip {
     &PRELOAD_TRIPSWITCH
 }
+
+#[test]
+fn test_server_mode_from_bytes() {
+    assert_eq!(ServerMode::from_bytes(b"normal"), Some(ServerMode::Normal));
+    assert_eq!(
+        ServerMode::from_bytes(b"READONLY"),
+        Some(ServerMode::ReadOnly)
+    );
+    assert_eq!(
+        ServerMode::from_bytes(b"Maintenance"),
+        Some(ServerMode::Maintenance)
+    );
+    assert_eq!(ServerMode::from_bytes(b"read-only"), None);
+}
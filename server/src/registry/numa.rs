@@ -0,0 +1,138 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # NUMA nodes
+//!
+//! On a host with more than one socket, memory is split between NUMA nodes and a thread
+//! reaches the memory of its own node faster than the memory of the others. Linux allocates
+//! memory on the node of the thread that first touches it, so when the worker threads are
+//! pinned to nodes (see [`topology`](super::topology)), the tables that they fill up stay
+//! on their nodes. This module finds the nodes (and their cores) and the memory that the
+//! process has on each of them. Nodes are only found on Linux
+
+use std::collections::BTreeMap;
+use std::fs;
+
+#[cfg(target_os = "linux")]
+const DIR_NODES: &str = "/sys/devices/system/node";
+
+#[cfg(target_os = "linux")]
+/// Returns the cores of every NUMA node, in the order of the nodes
+pub fn nodes() -> Vec<Vec<usize>> {
+    let mut nodes: Vec<(usize, Vec<usize>)> = match fs::read_dir(DIR_NODES) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let id = entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("node")?
+                    .parse()
+                    .ok()?;
+                let cpulist = fs::read_to_string(entry.path().join("cpulist")).ok()?;
+                Some((id, parse_cpulist(&cpulist)))
+            })
+            .filter(|(_, cores)| !cores.is_empty())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    nodes.sort_unstable();
+    nodes.into_iter().map(|(_, cores)| cores).collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+/// NUMA nodes are only found on Linux
+pub fn nodes() -> Vec<Vec<usize>> {
+    Vec::new()
+}
+
+/// Returns the bytes of memory that the process has on every NUMA node that it has any
+/// memory on, as `(name, value)` pairs (like `numa.0.bytes`)
+pub fn info() -> Vec<(String, String)> {
+    let maps = fs::read_to_string("/proc/self/numa_maps").unwrap_or_default();
+    parse_numa_maps(&maps)
+        .into_iter()
+        .map(|(node, bytes)| (format!("numa.{}.bytes", node), bytes.to_string()))
+        .collect()
+}
+
+/// Parse a list of cores like `0-3,8,10-11`, skipping anything that isn't understood
+fn parse_cpulist(cpulist: &str) -> Vec<usize> {
+    let mut cores = Vec::new();
+    for range in cpulist.trim().split(',') {
+        let mut bounds = range.splitn(2, '-').map(|bound| bound.parse::<usize>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(first)), Some(Ok(last))) => cores.extend(first..=last),
+            (Some(Ok(core)), None) => cores.push(core),
+            _ => {}
+        }
+    }
+    cores
+}
+
+/// Add up the pages of every mapping in `numa_maps` (with `N<node>=<pages>`) by node,
+/// returning the bytes on every node
+fn parse_numa_maps(maps: &str) -> BTreeMap<usize, u64> {
+    let mut nodes = BTreeMap::new();
+    for mapping in maps.lines() {
+        let fields: Vec<&str> = mapping.split_whitespace().collect();
+        let pagesize = fields
+            .iter()
+            .find_map(|field| field.strip_prefix("kernelpagesize_kB="))
+            .and_then(|kb| kb.parse::<u64>().ok())
+            .unwrap_or(4)
+            * 1024;
+        for field in fields.iter() {
+            let pages = field
+                .strip_prefix('N')
+                .and_then(|pages| pages.split_once('='))
+                .and_then(|(node, pages)| Some((node.parse().ok()?, pages.parse::<u64>().ok()?)));
+            if let Some((node, pages)) = pages {
+                *nodes.entry(node).or_insert(0) += pages * pagesize;
+            }
+        }
+    }
+    nodes
+}
+
+#[test]
+fn test_parse_cpulist() {
+    assert_eq!(parse_cpulist("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+    assert_eq!(parse_cpulist("\n"), Vec::<usize>::new());
+}
+
+#[test]
+fn test_parse_numa_maps() {
+    let maps = "\
+55d0c0a00000 default file=/usr/bin/skyd mapped=10 N0=10 kernelpagesize_kB=4
+7f0000000000 default anon=300 dirty=300 N0=100 N1=200 kernelpagesize_kB=4
+7f1000000000 default anon=1 dirty=1 N1=1 kernelpagesize_kB=2048
+";
+    let nodes = parse_numa_maps(maps);
+    assert_eq!(nodes.get(&0), Some(&(110 * 4096)));
+    assert_eq!(nodes.get(&1), Some(&(200 * 4096 + 2048 * 1024)));
+    assert_eq!(nodes.len(), 2);
+}
@@ -0,0 +1,287 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Memory pressure
+//!
+//! With a `[memory]` limit, the [memory governor](crate::services::governor) keeps the
+//! server from being killed for running out of memory by degrading it in order, as its
+//! resident memory crosses every threshold (a percentage of the limit):
+//! 1. [`Pressure::Connections`]: new connections are refused (except on admin listeners)
+//! 2. [`Pressure::Evict`]: the volatile tables are evicted (emptied)
+//! 3. [`Pressure::Writes`]: writes are rejected with `err-memory-pressure`
+//!
+//! The server only backs off a level once its memory falls [`HYSTERESIS_PERCENT`] of the
+//! limit below the level's threshold, so that it doesn't flap between levels.
+
+use crate::corestore::lazy::Lazy;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use parking_lot::RwLock;
+
+/// The default threshold (as a percentage of the limit) for refusing new connections
+pub const DEFAULT_CONNECTIONS: u64 = 80;
+/// The default threshold (as a percentage of the limit) for evicting the volatile tables
+pub const DEFAULT_EVICT: u64 = 90;
+/// The default threshold (as a percentage of the limit) for rejecting writes
+pub const DEFAULT_WRITES: u64 = 95;
+/// How far (as a percentage of the limit) below a threshold the memory has to fall to
+/// back off from its level
+pub const HYSTERESIS_PERCENT: u64 = 5;
+
+static POLICY: Lazy<RwLock<Option<MemoryPolicy>>, fn() -> RwLock<Option<MemoryPolicy>>> =
+    Lazy::new(|| RwLock::new(None));
+/// The current level
+static LEVEL: AtomicU8 = AtomicU8::new(Pressure::Normal as u8);
+/// The resident memory when it was last measured
+static RSS: AtomicU64 = AtomicU64::new(0);
+/// The number of times that the level has changed
+static TRANSITIONS: AtomicU64 = AtomicU64::new(0);
+/// The number of keys that were evicted from volatile tables
+static EVICTED: AtomicU64 = AtomicU64::new(0);
+/// The number of connections that were refused
+static REFUSED: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[repr(u8)]
+/// How far the server has degraded
+pub enum Pressure {
+    /// Everything works
+    Normal = 0,
+    /// New connections are refused
+    Connections = 1,
+    /// New connections are refused and volatile tables are evicted
+    Evict = 2,
+    /// New connections are refused, volatile tables are evicted and writes are rejected
+    Writes = 3,
+}
+
+impl Pressure {
+    const fn from_u8(level: u8) -> Self {
+        match level {
+            1 => Pressure::Connections,
+            2 => Pressure::Evict,
+            3 => Pressure::Writes,
+            _ => Pressure::Normal,
+        }
+    }
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Pressure::Normal => "normal",
+            Pressure::Connections => "refuse-connections",
+            Pressure::Evict => "evict-volatile",
+            Pressure::Writes => "reject-writes",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// The memory limit and the thresholds of the levels (as percentages of the limit)
+pub struct MemoryPolicy {
+    limit: u64,
+    connections: u64,
+    evict: u64,
+    writes: u64,
+}
+
+impl MemoryPolicy {
+    pub const fn new(limit: u64, connections: u64, evict: u64, writes: u64) -> Self {
+        Self {
+            limit,
+            connections,
+            evict,
+            writes,
+        }
+    }
+    /// Returns true if the thresholds are in order and within the limit
+    pub const fn has_valid_thresholds(&self) -> bool {
+        self.limit != 0
+            && self.connections <= self.evict
+            && self.evict <= self.writes
+            && self.writes <= 100
+    }
+    /// Returns the memory (in bytes) at which `level` starts
+    const fn threshold(&self, level: Pressure) -> u64 {
+        let percent = match level {
+            Pressure::Normal => 0,
+            Pressure::Connections => self.connections,
+            Pressure::Evict => self.evict,
+            Pressure::Writes => self.writes,
+        };
+        self.limit / 100 * percent
+    }
+    /// Returns the level that the server should be at with `rss` bytes of memory, given
+    /// that it's at `current`
+    pub fn level_for(&self, rss: u64, current: Pressure) -> Pressure {
+        let level = [Pressure::Writes, Pressure::Evict, Pressure::Connections]
+            .iter()
+            .copied()
+            .find(|level| rss >= self.threshold(*level))
+            .unwrap_or(Pressure::Normal);
+        let margin = self.limit / 100 * HYSTERESIS_PERCENT;
+        if level >= current || rss.saturating_add(margin) < self.threshold(current) {
+            level
+        } else {
+            current
+        }
+    }
+}
+
+/// Set the memory policy (or `None` to never degrade)
+pub fn set_policy(policy: Option<MemoryPolicy>) {
+    *POLICY.write() = policy;
+}
+
+/// Returns the memory policy, if there is one
+pub fn get_policy() -> Option<MemoryPolicy> {
+    *POLICY.read()
+}
+
+/// Returns the current level
+pub fn level() -> Pressure {
+    Pressure::from_u8(LEVEL.load(Ordering::Acquire))
+}
+
+/// Move to `level` with `rss` bytes of memory, logging the transition (if it is one)
+pub fn set_level(level: Pressure, rss: u64) {
+    RSS.store(rss, Ordering::Relaxed);
+    let previous = Pressure::from_u8(LEVEL.swap(level as u8, Ordering::AcqRel));
+    if previous == level {
+        return;
+    }
+    TRANSITIONS.fetch_add(1, Ordering::Relaxed);
+    if level > previous {
+        log::warn!(
+            "Memory pressure rose from `{}` to `{}` ({} bytes resident)",
+            previous.as_str(),
+            level.as_str(),
+            rss
+        );
+    } else {
+        log::info!(
+            "Memory pressure fell from `{}` to `{}` ({} bytes resident)",
+            previous.as_str(),
+            level.as_str(),
+            rss
+        );
+    }
+}
+
+/// Returns true if new connections are refused. The refusal is counted
+pub fn refuse_connection() -> bool {
+    let refuse = level() >= Pressure::Connections;
+    if refuse {
+        REFUSED.fetch_add(1, Ordering::Relaxed);
+    }
+    refuse
+}
+
+/// Returns true if writes are rejected
+pub fn rejects_writes() -> bool {
+    level() >= Pressure::Writes
+}
+
+/// Count keys that were evicted from volatile tables
+pub fn evicted(keys: u64) {
+    EVICTED.fetch_add(keys, Ordering::Relaxed);
+}
+
+/// Returns the memory statistics as `(name, value)` pairs
+pub fn info() -> Vec<(String, String)> {
+    let limit = get_policy().map_or(0, |policy| policy.limit);
+    vec![
+        ("memory.limit".to_owned(), limit.to_string()),
+        (
+            "memory.rss".to_owned(),
+            RSS.load(Ordering::Relaxed).to_string(),
+        ),
+        ("memory.pressure".to_owned(), level().as_str().to_owned()),
+        (
+            "memory.transitions".to_owned(),
+            TRANSITIONS.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "memory.evicted".to_owned(),
+            EVICTED.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "memory.refused".to_owned(),
+            REFUSED.load(Ordering::Relaxed).to_string(),
+        ),
+    ]
+}
+
+#[cfg(target_os = "linux")]
+/// Returns the resident memory of the server (in bytes)
+pub fn rss() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(parse_statm(&statm)? * page_size as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+/// The resident memory can't be found on this platform
+pub fn rss() -> Option<u64> {
+    None
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+/// Parse the number of resident pages out of `/proc/self/statm`
+fn parse_statm(statm: &str) -> Option<u64> {
+    statm.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[test]
+fn test_level_for() {
+    let policy = MemoryPolicy::new(1000, 80, 90, 95);
+    assert!(policy.has_valid_thresholds());
+    assert_eq!(policy.level_for(500, Pressure::Normal), Pressure::Normal);
+    assert_eq!(
+        policy.level_for(850, Pressure::Normal),
+        Pressure::Connections
+    );
+    assert_eq!(policy.level_for(960, Pressure::Normal), Pressure::Writes);
+    // backing off needs the memory to fall well below the threshold
+    assert_eq!(policy.level_for(930, Pressure::Writes), Pressure::Writes);
+    assert_eq!(
+        policy.level_for(890, Pressure::Writes),
+        Pressure::Connections
+    );
+    assert_eq!(
+        policy.level_for(760, Pressure::Connections),
+        Pressure::Connections
+    );
+    assert_eq!(
+        policy.level_for(700, Pressure::Connections),
+        Pressure::Normal
+    );
+    assert!(!MemoryPolicy::new(1000, 90, 80, 95).has_valid_thresholds());
+    assert!(!MemoryPolicy::new(1000, 80, 90, 120).has_valid_thresholds());
+}
+
+#[test]
+fn test_parse_statm() {
+    assert_eq!(parse_statm("5462 1203 843 220 0 1321 0\n"), Some(1203));
+    assert_eq!(parse_statm(""), None);
+}
@@ -0,0 +1,142 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Snapshot schedules
+//!
+//! Apart from (or instead of) the `every` interval, snapshots can be made on named cron
+//! schedules, like an `hourly` one and a `nightly` one. The schedules are read from the
+//! config file and can be changed with `SYS CONFIG` while the server runs, and the snapshot
+//! service is woken up whenever they change so that it can work out when the next snapshot
+//! is due
+
+use crate::config::cron::Cron;
+use crate::corestore::lazy::Lazy;
+use core::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use tokio::sync::Notify;
+
+type ScheduleMap = BTreeMap<String, Cron>;
+
+/// set once the snapshot service is running
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SCHEDULES: Lazy<Mutex<ScheduleMap>, fn() -> Mutex<ScheduleMap>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+static CHANGED: Lazy<Notify, fn() -> Notify> = Lazy::new(Notify::new);
+
+/// Note that snapshots are enabled, and so the schedules will be run
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release)
+}
+
+/// Check if the schedules will be run
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Add a schedule (or replace the schedule with the same name)
+pub fn set(name: &str, cron: Cron) {
+    SCHEDULES.lock().insert(name.to_owned(), cron);
+    CHANGED.notify_one();
+}
+
+/// Remove a schedule, returning false if there is no schedule with this name
+pub fn remove(name: &str) -> bool {
+    let removed = SCHEDULES.lock().remove(name).is_some();
+    if removed {
+        CHANGED.notify_one();
+    }
+    removed
+}
+
+/// Returns the schedule with the given name
+pub fn get(name: &str) -> Option<Cron> {
+    SCHEDULES.lock().get(name).cloned()
+}
+
+/// Returns all the schedules, sorted by their names
+pub fn list() -> Vec<(String, Cron)> {
+    SCHEDULES
+        .lock()
+        .iter()
+        .map(|(name, cron)| (name.clone(), cron.clone()))
+        .collect()
+}
+
+/// Returns the first time after `after` (as UNIX timestamps in seconds) that a snapshot is
+/// due, along with the names of the schedules that are due then
+pub fn next_due(after: u64) -> Option<(u64, Vec<String>)> {
+    let mut next: Option<(u64, Vec<String>)> = None;
+    for (name, cron) in SCHEDULES.lock().iter() {
+        let at = match cron.next_after(after) {
+            Some(at) => at,
+            None => continue,
+        };
+        match &mut next {
+            Some((due, names)) if *due == at => names.push(name.clone()),
+            Some((due, _)) if *due < at => {}
+            _ => next = Some((at, vec![name.clone()])),
+        }
+    }
+    next
+}
+
+/// Resolves once the schedules have changed (since this was last awaited)
+pub async fn changed() {
+    CHANGED.notified().await
+}
+
+#[test]
+fn test_schedules() {
+    // 2021-01-01 00:00:00
+    const NEW_YEAR: u64 = 1_609_459_200;
+    set("test-hourly", Cron::parse("0 * * * *").unwrap());
+    set("test-nightly", Cron::parse("0 0 * * *").unwrap());
+    set("test-quarter", Cron::parse("*/15 * * * *").unwrap());
+    assert_eq!(get("test-hourly").unwrap().as_str(), "0 * * * *");
+    assert_eq!(
+        next_due(NEW_YEAR),
+        Some((NEW_YEAR + 900, vec!["test-quarter".to_owned()]))
+    );
+    assert_eq!(
+        next_due(NEW_YEAR + 3000),
+        Some((
+            NEW_YEAR + 3600,
+            vec!["test-hourly".to_owned(), "test-quarter".to_owned()]
+        ))
+    );
+    assert!(remove("test-quarter"));
+    assert!(!remove("test-quarter"));
+    assert_eq!(
+        next_due(NEW_YEAR + 23 * 3600),
+        Some((
+            NEW_YEAR + 24 * 3600,
+            vec!["test-hourly".to_owned(), "test-nightly".to_owned()]
+        ))
+    );
+    assert!(remove("test-hourly"));
+    assert!(remove("test-nightly"));
+}
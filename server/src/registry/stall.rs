@@ -0,0 +1,187 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Write stalls
+//!
+//! A keyspace whose data can't be persisted is switched to read-only, instead of accepting
+//! writes that would be lost on a restart. Writes to it are rejected with:
+//! - `err-disk-full` while the disk that the data directory is on has less than the minimum
+//! free space (see [`set_min_free`]), or
+//! - `err-flush-failed` after the keyspace failed to flush
+//!
+//! The [disk guard](crate::services::diskguard) checks the free space periodically and
+//! lifts the stalls on its own: as soon as there is enough space again, and once a keyspace
+//! that failed to flush has been flushed successfully.
+
+use crate::corestore::memstore::Keyspace;
+use crate::protocol::responses;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The default minimum free space (in bytes)
+pub const DEFAULT_MIN_FREE: u64 = 64 * 1024 * 1024;
+/// The keyspace is stalled because the disk is (almost) full
+pub const DISK_FULL: u8 = 0b01;
+/// The keyspace is stalled because it failed to flush
+pub const FLUSH_FAILED: u8 = 0b10;
+
+/// The minimum free space (in bytes), below which every keyspace is stalled
+static MIN_FREE: AtomicU64 = AtomicU64::new(DEFAULT_MIN_FREE);
+/// The free space (in bytes) when it was last checked (`u64::MAX` if it's unknown)
+static FREE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Set the minimum free space (`0` to never stall for want of space)
+pub fn set_min_free(bytes: u64) {
+    MIN_FREE.store(bytes, Ordering::Release)
+}
+
+/// Returns the minimum free space
+pub fn get_min_free() -> u64 {
+    MIN_FREE.load(Ordering::Acquire)
+}
+
+/// Record the free space that was last seen
+pub fn set_free(bytes: u64) {
+    FREE.store(bytes, Ordering::Release)
+}
+
+/// Returns the free space that was last seen, if it's known
+pub fn get_free() -> Option<u64> {
+    match FREE.load(Ordering::Acquire) {
+        u64::MAX => None,
+        free => Some(free),
+    }
+}
+
+/// Returns true if `free` bytes are below the minimum free space
+pub fn is_low(free: u64) -> bool {
+    free < get_min_free()
+}
+
+/// Returns the name of the stall (for `SYS INFO`), if there is one
+pub const fn as_str(stall: u8) -> Option<&'static str> {
+    if stall & DISK_FULL != 0 {
+        Some("disk-full")
+    } else if stall & FLUSH_FAILED != 0 {
+        Some("flush-failed")
+    } else {
+        None
+    }
+}
+
+/// Returns the error that writes to a keyspace with the given stall are rejected with, if
+/// they are
+pub const fn response(stall: u8) -> Option<&'static [u8]> {
+    if stall & DISK_FULL != 0 {
+        Some(responses::groups::DISK_FULL)
+    } else if stall & FLUSH_FAILED != 0 {
+        Some(responses::groups::FLUSH_FAILED)
+    } else {
+        None
+    }
+}
+
+/// Stall writes to `keyspace` (named `name`) for the given reason, logging it if they
+/// weren't stalled for it already
+pub fn stall_keyspace(name: &str, keyspace: &Keyspace, reason: u8) {
+    if keyspace.set_stall(reason) {
+        log::error!(
+            "Writes to keyspace `{}` are stalled ({})",
+            name,
+            as_str(reason).unwrap_or_default()
+        );
+    }
+}
+
+/// Lift the stall of `keyspace` (named `name`) for the given reason, logging it if writes
+/// were stalled for it
+pub fn resume_keyspace(name: &str, keyspace: &Keyspace, reason: u8) {
+    if keyspace.clear_stall(reason) {
+        log::info!(
+            "Writes to keyspace `{}` are no longer stalled by `{}`",
+            name,
+            as_str(reason).unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(unix)]
+/// Returns the space (in bytes) that is available to us on the disk that `path` is on
+pub fn free_space(path: &str) -> Option<u64> {
+    use std::ffi::CString;
+    let path = CString::new(path).ok()?;
+    unsafe {
+        let mut stat: libc::statvfs = core::mem::zeroed();
+        if libc::statvfs(path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+    }
+}
+
+#[cfg(windows)]
+/// Returns the space (in bytes) that is available to us on the disk that `path` is on
+pub fn free_space(path: &str) -> Option<u64> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+    use winapi::um::winnt::ULARGE_INTEGER;
+    let path: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
+    unsafe {
+        let mut available: ULARGE_INTEGER = core::mem::zeroed();
+        let ret = GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            core::ptr::null_mut(),
+            core::ptr::null_mut(),
+        );
+        if ret == 0 {
+            return None;
+        }
+        Some(*available.QuadPart())
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+/// The free space can't be found on this platform
+pub fn free_space(_path: &str) -> Option<u64> {
+    None
+}
+
+#[test]
+fn test_stall_response() {
+    assert_eq!(response(0), None);
+    assert_eq!(
+        response(FLUSH_FAILED),
+        Some(responses::groups::FLUSH_FAILED)
+    );
+    // running out of space is the more pressing problem
+    assert_eq!(
+        response(DISK_FULL | FLUSH_FAILED),
+        Some(responses::groups::DISK_FULL)
+    );
+    assert_eq!(as_str(FLUSH_FAILED), Some("flush-failed"));
+    assert!(free_space(".").is_some() || cfg!(not(any(unix, windows))));
+}
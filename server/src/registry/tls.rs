@@ -0,0 +1,122 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # TLS policies
+//!
+//! Every TLS listener can restrict what its clients can negotiate with a `policy` table in
+//! its TLS settings (`[ssl.policy]`, or `policy = { ... }` in the `tls` of a `[[listener]]`):
+//! - `min_version`: the oldest TLS version that is accepted (`"1.2"` or `"1.3"`)
+//! - `ciphers`: the cipher suites for TLS 1.2, as an OpenSSL cipher list
+//! - `ciphersuites`: the cipher suites for TLS 1.3, separated by colons
+//! - `curves`: the curves (or groups) for the key exchange, most preferred first
+//!
+//! Anything that isn't set is left to OpenSSL's defaults. The policy of every TLS listener
+//! that has been started is reported by `SYS INFO`, keyed by its port
+
+use crate::corestore::lazy::Lazy;
+use parking_lot::RwLock;
+use serde::Deserialize;
+
+/// The policies of the TLS listeners that have been started, by port
+static POLICIES: Lazy<RwLock<Vec<(u16, TlsPolicy)>>, fn() -> RwLock<Vec<(u16, TlsPolicy)>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+/// A TLS protocol version
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            TlsVersion::Tls12 => "1.2",
+            TlsVersion::Tls13 => "1.3",
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Deserialize)]
+/// What the clients of a TLS listener can negotiate (OpenSSL's defaults for anything that
+/// isn't set)
+pub struct TlsPolicy {
+    /// The oldest TLS version that is accepted
+    pub min_version: Option<TlsVersion>,
+    /// The cipher suites for TLS 1.2, as an OpenSSL cipher list
+    pub ciphers: Option<String>,
+    /// The cipher suites for TLS 1.3, separated by colons
+    pub ciphersuites: Option<String>,
+    /// The curves for the key exchange, most preferred first
+    pub curves: Option<Vec<String>>,
+}
+
+impl TlsPolicy {
+    /// Returns true if the policy sets an empty list of ciphers or curves, which would leave
+    /// clients with nothing to negotiate
+    pub fn has_empty_list(&self) -> bool {
+        let empty = |list: &Option<String>| list.as_ref().map_or(false, |l| l.trim().is_empty());
+        empty(&self.ciphers)
+            || empty(&self.ciphersuites)
+            || self.curves.as_ref().map_or(false, Vec::is_empty)
+    }
+}
+
+/// Record the policy of a TLS listener that has been started on `port`
+pub fn register(port: u16, policy: TlsPolicy) {
+    let mut policies = POLICIES.write();
+    policies.retain(|(p, _)| *p != port);
+    policies.push((port, policy));
+}
+
+/// Returns the policy of every TLS listener as `(name, value)` pairs for `SYS INFO`, like
+/// `tls.2004.min_version` (`default` for anything that is left to OpenSSL)
+pub fn info() -> Vec<(String, String)> {
+    let policies = POLICIES.read();
+    let mut info = Vec::with_capacity(policies.len() * 4);
+    for (port, policy) in policies.iter() {
+        let or_default = |value: Option<&str>| value.unwrap_or("default").to_owned();
+        info.push((
+            format!("tls.{}.min_version", port),
+            or_default(policy.min_version.as_ref().map(TlsVersion::as_str)),
+        ));
+        info.push((
+            format!("tls.{}.ciphers", port),
+            or_default(policy.ciphers.as_deref()),
+        ));
+        info.push((
+            format!("tls.{}.ciphersuites", port),
+            or_default(policy.ciphersuites.as_deref()),
+        ));
+        info.push((
+            format!("tls.{}.curves", port),
+            or_default(policy.curves.as_ref().map(|c| c.join(":")).as_deref()),
+        ));
+    }
+    info
+}
@@ -0,0 +1,258 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Thread topology
+//!
+//! On a dedicated host, the jitter in query latencies can be cut down by keeping the threads
+//! that serve connections (the worker threads of the runtime, which also accept connections)
+//! on cores of their own, away from the threads that do heavy background work (snapshots,
+//! flushes, BGSAVE, recovery and the like). This is set with the `[topology]` section in the
+//! config file:
+//! - `workers`: the number of worker threads (one per CPU if `0`)
+//! - `workercores`: the cores that the worker threads are pinned to, one core per thread
+//! (taking turns if there are more threads than cores)
+//! - `backgroundcores`: the cores that the background threads are pinned to (any of them)
+//! - `numa`: pin every worker thread to the cores of a [NUMA node](super::numa) instead
+//! (taking turns between the nodes), so that the memory that it allocates stays on its node
+//!
+//! Threads aren't pinned if no cores are given. Pinning is only supported on Linux and
+//! Windows; on other platforms, the cores are ignored (with a warning)
+
+use super::numa;
+use crate::corestore::lazy::Lazy;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use parking_lot::RwLock;
+
+static TOPOLOGY: Lazy<RwLock<Topology>, fn() -> RwLock<Topology>> =
+    Lazy::new(|| RwLock::new(Topology::default()));
+/// The cores of the NUMA nodes that the worker threads are spread across (if any)
+static NODES: Lazy<RwLock<Vec<Vec<usize>>>, fn() -> RwLock<Vec<Vec<usize>>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+/// The number of runtime threads that have started so far
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+/// Set if a thread couldn't be pinned
+static PIN_FAILED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+/// The number of worker threads and the cores that threads are pinned to
+pub struct Topology {
+    workers: usize,
+    workercores: Vec<usize>,
+    backgroundcores: Vec<usize>,
+    numa: bool,
+}
+
+impl Topology {
+    pub const fn new(workers: usize, workercores: Vec<usize>, backgroundcores: Vec<usize>) -> Self {
+        Self {
+            workers,
+            workercores,
+            backgroundcores,
+            numa: false,
+        }
+    }
+    /// Pin the worker threads to NUMA nodes
+    pub fn with_numa(mut self, numa: bool) -> Self {
+        self.numa = numa;
+        self
+    }
+    /// Returns true if the worker threads are pinned to both cores and NUMA nodes
+    pub fn has_conflicting_pins(&self) -> bool {
+        self.numa && !self.workercores.is_empty()
+    }
+    /// Returns the first core that threads are pinned to but the host doesn't have, if any
+    pub fn missing_core(&self, cpus: usize) -> Option<usize> {
+        self.workercores
+            .iter()
+            .chain(self.backgroundcores.iter())
+            .copied()
+            .find(|core| *core >= cpus)
+    }
+    /// Returns true if a core is shared by the worker threads and the background threads
+    pub fn has_shared_core(&self) -> bool {
+        self.workercores
+            .iter()
+            .any(|core| self.backgroundcores.contains(core))
+    }
+    /// Returns the number of worker threads
+    fn worker_threads(&self) -> usize {
+        match self.workers {
+            0 => num_cpus::get(),
+            workers => workers,
+        }
+    }
+}
+
+/// Set the thread topology. This has to be done before the runtime is built
+pub fn set_topology(topology: Topology) {
+    if !(topology.workercores.is_empty() && topology.backgroundcores.is_empty())
+        && cfg!(not(any(target_os = "linux", windows)))
+    {
+        log::warn!("Pinning threads to cores isn't supported on this platform");
+    }
+    if topology.numa {
+        let nodes = numa::nodes();
+        if nodes.is_empty() {
+            log::warn!("No NUMA nodes were found, so the worker threads won't be pinned to any");
+        } else {
+            log::info!(
+                "Spreading the worker threads across {} NUMA nodes",
+                nodes.len()
+            );
+        }
+        *NODES.write() = nodes;
+    }
+    *TOPOLOGY.write() = topology;
+}
+
+/// Returns the number of worker threads that the runtime should have
+pub fn worker_threads() -> usize {
+    TOPOLOGY.read().worker_threads()
+}
+
+/// Pin a thread of the runtime as it starts. The runtime starts all of its worker threads
+/// as soon as it's built, so the first ones to start are the worker threads, and any thread
+/// started after them is a blocking thread (running background work like a snapshot)
+pub fn on_thread_start() {
+    let index = STARTED.fetch_add(1, Ordering::Relaxed);
+    let topology = TOPOLOGY.read();
+    if index < topology.worker_threads() {
+        let nodes = NODES.read();
+        if !nodes.is_empty() {
+            pin_or_warn(&nodes[index % nodes.len()]);
+        } else if !topology.workercores.is_empty() {
+            let core = topology.workercores[index % topology.workercores.len()];
+            pin_or_warn(&[core]);
+        }
+    } else if !topology.backgroundcores.is_empty() {
+        pin_or_warn(&topology.backgroundcores);
+    }
+}
+
+/// Pin a thread that does background work (like writing a snapshot) to the background cores
+pub fn pin_background() {
+    let topology = TOPOLOGY.read();
+    if !topology.backgroundcores.is_empty() {
+        pin_or_warn(&topology.backgroundcores);
+    }
+}
+
+/// Returns the effective topology as `(name, value)` pairs: the number of `cpus` and
+/// `workers`, the `workercores` and `backgroundcores` (comma separated, or `any` if the
+/// threads aren't pinned) and the number of NUMA nodes that the worker threads are spread
+/// across (`numanodes`, `0` if they aren't), like `topology.workers`
+pub fn info() -> Vec<(String, String)> {
+    let topology = TOPOLOGY.read();
+    let pinned = cfg!(any(target_os = "linux", windows)) && !PIN_FAILED.load(Ordering::Relaxed);
+    let cores = |cores: &[usize]| {
+        if pinned && !cores.is_empty() {
+            let cores: Vec<String> = cores.iter().map(usize::to_string).collect();
+            cores.join(",")
+        } else {
+            "any".to_owned()
+        }
+    };
+    vec![
+        ("topology.cpus".to_owned(), num_cpus::get().to_string()),
+        (
+            "topology.workers".to_owned(),
+            topology.worker_threads().to_string(),
+        ),
+        (
+            "topology.workercores".to_owned(),
+            cores(&topology.workercores),
+        ),
+        (
+            "topology.backgroundcores".to_owned(),
+            cores(&topology.backgroundcores),
+        ),
+        (
+            "topology.numanodes".to_owned(),
+            if pinned { NODES.read().len() } else { 0 }.to_string(),
+        ),
+    ]
+}
+
+fn pin_or_warn(cores: &[usize]) {
+    if cfg!(any(target_os = "linux", windows)) && !pin(cores) {
+        log::warn!("Failed to pin a thread to the cores {:?}", cores);
+        PIN_FAILED.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Pin the current thread to the given cores, returning true if it was pinned
+fn pin(cores: &[usize]) -> bool {
+    unsafe {
+        // SAFETY: an all-zero `cpu_set_t` is an empty set, and `CPU_SET` ignores cores that
+        // don't fit in the set
+        let mut set: libc::cpu_set_t = core::mem::zeroed();
+        for core in cores {
+            libc::CPU_SET(*core, &mut set);
+        }
+        libc::sched_setaffinity(0, core::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(windows)]
+/// Pin the current thread to the given cores, returning true if it was pinned
+fn pin(cores: &[usize]) -> bool {
+    use winapi::um::processthreadsapi::GetCurrentThread;
+    use winapi::um::winbase::SetThreadAffinityMask;
+    let mask = cores
+        .iter()
+        .filter_map(|core| 1usize.checked_shl(*core as u32))
+        .fold(0, |mask, core| mask | core);
+    unsafe {
+        // SAFETY: the pseudo handle of the current thread is always valid
+        SetThreadAffinityMask(GetCurrentThread(), mask) != 0
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+/// Threads can't be pinned on this platform
+fn pin(_cores: &[usize]) -> bool {
+    false
+}
+
+#[test]
+fn test_topology_checks() {
+    let topology = Topology::new(2, vec![0, 1], vec![2, 3]);
+    assert_eq!(topology.missing_core(4), None);
+    assert_eq!(topology.missing_core(3), Some(3));
+    assert!(!topology.has_shared_core());
+    assert!(Topology::new(2, vec![0, 1], vec![1]).has_shared_core());
+    assert!(Topology::new(2, vec![0], vec![])
+        .with_numa(true)
+        .has_conflicting_pins());
+    assert!(!Topology::new(2, vec![], vec![1])
+        .with_numa(true)
+        .has_conflicting_pins());
+    assert_eq!(
+        Topology::new(0, vec![], vec![]).worker_threads(),
+        num_cpus::get()
+    );
+}
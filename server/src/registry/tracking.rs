@@ -0,0 +1,255 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Key tracking
+//!
+//! A connection that has turned tracking on (with `TRACKING ON`) has the keys that it reads
+//! remembered, and is sent an invalidation once any of those keys changes, so that it can
+//! keep a local cache of what it read without ever serving stale values from it. A key is
+//! forgotten once it's invalidated, so a connection is only told about it again after it
+//! reads it again.
+//!
+//! Invalidations are queued on the connection's [`Tracker`] and written out by the connection
+//! in between queries as push frames (see [`Invalidation::encode`]). Only the [`MAX_TRACKED`]
+//! most recent reads are remembered: once there are more, the oldest ones are invalidated
+//! early, which is always safe
+
+use crate::corestore::lazy::Lazy;
+use bytes::Bytes;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Weak};
+use tokio::sync::Notify;
+
+/// The maximum number of reads that are remembered (across all the connections)
+pub const MAX_TRACKED: usize = 1 << 20;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+/// the number of reads that are remembered, so that writes don't have to lock anything
+/// when nothing is tracked
+static TRACKED: AtomicUsize = AtomicUsize::new(0);
+static STATE: Lazy<Mutex<State>, fn() -> Mutex<State>> = Lazy::new(|| Mutex::new(State::new()));
+
+/// A table (as `<keyspace>:<table>`) and a key in it
+type TrackedKey = (String, Bytes);
+
+struct State {
+    /// the trackers of the connections that have tracking on
+    trackers: HashMap<u64, Weak<Tracker>>,
+    /// the connections that read every key
+    readers: HashMap<TrackedKey, Vec<u64>>,
+    /// the keys that connections started tracking, oldest first (a key can be in here more
+    /// than once)
+    reads: VecDeque<TrackedKey>,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            trackers: HashMap::new(),
+            readers: HashMap::new(),
+            reads: VecDeque::new(),
+        }
+    }
+    /// Forget the readers of `key`, queueing an invalidation for each of them
+    fn invalidate(&mut self, key: &TrackedKey, out: &mut Vec<(Arc<Tracker>, TrackedKey)>) {
+        if let Some(readers) = self.readers.remove(key) {
+            for id in readers {
+                if let Some(tracker) = self.trackers.get(&id).and_then(Weak::upgrade) {
+                    out.push((tracker, key.clone()));
+                }
+            }
+        }
+    }
+    fn update_count(&self) {
+        TRACKED.store(self.readers.len(), Ordering::Release);
+    }
+}
+
+/// Queue every invalidation on its tracker, once the state is unlocked
+fn deliver(invalidated: Vec<(Arc<Tracker>, TrackedKey)>) {
+    for (tracker, (table, key)) in invalidated {
+        tracker.queue(table, key);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Keys of a table that have changed since a connection read them
+pub struct Invalidation {
+    /// the table, as `<keyspace>:<table>`
+    pub table: String,
+    pub keys: Vec<Bytes>,
+}
+
+impl Invalidation {
+    /// Serialize this invalidation into a push frame: `>1\n` followed by a typed array of
+    /// binary strings with the table and then the keys
+    pub fn encode(&self) -> Vec<u8> {
+        let mut frame = format!(">1\n@?{}\n", self.keys.len() + 1).into_bytes();
+        let elements =
+            std::iter::once(self.table.as_bytes()).chain(self.keys.iter().map(|k| k.as_ref()));
+        for element in elements {
+            frame.extend_from_slice(element.len().to_string().as_bytes());
+            frame.push(b'\n');
+            frame.extend_from_slice(element);
+            frame.push(b'\n');
+        }
+        frame
+    }
+}
+
+#[derive(Debug)]
+/// The tracking state of a connection: the invalidations that are waiting to be sent
+pub struct Tracker {
+    id: u64,
+    pending: Mutex<Vec<Invalidation>>,
+    notify: Notify,
+}
+
+impl Tracker {
+    /// Turn tracking on for a connection
+    pub fn start() -> Arc<Self> {
+        let tracker = Arc::new(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            pending: Mutex::new(Vec::new()),
+            notify: Notify::new(),
+        });
+        STATE
+            .lock()
+            .trackers
+            .insert(tracker.id, Arc::downgrade(&tracker));
+        tracker
+    }
+    /// Remember that this connection read the given keys from `table`. This has to be done
+    /// before the keys are read, so that a write that lands in between isn't missed
+    pub fn record(&self, table: &str, keys: Vec<Bytes>) {
+        let mut guard = STATE.lock();
+        let state = &mut *guard;
+        let mut evicted = Vec::new();
+        for key in keys {
+            let key = (table.to_owned(), key);
+            let readers = state.readers.entry(key.clone()).or_insert_with(Vec::new);
+            if !readers.contains(&self.id) {
+                readers.push(self.id);
+                state.reads.push_back(key);
+            }
+        }
+        while state.reads.len() > MAX_TRACKED {
+            let oldest = state.reads.pop_front().unwrap();
+            state.invalidate(&oldest, &mut evicted);
+        }
+        state.update_count();
+        drop(guard);
+        deliver(evicted);
+    }
+    fn queue(&self, table: String, key: Bytes) {
+        let mut pending = self.pending.lock();
+        match pending.iter_mut().find(|inv| inv.table == table) {
+            Some(inv) => inv.keys.push(key),
+            None => pending.push(Invalidation {
+                table,
+                keys: vec![key],
+            }),
+        }
+        drop(pending);
+        self.notify.notify_one();
+    }
+    /// Wait until there are invalidations to be sent
+    pub async fn notified(&self) {
+        self.notify.notified().await
+    }
+    /// Take the invalidations that are waiting to be sent
+    pub fn take_pending(&self) -> Vec<Invalidation> {
+        std::mem::take(&mut *self.pending.lock())
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        // the keys that it read are forgotten as they're invalidated
+        STATE.lock().trackers.remove(&self.id);
+    }
+}
+
+/// Returns true if any connection has read keys that haven't been invalidated yet
+pub fn is_active() -> bool {
+    TRACKED.load(Ordering::Acquire) != 0
+}
+
+/// Invalidate the given keys of `table`, which have just changed
+pub fn invalidate(table: &str, keys: Vec<Bytes>) {
+    let mut state = STATE.lock();
+    let mut invalidated = Vec::new();
+    for key in keys {
+        state.invalidate(&(table.to_owned(), key), &mut invalidated);
+    }
+    state.update_count();
+    drop(state);
+    deliver(invalidated);
+}
+
+/// Invalidate every key that was read, for changes that don't say which keys they change
+pub fn invalidate_all() {
+    let mut state = STATE.lock();
+    let mut invalidated = Vec::new();
+    let keys: Vec<TrackedKey> = state.readers.keys().cloned().collect();
+    for key in keys.iter() {
+        state.invalidate(key, &mut invalidated);
+    }
+    state.reads.clear();
+    state.update_count();
+    drop(state);
+    deliver(invalidated);
+}
+
+#[test]
+fn test_tracking_invalidate() {
+    let tracker = Tracker::start();
+    tracker.record(
+        "trackingtests:tbl",
+        vec![Bytes::from("x"), Bytes::from("y")],
+    );
+    invalidate(
+        "trackingtests:tbl",
+        vec![Bytes::from("x"), Bytes::from("z")],
+    );
+    let pending = tracker.take_pending();
+    assert_eq!(
+        pending,
+        vec![Invalidation {
+            table: "trackingtests:tbl".to_owned(),
+            keys: vec![Bytes::from("x")],
+        }]
+    );
+    assert_eq!(
+        pending[0].encode(),
+        b">1\n@?2\n17\ntrackingtests:tbl\n1\nx\n".to_vec()
+    );
+    // a key is forgotten once it's invalidated
+    invalidate("trackingtests:tbl", vec![Bytes::from("x")]);
+    assert!(tracker.take_pending().is_empty());
+}
@@ -0,0 +1,213 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Nested responses
+//!
+//! The writers in [`writer`](super::writer) can only write out flat arrays. Responses that
+//! need arrays inside arrays or key/value pairs are first built as a tree of
+//! [`ResponseElement`]s and then serialized in one go. Arrays use the `&` tsymbol and maps
+//! use the `%` tsymbol: a map is written as `%<number of pairs>\n` followed by every key
+//! element and its value element
+
+use crate::corestore::buffers::Integer64;
+use crate::protocol::responses::groups;
+use crate::resp::{IsConnection, Writable, TSYMBOL_BINARY, TSYMBOL_UNICODE};
+use bytes::Bytes;
+use std::future::Future;
+use std::io::Error as IoError;
+use std::pin::Pin;
+
+const TSYMBOL_ARRAY: u8 = b'&';
+const TSYMBOL_MAP: u8 = b'%';
+const TSYMBOL_INT: u8 = b':';
+
+#[derive(Debug, PartialEq)]
+/// An element of a response, which may contain other elements
+pub enum ResponseElement {
+    /// A unicode string; tsymbol: `+`
+    String(Bytes),
+    /// A binary string; tsymbol: `?`
+    Binary(Bytes),
+    /// An unsigned integer; tsymbol: `:`
+    UnsignedInt(u64),
+    /// The `Nil` response code
+    Nil,
+    /// An array of (possibly nested) elements; tsymbol: `&`
+    Array(Vec<ResponseElement>),
+    /// A list of key/value pairs; tsymbol: `%`
+    Map(Vec<(ResponseElement, ResponseElement)>),
+}
+
+impl ResponseElement {
+    /// Create an array from an iterator of elements
+    pub fn array(elements: impl IntoIterator<Item = ResponseElement>) -> Self {
+        Self::Array(elements.into_iter().collect())
+    }
+    /// Create a map from an iterator of key/value pairs
+    pub fn map(pairs: impl IntoIterator<Item = (ResponseElement, ResponseElement)>) -> Self {
+        Self::Map(pairs.into_iter().collect())
+    }
+    /// Serialize this element (and all its children) into `buf`
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::String(s) => encode_sized(buf, TSYMBOL_UNICODE, s),
+            Self::Binary(b) => encode_sized(buf, TSYMBOL_BINARY, b),
+            Self::UnsignedInt(int) => {
+                encode_sized(buf, TSYMBOL_INT, &Integer64::init(*int));
+            }
+            Self::Nil => buf.extend_from_slice(groups::NIL),
+            Self::Array(elements) => {
+                encode_header(buf, TSYMBOL_ARRAY, elements.len());
+                elements.iter().for_each(|element| element.encode_into(buf));
+            }
+            Self::Map(pairs) => {
+                encode_header(buf, TSYMBOL_MAP, pairs.len());
+                pairs.iter().for_each(|(key, value)| {
+                    key.encode_into(buf);
+                    value.encode_into(buf);
+                });
+            }
+        }
+    }
+    /// Serialize this element into a new buffer
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+        buf
+    }
+}
+
+/// Returns the header of an array with `len` elements, for responses that write out the
+/// elements of an array one by one instead of building all of them first
+pub fn array_header(len: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_header(&mut buf, TSYMBOL_ARRAY, len);
+    buf
+}
+
+/// Write `<tsymbol><len>\n`
+fn encode_header(buf: &mut Vec<u8>, tsymbol: u8, len: usize) {
+    buf.push(tsymbol);
+    buf.extend_from_slice(&Integer64::from(len));
+    buf.push(b'\n');
+}
+
+/// Write `<tsymbol><len>\n<payload>\n`
+fn encode_sized(buf: &mut Vec<u8>, tsymbol: u8, payload: &[u8]) {
+    encode_header(buf, tsymbol, payload.len());
+    buf.extend_from_slice(payload);
+    buf.push(b'\n');
+}
+
+impl From<&'static str> for ResponseElement {
+    fn from(s: &'static str) -> Self {
+        Self::String(Bytes::from_static(s.as_bytes()))
+    }
+}
+
+impl From<String> for ResponseElement {
+    fn from(s: String) -> Self {
+        Self::String(Bytes::from(s))
+    }
+}
+
+impl From<u64> for ResponseElement {
+    fn from(int: u64) -> Self {
+        Self::UnsignedInt(int)
+    }
+}
+
+impl Writable for ResponseElement {
+    fn write<'s>(
+        self,
+        con: &'s mut impl IsConnection,
+    ) -> Pin<Box<(dyn Future<Output = Result<(), IoError>> + Send + Sync + 's)>> {
+        self.encode().write(con)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResponseElement;
+    use crate::protocol::{Element, Parser, Query};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_encode_scalars() {
+        assert_eq!(ResponseElement::from("sayan").encode(), b"+5\nsayan\n");
+        assert_eq!(
+            ResponseElement::Binary(Bytes::from_static(b"\xF0\x90")).encode(),
+            b"?2\n\xF0\x90\n"
+        );
+        assert_eq!(ResponseElement::from(1234u64).encode(), b":4\n1234\n");
+        assert_eq!(ResponseElement::Nil.encode(), b"!1\n1\n");
+    }
+
+    #[test]
+    fn test_encode_map() {
+        let map = ResponseElement::map(vec![
+            ("version".into(), "0.7.0".into()),
+            (
+                "tables".into(),
+                ResponseElement::array(vec!["a".into(), "b".into()]),
+            ),
+            ("count".into(), 2u64.into()),
+        ]);
+        assert_eq!(
+            map.encode(),
+            b"%3\n+7\nversion\n+5\n0.7.0\n+6\ntables\n&2\n+1\na\n+1\nb\n+5\ncount\n:1\n2\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_nested_map() {
+        let map = ResponseElement::map(vec![(
+            "default".into(),
+            ResponseElement::map(vec![("default".into(), ResponseElement::Nil)]),
+        )]);
+        assert_eq!(map.encode(), b"%1\n+7\ndefault\n%1\n+7\ndefault\n!1\n1\n");
+    }
+
+    #[test]
+    fn test_deep_nesting_roundtrip() {
+        // the server's own parser understands nested arrays, so use it to check our encoding
+        const DEPTH: usize = 256;
+        let mut response = ResponseElement::array(vec!["bottom".into(), 0u64.into()]);
+        let mut expected = Element::Array(vec![
+            Element::String(Bytes::from_static(b"bottom")),
+            Element::UnsignedInt(0),
+        ]);
+        for level in 1..DEPTH as u64 {
+            response = ResponseElement::array(vec![level.into(), response]);
+            expected = Element::Array(vec![Element::UnsignedInt(level), expected]);
+        }
+        let mut packet = b"*1\n".to_vec();
+        response.encode_into(&mut packet);
+        let (query, forward_by) = Parser::new(&packet).parse().unwrap();
+        assert_eq!(forward_by, packet.len());
+        assert_eq!(query, Query::SimpleQuery(expected));
+    }
+}
@@ -32,9 +32,12 @@ use bytes::Bytes;
 use skytable::RespCode;
 use std::future::Future;
 use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::IoSlice;
 use std::pin::Pin;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
+pub mod builder;
 pub mod writer;
 
 pub const TSYMBOL_BINARY: u8 = b'?';
@@ -66,6 +69,12 @@ pub trait IsConnection: std::marker::Sync + std::marker::Send {
         &'s mut self,
         bytes: &'s [u8],
     ) -> Pin<Box<dyn Future<Output = Result<(), IoError>> + Send + Sync + 's>>;
+    /// Write all of `bufs` (in order) using vectored writes, picking up where a partial
+    /// write left off
+    fn write_lowlevel_vectored<'s>(
+        &'s mut self,
+        bufs: &'s [&'s [u8]],
+    ) -> Pin<Box<dyn Future<Output = Result<(), IoError>> + Send + Sync + 's>>;
 }
 
 impl<T> IsConnection for T
@@ -78,6 +87,70 @@ where
     ) -> Pin<Box<dyn Future<Output = Result<(), IoError>> + Send + Sync + 's>> {
         Box::pin(self.write_all(bytes))
     }
+    fn write_lowlevel_vectored<'s>(
+        &'s mut self,
+        bufs: &'s [&'s [u8]],
+    ) -> Pin<Box<dyn Future<Output = Result<(), IoError>> + Send + Sync + 's>> {
+        Box::pin(async move {
+            let mut remaining = RemainingSlices::new(bufs);
+            while !remaining.is_empty() {
+                let mut slices = [IoSlice::new(&[]); MAX_IOSLICES];
+                let count = remaining.fill(&mut slices);
+                let written = self.write_vectored(&slices[..count]).await?;
+                if written == 0 {
+                    return Err(IoError::from(ErrorKind::WriteZero));
+                }
+                remaining.advance(written);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The most slices that are passed to a single vectored write
+const MAX_IOSLICES: usize = 8;
+
+/// The slices (or parts of them) that are yet to be written
+struct RemainingSlices<'a> {
+    bufs: &'a [&'a [u8]],
+    /// the number of bytes of the first slice that have already been written
+    offset: usize,
+}
+
+impl<'a> RemainingSlices<'a> {
+    fn new(bufs: &'a [&'a [u8]]) -> Self {
+        let mut slf = Self { bufs, offset: 0 };
+        // skip any leading empty slices
+        slf.advance(0);
+        slf
+    }
+    fn is_empty(&self) -> bool {
+        self.bufs.is_empty()
+    }
+    /// Fill `out` with the remaining (non-empty) slices, returning how many were filled in
+    fn fill(&self, out: &mut [IoSlice<'a>]) -> usize {
+        let mut count = 0;
+        let rest = self.bufs.iter().skip(1).filter(|buf| !buf.is_empty());
+        let first = self.bufs.first().map(|buf| &buf[self.offset..]);
+        for buf in first.into_iter().chain(rest.copied()).take(out.len()) {
+            out[count] = IoSlice::new(buf);
+            count += 1;
+        }
+        count
+    }
+    /// Mark `written` bytes as written
+    fn advance(&mut self, mut written: usize) {
+        while let Some((first, rest)) = self.bufs.split_first() {
+            let left = first.len() - self.offset;
+            if left > written {
+                self.offset += written;
+                return;
+            }
+            written -= left;
+            self.bufs = rest;
+            self.offset = 0;
+        }
+    }
 }
 
 /// A `BytesWrapper` object wraps around a `Bytes` object that might have been pulled
@@ -107,6 +180,17 @@ impl Writable for Vec<u8> {
     }
 }
 
+/// A response that was serialized earlier (like the ones in a
+/// [response cache](crate::kvengine::respcache))
+impl Writable for Bytes {
+    fn write<'s>(
+        self,
+        con: &'s mut impl IsConnection,
+    ) -> Pin<Box<(dyn Future<Output = Result<(), IoError>> + Send + Sync + 's)>> {
+        Box::pin(async move { con.write_lowlevel(&self).await })
+    }
+}
+
 impl<const N: usize> Writable for [u8; N] {
     fn write<'s>(
         self,
@@ -139,18 +223,11 @@ impl Writable for &'static str {
             // string (we represent `String`s as `Byte` objects internally)
             // and since `Bytes` are effectively `String`s we will append the
             // type operator `+` to the stream
-            con.write_lowlevel(&[b'+']).await?;
             // Now get the size of the Bytes object as bytes
             let size = Integer64::from(bytes.len());
-            // Write this to the stream
-            con.write_lowlevel(&size).await?;
-            // Now write a LF character
-            con.write_lowlevel(&[b'\n']).await?;
-            // Now write the REAL bytes (of the object)
-            con.write_lowlevel(bytes.as_bytes()).await?;
-            // Now write another LF
-            con.write_lowlevel(&[b'\n']).await?;
-            Ok(())
+            // Then write out `+<size>\n<bytes>\n` in one go
+            con.write_lowlevel_vectored(&[b"+", &size, b"\n", bytes.as_bytes(), b"\n"])
+                .await
         }
         Box::pin(write_bytes(con, self))
     }
@@ -166,23 +243,36 @@ impl Writable for BytesWrapper {
             // string (we represent `String`s as `Byte` objects internally)
             // and since `Bytes` are effectively `String`s we will append the
             // type operator `+` to the stream
-            con.write_lowlevel(&[b'+']).await?;
             // Now get the size of the Bytes object as bytes
             let size = Integer64::from(bytes.len());
-            // Write this to the stream
-            con.write_lowlevel(&size).await?;
-            // Now write a LF character
-            con.write_lowlevel(&[b'\n']).await?;
-            // Now write the REAL bytes (of the object)
-            con.write_lowlevel(&bytes).await?;
-            // Now write another LF
-            con.write_lowlevel(&[b'\n']).await?;
-            Ok(())
+            // Then write out `+<size>\n<bytes>\n` in one go
+            con.write_lowlevel_vectored(&[b"+", &size, b"\n", &bytes, b"\n"])
+                .await
         }
         Box::pin(write_bytes(con, self.finish_into_bytes()))
     }
 }
 
+/// A `BinaryWrapper` is like a [`BytesWrapper`], except that the payload is written out as
+/// a binary string (`?`) since it isn't guaranteed to be valid unicode
+#[derive(Debug, PartialEq)]
+pub struct BinaryWrapper(pub Bytes);
+
+impl Writable for BinaryWrapper {
+    fn write<'s>(
+        self,
+        con: &'s mut impl IsConnection,
+    ) -> Pin<Box<(dyn Future<Output = Result<(), IoError>> + Send + Sync + 's)>> {
+        async fn write_bytes(con: &mut impl IsConnection, bytes: Bytes) -> Result<(), IoError> {
+            // the tsymbol for a blob is `?`
+            let size = Integer64::from(bytes.len());
+            con.write_lowlevel_vectored(&[&[TSYMBOL_BINARY], &size, b"\n", &bytes, b"\n"])
+                .await
+        }
+        Box::pin(write_bytes(con, self.0))
+    }
+}
+
 impl Writable for RespCode {
     fn write<'s>(
         self,
@@ -290,3 +380,31 @@ impl Writable for ObjectID {
         Box::pin(write_bytes(con, self))
     }
 }
+
+#[test]
+fn test_remaining_slices_partial_writes() {
+    let bufs: [&[u8]; 5] = [b"", b"+", b"5\n", b"", b"hello\n"];
+    let mut remaining = RemainingSlices::new(&bufs);
+    let mut slices = [IoSlice::new(&[]); MAX_IOSLICES];
+    let collect = |slices: &[IoSlice]| -> Vec<Vec<u8>> {
+        slices.iter().map(|slice| slice.to_vec()).collect()
+    };
+    let count = remaining.fill(&mut slices);
+    assert_eq!(
+        collect(&slices[..count]),
+        vec![b"+".to_vec(), b"5\n".to_vec(), b"hello\n".to_vec()]
+    );
+    // a write that ends in the middle of a slice
+    remaining.advance(2);
+    let count = remaining.fill(&mut slices);
+    assert_eq!(
+        collect(&slices[..count]),
+        vec![b"\n".to_vec(), b"hello\n".to_vec()]
+    );
+    // and one that ends right at the end of a slice
+    remaining.advance(1);
+    let count = remaining.fill(&mut slices);
+    assert_eq!(collect(&slices[..count]), vec![b"hello\n".to_vec()]);
+    remaining.advance(6);
+    assert!(remaining.is_empty());
+}
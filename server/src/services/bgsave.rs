@@ -24,35 +24,173 @@
  *
 */
 
+//! # BGSAVE
+//!
+//! The whole store is flushed every `every` seconds. With the adaptive policy (see
+//! [`AdaptiveFlush`]), tables are also checked every `min` seconds in between: _hot_ tables,
+//! that are written to at `hotrate` writes per second or more, are flushed early (hottest
+//! first) as long as the early flushes stay within the I/O budget, while quiet tables are
+//! left for the next full flush.
+
 use crate::config::BGSave;
+use crate::corestore::lazy::Lazy;
+use crate::corestore::memstore::ObjectID;
 use crate::corestore::Corestore;
 use crate::dbnet::Terminator;
+use crate::diskstore::instance;
 use crate::registry;
+use crate::registry::jobs::{Job, JobKind};
+use crate::registry::stall;
 use crate::storage;
+use crate::storage::interface::DIR_KSROOT;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use libsky::TResult;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fs;
+use std::time::Instant;
 use tokio::time::{self, Duration};
 
+/// The default shortest interval (in seconds) at which hot tables are flushed
+pub const DEFAULT_MIN: u64 = 10;
+/// The default write rate (writes per second) from which a table is hot
+pub const DEFAULT_HOT_RATE: u64 = 100;
+
+static ADAPTIVE: Lazy<RwLock<Option<AdaptiveFlush>>, fn() -> RwLock<Option<AdaptiveFlush>>> =
+    Lazy::new(|| RwLock::new(None));
+/// The interval between full flushes (`0` if BGSAVE is disabled)
+static EVERY: AtomicU64 = AtomicU64::new(0);
+/// The number of hot tables found in the last adaptive round
+static HOT_TABLES: AtomicUsize = AtomicUsize::new(0);
+/// The number of hot tables flushed early in the last adaptive round
+static FLUSHED: AtomicUsize = AtomicUsize::new(0);
+/// The number of hot tables left for later in the last adaptive round, for want of budget
+static DEFERRED: AtomicUsize = AtomicUsize::new(0);
+/// The number of bytes written in the last adaptive round
+static WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// The adaptive flush policy
+pub struct AdaptiveFlush {
+    /// the shortest interval (in seconds) at which hot tables are flushed
+    min: u64,
+    /// the write rate (writes per second) from which a table is hot
+    hotrate: u64,
+    /// the most bytes per second that early flushes can write (`0` if unlimited)
+    iobudget: u64,
+}
+
+impl AdaptiveFlush {
+    pub const fn new(min: u64, hotrate: u64, iobudget: u64) -> Self {
+        Self {
+            min,
+            hotrate,
+            iobudget,
+        }
+    }
+    /// Returns true if hot tables are checked for more often than the full flushes
+    pub const fn has_valid_interval(&self, every: u64) -> bool {
+        self.min != 0 && self.min < every
+    }
+    /// Returns the most bytes that the early flushes of one round can write
+    const fn round_budget(&self) -> u64 {
+        if self.iobudget == 0 {
+            u64::MAX
+        } else {
+            self.iobudget.saturating_mul(self.min)
+        }
+    }
+}
+
+/// Set the adaptive flush policy (or `None` to only run full flushes)
+pub fn set_adaptive(policy: Option<AdaptiveFlush>) {
+    *ADAPTIVE.write() = policy;
+}
+
+/// Returns the flush policy and what it did in its last round as `(name, value)` pairs
+pub fn info() -> Vec<(String, String)> {
+    let policy = *ADAPTIVE.read();
+    let every = EVERY.load(Ordering::Relaxed);
+    let name = match policy {
+        _ if every == 0 => "disabled",
+        Some(_) => "adaptive",
+        None => "fixed",
+    };
+    let mut info = vec![
+        ("bgsave.policy".to_owned(), name.to_owned()),
+        ("bgsave.every".to_owned(), every.to_string()),
+    ];
+    if let Some(policy) = policy {
+        info.push(("bgsave.min".to_owned(), policy.min.to_string()));
+        info.push(("bgsave.hotrate".to_owned(), policy.hotrate.to_string()));
+        info.push(("bgsave.iobudget".to_owned(), policy.iobudget.to_string()));
+        info.push((
+            "bgsave.hottables".to_owned(),
+            HOT_TABLES.load(Ordering::Relaxed).to_string(),
+        ));
+        info.push((
+            "bgsave.flushed".to_owned(),
+            FLUSHED.load(Ordering::Relaxed).to_string(),
+        ));
+        info.push((
+            "bgsave.deferred".to_owned(),
+            DEFERRED.load(Ordering::Relaxed).to_string(),
+        ));
+        info.push((
+            "bgsave.written".to_owned(),
+            WRITTEN.load(Ordering::Relaxed).to_string(),
+        ));
+    }
+    info
+}
+
+/// The number of writes that every table had when it was last flushed, and when that was
+type FlushTracker = HashMap<(ObjectID, ObjectID), (u64, Instant)>;
+
 /// The bgsave_scheduler calls the bgsave task in `Corestore` after `every` seconds
 ///
 /// The time after which the scheduler will wake up the BGSAVE task is determined by
 /// `bgsave_cfg` which is to be passed as an argument. If BGSAVE is disabled, this function
-/// immediately returns
+/// immediately returns. With the adaptive policy, the scheduler also wakes up every `min`
+/// seconds to flush the hot tables
 pub async fn bgsave_scheduler(handle: Corestore, bgsave_cfg: BGSave, mut terminator: Terminator) {
     match bgsave_cfg {
         BGSave::Enabled(duration) => {
+            EVERY.store(duration, Ordering::Relaxed);
+            let adaptive = *ADAPTIVE.read();
             // If we're here - the user doesn't trust his power supply or just values
             // his data - which is good! So we'll turn this into a `Duration`
             let duration = Duration::from_secs(duration);
+            let tick = adaptive.map_or(duration, |policy| Duration::from_secs(policy.min));
+            let mut next_full = time::Instant::now() + duration;
+            // the tables that were just restored are already on disk
+            let mut tracker = flushed_now(table_writes(&handle));
             loop {
                 tokio::select! {
-                    // Sleep until `duration` from the current time instant
-                    _ = time::sleep_until(time::Instant::now() + duration) => {
+                    // Sleep until the next round (or the next full flush) from the current time instant
+                    _ = time::sleep_until((time::Instant::now() + tick).min(next_full)) => {
+                        let full = time::Instant::now() >= next_full;
+                        if full {
+                            next_full = time::Instant::now() + duration;
+                        }
                         let cloned_handle = handle.clone();
                         // we spawn this process just to ensure that it doesn't block the runtime's workers
                         // dedicated to async tasks (non-blocking)
-                        tokio::task::spawn_blocking(move || {
+                        tracker = tokio::task::spawn_blocking(move || {
                             let owned_handle = cloned_handle;
-                            let _ = bgsave_blocking_section(owned_handle);
+                            match adaptive {
+                                Some(policy) if !full => {
+                                    flush_hot_tables(&owned_handle, policy, &mut tracker)
+                                }
+                                _ => {
+                                    // the writes made while flushing will count for the next round
+                                    let writes = table_writes(&owned_handle);
+                                    if bgsave_blocking_section(owned_handle) {
+                                        tracker = flushed_now(writes);
+                                    }
+                                }
+                            }
+                            tracker
                         }).await.expect("Something caused the background service to panic");
                     }
                     // Otherwise wait for a notification
@@ -70,25 +208,125 @@ pub async fn bgsave_scheduler(handle: Corestore, bgsave_cfg: BGSave, mut termina
     log::info!("BGSAVE service has exited");
 }
 
+/// Returns the number of writes that every persistent table has had
+fn table_writes(handle: &Corestore) -> Vec<((ObjectID, ObjectID), u64)> {
+    let mut writes = Vec::new();
+    for keyspace in handle.get_store().keyspaces.iter() {
+        for table in keyspace.value().tables.iter() {
+            if !table.value().is_volatile() {
+                writes.push((
+                    (keyspace.key().clone(), table.key().clone()),
+                    table.value().writes(),
+                ));
+            }
+        }
+    }
+    writes
+}
+
+/// Returns a tracker where all the given tables were flushed just now
+fn flushed_now(writes: Vec<((ObjectID, ObjectID), u64)>) -> FlushTracker {
+    let now = Instant::now();
+    writes
+        .into_iter()
+        .map(|(id, writes)| (id, (writes, now)))
+        .collect()
+}
+
+/// Flush the hot tables early, hottest first, within the I/O budget of the round
+///
+/// Only the tables that were in the last full flush are considered: if the tree has changed
+/// since (see [`registry::get_preload_tripswitch`]), the new tables wait for the next full
+/// flush, which writes the tree along with them
+fn flush_hot_tables(handle: &Corestore, policy: AdaptiveFlush, tracker: &mut FlushTracker) {
+    let now = Instant::now();
+    let mut hot: Vec<_> = table_writes(handle)
+        .into_iter()
+        .filter_map(|(id, writes)| {
+            let (flushed_writes, flushed_at) = *tracker.get(&id)?;
+            let elapsed = now.duration_since(flushed_at).as_secs_f64();
+            let rate = writes.saturating_sub(flushed_writes) as f64 / elapsed.max(1.0);
+            if rate >= policy.hotrate as f64 {
+                Some((id, writes, rate))
+            } else {
+                None
+            }
+        })
+        .collect();
+    hot.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(core::cmp::Ordering::Equal));
+    let (mut flushed, mut deferred, mut written) = (0, 0, 0u64);
+    if !hot.is_empty() && instance::is_owner() && !registry::get_preload_tripswitch().is_tripped() {
+        let _flush_lock = registry::lock_flush_state();
+        let budget = policy.round_budget();
+        for ((ksid, tblid), writes, _) in hot.iter() {
+            let (ksname, tblname) = unsafe { (ksid.as_str(), tblid.as_str()) };
+            let path = format!("{}/{}/{}", DIR_KSROOT, ksname, tblname);
+            // the table is assumed to be about as large as it was when it was last written
+            let estimate = fs::metadata(&path).map_or(0, |meta| meta.len());
+            if written.saturating_add(estimate) > budget {
+                deferred += 1;
+                continue;
+            }
+            let keyspace = match handle.get_store().get_keyspace_atomic_ref(ksid) {
+                Some(keyspace) => keyspace,
+                None => continue,
+            };
+            let table = match keyspace.get_table_atomic_ref(tblid) {
+                Some(table) => table,
+                None => continue,
+            };
+            if let Err(e) = storage::flush::oneshot::flush_table(tblid, ksid, &table) {
+                log::error!("Failed to flush `{}:{}`: {}", ksname, tblname, e);
+                stall::stall_keyspace(ksname, &keyspace, stall::FLUSH_FAILED);
+                break;
+            }
+            written += fs::metadata(&path).map_or(estimate, |meta| meta.len());
+            flushed += 1;
+            tracker.insert((ksid.clone(), tblid.clone()), (*writes, now));
+        }
+    }
+    HOT_TABLES.store(hot.len(), Ordering::Relaxed);
+    FLUSHED.store(flushed, Ordering::Relaxed);
+    DEFERRED.store(deferred, Ordering::Relaxed);
+    WRITTEN.store(written, Ordering::Relaxed);
+}
+
 /// Run bgsave
 ///
 /// This function just hides away the BGSAVE blocking section from the _public API_
 pub fn run_bgsave(handle: &Corestore) -> TResult<()> {
+    if !instance::is_owner() {
+        return Err("the data directory has been taken over by another process".into());
+    }
     storage::flush::flush_full(handle.get_store()).map_err(|e| e.into())
 }
 
 /// This just wraps around [`_bgsave_blocking_section`] and prints nice log messages depending on the outcome
 fn bgsave_blocking_section(handle: Corestore) -> bool {
     registry::lock_flush_state();
+    let mut job = Job::start(JobKind::Bgsave);
     match run_bgsave(&handle) {
         Ok(_) => {
             log::info!("BGSAVE completed successfully");
             registry::unpoison();
+            drop(job);
+            // now that we're done flushing, get rid of the holes in the value slabs
+            handle.get_store().compact_slabs();
             true
         }
         Err(e) => {
             log::error!("BGSAVE failed with error: {}", e);
-            registry::poison();
+            // the keyspaces that failed to flush have already been stalled; anything else
+            // (like failing to write the PRELOAD) stops all writes
+            let stalled = handle
+                .get_store()
+                .keyspaces
+                .iter()
+                .any(|ks| ks.value().stall() & stall::FLUSH_FAILED != 0);
+            if !stalled {
+                registry::poison();
+            }
+            job.fail();
             false
         }
     }
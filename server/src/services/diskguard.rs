@@ -0,0 +1,98 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Disk guard
+//!
+//! The disk guard checks the free space on the disk that the data directory is on every
+//! [`CHECK_INTERVAL`], and stalls or resumes writes to the keyspaces accordingly (see
+//! [`stall`]). Once there is enough space, it also retries the keyspaces that failed to
+//! flush, so that their writes resume as soon as they can be persisted again.
+
+use crate::corestore::Corestore;
+use crate::dbnet::Terminator;
+use crate::diskstore::instance;
+use crate::registry;
+use crate::registry::stall;
+use crate::storage::flush;
+use tokio::time::{self, Duration};
+
+/// How often the free space is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// The directory that the data is in
+const DATA_DIR: &str = "data";
+
+/// Check the free space every [`CHECK_INTERVAL`] until we're told to quit
+pub async fn disk_guard(handle: Corestore, mut terminator: Terminator) {
+    loop {
+        tokio::select! {
+            _ = time::sleep(CHECK_INTERVAL) => {
+                let handle = handle.clone();
+                tokio::task::spawn_blocking(move || check(&handle))
+                    .await
+                    .expect("Something caused the disk guard to panic");
+            }
+            _ = terminator.receive_signal() => {
+                // we got a notification to quit; so break out
+                break;
+            }
+        }
+    }
+    log::info!("Disk guard has exited");
+}
+
+/// Stall (or resume) the keyspaces depending on the free space, and retry the keyspaces
+/// that failed to flush if there's enough of it
+fn check(handle: &Corestore) {
+    let free = stall::free_space(DATA_DIR);
+    if let Some(free) = free {
+        stall::set_free(free);
+    }
+    let low = matches!(free, Some(free) if stall::is_low(free));
+    let store = handle.get_store();
+    let mut failed = Vec::new();
+    for keyspace in store.keyspaces.iter() {
+        let name = unsafe { keyspace.key().as_str() };
+        if low {
+            stall::stall_keyspace(name, keyspace.value(), stall::DISK_FULL);
+        } else {
+            stall::resume_keyspace(name, keyspace.value(), stall::DISK_FULL);
+        }
+        if keyspace.value().stall() & stall::FLUSH_FAILED != 0 {
+            failed.push((keyspace.key().clone(), keyspace.value().clone()));
+        }
+    }
+    if low || failed.is_empty() || !instance::is_owner() {
+        return;
+    }
+    let _flush_lock = registry::lock_flush_state();
+    for (ksid, keyspace) in failed {
+        let name = unsafe { ksid.as_str() };
+        match flush::flush_keyspace_full(&ksid, &keyspace) {
+            Ok(()) => stall::resume_keyspace(name, &keyspace, stall::FLUSH_FAILED),
+            Err(e) => log::debug!("Keyspace `{}` still fails to flush: {}", name, e),
+        }
+    }
+}
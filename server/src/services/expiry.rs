@@ -0,0 +1,175 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Expiry jitter
+//!
+//! When many keys are given the same TTL at about the same time (say, a cache that is filled
+//! in one go), they all expire in the same second and the expiry reaper has to remove them in
+//! one sweep, which holds up the worker thread that it runs on. A table can be given a jitter
+//! with a `[[jitter]]` entry in the config file, which stretches every TTL that is set on its
+//! keys (by `GETEX` or `EXPIREAT`) by a random amount of up to `percent` percent of the TTL, so
+//! that the expirations are spread out. Keys never expire earlier than they were asked to.
+//!
+//! How long the sweeps take is reported by `SYS INFO`, so that the effect can be checked
+
+use super::querylog;
+use crate::corestore::lazy::Lazy;
+use crate::corestore::Corestore;
+use crate::kvengine;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::RwLock;
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static JITTERS: Lazy<RwLock<Vec<Jitter>>, fn() -> RwLock<Vec<Jitter>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+/// The number of expiry sweeps that have run
+static SWEEPS: AtomicU64 = AtomicU64::new(0);
+/// The number of keys that the sweeps have removed
+static PURGED: AtomicU64 = AtomicU64::new(0);
+/// The most keys that one sweep has removed
+static MAX_PURGED: AtomicU64 = AtomicU64::new(0);
+/// How long the last sweep took (in microseconds)
+static LAST_SWEEP: AtomicU64 = AtomicU64::new(0);
+/// The longest that a sweep has taken (in microseconds)
+static MAX_SWEEP: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, PartialEq)]
+/// The jitter of a table's TTLs
+pub struct Jitter {
+    /// the table (as `<keyspace>:<table>`)
+    table: String,
+    /// the most that a TTL is stretched by, as a percentage of the TTL
+    percent: u8,
+}
+
+impl Jitter {
+    pub const fn new(table: String, percent: u8) -> Self {
+        Self { table, percent }
+    }
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+    /// Returns true if the percentage isn't between 1 and 100
+    pub const fn is_out_of_range(&self) -> bool {
+        self.percent == 0 || self.percent > 100
+    }
+}
+
+/// Set the jitters of the tables (there's one at most for every table)
+pub fn set_jitters(jitters: Vec<Jitter>) {
+    ENABLED.store(!jitters.is_empty(), Ordering::Release);
+    *JITTERS.write() = jitters;
+}
+
+/// Stretch a deadline (a UNIX timestamp in milliseconds) that is being set on a key of the
+/// connection's current table by the table's jitter, if it has one
+pub fn jitter(handle: &Corestore, deadline: u64) -> u64 {
+    if !ENABLED.load(Ordering::Acquire) {
+        return deadline;
+    }
+    let table = match handle.ctable_name() {
+        Some(table) => table,
+        None => return deadline,
+    };
+    let percent = JITTERS
+        .read()
+        .iter()
+        .find(|jitter| jitter.table == table)
+        .map(|jitter| jitter.percent);
+    match percent {
+        Some(percent) => stretch(
+            deadline,
+            kvengine::now_ms(),
+            percent,
+            querylog::next_random(),
+        ),
+        None => deadline,
+    }
+}
+
+/// Stretch `deadline` by `random` (modulo `percent` percent of the time left until it)
+fn stretch(deadline: u64, now: u64, percent: u8, random: u64) -> u64 {
+    let ttl = deadline.saturating_sub(now);
+    let most = ttl / 100 * percent as u64 + ttl % 100 * percent as u64 / 100;
+    if most == 0 {
+        // a deadline that has passed (or is too close to stretch) is left as it is
+        return deadline;
+    }
+    deadline.saturating_add(random % (most + 1))
+}
+
+/// Record an expiry sweep that removed `purged` keys and took `took`
+pub fn record_sweep(purged: usize, took: Duration) {
+    let took = took.as_micros() as u64;
+    SWEEPS.fetch_add(1, Ordering::Relaxed);
+    PURGED.fetch_add(purged as u64, Ordering::Relaxed);
+    MAX_PURGED.fetch_max(purged as u64, Ordering::Relaxed);
+    LAST_SWEEP.store(took, Ordering::Relaxed);
+    MAX_SWEEP.fetch_max(took, Ordering::Relaxed);
+}
+
+/// Returns the expiry sweep statistics as `(name, value)` pairs, like `expiry.sweeps`
+pub fn info() -> Vec<(String, String)> {
+    vec![
+        (
+            "expiry.sweeps".to_owned(),
+            SWEEPS.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "expiry.purged".to_owned(),
+            PURGED.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "expiry.max_purged".to_owned(),
+            MAX_PURGED.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "expiry.last_sweep_us".to_owned(),
+            LAST_SWEEP.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "expiry.max_sweep_us".to_owned(),
+            MAX_SWEEP.load(Ordering::Relaxed).to_string(),
+        ),
+    ]
+}
+
+#[test]
+fn test_stretch() {
+    let now = 1_000_000;
+    // a 100 second TTL with a 10% jitter ends up between 100 and 110 seconds
+    let deadline = now + 100_000;
+    assert_eq!(stretch(deadline, now, 10, 0), deadline);
+    assert_eq!(stretch(deadline, now, 10, 10_000), deadline + 10_000);
+    assert_eq!(stretch(deadline, now, 10, 10_001), deadline);
+    for random in [3, 4_321, 98_765, u64::MAX].iter() {
+        let stretched = stretch(deadline, now, 10, *random);
+        assert!(stretched >= deadline && stretched <= deadline + 10_000);
+    }
+    // deadlines that have passed aren't stretched
+    assert_eq!(stretch(now - 1, now, 10, 5), now - 1);
+}
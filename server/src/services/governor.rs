@@ -0,0 +1,94 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Memory governor
+//!
+//! The memory governor measures the resident memory of the server every
+//! [`CHECK_INTERVAL`] and moves it between the levels of the memory policy (see
+//! [`pressure`]). While the server is at [`Pressure::Evict`] or above, the volatile tables
+//! are evicted on every check.
+
+use crate::corestore::Corestore;
+use crate::dbnet::Terminator;
+use crate::registry::pressure::{self, Pressure};
+use tokio::time::{self, Duration};
+
+/// How often the resident memory is measured
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Enforce the memory policy (if there is one) until we're told to quit
+pub async fn memory_governor(handle: Corestore, mut terminator: Terminator) {
+    let policy = match pressure::get_policy() {
+        Some(policy) => policy,
+        None => return,
+    };
+    if pressure::rss().is_none() {
+        log::warn!("The memory limit is ignored as memory can't be measured on this platform");
+        return;
+    }
+    loop {
+        tokio::select! {
+            _ = time::sleep(CHECK_INTERVAL) => {
+                let rss = match pressure::rss() {
+                    Some(rss) => rss,
+                    None => continue,
+                };
+                let level = policy.level_for(rss, pressure::level());
+                pressure::set_level(level, rss);
+                if level >= Pressure::Evict {
+                    let handle = handle.clone();
+                    tokio::task::spawn_blocking(move || evict_volatile(&handle))
+                        .await
+                        .expect("Something caused the memory governor to panic");
+                }
+            }
+            _ = terminator.receive_signal() => {
+                // we got a notification to quit; so break out
+                break;
+            }
+        }
+    }
+    log::info!("Memory governor has exited");
+}
+
+/// Empty every volatile table (other than the virtual tables), since they're only caches
+/// that aren't persisted anyway
+fn evict_volatile(handle: &Corestore) {
+    let mut evicted = 0;
+    for keyspace in handle.get_store().keyspaces.iter() {
+        for table in keyspace.value().tables.iter() {
+            let table = table.value();
+            if table.is_volatile() && !table.is_virtual() && table.count() != 0 {
+                evicted += table.count() as u64;
+                table.truncate_table();
+            }
+        }
+    }
+    if evicted != 0 {
+        log::warn!("Evicted {} keys from volatile tables", evicted);
+        pressure::evicted(evicted);
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Latency monitor
+//!
+//! The latency monitor measures the latencies that can't be timed where they happen, and
+//! records them with the [latency registry](crate::registry::latency):
+//! - how late a task that sleeps for [`PROBE_INTERVAL`] wakes up, which is how long a task
+//! that was ready to run had to wait for the worker threads (which is also what
+//! [admission control](crate::registry::admission) goes by)
+//! - how long it takes to allocate (and touch) [`ALLOC_PROBE_SIZE`] bytes
+
+use crate::dbnet::Terminator;
+use crate::registry::admission;
+use crate::registry::latency::{self, Event};
+use std::time::Instant;
+use tokio::time::{self, Duration};
+
+/// How often the latencies are measured
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+/// The size of the allocation that is timed
+const ALLOC_PROBE_SIZE: usize = 1024 * 1024;
+
+/// Measure the latencies every [`PROBE_INTERVAL`] until we're told to quit
+pub async fn latency_monitor(mut terminator: Terminator) {
+    loop {
+        let deadline = time::Instant::now() + PROBE_INTERVAL;
+        tokio::select! {
+            _ = time::sleep_until(deadline) => {
+                let lag = time::Instant::now() - deadline;
+                admission::record_lag(lag);
+                latency::record(Event::EventLoop, lag);
+                latency::record(Event::Alloc, probe_alloc());
+            }
+            _ = terminator.receive_signal() => {
+                // we got a notification to quit; so break out
+                break;
+            }
+        }
+    }
+    log::info!("Latency monitor has exited");
+}
+
+/// Returns the time taken to allocate and fill [`ALLOC_PROBE_SIZE`] bytes
+fn probe_alloc() -> Duration {
+    let start = Instant::now();
+    let probe = vec![0xffu8; ALLOC_PROBE_SIZE];
+    unsafe {
+        // SAFETY: the index is in bounds. The read is volatile so that the allocation isn't
+        // optimized away
+        core::ptr::read_volatile(probe.as_ptr().add(ALLOC_PROBE_SIZE - 1));
+    }
+    start.elapsed()
+}
@@ -25,4 +25,17 @@
 */
 
 pub mod bgsave;
+pub mod diskguard;
+pub mod expiry;
+pub mod governor;
+pub mod latency;
+pub mod origin;
+pub mod querylog;
+pub mod reaper;
+pub mod recovery;
+pub mod schema;
+pub mod shadow;
 pub mod snapshot;
+pub mod systemd;
+pub mod throttle;
+pub mod warmup;
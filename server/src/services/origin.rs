@@ -0,0 +1,282 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Origins
+//!
+//! A table can be put in front of an _origin_ (an HTTP service that owns the data) with an
+//! `[[origin]]` entry in the config file, which turns the table into a cache for the origin:
+//! - If the origin is read-through, a `GET` that misses asks the origin for the key
+//! (`GET <url>/<key>`) and stores what it gets in the table before returning it
+//! - If the origin is write-through, every successful `SET` is propagated to the origin
+//! (`PUT <url>/<key>` with the value as the body) in the background
+//!
+//! Only plain `http://` origins are supported. Keys are percent-encoded in URLs
+
+use crate::corestore::lazy::Lazy;
+use bytes::Bytes;
+use core::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::RwLock;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{self, Duration};
+
+/// The longest that we wait for an origin to respond
+const ORIGIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// The largest response that we accept from an origin
+const MAX_RESPONSE: u64 = 16 * 1024 * 1024;
+const DEFAULT_HTTP_PORT: u16 = 80;
+
+type OriginList = Vec<Arc<Origin>>;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ORIGINS: Lazy<RwLock<OriginList>, fn() -> RwLock<OriginList>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+#[derive(Debug, Clone, PartialEq)]
+/// Where an origin is
+struct Endpoint {
+    /// `<host>:<port>`
+    authority: String,
+    /// the path that the keys are appended to (without the trailing slash)
+    path: String,
+}
+
+impl Endpoint {
+    /// Parse an `http://host[:port][/path]` URL, returning `None` if it isn't one that we
+    /// support
+    fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, ""),
+        };
+        let authority = match authority.rfind(':') {
+            Some(idx) if idx != 0 && authority[idx + 1..].parse::<u16>().is_ok() => {
+                authority.to_owned()
+            }
+            Some(_) => return None,
+            None if !authority.is_empty() => format!("{}:{}", authority, DEFAULT_HTTP_PORT),
+            None => return None,
+        };
+        Some(Self {
+            authority,
+            path: path.trim_end_matches('/').to_owned(),
+        })
+    }
+    /// Returns the path of the given key on the origin
+    fn path_of(&self, key: &[u8]) -> String {
+        let mut path = self.path.clone();
+        path.push('/');
+        for byte in key {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    path.push(*byte as char)
+                }
+                _ => path.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        path
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The origin of a table
+pub struct Origin {
+    /// the table that caches the origin (as `<keyspace>:<table>`)
+    table: String,
+    url: String,
+    /// `None` if the URL isn't one that we support
+    endpoint: Option<Endpoint>,
+    readthrough: bool,
+    writethrough: bool,
+}
+
+impl Origin {
+    pub fn new(table: String, url: String, readthrough: bool, writethrough: bool) -> Self {
+        Self {
+            endpoint: Endpoint::parse(&url),
+            table,
+            url,
+            readthrough,
+            writethrough,
+        }
+    }
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// Returns true if the origin's URL is one that we support
+    pub const fn is_supported(&self) -> bool {
+        self.endpoint.is_some()
+    }
+    /// Send a request to the origin, returning the status code and body of the response
+    async fn request(
+        &self,
+        method: &str,
+        key: &[u8],
+        body: Option<&[u8]>,
+    ) -> Result<(u16, Vec<u8>), IoError> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint,
+            None => return Err(IoError::new(ErrorKind::Other, "unsupported origin URL")),
+        };
+        // HTTP/1.0 so that the body simply runs until the origin closes the connection
+        let mut req = format!(
+            "{} {} HTTP/1.0\r\nHost: {}\r\n",
+            method,
+            endpoint.path_of(key),
+            endpoint.authority
+        );
+        if let Some(body) = body {
+            req.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        req.push_str("\r\n");
+        let exchange = async {
+            let mut stream = TcpStream::connect(&endpoint.authority).await?;
+            stream.write_all(req.as_bytes()).await?;
+            if let Some(body) = body {
+                stream.write_all(body).await?;
+            }
+            let mut response = Vec::new();
+            stream
+                .take(MAX_RESPONSE + 1)
+                .read_to_end(&mut response)
+                .await?;
+            if response.len() as u64 > MAX_RESPONSE {
+                return Err(IoError::new(ErrorKind::Other, "response too large"));
+            }
+            parse_response(response)
+        };
+        match time::timeout(ORIGIN_TIMEOUT, exchange).await {
+            Ok(ret) => ret,
+            Err(_) => Err(IoError::from(ErrorKind::TimedOut)),
+        }
+    }
+    /// Fetch the value of a key from the origin, returning `None` if the origin doesn't
+    /// have it
+    pub async fn fetch(&self, key: &[u8]) -> Result<Option<Vec<u8>>, IoError> {
+        match self.request("GET", key, None).await? {
+            (200, body) => Ok(Some(body)),
+            (404, _) => Ok(None),
+            (status, _) => Err(IoError::new(
+                ErrorKind::Other,
+                format!("origin returned status {}", status),
+            )),
+        }
+    }
+    /// Store the value of a key in the origin
+    pub async fn store(&self, key: &[u8], value: &[u8]) -> Result<(), IoError> {
+        match self.request("PUT", key, Some(value)).await? {
+            (200..=299, _) => Ok(()),
+            (status, _) => Err(IoError::new(
+                ErrorKind::Other,
+                format!("origin returned status {}", status),
+            )),
+        }
+    }
+}
+
+/// Split an HTTP response into its status code and body
+fn parse_response(mut response: Vec<u8>) -> Result<(u16, Vec<u8>), IoError> {
+    let bad_response = || IoError::new(ErrorKind::InvalidData, "bad response from origin");
+    let head_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(bad_response)?;
+    let status = std::str::from_utf8(&response[..head_end])
+        .ok()
+        .and_then(|head| head.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(bad_response)?;
+    let body = response.split_off(head_end + 4);
+    Ok((status, body))
+}
+
+/// Set the origins of the tables (there's one origin at most for every table)
+pub fn set_origins(origins: Vec<Origin>) {
+    ENABLED.store(!origins.is_empty(), Ordering::Release);
+    *ORIGINS.write() = origins.into_iter().map(Arc::new).collect();
+}
+
+/// Returns true if any table has an origin
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+fn get_origin(table: &str) -> Option<Arc<Origin>> {
+    ORIGINS
+        .read()
+        .iter()
+        .find(|origin| origin.table == table)
+        .cloned()
+}
+
+/// Returns the origin to read missing keys of the given table from, if any
+pub fn get_readthrough(table: &str) -> Option<Arc<Origin>> {
+    get_origin(table).filter(|origin| origin.readthrough)
+}
+
+/// Propagate a `SET` on the given table to its origin in the background, if the table
+/// has a write-through origin
+pub fn write_through(table: &str, key: Bytes, value: Bytes) {
+    if let Some(origin) = get_origin(table).filter(|origin| origin.writethrough) {
+        tokio::spawn(async move {
+            if let Err(e) = origin.store(&key, &value).await {
+                log::warn!(
+                    "Failed to write a key of table `{}` through to its origin: {}",
+                    origin.table,
+                    e
+                );
+            }
+        });
+    }
+}
+
+#[test]
+fn test_origin_url() {
+    let endpoint = Endpoint::parse("http://localhost:8080/keys/").unwrap();
+    assert_eq!(endpoint.authority, "localhost:8080");
+    assert_eq!(endpoint.path_of(b"a b/c"), "/keys/a%20b%2Fc");
+    let endpoint = Endpoint::parse("http://localhost").unwrap();
+    assert_eq!(endpoint.authority, "localhost:80");
+    assert_eq!(endpoint.path_of(b"key"), "/key");
+    assert!(Endpoint::parse("https://localhost").is_none());
+    assert!(Endpoint::parse("http://:8080").is_none());
+    assert!(Endpoint::parse("http://localhost:port").is_none());
+    assert!(Endpoint::parse("http:///keys").is_none());
+}
+
+#[test]
+fn test_parse_response() {
+    let response = b"HTTP/1.0 200 OK\r\nContent-Length: 5\r\n\r\nhello".to_vec();
+    assert_eq!(parse_response(response).unwrap(), (200, b"hello".to_vec()));
+    let response = b"HTTP/1.1 404 Not Found\r\n\r\n".to_vec();
+    assert_eq!(parse_response(response).unwrap(), (404, Vec::new()));
+    assert!(parse_response(b"garbage".to_vec()).is_err());
+}
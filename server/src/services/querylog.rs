@@ -0,0 +1,267 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Query log
+//!
+//! The query log writes a sample of the queries that are run to a file, so that the workload
+//! of an instance can be analysed without logging every query. It's enabled with a
+//! `[querylog]` section in the config file, which sets the percentage of queries that are
+//! sampled. Every sampled query is written as a line of JSON with:
+//! - `time`: when the query finished (a UNIX timestamp in milliseconds)
+//! - `client`: the user that the connection authenticated as (like `user:app`), or the
+//! address of the connection
+//! - `action`: the name of the action
+//! - `entity`: the current table (as `<keyspace>:<table>`), or `null` if there is none
+//! - `latency`: the time taken to run the query, in microseconds
+//! - `bytesin` and `bytesout`: the size of the query and of its response
+//!
+//! The lines are written by a background thread. If it falls behind, sampled queries are
+//! dropped instead of holding up the connections. Once the file grows beyond `maxsize`
+//! bytes, it's rotated: `query.log` is renamed to `query.log.1` (and `query.log.1` to
+//! `query.log.2` and so on), keeping `keep` rotated files at most
+
+use crate::protocol::{Element, Query};
+use crate::registry::topology;
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU64, Ordering};
+use parking_lot::{const_mutex, Mutex};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Result as IoResult, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+
+/// The file that the query log is written to, if the config doesn't say otherwise
+pub const DEFAULT_PATH: &str = "query.log";
+/// The size at which the query log is rotated, if the config doesn't say otherwise
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+/// The number of rotated files that are kept, if the config doesn't say otherwise
+pub const DEFAULT_KEEP: usize = 4;
+/// The most sampled queries that can wait to be written
+const QUEUE_SIZE: usize = 4096;
+
+/// A query is sampled if a random number is at most this (`0` if the query log is disabled)
+static THRESHOLD: AtomicU64 = AtomicU64::new(0);
+/// The sampled queries that were dropped because the writer fell behind
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+static SENDER: Mutex<Option<SyncSender<Entry>>> = const_mutex(None);
+
+thread_local! {
+    /// The state of this thread's xorshift generator (`0` until it's seeded)
+    static RNG: Cell<u64> = Cell::new(0);
+}
+
+#[derive(Debug, PartialEq)]
+/// The query log configuration, set in the `[querylog]` section of the config file
+pub struct QueryLog {
+    /// the file that the query log is written to
+    path: String,
+    /// the percentage of queries that are sampled
+    sample: f64,
+    /// the size (in bytes) at which the file is rotated
+    maxsize: u64,
+    /// the number of rotated files that are kept
+    keep: usize,
+}
+
+impl QueryLog {
+    pub fn new(path: String, sample: f64, maxsize: u64, keep: usize) -> Self {
+        Self {
+            path,
+            sample,
+            maxsize,
+            keep,
+        }
+    }
+    /// Returns true if the sampling percentage is between 0 and 100
+    pub fn has_valid_sample(&self) -> bool {
+        (0.0..=100.0).contains(&self.sample)
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// A sampled query
+pub struct Entry {
+    time: u64,
+    client: String,
+    action: String,
+    entity: Option<String>,
+    latency: u64,
+    bytesin: u64,
+    bytesout: u64,
+}
+
+impl Entry {
+    pub fn new(
+        client: String,
+        action: String,
+        entity: Option<String>,
+        latency: u64,
+        bytesin: u64,
+        bytesout: u64,
+    ) -> Self {
+        Self {
+            time: crate::kvengine::now_ms(),
+            client,
+            action,
+            entity,
+            latency,
+            bytesin,
+            bytesout,
+        }
+    }
+}
+
+/// Open the query log and start the thread that writes to it
+pub fn start(cfg: QueryLog) -> IoResult<()> {
+    let file = open(&cfg.path)?;
+    let (tx, rx) = mpsc::sync_channel(QUEUE_SIZE);
+    *SENDER.lock() = Some(tx);
+    let threshold = (cfg.sample / 100.0 * u64::MAX as f64) as u64;
+    log::info!("Logging {}% of queries to `{}`", cfg.sample, cfg.path);
+    thread::Builder::new()
+        .name("querylog".to_owned())
+        .spawn(move || write_entries(cfg, file, rx))?;
+    THRESHOLD.store(threshold, Ordering::Release);
+    Ok(())
+}
+
+/// Returns true if the next query should be logged
+pub fn should_sample() -> bool {
+    let threshold = THRESHOLD.load(Ordering::Acquire);
+    threshold != 0 && next_random() <= threshold
+}
+
+/// Queue a sampled query to be written out, dropping it if the writer has fallen behind
+pub fn log(entry: Entry) {
+    if let Some(tx) = SENDER.lock().as_ref() {
+        if let Err(TrySendError::Full(_)) = tx.try_send(entry) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Returns the number of sampled queries that were dropped because the writer fell behind
+pub fn dropped() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Returns the name of the action run by a query (in uppercase)
+pub fn action_of(query: &Query) -> String {
+    match query {
+        Query::SimpleQuery(Element::AnyArray(args)) => args
+            .first()
+            .map(|action| String::from_utf8_lossy(action).to_ascii_uppercase())
+            .unwrap_or_default(),
+        Query::SimpleQuery(_) => String::new(),
+        Query::PipelinedQuery(_) => "PIPELINE".to_owned(),
+    }
+}
+
+fn open(path: &str) -> IoResult<BufWriter<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// Write the sampled queries as they come, rotating the file whenever it gets too large
+fn write_entries(cfg: QueryLog, mut file: BufWriter<File>, rx: Receiver<Entry>) {
+    topology::pin_background();
+    let mut size = file.get_ref().metadata().map_or(0, |meta| meta.len());
+    while let Ok(entry) = rx.recv() {
+        let mut ret = Ok(());
+        // write everything that's waiting before we flush
+        for entry in Some(entry).into_iter().chain(rx.try_iter()) {
+            let mut line = serde_json::to_vec(&entry).expect("Failed to serialize a query");
+            line.push(b'\n');
+            size += line.len() as u64;
+            ret = file.write_all(&line);
+            if ret.is_err() {
+                break;
+            }
+        }
+        let ret = ret.and_then(|_| file.flush()).and_then(|_| {
+            // a `maxsize` of 0 means that the file is never rotated
+            if cfg.maxsize != 0 && size >= cfg.maxsize {
+                file = rotate(&cfg)?;
+                size = 0;
+            }
+            Ok(())
+        });
+        if let Err(e) = ret {
+            log::error!("Failed to write to the query log: {}", e);
+        }
+    }
+}
+
+/// Shift the rotated files along (dropping the oldest) and start a new file
+fn rotate(cfg: &QueryLog) -> IoResult<BufWriter<File>> {
+    if cfg.keep == 0 {
+        fs::remove_file(&cfg.path)?;
+    } else {
+        for i in (1..cfg.keep).rev() {
+            let from = format!("{}.{}", cfg.path, i);
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, format!("{}.{}", cfg.path, i + 1))?;
+            }
+        }
+        fs::rename(&cfg.path, format!("{}.1", cfg.path))?;
+    }
+    open(&cfg.path)
+}
+
+/// Returns a random number from this thread's xorshift generator, seeding it if needed
+pub(crate) fn next_random() -> u64 {
+    RNG.with(|rng| {
+        let mut x = rng.get();
+        if x == 0 {
+            let mut seed = [0u8; 8];
+            getrandom::getrandom(&mut seed).expect("Failed to seed the query log sampler");
+            x = u64::from_le_bytes(seed) | 1;
+        }
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        rng.set(x);
+        x
+    })
+}
+
+#[test]
+fn test_rotate() {
+    let dir = "querylog-test-rotate";
+    fs::create_dir_all(dir).unwrap();
+    let path = format!("{}/query.log", dir);
+    let cfg = QueryLog::new(path.clone(), 100.0, 1, 2);
+    for i in 0..4 {
+        fs::write(&path, i.to_string()).unwrap();
+        rotate(&cfg).unwrap();
+    }
+    // only the `keep` newest files are kept, and a new (empty) file is started
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    assert_eq!(fs::read_to_string(format!("{}.1", path)).unwrap(), "3");
+    assert_eq!(fs::read_to_string(format!("{}.2", path)).unwrap(), "2");
+    assert!(fs::metadata(format!("{}.3", path)).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
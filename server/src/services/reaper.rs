@@ -0,0 +1,173 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use crate::corestore::memstore::{Keyspace, Memstore, ObjectID, SYSTEM};
+use crate::corestore::Corestore;
+use crate::dbnet::Terminator;
+use crate::diskstore::instance;
+use crate::registry;
+use crate::services::expiry;
+use crate::storage::flush;
+use crate::storage::interface::DIR_KSROOT;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::time::{self, Duration};
+
+/// The longest that the reaper sleeps between two sweeps
+const REAP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The reaper frees dropped tables once their retention period is over
+///
+/// If dropped tables aren't retained, this function immediately returns
+pub async fn drop_reaper(handle: Corestore, mut terminator: Terminator) {
+    if let Some(retention) = registry::get_drop_retention() {
+        let interval = retention.min(REAP_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = time::sleep_until(time::Instant::now() + interval) => {
+                    handle.get_store().reap_dropped_tables(retention);
+                }
+                _ = terminator.receive_signal() => {
+                    // we got a notification to quit; so break out
+                    break;
+                }
+            }
+        }
+    }
+    log::info!("Drop reaper service has exited");
+}
+
+/// How often expired keys are looked for
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The expiry reaper removes the keys that have expired, but haven't been looked up since
+/// (looking up an expired key removes it anyway)
+pub async fn expiry_reaper(handle: Corestore, mut terminator: Terminator) {
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + EXPIRY_INTERVAL) => {
+                let start = Instant::now();
+                let purged = handle.get_store().purge_expired_keys();
+                expiry::record_sweep(purged, start.elapsed());
+                if purged != 0 {
+                    log::trace!("Removed {} expired keys", purged);
+                }
+            }
+            _ = terminator.receive_signal() => {
+                // we got a notification to quit; so break out
+                break;
+            }
+        }
+    }
+    log::info!("Expiry reaper service has exited");
+}
+
+/// The longest that the unloader sleeps between two sweeps
+const UNLOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The unloader drops the data of the tables that haven't been accessed for the configured
+/// period from memory, keeping it on disk. The tables are loaded back into memory once they're
+/// accessed again
+///
+/// If idle tables aren't unloaded, this function immediately returns
+pub async fn idle_unloader(handle: Corestore, mut terminator: Terminator) {
+    if let Some(idle) = registry::get_idle_unload() {
+        let interval = idle.min(UNLOAD_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = time::sleep_until(time::Instant::now() + interval) => {
+                    let store = handle.clone_store();
+                    let unloaded = tokio::task::spawn_blocking(move || unload_idle(&store, idle))
+                        .await
+                        .expect("Something caused the idle table unloader to panic");
+                    if unloaded != 0 {
+                        log::info!("Unloaded {} idle tables", unloaded);
+                    }
+                }
+                _ = terminator.receive_signal() => {
+                    // we got a notification to quit; so break out
+                    break;
+                }
+            }
+        }
+    }
+    log::info!("Idle table unloader has exited");
+}
+
+/// Write every persistent table that hasn't been accessed for `idle` to disk and drop its
+/// data from memory, returning the number of tables unloaded. Tables that something still
+/// holds a reference to (like the connections that have them as their current table) are
+/// left alone
+fn unload_idle(store: &Memstore, idle: Duration) -> usize {
+    let _flush_lock = registry::lock_flush_state();
+    if !instance::is_owner() || registry::get_preload_tripswitch().is_tripped() {
+        // new tables haven't made it to disk yet, so wait for the next flush
+        return 0;
+    }
+    let keyspaces: Vec<(ObjectID, Arc<Keyspace>)> = store
+        .keyspaces
+        .iter()
+        .filter(|keyspace| !SYSTEM.eq(keyspace.key()))
+        .map(|keyspace| (keyspace.key().clone(), keyspace.value().clone()))
+        .collect();
+    let is_idle = |idle_secs: u64| idle_secs >= idle.as_secs();
+    let mut unloaded = 0;
+    for (ksid, keyspace) in keyspaces {
+        let candidates: Vec<ObjectID> = keyspace
+            .tables
+            .iter()
+            .filter(|table| {
+                let table = table.value();
+                !table.is_volatile()
+                    && !table.is_virtual()
+                    && !table.is_unloaded()
+                    && !table.is_warm()
+                    && is_idle(table.idle_secs())
+            })
+            .map(|table| table.key().clone())
+            .collect();
+        for tblid in candidates {
+            // holding the entry keeps the table from being looked up while it's unloaded
+            let table = match keyspace.tables.get_mut(&tblid) {
+                Some(table) => table,
+                None => continue,
+            };
+            let table = table.value();
+            if Arc::strong_count(table) != 1 || !is_idle(table.idle_secs()) {
+                continue;
+            }
+            let (ksname, tblname) = unsafe { (ksid.as_str(), tblid.as_str()) };
+            match flush::oneshot::flush_table(&tblid, &ksid, table) {
+                Ok(()) => {
+                    table.unload(format!("{}/{}/{}", DIR_KSROOT, ksname, tblname));
+                    unloaded += 1;
+                }
+                Err(e) => log::error!("Failed to unload `{}:{}`: {}", ksname, tblname, e),
+            }
+        }
+    }
+    unloaded
+}
@@ -0,0 +1,103 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Recovery status listener
+//!
+//! While `skyd` restores its data (which can take a while for large data directories), the
+//! real listeners aren't up yet; so a client (or a health check) would either be refused or
+//! hang, which looks exactly like a server that has died. To tell the two apart, the
+//! [`StatusListener`] binds the plain-text port for the duration of the recovery and replies
+//! to every connection with `err-recovering` before closing it
+
+use crate::config::PortConfig;
+use crate::protocol::responses::full_responses::R_RECOVERING;
+use std::io::{ErrorKind, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How long the listener sleeps when there's no one waiting to be accepted
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A listener that answers clients while we're recovering. It is stopped (and the port
+/// released) when it is dropped
+pub struct StatusListener {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StatusListener {
+    /// Start answering on the plain-text port of `ports`, if there is one. TLS ports are left
+    /// alone, since a TLS client can't read a plain-text reply anyway
+    pub fn start(ports: &PortConfig) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let binding = match ports {
+            PortConfig::InsecureOnly { host, port } | PortConfig::Multi { host, port, .. } => {
+                Some((*host, *port))
+            }
+            PortConfig::SecureOnly { .. } => None,
+        };
+        let listener = binding.and_then(|binding| {
+            let listener = TcpListener::bind(binding).ok()?;
+            listener.set_nonblocking(true).ok()?;
+            Some(listener)
+        });
+        let handle = listener.map(|listener| {
+            let stop = stop.clone();
+            thread::spawn(move || self::answer(listener, &stop))
+        });
+        Self { stop, handle }
+    }
+}
+
+fn answer(listener: TcpListener, stop: &AtomicBool) {
+    while !stop.load(Ordering::Acquire) {
+        match listener.accept() {
+            Ok((mut stream, _)) => {
+                // this is just a courtesy, so we don't care if the client has already left
+                let _ = stream.set_nonblocking(false);
+                let _ = stream.write_all(R_RECOVERING);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                log::warn!("Recovery status listener failed to accept: {}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+impl Drop for StatusListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            // the listener is dropped (and the port freed) when the thread exits
+            let _ = handle.join();
+        }
+    }
+}
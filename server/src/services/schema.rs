@@ -0,0 +1,620 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Schema-on-write
+//!
+//! A table that is shared by many producers can be given a JSON Schema with a `[[schema]]`
+//! entry in the config file, which names the table and the file that the schema is read from
+//! when the server starts. Once a table has a schema, every value that is written to it must
+//! be a JSON document that matches the schema, so that one bad producer can't fill it with
+//! garbage. The values checked are the ones that queries supply (`SET`, `MSET`, `UPDATE`,
+//! `MUPDATE`, `USET`, `SSET`, `SUPDATE`, `SETV` and `MCAS`), the documents that `JSET` and
+//! `JDEL` leave behind and the values that custom actions set. Writes whose values can't be
+//! told from the query (`GETUPDATE`, `MERGE`, `SETBIT`, `BITOP`, `PFADD` and `PFMERGE`), along
+//! with `SYS LOAD` and `CLONE`s into a table that has a schema, are refused. A write that
+//! fails the check changes nothing and gets `err-schema-violation`, or the list of violations
+//! (like `$.age: expected integer`) if the connection agreed on the `verbose-errors`
+//! capability.
+//!
+//! Only a subset of JSON Schema is understood: `type`, `enum`, `const`, `properties`,
+//! `required`, `additionalProperties`, `items`, `minimum`, `maximum`, `exclusiveMinimum`,
+//! `exclusiveMaximum`, `minLength`, `maxLength`, `minItems` and `maxItems` (along with the
+//! `true` and `false` schemas). Annotations (like `title`) are ignored, while any other
+//! keyword fails the schema when it's loaded, so that a constraint is never silently skipped
+
+use crate::corestore::lazy::Lazy;
+use crate::corestore::Corestore;
+use crate::protocol::hello::Capabilities;
+use crate::protocol::responses;
+use bytes::Bytes;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
+use std::fs;
+use std::sync::Arc;
+
+type SchemaList = Vec<(String, Arc<Schema>)>;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SCHEMAS: Lazy<RwLock<SchemaList>, fn() -> RwLock<SchemaList>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+/// The number of writes that were rejected
+static REJECTED: AtomicU64 = AtomicU64::new(0);
+
+/// The most violations that are reported for one write
+const MAX_VIOLATIONS: usize = 16;
+/// The values that every `@write` action (see [`queryengine`](crate::queryengine)) writes to
+/// the current table. Actions that aren't listed here are refused on tables that have a
+/// schema, so every new `@write` action has to be added here
+const WRITES: [(&[u8], Values); 29] = [
+    (b"GETEX", Values::None),
+    (b"EXPIREAT", Values::None),
+    (b"SET", Values::Args(1, 2)),
+    (b"UPDATE", Values::Args(1, 2)),
+    (b"DEL", Values::None),
+    (b"MSET", Values::Args(1, 2)),
+    (b"MUPDATE", Values::Args(1, 2)),
+    (b"SSET", Values::Args(1, 2)),
+    (b"SDEL", Values::None),
+    (b"SUPDATE", Values::Args(1, 2)),
+    (b"FLUSHDB", Values::None),
+    (b"USET", Values::Args(1, 2)),
+    (b"POP", Values::None),
+    (b"CREATE", Values::None),
+    (b"DROP", Values::None),
+    (b"UNDROP", Values::None),
+    // the table that is cloned into is checked by the action
+    (b"CLONE", Values::None),
+    (b"MPOP", Values::None),
+    // the documents are checked by the actions
+    (b"JSET", Values::None),
+    (b"JDEL", Values::None),
+    (b"SETBIT", Values::Unchecked),
+    (b"BITOP", Values::Unchecked),
+    (b"PFADD", Values::Unchecked),
+    (b"PFMERGE", Values::Unchecked),
+    (b"MERGE", Values::Unchecked),
+    (b"SETV", Values::Args(2, 3)),
+    (b"GETUPDATE", Values::Unchecked),
+    (b"MCAS", Values::Args(2, 3)),
+    (b"PREFIXDROP", Values::None),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The values that a write action writes
+enum Values {
+    /// The action doesn't write any values (or checks them itself)
+    None,
+    /// Every `step`th argument is a value, starting with the argument at index `first`
+    Args(usize, usize),
+    /// The values can't be told from the arguments (like the result of a `MERGE`)
+    Unchecked,
+}
+/// Keywords that don't constrain anything
+const ANNOTATIONS: [&str; 7] = [
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+/// The schema file of a table, as declared in the config file
+pub struct SchemaFile {
+    /// the table (as `<keyspace>:<table>`)
+    table: String,
+    /// the file that the schema is read from
+    file: String,
+}
+
+impl SchemaFile {
+    pub const fn new(table: String, file: String) -> Self {
+        Self { table, file }
+    }
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The types of JSON values
+enum JsonType {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl JsonType {
+    const ALL: [Self; 7] = [
+        Self::Null,
+        Self::Boolean,
+        Self::Integer,
+        Self::Number,
+        Self::String,
+        Self::Array,
+        Self::Object,
+    ];
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Array => "array",
+            Self::Object => "object",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|ty| ty.as_str() == name)
+    }
+    /// Returns true if `value` is of this type (integers are numbers too)
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::Null, Value::Null) => true,
+            (Self::Boolean, Value::Bool(_)) => true,
+            (Self::Integer, Value::Number(n)) => n.as_f64().map_or(false, |n| n.fract() == 0.0),
+            (Self::Number, Value::Number(_)) => true,
+            (Self::String, Value::String(_)) => true,
+            (Self::Array, Value::Array(_)) => true,
+            (Self::Object, Value::Object(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// What is done with the members of an object that aren't listed in `properties`
+enum Additional {
+    Allowed,
+    Denied,
+    Checked(Box<Schema>),
+}
+
+impl Default for Additional {
+    fn default() -> Self {
+        Self::Allowed
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+/// A compiled schema
+pub struct Schema {
+    /// set for the `false` schema, which nothing matches
+    denied: bool,
+    types: Option<Vec<JsonType>>,
+    /// the allowed values (from `enum` or `const`)
+    values: Option<Vec<Value>>,
+    properties: Vec<(String, Schema)>,
+    required: Vec<String>,
+    additional: Additional,
+    items: Option<Box<Schema>>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+}
+
+/// Returns a keyword's value as a number
+fn number(keyword: &str, value: &Value) -> Result<f64, String> {
+    value
+        .as_f64()
+        .ok_or_else(|| format!("`{}` has to be a number", keyword))
+}
+
+/// Returns a keyword's value as a count (like a length)
+fn count(keyword: &str, value: &Value) -> Result<usize, String> {
+    value
+        .as_u64()
+        .map(|count| count as usize)
+        .ok_or_else(|| format!("`{}` has to be a non-negative integer", keyword))
+}
+
+impl Schema {
+    /// Compile a schema, failing on keywords that aren't understood (or that have values
+    /// of the wrong type)
+    pub fn compile(schema: &Value) -> Result<Self, String> {
+        let keywords = match schema {
+            Value::Bool(true) => return Ok(Self::default()),
+            Value::Bool(false) => {
+                return Ok(Self {
+                    denied: true,
+                    ..Self::default()
+                })
+            }
+            Value::Object(keywords) => keywords,
+            _ => return Err("a schema has to be an object or a boolean".to_owned()),
+        };
+        let mut compiled = Self::default();
+        for (keyword, value) in keywords {
+            match keyword.as_str() {
+                "type" => compiled.types = Some(Self::compile_types(value)?),
+                "enum" => match value {
+                    Value::Array(values) => compiled.values = Some(values.clone()),
+                    _ => return Err("`enum` has to be an array".to_owned()),
+                },
+                "const" => compiled.values = Some(vec![value.clone()]),
+                "properties" => compiled.properties = Self::compile_properties(value)?,
+                "required" => compiled.required = Self::compile_required(value)?,
+                "additionalProperties" => {
+                    compiled.additional = match value {
+                        Value::Bool(true) => Additional::Allowed,
+                        Value::Bool(false) => Additional::Denied,
+                        schema => Additional::Checked(Box::new(Self::compile(schema)?)),
+                    }
+                }
+                "items" => compiled.items = Some(Box::new(Self::compile(value)?)),
+                "minimum" => compiled.minimum = Some(number(keyword, value)?),
+                "maximum" => compiled.maximum = Some(number(keyword, value)?),
+                "exclusiveMinimum" => compiled.exclusive_minimum = Some(number(keyword, value)?),
+                "exclusiveMaximum" => compiled.exclusive_maximum = Some(number(keyword, value)?),
+                "minLength" => compiled.min_length = Some(count(keyword, value)?),
+                "maxLength" => compiled.max_length = Some(count(keyword, value)?),
+                "minItems" => compiled.min_items = Some(count(keyword, value)?),
+                "maxItems" => compiled.max_items = Some(count(keyword, value)?),
+                annotation if ANNOTATIONS.contains(&annotation) => {}
+                unknown => return Err(format!("unsupported keyword `{}`", unknown)),
+            }
+        }
+        Ok(compiled)
+    }
+    fn compile_types(value: &Value) -> Result<Vec<JsonType>, String> {
+        let names: Option<Vec<&str>> = match value {
+            Value::String(name) => Some(vec![name.as_str()]),
+            Value::Array(names) => names.iter().map(Value::as_str).collect(),
+            _ => None,
+        };
+        let names =
+            names.ok_or_else(|| "`type` has to be a string or an array of strings".to_owned())?;
+        names
+            .into_iter()
+            .map(|name| JsonType::from_name(name).ok_or_else(|| format!("unknown type `{}`", name)))
+            .collect()
+    }
+    fn compile_properties(value: &Value) -> Result<Vec<(String, Schema)>, String> {
+        match value {
+            Value::Object(properties) => properties
+                .iter()
+                .map(|(name, schema)| Ok((name.clone(), Self::compile(schema)?)))
+                .collect(),
+            _ => Err("`properties` has to be an object".to_owned()),
+        }
+    }
+    fn compile_required(value: &Value) -> Result<Vec<String>, String> {
+        value
+            .as_array()
+            .and_then(|names| {
+                names
+                    .iter()
+                    .map(|name| name.as_str().map(str::to_owned))
+                    .collect()
+            })
+            .ok_or_else(|| "`required` has to be an array of strings".to_owned())
+    }
+    /// Check `value`, returning the violations (at most [`MAX_VIOLATIONS`] of them)
+    pub fn validate(&self, value: &Value) -> Vec<String> {
+        let mut violations = Vec::new();
+        self.check(value, &mut "$".to_owned(), &mut violations);
+        violations.truncate(MAX_VIOLATIONS);
+        violations
+    }
+    /// Check `value` (found at `path`), adding the violations to `out`
+    fn check(&self, value: &Value, path: &mut String, out: &mut Vec<String>) {
+        if out.len() >= MAX_VIOLATIONS {
+            return;
+        }
+        let mut violation = |message: String| out.push(format!("{}: {}", path, message));
+        if self.denied {
+            return violation("not allowed".to_owned());
+        }
+        if let Some(types) = &self.types {
+            if !types.iter().any(|ty| ty.matches(value)) {
+                let names: Vec<&str> = types.iter().map(JsonType::as_str).collect();
+                // the other constraints would only add noise
+                return violation(format!("expected {}", names.join(" or ")));
+            }
+        }
+        if let Some(values) = &self.values {
+            if !values.contains(value) {
+                violation("not one of the allowed values".to_owned());
+            }
+        }
+        match value {
+            Value::Number(n) => self.check_number(n.as_f64().unwrap_or_default(), violation),
+            Value::String(s) => {
+                let len = s.chars().count();
+                if let Some(min) = self.min_length.filter(|min| len < *min) {
+                    violation(format!("shorter than {} characters", min));
+                }
+                if let Some(max) = self.max_length.filter(|max| len > *max) {
+                    violation(format!("longer than {} characters", max));
+                }
+            }
+            Value::Array(items) => self.check_array(items, path, out),
+            Value::Object(members) => self.check_object(members, path, out),
+            _ => {}
+        }
+    }
+    fn check_number(&self, n: f64, mut violation: impl FnMut(String)) {
+        if let Some(min) = self.minimum.filter(|min| n < *min) {
+            violation(format!("less than the minimum of {}", min));
+        }
+        if let Some(max) = self.maximum.filter(|max| n > *max) {
+            violation(format!("greater than the maximum of {}", max));
+        }
+        if let Some(min) = self.exclusive_minimum.filter(|min| n <= *min) {
+            violation(format!("not greater than {}", min));
+        }
+        if let Some(max) = self.exclusive_maximum.filter(|max| n >= *max) {
+            violation(format!("not less than {}", max));
+        }
+    }
+    fn check_array(&self, items: &[Value], path: &mut String, out: &mut Vec<String>) {
+        if let Some(min) = self.min_items.filter(|min| items.len() < *min) {
+            out.push(format!("{}: fewer than {} items", path, min));
+        }
+        if let Some(max) = self.max_items.filter(|max| items.len() > *max) {
+            out.push(format!("{}: more than {} items", path, max));
+        }
+        if let Some(schema) = &self.items {
+            for (idx, item) in items.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", idx));
+                schema.check(item, path, out);
+                path.truncate(len);
+            }
+        }
+    }
+    fn check_object(&self, members: &Map<String, Value>, path: &mut String, out: &mut Vec<String>) {
+        for name in self.required.iter() {
+            if !members.contains_key(name) {
+                out.push(format!("{}: missing required property `{}`", path, name));
+            }
+        }
+        for (name, member) in members {
+            let schema = match self.properties.iter().find(|(known, _)| known == name) {
+                Some((_, schema)) => schema,
+                None => match &self.additional {
+                    Additional::Allowed => continue,
+                    Additional::Denied => {
+                        out.push(format!("{}: unexpected property `{}`", path, name));
+                        continue;
+                    }
+                    Additional::Checked(schema) => schema,
+                },
+            };
+            let len = path.len();
+            path.push('.');
+            path.push_str(name);
+            schema.check(member, path, out);
+            path.truncate(len);
+        }
+    }
+}
+
+/// Read and compile the schemas of the tables (there's one at most for every table)
+pub fn load(files: Vec<SchemaFile>) -> Result<(), String> {
+    let mut schemas = Vec::with_capacity(files.len());
+    for SchemaFile { table, file } in files {
+        let schema = fs::read(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|schema| serde_json::from_slice::<Value>(&schema).map_err(|e| e.to_string()))
+            .and_then(|schema| Schema::compile(&schema))
+            .map_err(|e| {
+                format!(
+                    "Failed to load the schema of `{}` from `{}`: {}",
+                    table, file, e
+                )
+            })?;
+        schemas.push((table, Arc::new(schema)));
+    }
+    set_schemas(schemas);
+    Ok(())
+}
+
+fn set_schemas(schemas: SchemaList) {
+    ENABLED.store(!schemas.is_empty(), Ordering::Release);
+    *SCHEMAS.write() = schemas;
+}
+
+/// Returns the schema of the connection's current table, if it has one
+pub fn get(handle: &Corestore) -> Option<Arc<Schema>> {
+    if !ENABLED.load(Ordering::Acquire) {
+        return None;
+    }
+    let table = handle.ctable_name()?;
+    SCHEMAS
+        .read()
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, schema)| schema.clone())
+}
+
+/// Returns true if the given table (as `<keyspace>:<table>`) has a schema
+pub fn has_schema(table: &str) -> bool {
+    ENABLED.load(Ordering::Acquire) && SCHEMAS.read().iter().any(|(name, _)| *name == table)
+}
+
+/// Returns true if any table in the given keyspace has a schema
+pub fn keyspace_has_schema(keyspace: &str) -> bool {
+    ENABLED.load(Ordering::Acquire)
+        && SCHEMAS
+            .read()
+            .iter()
+            .any(|(name, _)| name.split_once(':').map_or(false, |(ks, _)| ks == keyspace))
+}
+
+/// Check a value that is about to be written, returning the violations
+pub fn validate(schema: &Schema, value: &[u8]) -> Vec<String> {
+    match serde_json::from_slice(value) {
+        Ok(value) => schema.validate(&value),
+        Err(_) => vec!["$: not a JSON document".to_owned()],
+    }
+}
+
+/// Returns the values that a write action writes
+fn values(action: &[u8]) -> Values {
+    WRITES
+        .iter()
+        .find(|(name, _)| *name == action)
+        .map_or(Values::Unchecked, |(_, values)| *values)
+}
+
+/// Check the values that a write action is about to write to the connection's current table,
+/// returning the error to be written if any of them don't match the table's schema (or if
+/// they can't be checked)
+pub fn check(handle: &Corestore, action: &[u8], args: &[Bytes]) -> Option<Vec<u8>> {
+    let schema = get(handle)?;
+    let (first, step) = match values(action) {
+        Values::None => return None,
+        Values::Args(first, step) => (first, step),
+        Values::Unchecked => return Some(refuse(handle, action)),
+    };
+    let violations: Vec<String> = args
+        .iter()
+        .skip(first)
+        .step_by(step)
+        .flat_map(|value| validate(&schema, value))
+        .take(MAX_VIOLATIONS)
+        .collect();
+    if violations.is_empty() {
+        None
+    } else {
+        Some(error(handle, violations))
+    }
+}
+
+/// Returns the error to be written for a write to a table with a schema that can't be checked
+/// against the schema
+pub fn refuse(handle: &Corestore, action: &[u8]) -> Vec<u8> {
+    let violation = format!(
+        "$: {} can't be checked against a schema",
+        String::from_utf8_lossy(action)
+    );
+    error(handle, vec![violation])
+}
+
+/// Returns the error to be written for a write that failed its schema: the violations (as a
+/// typed array of strings) if the connection agreed on the `verbose-errors` capability, and
+/// `err-schema-violation` otherwise
+pub fn error(handle: &Corestore, violations: Vec<String>) -> Vec<u8> {
+    REJECTED.fetch_add(1, Ordering::Relaxed);
+    let verbose = handle.handshake().map_or(false, |hs| {
+        hs.capabilities.contains(Capabilities::VERBOSE_ERRORS)
+    });
+    if !verbose {
+        return responses::groups::SCHEMA_VIOLATION.to_vec();
+    }
+    let mut ret = format!("@+{}\n", violations.len()).into_bytes();
+    for violation in violations {
+        ret.extend_from_slice(violation.len().to_string().as_bytes());
+        ret.push(b'\n');
+        ret.extend_from_slice(violation.as_bytes());
+        ret.push(b'\n');
+    }
+    ret
+}
+
+/// Returns the schema statistics as `(name, value)` pairs, like `schema.rejected`
+pub fn info() -> Vec<(String, String)> {
+    vec![
+        ("schema.tables".to_owned(), SCHEMAS.read().len().to_string()),
+        (
+            "schema.rejected".to_owned(),
+            REJECTED.load(Ordering::Relaxed).to_string(),
+        ),
+    ]
+}
+
+#[test]
+fn test_schema_validate() {
+    let schema = serde_json::json!({
+        "title": "user",
+        "type": "object",
+        "properties": {
+            "name": {"type": "string", "minLength": 1},
+            "age": {"type": "integer", "minimum": 0},
+            "tags": {"type": "array", "items": {"enum": ["admin", "staff"]}, "maxItems": 2}
+        },
+        "required": ["name"],
+        "additionalProperties": false
+    });
+    let schema = Schema::compile(&schema).unwrap();
+    let user = serde_json::json!({"name": "sayan", "age": 20, "tags": ["admin"]});
+    assert!(schema.validate(&user).is_empty());
+    let user = serde_json::json!({"age": -1.5, "tags": ["admin", "root"], "email": ""});
+    assert_eq!(
+        schema.validate(&user),
+        vec![
+            "$: missing required property `name`",
+            "$.age: expected integer",
+            "$: unexpected property `email`",
+            "$.tags[1]: not one of the allowed values",
+        ]
+    );
+    assert_eq!(
+        validate(&schema, b"not json"),
+        vec!["$: not a JSON document"]
+    );
+    assert_eq!(
+        Schema::compile(&serde_json::json!({"pattern": "^a"})).unwrap_err(),
+        "unsupported keyword `pattern`"
+    );
+    assert_eq!(
+        Schema::compile(&serde_json::json!({"type": "text"})).unwrap_err(),
+        "unknown type `text`"
+    );
+}
+
+#[test]
+fn test_schema_covers_writes() {
+    // every `@write` action has to say what it writes
+    for command in crate::queryengine::COMMANDS {
+        if command.guard == Some("write") {
+            assert!(
+                WRITES
+                    .iter()
+                    .any(|(name, _)| *name == command.name.as_bytes()),
+                "`{}` is missing from the schema writes",
+                command.name
+            );
+        }
+    }
+    assert_eq!(values(b"SETV"), Values::Args(2, 3));
+    assert_eq!(values(b"MERGE"), Values::Unchecked);
+    assert_eq!(values(b"SOMETHINGNEW"), Values::Unchecked);
+}
@@ -0,0 +1,552 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Shadow tables
+//!
+//! Before a table is moved (to a new keyspace, a new layout or another node), its writes can
+//! be mirrored to a _shadow table_ for a while with a `[[shadow]]` entry in the config file,
+//! so that the new home can be checked against the old one before clients are cut over.
+//! Every write that is run on the table is run on the shadow table too, in the background and
+//! in the same order, by the user that ran it. The shadow table is either on this node or, if
+//! the entry has a `host`, on another node (which is sent the writes over Skyhash, like any
+//! other client would).
+//!
+//! Once the shadow has run a write, its response is compared with the response that the
+//! table gave. A write that got a different response (say, a `SET` that created a key on the
+//! table but found it on the shadow) has _diverged_, which means that the two tables no longer
+//! hold the same data. The counts are reported by `SYS INFO` (like
+//! `shadow.app:users.diverged`).
+//!
+//! DDL and `FLUSHDB` aren't mirrored. Writes are dropped (and counted) rather than queued
+//! once [`QUEUE_SIZE`] of them are waiting for the shadows, so that a slow shadow never holds
+//! up the table
+
+use crate::corestore::lazy::Lazy;
+use crate::corestore::Corestore;
+use crate::dbnet::connection::ProtocolConnectionExt;
+use crate::dbnet::memory;
+use crate::dbnet::Terminator;
+use crate::protocol::hello::Handshake;
+use crate::protocol::responses;
+use bytes::{Bytes, BytesMut};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{self, Duration};
+
+/// The most writes that can be waiting for the shadows
+pub const QUEUE_SIZE: usize = 4096;
+/// The longest that we wait for a remote shadow to respond
+const SHADOW_TIMEOUT: Duration = Duration::from_secs(5);
+/// The largest response that we accept from a remote shadow
+const MAX_RESPONSE: usize = 16 * 1024 * 1024;
+/// The actions that aren't mirrored
+const NOT_MIRRORED: [&[u8]; 5] = [b"CREATE", b"DROP", b"UNDROP", b"CLONE", b"FLUSHDB"];
+const USE: &[u8] = "USE".as_bytes();
+const HELLO: &[u8] = "HELLO".as_bytes();
+
+type MirrorList = Vec<Arc<Mirror>>;
+type Queue = (mpsc::Sender<Write>, Mutex<Option<mpsc::Receiver<Write>>>);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static MIRRORS: Lazy<RwLock<MirrorList>, fn() -> RwLock<MirrorList>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+static QUEUE: Lazy<Queue, fn() -> Queue> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+    (tx, Mutex::new(Some(rx)))
+});
+
+#[derive(Debug, Clone, PartialEq)]
+/// The shadow of a table
+pub struct Shadow {
+    /// the table whose writes are mirrored (as `<keyspace>:<table>`)
+    table: String,
+    /// the shadow table (as `<keyspace>:<table>`)
+    target: String,
+    /// the node that the shadow table is on (as `<host>:<port>`), if it isn't this one
+    host: Option<String>,
+}
+
+impl Shadow {
+    pub const fn new(table: String, target: String, host: Option<String>) -> Self {
+        Self {
+            table,
+            target,
+            host,
+        }
+    }
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+    /// Returns true if the shadow table is on this node
+    pub const fn is_local(&self) -> bool {
+        self.host.is_none()
+    }
+}
+
+#[derive(Debug)]
+/// A shadow, along with what has happened to the writes that were mirrored to it
+struct Mirror {
+    shadow: Shadow,
+    /// writes that are waiting for the shadow
+    pending: AtomicU64,
+    /// writes that the shadow has run
+    mirrored: AtomicU64,
+    /// writes that got a different response from the shadow
+    diverged: AtomicU64,
+    /// writes that couldn't be run on the shadow
+    failed: AtomicU64,
+    /// writes that were dropped because the queue was full
+    dropped: AtomicU64,
+}
+
+impl Mirror {
+    fn new(shadow: Shadow) -> Self {
+        Self {
+            shadow,
+            pending: AtomicU64::new(0),
+            mirrored: AtomicU64::new(0),
+            diverged: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+}
+
+/// A write that is waiting for a shadow
+struct Write {
+    mirror: Arc<Mirror>,
+    /// the instance to run the write on, for shadows on this node
+    local: Option<Corestore>,
+    /// what the connection that ran the write agreed on, since the response can depend on it
+    handshake: Option<Handshake>,
+    query: Vec<Bytes>,
+    /// the response that the table gave, if we could tell what it was
+    expected: Option<Vec<u8>>,
+}
+
+/// Set the shadows of the tables (there's one at most for every table)
+pub fn set_shadows(shadows: Vec<Shadow>) {
+    ENABLED.store(!shadows.is_empty(), Ordering::Release);
+    *MIRRORS.write() = shadows.into_iter().map(Mirror::new).map(Arc::new).collect();
+}
+
+/// Where the response to a write starts in a connection's output: the bytes that had been
+/// written to the stream and the bytes that were buffered
+type Mark = (u64, usize);
+
+fn mark<T, Strm>(con: &T) -> Mark
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    (con.get_io_counts().1, con.get_stream().buffer().len())
+}
+
+/// A write to a table with a shadow, which is queued for the shadow once it has run
+pub struct Prepared {
+    write: Write,
+    mark: Mark,
+}
+
+/// Prepare to mirror a write action that is about to run on the connection's current table,
+/// if the table has a shadow
+pub fn prepare<T, Strm>(
+    handle: &Corestore,
+    con: &T,
+    action: &[u8],
+    args: &[Bytes],
+) -> Option<Prepared>
+where
+    T: ProtocolConnectionExt<Strm>,
+    Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+{
+    if !ENABLED.load(Ordering::Acquire) || NOT_MIRRORED.contains(&action) {
+        return None;
+    }
+    let table = handle.ctable_name()?;
+    let mirror = MIRRORS
+        .read()
+        .iter()
+        .find(|mirror| mirror.shadow.table == table)
+        .cloned()?;
+    let mut query = Vec::with_capacity(args.len() + 1);
+    query.push(Bytes::copy_from_slice(action));
+    query.extend_from_slice(args);
+    let local = if mirror.shadow.is_local() {
+        Some(handle.detach())
+    } else {
+        None
+    };
+    Some(Prepared {
+        write: Write {
+            mirror,
+            local,
+            handshake: handle.handshake(),
+            query,
+            expected: None,
+        },
+        mark: mark(con),
+    })
+}
+
+impl Prepared {
+    /// Queue the write for the shadow, along with the response that the table gave
+    pub fn queue<T, Strm>(self, con: &T)
+    where
+        T: ProtocolConnectionExt<Strm>,
+        Strm: AsyncReadExt + AsyncWriteExt + Unpin + Send + Sync,
+    {
+        let Self { mut write, mark } = self;
+        let (written, buffered) = (con.get_io_counts().1, con.get_stream().buffer());
+        if written == mark.0 && buffered.len() >= mark.1 {
+            // the response hasn't been flushed, so we can read it back
+            write.expected = Some(buffered[mark.1..].to_vec());
+        }
+        let mirror = write.mirror.clone();
+        mirror.pending.fetch_add(1, Ordering::Relaxed);
+        if QUEUE.0.try_send(write).is_err() {
+            mirror.pending.fetch_sub(1, Ordering::Relaxed);
+            mirror.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Serialize a query into a packet
+fn encode_query<T: AsRef<[u8]>>(args: &[T]) -> Vec<u8> {
+    let mut packet = format!("*1\n~{}\n", args.len()).into_bytes();
+    for arg in args {
+        let arg = arg.as_ref();
+        packet.extend_from_slice(arg.len().to_string().as_bytes());
+        packet.push(b'\n');
+        packet.extend_from_slice(arg);
+        packet.push(b'\n');
+    }
+    packet
+}
+
+/// Returns the query that agrees on `handshake` with `HELLO`
+fn hello_query(handshake: &Handshake) -> Vec<Vec<u8>> {
+    let mut query = vec![HELLO.to_vec(), handshake.version.to_string().into_bytes()];
+    query.extend(
+        handshake
+            .capabilities
+            .names()
+            .into_iter()
+            .map(|name| name.as_bytes().to_vec()),
+    );
+    query
+}
+
+/// Returns the position right after the line that starts at `at`, along with the line
+fn read_line(buf: &[u8], at: usize) -> Option<(&[u8], usize)> {
+    let end = at + buf.get(at..)?.iter().position(|byte| *byte == b'\n')?;
+    Some((&buf[at..end], end + 1))
+}
+
+/// Returns the length that a line holds
+fn parse_len(line: &[u8]) -> Result<usize, ()> {
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|len| len.parse().ok())
+        .ok_or(())
+}
+
+/// Returns the position right after the `<len>\n<bytes>\n` that starts at `at`, or `Ok(None)`
+/// if it hasn't all been received yet
+fn sized_end(buf: &[u8], at: usize) -> Result<Option<usize>, ()> {
+    let (len, next) = match read_line(buf, at) {
+        Some((len, next)) => (parse_len(len)?, next),
+        None => return Ok(None),
+    };
+    let end = next + len + 1;
+    match buf.get(end - 1) {
+        Some(b'\n') => Ok(Some(end)),
+        Some(_) => Err(()),
+        None => Ok(None),
+    }
+}
+
+/// Returns the position right after the response element that starts at `at`, or `Ok(None)`
+/// if it hasn't all been received yet
+fn element_end(buf: &[u8], at: usize) -> Result<Option<usize>, ()> {
+    let tsymbol = match buf.get(at) {
+        Some(tsymbol) => *tsymbol,
+        None => return Ok(None),
+    };
+    let (count_at, typed) = match tsymbol {
+        b'!' | b'+' | b'?' | b':' => return sized_end(buf, at + 1),
+        b'&' | b'_' => (at + 1, false),
+        b'@' => (at + 2, true),
+        _ => return Err(()),
+    };
+    let (count, mut next) = match read_line(buf, count_at) {
+        Some((count, next)) => (parse_len(count)?, next),
+        None => return Ok(None),
+    };
+    for _ in 0..count {
+        let end = match buf.get(next) {
+            // a null in a typed array
+            Some(b'\0') if typed => Some(next + 2),
+            Some(_) if typed => sized_end(buf, next)?,
+            Some(_) => element_end(buf, next)?,
+            None => None,
+        };
+        next = match end {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(next))
+}
+
+/// Returns the response element in a response packet, or `Ok(None)` if it hasn't all been
+/// received yet
+fn response_element(packet: &[u8]) -> Result<Option<&[u8]>, ()> {
+    if packet.len() < 3 {
+        return Ok(None);
+    }
+    if !packet.starts_with(b"*1\n") {
+        return Err(());
+    }
+    Ok(element_end(packet, 3)?.map(|end| &packet[3..end]))
+}
+
+/// A connection to a remote shadow
+#[derive(Default)]
+struct Remote {
+    stream: Option<TcpStream>,
+    /// what we agreed on with `HELLO` on this connection
+    handshake: Option<Handshake>,
+}
+
+impl Remote {
+    /// Run a query on the remote shadow, returning its response element
+    async fn run(&mut self, host: &str, packet: &[u8]) -> Result<Vec<u8>, String> {
+        let stream = self.stream.as_mut().ok_or("not connected")?;
+        stream.write_all(packet).await.map_err(|e| e.to_string())?;
+        let mut response = BytesMut::new();
+        loop {
+            match response_element(&response) {
+                Ok(Some(element)) => return Ok(element.to_vec()),
+                Ok(None) if response.len() < MAX_RESPONSE => {}
+                Ok(None) => return Err("response too large".to_owned()),
+                Err(()) => return Err(format!("bad response from `{}`", host)),
+            }
+            if stream
+                .read_buf(&mut response)
+                .await
+                .map_err(|e| e.to_string())?
+                == 0
+            {
+                return Err("connection closed".to_owned());
+            }
+        }
+    }
+    /// Connect to the remote shadow (if we aren't connected yet), switch to the shadow table
+    /// and agree on `handshake`
+    async fn prepare(
+        &mut self,
+        shadow: &Shadow,
+        handshake: Option<Handshake>,
+    ) -> Result<(), String> {
+        let host = shadow.host.as_deref().unwrap_or_default();
+        if self.stream.is_some() && self.handshake.is_some() && handshake.is_none() {
+            // there's no way to take back a `HELLO`
+            self.stream = None;
+        }
+        if self.stream.is_none() {
+            self.handshake = None;
+            self.stream = Some(TcpStream::connect(host).await.map_err(|e| e.to_string())?);
+            let switched = self
+                .run(host, &encode_query(&[USE, shadow.target.as_bytes()]))
+                .await?;
+            if switched != responses::groups::OKAY {
+                return Err(format!("couldn't switch to `{}`", shadow.target));
+            }
+        }
+        if let Some(handshake) = handshake.filter(|hs| Some(*hs) != self.handshake) {
+            self.run(host, &encode_query(&hello_query(&handshake)))
+                .await?;
+            self.handshake = Some(handshake);
+        }
+        Ok(())
+    }
+}
+
+/// Run a write on the local shadow table, returning its response element
+async fn run_local(mut db: Corestore, shadow: &Shadow, query: &[Bytes]) -> Result<Vec<u8>, String> {
+    let switched = memory::execute(&mut db, &encode_query(&[USE, shadow.target.as_bytes()]))
+        .await
+        .map_err(|e| e.to_string())?;
+    if switched != responses::full_responses::R_OKAY {
+        return Err(format!("couldn't switch to `{}`", shadow.target));
+    }
+    let response = memory::execute(&mut db, &encode_query(query))
+        .await
+        .map_err(|e| e.to_string())?;
+    match response_element(&response) {
+        Ok(Some(element)) => Ok(element.to_vec()),
+        _ => Err("bad response".to_owned()),
+    }
+}
+
+/// Run a write on its shadow
+async fn mirror(write: Write, remotes: &mut HashMap<String, Remote>) {
+    let Write {
+        mirror,
+        local,
+        handshake,
+        query,
+        expected,
+    } = write;
+    let shadow = &mirror.shadow;
+    let ret = match (local, &shadow.host) {
+        (Some(db), _) => run_local(db, shadow, &query).await,
+        (None, Some(host)) => {
+            let remote = remotes.entry(shadow.table.clone()).or_default();
+            let run = async {
+                remote.prepare(shadow, handshake).await?;
+                remote.run(host, &encode_query(&query)).await
+            };
+            match time::timeout(SHADOW_TIMEOUT, run).await {
+                Ok(Ok(response)) => Ok(response),
+                Ok(Err(e)) => {
+                    remote.stream = None;
+                    Err(e)
+                }
+                Err(_) => {
+                    remote.stream = None;
+                    Err("timed out".to_owned())
+                }
+            }
+        }
+        (None, None) => Err("no shadow to run the write on".to_owned()),
+    };
+    mirror.pending.fetch_sub(1, Ordering::Relaxed);
+    match ret {
+        Ok(response) => {
+            mirror.mirrored.fetch_add(1, Ordering::Relaxed);
+            if matches!(expected, Some(expected) if expected != response) {
+                mirror.diverged.fetch_add(1, Ordering::Relaxed);
+                log::debug!(
+                    "A `{}` on `{}` diverged on its shadow",
+                    String::from_utf8_lossy(&query[0]),
+                    shadow.table
+                );
+            }
+        }
+        Err(e) => {
+            mirror.failed.fetch_add(1, Ordering::Relaxed);
+            log::warn!(
+                "Failed to mirror a write on `{}` to `{}`: {}",
+                shadow.table,
+                shadow.target,
+                e
+            );
+        }
+    }
+}
+
+/// The shadow writer runs the writes that were mirrored to the shadows, one after the other
+///
+/// If no table has a shadow, this function immediately returns
+pub async fn shadow_writer(mut terminator: Terminator) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    let mut queue = match QUEUE.1.lock().take() {
+        Some(queue) => queue,
+        None => return,
+    };
+    let mut remotes = HashMap::new();
+    loop {
+        tokio::select! {
+            write = queue.recv() => match write {
+                Some(write) => mirror(write, &mut remotes).await,
+                None => break,
+            },
+            _ = terminator.receive_signal() => {
+                // we got a notification to quit; so break out
+                break;
+            }
+        }
+    }
+    log::info!("Shadow writer service has exited");
+}
+
+/// Returns the mirroring statistics of every shadow as `(name, value)` pairs, like
+/// `shadow.app:users.diverged`
+pub fn info() -> Vec<(String, String)> {
+    let mut info = Vec::new();
+    for mirror in MIRRORS.read().iter() {
+        let counts = [
+            ("pending", &mirror.pending),
+            ("mirrored", &mirror.mirrored),
+            ("diverged", &mirror.diverged),
+            ("failed", &mirror.failed),
+            ("dropped", &mirror.dropped),
+        ];
+        info.push((
+            format!("shadow.{}.target", mirror.shadow.table),
+            match &mirror.shadow.host {
+                Some(host) => format!("{}/{}", host, mirror.shadow.target),
+                None => mirror.shadow.target.clone(),
+            },
+        ));
+        for (name, count) in counts.iter() {
+            info.push((
+                format!("shadow.{}.{}", mirror.shadow.table, name),
+                count.load(Ordering::Relaxed).to_string(),
+            ));
+        }
+    }
+    info
+}
+
+#[test]
+fn test_response_element() {
+    assert_eq!(response_element(b"*1\n!1\n0\n"), Ok(Some(&b"!1\n0\n"[..])));
+    assert_eq!(response_element(b"*1\n!1\n"), Ok(None));
+    assert_eq!(
+        response_element(b"*1\n@+2\n1\na\n\0\n"),
+        Ok(Some(&b"@+2\n1\na\n\0\n"[..]))
+    );
+    assert_eq!(
+        response_element(b"*1\n&2\n+1\na\n:2\n10\n"),
+        Ok(Some(&b"&2\n+1\na\n:2\n10\n"[..]))
+    );
+    assert_eq!(response_element(b"*1\n@+2\n1\na\n"), Ok(None));
+    assert_eq!(response_element(b"*1\n!1\n0x"), Err(()));
+    assert_eq!(response_element(b"HTTP/1.1"), Err(()));
+}
@@ -27,15 +27,18 @@
 use crate::config::SnapshotConfig;
 use crate::corestore::Corestore;
 use crate::dbnet::Terminator;
+use crate::kvengine::now_ms;
 use crate::registry;
+use crate::registry::schedules;
 use crate::storage::sengine::SnapshotEngine;
 use std::sync::Arc;
-use tokio::time::{self, Duration};
+use tokio::time::{self, Duration, Instant};
 
 /// The snapshot service
 ///
 /// This service calls `SnapEngine::mksnap()` periodically to create snapshots. Whenever
-/// the interval for snapshotting expires or elapses, we create a snapshot. The snapshot service
+/// the interval for snapshotting expires or elapses, or one of the snapshot schedules (see
+/// [`schedules`]) is due, we create a snapshot. The snapshot service
 /// keeps creating snapshots, as long as the database keeps running. Once [`dbnet::run`] broadcasts
 /// a termination signal, we're ready to quit. This function will, by default, poison the database
 /// if snapshotting fails, unless customized by the user.
@@ -51,11 +54,37 @@ pub async fn snapshot_service(
             return;
         }
         SnapshotConfig::Enabled(configuration) => {
-            let (duration, _, failsafe) = configuration.decompose();
-            let duration = Duration::from_secs(duration);
+            for schedule in configuration.schedules.iter() {
+                // the schedules were checked when the config was read
+                if let Ok(cron) = schedule.parse() {
+                    schedules::set(&schedule.name, cron);
+                }
+            }
+            schedules::enable();
+            let failsafe = configuration.poison;
+            let interval = match configuration.every {
+                0 => None,
+                every => Some(Duration::from_secs(every)),
+            };
+            let mut next_interval = interval.map(|interval| Instant::now() + interval);
             loop {
+                // the schedules can change while we sleep, in which case we start over
+                let now = now_ms() / 1000;
+                let next_schedule = schedules::next_due(now);
+                let next_scheduled = next_schedule
+                    .as_ref()
+                    .map(|(at, _)| Instant::now() + Duration::from_secs(at - now));
+                let deadline = match (next_interval, next_scheduled) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
                 tokio::select! {
-                    _ = time::sleep_until(time::Instant::now() + duration) => {
+                    _ = sleep_until(deadline) => {
+                        if deadline == next_interval {
+                            next_interval = interval.map(|interval| Instant::now() + interval);
+                        } else if let Some((_, names)) = next_schedule {
+                            log::info!("Creating snapshot for schedule(s): {}", names.join(", "));
+                        }
                         if engine.mksnap(handle.clone_store()).await == 0 {
                             // it passed, so unpoison the handle
                             registry::unpoison();
@@ -65,6 +94,7 @@ pub async fn snapshot_service(
                             registry::poison();
                         }
                     },
+                    _ = schedules::changed() => {},
                     _ = termination_signal.receive_signal() => {
                         // time to terminate; goodbye!
                         break;
@@ -75,3 +105,11 @@ pub async fn snapshot_service(
     }
     log::info!("Snapshot service has exited");
 }
+
+/// Sleep until the deadline, or forever if there is none
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
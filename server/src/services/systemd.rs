@@ -0,0 +1,187 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # systemd integration
+//!
+//! When `skyd` runs as a `Type=notify` systemd service (that is, when `NOTIFY_SOCKET` is set),
+//! it tells systemd that it is ready only once the data has been restored and all the
+//! listeners are bound, and that it is stopping once it starts shutting down. If the unit
+//! sets `WatchdogSec`, the [`watchdog`] service also pings systemd at half that interval so
+//! that a hung `skyd` is restarted. Outside systemd (and on other platforms), all of this
+//! does nothing
+
+use crate::dbnet::Terminator;
+use std::env;
+use tokio::time::{self, Duration};
+
+/// Tell systemd that we have started up and are accepting connections
+pub fn notify_ready() {
+    notify("READY=1\nSTATUS=Accepting connections");
+}
+
+/// Update the status that systemd shows for us (in `systemctl status`)
+pub fn notify_status(status: &str) {
+    notify(&format!("STATUS={}", status));
+}
+
+/// Tell systemd that we are shutting down
+pub fn notify_stopping() {
+    notify("STOPPING=1\nSTATUS=Shutting down");
+}
+
+/// Returns how often the watchdog expects to be pinged, if it is enabled for this process
+fn watchdog_interval() -> Option<Duration> {
+    let usecs: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        // the watchdog is meant for some other process
+        if pid.parse() != Ok(std::process::id()) {
+            return None;
+        }
+    }
+    if usecs == 0 {
+        None
+    } else {
+        Some(Duration::from_micros(usecs))
+    }
+}
+
+/// The watchdog service pings the systemd watchdog at half the interval that it expects
+/// pings at. If the watchdog isn't enabled, this function immediately returns
+pub async fn watchdog(mut terminator: Terminator) {
+    if let Some(interval) = watchdog_interval() {
+        let interval = interval / 2;
+        loop {
+            tokio::select! {
+                _ = time::sleep_until(time::Instant::now() + interval) => {
+                    notify("WATCHDOG=1");
+                }
+                _ = terminator.receive_signal() => {
+                    // we got a notification to quit; so break out
+                    break;
+                }
+            }
+        }
+        log::info!("Watchdog service has exited");
+    }
+}
+
+/// Send `state` to systemd, if we were started by it
+fn notify(state: &str) {
+    if let Some(socket) = env::var_os("NOTIFY_SOCKET") {
+        if let Err(e) = sys::send(&socket, state.as_bytes()) {
+            log::warn!("Failed to notify systemd: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use core::mem;
+    use std::ffi::OsStr;
+    use std::io::{Error, ErrorKind, Result};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixDatagram;
+    /// Send a datagram to the unix socket at `socket`, which is in the abstract namespace if it
+    /// starts with `@` (std can't address those, so the address is put together by hand)
+    pub fn send(socket: &OsStr, datagram: &[u8]) -> Result<()> {
+        let socket = socket.as_bytes();
+        let mut addr: libc::sockaddr_un = unsafe {
+            // UNSAFE(@ohsayan): sockaddr_un is plain old data, so all zeroes is a valid value
+            mem::zeroed()
+        };
+        if socket.is_empty() || socket.len() >= addr.sun_path.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "bad NOTIFY_SOCKET"));
+        }
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (dst, src) in addr.sun_path.iter_mut().zip(socket) {
+            *dst = *src as libc::c_char;
+        }
+        if socket[0] == b'@' {
+            addr.sun_path[0] = 0;
+        }
+        let addrlen = mem::size_of::<libc::sa_family_t>() + socket.len();
+        let sock = UnixDatagram::unbound()?;
+        let ret = unsafe {
+            // UNSAFE(@ohsayan): the address and the datagram outlive the call, and their
+            // lengths are right
+            libc::sendto(
+                sock.as_raw_fd(),
+                datagram.as_ptr() as *const libc::c_void,
+                datagram.len(),
+                libc::MSG_NOSIGNAL,
+                &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+                addrlen as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            Err(Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::ffi::OsStr;
+    use std::io::Result;
+    /// systemd only runs on Linux
+    pub fn send(_socket: &OsStr, _datagram: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_notify_abstract_socket() {
+    use std::os::unix::net::UnixDatagram;
+    // bind to an abstract socket through the same code path that sends to it
+    let name = format!("@skyd-test-notify-{}", std::process::id());
+    let listener = UnixDatagram::unbound().unwrap();
+    {
+        use core::mem;
+        use std::os::unix::io::AsRawFd;
+        let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (dst, src) in addr.sun_path.iter_mut().zip(name.as_bytes()).skip(1) {
+            *dst = *src as libc::c_char;
+        }
+        let addrlen = mem::size_of::<libc::sa_family_t>() + name.len();
+        let ret = unsafe {
+            libc::bind(
+                listener.as_raw_fd(),
+                &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+                addrlen as libc::socklen_t,
+            )
+        };
+        assert_eq!(ret, 0);
+    }
+    sys::send(name.as_ref(), b"READY=1").unwrap();
+    let mut buf = [0u8; 16];
+    let len = listener.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"READY=1");
+}
@@ -0,0 +1,155 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Write throttling
+//!
+//! A table can be given a maximum write rate with a `[[throttle]]` entry in the config file,
+//! which protects it (and the disk behind it) from bursty producers. Once the writes in the
+//! current one-second window run out, further writes to the table are either:
+//! - _delayed_ until the next window, which stops the connection from reading any more
+//! queries in the meantime (so that the producer sees backpressure), or
+//! - _rejected_ with `err-throttled`
+//!
+//! The writes counted are those run by `@write` actions against the connection's current
+//! table
+
+use crate::corestore::lazy::Lazy;
+use core::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type ThrottleList = Vec<Arc<Throttle>>;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static THROTTLES: Lazy<RwLock<ThrottleList>, fn() -> RwLock<ThrottleList>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// What happens to the writes beyond the rate of a table
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThrottleMode {
+    /// Wait until the next window
+    Delay,
+    /// Fail with `err-throttled`
+    Reject,
+}
+
+#[derive(Debug)]
+/// The maximum write rate of a table
+pub struct Throttle {
+    /// the throttled table (as `<keyspace>:<table>`)
+    table: String,
+    /// the maximum number of writes per second
+    writerate: u64,
+    mode: ThrottleMode,
+    /// the start of the current one-second window and the writes run in it
+    window: Mutex<(Instant, u64)>,
+}
+
+impl PartialEq for Throttle {
+    fn eq(&self, other: &Self) -> bool {
+        // the window is runtime state
+        self.table == other.table && self.writerate == other.writerate && self.mode == other.mode
+    }
+}
+
+impl Throttle {
+    pub fn new(table: String, writerate: u64, mode: ThrottleMode) -> Self {
+        Self {
+            table,
+            writerate,
+            mode,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+    /// Returns true if the table can't run any writes at all
+    pub const fn is_blocked(&self) -> bool {
+        self.writerate == 0
+    }
+    /// Account for one write, returning how long is left of the current window if the
+    /// table has already run as many writes as it is allowed to run in it
+    fn take_write(&self, now: Instant) -> Result<(), Duration> {
+        let mut window = self.window.lock();
+        if now.saturating_duration_since(window.0) >= WINDOW {
+            *window = (now, 0);
+        }
+        if window.1 < self.writerate {
+            window.1 += 1;
+            Ok(())
+        } else {
+            Err(WINDOW - now.saturating_duration_since(window.0))
+        }
+    }
+    /// Account for one write, waiting for the next window if the table has run out of
+    /// writes and is in delay mode. Returns false if the write has to be rejected
+    pub async fn acquire(&self) -> bool {
+        loop {
+            match self.take_write(Instant::now()) {
+                Ok(()) => return true,
+                Err(_) if self.mode == ThrottleMode::Reject => return false,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+}
+
+/// Set the maximum write rates of the tables (there's one at most for every table)
+pub fn set_throttles(throttles: Vec<Throttle>) {
+    ENABLED.store(!throttles.is_empty(), Ordering::Release);
+    *THROTTLES.write() = throttles.into_iter().map(Arc::new).collect();
+}
+
+/// Returns true if any table has a maximum write rate
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Returns the maximum write rate of the given table, if it has one
+pub fn get_throttle(table: &str) -> Option<Arc<Throttle>> {
+    THROTTLES
+        .read()
+        .iter()
+        .find(|throttle| throttle.table == table)
+        .cloned()
+}
+
+#[test]
+fn test_take_write() {
+    let throttle = Throttle::new("default:default".to_owned(), 2, ThrottleMode::Reject);
+    let start = throttle.window.lock().0;
+    assert!(throttle.take_write(start).is_ok());
+    assert!(throttle.take_write(start).is_ok());
+    let half = start + Duration::from_millis(500);
+    assert_eq!(throttle.take_write(half), Err(Duration::from_millis(500)));
+    // a new window starts once the current one is over
+    assert!(throttle.take_write(start + WINDOW).is_ok());
+}
@@ -0,0 +1,87 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+//! # Warming up tables
+//!
+//! The tables listed in `preload` are read into memory at startup, before the listeners are
+//! bound: tables that were unloaded are loaded, and every key and value is read through so
+//! that its pages are faulted in. This keeps the first queries after a restart from running
+//! against cold memory. Warmed up tables are never unloaded (see [`Table::warm`]), and
+//! `SYS WARM` warms them up again on demand (like on a standby, right before it takes over
+//! after a failover)
+
+use crate::corestore::lazy::Lazy;
+use crate::corestore::memstore::Memstore;
+use crate::corestore::table::Table;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Instant;
+
+static TABLES: Lazy<RwLock<Vec<String>>, fn() -> RwLock<Vec<String>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Set the tables (as `<keyspace>:<table>`) that are warmed up by [`warm_up`]
+pub fn set_tables(tables: Vec<String>) {
+    *TABLES.write() = tables;
+}
+
+/// Warm up all the tables that were set with [`set_tables`], returning the number of bytes
+/// that were read. Tables that don't exist are skipped
+pub fn warm_up(store: &Memstore) -> usize {
+    let tables = TABLES.read().clone();
+    let mut bytes = 0;
+    for name in tables.iter() {
+        match warm_table(store, name) {
+            Some(read) => bytes += read,
+            None => log::warn!("Not warming up `{}` since it doesn't exist", name),
+        }
+    }
+    bytes
+}
+
+/// Warm up the table with the given name (as `<keyspace>:<table>`), returning the number of
+/// bytes that were read or `None` if there's no such table
+fn warm_table(store: &Memstore, name: &str) -> Option<usize> {
+    let table = find_table(store, name)?;
+    let start = Instant::now();
+    let bytes = table.warm();
+    log::info!(
+        "Warmed up `{}` ({} bytes) in {:?}",
+        name,
+        bytes,
+        start.elapsed()
+    );
+    Some(bytes)
+}
+
+/// Find the table with the given name, counting it as accessed (so that the idle unloader
+/// doesn't unload it right after it's warmed up)
+fn find_table(store: &Memstore, name: &str) -> Option<Arc<Table>> {
+    let mut parts = name.splitn(2, ':');
+    let keyspace = store.get_keyspace_atomic_ref(parts.next()?.as_bytes())?;
+    let table = keyspace.get_table_atomic_ref(parts.next()?.as_bytes())?;
+    table.touch();
+    Some(table)
+}
@@ -0,0 +1,61 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Virtual clock
+//!
+//! While a simulation is running on a thread, [`now_ms`] follows tokio's (paused) clock
+//! from a fixed starting point instead of the system clock. Since tokio moves a paused clock
+//! forward on its own whenever every task is waiting on a timer, an hour of expiries and
+//! timeouts can be simulated in no time, and the same way every time
+
+use std::cell::Cell;
+use tokio::time::Instant;
+
+/// The UNIX timestamp (in milliseconds) that simulations start at
+pub const EPOCH_MS: u64 = 1_600_000_000_000;
+
+thread_local! {
+    static START: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// Start the virtual clock on this thread. This has to be called within the simulation's
+/// runtime, so that it picks up the paused clock
+pub fn start() {
+    START.with(|start| start.set(Some(Instant::now())));
+}
+
+/// Stop the virtual clock on this thread
+pub fn stop() {
+    START.with(|start| start.set(None));
+}
+
+/// Returns the virtual time (as a UNIX timestamp in milliseconds), if the virtual clock
+/// is running on this thread
+pub fn now_ms() -> Option<u64> {
+    START
+        .with(|start| start.get())
+        .map(|start| EPOCH_MS + start.elapsed().as_millis() as u64)
+}
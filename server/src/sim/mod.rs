@@ -0,0 +1,319 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Deterministic simulation
+//!
+//! With the `simulation` feature, the server can be run within a [`Simulation`]: a
+//! single-threaded runtime with a paused clock (see [`clock`]), a seeded random number
+//! generator and in-memory connections (see [`transport`]). Given the same seed, a
+//! simulation runs the same way every time, so protocol and expiry bugs that only show
+//! up with a particular ordering (or a particular split of the bytes on the wire) can be
+//! found by trying many seeds, and then replayed from the failing seed
+//!
+//! ```ignore
+//! let mut sim = Simulation::new(seed);
+//! let mut client = sim.connect();
+//! sim.block_on(async {
+//!     client.query(&["SET", "x", "100"]).await.unwrap();
+//! });
+//! ```
+
+pub mod clock;
+pub mod rng;
+pub mod transport;
+
+use self::rng::SimRng;
+use self::transport::SimStream;
+use crate::corestore::memstore::Memstore;
+use crate::corestore::Corestore;
+use crate::dbnet::connection::ConnectionHandler;
+use crate::dbnet::tcp::Connection;
+use crate::dbnet::{Terminator, MAXIMUM_CONNECTION_LIMIT};
+use crate::services;
+use crate::storage::sengine::SnapshotEngine;
+use crate::IoResult;
+use bytes::BytesMut;
+use core::future::Future;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::{broadcast, mpsc, Semaphore};
+
+/// A simulated server
+pub struct Simulation {
+    seed: u64,
+    rng: SimRng,
+    runtime: Runtime,
+    db: Corestore,
+    climit: Arc<Semaphore>,
+    signal: broadcast::Sender<()>,
+    terminate_tx: mpsc::Sender<()>,
+    _terminate_rx: mpsc::Receiver<()>,
+}
+
+impl Simulation {
+    /// Create a new simulation with an empty store. The virtual clock starts running on
+    /// the current thread, so the simulation should be driven from this thread
+    pub fn new(seed: u64) -> Self {
+        let runtime = Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .expect("failed to start the simulation runtime");
+        let db = Corestore::default_with_store(
+            Memstore::new_default(),
+            Arc::new(SnapshotEngine::new_disabled()),
+        );
+        let (signal, _) = broadcast::channel(1);
+        let (terminate_tx, _terminate_rx) = mpsc::channel(1);
+        {
+            let _guard = runtime.enter();
+            clock::start();
+            runtime.spawn(services::reaper::expiry_reaper(
+                db.clone(),
+                Terminator::new(signal.subscribe()),
+            ));
+        }
+        Self {
+            seed,
+            rng: SimRng::new(seed),
+            runtime,
+            db,
+            climit: Arc::new(Semaphore::new(MAXIMUM_CONNECTION_LIMIT)),
+            signal,
+            terminate_tx,
+            _terminate_rx,
+        }
+    }
+    /// Returns the seed that this simulation was created with
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Returns the simulation's random number generator, for tests to make their own
+    /// (reproducible) choices
+    pub fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+    /// Run a future to completion on the simulation's runtime
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+    /// Open a new connection to the simulated server
+    pub fn connect(&mut self) -> SimClient {
+        let (client, server) = transport::pipe(&mut self.rng);
+        self.climit
+            .try_acquire()
+            .expect("too many connections")
+            .forget();
+        let mut handler = ConnectionHandler::new(
+            self.db.clone(),
+            Connection::new(server),
+            self.climit.clone(),
+            Terminator::new(self.signal.subscribe()),
+            self.terminate_tx.clone(),
+            false,
+        );
+        self.runtime.spawn(async move {
+            if let Err(e) = handler.run().await {
+                log::error!("Simulated connection failed: {}", e);
+            }
+        });
+        SimClient {
+            stream: client,
+            buffer: BytesMut::new(),
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        let _ = self.signal.send(());
+        clock::stop();
+    }
+}
+
+/// A connection to a simulated server
+pub struct SimClient {
+    stream: SimStream,
+    buffer: BytesMut,
+}
+
+impl SimClient {
+    /// Run a simple query, returning the raw response
+    pub async fn query(&mut self, args: &[&str]) -> IoResult<Vec<u8>> {
+        let mut query = format!("*1\n~{}\n", args.len()).into_bytes();
+        for arg in args {
+            query.extend_from_slice(arg.len().to_string().as_bytes());
+            query.push(b'\n');
+            query.extend_from_slice(arg.as_bytes());
+            query.push(b'\n');
+        }
+        self.stream.write_all(&query).await?;
+        self.stream.flush().await?;
+        loop {
+            if let Some(len) = response_len(&self.buffer) {
+                return Ok(self.buffer.split_to(len).to_vec());
+            }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(IoError::from(ErrorKind::UnexpectedEof));
+            }
+        }
+    }
+}
+
+/// Returns the length of the response at the start of `buf`, if all of it is there
+fn response_len(buf: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    if *buf.first()? != b'*' {
+        return None;
+    }
+    pos += 1;
+    for _ in 0..read_size(buf, &mut pos)? {
+        skip_element(buf, &mut pos)?;
+    }
+    Some(pos)
+}
+
+/// Read a `<size>\n` line
+fn read_size(buf: &[u8], pos: &mut usize) -> Option<usize> {
+    let line = buf.get(*pos..)?;
+    let end = line.iter().position(|b| *b == b'\n')?;
+    let size = std::str::from_utf8(&line[..end]).ok()?.parse().ok()?;
+    *pos += end + 1;
+    Some(size)
+}
+
+/// Skip over `<len>\n<payload>\n`
+fn skip_payload(buf: &[u8], pos: &mut usize) -> Option<()> {
+    *pos += read_size(buf, pos)? + 1;
+    if *pos > buf.len() {
+        None
+    } else {
+        Some(())
+    }
+}
+
+fn skip_element(buf: &[u8], pos: &mut usize) -> Option<()> {
+    let tsymbol = *buf.get(*pos)?;
+    *pos += 1;
+    match tsymbol {
+        // any arrays and flat arrays, whose elements have their own tsymbols
+        b'&' | b'_' => {
+            for _ in 0..read_size(buf, pos)? {
+                skip_element(buf, pos)?;
+            }
+        }
+        // typed arrays, whose elements are either a payload or a null
+        b'@' => {
+            *pos += 1;
+            for _ in 0..read_size(buf, pos)? {
+                if *buf.get(*pos)? == b'\0' {
+                    *pos += 2;
+                } else {
+                    skip_payload(buf, pos)?;
+                }
+            }
+        }
+        _ => skip_payload(buf, pos)?,
+    }
+    if *pos > buf.len() {
+        None
+    } else {
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Simulation;
+    use crate::protocol::responses::full_responses;
+    use std::time::Duration;
+
+    /// Run a session that sets keys with expiries and reads them back, returning the
+    /// responses
+    fn run_session(seed: u64) -> Vec<Vec<u8>> {
+        let mut sim = Simulation::new(seed);
+        let mut a = sim.connect();
+        let mut b = sim.connect();
+        sim.block_on(async move {
+            let mut transcript = Vec::new();
+            for i in 0..20 {
+                let key = format!("key{}", i);
+                transcript.push(
+                    a.query(&["SET", &key, &"x".repeat((i + 1) * 100)])
+                        .await
+                        .unwrap(),
+                );
+                transcript.push(b.query(&["GETEX", &key, "5"]).await.unwrap());
+            }
+            transcript.push(b.query(&["MGET", "key1", "key2", "nokey"]).await.unwrap());
+            transcript.push(a.query(&["DBSIZE"]).await.unwrap());
+            tokio::time::sleep(Duration::from_secs(6)).await;
+            transcript.push(a.query(&["DBSIZE"]).await.unwrap());
+            transcript
+        })
+    }
+
+    #[test]
+    fn test_simulated_expiry() {
+        let mut sim = Simulation::new(1);
+        let mut client = sim.connect();
+        sim.block_on(async move {
+            assert_eq!(
+                client.query(&["SET", "x", "100"]).await.unwrap(),
+                full_responses::R_OKAY
+            );
+            assert!(client
+                .query(&["GETEX", "x", "3600"])
+                .await
+                .unwrap()
+                .ends_with(b"3\n100\n"));
+            // an hour goes by in no time
+            tokio::time::sleep(Duration::from_secs(3599)).await;
+            assert!(client
+                .query(&["GET", "x"])
+                .await
+                .unwrap()
+                .ends_with(b"3\n100\n"));
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert_eq!(
+                client.query(&["GET", "x"]).await.unwrap(),
+                full_responses::R_NIL
+            );
+        });
+    }
+
+    #[test]
+    fn test_same_seed_same_run() {
+        for seed in 0..8 {
+            let run = run_session(seed);
+            assert_eq!(run, run_session(seed));
+            // every key expired
+            assert!(run.last().unwrap().ends_with(b"1\n0\n"));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! A small seeded random number generator (SplitMix64), so that a simulation makes the
+//! same choices every time it is run with the same seed
+
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Returns a number in `1..=max` (or `0` if `max` is `0`)
+    pub fn upto(&mut self, max: usize) -> usize {
+        if max == 0 {
+            0
+        } else {
+            (self.next_u64() % max as u64) as usize + 1
+        }
+    }
+    /// Returns a new generator that is seeded from this one
+    pub fn fork(&mut self) -> Self {
+        Self::new(self.next_u64())
+    }
+}
+
+#[test]
+fn test_rng_is_deterministic() {
+    let (mut a, mut b) = (SimRng::new(42), SimRng::new(42));
+    let run_a: Vec<u64> = (0..16).map(|_| a.next_u64()).collect();
+    let run_b: Vec<u64> = (0..16).map(|_| b.next_u64()).collect();
+    assert_eq!(run_a, run_b);
+    let mut c = SimRng::new(43);
+    assert_ne!(run_a, (0..16).map(|_| c.next_u64()).collect::<Vec<_>>());
+    assert!((0..100).all(|_| (1..=10).contains(&a.upto(10))));
+    assert_eq!(a.upto(0), 0);
+}
@@ -0,0 +1,101 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # In-memory transport
+//!
+//! A [`SimStream`] is one end of an in-memory pipe. Reads and writes only move a (seeded)
+//! random number of bytes at a time, so that queries and responses arrive split at every
+//! possible point, just like they can over a real network
+
+use super::rng::SimRng;
+use crate::dbnet::tcp::BufferedSocketStream;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io::Result as IoResult;
+use tokio::io::{self, AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+
+/// The capacity of each direction of the pipe
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+pub struct SimStream {
+    inner: DuplexStream,
+    rng: SimRng,
+}
+
+/// Returns both ends of a new pipe
+pub fn pipe(rng: &mut SimRng) -> (SimStream, SimStream) {
+    let (a, b) = io::duplex(PIPE_CAPACITY);
+    (
+        SimStream {
+            inner: a,
+            rng: rng.fork(),
+        },
+        SimStream {
+            inner: b,
+            rng: rng.fork(),
+        },
+    )
+}
+
+impl BufferedSocketStream for SimStream {}
+
+impl AsyncRead for SimStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        let max = this.rng.upto(buf.remaining());
+        let mut chunk = buf.take(max);
+        match Pin::new(&mut this.inner).poll_read(cx, &mut chunk) {
+            Poll::Ready(Ok(())) => {
+                let read = chunk.filled().len();
+                unsafe {
+                    // SAFETY: the inner stream initialized these bytes
+                    buf.assume_init(read);
+                }
+                buf.advance(read);
+                Poll::Ready(Ok(()))
+            }
+            other => other,
+        }
+    }
+}
+
+impl AsyncWrite for SimStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let max = this.rng.upto(buf.len());
+        Pin::new(&mut this.inner).poll_write(cx, &buf[..max])
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -46,3 +46,9 @@ pub const BYTEMARK_MODEL_KV_STR_BIN: u8 = 3;
 pub const BYTEMARK_STORAGE_PERSISTENT: u8 = 0;
 /// Volatile storage bytemark
 pub const BYTEMARK_STORAGE_VOLATILE: u8 = 1;
+
+// records (from format version 3)
+/// Record type bytemark for a plain value
+pub const BYTEMARK_RECORD_TYPE_RAW: u8 = 0;
+/// Record flag bytemark for a key that expires (the record header then has its deadline)
+pub const BYTEMARK_RECORD_FLAG_EXPIRES: u8 = 0b0000_0001;
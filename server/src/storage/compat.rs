@@ -0,0 +1,164 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # On-disk format versions
+//!
+//! Every data directory (and every snapshot) has a format version, which is stored in the
+//! meta segment of its `PRELOAD`. The versions are:
+//! 1. The original format, where table files are just the serialized map
+//! 2. Table files start with a header: [`TABLE_MAGIC`] followed by the format version
+//! 3. Every record in a table file has a header of its own, which holds the type of the
+//! value, flags and the expiry of the key (if it has one)
+//!
+//! Data in any older format can always be read; a data directory in an older format is
+//! upgraded in place when the server starts. For going back to an older server, `SYS EXPORT`
+//! writes a copy of the data in an older format (keys don't expire in formats older than
+//! version 3, since their records can't carry an expiry).
+//!
+//! Since the first 8 bytes of a version 1 table file are the number of entries in the table,
+//! a version 1 file can't start with [`TABLE_MAGIC`] (that would be ~5 * 10^18 entries). This
+//! is why table files are checked for the magic instead of trusting the `PRELOAD`, which also
+//! keeps a tree readable if we crash halfway through an upgrade
+
+use crate::IoResult;
+use std::io::{Error as IoError, ErrorKind, Write};
+
+/// The original format, where table files have no header
+pub const FORMAT_V1: u8 = 1;
+/// The format where table files have a header
+pub const FORMAT_V2: u8 = 2;
+/// The format where records have a header
+pub const FORMAT_V3: u8 = 3;
+/// The format written by this version of the server
+pub const FORMAT_CURRENT: u8 = FORMAT_V3;
+/// The magic that table files start with (from version 2)
+pub const TABLE_MAGIC: &[u8; 8] = b"SKYTABLE";
+const TABLE_HEADER_LEN: usize = TABLE_MAGIC.len() + 1;
+
+// the high nibble of the meta segment is 0b1000 for version 1, 0b1001 for version 2 and so
+// on, while the low nibble is the endian mark
+const META_VERSION_BASE: u8 = 0b1000;
+
+#[cfg(target_endian = "little")]
+const ENDIAN_MARK: u8 = 0b0000;
+
+#[cfg(target_endian = "big")]
+const ENDIAN_MARK: u8 = 0b0001;
+
+fn newer_format(format: u8) -> IoError {
+    IoError::new(
+        ErrorKind::Unsupported,
+        format!(
+            "data is in format version {}, but this server only supports versions up to {}",
+            format, FORMAT_CURRENT
+        ),
+    )
+}
+
+/// Returns true if data can be written in the given format
+pub fn is_supported(format: u8) -> bool {
+    (FORMAT_V1..=FORMAT_CURRENT).contains(&format)
+}
+
+/// Returns the meta segment of a `PRELOAD` written in the given format
+pub const fn preload_meta(format: u8) -> u8 {
+    ((META_VERSION_BASE + format - 1) << 4) | ENDIAN_MARK
+}
+
+/// Returns the format of a `PRELOAD` with the given meta segment
+pub fn preload_format(meta: u8) -> IoResult<u8> {
+    let version_nibble = meta >> 4;
+    if meta & 0b1111 != ENDIAN_MARK || version_nibble < META_VERSION_BASE {
+        return Err(IoError::from(ErrorKind::Unsupported));
+    }
+    let format = version_nibble - META_VERSION_BASE + 1;
+    if format > FORMAT_CURRENT {
+        return Err(newer_format(format));
+    }
+    Ok(format)
+}
+
+/// Write the header of a table file in the given format
+pub fn write_table_header<W: Write>(w: &mut W, format: u8) -> IoResult<()> {
+    if format > FORMAT_V1 {
+        w.write_all(TABLE_MAGIC)?;
+        w.write_all(&[format])?;
+    }
+    Ok(())
+}
+
+/// Returns the format of a table file and the length of its header
+pub fn read_table_header(data: &[u8]) -> IoResult<(u8, usize)> {
+    if !data.starts_with(TABLE_MAGIC) {
+        return Ok((FORMAT_V1, 0));
+    }
+    match data.get(TABLE_MAGIC.len()) {
+        Some(format) if *format > FORMAT_CURRENT => Err(newer_format(*format)),
+        Some(format) if *format > FORMAT_V1 => Ok((*format, TABLE_HEADER_LEN)),
+        _ => Err(IoError::from(ErrorKind::InvalidData)),
+    }
+}
+
+#[test]
+fn test_preload_meta() {
+    // version 1 is what we've always written
+    #[cfg(target_endian = "little")]
+    assert_eq!(preload_meta(FORMAT_V1), 0b1000_0000);
+    #[cfg(target_endian = "big")]
+    assert_eq!(preload_meta(FORMAT_V1), 0b1000_0001);
+    for format in FORMAT_V1..=FORMAT_CURRENT {
+        assert_eq!(preload_format(preload_meta(format)).unwrap(), format);
+    }
+    let newer = preload_meta(FORMAT_CURRENT) + 0b1_0000;
+    assert_eq!(
+        preload_format(newer).unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+    assert!(preload_format(0).is_err());
+}
+
+#[test]
+fn test_table_header() {
+    let mut file = Vec::new();
+    write_table_header(&mut file, FORMAT_V1).unwrap();
+    assert!(file.is_empty());
+    // a version 1 file starts with the number of entries
+    file.extend_from_slice(&10u64.to_le_bytes());
+    assert_eq!(read_table_header(&file).unwrap(), (FORMAT_V1, 0));
+    let mut file = Vec::new();
+    write_table_header(&mut file, FORMAT_CURRENT).unwrap();
+    file.extend_from_slice(&10u64.to_le_bytes());
+    assert_eq!(
+        read_table_header(&file).unwrap(),
+        (FORMAT_CURRENT, TABLE_HEADER_LEN)
+    );
+    file[TABLE_MAGIC.len()] = FORMAT_CURRENT + 1;
+    assert_eq!(
+        read_table_header(&file).unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+    assert!(read_table_header(TABLE_MAGIC).is_err());
+}
@@ -0,0 +1,185 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Snapshot diffs
+//!
+//! Routines to compare two stores (a snapshot and the live data, or two snapshots)
+//! key by key. Volatile tables are skipped since snapshots never have their data
+
+use crate::corestore::htable::Coremap;
+use crate::corestore::memstore::Memstore;
+use crate::corestore::table::{DataModel, Table};
+use crate::corestore::Data;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The differences between two versions of a table
+#[derive(Debug, PartialEq, Default)]
+pub struct TableDiff {
+    /// keys that are only in the newer version
+    pub added: usize,
+    /// keys that are only in the older version
+    pub removed: usize,
+    /// keys that are in both versions, but with different values
+    pub changed: usize,
+}
+
+impl TableDiff {
+    fn of(old: &Coremap<Data, Data>, new: &Coremap<Data, Data>) -> Self {
+        let mut diff = Self::default();
+        for kv in new.iter() {
+            match old.get(kv.key()) {
+                Some(oldval) if oldval.value() != kv.value() => diff.changed += 1,
+                Some(_) => {}
+                None => diff.added += 1,
+            }
+        }
+        diff.removed = old.iter().filter(|kv| !new.contains_key(kv.key())).count();
+        diff
+    }
+    /// Returns true if both versions of the table have the same data
+    pub const fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.changed == 0
+    }
+}
+
+/// Collect all the persistent tables in the store, by entity
+fn tables(store: &Memstore) -> BTreeMap<String, Arc<Table>> {
+    let mut tables = BTreeMap::new();
+    for keyspace in store.keyspaces.iter() {
+        for table in keyspace.value().tables.iter() {
+            if table.value().is_volatile() {
+                continue;
+            }
+            let entity = unsafe {
+                // SAFETY: object IDs are always valid UTF-8
+                format!("{}:{}", keyspace.key().as_str(), table.key().as_str())
+            };
+            tables.insert(entity, table.value().clone());
+        }
+    }
+    tables
+}
+
+fn table_data(table: &Table) -> &Coremap<Data, Data> {
+    // an unloaded live table has to be compared by what's on disk
+    table.ensure_loaded();
+    match table.get_model_ref() {
+        DataModel::KV(kve) => kve.__get_inner_ref(),
+    }
+}
+
+/// Compare the `old` store with the `new` one, returning the differences of every table
+/// (by entity) that changed. A table that is only in one of the stores is compared with
+/// an empty table
+pub fn diff(old: &Memstore, new: &Memstore) -> Vec<(String, TableDiff)> {
+    let empty = Coremap::new();
+    let old_tables = self::tables(old);
+    let new_tables = self::tables(new);
+    let mut entities: Vec<&String> = old_tables.keys().chain(new_tables.keys()).collect();
+    entities.sort();
+    entities.dedup();
+    entities
+        .into_iter()
+        .filter_map(|entity| {
+            let old_data = old_tables.get(entity).map_or(&empty, |tbl| table_data(tbl));
+            let new_data = new_tables.get(entity).map_or(&empty, |tbl| table_data(tbl));
+            let diff = TableDiff::of(old_data, new_data);
+            if diff.is_empty() {
+                None
+            } else {
+                Some((entity.clone(), diff))
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_diff_stores() {
+    use crate::corestore::memstore::{ObjectID, DEFAULT};
+    let old = Memstore::new_default();
+    let new = Memstore::new_default();
+    let get_default = |store: &Memstore| {
+        store
+            .get_keyspace_atomic_ref(&DEFAULT)
+            .unwrap()
+            .get_table_atomic_ref(&DEFAULT)
+            .unwrap()
+    };
+    let (old_tbl, new_tbl) = (get_default(&old), get_default(&new));
+    let (old_kve, new_kve) = (
+        old_tbl.get_kvstore().unwrap(),
+        new_tbl.get_kvstore().unwrap(),
+    );
+    old_kve.set("same".into(), "value".into()).unwrap();
+    new_kve.set("same".into(), "value".into()).unwrap();
+    old_kve.set("changed".into(), "old".into()).unwrap();
+    new_kve.set("changed".into(), "new".into()).unwrap();
+    old_kve.set("removed".into(), "value".into()).unwrap();
+    new_kve.set("added".into(), "value".into()).unwrap();
+    // a table that only exists in the new store
+    let newtbl = Table::new_default_kve();
+    newtbl
+        .get_kvstore()
+        .unwrap()
+        .set("hello".into(), "world".into())
+        .unwrap();
+    new.get_keyspace_atomic_ref(&DEFAULT)
+        .unwrap()
+        .create_table(unsafe { ObjectID::from_slice("mytbl") }, newtbl);
+    // volatile tables are ignored
+    let volatile = Table::new_kve_with_volatile(true);
+    volatile
+        .get_kvstore()
+        .unwrap()
+        .set("hello".into(), "world".into())
+        .unwrap();
+    new.get_keyspace_atomic_ref(&DEFAULT)
+        .unwrap()
+        .create_table(unsafe { ObjectID::from_slice("myvolatile") }, volatile);
+    assert_eq!(
+        diff(&old, &new),
+        vec![
+            (
+                "default:default".to_owned(),
+                TableDiff {
+                    added: 1,
+                    removed: 1,
+                    changed: 1
+                }
+            ),
+            (
+                "default:mytbl".to_owned(),
+                TableDiff {
+                    added: 1,
+                    removed: 0,
+                    changed: 0
+                }
+            ),
+        ]
+    );
+    assert!(diff(&old, &old).is_empty());
+}
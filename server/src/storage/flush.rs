@@ -29,12 +29,25 @@
 //! This module contains multiple flush routines: at the memstore level, the keyspace level and
 //! the table level
 
+use super::compat;
 use super::interface;
 use crate::corestore::memstore::Keyspace;
 use crate::corestore::memstore::Memstore;
 use crate::corestore::memstore::ObjectID;
+use crate::corestore::table::Table;
 use crate::registry;
+use crate::registry::bgio::Limiter;
+use crate::registry::jobs::Job;
+use crate::registry::latency::{self, Event};
+use crate::registry::stall;
+use crate::registry::topology;
 use crate::IoResult;
+use parking_lot::Mutex;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
 
 /// Flushes the entire **keyspace + partmap**
 pub fn flush_keyspace_full(ksid: &ObjectID, keyspace: &Keyspace) -> IoResult<()> {
@@ -53,29 +66,175 @@ pub fn flush_full(store: &Memstore) -> IoResult<()> {
         super::interface::create_tree(store)?;
         self::oneshot::flush_preload(store)?;
     }
+    // a keyspace that fails to flush has its writes stalled (see `stall`), but the other
+    // keyspaces are still flushed
+    let mut ret = Ok(());
     for keyspace in store.keyspaces.iter() {
-        self::flush_keyspace_full(keyspace.key(), keyspace.value())?;
+        let name = unsafe { keyspace.key().as_str() };
+        match self::flush_keyspace_full(keyspace.key(), keyspace.value()) {
+            Ok(()) => stall::resume_keyspace(name, keyspace.value(), stall::FLUSH_FAILED),
+            Err(e) => {
+                stall::stall_keyspace(name, keyspace.value(), stall::FLUSH_FAILED);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Flush a single keyspace (along with its partmap) or, if `tblid` is set, a single table
+/// of it. The tree and the preload are written first if keyspaces or tables have been
+/// created since the last flush, so that the files are found on startup. Returns false if
+/// the keyspace or the table doesn't exist
+pub fn flush_entity(store: &Memstore, ksid: &ObjectID, tblid: Option<&ObjectID>) -> IoResult<bool> {
+    let keyspace = match store.get_keyspace_atomic_ref(ksid) {
+        Some(keyspace) => keyspace,
+        None => return Ok(false),
+    };
+    if registry::get_preload_tripswitch().check_and_untrip() {
+        super::interface::create_tree(store)?;
+        self::oneshot::flush_preload(store)?;
     }
-    Ok(())
+    match tblid {
+        Some(tblid) => match keyspace.get_table_atomic_ref(tblid) {
+            Some(table) => self::oneshot::flush_table(tblid, ksid, &table)?,
+            None => return Ok(false),
+        },
+        None => self::flush_keyspace_full(ksid, &keyspace)?,
+    }
+    Ok(true)
 }
 
-pub fn snap_flush_keyspace_full(
+/// Flush a full snapshot of the store in the given format (see [`compat`]), reporting a
+/// unit of progress to `job` for every table that is written
+///
+/// The tables are written in parallel (see [`registry::get_snapshot_threads`]), each to its
+/// own file. The `PRELOAD` (which, along with the `PARTMAP`s, ties the tables together) is
+/// written last, so a snapshot that doesn't have one was never finished. If the job's I/O is
+/// [limited](registry::bgio), the tables are written at its rate and everything is written at
+/// its priority
+pub fn snap_flush_full(
     snapdir: &str,
     snapid: &str,
-    ksid: &ObjectID,
-    keyspace: &Keyspace,
+    store: &Memstore,
+    format: u8,
+    job: &Job,
 ) -> IoResult<()> {
-    self::oneshot::snap_flush_partmap(snapdir, snapid, ksid, keyspace)?;
-    self::oneshot::snap_flush_keyspace(snapdir, snapid, ksid, keyspace)
-}
-
-pub fn snap_flush_full(snapdir: &str, snapid: &str, store: &Memstore) -> IoResult<()> {
+    let _priority = job.limiter().map(|limiter| limiter.prioritize());
     super::interface::snap_create_tree(snapdir, snapid, store)?;
-    self::oneshot::snap_flush_preload(snapdir, snapid, store)?;
+    let mut tables = Vec::new();
     for keyspace in store.keyspaces.iter() {
-        self::snap_flush_keyspace_full(snapdir, snapid, keyspace.key(), keyspace.value())?;
+        self::oneshot::snap_flush_partmap(snapdir, snapid, keyspace.key(), keyspace.value())?;
+        for table in keyspace.value().tables.iter() {
+            tables.push((
+                keyspace.key().clone(),
+                keyspace.value().clone(),
+                table.key().clone(),
+                table.value().clone(),
+            ));
+        }
     }
-    Ok(())
+    job.set_total(tables.len() as u64);
+    self::snap_flush_tables(snapdir, snapid, tables, format, job)?;
+    self::oneshot::snap_flush_preload(snapdir, snapid, store, format)
+}
+
+/// A table that is yet to be written to a snapshot: the ID of its keyspace, its keyspace, its
+/// ID and the table
+type SnapTableJob = (ObjectID, Arc<Keyspace>, ObjectID, Arc<Table>);
+
+/// Write all the `tables` to the snapshot `snapid` in `snapdir`, with as many threads as
+/// the registry allows
+fn snap_flush_tables(
+    snapdir: &str,
+    snapid: &str,
+    tables: Vec<SnapTableJob>,
+    format: u8,
+    job: &Job,
+) -> IoResult<()> {
+    let threads = registry::get_snapshot_threads().min(tables.len()).max(1);
+    let limiter = job.limiter().cloned();
+    let tables = Arc::new(Mutex::new(tables.into_iter()));
+    let (done_tx, done_rx) = mpsc::channel();
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let (snapdir, snapid) = (snapdir.to_owned(), snapid.to_owned());
+            let (tables, done_tx) = (tables.clone(), done_tx.clone());
+            let limiter = limiter.clone();
+            thread::spawn(move || {
+                topology::pin_background();
+                let _priority = limiter.as_deref().map(Limiter::prioritize);
+                loop {
+                    let next = tables.lock().next();
+                    let (ksid, keyspace, tblid, table) = match next {
+                        Some(next) => next,
+                        None => break Ok(()),
+                    };
+                    // copy the table at a rendezvous so that the snapshot never has half of an
+                    // `MSET`, and so that writers aren't held off while the copy is written out
+                    let table = if table.is_volatile() {
+                        table
+                    } else if table.is_unloaded() {
+                        // an unloaded table is copied from its file instead, so that taking a
+                        // snapshot doesn't load every idle table back into memory
+                        let copy = super::unflush::read_table(
+                            interface::DIR_KSROOT,
+                            &ksid,
+                            &tblid,
+                            false,
+                            table.get_model_code(),
+                        );
+                        match copy {
+                            Ok(copy) => Arc::new(copy),
+                            Err(e) => {
+                                *tables.lock() = Vec::new().into_iter();
+                                break Err(e);
+                            }
+                        }
+                    } else {
+                        let (copy, paused) = keyspace.rendezvous(|| {
+                            let start = Instant::now();
+                            (table.snapshot(), start.elapsed())
+                        });
+                        latency::record(Event::Snapshot, paused);
+                        Arc::new(copy)
+                    };
+                    let ret = self::oneshot::snap_flush_table_limited(
+                        &snapdir,
+                        &snapid,
+                        &ksid,
+                        &tblid,
+                        &table,
+                        format,
+                        limiter.as_deref(),
+                    );
+                    if let Err(e) = ret {
+                        // the snapshot has failed, so make the other threads stop early
+                        *tables.lock() = Vec::new().into_iter();
+                        break Err(e);
+                    }
+                    let _ = done_tx.send(());
+                }
+            })
+        })
+        .collect();
+    // count the tables as they're written; this ends once all the threads have exited
+    drop(done_tx);
+    for () in done_rx {
+        job.progress();
+    }
+    let mut ret = Ok(());
+    for handle in handles {
+        let result = handle
+            .join()
+            .unwrap_or_else(|_| Err(IoError::new(ErrorKind::Other, "snapshot thread panicked")));
+        if ret.is_ok() {
+            ret = result;
+        }
+    }
+    ret
 }
 
 pub mod oneshot {
@@ -86,11 +245,56 @@ pub mod oneshot {
     //!
     use super::*;
     use crate::corestore::table::{DataModel, Table};
+    use crate::registry::bgio::Limited;
     use crate::storage::interface::DIR_KSROOT;
     use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
 
     const PRELOAD_FILE_PATH_TEMP: &str = "data/ks/PRELOAD_";
-    const PRELOAD_FILE_PATH: &str = "data/ks/PRELOAD";
+
+    /// Write a file atomically: `write` fills in the temporary file `tmppath` (the final
+    /// path followed by an `_`), which is then synced and renamed over the final path. The
+    /// directory is synced after the rename so that the rename itself survives a crash.
+    /// Hence, a crash at any point leaves either the old file or the new one, but never a
+    /// truncated file
+    pub(in crate::storage) fn write_atomic(
+        tmppath: &str,
+        write: impl FnOnce(&mut File) -> IoResult<()>,
+    ) -> IoResult<()> {
+        let path = &tmppath[..tmppath.len() - 1];
+        let ret = File::create(tmppath).and_then(|mut file| {
+            write(&mut file)?;
+            let start = Instant::now();
+            let ret = file.sync_all();
+            latency::record(Event::Fsync, start.elapsed());
+            ret
+        });
+        if let Err(e) = ret {
+            // don't leave a partially written file lying around
+            let _ = fs::remove_file(tmppath);
+            return Err(e);
+        }
+        fs::rename(tmppath, path)?;
+        self::sync_parent_dir(path)
+    }
+
+    #[cfg(unix)]
+    /// Sync the directory that `path` is in, persisting any renames in it
+    fn sync_parent_dir(path: &str) -> IoResult<()> {
+        let parent = match Path::new(path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()
+    }
+
+    #[cfg(not(unix))]
+    /// Directories can't be opened (and hence synced) like files on other platforms, where
+    /// `MoveFileEx` is expected to persist the rename
+    fn sync_parent_dir(_path: &str) -> IoResult<()> {
+        Ok(())
+    }
 
     macro_rules! tbl_path {
         ($ksid:expr, $tableid:expr) => {
@@ -115,70 +319,96 @@ pub mod oneshot {
         };
     }
 
+    /// Write the header and the data of a table
+    fn write_table<W: Write>(file: &mut W, table: &Table, format: u8) -> IoResult<()> {
+        compat::write_table_header(file, format)?;
+        match table.get_model_ref() {
+            DataModel::KV(kve) => {
+                // keys that have expired shouldn't come back when the file is read
+                kve.purge_expired();
+                if format < compat::FORMAT_V3 {
+                    super::interface::serialize_map_into_slow_buffer(file, kve.__get_inner_ref())
+                } else {
+                    super::interface::serialize_records_into_slow_buffer(file, kve)
+                }
+            }
+        }
+    }
+
     macro_rules! routine_flushtable {
-        ($table:ident, $path:expr) => {
+        ($table:ident, $path:expr, $format:expr) => {
+            routine_flushtable!($table, $path, $format, None::<&Limiter>)
+        };
+        ($table:ident, $path:expr, $format:expr, $limiter:expr) => {
             if $table.is_volatile() {
                 // no flushing needed
                 Ok(())
             } else {
                 // fine, this needs to be flushed
-                let mut file = File::create(&$path)?;
-                match $table.get_model_ref() {
-                    DataModel::KV(kve) => super::interface::serialize_map_into_slow_buffer(
-                        &mut file,
-                        kve.__get_inner_ref(),
-                    )?,
-                }
-                file.sync_all()?;
-                fs::rename(&$path, &$path[..$path.len() - 1])
+                self::write_atomic(&$path, |file| match $limiter {
+                    Some(limiter) => {
+                        self::write_table(&mut Limited::new(file, limiter), $table, $format)
+                    }
+                    None => self::write_table(file, $table, $format),
+                })
             }
         };
     }
-    /// No `partmap` handling. Just flushes the table to the expected location
+    /// No `partmap` handling. Just flushes the table to the expected location. Unloaded tables
+    /// are skipped, since their file already has all of their data
     pub fn flush_table(tableid: &ObjectID, ksid: &ObjectID, table: &Table) -> IoResult<()> {
-        routine_flushtable!(table, tbl_path!(ksid, tableid))
+        if table.is_unloaded() {
+            return Ok(());
+        }
+        routine_flushtable!(table, tbl_path!(ksid, tableid), compat::FORMAT_CURRENT)
     }
 
-    /// Same as flush_table, except for it being built specifically for snapshots
+    /// Same as flush_table, except for it being built specifically for snapshots (which
+    /// can be written in an older format)
     pub fn snap_flush_table(
         snapdir: &str,
         snapid: &str,
         ksid: &ObjectID,
         tableid: &ObjectID,
         table: &Table,
+        format: u8,
     ) -> IoResult<()> {
-        routine_flushtable!(table, snap_tbl_path!(snapdir, snapid, ksid, tableid))
+        self::snap_flush_table_limited(snapdir, snapid, ksid, tableid, table, format, None)
     }
 
-    /// Flushes an entire keyspace to the expected location. No `partmap` or `preload` handling
-    pub fn flush_keyspace(ksid: &ObjectID, keyspace: &Keyspace) -> IoResult<()> {
-        for table in keyspace.tables.iter() {
-            self::flush_table(table.key(), ksid, table.value())?;
-        }
-        Ok(())
-    }
-
-    /// Flushes an entire keyspace to the expected location. No `partmap` or `preload` handling
-    pub fn snap_flush_keyspace(
+    /// Same as snap_flush_table, except for the table being written at the rate of `limiter`
+    /// (if there is one)
+    pub fn snap_flush_table_limited(
         snapdir: &str,
         snapid: &str,
         ksid: &ObjectID,
-        keyspace: &Keyspace,
+        tableid: &ObjectID,
+        table: &Table,
+        format: u8,
+        limiter: Option<&Limiter>,
     ) -> IoResult<()> {
+        routine_flushtable!(
+            table,
+            snap_tbl_path!(snapdir, snapid, ksid, tableid),
+            format,
+            limiter
+        )
+    }
+
+    /// Flushes an entire keyspace to the expected location. No `partmap` or `preload` handling
+    pub fn flush_keyspace(ksid: &ObjectID, keyspace: &Keyspace) -> IoResult<()> {
         for table in keyspace.tables.iter() {
-            self::snap_flush_table(snapdir, snapid, table.key(), ksid, table.value())?;
+            self::flush_table(table.key(), ksid, table.value())?;
         }
         Ok(())
     }
 
     macro_rules! routine_flushpartmap {
-        ($path:expr, $keyspace:ident) => {{
-            let mut file = File::create(&$path)?;
-            super::interface::serialize_partmap_into_slow_buffer(&mut file, $keyspace)?;
-            file.sync_all()?;
-            fs::rename(&$path, &$path[..$path.len() - 1])?;
-            Ok(())
-        }};
+        ($path:expr, $keyspace:ident) => {
+            self::write_atomic(&$path, |file| {
+                super::interface::serialize_partmap_into_slow_buffer(file, $keyspace)
+            })
+        };
     }
 
     /// Flushes a single partmap
@@ -200,24 +430,26 @@ pub mod oneshot {
     }
 
     macro_rules! routine_flushpreload {
-        ($store:expr, $preloadtmp:expr, $preloadfinal:expr) => {{
-            let mut file = File::create(&$preloadtmp)?;
-            super::interface::serialize_preload_into_slow_buffer(&mut file, $store)?;
-            file.sync_all()?;
-            fs::rename(&$preloadtmp, &$preloadfinal)?;
-            Ok(())
-        }};
+        ($store:expr, $preloadtmp:expr, $format:expr) => {
+            self::write_atomic(&$preloadtmp, |file| {
+                super::interface::serialize_preload_into_slow_buffer(file, $store, $format)
+            })
+        };
     }
 
     // Flush the `PRELOAD`
     pub fn flush_preload(store: &Memstore) -> IoResult<()> {
-        routine_flushpreload!(store, PRELOAD_FILE_PATH_TEMP, PRELOAD_FILE_PATH)
+        routine_flushpreload!(store, PRELOAD_FILE_PATH_TEMP, compat::FORMAT_CURRENT)
     }
 
     /// Same as flush_preload, but for snapshots
-    pub fn snap_flush_preload(snapdir: &str, snapid: &str, store: &Memstore) -> IoResult<()> {
+    pub fn snap_flush_preload(
+        snapdir: &str,
+        snapid: &str,
+        store: &Memstore,
+        format: u8,
+    ) -> IoResult<()> {
         let preload_tmp = concat_str!(snapdir, "/", snapid, "/", "PRELOAD_");
-        let preload = &preload_tmp[..preload_tmp.len() - 1];
-        routine_flushpreload!(store, preload_tmp, preload)
+        routine_flushpreload!(store, preload_tmp, format)
     }
 }
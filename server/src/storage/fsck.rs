@@ -0,0 +1,205 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Repairs
+//!
+//! A table file that was truncated or corrupted (or that went missing) would otherwise keep
+//! the server from starting. Instead, such tables are set aside while the data directory is
+//! read and repaired here: the damaged file is moved into `data/quarantine`, and the table
+//! is restored from the most recent local snapshot that has a good copy of it, or is
+//! recreated empty if none does. Every repaired table is logged (along with where its data
+//! came from), and the repaired tables are flushed right away
+
+use super::interface::{DIR_KSROOT, DIR_QUARANTINE, DIR_SNAPROOT};
+use crate::corestore::htable::Coremap;
+use crate::corestore::memstore::{Memstore, ObjectID};
+use crate::corestore::table::Table;
+use crate::IoResult;
+use std::fs;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A table that couldn't be read
+pub struct Damaged {
+    pub ksid: ObjectID,
+    pub tblid: ObjectID,
+    pub model_code: u8,
+    pub error: IoError,
+}
+
+/// What was done to repair a damaged table
+#[derive(Debug)]
+pub struct Repair {
+    /// The table, as `keyspace:table`
+    pub entity: String,
+    /// Where the damaged file was moved to (if there was one)
+    pub quarantined: Option<String>,
+    /// The snapshot that the table was restored from (if any)
+    pub snapshot: Option<String>,
+    /// The number of keys in the repaired table
+    pub keys: usize,
+}
+
+/// Returns true if `error` means that a table file is damaged (rather than, say, unreadable
+/// because of its permissions)
+pub fn is_damage(error: &IoError) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::InvalidData | ErrorKind::UnexpectedEof | ErrorKind::NotFound
+    )
+}
+
+/// Repair all the `damaged` tables and add them to `store`, returning what was done
+pub fn repair(store: &Memstore, damaged: Vec<Damaged>) -> IoResult<Vec<Repair>> {
+    if damaged.is_empty() {
+        return Ok(Vec::new());
+    }
+    log::warn!(
+        "Found {} damaged table(s) while reading the data directory. Repairing",
+        damaged.len()
+    );
+    let quarantine = self::quarantine_dir();
+    let snapshots = self::local_snapshots();
+    let mut repairs = Vec::with_capacity(damaged.len());
+    for damaged in damaged {
+        let repair = self::repair_table(store, &damaged, &quarantine, &snapshots)?;
+        match &repair.snapshot {
+            Some(snapshot) => log::warn!(
+                "Table `{}` was damaged ({}). Restored {} key(s) from snapshot `{}`",
+                repair.entity,
+                damaged.error,
+                repair.keys,
+                snapshot
+            ),
+            None => log::warn!(
+                "Table `{}` was damaged ({}) and no snapshot has it. Recreated it empty",
+                repair.entity,
+                damaged.error
+            ),
+        }
+        if let Some(path) = &repair.quarantined {
+            log::warn!(
+                "The damaged file of `{}` was moved to {}",
+                repair.entity,
+                path
+            );
+        }
+        repairs.push(repair);
+    }
+    Ok(repairs)
+}
+
+fn repair_table(
+    store: &Memstore,
+    damaged: &Damaged,
+    quarantine: &str,
+    snapshots: &[String],
+) -> IoResult<Repair> {
+    let (ksid, tblid) = unsafe { (damaged.ksid.as_str(), damaged.tblid.as_str()) };
+    let entity = concat_str!(ksid, ":", tblid);
+    // move the damaged file out of the way, so that nothing reads it again
+    let path = concat_path!(DIR_KSROOT, ksid, tblid);
+    let quarantined = if path.exists() {
+        let dir = concat_path!(quarantine, ksid);
+        fs::create_dir_all(&dir)?;
+        let target = dir.join(tblid);
+        fs::rename(&path, &target)?;
+        Some(target.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+    let (table, snapshot) = match self::restore(damaged, snapshots) {
+        Some((table, snapshot)) => (table, Some(snapshot)),
+        None => (
+            super::unflush::new_table(Coremap::new(), false, damaged.model_code)?,
+            None,
+        ),
+    };
+    let keys = table.count();
+    // write it right away, so that the directory is whole again even if we crash
+    super::flush::oneshot::flush_table(&damaged.tblid, &damaged.ksid, &table)?;
+    if let Some(keyspace) = store.keyspaces.get(&damaged.ksid) {
+        keyspace
+            .tables
+            .upsert(damaged.tblid.clone(), Arc::new(table));
+    }
+    Ok(Repair {
+        entity,
+        quarantined,
+        snapshot,
+        keys,
+    })
+}
+
+/// Read the most recent good copy of the `damaged` table from the local `snapshots`, along
+/// with the name of the snapshot that it was read from
+fn restore(damaged: &Damaged, snapshots: &[String]) -> Option<(Table, String)> {
+    snapshots.iter().find_map(|snapshot| {
+        let root = concat_str!(DIR_SNAPROOT, "/", snapshot);
+        let partmap = super::unflush::read_partmap(&root, &damaged.ksid).ok()?;
+        match partmap.get(&damaged.tblid) {
+            // the table must have been persistent and had the same model back then
+            Some((0, model_code)) if *model_code == damaged.model_code => {}
+            _ => return None,
+        }
+        super::unflush::read_table(
+            &root,
+            &damaged.ksid,
+            &damaged.tblid,
+            false,
+            damaged.model_code,
+        )
+        .ok()
+        .map(|table| (table, snapshot.clone()))
+    })
+}
+
+/// The local snapshots that were finished (and hence have a `PRELOAD`), most recent first
+fn local_snapshots() -> Vec<String> {
+    let mut snapshots: Vec<String> = match fs::read_dir(DIR_SNAPROOT) {
+        Ok(dir) => dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|snapshot| {
+                Path::new(&concat_path!(DIR_SNAPROOT, snapshot, "PRELOAD")).is_file()
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // snapshot names are timestamps (like `20210811-224512`), so they sort by age
+    snapshots.sort_unstable_by(|a, b| b.cmp(a));
+    snapshots
+}
+
+/// A new directory under `data/quarantine` for this run's damaged files
+fn quarantine_dir() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    concat_str!(DIR_QUARANTINE, "/", &now.to_string())
+}
@@ -30,6 +30,7 @@ use crate::corestore::htable::Coremap;
 use crate::corestore::htable::Data;
 use crate::corestore::memstore::Keyspace;
 use crate::corestore::memstore::Memstore;
+use crate::kvengine::KVEngine;
 use crate::registry;
 use crate::IoResult;
 use std::collections::HashSet;
@@ -40,6 +41,8 @@ pub const DIR_KSROOT: &str = "data/ks";
 pub const DIR_SNAPROOT: &str = "data/snaps";
 pub const DIR_RSNAPROOT: &str = "data/rsnap";
 pub const DIR_BACKUPS: &str = "data/backups";
+pub const DIR_IMPORTS: &str = "data/imports";
+pub const DIR_QUARANTINE: &str = "data/quarantine";
 pub const DIR_ROOT: &str = "data";
 
 /// This creates the root directory structure:
@@ -51,6 +54,7 @@ pub const DIR_ROOT: &str = "data";
 ///         ks3/
 ///     snaps/
 ///     backups/
+///     imports/
 /// ```
 ///
 /// If any directories exist, they are simply ignored
@@ -59,6 +63,7 @@ pub fn create_tree(memroot: &Memstore) -> IoResult<()> {
         DIR_ROOT,
         DIR_KSROOT,
         DIR_BACKUPS,
+        DIR_IMPORTS,
         DIR_SNAPROOT,
         DIR_RSNAPROOT
     );
@@ -134,6 +139,18 @@ pub fn serialize_map_into_slow_buffer<T: Write>(
     Ok(())
 }
 
+/// Same as [`serialize_map_into_slow_buffer`], except that a header is written with every
+/// record (see [`compat`](super::compat))
+pub fn serialize_records_into_slow_buffer<T: Write>(
+    buffer: &mut T,
+    kve: &KVEngine,
+) -> IoResult<()> {
+    let mut buffer = BufWriter::new(buffer);
+    super::se::raw_serialize_records(kve, &mut buffer)?;
+    buffer.flush()?;
+    Ok(())
+}
+
 pub fn serialize_partmap_into_slow_buffer<T: Write>(buffer: &mut T, ks: &Keyspace) -> IoResult<()> {
     let mut buffer = BufWriter::new(buffer);
     super::se::raw_serialize_partmap(&mut buffer, ks)?;
@@ -144,9 +161,10 @@ pub fn serialize_partmap_into_slow_buffer<T: Write>(buffer: &mut T, ks: &Keyspac
 pub fn serialize_preload_into_slow_buffer<T: Write>(
     buffer: &mut T,
     store: &Memstore,
+    format: u8,
 ) -> IoResult<()> {
     let mut buffer = BufWriter::new(buffer);
-    super::preload::raw_generate_preload(&mut buffer, store)?;
+    super::preload::raw_generate_preload(&mut buffer, store, format)?;
     buffer.flush()?;
     Ok(())
 }
@@ -51,6 +51,8 @@
 use crate::corestore::array::Array;
 use crate::corestore::htable::Coremap;
 use crate::corestore::Data;
+use crate::kvengine::KVEngine;
+use crate::storage::bytemarks::{BYTEMARK_RECORD_FLAG_EXPIRES, BYTEMARK_RECORD_TYPE_RAW};
 use core::hash::Hash;
 use core::mem;
 use core::ptr;
@@ -62,11 +64,16 @@ use std::io::Write;
 mod macros;
 // endof do not mess
 pub mod bytemarks;
+pub mod compat;
+pub mod diff;
 pub mod flush;
+pub mod fsck;
 pub mod interface;
 pub mod preload;
+pub mod progress;
 pub mod sengine;
 pub mod unflush;
+pub mod usage;
 // test
 #[cfg(test)]
 mod tests;
@@ -170,6 +177,11 @@ mod tests;
     Do not down cast before swapping bytes
 */
 
+/// The length of a record header without any optional fields: `[HLEN][TYPE][FLAGS]`
+const RECORD_HEADER_LEN: u8 = 3;
+/// The length of a record header for a key that expires: `[HLEN][TYPE][FLAGS][EXPIRY:8B]`
+const RECORD_HEADER_LEN_EXPIRES: u8 = RECORD_HEADER_LEN + 8;
+
 /// Get the raw bytes of anything.
 ///
 /// DISCLAIMER: THIS FUNCTION CAN DO TERRIBLE THINGS
@@ -214,6 +226,39 @@ mod se {
         Ok(())
     }
 
+    /// Serialize the records of a key/value table and write them to a provided buffer
+    /// ```text
+    /// [8B: LEN]([1B: HLEN][1B: TYPE][1B: FLAGS][8B: EXPIRY]?[8B: KLEN][8B: VLEN][?B: K][?B: V])*
+    /// ```
+    /// Every record header starts with its own length so that fields can be added to it
+    /// later on; the expiry (a UNIX timestamp in milliseconds, in little endian) is only
+    /// there if the expiry flag is set
+    pub fn raw_serialize_records<W: Write>(kve: &KVEngine, w: &mut W) -> std::io::Result<()> {
+        let map = kve.__get_inner_ref();
+        unsafe {
+            w.write_all(raw_byte_repr(&to_64bit_little_endian!(map.len())))?;
+            for kv in map.iter() {
+                let (k, v) = (kv.key(), kv.value());
+                match kve.expiry_of(k) {
+                    Some(deadline) => {
+                        w.write_all(&[
+                            RECORD_HEADER_LEN_EXPIRES,
+                            BYTEMARK_RECORD_TYPE_RAW,
+                            BYTEMARK_RECORD_FLAG_EXPIRES,
+                        ])?;
+                        w.write_all(&deadline.to_le_bytes())?;
+                    }
+                    None => w.write_all(&[RECORD_HEADER_LEN, BYTEMARK_RECORD_TYPE_RAW, 0])?,
+                }
+                w.write_all(raw_byte_repr(&to_64bit_little_endian!(k.len())))?;
+                w.write_all(raw_byte_repr(&to_64bit_little_endian!(v.len())))?;
+                w.write_all(k)?;
+                w.write_all(v)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Serialize a set and write it to a provided buffer
     pub fn raw_serialize_set<W, K, V>(map: &Coremap<K, V>, w: &mut W) -> std::io::Result<()>
     where
@@ -441,6 +486,82 @@ mod de {
         }
     }
 
+    /// Deserialize a file that contains serialized records (see
+    /// [`raw_serialize_records`](super::se::raw_serialize_records)), returning the map along
+    /// with the deadlines of the keys that expire. Keys that expired before `now` are left out
+    ///
+    /// Fields at the end of a record header that we don't know about are skipped, but types
+    /// and flags that we don't know about are rejected, since they change what the record means
+    pub fn deserialize_records(
+        data: Vec<u8>,
+        now: u64,
+    ) -> Option<(Coremap<Data, Data>, Coremap<Data, u64>)> {
+        if data.len() < 8 {
+            return None;
+        }
+        unsafe {
+            let mut ptr = data.as_ptr();
+            let len = transmute_len(ptr);
+            ptr = ptr.add(8);
+            let hm = Coremap::with_capacity(len);
+            let expiry = Coremap::new();
+            let end_ptr = data.as_ptr().add(data.len());
+            for _ in 0..len {
+                if ptr >= end_ptr {
+                    return None;
+                }
+                let hlen = ptr::read(ptr);
+                if hlen < RECORD_HEADER_LEN || ptr.add(hlen as usize + 16) > end_ptr {
+                    // not enough space for the header and the lengths
+                    return None;
+                }
+                let (rtype, flags) = (ptr::read(ptr.add(1)), ptr::read(ptr.add(2)));
+                if rtype != BYTEMARK_RECORD_TYPE_RAW || flags & !BYTEMARK_RECORD_FLAG_EXPIRES != 0 {
+                    return None;
+                }
+                let deadline = if flags & BYTEMARK_RECORD_FLAG_EXPIRES != 0 {
+                    if hlen < RECORD_HEADER_LEN_EXPIRES {
+                        return None;
+                    }
+                    Some(u64::from_le_bytes(ptr::read_unaligned(
+                        ptr.add(RECORD_HEADER_LEN as usize).cast(),
+                    )))
+                } else {
+                    None
+                };
+                // skip over the header, including any fields that we don't know about
+                ptr = ptr.add(hlen as usize);
+                let lenkey = transmute_len(ptr);
+                ptr = ptr.add(8);
+                let lenval = transmute_len(ptr);
+                ptr = ptr.add(8);
+                if (ptr.add(lenkey + lenval)) > end_ptr {
+                    // not enough data left
+                    return None;
+                }
+                let key = Data::copy_from_slice(slice::from_raw_parts(ptr, lenkey));
+                ptr = ptr.add(lenkey);
+                let val = Data::copy_from_slice(slice::from_raw_parts(ptr, lenval));
+                ptr = ptr.add(lenval);
+                match deadline {
+                    // this key expired while it was on disk
+                    Some(deadline) if deadline <= now => {}
+                    Some(deadline) => {
+                        expiry.upsert(key.clone(), deadline);
+                        hm.upsert(key, val);
+                    }
+                    None => hm.upsert(key, val),
+                }
+            }
+            if ptr == end_ptr {
+                Some((hm, expiry))
+            } else {
+                // nope, someone gave us more data
+                None
+            }
+        }
+    }
+
     #[allow(clippy::needless_return)] // Clippy really misunderstands this
     pub(super) unsafe fn transmute_len(start_ptr: *const u8) -> usize {
         little_endian!({
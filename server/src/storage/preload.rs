@@ -32,6 +32,7 @@
 //! 2. the `PARTMAP` preload that is placed in the ks directory
 //!
 
+use super::compat;
 use crate::corestore::memstore::Memstore;
 use crate::corestore::memstore::ObjectID;
 use crate::IoResult;
@@ -44,25 +45,20 @@ use std::io::Write;
 
 pub type LoadedPartfile = HashMap<ObjectID, (u8, u8)>;
 
-// our version and endian are based on nibbles
-
-#[cfg(target_endian = "little")]
-const META_SEGMENT: u8 = 0b1000_0000;
-
-#[cfg(target_endian = "big")]
-const META_SEGMENT: u8 = 0b1000_0001;
-
 /// Generate the `PRELOAD` disk file for this instance
 /// ```text
-/// [1B: Endian Mark/Version Mark (padded)] => Meta segment
+/// [1B: Endian Mark/Version Mark (padded)] => Meta segment (see [`compat::preload_meta`])
 /// [8B: Extent header] => Predata Segment
 /// ([8B: Partion ID len][8B: Parition ID (not padded)])* => Data segment
 /// ```
 ///
-pub(super) fn raw_generate_preload<W: Write>(w: &mut W, store: &Memstore) -> IoResult<()> {
+pub(super) fn raw_generate_preload<W: Write>(
+    w: &mut W,
+    store: &Memstore,
+    format: u8,
+) -> IoResult<()> {
     // generate the meta segment
-    #[allow(clippy::identity_op)]
-    w.write_all(&[META_SEGMENT])?;
+    w.write_all(&[compat::preload_meta(format)])?;
     super::se::raw_serialize_set(&store.keyspaces, w)?;
     Ok(())
 }
@@ -76,9 +72,7 @@ pub(super) fn read_preload_raw(preload: Vec<u8>) -> IoResult<HashSet<ObjectID>>
     // first read in the meta segment
     unsafe {
         let meta_segment: u8 = ptr::read(preload.as_ptr());
-        if meta_segment != META_SEGMENT {
-            return Err(IoError::from(ErrorKind::Unsupported));
-        }
+        compat::preload_format(meta_segment)?;
     }
     // all checks complete; time to decode
     let ret = super::de::deserialize_set_ctype(&preload[1..]);
@@ -0,0 +1,137 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Recovery progress
+//!
+//! Restoring a large data directory can take a while, so while we read it in, we log how far
+//! along we are (and tell systemd, if it started us) every few seconds. That way, an operator
+//! can tell a server that's still recovering apart from one that has hung
+
+use crate::corestore::memstore::ObjectID;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// How often progress is reported
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tracks how many tables (and how many bytes) of a tree have been read in
+pub struct Progress {
+    tables_total: usize,
+    tables_done: usize,
+    bytes_total: u64,
+    bytes_done: u64,
+    started: Instant,
+    last_report: Instant,
+}
+
+impl Progress {
+    /// Start tracking the recovery of the tree at `root`, given the `(keyspace, table)` IDs
+    /// of all the tables in it
+    pub fn new<'a>(root: &str, tables: impl Iterator<Item = (&'a ObjectID, &'a ObjectID)>) -> Self {
+        let mut tables_total = 0;
+        let mut bytes_total = 0;
+        for (ksid, tblid) in tables {
+            tables_total += 1;
+            let filepath = unsafe { concat_path!(root, ksid.as_str(), tblid.as_str()) };
+            // volatile tables don't have files, so they don't count
+            bytes_total += fs::metadata(filepath).map_or(0, |meta| meta.len());
+        }
+        if tables_total != 0 {
+            log::info!(
+                "Recovering {} tables ({} bytes) from {}",
+                tables_total,
+                bytes_total,
+                root
+            );
+        }
+        let now = Instant::now();
+        Self {
+            tables_total,
+            tables_done: 0,
+            bytes_total,
+            bytes_done: 0,
+            started: now,
+            last_report: now,
+        }
+    }
+    /// Record that a table of `bytes` bytes has been read in
+    pub fn table_done(&mut self, bytes: u64) {
+        self.tables_done += 1;
+        self.bytes_done += bytes;
+        if self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.last_report = Instant::now();
+            let status = self.status();
+            log::info!("{}", status);
+            crate::services::systemd::notify_status(&status);
+        }
+    }
+    /// Returns the estimated time left, extrapolating from the bytes read so far
+    fn eta(&self) -> Option<Duration> {
+        if self.bytes_done == 0 {
+            return None;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let left = (self.bytes_total.saturating_sub(self.bytes_done)) as f64;
+        Some(Duration::from_secs_f64(
+            elapsed * left / self.bytes_done as f64,
+        ))
+    }
+    fn status(&self) -> String {
+        let mut status = format!(
+            "Recovering: {}/{} tables, {}/{} bytes",
+            self.tables_done, self.tables_total, self.bytes_done, self.bytes_total
+        );
+        if let Some(eta) = self.eta() {
+            status.push_str(&format!(", about {}s left", eta.as_secs()));
+        }
+        status
+    }
+    /// Log the summary once everything has been read in
+    pub fn finish(&self) {
+        if self.tables_total != 0 {
+            log::info!(
+                "Recovered {} tables ({} bytes) in {:.2}s",
+                self.tables_done,
+                self.bytes_done,
+                self.started.elapsed().as_secs_f64()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_progress_status() {
+    let mut progress = Progress::new("data/ks", std::iter::empty());
+    assert!(progress.eta().is_none());
+    assert_eq!(progress.status(), "Recovering: 0/0 tables, 0/0 bytes");
+    progress.tables_total = 2;
+    progress.bytes_total = 200;
+    progress.table_done(100);
+    assert!(progress.eta().is_some());
+    assert!(progress
+        .status()
+        .starts_with("Recovering: 1/2 tables, 100/200 bytes, about "));
+}
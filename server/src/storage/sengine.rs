@@ -24,17 +24,25 @@
  *
 */
 
+// without the `snapshots` feature, the engine never makes any snapshots
+#![cfg_attr(not(feature = "snapshots"), allow(dead_code, unused_imports))]
+
 use self::queue::Queue;
+use super::compat::FORMAT_CURRENT;
 use super::interface::DIR_SNAPROOT;
+use crate::allocator::{self, Subsystem};
 use crate::corestore::iarray::IArray;
 use crate::corestore::lazy::Lazy;
 use crate::corestore::lock::QuickLock;
+use crate::registry::jobs::{Job, JobKind};
 use crate::storage::interface::DIR_RSNAPROOT;
 use crate::Memstore;
 use bytes::Bytes;
+#[cfg(feature = "snapshots")]
 use chrono::prelude::Utc;
 use core::fmt;
 use core::str;
+use libsky::manifest;
 use regex::Regex;
 use std::fs;
 use std::io::Error as IoError;
@@ -133,18 +141,32 @@ impl SnapshotEngine {
         parse_dir!(self.local_queue, DIR_SNAPROOT);
         Ok(())
     }
+    #[cfg(feature = "snapshots")]
     /// Generate the snapshot name
     fn get_snapname(&self) -> String {
         Utc::now().format("%Y%m%d-%H%M%S").to_string()
     }
     fn _mksnap_blocking_section(store: &Memstore, name: &str) -> SnapshotResult<()> {
-        super::flush::snap_flush_full(DIR_SNAPROOT, name, store)?;
+        let mut job = Job::start(JobKind::Snapshot);
+        super::flush::snap_flush_full(DIR_SNAPROOT, name, store, FORMAT_CURRENT, &job)
+            .and_then(|_| manifest::write(&concat_path!(DIR_SNAPROOT, name)))
+            .map_err(|e| {
+                job.fail();
+                e
+            })?;
         Ok(())
     }
     fn _rmksnap_blocking_section(store: &Memstore, name: &str) -> SnapshotResult<()> {
-        super::flush::snap_flush_full(DIR_RSNAPROOT, name, store)?;
+        let mut job = Job::start(JobKind::RemoteSnapshot);
+        super::flush::snap_flush_full(DIR_RSNAPROOT, name, store, FORMAT_CURRENT, &job)
+            .and_then(|_| manifest::write(&concat_path!(DIR_RSNAPROOT, name)))
+            .map_err(|e| {
+                job.fail();
+                e
+            })?;
         Ok(())
     }
+    #[cfg(feature = "snapshots")]
     /// Spawns a blocking task on a threadpool for blocking tasks. Returns either of:
     /// - `0` => Okay (returned **even if old snap deletion failed**)
     /// - `1` => Error
@@ -161,7 +183,9 @@ impl SnapshotEngine {
             let nameclone = name.clone();
             let todel = queue.add_new(name);
             let snap_create_result = tokio::task::spawn_blocking(move || {
-                Self::_mksnap_blocking_section(&store, &nameclone)
+                allocator::scope(Subsystem::Snapshots, || {
+                    Self::_mksnap_blocking_section(&store, &nameclone)
+                })
             })
             .await
             .expect("mksnap thread panicked");
@@ -196,6 +220,7 @@ impl SnapshotEngine {
             2
         }
     }
+    #[cfg(feature = "snapshots")]
     /// Spawns a blocking task to create a remote snapshot. Returns either of:
     /// - `0` => Okay
     /// - `1` => Error
@@ -211,7 +236,10 @@ impl SnapshotEngine {
                 // SAFETY: We have already checked if name is UTF-8
                 str::from_utf8_unchecked(&name)
             };
-            if let Err(e) = Self::_rmksnap_blocking_section(&store, name_str) {
+            let result = allocator::scope(Subsystem::Snapshots, || {
+                Self::_rmksnap_blocking_section(&store, name_str)
+            });
+            if let Err(e) = result {
                 log::error!("Remote snapshot failed with: {}", e);
                 1
             } else {
@@ -222,6 +250,16 @@ impl SnapshotEngine {
         .await
         .expect("rmksnap thread panicked")
     }
+    #[cfg(not(feature = "snapshots"))]
+    /// Snapshots need the `snapshots` feature, so this always returns `2` (disabled)
+    pub async fn mksnap(&self, _store: Arc<Memstore>) -> u8 {
+        2
+    }
+    #[cfg(not(feature = "snapshots"))]
+    /// Snapshots need the `snapshots` feature, so this always returns `2` (disabled)
+    pub async fn mkrsnap(&self, _name: Bytes, _store: Arc<Memstore>) -> u8 {
+        2
+    }
 }
 
 mod queue {
@@ -276,10 +314,8 @@ mod queue {
             if self.queue.is_empty() {
                 None
             } else {
-                Some(unsafe {
-                    // SAFETY: We have already checked if the queue is empty or not
-                    self.queue.remove(0)
-                })
+                // we have already checked if the queue is empty or not
+                Some(self.queue.remove(0))
             }
         }
         pub fn pop_last(&mut self) -> Option<String> {
@@ -47,6 +47,77 @@ fn test_ser_de_few_elements() {
         .all(|kv| cmap.get(kv.key()).unwrap().eq(kv.value())));
 }
 
+#[test]
+fn test_ser_de_binary() {
+    let all_bytes: Vec<u8> = (0..=u8::MAX).collect();
+    let cmap = Coremap::new();
+    cmap.upsert(Data::from(all_bytes.clone()), Data::from(all_bytes.clone()));
+    cmap.upsert("lf".into(), Data::from(b"\n\n\r\n".to_vec()));
+    cmap.upsert(
+        Data::from(b"\xF0\x90\x80".to_vec()),
+        Data::from(b"\0".to_vec()),
+    );
+    cmap.upsert("empty".into(), Data::from(Vec::new()));
+    let ser = se::serialize_map(&cmap).unwrap();
+    let de = de::deserialize_map(ser).unwrap();
+    assert_eq!(de.len(), cmap.len());
+    assert!(de
+        .iter()
+        .all(|kv| cmap.get(kv.key()).unwrap().eq(kv.value())));
+}
+
+#[test]
+fn test_ser_de_records() {
+    use crate::kvengine::now_ms;
+    let kve = KVEngine::default();
+    kve.set("forever".into(), "value".into()).unwrap();
+    kve.set("later".into(), "value".into()).unwrap();
+    kve.set("soon".into(), "value".into()).unwrap();
+    kve.set("empty".into(), Data::from(Vec::new())).unwrap();
+    let later = now_ms() + 60_000;
+    kve.set_expiry("later".into(), later).unwrap();
+    kve.set_expiry("soon".into(), now_ms() + 1_000).unwrap();
+    let mut ser = Vec::new();
+    se::raw_serialize_records(&kve, &mut ser).unwrap();
+    let (data, expiry) = de::deserialize_records(ser.clone(), now_ms()).unwrap();
+    assert_eq!(data.len(), 4);
+    assert_eq!(expiry.len(), 2);
+    assert_eq!(expiry.get_cloned("later".as_bytes()), Some(later));
+    // keys that expired while on disk don't come back
+    let (data, expiry) = de::deserialize_records(ser.clone(), later).unwrap();
+    assert_eq!(data.len(), 2);
+    assert!(data.contains_key("forever".as_bytes()));
+    assert!(expiry.len() == 0);
+    // records with types that we don't know about are rejected
+    let mut bad = ser.clone();
+    bad[9] = 0xFF;
+    assert!(de::deserialize_records(bad, now_ms()).is_none());
+    // truncated
+    ser.pop();
+    assert!(de::deserialize_records(ser, now_ms()).is_none());
+}
+
+#[test]
+fn test_de_records_skips_unknown_header_fields() {
+    // a record header with two bytes that we don't know about at the end
+    let mut ser = 1u64.to_le_bytes().to_vec();
+    ser.extend_from_slice(&[
+        RECORD_HEADER_LEN + 2,
+        BYTEMARK_RECORD_TYPE_RAW,
+        0,
+        0xAB,
+        0xCD,
+    ]);
+    ser.extend_from_slice(&5u64.to_le_bytes());
+    ser.extend_from_slice(&5u64.to_le_bytes());
+    ser.extend_from_slice(b"helloworld");
+    let (data, _) = de::deserialize_records(ser, 0).unwrap();
+    assert_eq!(
+        data.get_cloned("hello".as_bytes()).unwrap(),
+        Data::from("world")
+    );
+}
+
 cfg_test!(
     use libstress::utils::generate_random_string_vector;
     use rand::thread_rng;
@@ -165,7 +236,7 @@ mod preload_tests {
     fn test_preload() {
         let memstore = Memstore::new_default();
         let mut v = Vec::new();
-        preload::raw_generate_preload(&mut v, &memstore).unwrap();
+        preload::raw_generate_preload(&mut v, &memstore, compat::FORMAT_CURRENT).unwrap();
         let de: Vec<String> = preload::read_preload_raw(v)
             .unwrap()
             .into_iter()
@@ -242,6 +313,7 @@ mod flush_routines {
     use crate::corestore::memstore::ObjectID;
     use crate::corestore::table::Table;
     use crate::corestore::Data;
+    use crate::storage::interface::DIR_KSROOT;
     use std::fs;
     #[test]
     fn test_flush_unflush_table() {
@@ -256,7 +328,7 @@ mod flush_routines {
         fs::create_dir_all("data/ks/myks1").unwrap();
         super::flush::oneshot::flush_table(&tblid, &ksid, &tbl).unwrap();
         // now that it's flushed, let's read the table using and unflush routine
-        let ret = super::unflush::read_table(&ksid, &tblid, false, 0).unwrap();
+        let ret = super::unflush::read_table(DIR_KSROOT, &ksid, &tblid, false, 0).unwrap();
         assert_eq!(
             ret.get_kvstore()
                 .unwrap()
@@ -268,6 +340,29 @@ mod flush_routines {
         );
     }
     #[test]
+    fn test_flush_unflush_binary_table() {
+        let all_bytes: Vec<u8> = (0..=u8::MAX).rev().collect();
+        let tbl = Table::new_default_kve();
+        tbl.get_kvstore()
+            .unwrap()
+            .set(Data::from(all_bytes.clone()), Data::from(all_bytes.clone()))
+            .unwrap();
+        let tblid = unsafe { ObjectID::from_slice("mybintbl") };
+        let ksid = unsafe { ObjectID::from_slice("mybinks") };
+        fs::create_dir_all("data/ks/mybinks").unwrap();
+        super::flush::oneshot::flush_table(&tblid, &ksid, &tbl).unwrap();
+        let ret = super::unflush::read_table(DIR_KSROOT, &ksid, &tblid, false, 0).unwrap();
+        assert_eq!(
+            ret.get_kvstore()
+                .unwrap()
+                .get(&Data::from(all_bytes.clone()))
+                .unwrap()
+                .unwrap()
+                .clone(),
+            Data::from(all_bytes)
+        );
+    }
+    #[test]
     fn test_flush_unflush_keyspace() {
         // create the temp dir for this test
         fs::create_dir_all("data/ks/myks_1").unwrap();
@@ -286,7 +381,7 @@ mod flush_routines {
         // and a volatile table
         ks.create_table(tbl2.clone(), Table::new_kve_with_volatile(true));
         super::flush::flush_keyspace_full(&ksid, &ks).unwrap();
-        let ret = super::unflush::read_keyspace(&ksid).unwrap();
+        let ret = super::unflush::read_keyspace(DIR_KSROOT, &ksid).unwrap();
         let tbl1_ret = ret.get(&tbl1).unwrap();
         let tbl2_ret = ret.get(&tbl2).unwrap();
         assert_eq!(
@@ -301,4 +396,236 @@ mod flush_routines {
         );
         assert!(tbl2_ret.get_kvstore().unwrap().len() == 0);
     }
+    #[test]
+    fn test_flush_unflush_table_formats() {
+        use crate::storage::compat::{FORMAT_V1, FORMAT_V2, FORMAT_V3, TABLE_MAGIC};
+        let tbl = Table::new_default_kve();
+        tbl.get_kvstore()
+            .unwrap()
+            .set("hello".into(), "world".into())
+            .unwrap();
+        let tblid = unsafe { ObjectID::from_slice("mytbl") };
+        let ksid = unsafe { ObjectID::from_slice("myformatks") };
+        fs::create_dir_all("data/formattests/myformatks").unwrap();
+        for format in [FORMAT_V1, FORMAT_V2, FORMAT_V3].iter() {
+            super::flush::oneshot::snap_flush_table(
+                "data",
+                "formattests",
+                &ksid,
+                &tblid,
+                &tbl,
+                *format,
+            )
+            .unwrap();
+            let file = fs::read("data/formattests/myformatks/mytbl").unwrap();
+            assert_eq!(file.starts_with(TABLE_MAGIC), *format != FORMAT_V1);
+            let ret =
+                super::unflush::read_table("data/formattests", &ksid, &tblid, false, 0).unwrap();
+            assert_eq!(
+                ret.get_kvstore()
+                    .unwrap()
+                    .get(&Data::from("hello"))
+                    .unwrap()
+                    .unwrap()
+                    .clone(),
+                Data::from("world")
+            );
+        }
+    }
+    #[test]
+    fn test_flush_unflush_table_expiry() {
+        use crate::kvengine::now_ms;
+        use crate::storage::compat::FORMAT_CURRENT;
+        let tbl = Table::new_default_kve();
+        let kve = tbl.get_kvstore().unwrap();
+        kve.set("hello".into(), "world".into()).unwrap();
+        kve.set("bye".into(), "world".into()).unwrap();
+        let later = now_ms() + 60_000;
+        kve.set_expiry("hello".into(), later).unwrap();
+        let tblid = unsafe { ObjectID::from_slice("mytbl") };
+        let ksid = unsafe { ObjectID::from_slice("myexpiryks") };
+        fs::create_dir_all("data/expirytests/myexpiryks").unwrap();
+        super::flush::oneshot::snap_flush_table(
+            "data",
+            "expirytests",
+            &ksid,
+            &tblid,
+            &tbl,
+            FORMAT_CURRENT,
+        )
+        .unwrap();
+        let ret = super::unflush::read_table("data/expirytests", &ksid, &tblid, false, 0).unwrap();
+        let kve = ret.get_kvstore().unwrap();
+        assert_eq!(kve.expiry_of("hello".as_bytes()), Some(later));
+        assert_eq!(kve.expiry_of("bye".as_bytes()), None);
+    }
+    #[tokio::test]
+    async fn test_unload_reload_table() {
+        use crate::kvengine::now_ms;
+        use crate::storage::compat::FORMAT_CURRENT;
+        use std::sync::Arc;
+        let tbl = Arc::new(Table::new_default_kve());
+        let kve = tbl.get_kvstore().unwrap();
+        kve.set("hello".into(), "world".into()).unwrap();
+        let later = now_ms() + 60_000;
+        kve.set_expiry("hello".into(), later).unwrap();
+        let tblid = unsafe { ObjectID::from_slice("mytbl") };
+        let ksid = unsafe { ObjectID::from_slice("myunloadks") };
+        fs::create_dir_all("data/unloadtests/myunloadks").unwrap();
+        super::flush::oneshot::snap_flush_table(
+            "data",
+            "unloadtests",
+            &ksid,
+            &tblid,
+            &tbl,
+            FORMAT_CURRENT,
+        )
+        .unwrap();
+        tbl.unload("data/unloadtests/myunloadks/mytbl".to_owned());
+        assert!(tbl.is_unloaded());
+        assert_eq!(tbl.count(), 0);
+        // the keyspace has no directory in the live tree, so this would fail if it wrote
+        // anything
+        super::flush::oneshot::flush_table(&tblid, &ksid, &tbl).unwrap();
+        // accessing the table doesn't load it; that's done on a blocking thread
+        tbl.touch();
+        assert!(tbl.is_unloaded());
+        tbl.load().await;
+        assert!(!tbl.is_unloaded());
+        assert_eq!(tbl.unloaded_from(), None);
+        let kve = tbl.get_kvstore().unwrap();
+        assert_eq!(
+            kve.get(&Data::from("hello")).unwrap().unwrap().clone(),
+            Data::from("world")
+        );
+        assert_eq!(kve.expiry_of("hello".as_bytes()), Some(later));
+    }
+    #[test]
+    fn test_warm_unloaded_table() {
+        use crate::storage::compat::FORMAT_CURRENT;
+        let tbl = Table::new_default_kve();
+        let kve = tbl.get_kvstore().unwrap();
+        kve.set("hello".into(), "world".into()).unwrap();
+        let tblid = unsafe { ObjectID::from_slice("mytbl") };
+        let ksid = unsafe { ObjectID::from_slice("mywarmks") };
+        fs::create_dir_all("data/warmtests/mywarmks").unwrap();
+        super::flush::oneshot::snap_flush_table(
+            "data",
+            "warmtests",
+            &ksid,
+            &tblid,
+            &tbl,
+            FORMAT_CURRENT,
+        )
+        .unwrap();
+        tbl.unload("data/warmtests/mywarmks/mytbl".to_owned());
+        assert!(!tbl.is_warm());
+        // warming up loads the table and reads through every key and value
+        assert_eq!(tbl.warm(), "hello".len() + "world".len());
+        assert!(tbl.is_warm());
+        assert!(!tbl.is_unloaded());
+        assert_eq!(tbl.count(), 1);
+    }
+    #[test]
+    fn test_write_atomic_failure_keeps_old_file() {
+        use std::io::{Error, ErrorKind, Write};
+        fs::create_dir_all("data/atomictests").unwrap();
+        super::flush::oneshot::write_atomic("data/atomictests/myfile_", |file| {
+            file.write_all(b"old")
+        })
+        .unwrap();
+        // a write that fails halfway through
+        let ret = super::flush::oneshot::write_atomic("data/atomictests/myfile_", |file| {
+            file.write_all(b"ne")?;
+            Err(Error::new(ErrorKind::Other, "crashed"))
+        });
+        assert!(ret.is_err());
+        assert_eq!(fs::read("data/atomictests/myfile").unwrap(), b"old");
+        assert!(!std::path::Path::new("data/atomictests/myfile_").exists());
+    }
+    #[test]
+    fn test_snapshot_manifest() {
+        use libsky::manifest::{self, Problem};
+        use std::path::Path;
+        let root = Path::new("data/manifesttests");
+        fs::create_dir_all("data/manifesttests/myks").unwrap();
+        fs::write("data/manifesttests/PRELOAD", b"preload").unwrap();
+        fs::write("data/manifesttests/myks/mytbl", b"hello world").unwrap();
+        fs::write("data/manifesttests/myks/other", b"bye").unwrap();
+        manifest::write(root).unwrap();
+        let entries = manifest::read(root).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, ["PRELOAD", "myks/mytbl", "myks/other"]);
+        let report = manifest::verify(root).unwrap();
+        assert!(report.is_intact());
+        assert_eq!(report.verified, 3);
+        // flip a byte, drop a file and add one
+        fs::write("data/manifesttests/myks/mytbl", b"hello wORld").unwrap();
+        fs::remove_file("data/manifesttests/myks/other").unwrap();
+        fs::write("data/manifesttests/myks/extra", b"").unwrap();
+        let report = manifest::verify(root).unwrap();
+        assert_eq!(report.verified, 1);
+        assert_eq!(
+            report.problems,
+            [
+                ("myks/mytbl".to_owned(), Problem::ChecksumMismatch),
+                ("myks/other".to_owned(), Problem::Missing),
+                ("myks/extra".to_owned(), Problem::Unlisted),
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+    #[test]
+    fn test_repair_damaged_table() {
+        use crate::corestore::memstore::Memstore;
+        use crate::storage::bytemarks::BYTEMARK_MODEL_KV_BIN_BIN;
+        use crate::storage::fsck::{self, Damaged};
+        fs::create_dir_all("data/ks/myrepairks").unwrap();
+        let ksid = unsafe { ObjectID::from_slice("myrepairks") };
+        let tblid = unsafe { ObjectID::from_slice("mytbl") };
+        let tbl = Table::new_default_kve();
+        tbl.get_kvstore()
+            .unwrap()
+            .set("hello".into(), "world".into())
+            .unwrap();
+        super::flush::oneshot::flush_table(&tblid, &ksid, &tbl).unwrap();
+        // chop off the end of the file, like a crash halfway through writing it would
+        let file = fs::read("data/ks/myrepairks/mytbl").unwrap();
+        let truncated = &file[..file.len() - 3];
+        fs::write("data/ks/myrepairks/mytbl", truncated).unwrap();
+        let error = match super::unflush::read_table(
+            DIR_KSROOT,
+            &ksid,
+            &tblid,
+            false,
+            BYTEMARK_MODEL_KV_BIN_BIN,
+        ) {
+            Ok(_) => panic!("Read a truncated table"),
+            Err(e) => e,
+        };
+        assert!(fsck::is_damage(&error));
+        let store = Memstore::new_default();
+        store.create_keyspace(ksid.clone());
+        let damaged = Damaged {
+            ksid: ksid.clone(),
+            tblid: tblid.clone(),
+            model_code: BYTEMARK_MODEL_KV_BIN_BIN,
+            error,
+        };
+        let repairs = fsck::repair(&store, vec![damaged]).unwrap();
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repairs[0].entity, "myrepairks:mytbl");
+        // no snapshot has this table, so it's recreated empty
+        assert!(repairs[0].snapshot.is_none());
+        assert_eq!(repairs[0].keys, 0);
+        let quarantined = repairs[0].quarantined.as_ref().unwrap();
+        assert_eq!(fs::read(quarantined).unwrap(), truncated);
+        // the repaired table was flushed, so it can be read again
+        let ret =
+            super::unflush::read_table(DIR_KSROOT, &ksid, &tblid, false, BYTEMARK_MODEL_KV_BIN_BIN)
+                .unwrap();
+        assert_eq!(ret.count(), 0);
+        let keyspace = store.get_keyspace_atomic_ref(&ksid).unwrap();
+        assert!(keyspace.tables.get(&tblid).is_some());
+    }
 }
@@ -29,42 +29,69 @@
 //! Routines for unflushing data
 
 use super::bytemarks;
+use super::compat;
+use super::fsck::{self, Damaged};
 use crate::corestore::memstore::Keyspace;
 use crate::corestore::memstore::Memstore;
 use crate::corestore::memstore::ObjectID;
 use crate::corestore::table::Table;
+use crate::corestore::Data;
+use crate::kvengine;
+use crate::registry;
+use crate::registry::topology;
 use crate::storage::interface::DIR_KSROOT;
 use crate::storage::preload::LoadedPartfile;
+use crate::storage::progress::Progress;
 use crate::storage::Coremap;
 use crate::IoResult;
+use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::fs;
 use std::io::Error as IoError;
 use std::io::ErrorKind;
 use std::path::Path;
 use std::sync::Arc;
+use std::thread;
+use std::vec;
 
 type PreloadSet = std::collections::HashSet<ObjectID>;
-const PRELOAD_PATH: &str = "data/ks/PRELOAD";
 
-/// Read a given table into a [`Table`] object
+/// Read a given table from the tree at `root` into a [`Table`] object
 ///
 /// This will take care of volatility and the model_code. Just make sure that you pass the proper
 /// keyspace ID and a valid table ID
 pub fn read_table(
+    root: &str,
     ksid: &ObjectID,
     tblid: &ObjectID,
     volatile: bool,
     model_code: u8,
 ) -> IoResult<Table> {
-    let filepath = unsafe { concat_path!(DIR_KSROOT, ksid.as_str(), tblid.as_str()) };
-    let data = if volatile {
+    let filepath = unsafe { concat_path!(root, ksid.as_str(), tblid.as_str()) };
+    let (data, expiry) = if volatile {
         // no need to read anything; table is volatile and has no file
-        Coremap::new()
+        (Coremap::new(), Coremap::new())
     } else {
         // not volatile, so read this in
-        let f = fs::read(filepath)?;
-        super::de::deserialize_map(f).ok_or_else(|| bad_data!())?
+        self::read_table_data(filepath)?
     };
+    let tbl = self::new_table(data, volatile, model_code)?;
+    let encoding_is_okay = match tbl.get_kvstore() {
+        Ok(kve) => {
+            kve.load_expiries(expiry);
+            kve.verify_encoding()
+        }
+        Err(_) => true,
+    };
+    if !encoding_is_okay {
+        // a str table has invalid unicode; someone has been messing with the file
+        return Err(bad_data!());
+    }
+    Ok(tbl)
+}
+
+/// Create a table with the given `data` and model code
+pub fn new_table(data: Coremap<Data, Data>, volatile: bool, model_code: u8) -> IoResult<Table> {
     let tbl = match model_code {
         bytemarks::BYTEMARK_MODEL_KV_BIN_BIN => {
             Table::new_kve_with_data(data, volatile, false, false)
@@ -83,31 +110,197 @@ pub fn read_table(
     Ok(tbl)
 }
 
-/// Read an entire keyspace into a Coremap. You'll need to initialize the rest
-pub fn read_keyspace(ksid: &ObjectID) -> IoResult<Coremap<ObjectID, Arc<Table>>> {
-    let partmap = self::read_partmap(ksid)?;
+/// Read the keys and the deadlines (of the keys that haven't expired yet) from the table
+/// file at `filepath`, which can be in any of the supported formats
+pub fn read_table_data(
+    filepath: impl AsRef<Path>,
+) -> IoResult<(Coremap<Data, Data>, Coremap<Data, u64>)> {
+    let mut f = fs::read(filepath)?;
+    let (format, header_len) = compat::read_table_header(&f)?;
+    f.drain(..header_len);
+    if format < compat::FORMAT_V3 {
+        // versions 1 and 2 share the same data segment, which has no expiries
+        let data = super::de::deserialize_map(f).ok_or_else(|| bad_data!())?;
+        Ok((data, Coremap::new()))
+    } else {
+        super::de::deserialize_records(f, kvengine::now_ms()).ok_or_else(|| bad_data!())
+    }
+}
+
+/// Read an entire keyspace from the tree at `root` into a Coremap. You'll need to initialize
+/// the rest
+pub fn read_keyspace(root: &str, ksid: &ObjectID) -> IoResult<Coremap<ObjectID, Arc<Table>>> {
+    let partmap = self::read_partmap(root, ksid)?;
     let ks: Coremap<ObjectID, Arc<Table>> = Coremap::with_capacity(partmap.len());
     for (tableid, (table_storage_type, model_code)) in partmap.into_iter() {
-        if table_storage_type > 1 {
-            return Err(bad_data!());
-        }
-        let is_volatile = table_storage_type == bytemarks::BYTEMARK_STORAGE_VOLATILE;
-        let tbl = self::read_table(ksid, &tableid, is_volatile, model_code)?;
+        let tbl = self::read_partmap_table(root, ksid, &tableid, table_storage_type, model_code)?;
         ks.true_if_insert(tableid, Arc::new(tbl));
     }
     Ok(ks)
 }
 
-/// Read the `PARTMAP` for a given keyspace
-pub fn read_partmap(ksid: &ObjectID) -> IoResult<LoadedPartfile> {
-    let filepath = unsafe { concat_path!(DIR_KSROOT, ksid.as_str(), "PARTMAP") };
+/// Read a table with the storage type and model code that its keyspace's `PARTMAP` has
+fn read_partmap_table(
+    root: &str,
+    ksid: &ObjectID,
+    tblid: &ObjectID,
+    table_storage_type: u8,
+    model_code: u8,
+) -> IoResult<Table> {
+    if table_storage_type > 1 {
+        return Err(bad_data!());
+    }
+    let is_volatile = table_storage_type == bytemarks::BYTEMARK_STORAGE_VOLATILE;
+    self::read_table(root, ksid, tblid, is_volatile, model_code)
+}
+
+/// A table that is yet to be read: its keyspace, its ID, and its storage type and model code
+type TableJob = (ObjectID, ObjectID, u8, u8);
+
+/// The tables that were read, and the ones that were damaged (see [`fsck`])
+type ReadTables = (Vec<(ObjectID, ObjectID, Table)>, Vec<Damaged>);
+
+/// Read all the tables in `jobs` from the tree at `root`, with as many threads as the
+/// registry allows (see [`registry::get_recovery_threads`]). Every table that is read is
+/// reported to `progress`
+///
+/// If `repair` is set, tables that are damaged are returned instead of failing the read
+fn read_tables(
+    root: &str,
+    jobs: Vec<TableJob>,
+    progress: &Arc<Mutex<Progress>>,
+    repair: bool,
+) -> IoResult<ReadTables> {
+    let threads = registry::get_recovery_threads().min(jobs.len()).max(1);
+    let jobs = Arc::new(Mutex::new(jobs.into_iter()));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let (root, jobs, progress) = (root.to_owned(), jobs.clone(), progress.clone());
+            thread::spawn(move || {
+                topology::pin_background();
+                let ret = self::read_tables_worker(&root, &jobs, &progress, repair);
+                if ret.is_err() {
+                    // the recovery has failed, so make the other threads stop early
+                    *jobs.lock() = Vec::new().into_iter();
+                }
+                ret
+            })
+        })
+        .collect();
+    let mut tables = Vec::new();
+    let mut damaged = Vec::new();
+    let mut error = None;
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok((read, bad))) => {
+                tables.extend(read);
+                damaged.extend(bad);
+            }
+            Ok(Err(e)) => {
+                error.get_or_insert(e);
+            }
+            Err(_) => {
+                error.get_or_insert(IoError::new(ErrorKind::Other, "recovery thread panicked"));
+            }
+        }
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok((tables, damaged)),
+    }
+}
+
+/// Keep reading tables off `jobs` till there are none left
+fn read_tables_worker(
+    root: &str,
+    jobs: &Mutex<vec::IntoIter<TableJob>>,
+    progress: &Mutex<Progress>,
+    repair: bool,
+) -> IoResult<ReadTables> {
+    let mut read = Vec::new();
+    let mut damaged = Vec::new();
+    loop {
+        let job = jobs.lock().next();
+        let (ksid, tblid, table_storage_type, model_code) = match job {
+            Some(job) => job,
+            None => break,
+        };
+        let ret = self::read_partmap_table(root, &ksid, &tblid, table_storage_type, model_code);
+        let filepath = unsafe { concat_path!(root, ksid.as_str(), tblid.as_str()) };
+        progress
+            .lock()
+            .table_done(fs::metadata(filepath).map_or(0, |meta| meta.len()));
+        match ret {
+            Ok(tbl) => read.push((ksid, tblid, tbl)),
+            Err(error) if repair && fsck::is_damage(&error) => damaged.push(Damaged {
+                ksid,
+                tblid,
+                model_code,
+                error,
+            }),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((read, damaged))
+}
+
+/// Read the `PARTMAP` for a given keyspace from the tree at `root`
+pub fn read_partmap(root: &str, ksid: &ObjectID) -> IoResult<LoadedPartfile> {
+    let filepath = unsafe { concat_path!(root, ksid.as_str(), "PARTMAP") };
     super::preload::read_partfile_raw(fs::read(filepath)?)
 }
 
-/// Read the `PRELOAD`
-pub fn read_preload() -> IoResult<PreloadSet> {
-    let read = fs::read(PRELOAD_PATH)?;
-    super::preload::read_preload_raw(read)
+/// Read the `PRELOAD` from the tree at `root`, returning the format of the tree (see
+/// [`compat`]) along with the keyspaces
+pub fn read_preload(root: &str) -> IoResult<(u8, PreloadSet)> {
+    let read = fs::read(concat_path!(root, "PRELOAD"))?;
+    let format = match read.first() {
+        Some(meta) => compat::preload_format(*meta)?,
+        None => return Err(IoError::from(ErrorKind::UnexpectedEof)),
+    };
+    Ok((format, super::preload::read_preload_raw(read)?))
+}
+
+/// Read the whole tree at `root` (laid out like `data/ks`, which is also how snapshots
+/// are laid out) into a [`Memstore`], returning the format that the tree is in
+pub fn read_tree_with_format(root: &str) -> IoResult<(u8, Memstore)> {
+    self::read_tree_inner(root, false).map(|(format, store, _)| (format, store))
+}
+
+/// Read the whole tree at `root`, returning the damaged tables (which are left out of the
+/// store) instead of failing if `repair` is set
+fn read_tree_inner(root: &str, repair: bool) -> IoResult<(u8, Memstore, Vec<Damaged>)> {
+    let (format, preload) = self::read_preload(root)?;
+    let mut keyspaces = HashMap::with_capacity(preload.len());
+    let mut jobs = Vec::new();
+    for ksid in preload {
+        let partmap = self::read_partmap(root, &ksid)?;
+        let ks: Coremap<ObjectID, Arc<Table>> = Coremap::with_capacity(partmap.len());
+        for (tblid, (table_storage_type, model_code)) in partmap {
+            jobs.push((ksid.clone(), tblid, table_storage_type, model_code));
+        }
+        keyspaces.insert(ksid, ks);
+    }
+    // the tables are read in parallel, and put into their keyspaces once they're all in
+    let progress = Progress::new(root, jobs.iter().map(|(ksid, tblid, ..)| (ksid, tblid)));
+    let progress = Arc::new(Mutex::new(progress));
+    let (tables, damaged) = self::read_tables(root, jobs, &progress, repair)?;
+    for (ksid, tblid, tbl) in tables {
+        if let Some(ks) = keyspaces.get(&ksid) {
+            ks.true_if_insert(tblid, Arc::new(tbl));
+        }
+    }
+    progress.lock().finish();
+    let ksmap = Coremap::with_capacity(keyspaces.len());
+    for (ksid, ks) in keyspaces {
+        ksmap.upsert(ksid, Arc::new(Keyspace::init_with_all_def_strategy(ks)));
+    }
+    Ok((format, Memstore::init_with_all(ksmap), damaged))
+}
+
+/// Read the whole tree at `root` into a [`Memstore`]
+pub fn read_tree(root: &str) -> IoResult<Memstore> {
+    self::read_tree_with_format(root).map(|(_, store)| store)
 }
 
 /// Read everything and return a [`Memstore`]
@@ -136,13 +329,25 @@ pub fn read_full() -> IoResult<Memstore> {
         super::flush::flush_full(&store)?;
         return Ok(store);
     }
-    let preload = self::read_preload()?;
-    let ksmap = Coremap::with_capacity(preload.len());
-    for ksid in preload {
-        let ks = Keyspace::init_with_all_def_strategy(self::read_keyspace(&ksid)?);
-        ksmap.upsert(ksid, Arc::new(ks));
+    let (format, store, damaged) = self::read_tree_inner(DIR_KSROOT, true)?;
+    super::fsck::repair(&store, damaged)?;
+    if format < compat::FORMAT_CURRENT {
+        /*
+        The tree is in an older format, so upgrade it in place. The tables are rewritten
+        first and the PRELOAD last; if we crash in-between, the tables that were already
+        rewritten are still recognized by their headers
+        */
+        log::info!(
+            "Upgrading data directory from format version {} to {}",
+            format,
+            compat::FORMAT_CURRENT
+        );
+        for keyspace in store.keyspaces.iter() {
+            super::flush::flush_keyspace_full(keyspace.key(), keyspace.value())?;
+        }
+        super::flush::oneshot::flush_preload(&store)?;
     }
-    Ok(Memstore::init_with_all(ksmap))
+    Ok(store)
 }
 
 /// Check if the data/ks/PRELOAD file exists (if not: we're on a new instance)
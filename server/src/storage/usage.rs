@@ -0,0 +1,117 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Disk usage
+//!
+//! Routines to find out how many bytes each keyspace and table uses on disk, both for
+//! its data files and for its share of the snapshots (and remote snapshots)
+
+use crate::storage::interface::{DIR_KSROOT, DIR_RSNAPROOT, DIR_SNAPROOT};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The number of bytes used on disk by a keyspace or a table
+#[derive(Debug, Default, PartialEq)]
+pub struct DiskUsage {
+    /// bytes used by the data files
+    pub data: u64,
+    /// bytes used in all the snapshots
+    pub snapshots: u64,
+}
+
+/// Returns the size of the file, or zero if it doesn't exist (like volatile tables)
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |meta| meta.len())
+}
+
+/// Returns the total size of the files in the directory (not including subdirectories)
+fn dir_size(path: &Path) -> u64 {
+    match fs::read_dir(path) {
+        Ok(dir) => dir
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len())
+            .sum(),
+        Err(_) => 0,
+    }
+}
+
+/// Returns the directories of all the snapshots and remote snapshots
+fn snapshot_dirs() -> Vec<PathBuf> {
+    [DIR_SNAPROOT, DIR_RSNAPROOT]
+        .iter()
+        .filter_map(|root| fs::read_dir(root).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// Returns the disk usage of the keyspace (including its `PARTMAP`)
+pub fn keyspace_usage(ksid: &str) -> DiskUsage {
+    DiskUsage {
+        data: dir_size(&concat_path!(DIR_KSROOT, ksid)),
+        snapshots: snapshot_dirs()
+            .iter()
+            .map(|snapdir| dir_size(&snapdir.join(ksid)))
+            .sum(),
+    }
+}
+
+/// Returns the disk usage of the table in the keyspace
+pub fn table_usage(ksid: &str, tblid: &str) -> DiskUsage {
+    DiskUsage {
+        data: file_size(&concat_path!(DIR_KSROOT, ksid, tblid)),
+        snapshots: snapshot_dirs()
+            .iter()
+            .map(|snapdir| file_size(&snapdir.join(ksid).join(tblid)))
+            .sum(),
+    }
+}
+
+#[test]
+fn test_disk_usage() {
+    fs::create_dir_all("data/ks/myusageks").unwrap();
+    fs::write("data/ks/myusageks/mytbl", [0u8; 10]).unwrap();
+    fs::write("data/ks/myusageks/PARTMAP", [0u8; 5]).unwrap();
+    assert_eq!(
+        table_usage("myusageks", "mytbl"),
+        DiskUsage {
+            data: 10,
+            snapshots: 0
+        }
+    );
+    assert_eq!(
+        keyspace_usage("myusageks"),
+        DiskUsage {
+            data: 15,
+            snapshots: 0
+        }
+    );
+    // missing tables (like volatile ones) don't use anything
+    assert_eq!(table_usage("myusageks", "missing"), DiskUsage::default());
+}
@@ -0,0 +1,118 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{Element, RespCode};
+    async fn test_auth_bad_credentials() {
+        // the test server has no users, so no credentials are right
+        query.push("AUTH");
+        query.push("root");
+        query.push("notatoken");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-bad-credentials".to_owned()))
+        );
+    }
+    async fn test_auth_wrong_args() {
+        query.push("AUTH");
+        query.push("root");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+}
+
+mod revocation {
+    use super::super::local::{self, response, run};
+    use crate::protocol::responses::{full_responses, groups};
+    use crate::registry::auth::{self, User};
+
+    #[tokio::test]
+    async fn test_del_and_passwd_revoke_connections() {
+        let _global = local::lock();
+        let okay = full_responses::R_OKAY;
+        // the admin stays, so that authentication stays enabled once the user is removed
+        auth::add_user(User::new(
+            "revocation-admin".to_owned(),
+            "token".to_owned(),
+            None,
+        ));
+        let mut admin = local::store();
+        let mut con = admin.clone();
+        assert_eq!(
+            run(&mut admin, &["AUTH", "revocation-admin", "token"], true).await,
+            okay
+        );
+        assert_eq!(
+            run(
+                &mut admin,
+                &["SYS", "USER", "ADD", "revocation-user", "old"],
+                true
+            )
+            .await,
+            okay
+        );
+        assert_eq!(
+            run(&mut con, &["AUTH", "revocation-user", "old"], false).await,
+            okay
+        );
+        assert_eq!(run(&mut con, &["SET", "x", "100"], false).await, okay);
+        // changing the password revokes the connection
+        assert_eq!(
+            run(
+                &mut admin,
+                &["SYS", "USER", "PASSWD", "revocation-user", "new"],
+                true
+            )
+            .await,
+            okay
+        );
+        assert_eq!(
+            run(&mut con, &["GET", "x"], false).await,
+            response(groups::AUTH_REQUIRED)
+        );
+        assert_eq!(
+            run(&mut con, &["AUTH", "revocation-user", "new"], false).await,
+            okay
+        );
+        assert_eq!(
+            run(&mut con, &["DEL", "x"], false).await,
+            response(b":1\n1\n")
+        );
+        // and so does removing the user
+        assert_eq!(
+            run(&mut admin, &["SYS", "USER", "DEL", "revocation-user"], true).await,
+            okay
+        );
+        assert_eq!(
+            run(&mut con, &["GET", "x"], false).await,
+            response(groups::AUTH_REQUIRED)
+        );
+        auth::remove_user("revocation-admin");
+    }
+}
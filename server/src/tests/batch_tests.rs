@@ -0,0 +1,76 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{types::Array, Element, RespCode};
+    async fn test_batch() {
+        query.push("BATCH");
+        query.push("3");
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        query.push("2");
+        query.push("GET");
+        query.push("x");
+        query.push("3");
+        query.push("SET");
+        query.push("x");
+        query.push("200");
+        query.push("1");
+        query.push("NOTANACTION");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Recursive(vec![
+                Element::RespCode(RespCode::Okay),
+                Element::String("100".to_owned()),
+                Element::RespCode(RespCode::OverwriteError),
+                Element::RespCode(RespCode::ErrorString("Unknown action".to_owned())),
+            ]))
+        );
+    }
+    async fn test_batch_bad_count() {
+        query.push("BATCH");
+        query.push("3");
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_batch_nested() {
+        query.push("BATCH");
+        query.push("3");
+        query.push("batch");
+        query.push("1");
+        query.push("HEYA");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    macro_rules! runeq {
+        ($con:ident, [$($arg:expr),*], $resp:expr) => {
+            let mut q = Query::new();
+            $(q.push($arg);)*
+            assert_eq!($con.run_simple_query(&q).await.unwrap(), $resp);
+        };
+    }
+    use skytable::{Element, Query, RespCode};
+    /*
+     The test tables use string values, so we stick to bits that keep the value valid UTF-8
+    */
+    async fn test_setbit_getbit() {
+        query.push("SETBIT");
+        query.push("flags");
+        query.push("1");
+        query.push("1");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+        runeq!(con, ["GETBIT", "flags", "1"], Element::UnsignedInt(1));
+        runeq!(con, ["GETBIT", "flags", "2"], Element::UnsignedInt(0));
+        runeq!(con, ["GETBIT", "flags", "1000"], Element::UnsignedInt(0));
+        runeq!(con, ["GET", "flags"], Element::String("@".to_owned()));
+        runeq!(con, ["SETBIT", "flags", "1", "0"], Element::UnsignedInt(1));
+        runeq!(con, ["GETBIT", "flags", "1"], Element::UnsignedInt(0));
+    }
+    async fn test_getbit_missing_key() {
+        query.push("GETBIT");
+        query.push("nope");
+        query.push("10");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+    }
+    async fn test_setbit_bad_bit() {
+        query.push("SETBIT");
+        query.push("flags");
+        query.push("1");
+        query.push("2");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-bad-bit".to_owned()))
+        );
+        runeq!(
+            con,
+            ["SETBIT", "flags", "-1", "1"],
+            Element::RespCode(RespCode::ErrorString("err-bad-bit".to_owned()))
+        );
+    }
+    async fn test_bitcount() {
+        query.push("SET");
+        query.push("x");
+        query.push("foobar");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, ["BITCOUNT", "x"], Element::UnsignedInt(26));
+        runeq!(con, ["BITCOUNT", "nope"], Element::UnsignedInt(0));
+    }
+    async fn test_bitop() {
+        query.push("MSET");
+        query.push("a");
+        query.push("abc");
+        query.push("b");
+        query.push("a");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        runeq!(
+            con,
+            ["BITOP", "AND", "dest", "a", "b"],
+            Element::UnsignedInt(3)
+        );
+        runeq!(con, ["GET", "dest"], Element::String("a\0\0".to_owned()));
+        runeq!(
+            con,
+            ["BITOP", "OR", "dest", "a", "b"],
+            Element::UnsignedInt(3)
+        );
+        runeq!(con, ["GET", "dest"], Element::String("abc".to_owned()));
+        runeq!(
+            con,
+            ["BITOP", "XOR", "dest", "a", "a"],
+            Element::UnsignedInt(3)
+        );
+        runeq!(con, ["BITCOUNT", "dest"], Element::UnsignedInt(0));
+    }
+    async fn test_bitop_bad_op() {
+        query.push("BITOP");
+        query.push("NAND");
+        query.push("dest");
+        query.push("a");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-bitop".to_owned()))
+        );
+        runeq!(
+            con,
+            ["BITOP", "NOT", "dest", "a", "b"],
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+}
@@ -58,6 +58,48 @@ mod __private {
             Element::RespCode(RespCode::Okay)
         );
     }
+    async fn test_create_keyspace_persistence_none() {
+        let mut rng = rand::thread_rng();
+        let ksname = utils::rand_alphastring(10, &mut rng);
+        query.push("create");
+        query.push("keyspace");
+        query.push(&ksname);
+        query.push("persistence(none)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let entity = format!("{}:mytbl", ksname);
+        let mut query = Query::new();
+        query.push("create");
+        query.push("table");
+        query.push(&entity);
+        query.push("keymap(str,str)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("inspect");
+        query.push("table");
+        query.push(entity);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("Keymap { data:(str,str), volatile:true }".to_owned())
+        );
+    }
+    async fn test_create_keyspace_bad_persistence() {
+        let mut rng = rand::thread_rng();
+        let ksname = utils::rand_alphastring(10, &mut rng);
+        query.push("create");
+        query.push("keyspace");
+        query.push(&ksname);
+        query.push("persistence(journal)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-property".to_owned()))
+        );
+    }
     async fn test_create_table() {
         let mut rng = rand::thread_rng();
         let tblname = utils::rand_alphastring(10, &mut rng);
@@ -110,6 +152,279 @@ mod __private {
             Element::RespCode(RespCode::Okay)
         );
     }
+    async fn test_create_table_bloom() {
+        let mykeyspace: &str = __MYENTITY__.split(':').collect::<Vec<&str>>()[0];
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        let my_fqe = mykeyspace.to_owned() + ":" + &tblname;
+        query.push("create");
+        query.push("table");
+        query.push(&my_fqe);
+        query.push("keymap(str,str)");
+        query.push("bloom(0.01)");
+        query.push("volatile");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("USE");
+        query.push(&my_fqe);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("y");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_create_table_bad_bloom() {
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        query.push("create");
+        query.push("table");
+        query.push(&tblname);
+        query.push("keymap(str,str)");
+        query.push("bloom(1.5)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-property".to_owned()))
+        );
+    }
+    async fn test_create_table_response_cache() {
+        let mykeyspace: &str = __MYENTITY__.split(':').collect::<Vec<&str>>()[0];
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        let my_fqe = mykeyspace.to_owned() + ":" + &tblname;
+        query.push("create");
+        query.push("table");
+        query.push(&my_fqe);
+        query.push("keymap(str,str)");
+        query.push("cache(16)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("USE");
+        query.push(&my_fqe);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut get = Query::new();
+        get.push("GET");
+        get.push("x");
+        for _ in 0..2 {
+            assert_eq!(
+                con.run_simple_query(&get).await.unwrap(),
+                Element::String("100".to_owned())
+            );
+        }
+        // a write invalidates the cached response
+        let mut query = Query::new();
+        query.push("UPDATE");
+        query.push("x");
+        query.push("200");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            con.run_simple_query(&get).await.unwrap(),
+            Element::String("200".to_owned())
+        );
+    }
+    async fn test_create_table_bad_cache() {
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        query.push("create");
+        query.push("table");
+        query.push(&tblname);
+        query.push("keymap(str,str)");
+        query.push("cache(0)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-property".to_owned()))
+        );
+    }
+    async fn test_create_table_merge() {
+        let mykeyspace: &str = __MYENTITY__.split(':').collect::<Vec<&str>>()[0];
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        let my_fqe = mykeyspace.to_owned() + ":" + &tblname;
+        query.push("create");
+        query.push("table");
+        query.push(&my_fqe);
+        query.push("keymap(str,str)");
+        query.push("merge(add)");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        // the table that we're using has no merge operator
+        let mut merge = Query::new();
+        merge.push("MERGE");
+        merge.push("x");
+        merge.push("5");
+        merge.push("2");
+        assert_eq!(
+            con.run_simple_query(&merge).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-no-merge-operator".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push("USE");
+        query.push(&my_fqe);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                con.run_simple_query(&merge).await.unwrap(),
+                Element::RespCode(RespCode::Okay)
+            );
+        }
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("14".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("MERGE");
+        query.push("x");
+        query.push("five");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-bad-operand".to_owned()))
+        );
+    }
+    async fn test_clone_table() {
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("clone");
+        query.push("table");
+        query.push(&__MYENTITY__);
+        query.push(&tblname);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("SET");
+        query.push("x");
+        query.push("200");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::OverwriteError)
+        );
+        let mut query = Query::new();
+        query.push("USE");
+        query.push(&tblname);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_clone_table_already_exists() {
+        query.push("clone");
+        query.push("table");
+        query.push(&__MYENTITY__);
+        query.push(&__MYENTITY__);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-already-exists".to_owned()))
+        );
+    }
+    async fn test_clone_keyspace() {
+        let mykeyspace: &str = __MYENTITY__.split(':').collect::<Vec<&str>>()[0];
+        let mut rng = rand::thread_rng();
+        let ksname = utils::rand_alphastring(10, &mut rng);
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("clone");
+        query.push("keyspace");
+        query.push(mykeyspace);
+        query.push(&ksname);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mytable: &str = __MYENTITY__.split(':').collect::<Vec<&str>>()[1];
+        let mut query = Query::new();
+        query.push("USE");
+        query.push(ksname + ":" + mytable);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_clone_syntax_error() {
+        query.push("clone");
+        query.push("table");
+        query.push(&__MYENTITY__);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
     async fn test_drop_table() {
         let mut rng = rand::thread_rng();
         let tblname = utils::rand_alphastring(10, &mut rng);
@@ -152,6 +467,26 @@ mod __private {
             Element::RespCode(RespCode::Okay)
         );
     }
+    async fn test_undrop_table_not_dropped() {
+        // tables aren't retained after a drop unless `dropretention` is set
+        let mut rng = rand::thread_rng();
+        let tblname = utils::rand_alphastring(10, &mut rng);
+        query.push("undrop");
+        query.push("table");
+        query.push(&tblname);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("container-not-found".to_owned()))
+        );
+    }
+    async fn test_undrop_syntax_error() {
+        query.push("undrop");
+        query.push("table");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
     async fn test_use() {
         query.push("USE");
         query.push(&__MYENTITY__);
@@ -0,0 +1,71 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{Element, Query, RespCode};
+    async fn test_dump_table() {
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        con.run_simple_query(&query).await.unwrap();
+        let mut query = Query::new();
+        query.push("DUMP");
+        query.push("TABLE");
+        query.push(&__MYENTITY__);
+        assert!(matches!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(_)
+        ));
+    }
+    async fn test_dump_table_not_found() {
+        query.push("DUMP");
+        query.push("TABLE");
+        query.push("testsuite:thisdoesnotexist");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("container-not-found".to_owned()))
+        );
+    }
+    async fn test_dump_system_keyspace() {
+        query.push("DUMP");
+        query.push("KEYSPACE");
+        query.push("system");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-protected-object".to_owned()))
+        );
+    }
+    async fn test_dump_syntax_error() {
+        query.push("DUMP");
+        query.push("TABLES");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+}
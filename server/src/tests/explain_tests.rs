@@ -0,0 +1,165 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+#[sky_macros::dbtest(skip = "plan")]
+mod __private {
+    use skytable::{types::Array, Element, Query, RespCode};
+    /// Returns the plan that EXPLAIN should return for the test table
+    fn plan(entity: &str, access: &str, keys: usize) -> Element {
+        let keys = keys.to_string();
+        let plan = vec![
+            "entity",
+            entity,
+            "model",
+            "Keymap { data:(str,str), volatile:true }",
+            "access",
+            access,
+            "keys",
+            &keys,
+        ];
+        Element::Array(Array::Str(
+            plan.into_iter().map(|s| Some(s.to_owned())).collect(),
+        ))
+    }
+    async fn test_explain_lookup() {
+        query.push("EXPLAIN");
+        query.push("MSET");
+        query.push("x");
+        query.push("100");
+        query.push("y");
+        query.push("200");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            plan(&__MYENTITY__, "lookup", 2)
+        );
+    }
+    async fn test_explain_does_not_run() {
+        query.push("EXPLAIN");
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        con.run_simple_query(&query).await.unwrap();
+        let mut query = Query::new();
+        query.push("EXISTS");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+    }
+    async fn test_explain_scan_with_entity() {
+        query.push("MSET");
+        query.push("x");
+        query.push("100");
+        query.push("y");
+        query.push("200");
+        con.run_simple_query(&query).await.unwrap();
+        let mut query = Query::new();
+        query.push("EXPLAIN");
+        query.push("LSKEYS");
+        query.push(&__MYENTITY__);
+        query.push("10");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            plan(&__MYENTITY__, "scan", 2)
+        );
+    }
+    async fn test_explain_unknown_entity() {
+        query.push("EXPLAIN");
+        query.push("DBSIZE");
+        query.push("testsuite:thisdoesnotexist");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("container-not-found".to_owned()))
+        );
+    }
+    async fn test_explain_cannot_explain() {
+        query.push("EXPLAIN");
+        query.push("HEYA");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-cannot-explain".to_owned()))
+        );
+    }
+    async fn test_explain_wrong_args() {
+        query.push("EXPLAIN");
+        query.push("SET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_explain_dry_run() {
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        con.run_simple_query(&query).await.unwrap();
+        let mut query = Query::new();
+        query.push("EXPLAIN");
+        query.push("DRYRUN");
+        query.push("USET");
+        query.push("x");
+        query.push("1000");
+        query.push("y");
+        query.push("1");
+        let outcome = vec![
+            "created",
+            "1",
+            "overwritten",
+            "1",
+            "deleted",
+            "0",
+            "unchanged",
+            "0",
+            "bytes",
+            "3",
+        ];
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(
+                outcome.into_iter().map(|s| Some(s.to_owned())).collect()
+            ))
+        );
+        // nothing was changed
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_explain_dry_run_cannot_explain() {
+        query.push("EXPLAIN");
+        query.push("DRYRUN");
+        query.push("GET");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-cannot-explain".to_owned()))
+        );
+    }
+}
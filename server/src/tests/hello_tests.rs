@@ -0,0 +1,168 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{types::Array, Element, Query, RespCode};
+    async fn test_hello_offer() {
+        query.push("HELLO");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![
+                Some("1".to_owned()),
+                Some("typed-arrays".to_owned()),
+                Some("verbose-errors".to_owned()),
+                Some("key-errors".to_owned()),
+                Some("invalidations".to_owned()),
+                Some("metadata".to_owned()),
+                Some("nested".to_owned())
+            ]))
+        );
+    }
+    async fn test_hello_negotiate() {
+        // capabilities that the server doesn't know are left out
+        query.push("HELLO");
+        query.push("2");
+        query.push("compression");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("1".to_owned())]))
+        );
+        // and a bare HELLO now returns what was agreed on
+        let query = Query::from("HELLO");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("1".to_owned())]))
+        );
+    }
+    async fn test_hello_unsupported_version() {
+        query.push("HELLO");
+        query.push("0");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-unsupported-version".to_owned()))
+        );
+    }
+    async fn test_whoami() {
+        query.push("HELLO");
+        query.push("1");
+        query.push("typed-arrays");
+        con.run_simple_query(&query).await.unwrap();
+        let query = Query::from("WHOAMI");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(fields)) => {
+                assert_eq!(fields.len(), 10);
+                assert_eq!(fields[0].as_deref(), Some("keyspace"));
+                assert_eq!(fields[2].as_deref(), Some("table"));
+                assert!(fields[3].is_some());
+                // auth isn't enabled in the test suite
+                assert_eq!(fields[4].as_deref(), Some("user"));
+                assert_eq!(fields[5], None);
+                assert_eq!(fields[7].as_deref(), Some("1"));
+                assert_eq!(fields[9].as_deref(), Some("typed-arrays"));
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_verbose_arity_error() {
+        // without the capability, a wrong number of arguments is a plain action error
+        query.push("GET");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+        let mut query = Query::from("HELLO");
+        query.push("1");
+        query.push("verbose-errors");
+        con.run_simple_query(&query).await.unwrap();
+        let query = Query::from("GET");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-wrong-arity GET".to_owned()))
+        );
+    }
+    async fn test_key_errors() {
+        query.push("MSET");
+        query.push("x");
+        query.push("100");
+        query.push("y");
+        query.push("200");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        // without the capability, strong actions just return the error code
+        let mut query = Query::from("SSET");
+        query.push("x");
+        query.push("1");
+        query.push("z");
+        query.push("3");
+        query.push("y");
+        query.push("2");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::OverwriteError)
+        );
+        let mut hello = Query::from("HELLO");
+        hello.push("1");
+        hello.push("key-errors");
+        con.run_simple_query(&hello).await.unwrap();
+        // but with it, they return every key that failed the check
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("x".to_owned()), Some("y".to_owned())]))
+        );
+        let mut query = Query::from("SDEL");
+        query.push("a");
+        query.push("x");
+        query.push("b");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("a".to_owned()), Some("b".to_owned())]))
+        );
+        let mut query = Query::from("SUPDATE");
+        query.push("y");
+        query.push("1");
+        query.push("c");
+        query.push("2");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("c".to_owned())]))
+        );
+        // nothing was changed
+        let mut query = Query::from("MGET");
+        query.push("x");
+        query.push("y");
+        query.push("z");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![
+                Some("100".to_owned()),
+                Some("200".to_owned()),
+                None
+            ]))
+        );
+    }
+}
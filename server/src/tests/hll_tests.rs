@@ -0,0 +1,104 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    macro_rules! runeq {
+        ($con:ident, [$($arg:expr),*], $resp:expr) => {
+            let mut q = Query::new();
+            $(q.push($arg);)*
+            assert_eq!($con.run_simple_query(&q).await.unwrap(), $resp);
+        };
+    }
+    use skytable::{Element, Query, RespCode};
+    async fn test_pfadd_pfcount() {
+        query.push("PFADD");
+        query.push("visitors");
+        query.push("alice");
+        query.push("bob");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        // nothing changes if we add the same elements again
+        runeq!(con, ["PFADD", "visitors", "bob"], Element::UnsignedInt(0));
+        runeq!(con, ["PFCOUNT", "visitors"], Element::UnsignedInt(2));
+        runeq!(con, ["PFCOUNT", "nope"], Element::UnsignedInt(0));
+    }
+    async fn test_pfadd_creates_key() {
+        query.push("PFADD");
+        query.push("visitors");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        runeq!(con, ["PFADD", "visitors"], Element::UnsignedInt(0));
+        runeq!(con, ["PFCOUNT", "visitors"], Element::UnsignedInt(0));
+    }
+    async fn test_pfmerge() {
+        query.push("PFADD");
+        query.push("a");
+        query.push("1");
+        query.push("2");
+        query.push("3");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        runeq!(con, ["PFADD", "b", "3", "4"], Element::UnsignedInt(1));
+        runeq!(con, ["PFCOUNT", "a", "b"], Element::UnsignedInt(4));
+        runeq!(
+            con,
+            ["PFMERGE", "c", "a", "b"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, ["PFCOUNT", "c"], Element::UnsignedInt(4));
+    }
+    async fn test_pf_invalid_hll() {
+        query.push("SET");
+        query.push("x");
+        query.push("not a hll");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["PFADD", "x", "1"],
+            Element::RespCode(RespCode::ErrorString("err-invalid-hll".to_owned()))
+        );
+        runeq!(
+            con,
+            ["PFCOUNT", "x"],
+            Element::RespCode(RespCode::ErrorString("err-invalid-hll".to_owned()))
+        );
+        runeq!(
+            con,
+            ["PFMERGE", "y", "x"],
+            Element::RespCode(RespCode::ErrorString("err-invalid-hll".to_owned()))
+        );
+    }
+}
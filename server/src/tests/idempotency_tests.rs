@@ -0,0 +1,106 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    macro_rules! runeq {
+        ($con:ident, [$($arg:expr),*], $resp:expr) => {
+            let mut q = Query::new();
+            $(q.push($arg);)*
+            assert_eq!($con.run_simple_query(&q).await.unwrap(), $resp);
+        };
+    }
+    use libstress::utils;
+    use skytable::{Element, Query, RespCode};
+    async fn test_idempotent_retry() {
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "SET", "x", "100"],
+            Element::RespCode(RespCode::Okay)
+        );
+        // a second SET would fail with an overwrite error, but the retry isn't run again
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "SET", "x", "100"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["SET", "x", "100"],
+            Element::RespCode(RespCode::OverwriteError)
+        );
+    }
+    async fn test_idempotent_token_reused() {
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "SET", "x", "100"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "SET", "y", "200"],
+            Element::RespCode(RespCode::ErrorString("err-token-reused".to_owned()))
+        );
+    }
+    async fn test_idempotent_token_reused_on_another_table() {
+        let mykeyspace: &str = __MYENTITY__.split(':').collect::<Vec<&str>>()[0];
+        let mut rng = rand::thread_rng();
+        let other = mykeyspace.to_owned() + ":" + &utils::rand_alphastring(10, &mut rng);
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "SET", "x", "100"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["CREATE", "TABLE", &other, "keymap(str,str)", "volatile"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, ["USE", &other], Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "SET", "x", "100"],
+            Element::RespCode(RespCode::ErrorString("err-token-reused".to_owned()))
+        );
+        runeq!(
+            con,
+            ["USE", &__MYENTITY__],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["DROP", "TABLE", &other],
+            Element::RespCode(RespCode::Okay)
+        );
+    }
+    async fn test_idempotent_read() {
+        runeq!(
+            con,
+            ["IDEMPOTENT", "retry-1", "GET", "x"],
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+}
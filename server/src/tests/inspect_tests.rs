@@ -26,7 +26,8 @@
 
 #[sky_macros::dbtest]
 mod __private {
-    use skytable::{types::Array, Element, RespCode};
+    use libstress::utils;
+    use skytable::{types::Array, Element, Query, RespCode};
     async fn test_inspect_keyspaces() {
         query.push("INSPECT");
         query.push("KEYSPACES");
@@ -68,6 +69,28 @@ mod __private {
             _ => panic!("Bad response for inspect table"),
         }
     }
+    async fn test_inspect_history() {
+        let mut rng = rand::thread_rng();
+        let ksname = utils::rand_alphastring(10, &mut rng);
+        query.push("CREATE");
+        query.push("KEYSPACE");
+        query.push(&ksname);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("INSPECT");
+        query.push("HISTORY");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(entries)) => {
+                assert_eq!(entries.len() % 4, 0);
+                let ddl = format!("CREATE KEYSPACE {}", ksname);
+                assert!(entries.iter().any(|entry| entry.as_deref() == Some(&ddl)));
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
     async fn test_inspect_keyspaces_syntax_error() {
         query.push("INSPECT");
         query.push("KEYSPACES");
@@ -97,4 +120,96 @@ mod __private {
             Element::RespCode(RespCode::ActionError)
         );
     }
+    async fn test_inspect_usage() {
+        query.push("INSPECT");
+        query.push("USAGE");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(usage)) => {
+                assert!(usage.contains(&Some("default.data".to_owned())));
+                assert!(usage.contains(&Some("default.snapshots".to_owned())));
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_inspect_usage_keyspace() {
+        let (my_keyspace, my_table) = {
+            let entity: Vec<&str> = __MYENTITY__.split(':').collect();
+            (entity[0], entity[1])
+        };
+        query.push("INSPECT");
+        query.push("USAGE");
+        query.push(my_keyspace);
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(usage)) => {
+                // our table is volatile, so it has nothing on disk
+                let data = usage.chunks_exact(2).find_map(|kv| match kv {
+                    [Some(name), value] if *name == format!("{}.data", my_table) => value.clone(),
+                    _ => None,
+                });
+                assert_eq!(data.unwrap(), "0");
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_inspect_keyspace_paginated() {
+        let mut rng = rand::thread_rng();
+        let ksname = utils::rand_alphastring(10, &mut rng);
+        query.push("CREATE");
+        query.push("KEYSPACE");
+        query.push(&ksname);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        for tbl in &["tblc", "tbla", "other", "tblb"] {
+            let mut query = Query::new();
+            query.push("CREATE");
+            query.push("TABLE");
+            query.push(format!("{}:{}", ksname, tbl));
+            query.push("keymap(str,str)");
+            assert_eq!(
+                con.run_simple_query(&query).await.unwrap(),
+                Element::RespCode(RespCode::Okay)
+            );
+        }
+        let mut cursor = "0".to_owned();
+        let mut pages = Vec::new();
+        loop {
+            let mut query = Query::new();
+            query.push("INSPECT");
+            query.push("KEYSPACE");
+            query.push(&ksname);
+            query.push(&cursor);
+            query.push("2");
+            query.push("tbl");
+            match con.run_simple_query(&query).await.unwrap() {
+                Element::Array(Array::Str(page)) => {
+                    let mut page = page.into_iter().map(Option::unwrap);
+                    cursor = page.next().unwrap();
+                    pages.push(page.collect::<Vec<String>>());
+                }
+                x => panic!("Got unexpected element: {:?}", x),
+            }
+            if cursor == "0" {
+                break;
+            }
+        }
+        assert_eq!(
+            pages,
+            vec![
+                vec!["tbla".to_owned(), "tblb".to_owned()],
+                vec!["tblc".to_owned()]
+            ]
+        );
+    }
+    async fn test_inspect_keyspaces_paginated_bad_count() {
+        query.push("INSPECT");
+        query.push("KEYSPACES");
+        query.push("0");
+        query.push("many");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
 }
@@ -0,0 +1,167 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    macro_rules! runeq {
+        ($con:ident, [$($arg:expr),*], $resp:expr) => {
+            let mut q = Query::new();
+            $(q.push($arg);)*
+            assert_eq!($con.run_simple_query(&q).await.unwrap(), $resp);
+        };
+    }
+    use skytable::{Element, Query, RespCode};
+    async fn test_jset_jget_root() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$");
+        query.push(r#"{"name":"sayan","langs":["rust"]}"#);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["JGET", "doc", "$.langs[0]"],
+            Element::String(r#""rust""#.to_owned())
+        );
+    }
+    async fn test_jset_nested() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$");
+        query.push(r#"{"user":{}}"#);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["JSET", "doc", "$.user.name", r#""sayan""#],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["JGET", "doc"],
+            Element::String(r#"{"user":{"name":"sayan"}}"#.to_owned())
+        );
+    }
+    async fn test_jget_after_overwrite() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$");
+        query.push(r#"{"v":1}"#);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        // the document is cached now
+        runeq!(con, ["JGET", "doc", "$.v"], Element::String("1".to_owned()));
+        // a write that isn't a JSON action still invalidates it
+        runeq!(
+            con,
+            ["UPDATE", "doc", r#"{"v":2}"#],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, ["JGET", "doc", "$.v"], Element::String("2".to_owned()));
+        runeq!(
+            con,
+            ["JSET", "doc", "$.v", "3"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["JGET", "doc"],
+            Element::String(r#"{"v":3}"#.to_owned())
+        );
+    }
+    async fn test_jset_bad_json() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$");
+        query.push("{not json");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-bad-json".to_owned()))
+        );
+    }
+    async fn test_jset_bad_path() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$..x");
+        query.push("1");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-bad-json-path".to_owned()))
+        );
+    }
+    async fn test_jset_missing_key_nested_path() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$.x");
+        query.push("1");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_jdel() {
+        query.push("JSET");
+        query.push("doc");
+        query.push("$");
+        query.push(r#"{"a":1,"b":2}"#);
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["JDEL", "doc", "$.a"],
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            ["JDEL", "doc", "$.a"],
+            Element::RespCode(RespCode::NotFound)
+        );
+        runeq!(
+            con,
+            ["JGET", "doc"],
+            Element::String(r#"{"b":2}"#.to_owned())
+        );
+        runeq!(con, ["JDEL", "doc", "$"], Element::RespCode(RespCode::Okay));
+        runeq!(con, ["JGET", "doc"], Element::RespCode(RespCode::NotFound));
+    }
+    async fn test_jdel_root_not_json() {
+        runeq!(con, ["SET", "doc", "{"], Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            ["JDEL", "doc", "$"],
+            Element::RespCode(RespCode::ErrorString("err-bad-json".to_owned()))
+        );
+        runeq!(con, ["GET", "doc"], Element::String("{".to_owned()));
+    }
+}
@@ -207,6 +207,24 @@ mod __private {
         );
     }
 
+    /// Test a DELETE query, which is an alias for DEL
+    async fn test_del_alias() {
+        query.push("set");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("delete");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+    }
+
     /// Test a DEL query: which should return int 0
     async fn test_del_single_zero() {
         query.push("del");
@@ -371,6 +389,25 @@ mod __private {
         );
     }
 
+    /// Test that MGET tells an empty value apart from a missing key
+    async fn test_mget_empty_value_is_not_null() {
+        query.push("set");
+        query.push("x");
+        query.push("");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("mget");
+        query.push("x");
+        query.push("y");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("".to_owned()), None]))
+        );
+    }
+
     /// Test an MGET query with an incorrect number of arguments
     async fn test_mget_syntax_error() {
         query.push("mget");
@@ -1139,6 +1176,52 @@ mod __private {
             panic!("Expected flat string array");
         }
     }
+    async fn test_lskeys_ordered_pages() {
+        setkeys!(
+            con,
+            "x":100,
+            "y":200,
+            "z":300,
+            "a":400
+        );
+        query.push("lskeys");
+        query.push("LIMIT");
+        query.push("2");
+        query.push("OFFSET");
+        query.push("1");
+        query.push("ORDERED");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("x".to_owned()), Some("y".to_owned())]))
+        );
+        let mut query = Query::new();
+        query.push("lskeys");
+        query.push(&__MYENTITY__);
+        query.push("10");
+        query.push("OFFSET");
+        query.push("3");
+        query.push("ORDERED");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![Some("z".to_owned())]))
+        );
+    }
+    async fn test_lskeys_bad_option() {
+        query.push("lskeys");
+        query.push("LIMIT");
+        query.push("ten");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+        let mut query = Query::new();
+        query.push("lskeys");
+        query.push("OFFSET");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
     async fn test_lskeys_syntax_error() {
         query.push("lskeys");
         query.push("abcdefg");
@@ -1222,4 +1305,412 @@ mod __private {
             Element::RespCode(RespCode::NotFound)
         );
     }
+    async fn test_get_withttl_nil() {
+        query.push("get");
+        query.push("x");
+        query.push("withttl");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_get_bad_option() {
+        setkeys!(
+            con,
+            "x":100
+        );
+        query.push("get");
+        query.push("x");
+        query.push("withoutttl");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_getex_syntax_error() {
+        query.push("getex");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_getex_nil() {
+        query.push("getex");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_getex_okay() {
+        setkeys!(
+            con,
+            "x":100
+        );
+        query.push("getex");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("getex");
+        query.push("x");
+        query.push("persist");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_expireat_nil() {
+        query.push("expireat");
+        query.push("x");
+        query.push("4102444800");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_expireat_okay() {
+        setkeys!(
+            con,
+            "x":100,
+            "y":200
+        );
+        query.push("expireat");
+        query.push("x");
+        query.push("4102444800");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        // a timestamp in the past removes the key
+        let mut query = Query::new();
+        query.push("expireat");
+        query.push("y");
+        query.push("1");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("y");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    /// `WAIT` flushes the data and returns the replicas that acknowledged (none, for now)
+    async fn test_wait() {
+        setkeys!(con, "x":100);
+        query.push("wait");
+        query.push("0");
+        query.push("1000");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+    }
+    async fn test_wait_wrongtype() {
+        query.push("wait");
+        query.push("many");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
+    /// `OBJECT` reports the key's metadata
+    async fn test_object() {
+        setkeys!(con, "x":100);
+        query.push("object");
+        query.push("x");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(fields)) => {
+                assert_eq!(fields.len(), 12);
+                assert_eq!(fields[0].as_deref(), Some("type"));
+                assert_eq!(fields[2].as_deref(), Some("len"));
+                assert_eq!(fields[3].as_deref(), Some("3"));
+                // setting the key counts as an access
+                assert_eq!(fields[6].as_deref(), Some("lastaccess"));
+                assert!(fields[7].is_some());
+                assert_eq!(fields[8].as_deref(), Some("idle"));
+                assert!(fields[9].is_some());
+                // the key doesn't expire
+                assert_eq!(fields[10].as_deref(), Some("ttl"));
+                assert_eq!(fields[11], None);
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_object_nil() {
+        query.push("object");
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_mset_tables() {
+        let other = format!("{}other", __MYENTITY__);
+        let mut create = Query::new();
+        create.push("create");
+        create.push("table");
+        create.push(&other);
+        create.push("keymap(binstr,binstr)");
+        assert_eq!(
+            con.run_simple_query(&create).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        query.push("mset");
+        query.push("tables");
+        query.push(&__MYENTITY__);
+        query.push("x");
+        query.push("100");
+        query.push(&other);
+        query.push("y");
+        query.push("200");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        let mut get = Query::new();
+        get.push("get");
+        get.push("x");
+        assert_eq!(
+            con.run_simple_query(&get).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_mset_tables_missing_table() {
+        query.push("mset");
+        query.push("tables");
+        query.push(&__MYENTITY__);
+        query.push("x");
+        query.push("100");
+        query.push("testsuite:nosuchtable");
+        query.push("y");
+        query.push("200");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("container-not-found".to_owned()))
+        );
+        // nothing was set
+        let mut get = Query::new();
+        get.push("get");
+        get.push("x");
+        assert_eq!(
+            con.run_simple_query(&get).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_setv() {
+        query.push("setv");
+        query.push("x");
+        query.push("0");
+        query.push("100");
+        let version = match con.run_simple_query(&query).await.unwrap() {
+            Element::UnsignedInt(version) => version,
+            x => panic!("Got unexpected element: {:?}", x),
+        };
+        let mut setv = Query::new();
+        setv.push("setv");
+        setv.push("x");
+        setv.push(version.to_string());
+        setv.push("200");
+        assert!(matches!(
+            con.run_simple_query(&setv).await.unwrap(),
+            Element::UnsignedInt(new) if new > version
+        ));
+        // the key was written since `version` was handed out
+        assert_eq!(
+            con.run_simple_query(&setv).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-version-mismatch".to_owned()))
+        );
+    }
+    async fn test_setv_bad_version() {
+        query.push("setv");
+        query.push("x");
+        query.push("latest");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
+    async fn test_get_withversion_nil() {
+        query.push("get");
+        query.push("x");
+        query.push("withversion");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_getupdate_add() {
+        setkeys!(con, "x":100);
+        query.push("getupdate");
+        query.push("x");
+        query.push("add");
+        query.push("-58");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("42".to_owned())
+        );
+    }
+    async fn test_getupdate_append_missing() {
+        query.push("getupdate");
+        query.push("x");
+        query.push("append");
+        query.push("hello");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("hello".to_owned())
+        );
+    }
+    async fn test_getupdate_max_not_integer() {
+        setkeys!(con, "x":1.5);
+        query.push("getupdate");
+        query.push("x");
+        query.push("max");
+        query.push("11");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
+    async fn test_mset_tables_syntax_error() {
+        query.push("mset");
+        query.push("tables");
+        query.push(&__MYENTITY__);
+        query.push("x");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_mcas() {
+        query.push("mset");
+        query.push("x");
+        query.push("1");
+        query.push("y");
+        query.push("2");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        let mut query = Query::from("mcas");
+        query.push("x");
+        query.push("1");
+        query.push("10");
+        query.push("y");
+        query.push("2");
+        query.push("20");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::from("mget");
+        query.push("x");
+        query.push("y");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![
+                Some("10".to_owned()),
+                Some("20".to_owned())
+            ]))
+        );
+    }
+    async fn test_mcas_mismatch() {
+        query.push("mset");
+        query.push("x");
+        query.push("1");
+        query.push("y");
+        query.push("2");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        // `y` doesn't have the expected value and `z` doesn't exist
+        let mut query = Query::from("mcas");
+        query.push("x");
+        query.push("1");
+        query.push("10");
+        query.push("y");
+        query.push("3");
+        query.push("30");
+        query.push("z");
+        query.push("1");
+        query.push("10");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        // nothing was written
+        let mut query = Query::from("mget");
+        query.push("x");
+        query.push("y");
+        query.push("z");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![
+                Some("1".to_owned()),
+                Some("2".to_owned()),
+                None
+            ]))
+        );
+    }
+    async fn test_mcas_syntax_error() {
+        query.push("mcas");
+        query.push("x");
+        query.push("1");
+        query.push("10");
+        query.push("y");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_prefixstats() {
+        setkeys!(con, "user:1":"ab", "user:2":"cde", "item:1":"f");
+        query.push("prefixstats");
+        query.push("user:");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::Array(Array::Str(vec![
+                Some("keys".to_owned()),
+                Some("2".to_owned()),
+                Some("bytes".to_owned()),
+                Some("17".to_owned())
+            ]))
+        );
+    }
+    async fn test_prefixdrop() {
+        setkeys!(con, "user:1":"ab", "user:2":"cde", "item:1":"f");
+        query.push("prefixdrop");
+        query.push("user:");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::UnsignedInt(_) => {}
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+        // the keys are deleted in the background
+        let mut dbsize = Query::new();
+        dbsize.push("dbsize");
+        let mut tries = 0;
+        while con.run_simple_query(&dbsize).await.unwrap() != Element::UnsignedInt(1) {
+            tries += 1;
+            assert!(tries < 100, "prefix drop didn't finish");
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        let mut exists = Query::new();
+        exists.push("exists");
+        exists.push("item:1");
+        assert_eq!(
+            con.run_simple_query(&exists).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+    }
 }
@@ -26,9 +26,24 @@
 
 //! This module contains automated tests for queries
 
+mod auth_tests;
+mod batch_tests;
+mod bitmap_tests;
 mod ddl_tests;
+mod dump_tests;
+mod explain_tests;
+mod hello_tests;
+mod hll_tests;
+mod idempotency_tests;
 mod inspect_tests;
+mod json_tests;
 mod kvengine;
+mod pin_tests;
+mod session_tests;
+mod shadow_tests;
+mod sys_tests;
+mod tracking_tests;
+mod verbose_tests;
 
 mod ssl {
     use skytable::aio::TlsConnection;
@@ -46,3 +61,67 @@ mod ssl {
         );
     }
 }
+
+mod buffering {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    const HEYA: &[u8] = b"*1\n~1\n4\nHEYA\n";
+    const HEY: &[u8] = b"*1\n+4\nHEY!\n";
+    #[tokio::test]
+    async fn test_back_to_back_queries() {
+        // both queries arrive in one read, and the second shouldn't wait for more data
+        let mut con = TcpStream::connect("127.0.0.1:2003").await.unwrap();
+        con.write_all(&[HEYA, HEYA].concat()).await.unwrap();
+        let mut response = vec![0; HEY.len() * 2];
+        con.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, [HEY, HEY].concat());
+    }
+}
+
+mod local {
+    //! The test server has no users, no shadows and no admin listener, so the tests that need
+    //! them run their queries on a store in this process instead
+    use crate::corestore::memstore::Memstore;
+    use crate::dbnet::connection::prelude::*;
+    use crate::dbnet::memory::MemoryConnection;
+    use crate::protocol::Parser;
+    use crate::storage::sengine::SnapshotEngine;
+    use parking_lot::{const_mutex, Mutex, MutexGuard};
+    use std::sync::Arc;
+
+    /// The users, the server mode and the shadows are shared by every store in this process,
+    /// so the tests that change them run one at a time
+    static GLOBAL_STATE: Mutex<()> = const_mutex(());
+
+    /// Lock the server-wide state for the rest of a test
+    pub fn lock() -> MutexGuard<'static, ()> {
+        GLOBAL_STATE.lock()
+    }
+
+    /// Returns an instance on a fresh in-memory store
+    pub fn store() -> Corestore {
+        Corestore::default_with_store(
+            Memstore::new_default(),
+            Arc::new(SnapshotEngine::new_disabled()),
+        )
+    }
+
+    /// Run a query with the given arguments on `db`, as if it came from an admin listener if
+    /// `admin` is set, returning the response packet
+    pub async fn run(db: &mut Corestore, args: &[&str], admin: bool) -> Vec<u8> {
+        let mut packet = format!("*1\n~{}\n", args.len());
+        for arg in args {
+            packet.push_str(&format!("{}\n{}\n", arg.len(), arg));
+        }
+        let (query, _) = Parser::new(packet.as_bytes()).parse().unwrap();
+        let mut con = MemoryConnection::new();
+        db.execute_query(query, &mut con, admin).await.unwrap();
+        con.flush_stream().await.unwrap();
+        con.into_response()
+    }
+
+    /// Returns the response packet that holds the given element
+    pub fn response(element: &[u8]) -> Vec<u8> {
+        [&b"*1\n"[..], element].concat()
+    }
+}
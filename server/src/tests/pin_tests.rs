@@ -0,0 +1,99 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{query, AsyncConnection, Element, RespCode};
+    async fn test_pin_frozen_reads() {
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            con.run_simple_query(&query!("PIN")).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        // the live data changes under us
+        let mut other = AsyncConnection::new("127.0.0.1", 2003).await.unwrap();
+        assert_eq!(
+            other
+                .run_simple_query(&query!("USE", __MYENTITY__.clone()))
+                .await
+                .unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            other
+                .run_simple_query(&query!("UPDATE", "x", "200"))
+                .await
+                .unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            con.run_simple_query(&query!("GET", "x")).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+        assert_eq!(
+            con.run_simple_query(&query!("UNPIN")).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            con.run_simple_query(&query!("GET", "x")).await.unwrap(),
+            Element::String("200".to_owned())
+        );
+    }
+    async fn test_pin_rejects_writes() {
+        query.push("PIN");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            con.run_simple_query(&query!("SET", "x", "100"))
+                .await
+                .unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-pinned".to_owned()))
+        );
+    }
+    async fn test_pin_unknown_snapshot() {
+        query.push("PIN");
+        query.push("notasnapshot");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-snapshot-not-found".to_owned()))
+        );
+    }
+    async fn test_unpin_not_pinned() {
+        query.push("UNPIN");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-not-pinned".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{query, AsyncConnection, Element, RespCode};
+    async fn test_session_resume() {
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let token = match con
+            .run_simple_query(&query!("SESSION", "CREATE"))
+            .await
+            .unwrap()
+        {
+            Element::String(token) => token,
+            x => panic!("Got unexpected element: {:?}", x),
+        };
+        // a new connection should get back to our table in one go
+        let mut other = AsyncConnection::new("127.0.0.1", 2003).await.unwrap();
+        assert_eq!(
+            other
+                .run_simple_query(&query!("SESSION", "RESUME", token))
+                .await
+                .unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            other.run_simple_query(&query!("GET", "x")).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_session_resume_unknown() {
+        query.push("SESSION");
+        query.push("RESUME");
+        query.push("notatoken");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-unknown-session".to_owned()))
+        );
+    }
+    async fn test_session_unknown_query() {
+        query.push("SESSION");
+        query.push("TEAPOT");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-session-query".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! The test server has no shadows, so these tests mirror the writes to a shadow table in
+//! this process
+
+use super::local::{self, response, run};
+use crate::dbnet::Terminator;
+use crate::protocol::responses::full_responses;
+use crate::services::shadow::{self, Shadow, QUEUE_SIZE};
+use tokio::sync::broadcast;
+use tokio::time::{self, Duration};
+
+const TABLE: &str = "shadowtests:source";
+const TARGET: &str = "shadowtests:shadow";
+
+/// Returns one of the mirroring statistics of the shadow, like `diverged`
+fn count(name: &str) -> usize {
+    let field = format!("shadow.{}.{}", TABLE, name);
+    shadow::info()
+        .into_iter()
+        .find(|(key, _)| *key == field)
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_local_shadow() {
+    let _global = local::lock();
+    let okay = full_responses::R_OKAY;
+    shadow::set_shadows(vec![Shadow::new(TABLE.to_owned(), TARGET.to_owned(), None)]);
+    let mut con = local::store();
+    assert_eq!(
+        run(&mut con, &["CREATE", "KEYSPACE", "shadowtests"], false).await,
+        okay
+    );
+    for &table in [TABLE, TARGET].iter() {
+        assert_eq!(
+            run(
+                &mut con,
+                &["CREATE", "TABLE", table, "keymap(str,str)"],
+                false
+            )
+            .await,
+            okay
+        );
+    }
+    // the shadow already has `y`, so a `SET` of `y` will diverge
+    assert_eq!(run(&mut con, &["USE", TARGET], false).await, okay);
+    assert_eq!(run(&mut con, &["SET", "y", "200"], false).await, okay);
+    assert_eq!(run(&mut con, &["USE", TABLE], false).await, okay);
+    // DDL and FLUSHDB aren't mirrored (a mirrored FLUSHDB would empty the shadow too)
+    assert_eq!(
+        run(
+            &mut con,
+            &["CREATE", "TABLE", "other", "keymap(str,str)"],
+            false
+        )
+        .await,
+        okay
+    );
+    assert_eq!(
+        run(&mut con, &["DROP", "TABLE", "shadowtests:other"], false).await,
+        okay
+    );
+    assert_eq!(run(&mut con, &["FLUSHDB"], false).await, okay);
+    assert_eq!(count("pending"), 0);
+    assert_eq!(run(&mut con, &["SET", "x", "100"], false).await, okay);
+    assert_eq!(run(&mut con, &["SET", "y", "200"], false).await, okay);
+    assert_eq!(count("pending"), 2);
+    // nothing runs the writes yet, so the queue fills up and the writes past it are dropped
+    for key in 0..QUEUE_SIZE - 1 {
+        let key = key.to_string();
+        assert_eq!(run(&mut con, &["SET", &key, "1"], false).await, okay);
+    }
+    assert_eq!(count("pending"), QUEUE_SIZE);
+    assert_eq!(count("dropped"), 1);
+    let (signal, _) = broadcast::channel(1);
+    let writer = tokio::spawn(shadow::shadow_writer(Terminator::new(signal.subscribe())));
+    for _ in 0..500 {
+        if count("pending") == 0 {
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(count("pending"), 0);
+    assert_eq!(count("mirrored"), QUEUE_SIZE);
+    assert_eq!(count("diverged"), 1);
+    assert_eq!(count("failed"), 0);
+    signal.send(()).unwrap();
+    writer.await.unwrap();
+    shadow::set_shadows(Vec::new());
+    // the writes reached the shadow, except for the one that was dropped
+    assert_eq!(run(&mut con, &["USE", TARGET], false).await, okay);
+    assert_eq!(
+        run(&mut con, &["GET", "x"], false).await,
+        response(b"+3\n100\n")
+    );
+    assert_eq!(
+        run(&mut con, &["GET", "y"], false).await,
+        response(b"+3\n200\n")
+    );
+    assert_eq!(
+        run(&mut con, &["EXISTS", &(QUEUE_SIZE - 3).to_string()], false).await,
+        response(b":1\n1\n")
+    );
+    assert_eq!(
+        run(&mut con, &["EXISTS", &(QUEUE_SIZE - 2).to_string()], false).await,
+        response(b":1\n0\n")
+    );
+}
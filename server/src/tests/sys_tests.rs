@@ -0,0 +1,337 @@
+/*
+ * Created on Sat Oct 16 2021
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest]
+mod __private {
+    use skytable::{types::Array, Element, Query, RespCode};
+    async fn test_sys_mode_get() {
+        query.push("SYS");
+        query.push("MODE");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String("normal".to_owned())
+        );
+    }
+    async fn test_sys_mode_set_admin_only() {
+        query.push("SYS");
+        query.push("MODE");
+        query.push("normal");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_mode_syntax_error() {
+        query.push("SYS");
+        query.push("MODE");
+        query.push("normal");
+        query.push("readonly");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_sys_unknown_query() {
+        query.push("SYS");
+        query.push("TEAPOT");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-sys-query".to_owned()))
+        );
+    }
+    async fn test_sys_info() {
+        query.push("SYS");
+        query.push("INFO");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(info)) => {
+                assert_eq!(info.len() % 2, 0);
+                let mode = info.chunks_exact(2).find_map(|kv| match kv {
+                    [Some(name), value] if name == "mode" => value.clone(),
+                    _ => None,
+                });
+                assert_eq!(mode.unwrap(), "normal");
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_sys_commands() {
+        query.push("SYS");
+        query.push("COMMANDS");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(commands)) => {
+                assert_eq!(commands.len() % 5, 0);
+                let del = commands
+                    .chunks_exact(5)
+                    .find(|cmd| cmd[0].as_deref() == Some("DEL"))
+                    .unwrap();
+                assert_eq!(del[1].as_deref(), Some(">=1"));
+                assert_eq!(del[2].as_deref(), Some("write"));
+                assert_eq!(del[3].as_deref(), Some("user"));
+                assert_eq!(del[4].as_deref(), Some("DELETE"));
+            }
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_sys_diff_admin_only() {
+        query.push("SYS");
+        query.push("DIFF");
+        query.push("mysnapshot");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_jobs() {
+        query.push("SYS");
+        query.push("JOBS");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(jobs)) => assert_eq!(jobs.len() % 6, 0),
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_sys_jobs_cancel_admin_only() {
+        query.push("SYS");
+        query.push("JOBS");
+        query.push("CANCEL");
+        query.push("1");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_latency() {
+        query.push("SYS");
+        query.push("LATENCY");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(spikes)) => assert_eq!(spikes.len() % 12, 0),
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_sys_latency_reset_admin_only() {
+        query.push("SYS");
+        query.push("LATENCY");
+        query.push("RESET");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_export_admin_only() {
+        query.push("SYS");
+        query.push("EXPORT");
+        query.push("mybackup");
+        query.push("1");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_user_admin_only() {
+        query.push("SYS");
+        query.push("USER");
+        query.push("ADD");
+        query.push("acme");
+        query.push("hunter2");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_topclients_admin_only() {
+        query.push("SYS");
+        query.push("TOPCLIENTS");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_config() {
+        query.push("SYS");
+        query.push("CONFIG");
+        match con.run_simple_query(&query).await.unwrap() {
+            Element::Array(Array::Str(config)) => assert_eq!(config.len() % 2, 0),
+            x => panic!("Got unexpected element: {:?}", x),
+        }
+    }
+    async fn test_sys_config_get_unset() {
+        query.push("SYS");
+        query.push("CONFIG");
+        query.push("GET");
+        query.push("snapshot.schedule.test-nightly");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_sys_config_unknown_key() {
+        query.push("SYS");
+        query.push("CONFIG");
+        query.push("GET");
+        query.push("teapot");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("unknown-property".to_owned()))
+        );
+    }
+    async fn test_sys_config_set_admin_only() {
+        query.push("SYS");
+        query.push("CONFIG");
+        query.push("SET");
+        query.push("snapshot.schedule.test-nightly");
+        query.push("0 3 * * *");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_load_admin_only() {
+        query.push("SYS");
+        query.push("LOAD");
+        query.push("myimport");
+        query.push("default:default");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_debug_persist_admin_only() {
+        query.push("SYS");
+        query.push("DEBUG");
+        query.push("PERSIST");
+        query.push("TABLE");
+        query.push("default:default");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+        );
+    }
+    async fn test_sys_hash() {
+        query.push("SYS");
+        query.push("HASH");
+        query.push("foo");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::UnsignedInt(12182)
+        );
+        // keys with the same hash tag are always in the same slot
+        for key in ["{user:1}:name", "{user:1}:email"].iter() {
+            let mut query = Query::new();
+            query.push("SYS");
+            query.push("HASH");
+            query.push(*key);
+            assert_eq!(
+                con.run_simple_query(&query).await.unwrap(),
+                Element::UnsignedInt(10778)
+            );
+        }
+    }
+    async fn test_virtual_table_get() {
+        query.push("USE");
+        query.push("system:info");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("GET");
+        query.push("version");
+        assert!(matches!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::String(_)
+        ));
+    }
+    async fn test_virtual_table_read_only() {
+        query.push("USE");
+        query.push("system:jobs");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("SET");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-protected-object".to_owned()))
+        );
+    }
+    async fn test_system_table_protected() {
+        query.push("USE");
+        query.push("system:users");
+        assert_eq!(
+            con.run_simple_query(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("err-protected-object".to_owned()))
+        );
+    }
+}
+
+mod mode {
+    use super::super::local::{self, response, run};
+    use crate::protocol::responses::{full_responses, groups};
+
+    #[tokio::test]
+    async fn test_sys_mode_set() {
+        let _global = local::lock();
+        let okay = full_responses::R_OKAY;
+        let mut admin = local::store();
+        let mut con = admin.clone();
+        assert_eq!(
+            run(&mut admin, &["SYS", "MODE", "sleeping"], true).await,
+            response(groups::UNKNOWN_MODE)
+        );
+        // read-only mode rejects writes, but not reads
+        assert_eq!(
+            run(&mut admin, &["SYS", "MODE", "readonly"], true).await,
+            okay
+        );
+        assert_eq!(
+            run(&mut con, &["SET", "x", "100"], false).await,
+            response(groups::READ_ONLY_MODE)
+        );
+        assert_eq!(
+            run(&mut con, &["GET", "x"], false).await,
+            full_responses::R_NIL
+        );
+        // maintenance mode rejects reads too
+        assert_eq!(
+            run(&mut admin, &["SYS", "MODE", "maintenance"], true).await,
+            okay
+        );
+        assert_eq!(
+            run(&mut con, &["GET", "x"], false).await,
+            response(groups::MAINTENANCE_MODE)
+        );
+        assert_eq!(
+            run(&mut con, &["SYS", "MODE"], false).await,
+            response(b"+11\nmaintenance\n")
+        );
+        assert_eq!(
+            run(&mut admin, &["SYS", "MODE", "normal"], true).await,
+            okay
+        );
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#![deny(unused_crate_dependencies)]
+#![deny(unused_imports)]
+
+//! # `sky-admin`
+//!
+//! A tool for operators to run administrative actions (health checks, snapshots, changing
+//! modes and so on) on one or more instances. Instances are handled one after the other, so
+//! that a health check doubles as a rolling health check. Snapshots can also be verified
+//! offline, without a running instance
+
+mod node;
+use crate::node::Node;
+use clap::{load_yaml, App};
+use libsky::manifest;
+use skytable::types::Array;
+use skytable::{Element, Query};
+use std::path::Path;
+use std::process;
+
+/// The instance used if none are passed
+const DEFAULT_NODE: &str = "127.0.0.1:2003";
+
+/// An administrative action
+enum Action<'a> {
+    /// Run `HEYA` and `SYS MODE`
+    Health,
+    /// Run `SYS INFO`
+    Info,
+    /// Run `MKSNAP`, creating a remote snapshot if a name is given
+    Snapshot(Option<&'a str>),
+    /// Run `SYS MODE <mode>`
+    Mode(&'a str),
+}
+
+#[tokio::main]
+async fn main() {
+    let cfg_layout = load_yaml!("./cli.yml");
+    let matches = App::from_yaml(cfg_layout).get_matches();
+    let nodes: Vec<&str> = match matches.values_of("node") {
+        Some(nodes) => nodes.collect(),
+        None => vec![DEFAULT_NODE],
+    };
+    let sslcert = matches.value_of("cert");
+    let action = match matches.subcommand() {
+        ("health", _) => Action::Health,
+        ("info", _) => Action::Info,
+        ("snapshot", Some(args)) => Action::Snapshot(args.value_of("name")),
+        ("mode", Some(args)) => match args.value_of("mode") {
+            Some(mode) => Action::Mode(mode),
+            None => err("No mode was given"),
+        },
+        ("verify", Some(args)) => match args.value_of("snapshot") {
+            Some(dir) => verify(dir),
+            None => err("No snapshot was given"),
+        },
+        _ => err("No action was given (see --help)"),
+    };
+    let mut okay = true;
+    for addr in nodes {
+        match run(addr, sslcert, &action).await {
+            Ok(result) => println!("{}: {}", addr, result),
+            Err(e) => {
+                okay = false;
+                eprintln!("{}: ERROR: {}", addr, e);
+            }
+        }
+    }
+    if !okay {
+        process::exit(0x01);
+    }
+}
+
+fn err(msg: &str) -> ! {
+    eprintln!("ERROR: {}", msg);
+    process::exit(0x01)
+}
+
+/// Verify the snapshot in the directory `dir` against its manifest and exit, with an error
+/// if any of its files don't match it
+fn verify(dir: &str) -> ! {
+    let report = match manifest::verify(Path::new(dir)) {
+        Ok(report) => report,
+        Err(e) => err(&format!("Failed to verify {}: {}", dir, e)),
+    };
+    for (path, problem) in &report.problems {
+        eprintln!("{}: {}", path, problem.as_str());
+    }
+    if report.is_intact() {
+        println!("{}: {} file(s) verified", dir, report.verified);
+        process::exit(0x00)
+    } else {
+        err(&format!(
+            "{}: {} file(s) don't match the manifest",
+            dir,
+            report.problems.len()
+        ))
+    }
+}
+
+/// Run the action on the instance at `addr`, returning a description of the result
+async fn run(addr: &str, sslcert: Option<&str>, action: &Action<'_>) -> Result<String, String> {
+    let mut node = Node::connect(addr, sslcert).await?;
+    match action {
+        Action::Health => {
+            match node.run(Query::from("HEYA")).await? {
+                Element::String(hey) if hey == "HEY!" => {}
+                _ => return Err("unexpected response to HEYA".to_owned()),
+            }
+            match node.run(Query::from("SYS").arg("MODE")).await? {
+                Element::String(mode) => Ok(format!("healthy (mode: {})", mode)),
+                _ => Err("unexpected response to SYS MODE".to_owned()),
+            }
+        }
+        Action::Info => match node.run(Query::from("SYS").arg("INFO")).await? {
+            Element::Array(Array::Str(fields)) => {
+                let fields: Vec<String> = fields.into_iter().flatten().collect();
+                let info: Vec<String> = fields
+                    .chunks(2)
+                    .map(|field| {
+                        format!(
+                            "\n  {}: {}",
+                            field[0],
+                            field.get(1).map_or("", |v| v.as_str())
+                        )
+                    })
+                    .collect();
+                Ok(info.concat())
+            }
+            _ => Err("unexpected response to SYS INFO".to_owned()),
+        },
+        Action::Snapshot(name) => {
+            let mut query = Query::from("MKSNAP");
+            if let Some(name) = name {
+                query.push(*name);
+            }
+            node.run_okay(query).await?;
+            Ok("created snapshot".to_owned())
+        }
+        Action::Mode(mode) => {
+            node.run_okay(Query::from("SYS").arg("MODE").arg(*mode))
+                .await?;
+            Ok(format!("mode set to {}", mode))
+        }
+    }
+}
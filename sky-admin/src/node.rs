@@ -0,0 +1,106 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Connections to instances, either over plain TCP or over TLS
+
+use skytable::aio::TlsConnection;
+use skytable::{AsyncConnection, Element, Query, RespCode};
+
+/// A connection to an instance
+pub enum Node {
+    Plain(AsyncConnection),
+    Tls(TlsConnection),
+}
+
+impl Node {
+    /// Connect to the instance at `addr` (`<host>:<port>`, with IPv6 hosts in brackets like
+    /// `[::1]:2003`), over TLS if `sslcert` is set
+    pub async fn connect(addr: &str, sslcert: Option<&str>) -> Result<Self, String> {
+        let (host, port) = split_addr(addr)?;
+        let node = match sslcert {
+            Some(cert) => TlsConnection::new(host, port, cert)
+                .await
+                .map(Self::Tls)
+                .map_err(|e| e.to_string())?,
+            None => AsyncConnection::new(host, port)
+                .await
+                .map(Self::Plain)
+                .map_err(|e| e.to_string())?,
+        };
+        Ok(node)
+    }
+    /// Run a query and return the response
+    pub async fn run(&mut self, query: Query) -> Result<Element, String> {
+        let ret = match self {
+            Self::Plain(con) => con.run_simple_query(&query).await,
+            Self::Tls(con) => con.run_simple_query(&query).await,
+        };
+        ret.map_err(|e| e.to_string())
+    }
+    /// Run a query that is expected to return an `Okay`
+    pub async fn run_okay(&mut self, query: Query) -> Result<(), String> {
+        match self.run(query).await? {
+            Element::RespCode(RespCode::Okay) => Ok(()),
+            Element::RespCode(RespCode::ErrorString(e)) => Err(e),
+            Element::RespCode(code) => Err(format!("server returned {:?}", code)),
+            _ => Err("unexpected response from server".to_owned()),
+        }
+    }
+}
+
+/// Split `<host>:<port>` into the host (without the brackets around an IPv6 host) and the port
+fn split_addr(addr: &str) -> Result<(&str, u16), String> {
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected <host>:<port> but got '{}'", addr))?;
+    let host = match host.strip_prefix('[') {
+        Some(host) => host
+            .strip_suffix(']')
+            .ok_or_else(|| format!("bad host in '{}'", addr))?,
+        None if host.contains(':') => {
+            return Err(format!(
+                "expected an IPv6 host in brackets (like '[::1]:2003') but got '{}'",
+                addr
+            ))
+        }
+        None => host,
+    };
+    let port = port
+        .parse()
+        .map_err(|_| format!("bad port in '{}'", addr))?;
+    Ok((host, port))
+}
+
+#[test]
+fn test_split_addr() {
+    assert_eq!(split_addr("127.0.0.1:2003"), Ok(("127.0.0.1", 2003)));
+    assert_eq!(split_addr("localhost:2003"), Ok(("localhost", 2003)));
+    assert_eq!(split_addr("[::1]:2003"), Ok(("::1", 2003)));
+    assert!(split_addr("::1:2003").is_err());
+    assert!(split_addr("[::1:2003").is_err());
+    assert!(split_addr("127.0.0.1").is_err());
+    assert!(split_addr("127.0.0.1:port").is_err());
+}
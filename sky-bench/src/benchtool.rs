@@ -24,14 +24,18 @@
  *
 */
 
-use crate::report;
+use crate::histogram::Histogram;
+use crate::report::{self, BenchConfig};
 use devtimer::DevTime;
 use libstress::utils::generate_random_string_vector;
 use libstress::PoolConfig;
 use rand::thread_rng;
 use skytable::Query;
-use std::io::{Read, Write};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
 use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Just a sweet `*1\n`
 const SIMPLE_QUERY_SIZE: usize = 3;
@@ -107,16 +111,61 @@ fn test_simple_query_metaframe_size() {
     assert_eq!(calculate_metaframe_size(1), SIMPLE_QUERY_SIZE);
 }
 
+/// Send `packet` and read back a response of `response_size` bytes, recording the time
+/// taken for the round trip in `latency`. With pipelining, `packet` has multiple queries and
+/// hence the recorded time is that of the entire pipeline
+fn timed_query(sock: &mut TcpStream, packet: &[u8], response_size: usize, latency: &Histogram) {
+    let start = Instant::now();
+    sock.write_all(packet).unwrap();
+    let mut v = vec![0; response_size];
+    let _ = sock.read_exact(&mut v).unwrap();
+    latency.record(start.elapsed().as_nanos() as u64);
+}
+
+/// Format a latency (in nanoseconds) as milliseconds
+pub fn fmt_latency(nanos: u64) -> String {
+    format!("{:.3}ms", nanos as f64 / 1_000_000_f64)
+}
+
+/// Group `packets` into pipelines of (at most) `depth` queries each, returning the number of
+/// queries in every pipeline along with the pipeline itself
+fn pipeline(packets: Vec<Vec<u8>>, depth: usize) -> Vec<(usize, Vec<u8>)> {
+    packets
+        .chunks(depth)
+        .map(|chunk| (chunk.len(), chunk.concat()))
+        .collect()
+}
+
+#[test]
+fn test_pipeline() {
+    let packets = vec![vec![1], vec![2], vec![3]];
+    assert_eq!(
+        pipeline(packets.clone(), 2),
+        vec![(2, vec![1, 2]), (1, vec![3])]
+    );
+    assert_eq!(
+        pipeline(packets, 1),
+        vec![(1, vec![1]), (1, vec![2]), (1, vec![3])]
+    );
+}
+
 /// Run the benchmark tool
 pub fn runner(
     host: String,
     port: u16,
-    max_connections: usize,
-    max_queries: usize,
-    per_kv_size: usize,
+    config: BenchConfig,
     json_out: bool,
-    runs: usize,
+    hdr_out: Option<String>,
+    results_out: Option<String>,
 ) {
+    let BenchConfig {
+        connections: max_connections,
+        queries: max_queries,
+        kvsize: per_kv_size,
+        pipeline: depth,
+        runs,
+        warmup,
+    } = config;
     if !json_out {
         println!("Running sanity test ...");
     }
@@ -128,6 +177,7 @@ pub fn runner(
         println!("Connections: {}", max_connections);
         println!("Queries: {}", max_queries);
         println!("Data size (key+value): {} bytes", (per_kv_size * 2));
+        println!("Pipeline depth: {}", depth);
     }
     let host = hoststr!(host, port);
     let mut rand = thread_rng();
@@ -156,10 +206,10 @@ pub fn runner(
             let _ = stream.read_exact(&mut v).unwrap();
             stream
         },
-        move |sock, packet: Vec<u8>| {
+        move |sock, (count, packet): (usize, Vec<u8>)| {
             sock.write_all(&packet).unwrap();
             // all `okay`s are returned (for both update and set)
-            let mut v = vec![0; response_okay_size];
+            let mut v = vec![0; response_okay_size * count];
             let _ = sock.read_exact(&mut v).unwrap();
         },
         |socket| {
@@ -215,9 +265,45 @@ pub fn runner(
         println!("Per-packet size (GET): {} bytes", get_packs[0].len());
         println!("Per-packet size (SET): {} bytes", set_packs[0].len());
         println!("Per-packet size (UPDATE): {} bytes", update_packs[0].len());
+    }
+    // drops all the data in the temporary table
+    let flush_table = || {
+        let flushdb = Query::new()
+            .arg("FLUSHDB")
+            .arg(format!("default:{}", &temp_table))
+            .into_raw_query();
+        let drop_pool = pool_config.get_pool_with_workers(1);
+        drop_pool.execute((1, flushdb));
+        drop(drop_pool);
+    };
+    let set_packs = pipeline(set_packs, depth);
+    let get_packs = pipeline(get_packs, depth);
+    let update_packs = pipeline(update_packs, depth);
+    if warmup != 0 {
+        if !json_out {
+            println!("Warming up for {} seconds ...", warmup);
+        }
+        // SETs and UPDATEs until the time's up (since the responses for both are `okay`s)
+        let warmup = Duration::from_secs(warmup);
+        let start = Instant::now();
+        while start.elapsed() < warmup {
+            pool_config
+                .get_pool()
+                .execute_and_finish_iter(set_packs.clone());
+            pool_config
+                .get_pool()
+                .execute_and_finish_iter(update_packs.clone());
+            flush_table();
+        }
+    }
+    if !json_out {
         println!("Initialization complete! Benchmark started");
     }
     let mut report = report::AggregatedReport::new(3, runs, max_queries);
+    // the latency distributions across all runs
+    let set_latency = Arc::new(Histogram::new());
+    let get_latency = Arc::new(Histogram::new());
+    let update_latency = Arc::new(Histogram::new());
     for i in 1..runs + 1 {
         let mut dt = DevTime::new_complex();
         // clone in the keys
@@ -226,7 +312,12 @@ pub fn runner(
         let update_packs = update_packs.clone();
 
         // bench SET
-        let setpool = pool_config.get_pool();
+        let latency = set_latency.clone();
+        let setpool =
+            pool_config.with_loop_closure(move |sock: &mut TcpStream, (count, packet)| {
+                // all `okay`s are returned (for both update and set)
+                timed_query(sock, &packet, response_okay_size * count, &latency)
+            });
         dt.create_timer("SET").unwrap();
         dt.start_timer("SET").unwrap();
         setpool.execute_and_finish_iter(set_packs);
@@ -236,12 +327,11 @@ pub fn runner(
         // bench GET
         let get_response_packet_size =
             calculate_monoelement_dataframe_size(per_kv_size) + SIMPLE_QUERY_SIZE;
+        let latency = get_latency.clone();
         let getpool =
-            pool_config.with_loop_closure(move |sock: &mut TcpStream, packet: Vec<u8>| {
-                sock.write_all(&packet).unwrap();
+            pool_config.with_loop_closure(move |sock: &mut TcpStream, (count, packet)| {
                 // read exact for the key size
-                let mut v = vec![0; get_response_packet_size];
-                let _ = sock.read_exact(&mut v).unwrap();
+                timed_query(sock, &packet, get_response_packet_size * count, &latency)
             });
         dt.create_timer("GET").unwrap();
         dt.start_timer("GET").unwrap();
@@ -249,7 +339,11 @@ pub fn runner(
         dt.stop_timer("GET").unwrap();
 
         // bench UPDATE
-        let update_pool = pool_config.get_pool();
+        let latency = update_latency.clone();
+        let update_pool =
+            pool_config.with_loop_closure(move |sock: &mut TcpStream, (count, packet)| {
+                timed_query(sock, &packet, response_okay_size * count, &latency)
+            });
         dt.create_timer("UPDATE").unwrap();
         dt.start_timer("UPDATE").unwrap();
         update_pool.execute_and_finish_iter(update_packs);
@@ -260,24 +354,38 @@ pub fn runner(
         }
 
         // drop table
-        let flushdb = Query::new()
-            .arg("FLUSHDB")
-            .arg(format!("default:{}", &temp_table))
-            .into_raw_query();
-        let drop_pool = pool_config.get_pool_with_workers(1);
-        drop_pool.execute(flushdb);
-        drop(drop_pool);
+        flush_table();
         dt.iter()
             .for_each(|(name, timer)| report.insert(name, timer.time_in_nanos().unwrap()));
     }
+    let latencies = [
+        ("GET", get_latency),
+        ("SET", set_latency),
+        ("UPDATE", update_latency),
+    ];
+    for (name, histogram) in latencies.iter() {
+        report.set_latency(*name, histogram);
+        if let Some(prefix) = &hdr_out {
+            let path = format!("{}-{}.hgrm", prefix, name);
+            let written =
+                File::create(&path).and_then(|file| histogram.write_hgrm(BufWriter::new(file)));
+            if let Err(e) = written {
+                err!(format!("Failed to write histogram to '{}': {}", path, e));
+            }
+        }
+    }
+    let (report, maxpad) = report.into_sorted_stat();
+    if let Some(path) = &results_out {
+        if let Err(e) = report::write_results(path, &config, &report) {
+            err!(format!("Failed to write results to '{}': {}", path, e));
+        }
+    }
     if json_out {
-        let serialized = report.into_json();
-        println!("{}", serialized);
+        println!("{}", serde_json::to_string(&report).unwrap());
     } else {
         println!("===========RESULTS===========");
-        let (report, maxpad) = report.into_sorted_stat();
         let pad = |clen: usize| " ".repeat(maxpad - clen);
-        report.into_iter().for_each(|block| {
+        report.iter().for_each(|block| {
             println!(
                 "{}{} {:.6}/sec",
                 block.get_report(),
@@ -285,6 +393,21 @@ pub fn runner(
                 block.get_stat()
             );
         });
+        println!("===========LATENCY===========");
+        report.iter().for_each(|block| {
+            if let Some(latency) = block.get_latency() {
+                println!(
+                    "{}{} p50: {} p90: {} p99: {} p99.9: {} max: {}",
+                    block.get_report(),
+                    pad(block.get_report().len()),
+                    fmt_latency(latency.p50),
+                    fmt_latency(latency.p90),
+                    fmt_latency(latency.p99),
+                    fmt_latency(latency.p999),
+                    fmt_latency(latency.max)
+                );
+            }
+        });
         println!("=============================");
     }
 }
@@ -0,0 +1,227 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! A concurrent latency histogram
+//!
+//! Values are recorded into log-linear buckets (like a HDR histogram): values under
+//! [`LINEAR_MAX`] get a bucket each and every power of two above that is split into
+//! [`SUB_BUCKETS`] buckets, so every recorded value is off by less than 2%. The buckets are
+//! atomic counters, so every worker can record into the same histogram without locking
+
+use std::io::{Result as IoResult, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Every value below this has its own bucket
+const LINEAR_MAX: u64 = 128;
+/// The number of buckets for every power of two above [`LINEAR_MAX`]
+const SUB_BUCKETS: u64 = 64;
+/// `log2(SUB_BUCKETS)`
+const SUB_BUCKET_BITS: u32 = 6;
+/// Enough buckets to hold `u64::MAX`
+const BUCKET_COUNT: usize = (LINEAR_MAX + (63 - SUB_BUCKET_BITS as u64) * SUB_BUCKETS) as usize;
+
+/// Returns the index of the bucket for `value`
+const fn bucket_of(value: u64) -> usize {
+    if value < LINEAR_MAX {
+        value as usize
+    } else {
+        let msb = 63 - value.leading_zeros();
+        let shift = msb - SUB_BUCKET_BITS;
+        let top = value >> shift;
+        (LINEAR_MAX + (shift as u64 - 1) * SUB_BUCKETS + (top - SUB_BUCKETS)) as usize
+    }
+}
+
+/// Returns the largest value that is counted in the bucket at `idx`
+const fn highest_in_bucket(idx: usize) -> u64 {
+    let idx = idx as u64;
+    if idx < LINEAR_MAX {
+        idx
+    } else {
+        let shift = (idx - LINEAR_MAX) / SUB_BUCKETS + 1;
+        let top = (idx - LINEAR_MAX) % SUB_BUCKETS + SUB_BUCKETS;
+        // don't overflow for the very last bucket
+        (top << shift) + ((1 << shift) - 1)
+    }
+}
+
+#[derive(Debug)]
+/// A histogram of latencies (in nanoseconds)
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+    /// Record a value
+    pub fn record(&self, value: u64) {
+        self.buckets[bucket_of(value)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+    /// Returns the number of recorded values
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+    /// Returns the largest recorded value
+    pub fn max(&self) -> u64 {
+        self.max.load(Ordering::Relaxed)
+    }
+    /// Returns the mean of all recorded values
+    pub fn mean(&self) -> f64 {
+        match self.count() {
+            0 => 0.0,
+            count => self.sum.load(Ordering::Relaxed) as f64 / count as f64,
+        }
+    }
+    /// Returns the (highest equivalent) value below which `percentile`% of all values fall
+    pub fn value_at_percentile(&self, percentile: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+        let target = ((percentile / 100.0) * count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (idx, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= target {
+                return highest_in_bucket(idx).min(self.max());
+            }
+        }
+        self.max()
+    }
+    /// Write out the percentile distribution in the format that is used by HdrHistogram
+    /// (the `.hgrm` format), with values in microseconds
+    pub fn write_hgrm(&self, mut w: impl Write) -> IoResult<()> {
+        const SCALE: f64 = 1000.0;
+        let count = self.count();
+        writeln!(
+            w,
+            "{:>12} {:>14} {:>10} {:>14}\n",
+            "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+        )?;
+        let mut seen = 0;
+        for (idx, bucket) in self.buckets.iter().enumerate() {
+            let in_bucket = bucket.load(Ordering::Relaxed);
+            if in_bucket == 0 {
+                continue;
+            }
+            seen += in_bucket;
+            let value = highest_in_bucket(idx).min(self.max()) as f64 / SCALE;
+            let percentile = seen as f64 / count as f64;
+            if seen == count {
+                writeln!(w, "{:12.3} {:2.12} {:10}", value, percentile, seen)?;
+            } else {
+                writeln!(
+                    w,
+                    "{:12.3} {:2.12} {:10} {:14.2}",
+                    value,
+                    percentile,
+                    seen,
+                    1.0 / (1.0 - percentile)
+                )?;
+            }
+        }
+        writeln!(
+            w,
+            "#[Mean    = {:12.3}, Max            = {:12.3}]",
+            self.mean() / SCALE,
+            self.max() as f64 / SCALE
+        )?;
+        writeln!(
+            w,
+            "#[Total count    = {:12}, Buckets        = {:12}]",
+            count, BUCKET_COUNT
+        )?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_buckets_are_contiguous() {
+    // every bucket starts right after the previous one ends
+    for idx in 1..BUCKET_COUNT {
+        let start = highest_in_bucket(idx - 1) + 1;
+        assert_eq!(bucket_of(start), idx);
+        assert_eq!(bucket_of(highest_in_bucket(idx)), idx);
+    }
+    assert_eq!(bucket_of(u64::MAX), BUCKET_COUNT - 1);
+    assert_eq!(highest_in_bucket(BUCKET_COUNT - 1), u64::MAX);
+}
+
+#[test]
+fn test_percentiles() {
+    let hist = Histogram::new();
+    (1..=10_000).for_each(|v| hist.record(v * 1000));
+    assert_eq!(hist.count(), 10_000);
+    assert_eq!(hist.max(), 10_000_000);
+    let within =
+        |v: u64, expected: u64| (v as f64 - expected as f64).abs() / expected as f64 <= 0.02;
+    assert!(within(hist.value_at_percentile(50.0), 5_000_000));
+    assert!(within(hist.value_at_percentile(99.0), 9_900_000));
+    assert!(within(hist.value_at_percentile(99.9), 9_990_000));
+    assert_eq!(hist.value_at_percentile(100.0), 10_000_000);
+    assert_eq!(Histogram::new().value_at_percentile(50.0), 0);
+}
+
+#[test]
+fn test_write_hgrm() {
+    let hist = Histogram::new();
+    // small values are exact
+    hist.record(100);
+    hist.record(120);
+    let mut out = Vec::new();
+    hist.write_hgrm(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines[0].contains("Percentile"));
+    assert_eq!(
+        lines[2].split_whitespace().collect::<Vec<_>>()[..3],
+        ["0.100", "0.500000000000", "1"]
+    );
+    assert_eq!(
+        lines[3].split_whitespace().collect::<Vec<_>>(),
+        ["0.120", "1.000000000000", "2"]
+    );
+    assert!(lines[4].starts_with("#[Mean"));
+}
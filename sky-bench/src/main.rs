@@ -34,9 +34,12 @@
 #[macro_use]
 mod util;
 mod benchtool;
+mod histogram;
 mod report;
 mod testkey;
+mod workload;
 use crate::util::DEFAULT_PACKET_SIZE;
+use crate::util::DEFAULT_PIPELINE_DEPTH;
 use crate::util::DEFAULT_QUERY_COUNT;
 use crate::util::DEFAULT_REPEAT;
 use crate::util::DEFAULT_WORKER_COUNT;
@@ -79,7 +82,17 @@ fn main() {
         Some(Err(_)) => err!("Bad value for runs"),
         None => DEFAULT_REPEAT,
     };
-    if packet_size == 0 || max_queries == 0 || max_connections == 0 {
+    let pipeline: usize = match matches.value_of("pipeline").map(|v| v.parse()) {
+        Some(Ok(depth)) => depth,
+        Some(Err(_)) => err!("Bad value for pipeline depth"),
+        None => DEFAULT_PIPELINE_DEPTH,
+    };
+    let warmup: u64 = match matches.value_of("warmup").map(|v| v.parse()) {
+        Some(Ok(secs)) => secs,
+        Some(Err(_)) => err!("Bad value for warmup"),
+        None => 0,
+    };
+    if packet_size == 0 || max_queries == 0 || max_connections == 0 || pipeline == 0 {
         err!("All inputs must be non-zero values");
     }
     if let Some(cmd) = matches.subcommand_matches("testkey") {
@@ -96,15 +109,62 @@ fn main() {
         };
         println!("warning: Ignoring any other invalid flags/options (if they were supplied)");
         testkey::create_testkeys(&host, port, count, max_connections, packet_size);
-    } else {
-        benchtool::runner(
+    } else if let Some(cmd) = matches.subcommand_matches("workload") {
+        let keys = match cmd.value_of("keys").map(|v| v.parse::<usize>()) {
+            Some(Ok(keys)) if keys != 0 => keys,
+            None => workload::DEFAULT_KEY_COUNT,
+            _ => err!("Bad value for key count"),
+        };
+        let read_ratio = match cmd.value_of("ratio").map(|v| v.parse::<u8>()) {
+            Some(Ok(ratio)) if ratio <= 100 => ratio,
+            None => workload::DEFAULT_READ_RATIO,
+            _ => err!("Bad value for read ratio"),
+        };
+        let value_size = match cmd.value_of("valuesize") {
+            Some(size) => match workload::ValueSize::parse(size) {
+                Some(size) => size,
+                None => err!("Bad value for value size"),
+            },
+            None => workload::ValueSize::parse(&packet_size.to_string()).unwrap(),
+        };
+        let exponent = match cmd.value_of("exponent").map(|v| v.parse::<f64>()) {
+            Some(Ok(exponent)) if exponent > 0.0 => exponent,
+            None => workload::DEFAULT_ZIPF_EXPONENT,
+            _ => err!("Bad value for Zipf exponent"),
+        };
+        let distribution = match cmd.value_of("distribution") {
+            Some("zipf") => workload::KeyDistribution::Zipf(exponent),
+            _ => workload::KeyDistribution::Uniform,
+        };
+        workload::runner(
             host,
             port,
             max_connections,
             max_queries,
             packet_size,
+            workload::Workload {
+                keys,
+                read_ratio,
+                value_size,
+                distribution,
+            },
+            json_out,
+        );
+    } else {
+        benchtool::runner(
+            host,
+            port,
+            report::BenchConfig {
+                connections: max_connections,
+                queries: max_queries,
+                kvsize: packet_size,
+                pipeline,
+                runs,
+                warmup,
+            },
             json_out,
-            runs,
+            matches.value_of("hdr").map(|prefix| prefix.to_owned()),
+            matches.value_of("results").map(|path| path.to_owned()),
         );
     }
 }
@@ -24,14 +24,18 @@
  *
 */
 
+use crate::histogram::Histogram;
 use crate::util;
 use core::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Result as IoResult};
 
 /// A map of reports
 pub struct AggregatedReport {
     map: HashMap<&'static str, Report>,
+    latencies: HashMap<&'static str, Latency>,
     queries: usize,
     cap: usize,
 }
@@ -45,6 +49,7 @@ impl AggregatedReport {
     pub fn new(report_count: usize, cap: usize, queries: usize) -> Self {
         Self {
             map: HashMap::with_capacity(report_count),
+            latencies: HashMap::with_capacity(report_count),
             cap,
             queries,
         }
@@ -61,10 +66,20 @@ impl AggregatedReport {
             }
         }
     }
+    /// Set the latency distribution for a bench (across all the runs)
+    pub fn set_latency(&mut self, name: &'static str, histogram: &Histogram) {
+        self.latencies
+            .insert(name, Latency::from_histogram(histogram));
+    }
     /// Returns a vector of sorted statistics (lexicographical) and the length of the longest
     /// bench name. `(Vec<Stat>, longest_bench_name)`
     pub fn into_sorted_stat(self) -> (Vec<Stat>, usize) {
-        let Self { map, queries, .. } = self;
+        let Self {
+            map,
+            mut latencies,
+            queries,
+            ..
+        } = self;
         let mut maxpad = 0usize;
         let mut repvec: Vec<Stat> = map
             .into_iter()
@@ -72,16 +87,52 @@ impl AggregatedReport {
                 if name.len() > maxpad {
                     maxpad = name.len();
                 }
-                report.into_stat(queries, name)
+                let mut stat = report.into_stat(queries, name);
+                stat.latency = latencies.remove(name);
+                stat
             })
             .collect();
         repvec.sort();
         (repvec, maxpad)
     }
-    /// Returns a minified JSON string
-    pub fn into_json(self) -> String {
-        serde_json::to_string(&self.into_sorted_stat().0).unwrap()
-    }
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy)]
+/// The settings that a benchmark was run with
+pub struct BenchConfig {
+    /// the number of simultaneous clients
+    pub connections: usize,
+    /// the number of queries per bench
+    pub queries: usize,
+    /// the size of keys and values
+    pub kvsize: usize,
+    /// the number of queries sent in one go
+    pub pipeline: usize,
+    /// the number of times the benches are run
+    pub runs: usize,
+    /// the warmup duration (in seconds)
+    pub warmup: u64,
+}
+
+#[derive(serde::Serialize)]
+/// The results of a benchmark along with the settings it was run with, so that the results
+/// of different runs can be compared
+struct Results<'a> {
+    config: &'a BenchConfig,
+    results: &'a [Stat],
+}
+
+/// Write the results (as JSON) to the file at `path`
+pub fn write_results(path: &str, config: &BenchConfig, stats: &[Stat]) -> IoResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(
+        file,
+        &Results {
+            config,
+            results: stats,
+        },
+    )?;
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -105,6 +156,29 @@ impl Report {
         Stat {
             name,
             stat: util::calc(reqs, avg),
+            latency: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy)]
+/// Latency percentiles for a bench (in nanoseconds)
+pub struct Latency {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+impl Latency {
+    pub fn from_histogram(histogram: &Histogram) -> Self {
+        Self {
+            p50: histogram.value_at_percentile(50.0),
+            p90: histogram.value_at_percentile(90.0),
+            p99: histogram.value_at_percentile(99.0),
+            p999: histogram.value_at_percentile(99.9),
+            max: histogram.max(),
         }
     }
 }
@@ -114,6 +188,8 @@ impl Report {
 pub struct Stat {
     name: &'static str,
     stat: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency: Option<Latency>,
 }
 
 impl Stat {
@@ -125,6 +201,10 @@ impl Stat {
     pub fn get_stat(&self) -> f64 {
         self.stat
     }
+    /// Get the latency percentiles, if they were recorded
+    pub fn get_latency(&self) -> Option<Latency> {
+        self.latency
+    }
 }
 
 impl PartialEq for Stat {
@@ -32,6 +32,7 @@ pub const DEFAULT_WORKER_COUNT: usize = 10;
 pub const DEFAULT_PACKET_SIZE: usize = 4;
 pub const DEFAULT_QUERY_COUNT: usize = 100_000;
 pub const DEFAULT_REPEAT: usize = 5;
+pub const DEFAULT_PIPELINE_DEPTH: usize = 1;
 
 #[macro_export]
 macro_rules! hoststr {
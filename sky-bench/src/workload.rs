@@ -0,0 +1,354 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Mixed read/write workloads
+//!
+//! Unlike the default bench (which runs SET, GET and UPDATE one after the other on every
+//! key), a workload first loads a fixed set of keys and then runs a mix of reads (`GET`)
+//! and writes (`UPDATE`) on keys that are picked either uniformly or with a Zipfian
+//! distribution (where a few keys are very hot), which is a lot closer to production traffic
+
+use crate::benchtool::fmt_latency;
+use crate::histogram::Histogram;
+use crate::report::Latency;
+use libstress::utils::{generate_random_string_vector, ran_string};
+use libstress::PoolConfig;
+use rand::{thread_rng, Rng};
+use skytable::Query;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+use std::time::Instant;
+
+pub const DEFAULT_KEY_COUNT: usize = 10_000;
+pub const DEFAULT_READ_RATIO: u8 = 90;
+pub const DEFAULT_ZIPF_EXPONENT: f64 = 0.99;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How keys are picked for every query
+pub enum KeyDistribution {
+    /// Every key is equally likely
+    Uniform,
+    /// The probability of the `k`th key is proportional to `1/k^s`
+    Zipf(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The size of values, which is picked uniformly from `min..=max`
+pub struct ValueSize {
+    min: usize,
+    max: usize,
+}
+
+impl ValueSize {
+    /// Parse either a fixed size (`64`) or a range (`16-256`)
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut bounds = input.splitn(2, '-');
+        let min: usize = bounds.next()?.trim().parse().ok()?;
+        let max: usize = match bounds.next() {
+            Some(max) => max.trim().parse().ok()?,
+            None => min,
+        };
+        if min == 0 || min > max {
+            None
+        } else {
+            Some(Self { min, max })
+        }
+    }
+    fn pick(&self, rng: &mut impl Rng) -> usize {
+        rng.gen_range(self.min..=self.max)
+    }
+}
+
+#[derive(Debug)]
+/// Picks keys (by their index) according to a [`KeyDistribution`]
+struct KeyPicker {
+    count: usize,
+    /// the cumulative distribution for the Zipfian distribution
+    cdf: Option<Vec<f64>>,
+}
+
+impl KeyPicker {
+    fn new(count: usize, distribution: KeyDistribution) -> Self {
+        let cdf = match distribution {
+            KeyDistribution::Uniform => None,
+            KeyDistribution::Zipf(exponent) => {
+                let mut sum = 0.0;
+                let mut cdf: Vec<f64> = (1..=count)
+                    .map(|rank| {
+                        sum += 1.0 / (rank as f64).powf(exponent);
+                        sum
+                    })
+                    .collect();
+                cdf.iter_mut().for_each(|p| *p /= sum);
+                Some(cdf)
+            }
+        };
+        Self { count, cdf }
+    }
+    fn pick(&self, rng: &mut impl Rng) -> usize {
+        match &self.cdf {
+            None => rng.gen_range(0..self.count),
+            Some(cdf) => {
+                let point: f64 = rng.gen();
+                // the first key whose cumulative probability is at least `point`
+                cdf.partition_point(|p| *p < point).min(self.count - 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The settings for a workload
+pub struct Workload {
+    pub keys: usize,
+    /// the percentage of queries that are reads
+    pub read_ratio: u8,
+    pub value_size: ValueSize,
+    pub distribution: KeyDistribution,
+}
+
+#[derive(serde::Serialize, Debug)]
+/// The results of a workload
+struct WorkloadReport {
+    queries: usize,
+    reads: u64,
+    writes: u64,
+    /// queries per second
+    stat: f64,
+    read_latency: Latency,
+    write_latency: Latency,
+}
+
+/// Read a single element response (`*1\n<tsymbol><len>\n<element>\n`), since unlike the
+/// default bench, the response sizes aren't known in advance
+fn read_response(sock: &mut BufReader<TcpStream>) {
+    let mut line = Vec::new();
+    // the metaframe
+    sock.read_until(b'\n', &mut line).unwrap();
+    line.clear();
+    // the tsymbol and the size
+    sock.read_until(b'\n', &mut line).unwrap();
+    let size: usize = match line.get(1..line.len() - 1).map(String::from_utf8_lossy) {
+        Some(size) => match size.parse() {
+            Ok(size) => size,
+            Err(_) => err!("Bad response from server"),
+        },
+        None => err!("Bad response from server"),
+    };
+    // the element and the final LF
+    let mut element = vec![0; size + 1];
+    sock.read_exact(&mut element).unwrap();
+}
+
+/// Run a workload
+pub fn runner(
+    host: String,
+    port: u16,
+    max_connections: usize,
+    max_queries: usize,
+    key_size: usize,
+    workload: Workload,
+    json_out: bool,
+) {
+    if !json_out {
+        println!("Running sanity test ...");
+    }
+    if let Err(e) = sanity_test!(host, port) {
+        err!(format!("Sanity test failed with error: {}", e));
+    }
+    if !json_out {
+        println!("Finished sanity test. Initializing workload ...");
+        println!("Connections: {}", max_connections);
+        println!("Queries: {}", max_queries);
+        println!("Keys: {}", workload.keys);
+        println!("Reads: {}%", workload.read_ratio);
+        println!(
+            "Value size: {}-{} bytes",
+            workload.value_size.min, workload.value_size.max
+        );
+        println!("Key distribution: {:?}", workload.distribution);
+    }
+    let host = hoststr!(host, port);
+    let mut rand = thread_rng();
+
+    let temp_table = libstress::utils::rand_alphastring(10, &mut rand);
+    let create_table = Query::from("create")
+        .arg("table")
+        .arg(&temp_table)
+        .arg("keymap(binstr,binstr)")
+        .arg("volatile")
+        .into_raw_query();
+    let switch_table = Query::from("use")
+        .arg(format!("default:{}", &temp_table))
+        .into_raw_query();
+    let mut create_table_connection = BufReader::new(TcpStream::connect(&host).unwrap());
+    create_table_connection
+        .get_mut()
+        .write_all(&create_table)
+        .unwrap();
+    read_response(&mut create_table_connection);
+
+    let pool_config = PoolConfig::new(
+        max_connections,
+        move || {
+            let mut stream = BufReader::new(TcpStream::connect(&host).unwrap());
+            stream.get_mut().write_all(&switch_table).unwrap();
+            read_response(&mut stream);
+            stream
+        },
+        move |sock, (_, packet): (bool, Vec<u8>)| {
+            sock.get_mut().write_all(&packet).unwrap();
+            read_response(sock);
+        },
+        |sock| {
+            sock.get_mut().shutdown(Shutdown::Both).unwrap();
+        },
+        true,
+        Some(max_queries.max(workload.keys)),
+    );
+
+    // load all the keys first
+    let keys = generate_random_string_vector(workload.keys, key_size, &mut rand, true);
+    let load_packs: Vec<(bool, Vec<u8>)> = keys
+        .iter()
+        .map(|key| {
+            let value = ran_string(workload.value_size.pick(&mut rand), &mut rand);
+            (
+                false,
+                Query::from("SET").arg(key).arg(value).into_raw_query(),
+            )
+        })
+        .collect();
+    pool_config.get_pool().execute_and_finish_iter(load_packs);
+
+    // now generate the mix; a read is a `GET` and a write is an `UPDATE` with a new value
+    let picker = KeyPicker::new(workload.keys, workload.distribution);
+    let packs: Vec<(bool, Vec<u8>)> = (0..max_queries)
+        .map(|_| {
+            let key = &keys[picker.pick(&mut rand)];
+            if rand.gen_range(0..100) < workload.read_ratio {
+                (true, Query::from("GET").arg(key).into_raw_query())
+            } else {
+                let value = ran_string(workload.value_size.pick(&mut rand), &mut rand);
+                (
+                    false,
+                    Query::from("UPDATE").arg(key).arg(value).into_raw_query(),
+                )
+            }
+        })
+        .collect();
+    if !json_out {
+        println!("Loaded {} keys. Workload started", workload.keys);
+    }
+    let read_latency = Arc::new(Histogram::new());
+    let write_latency = Arc::new(Histogram::new());
+    let (rlat, wlat) = (read_latency.clone(), write_latency.clone());
+    let pool = pool_config.with_loop_closure(
+        move |sock: &mut BufReader<TcpStream>, (is_read, packet): (bool, Vec<u8>)| {
+            let start = Instant::now();
+            sock.get_mut().write_all(&packet).unwrap();
+            read_response(sock);
+            let elapsed = start.elapsed().as_nanos() as u64;
+            if is_read {
+                rlat.record(elapsed);
+            } else {
+                wlat.record(elapsed);
+            }
+        },
+    );
+    let start = Instant::now();
+    pool.execute_and_finish_iter(packs);
+    let elapsed = start.elapsed().as_nanos();
+
+    // drop the data
+    let flushdb = Query::new()
+        .arg("FLUSHDB")
+        .arg(format!("default:{}", &temp_table))
+        .into_raw_query();
+    let drop_pool = pool_config.get_pool_with_workers(1);
+    drop_pool.execute((false, flushdb));
+    drop(drop_pool);
+
+    let report = WorkloadReport {
+        queries: max_queries,
+        reads: read_latency.count(),
+        writes: write_latency.count(),
+        stat: max_queries as f64 / (elapsed as f64 / 1_000_000_000_f64),
+        read_latency: Latency::from_histogram(&read_latency),
+        write_latency: Latency::from_histogram(&write_latency),
+    };
+    if json_out {
+        println!("{}", serde_json::to_string(&report).unwrap());
+    } else {
+        println!("===========RESULTS===========");
+        println!("Reads:  {}", report.reads);
+        println!("Writes: {}", report.writes);
+        println!("MIXED  {:.6}/sec", report.stat);
+        println!("===========LATENCY===========");
+        for (name, latency) in [
+            ("READ ", &report.read_latency),
+            ("WRITE", &report.write_latency),
+        ]
+        .iter()
+        {
+            println!(
+                "{} p50: {} p90: {} p99: {} p99.9: {} max: {}",
+                name,
+                fmt_latency(latency.p50),
+                fmt_latency(latency.p90),
+                fmt_latency(latency.p99),
+                fmt_latency(latency.p999),
+                fmt_latency(latency.max)
+            );
+        }
+        println!("=============================");
+    }
+}
+
+#[test]
+fn test_value_size_parse() {
+    assert_eq!(ValueSize::parse("64"), Some(ValueSize { min: 64, max: 64 }));
+    assert_eq!(
+        ValueSize::parse("16-256"),
+        Some(ValueSize { min: 16, max: 256 })
+    );
+    assert_eq!(ValueSize::parse("0"), None);
+    assert_eq!(ValueSize::parse("256-16"), None);
+    assert_eq!(ValueSize::parse("16-"), None);
+}
+
+#[test]
+fn test_zipf_picks_hot_keys() {
+    let picker = KeyPicker::new(1000, KeyDistribution::Zipf(DEFAULT_ZIPF_EXPONENT));
+    let cdf = picker.cdf.as_ref().unwrap();
+    assert!((cdf[cdf.len() - 1] - 1.0).abs() < 1e-9);
+    let mut rng = thread_rng();
+    let hot = (0..10_000).filter(|_| picker.pick(&mut rng) < 10).count();
+    // the ten hottest keys should see roughly 40% of the traffic; with a uniform
+    // distribution they'd only see 1%
+    assert!(hot > 2_000);
+}
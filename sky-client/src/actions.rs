@@ -0,0 +1,301 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Typed actions
+//!
+//! Every function here builds an [`Action`], which is a query along with a way to turn
+//! the server's response into a Rust type. Run them with [`Connection::run_action`]
+//!
+//! [`Connection::run_action`]: crate::Connection::run_action
+
+use crate::error::{Error, SkyResult};
+use crate::query::Query;
+use crate::response::{Element, RespCode};
+
+/// A query, and a conversion of its response into `T`
+pub struct Action<T> {
+    query: Query,
+    convert: fn(Element) -> SkyResult<T>,
+}
+
+impl<T> Action<T> {
+    fn new(query: Query, convert: fn(Element) -> SkyResult<T>) -> Self {
+        Self { query, convert }
+    }
+    /// Returns the query that this action will run
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+    /// Turn a response to this action's query into its typed result
+    pub fn convert(&self, response: Element) -> SkyResult<T> {
+        (self.convert)(response)
+    }
+}
+
+fn okay(element: Element) -> SkyResult<()> {
+    match element {
+        Element::RespCode(RespCode::Okay) => Ok(()),
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+fn int(element: Element) -> SkyResult<u64> {
+    match element {
+        Element::UnsignedInt(int) => Ok(int),
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+fn string(element: Element) -> SkyResult<String> {
+    match element {
+        Element::String(st) => Ok(st),
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+fn optional_value(element: Element) -> SkyResult<Option<Vec<u8>>> {
+    match element {
+        Element::String(st) => Ok(Some(st.into_bytes())),
+        Element::Binary(bin) => Ok(Some(bin)),
+        Element::RespCode(RespCode::NotFound) => Ok(None),
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+/// Returns true on `Okay` and false when the server refused the write (`Overwrite` or
+/// `Nil`)
+fn written(element: Element) -> SkyResult<bool> {
+    match element {
+        Element::RespCode(RespCode::Okay) => Ok(true),
+        Element::RespCode(RespCode::OverwriteError) | Element::RespCode(RespCode::NotFound) => {
+            Ok(false)
+        }
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+fn strings(element: Element) -> SkyResult<Vec<String>> {
+    match element {
+        Element::StrArray(array) => Ok(array.into_iter().flatten().collect()),
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+fn keys(element: Element) -> SkyResult<Vec<Vec<u8>>> {
+    match element {
+        Element::StrArray(array) => Ok(array
+            .into_iter()
+            .flatten()
+            .map(String::into_bytes)
+            .collect()),
+        Element::BinArray(array) => Ok(array.into_iter().flatten().collect()),
+        e => Err(Error::unexpected(e)),
+    }
+}
+
+fn multi_query(action: &str, args: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Query {
+    let mut query = Query::new(action);
+    for arg in args {
+        query.push(arg);
+    }
+    query
+}
+
+/// `HEYA`: check that the server is alive
+pub fn heya() -> Action<String> {
+    Action::new(Query::new("HEYA"), string)
+}
+
+/// `GET`: returns the value of `key`, if it exists
+pub fn get(key: impl AsRef<[u8]>) -> Action<Option<Vec<u8>>> {
+    Action::new(Query::new("GET").arg(key), optional_value)
+}
+
+/// `SET`: set `key` to `value`, returning false if `key` already exists
+pub fn set(key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Action<bool> {
+    Action::new(Query::new("SET").arg(key).arg(value), written)
+}
+
+/// `UPDATE`: update `key` to `value`, returning false if `key` doesn't exist
+pub fn update(key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Action<bool> {
+    Action::new(Query::new("UPDATE").arg(key).arg(value), written)
+}
+
+/// `DEL`: remove the given keys, returning how many of them were removed
+pub fn del(keys: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Action<u64> {
+    Action::new(multi_query("DEL", keys), int)
+}
+
+/// `EXISTS`: returns how many of the given keys exist
+pub fn exists(keys: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Action<u64> {
+    Action::new(multi_query("EXISTS", keys), int)
+}
+
+/// `MSET`: set the given key/value pairs, returning how many of them were set
+pub fn mset(pairs: impl IntoIterator<Item = (impl AsRef<[u8]>, impl AsRef<[u8]>)>) -> Action<u64> {
+    let mut query = Query::new("MSET");
+    for (key, value) in pairs {
+        query.push(key);
+        query.push(value);
+    }
+    Action::new(query, int)
+}
+
+/// `MGET`: returns the values of the given keys, with `None` for keys that don't exist
+pub fn mget(keys: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Action<Vec<Option<Vec<u8>>>> {
+    Action::new(multi_query("MGET", keys), |element| match element {
+        Element::StrArray(array) => Ok(array
+            .into_iter()
+            .map(|v| v.map(String::into_bytes))
+            .collect()),
+        Element::BinArray(array) => Ok(array),
+        e => Err(Error::unexpected(e)),
+    })
+}
+
+/// `KEYLEN`: returns the length of the value of `key`, if it exists
+pub fn keylen(key: impl AsRef<[u8]>) -> Action<Option<u64>> {
+    Action::new(Query::new("KEYLEN").arg(key), |element| match element {
+        Element::UnsignedInt(len) => Ok(Some(len)),
+        Element::RespCode(RespCode::NotFound) => Ok(None),
+        e => Err(Error::unexpected(e)),
+    })
+}
+
+/// `DBSIZE`: returns the number of keys in the current table
+pub fn dbsize() -> Action<u64> {
+    Action::new(Query::new("DBSIZE"), int)
+}
+
+/// `FLUSHDB`: remove every key in the current table
+pub fn flushdb() -> Action<()> {
+    Action::new(Query::new("FLUSHDB"), okay)
+}
+
+/// `USE`: switch to the given entity (`keyspace` or `keyspace:table`)
+pub fn use_entity(entity: impl AsRef<[u8]>) -> Action<()> {
+    Action::new(Query::new("USE").arg(entity), okay)
+}
+
+/// `USET`: set the given key/value pairs, overwriting existing keys, and return how many
+/// of them were set
+pub fn uset(pairs: impl IntoIterator<Item = (impl AsRef<[u8]>, impl AsRef<[u8]>)>) -> Action<u64> {
+    let mut query = Query::new("USET");
+    for (key, value) in pairs {
+        query.push(key);
+        query.push(value);
+    }
+    Action::new(query, int)
+}
+
+/// `LSKEYS`: returns up to `count` keys from the table `entity`
+pub fn lskeys(entity: impl AsRef<[u8]>, count: u64) -> Action<Vec<Vec<u8>>> {
+    let query = Query::new("LSKEYS").arg(entity).arg(count.to_string());
+    Action::new(query, keys)
+}
+
+/// `LSKEYS ... ORDERED`: returns up to `count` keys from the table `entity` in lexicographic
+/// order, after skipping the first `offset` keys
+pub fn lskeys_ordered(entity: impl AsRef<[u8]>, offset: u64, count: u64) -> Action<Vec<Vec<u8>>> {
+    let query = Query::new("LSKEYS")
+        .arg(entity)
+        .arg(count.to_string())
+        .arg("OFFSET")
+        .arg(offset.to_string())
+        .arg("ORDERED");
+    Action::new(query, keys)
+}
+
+/// `DBSIZE <entity>`: returns the number of keys in the table `entity`
+pub fn dbsize_of(entity: impl AsRef<[u8]>) -> Action<u64> {
+    Action::new(Query::new("DBSIZE").arg(entity), int)
+}
+
+/// `INSPECT KEYSPACES`: returns the names of all the keyspaces
+pub fn inspect_keyspaces() -> Action<Vec<String>> {
+    Action::new(Query::new("INSPECT").arg("KEYSPACES"), strings)
+}
+
+/// `INSPECT KEYSPACE`: returns the names of the tables in the given keyspace
+pub fn inspect_keyspace(keyspace: impl AsRef<[u8]>) -> Action<Vec<String>> {
+    Action::new(Query::new("INSPECT").arg("KEYSPACE").arg(keyspace), strings)
+}
+
+/// `INSPECT TABLE`: returns the description of the table `entity` (like
+/// `Keymap { data:(str,str), volatile:false }`)
+pub fn inspect_table(entity: impl AsRef<[u8]>) -> Action<String> {
+    Action::new(Query::new("INSPECT").arg("TABLE").arg(entity), string)
+}
+
+/// `CREATE KEYSPACE`: create a keyspace
+pub fn create_keyspace(keyspace: impl AsRef<[u8]>) -> Action<()> {
+    Action::new(Query::new("CREATE").arg("KEYSPACE").arg(keyspace), okay)
+}
+
+/// `CREATE TABLE`: create the table `entity` with the given model (like
+/// `keymap(str,binstr)`)
+pub fn create_table(
+    entity: impl AsRef<[u8]>,
+    model: impl AsRef<[u8]>,
+    volatile: bool,
+) -> Action<()> {
+    let mut query = Query::new("CREATE").arg("TABLE").arg(entity).arg(model);
+    if volatile {
+        query.push("volatile");
+    }
+    Action::new(query, okay)
+}
+
+#[test]
+fn test_action_conversions() {
+    let action = get("x");
+    assert_eq!(action.query(), &Query::new("GET").arg("x"));
+    assert_eq!(
+        action.convert(Element::String("100".to_owned())).unwrap(),
+        Some(b"100".to_vec())
+    );
+    assert_eq!(
+        action
+            .convert(Element::RespCode(RespCode::NotFound))
+            .unwrap(),
+        None
+    );
+    let action = set("x", "100");
+    assert!(!action
+        .convert(Element::RespCode(RespCode::OverwriteError))
+        .unwrap());
+    match action.convert(Element::RespCode(RespCode::ServerError)) {
+        Err(Error::Response(RespCode::ServerError)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let action = mget(vec!["x", "y"]);
+    assert_eq!(
+        action
+            .convert(Element::StrArray(vec![Some("1".to_owned()), None]))
+            .unwrap(),
+        vec![Some(b"1".to_vec()), None]
+    );
+}
@@ -0,0 +1,185 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Connections to a Skytable server
+
+use crate::actions::Action;
+use crate::error::{Error, SkyResult};
+use crate::query::Query;
+use crate::response::{self, Element, ParseError};
+use bytes::{Buf, BytesMut};
+use openssl::ssl::{SslConnector, SslMethod};
+use std::io::{Error as IoError, ErrorKind};
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_openssl::SslStream;
+
+const BUF_CAP: usize = 4096;
+
+/// A stream that we can talk Skyhash over
+trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+/// A connection to a Skytable server
+pub struct Connection {
+    stream: Box<dyn Socket>,
+    buffer: BytesMut,
+    packet: Vec<u8>,
+    poisoned: bool,
+}
+
+impl Connection {
+    /// Connect to the server at `host:port`
+    pub async fn connect(host: &str, port: u16) -> SkyResult<Self> {
+        let stream = TcpStream::connect((host, port)).await?;
+        Ok(Self::new(Box::new(stream)))
+    }
+    /// Connect to the server at `host:port` over TLS, verifying the server's certificate
+    /// with the CA certificate at `sslcert`
+    pub async fn connect_tls(host: &str, port: u16, sslcert: &str) -> SkyResult<Self> {
+        let mut builder =
+            SslConnector::builder(SslMethod::tls()).map_err(|e| Error::Ssl(e.to_string()))?;
+        builder
+            .set_ca_file(sslcert)
+            .map_err(|e| Error::Ssl(e.to_string()))?;
+        let ssl = builder
+            .build()
+            .configure()
+            .and_then(|config| config.into_ssl(host))
+            .map_err(|e| Error::Ssl(e.to_string()))?;
+        let stream = TcpStream::connect((host, port)).await?;
+        let mut stream = SslStream::new(ssl, stream).map_err(|e| Error::Ssl(e.to_string()))?;
+        Pin::new(&mut stream)
+            .connect()
+            .await
+            .map_err(|e| Error::Ssl(e.to_string()))?;
+        Ok(Self::new(Box::new(stream)))
+    }
+    fn new(stream: Box<dyn Socket>) -> Self {
+        Self {
+            stream,
+            buffer: BytesMut::with_capacity(BUF_CAP),
+            packet: Vec::new(),
+            poisoned: false,
+        }
+    }
+    /// Run a query and return the server's response
+    pub async fn run(&mut self, query: &Query) -> SkyResult<Element> {
+        self.packet.clear();
+        query.write_packet(&mut self.packet);
+        self.send_packet().await?;
+        self.read_response().await
+    }
+    /// Run an action and return its typed result
+    pub async fn run_action<T>(&mut self, action: Action<T>) -> SkyResult<T> {
+        let response = self.run(action.query()).await?;
+        action.convert(response)
+    }
+    /// Send all the queries in one go and then read their responses (in order). This
+    /// saves a round trip for every query but the first
+    pub async fn pipeline(&mut self, queries: &[Query]) -> SkyResult<Vec<Element>> {
+        self.packet.clear();
+        for query in queries {
+            query.write_packet(&mut self.packet);
+        }
+        self.send_packet().await?;
+        let mut responses = Vec::with_capacity(queries.len());
+        for _ in 0..queries.len() {
+            responses.push(self.read_response().await?);
+        }
+        Ok(responses)
+    }
+    /// Returns true if this connection saw an I/O or protocol error, and can't be used
+    /// any more
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+    async fn send_packet(&mut self) -> SkyResult<()> {
+        if self.poisoned {
+            return Err(Error::Io(IoError::from(ErrorKind::NotConnected)));
+        }
+        let ret = self.stream.write_all(&self.packet).await;
+        self.poison_on_err(ret.map_err(Error::from))
+    }
+    async fn read_response(&mut self) -> SkyResult<Element> {
+        loop {
+            match response::parse_response(&self.buffer) {
+                Ok((element, forward_by)) => {
+                    self.buffer.advance(forward_by);
+                    return Ok(element);
+                }
+                Err(ParseError::NotEnough) => {}
+                Err(ParseError::BadResponse) => return self.poison_on_err(Err(Error::BadResponse)),
+            }
+            match self.stream.read_buf(&mut self.buffer).await {
+                Ok(0) => {
+                    let e = IoError::from(ErrorKind::ConnectionReset);
+                    return self.poison_on_err(Err(Error::Io(e)));
+                }
+                Ok(_) => {}
+                Err(e) => return self.poison_on_err(Err(Error::Io(e))),
+            }
+        }
+    }
+    fn poison_on_err<T>(&mut self, ret: SkyResult<T>) -> SkyResult<T> {
+        if ret.is_err() {
+            self.poisoned = true;
+        }
+        ret
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Where (and how) to connect to a server
+pub struct ConnectionConfig {
+    host: String,
+    port: u16,
+    sslcert: Option<String>,
+}
+
+impl ConnectionConfig {
+    /// A config for a plain TCP connection to `host:port`
+    pub fn new(host: impl ToString, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            sslcert: None,
+        }
+    }
+    /// Use TLS, verifying the server's certificate with the CA certificate at `sslcert`
+    pub fn tls(mut self, sslcert: impl ToString) -> Self {
+        self.sslcert = Some(sslcert.to_string());
+        self
+    }
+    /// Open a new connection with this config
+    pub async fn connect(&self) -> SkyResult<Connection> {
+        match &self.sslcert {
+            Some(cert) => Connection::connect_tls(&self.host, self.port, cert).await,
+            None => Connection::connect(&self.host, self.port).await,
+        }
+    }
+}
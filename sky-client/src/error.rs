@@ -0,0 +1,80 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Errors returned by the client
+
+use crate::response::{Element, RespCode};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Error as IoError;
+
+/// The result of a client operation
+pub type SkyResult<T> = Result<T, Error>;
+
+#[derive(Debug)]
+/// An error returned by the client
+pub enum Error {
+    /// An I/O error
+    Io(IoError),
+    /// An error while setting up a TLS connection
+    Ssl(String),
+    /// The server sent something that isn't valid Skyhash
+    BadResponse,
+    /// The server returned an error
+    Response(RespCode),
+    /// The server's response wasn't what the action expected
+    UnexpectedResponse(Element),
+}
+
+impl Error {
+    /// Returns the error for a response that an action didn't expect
+    pub(crate) fn unexpected(element: Element) -> Self {
+        match element {
+            Element::RespCode(code) => Self::Response(code),
+            element => Self::UnexpectedResponse(element),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Ssl(e) => write!(f, "TLS error: {}", e),
+            Self::BadResponse => write!(f, "the server sent an invalid response"),
+            Self::Response(code) => write!(f, "the server returned an error: {:?}", code),
+            Self::UnexpectedResponse(e) => write!(f, "unexpected response: {:?}", e),
+        }
+    }
+}
+
+impl StdError for Error {}
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Self {
+        Self::Io(e)
+    }
+}
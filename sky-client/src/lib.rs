@@ -0,0 +1,68 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#![deny(unused_crate_dependencies)]
+#![deny(unused_imports)]
+
+//! # `sky-client`
+//!
+//! An async client for Skytable that is kept in lockstep with the server's protocol. It
+//! has:
+//! - Typed actions (see [`actions`]) that return Rust types instead of raw responses
+//! - TLS connections (see [`Connection::connect_tls`])
+//! - Pipelining, where several queries are sent before their responses are read (see
+//! [`Connection::pipeline`])
+//! - A connection pool (see [`Pool`])
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use sky_client::{actions, Connection};
+//!
+//! #[tokio::main]
+//! async fn main() -> sky_client::SkyResult<()> {
+//!     let mut con = Connection::connect("127.0.0.1", 2003).await?;
+//!     con.run_action(actions::set("x", "100")).await?;
+//!     assert_eq!(
+//!         con.run_action(actions::get("x")).await?,
+//!         Some(b"100".to_vec())
+//!     );
+//!     Ok(())
+//! }
+//! ```
+
+pub mod actions;
+mod connection;
+mod error;
+mod pool;
+mod query;
+mod response;
+pub use crate::actions::Action;
+pub use crate::connection::{Connection, ConnectionConfig};
+pub use crate::error::{Error, SkyResult};
+pub use crate::pool::{Pool, PooledConnection};
+pub use crate::query::Query;
+pub use crate::response::{Element, RespCode};
@@ -0,0 +1,116 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! A connection pool
+
+use crate::connection::{Connection, ConnectionConfig};
+use crate::error::{Error, SkyResult};
+use std::io::{Error as IoError, ErrorKind};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A pool of connections to a server. It's cheap to clone, and every clone shares the
+/// same connections
+#[derive(Clone)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    config: ConnectionConfig,
+    idle: Mutex<Vec<Connection>>,
+    permits: Arc<Semaphore>,
+}
+
+impl Pool {
+    /// Create a pool that holds at most `max` connections, which are opened lazily
+    pub fn new(config: ConnectionConfig, max: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                config,
+                idle: Mutex::new(Vec::with_capacity(max)),
+                permits: Arc::new(Semaphore::new(max)),
+            }),
+        }
+    }
+    /// Get a connection from the pool, waiting if all of them are in use. An idle
+    /// connection is reused if there is one; otherwise a new connection is opened
+    pub async fn get(&self) -> SkyResult<PooledConnection> {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Io(IoError::from(ErrorKind::BrokenPipe)))?;
+        let idle = self.inner.idle.lock().unwrap().pop();
+        let con = match idle {
+            Some(con) => con,
+            None => self.inner.config.connect().await?,
+        };
+        Ok(PooledConnection {
+            con: Some(con),
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+    /// Returns the number of idle connections in the pool
+    pub fn idle(&self) -> usize {
+        self.inner.idle.lock().unwrap().len()
+    }
+}
+
+/// A connection that goes back to its pool when dropped. Connections that saw an error
+/// are closed instead
+pub struct PooledConnection {
+    con: Option<Connection>,
+    pool: Arc<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+    fn deref(&self) -> &Connection {
+        self.con.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.con.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(con) = self.con.take() {
+            if !con.is_poisoned() {
+                self.pool.idle.lock().unwrap().push(con);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Queries, and their encoding into Skyhash packets
+
+#[derive(Debug, Clone, PartialEq, Default)]
+/// A query, which is an action followed by its arguments
+pub struct Query {
+    args: Vec<Vec<u8>>,
+    /// the number of milliseconds after which the server should skip the query
+    deadline: Option<u64>,
+}
+
+impl Query {
+    /// Create a new query for `action`
+    pub fn new(action: impl AsRef<[u8]>) -> Self {
+        Self {
+            args: vec![action.as_ref().to_owned()],
+            deadline: None,
+        }
+    }
+    /// Ask the server to skip the query (and respond with `err-deadline-exceeded`) if it
+    /// hasn't started running it within `ms` milliseconds of receiving it (builder style)
+    pub fn deadline(mut self, ms: u64) -> Self {
+        self.deadline = Some(ms);
+        self
+    }
+    /// Add an argument (builder style)
+    pub fn arg(mut self, arg: impl AsRef<[u8]>) -> Self {
+        self.push(arg);
+        self
+    }
+    /// Add an argument
+    pub fn push(&mut self, arg: impl AsRef<[u8]>) {
+        self.args.push(arg.as_ref().to_owned());
+    }
+    /// Returns the number of items in the query (including the action)
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+    /// Returns true if the query has nothing in it
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+    /// Write the query out as a simple query packet:
+    /// ```text
+    /// *1[@<deadline>]\n
+    /// ~<n>\n
+    /// (<len>\n<arg>\n)*
+    /// ```
+    pub(crate) fn write_packet(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(b"*1");
+        if let Some(deadline) = self.deadline {
+            buf.push(b'@');
+            buf.extend_from_slice(deadline.to_string().as_bytes());
+        }
+        buf.extend_from_slice(b"\n~");
+        buf.extend_from_slice(self.args.len().to_string().as_bytes());
+        buf.push(b'\n');
+        for arg in self.args.iter() {
+            buf.extend_from_slice(arg.len().to_string().as_bytes());
+            buf.push(b'\n');
+            buf.extend_from_slice(arg);
+            buf.push(b'\n');
+        }
+    }
+}
+
+#[test]
+fn test_query_packet() {
+    let mut packet = Vec::new();
+    Query::new("SET")
+        .arg("x")
+        .arg("100")
+        .write_packet(&mut packet);
+    assert_eq!(packet, b"*1\n~3\n3\nSET\n1\nx\n3\n100\n");
+}
+
+#[test]
+fn test_query_packet_binary() {
+    let mut packet = Vec::new();
+    Query::new("HEYA")
+        .arg([0xFF, b'\n'])
+        .write_packet(&mut packet);
+    assert_eq!(packet, b"*1\n~2\n4\nHEYA\n2\n\xFF\n\n");
+}
+
+#[test]
+fn test_query_packet_deadline() {
+    let mut packet = Vec::new();
+    Query::new("GET")
+        .arg("x")
+        .deadline(50)
+        .write_packet(&mut packet);
+    assert_eq!(packet, b"*1@50\n~2\n3\nGET\n1\nx\n");
+}
@@ -0,0 +1,324 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Responses, and their decoding from Skyhash packets
+
+#[derive(Debug, Clone, PartialEq)]
+/// A response code
+pub enum RespCode {
+    /// `0`
+    Okay,
+    /// `1`
+    NotFound,
+    /// `2`
+    OverwriteError,
+    /// `3`
+    ActionError,
+    /// `4`
+    PacketError,
+    /// `5`
+    ServerError,
+    /// `6`
+    OtherError,
+    /// `7`
+    Wrongtype,
+    /// `8`
+    UnknownDataType,
+    /// `9`
+    EncodingError,
+    /// An error with a description (like `err-snapshot-busy`)
+    ErrorString(String),
+}
+
+impl RespCode {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match bytes {
+            b"0" => Self::Okay,
+            b"1" => Self::NotFound,
+            b"2" => Self::OverwriteError,
+            b"3" => Self::ActionError,
+            b"4" => Self::PacketError,
+            b"5" => Self::ServerError,
+            b"6" => Self::OtherError,
+            b"7" => Self::Wrongtype,
+            b"8" => Self::UnknownDataType,
+            b"9" => Self::EncodingError,
+            _ => Self::ErrorString(String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An element in a response
+pub enum Element {
+    /// A unicode string (`+`)
+    String(String),
+    /// A binary string (`?`)
+    Binary(Vec<u8>),
+    /// An unsigned integer (`:`)
+    UnsignedInt(u64),
+    /// A response code (`!`)
+    RespCode(RespCode),
+    /// An array that can hold any element, including other arrays (`&`)
+    Array(Vec<Element>),
+    /// A key/value map (`%`)
+    Map(Vec<(Element, Element)>),
+    /// An array of non-array elements (`_`)
+    FlatArray(Vec<Element>),
+    /// A typed array of unicode strings, where `None` is a null (`@+`)
+    StrArray(Vec<Option<String>>),
+    /// A typed array of binary strings, where `None` is a null (`@?`)
+    BinArray(Vec<Option<Vec<u8>>>),
+}
+
+#[derive(Debug, PartialEq)]
+/// An error while decoding a response
+pub(crate) enum ParseError {
+    /// More data is needed
+    NotEnough,
+    /// The response is invalid
+    BadResponse,
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Decode a (simple) response from `buf`, returning the element and the number of bytes
+/// it took up
+pub(crate) fn parse_response(buf: &[u8]) -> ParseResult<(Element, usize)> {
+    let mut parser = Parser { buf, cursor: 0 };
+    if parser.read_line()? != b"*1" {
+        return Err(ParseError::BadResponse);
+    }
+    let element = parser.parse_element()?;
+    Ok((element, parser.cursor))
+}
+
+struct Parser<'a> {
+    buf: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next_byte(&mut self) -> ParseResult<u8> {
+        let byte = *self.buf.get(self.cursor).ok_or(ParseError::NotEnough)?;
+        self.cursor += 1;
+        Ok(byte)
+    }
+    fn peek_byte(&self) -> ParseResult<u8> {
+        self.buf
+            .get(self.cursor)
+            .copied()
+            .ok_or(ParseError::NotEnough)
+    }
+    /// Read up to the next LF (and skip it)
+    fn read_line(&mut self) -> ParseResult<&'a [u8]> {
+        let rest = &self.buf[self.cursor..];
+        match rest.iter().position(|b| *b == b'\n') {
+            Some(pos) => {
+                self.cursor += pos + 1;
+                Ok(&rest[..pos])
+            }
+            None => Err(ParseError::NotEnough),
+        }
+    }
+    fn read_usize(&mut self) -> ParseResult<usize> {
+        let line = self.read_line()?;
+        if line.is_empty() || !line.iter().all(u8::is_ascii_digit) {
+            return Err(ParseError::BadResponse);
+        }
+        String::from_utf8_lossy(line)
+            .parse()
+            .map_err(|_| ParseError::BadResponse)
+    }
+    /// Read a sized payload: `<len>\n<payload>\n`
+    fn read_sized(&mut self) -> ParseResult<&'a [u8]> {
+        let len = self.read_usize()?;
+        let end = self.cursor + len;
+        if self.buf.len() <= end {
+            return Err(ParseError::NotEnough);
+        }
+        if self.buf[end] != b'\n' {
+            return Err(ParseError::BadResponse);
+        }
+        let payload = &self.buf[self.cursor..end];
+        self.cursor = end + 1;
+        Ok(payload)
+    }
+    fn read_string(&mut self) -> ParseResult<String> {
+        String::from_utf8(self.read_sized()?.to_owned()).map_err(|_| ParseError::BadResponse)
+    }
+    fn parse_element(&mut self) -> ParseResult<Element> {
+        let element = match self.next_byte()? {
+            b'+' => Element::String(self.read_string()?),
+            b'?' => Element::Binary(self.read_sized()?.to_owned()),
+            b':' => {
+                let int = String::from_utf8_lossy(self.read_sized()?)
+                    .parse()
+                    .map_err(|_| ParseError::BadResponse)?;
+                Element::UnsignedInt(int)
+            }
+            b'!' => Element::RespCode(RespCode::from_bytes(self.read_sized()?)),
+            b'&' => {
+                let len = self.read_usize()?;
+                let mut array = Vec::with_capacity(len);
+                for _ in 0..len {
+                    array.push(self.parse_element()?);
+                }
+                Element::Array(array)
+            }
+            b'%' => {
+                let len = self.read_usize()?;
+                let mut map = Vec::with_capacity(len);
+                for _ in 0..len {
+                    map.push((self.parse_element()?, self.parse_element()?));
+                }
+                Element::Map(map)
+            }
+            b'_' => {
+                let len = self.read_usize()?;
+                let mut array = Vec::with_capacity(len);
+                for _ in 0..len {
+                    match self.peek_byte()? {
+                        b'&' | b'%' | b'_' | b'@' => return Err(ParseError::BadResponse),
+                        _ => array.push(self.parse_element()?),
+                    }
+                }
+                Element::FlatArray(array)
+            }
+            b'@' => {
+                let tsymbol = self.next_byte()?;
+                let len = self.read_usize()?;
+                match tsymbol {
+                    b'+' => {
+                        let mut array = Vec::with_capacity(len);
+                        for _ in 0..len {
+                            array.push(self.read_nullable(Self::read_string)?);
+                        }
+                        Element::StrArray(array)
+                    }
+                    b'?' => {
+                        let mut array = Vec::with_capacity(len);
+                        for _ in 0..len {
+                            array.push(self.read_nullable(|p| Ok(p.read_sized()?.to_owned()))?);
+                        }
+                        Element::BinArray(array)
+                    }
+                    _ => return Err(ParseError::BadResponse),
+                }
+            }
+            _ => return Err(ParseError::BadResponse),
+        };
+        Ok(element)
+    }
+    /// Read a typed array element, which is either a null (`\0\n`) or a sized payload
+    fn read_nullable<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Option<T>> {
+        if self.peek_byte()? == b'\0' {
+            self.cursor += 1;
+            if self.next_byte()? != b'\n' {
+                return Err(ParseError::BadResponse);
+            }
+            Ok(None)
+        } else {
+            read(self).map(Some)
+        }
+    }
+}
+
+#[test]
+fn test_parse_simple_elements() {
+    assert_eq!(
+        parse_response(b"*1\n+4\nHEY!\n").unwrap(),
+        (Element::String("HEY!".to_owned()), 11)
+    );
+    assert_eq!(
+        parse_response(b"*1\n?2\n\xFF\n\n").unwrap().0,
+        Element::Binary(vec![0xFF, b'\n'])
+    );
+    assert_eq!(
+        parse_response(b"*1\n:3\n100\n").unwrap().0,
+        Element::UnsignedInt(100)
+    );
+    assert_eq!(
+        parse_response(b"*1\n!1\n0\n").unwrap().0,
+        Element::RespCode(RespCode::Okay)
+    );
+    assert_eq!(
+        parse_response(b"*1\n!14\nerr-admin-only\n").unwrap().0,
+        Element::RespCode(RespCode::ErrorString("err-admin-only".to_owned()))
+    );
+}
+
+#[test]
+fn test_parse_arrays() {
+    assert_eq!(
+        parse_response(b"*1\n@+3\n1\na\n\0\n1\nc\n").unwrap().0,
+        Element::StrArray(vec![Some("a".to_owned()), None, Some("c".to_owned())])
+    );
+    assert_eq!(
+        parse_response(b"*1\n_2\n+1\na\n!1\n1\n").unwrap().0,
+        Element::FlatArray(vec![
+            Element::String("a".to_owned()),
+            Element::RespCode(RespCode::NotFound)
+        ])
+    );
+    assert_eq!(
+        parse_response(b"*1\n&2\n:1\n1\n%1\n+1\nk\n&0\n").unwrap().0,
+        Element::Array(vec![
+            Element::UnsignedInt(1),
+            Element::Map(vec![(
+                Element::String("k".to_owned()),
+                Element::Array(vec![])
+            )])
+        ])
+    );
+}
+
+#[test]
+fn test_parse_incomplete_and_bad() {
+    let full = b"*1\n@?2\n1\na\n2\nbc\n";
+    for end in 0..full.len() {
+        assert_eq!(
+            parse_response(&full[..end]).unwrap_err(),
+            ParseError::NotEnough
+        );
+    }
+    assert_eq!(parse_response(full).unwrap().1, full.len());
+    assert_eq!(
+        parse_response(b"*2\n+1\na\n").unwrap_err(),
+        ParseError::BadResponse
+    );
+    assert_eq!(
+        parse_response(b"*1\n+1\nab\n").unwrap_err(),
+        ParseError::BadResponse
+    );
+    assert_eq!(
+        parse_response(b"*1\n_1\n&0\n").unwrap_err(),
+        ParseError::BadResponse
+    );
+}
@@ -0,0 +1,131 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `sky-dump`
+//!
+//! Writes a logical dump of an instance (see [`sky_dump::format`]), or rewrites an older
+//! dump in the current format with `--convert`
+
+use clap::{load_yaml, App};
+use sky_client::{actions, Connection};
+use sky_dump::format::{self, DumpWriter, Record};
+use sky_dump::{DumpResult, BATCH, SYSTEM_KEYSPACES};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+
+#[tokio::main]
+async fn main() {
+    let cfg_layout = load_yaml!("./dump.yml");
+    let matches = App::from_yaml(cfg_layout).get_matches();
+    let path = matches.value_of("file").unwrap();
+    let ret = match matches.value_of("convert") {
+        Some(old) => convert(old, path),
+        None => dump(&matches, path).await,
+    };
+    match ret {
+        Ok(msg) => println!("{}", msg),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            process::exit(0x01);
+        }
+    }
+}
+
+/// Rewrite the dump at `old` in the current format
+fn convert(old: &str, path: &str) -> DumpResult<String> {
+    let input = BufReader::new(File::open(old)?);
+    let output = BufWriter::new(File::create(path)?);
+    let count = format::convert(input, output)?;
+    Ok(format!(
+        "Converted {} records to version {} of the dump format",
+        count,
+        format::VERSION
+    ))
+}
+
+/// Dump the instance to `path`
+async fn dump(matches: &clap::ArgMatches<'_>, path: &str) -> DumpResult<String> {
+    let mut con = sky_dump::connect(matches).await?;
+    let mut writer = DumpWriter::new(BufWriter::new(File::create(path)?))?;
+    let (mut tables, mut keys) = (0, 0);
+    for keyspace in con.run_action(actions::inspect_keyspaces()).await? {
+        if SYSTEM_KEYSPACES.contains(&keyspace.as_str()) {
+            continue;
+        }
+        writer.write(&Record::Keyspace(keyspace.clone()))?;
+        for table in con.run_action(actions::inspect_keyspace(&keyspace)).await? {
+            keys += dump_table(&mut con, &mut writer, &keyspace, table).await?;
+            tables += 1;
+        }
+    }
+    writer.finish()?;
+    Ok(format!(
+        "Dumped {} tables ({} keys) to {}",
+        tables, keys, path
+    ))
+}
+
+/// Write the table `keyspace:table` and all its keys, returning the number of keys written
+async fn dump_table<W: Write>(
+    con: &mut Connection,
+    writer: &mut DumpWriter<W>,
+    keyspace: &str,
+    table: String,
+) -> DumpResult<u64> {
+    let entity = format!("{}:{}", keyspace, table);
+    let description = con.run_action(actions::inspect_table(&entity)).await?;
+    let (model, volatile) = match sky_dump::parse_description(&description) {
+        Some(model) => model,
+        None => {
+            return Err(format!(
+                "table {} has a model that isn't supported: {}",
+                entity, description
+            )
+            .into())
+        }
+    };
+    writer.write(&Record::Table {
+        name: table,
+        model,
+        volatile,
+    })?;
+    let count = con.run_action(actions::dbsize_of(&entity)).await?;
+    let keys = con.run_action(actions::lskeys(&entity, count)).await?;
+    con.run_action(actions::use_entity(&entity)).await?;
+    let mut written = 0;
+    for batch in keys.chunks(BATCH) {
+        let values = con.run_action(actions::mget(batch)).await?;
+        // keys that were removed after we listed them come back as nulls
+        for (key, value) in batch.iter().zip(values) {
+            if let Some(value) = value {
+                writer.write(&Record::Pair(key.clone(), value))?;
+                written += 1;
+            }
+        }
+    }
+    Ok(written)
+}
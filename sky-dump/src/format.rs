@@ -0,0 +1,289 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # The dump format
+//!
+//! A dump is a logical copy of the data in an instance: it only records the keyspaces, the
+//! tables (with their models) and the key/value pairs in them, and nothing about how the
+//! server lays them out on disk. This is what makes a dump loadable by a server with a
+//! different flush format.
+//!
+//! A dump starts with the magic [`MAGIC`] and the format version as a 32-bit little-endian
+//! integer, followed by records. Every record starts with a one byte tag:
+//! - `K` starts a keyspace and is followed by the keyspace's name
+//! - `T` starts a table in the last keyspace and is followed by the table's name, its model
+//! (like `keymap(str,binstr)`) and a byte that is `1` if the table is volatile
+//! - `P` is a key/value pair in the last table and is followed by the key and the value
+//! - `E` marks the end of the dump
+//!
+//! Names, models, keys and values are written as a 64-bit little-endian length followed by
+//! the bytes themselves. Readers accept every version up to [`VERSION`] and upgrade the
+//! records of older versions as they read them, so that a dump made by an older `sky-dump`
+//! can always be loaded by a newer `sky-load`
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// The magic that every dump starts with
+pub const MAGIC: &[u8; 8] = b"SKYDUMP\n";
+/// The version of the dump format written by this crate
+pub const VERSION: u32 = 1;
+
+const TAG_KEYSPACE: u8 = b'K';
+const TAG_TABLE: u8 = b'T';
+const TAG_PAIR: u8 = b'P';
+const TAG_END: u8 = b'E';
+
+#[derive(Debug, Clone, PartialEq)]
+/// A record in a dump
+pub enum Record {
+    /// A keyspace; the tables that follow belong to it
+    Keyspace(String),
+    /// A table; the pairs that follow belong to it
+    Table {
+        name: String,
+        model: String,
+        volatile: bool,
+    },
+    /// A key/value pair
+    Pair(Vec<u8>, Vec<u8>),
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Writes a dump in the current version of the format
+pub struct DumpWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> DumpWriter<W> {
+    /// Writes the header and returns a writer for the records
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&VERSION.to_le_bytes())?;
+        Ok(Self { inner })
+    }
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        self.inner.write_all(bytes)
+    }
+    /// Write a record
+    pub fn write(&mut self, record: &Record) -> Result<()> {
+        match record {
+            Record::Keyspace(name) => {
+                self.inner.write_all(&[TAG_KEYSPACE])?;
+                self.write_bytes(name.as_bytes())
+            }
+            Record::Table {
+                name,
+                model,
+                volatile,
+            } => {
+                self.inner.write_all(&[TAG_TABLE])?;
+                self.write_bytes(name.as_bytes())?;
+                self.write_bytes(model.as_bytes())?;
+                self.inner.write_all(&[*volatile as u8])
+            }
+            Record::Pair(key, value) => {
+                self.inner.write_all(&[TAG_PAIR])?;
+                self.write_bytes(key)?;
+                self.write_bytes(value)
+            }
+        }
+    }
+    /// Write the end of the dump and return the inner writer
+    pub fn finish(mut self) -> Result<W> {
+        self.inner.write_all(&[TAG_END])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads a dump made with any version of the format up to [`VERSION`]
+pub struct DumpReader<R: Read> {
+    inner: R,
+    version: u32,
+    finished: bool,
+}
+
+impl<R: Read> DumpReader<R> {
+    /// Reads and checks the header, returning a reader for the records
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            return Err(invalid("not a dump file"));
+        }
+        let mut version = [0u8; 4];
+        inner.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version == 0 || version > VERSION {
+            return Err(invalid(format!(
+                "dump has version {}, but only versions up to {} are supported",
+                version, VERSION
+            )));
+        }
+        Ok(Self {
+            inner,
+            version,
+            finished: false,
+        })
+    }
+    /// Returns the version of the format that the dump was written with
+    pub const fn version(&self) -> u32 {
+        self.version
+    }
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut byte = [0u8; 1];
+        self.inner.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut len = [0u8; 8];
+        self.inner.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+        let mut bytes = Vec::new();
+        (&mut self.inner).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes)
+    }
+    fn read_string(&mut self) -> Result<String> {
+        String::from_utf8(self.read_bytes()?).map_err(|_| invalid("name is not valid UTF-8"))
+    }
+    /// Read the next record, upgrading it to the current version of the format. Returns
+    /// `None` once the end of the dump has been read
+    pub fn next_record(&mut self) -> Result<Option<Record>> {
+        if self.finished {
+            return Ok(None);
+        }
+        // there's only one version so far; older versions are upgraded here as the format
+        // changes
+        let record = match self.read_u8()? {
+            TAG_KEYSPACE => Record::Keyspace(self.read_string()?),
+            TAG_TABLE => Record::Table {
+                name: self.read_string()?,
+                model: self.read_string()?,
+                volatile: self.read_u8()? == 1,
+            },
+            TAG_PAIR => Record::Pair(self.read_bytes()?, self.read_bytes()?),
+            TAG_END => {
+                self.finished = true;
+                return Ok(None);
+            }
+            tag => return Err(invalid(format!("unknown record tag {}", tag))),
+        };
+        Ok(Some(record))
+    }
+}
+
+/// Rewrite a dump made with any supported version of the format in the current version,
+/// returning the number of records that were copied
+pub fn convert<R: Read, W: Write>(input: R, output: W) -> Result<u64> {
+    let mut reader = DumpReader::new(input)?;
+    let mut writer = DumpWriter::new(output)?;
+    let mut count = 0;
+    while let Some(record) = reader.next_record()? {
+        writer.write(&record)?;
+        count += 1;
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+fn sample() -> Vec<Record> {
+    vec![
+        Record::Keyspace("default".to_owned()),
+        Record::Table {
+            name: "default".to_owned(),
+            model: "keymap(binstr,binstr)".to_owned(),
+            volatile: false,
+        },
+        Record::Pair(b"x".to_vec(), vec![0, 159, 146, 150]),
+        Record::Pair(b"y".to_vec(), vec![]),
+        Record::Table {
+            name: "cache".to_owned(),
+            model: "keymap(str,str)".to_owned(),
+            volatile: true,
+        },
+    ]
+}
+
+#[cfg(test)]
+fn write_sample() -> Vec<u8> {
+    let mut writer = DumpWriter::new(Vec::new()).unwrap();
+    for record in sample() {
+        writer.write(&record).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+#[test]
+fn test_dump_roundtrip() {
+    let dump = write_sample();
+    let mut reader = DumpReader::new(&dump[..]).unwrap();
+    assert_eq!(reader.version(), VERSION);
+    let mut records = Vec::new();
+    while let Some(record) = reader.next_record().unwrap() {
+        records.push(record);
+    }
+    assert_eq!(records, sample());
+    assert_eq!(reader.next_record().unwrap(), None);
+}
+
+#[test]
+fn test_dump_convert() {
+    let dump = write_sample();
+    let mut converted = Vec::new();
+    assert_eq!(convert(&dump[..], &mut converted).unwrap(), 5);
+    assert_eq!(converted, dump);
+}
+
+#[test]
+fn test_dump_bad_header() {
+    let mut dump = write_sample();
+    assert!(DumpReader::new(&dump[1..]).is_err());
+    // a dump from the future
+    dump[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert_eq!(
+        DumpReader::new(&dump[..]).err().unwrap().kind(),
+        ErrorKind::InvalidData
+    );
+    // a truncated dump
+    let dump = write_sample();
+    let mut reader = DumpReader::new(&dump[..dump.len() - 3]).unwrap();
+    let err = loop {
+        match reader.next_record() {
+            Ok(Some(_)) => continue,
+            Ok(None) => panic!("truncated dump was read in full"),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
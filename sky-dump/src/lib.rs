@@ -0,0 +1,96 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `sky-dump`
+//!
+//! Logical dumps of the data in an instance. `sky-dump` writes every keyspace, table and
+//! key/value pair in an instance to a dump file, and `sky-load` creates them in another
+//! instance. Since a dump doesn't depend on the server's on-disk layout, dumps are the
+//! supported way to move data across versions that change the flush format. See [`format`]
+//! for the format itself
+
+pub mod format;
+use clap::ArgMatches;
+use sky_client::{Connection, ConnectionConfig};
+use std::error::Error;
+
+/// The result of a dump or a load
+pub type DumpResult<T> = Result<T, Box<dyn Error>>;
+
+/// The number of keys fetched or written with a single query
+pub const BATCH: usize = 1024;
+/// The host used if none is passed
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+/// The port used if none is passed
+pub const DEFAULT_PORT: u16 = 2003;
+/// Keyspaces that belong to the server and are never dumped
+pub const SYSTEM_KEYSPACES: [&str; 1] = ["system"];
+
+/// Connect to the instance given by the `host`, `port` and `cert` arguments
+pub async fn connect(matches: &ArgMatches<'_>) -> DumpResult<Connection> {
+    let host = matches.value_of("host").unwrap_or(DEFAULT_HOST);
+    let port = match matches.value_of("port") {
+        Some(port) => port
+            .parse::<u16>()
+            .map_err(|_| format!("bad port '{}'", port))?,
+        None => DEFAULT_PORT,
+    };
+    let mut config = ConnectionConfig::new(host, port);
+    if let Some(cert) = matches.value_of("cert") {
+        config = config.tls(cert);
+    }
+    Ok(config.connect().await?)
+}
+
+/// Turn a table's description (as returned by `INSPECT TABLE`, like
+/// `Keymap { data:(str,binstr), volatile:true }`) into its model (like
+/// `keymap(str,binstr)`) and volatility
+pub fn parse_description(description: &str) -> Option<(String, bool)> {
+    let (model, rest) = description.split_once(" { data:(")?;
+    let (types, rest) = rest.split_once("), volatile:")?;
+    let volatile = match rest.strip_suffix(" }")? {
+        "true" => true,
+        "false" => false,
+        _ => return None,
+    };
+    Some((
+        format!("{}({})", model.to_ascii_lowercase(), types),
+        volatile,
+    ))
+}
+
+#[test]
+fn test_parse_description() {
+    assert_eq!(
+        parse_description("Keymap { data:(str,binstr), volatile:true }").unwrap(),
+        ("keymap(str,binstr)".to_owned(), true)
+    );
+    assert_eq!(
+        parse_description("Keymap { data:(binstr,binstr), volatile:false }").unwrap(),
+        ("keymap(binstr,binstr)".to_owned(), false)
+    );
+    assert!(parse_description("Keylist { data:(str), volatile:maybe }").is_none());
+}
@@ -0,0 +1,125 @@
+/*
+ * Created on Thu Oct 15 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2021, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `sky-load`
+//!
+//! Loads a dump made by `sky-dump` into an instance
+
+use clap::{load_yaml, App};
+use sky_client::{actions, Connection, Error, RespCode};
+use sky_dump::format::{DumpReader, Record};
+use sky_dump::{DumpResult, BATCH};
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+#[tokio::main]
+async fn main() {
+    let cfg_layout = load_yaml!("./load.yml");
+    let matches = App::from_yaml(cfg_layout).get_matches();
+    let path = matches.value_of("file").unwrap();
+    match load(&matches, path).await {
+        Ok(msg) => println!("{}", msg),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            process::exit(0x01);
+        }
+    }
+}
+
+/// Returns true if creating an object failed only because it already exists
+fn already_exists(ret: &Result<(), Error>) -> bool {
+    matches!(
+        ret,
+        Err(Error::Response(RespCode::ErrorString(e))) if e == "err-already-exists"
+    )
+}
+
+/// Write the batched pairs to the current table
+async fn flush(con: &mut Connection, batch: &mut Vec<(Vec<u8>, Vec<u8>)>) -> DumpResult<()> {
+    if !batch.is_empty() {
+        con.run_action(actions::uset(batch.drain(..))).await?;
+    }
+    Ok(())
+}
+
+/// Load the dump at `path` into the instance
+async fn load(matches: &clap::ArgMatches<'_>, path: &str) -> DumpResult<String> {
+    let mut reader = DumpReader::new(BufReader::new(File::open(path)?))?;
+    let mut con = sky_dump::connect(matches).await?;
+    let mut keyspace = None;
+    let mut batch = Vec::with_capacity(BATCH);
+    let (mut tables, mut keys) = (0, 0);
+    while let Some(record) = reader.next_record()? {
+        match record {
+            Record::Keyspace(name) => {
+                flush(&mut con, &mut batch).await?;
+                let ret = con.run_action(actions::create_keyspace(&name)).await;
+                if !already_exists(&ret) {
+                    ret?;
+                }
+                keyspace = Some(name);
+            }
+            Record::Table {
+                name,
+                model,
+                volatile,
+            } => {
+                flush(&mut con, &mut batch).await?;
+                let entity = match &keyspace {
+                    Some(keyspace) => format!("{}:{}", keyspace, name),
+                    None => return Err("dump has a table outside a keyspace".into()),
+                };
+                let ret = con
+                    .run_action(actions::create_table(&entity, &model, volatile))
+                    .await;
+                if !already_exists(&ret) {
+                    ret?;
+                }
+                con.run_action(actions::use_entity(&entity)).await?;
+                tables += 1;
+            }
+            Record::Pair(key, value) => {
+                if tables == 0 {
+                    return Err("dump has a key outside a table".into());
+                }
+                batch.push((key, value));
+                keys += 1;
+                if batch.len() == BATCH {
+                    flush(&mut con, &mut batch).await?;
+                }
+            }
+        }
+    }
+    flush(&mut con, &mut batch).await?;
+    Ok(format!(
+        "Loaded {} tables ({} keys) from version {} dump {}",
+        tables,
+        keys,
+        reader.version(),
+        path
+    ))
+}